@@ -0,0 +1,40 @@
+// The vignette and CRT pass; see src/postfx.rs.
+
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct PostFx {
+    vignette: f32,
+    crt: f32,
+    _padding: vec2<f32>,
+}
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var texture_sampler: sampler;
+@group(0) @binding(2) var<uniform> settings: PostFx;
+
+const PI: f32 = 3.14159265;
+// pixels from one scanline to the next
+const SCANLINE_PERIOD: f32 = 3.0;
+// how far the red and blue edges drift apart, in uv
+const FRINGE: f32 = 0.0015;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let uv = in.uv;
+    let center = uv - vec2<f32>(0.5);
+
+    // sampled in uniform control flow, as WebGL2 wants
+    let base = textureSample(screen_texture, texture_sampler, uv);
+    let fringe = center * FRINGE * settings.crt;
+    let red = textureSample(screen_texture, texture_sampler, uv + fringe).r;
+    let blue = textureSample(screen_texture, texture_sampler, uv - fringe).b;
+    var color = mix(base.rgb, vec3<f32>(red, base.g, blue), settings.crt);
+
+    let line = 0.5 + 0.5 * sin(in.position.y * 2.0 * PI / SCANLINE_PERIOD);
+    color *= mix(1.0, 0.8 + 0.2 * line, settings.crt);
+
+    let edge = smoothstep(0.25, 0.75, length(center));
+    color *= 1.0 - edge * settings.vignette;
+
+    return vec4<f32>(color, base.a);
+}
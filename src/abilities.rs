@@ -0,0 +1,471 @@
+//! Player abilities.
+//!
+//! Rewind keeps a few seconds of the player's position and health, recorded
+//! every fixed tick, and on [`Action::Rewind`] plays the player back through
+//! them and restores the health it had at the start.
+//!
+//! Dash, on [`Action::Dash`], throws the player [`DashConfig::distance`]
+//! over a fraction of a second, in the [`DashDirection`] picked in the
+//! settings. Steering is suspended until it ends, so the dash alone moves
+//! the player, and they keep [`MotionConfig::dash_carry`] of its speed into
+//! their run afterwards. They are [`Invulnerable`] for
+//! [`DashConfig::invulnerable`] seconds from its start. Fading copies of
+//! the player are left along the way while effects allow extras.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    budget::{Budget, BudgetCategory},
+    camera::CameraView,
+    config::{Palette, PlayerNose},
+    death::Invulnerable,
+    despawn::DespawnQueue,
+    events::PlayerDashed,
+    health::Health,
+    motion::{MotionConfig, Velocity},
+    quality::EffectsQuality,
+    settings::ControlSettings,
+    state::GameState,
+    tween::{Ease, SpriteColorLens, Tween, TweenCompleted},
+    Action, Player,
+};
+
+/// Which way a dash goes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum DashDirection {
+    /// Along the move stick, or the current facing when it is released.
+    #[default]
+    Movement,
+    /// Along the look stick, or the current facing when it is released.
+    Aim,
+    /// Along the move stick, falling back to the look stick and then the
+    /// current facing.
+    MovementThenAim,
+}
+
+impl DashDirection {
+    pub const ALL: [DashDirection; 3] = [
+        DashDirection::Movement,
+        DashDirection::Aim,
+        DashDirection::MovementThenAim,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DashDirection::Movement => "Movement",
+            DashDirection::Aim => "Aim",
+            DashDirection::MovementThenAim => "Move, then aim",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// The normalized dash direction for the given move and look axis pairs.
+    ///
+    /// Always falls back to `facing`, so a dash with no input still moves
+    /// the player forward instead of doing nothing.
+    pub fn resolve(self, movement: Vec2, look: Vec2, facing: Vec2) -> Vec2 {
+        let movement = movement.try_normalize();
+        let look = look.try_normalize();
+        let chosen = match self {
+            DashDirection::Movement => movement,
+            DashDirection::Aim => look,
+            DashDirection::MovementThenAim => movement.or(look),
+        };
+        chosen.or_else(|| facing.try_normalize()).unwrap_or(Vec2::X)
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct RewindConfig {
+    /// How far back a rewind goes, in seconds.
+    pub seconds: f32,
+    /// How long the trip back takes, in seconds.
+    pub playback: f32,
+    /// Seconds before the rewind can be used again.
+    pub cooldown: f32,
+}
+
+impl Default for RewindConfig {
+    fn default() -> Self {
+        Self {
+            seconds: 3.,
+            playback: 0.5,
+            cooldown: 20.,
+        }
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct DashConfig {
+    /// How far a dash goes, in world units.
+    pub distance: f32,
+    /// How long it takes, in seconds.
+    pub duration: f32,
+    /// Seconds before the dash can be used again.
+    pub cooldown: f32,
+    /// Seconds the player can't be hurt, from the start of the dash.
+    pub invulnerable: f32,
+    /// Seconds between two ghosts left behind.
+    pub ghost_interval: f32,
+    /// Seconds a ghost takes to fade out.
+    pub ghost_fade: f32,
+}
+
+impl Default for DashConfig {
+    fn default() -> Self {
+        Self {
+            distance: 140.,
+            duration: 0.15,
+            cooldown: 1.,
+            invulnerable: 0.25,
+            ghost_interval: 0.03,
+            ghost_fade: 0.2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RewindSample {
+    pub position: Vec2,
+    pub rotation: Quat,
+    pub health: f32,
+}
+
+/// The player's recent states, newest last, one per fixed tick.
+#[derive(Component, Debug, Default)]
+pub struct RewindHistory {
+    samples: VecDeque<RewindSample>,
+}
+
+impl RewindHistory {
+    /// Records `sample`, keeping at most `capacity` samples.
+    pub fn push(&mut self, sample: RewindSample, capacity: usize) {
+        while self.samples.len() >= capacity.max(1) {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// The state `t` of the way back through the history, from the newest
+    /// sample at 0 to the oldest at 1, interpolated between samples.
+    pub fn sample_back(&self, t: f32) -> Option<RewindSample> {
+        let last = self.samples.len().checked_sub(1)?;
+        let index = (1. - t.clamp(0., 1.)) * last as f32;
+        let (lower, upper) = (index.floor() as usize, index.ceil() as usize);
+        let (a, b) = (self.samples[lower], self.samples[upper]);
+        let blend = index.fract();
+        Some(RewindSample {
+            position: a.position.lerp(b.position, blend),
+            rotation: a.rotation.slerp(b.rotation, blend),
+            health: a.health + (b.health - a.health) * blend,
+        })
+    }
+}
+
+/// Rewind cooldown and playback state.
+#[derive(Component, Debug, Default)]
+pub struct Rewind {
+    /// Seconds left until the rewind can be used again.
+    pub cooldown: f32,
+}
+
+/// On the player while it is being rewound; movement and recording are
+/// suspended until playback finishes.
+#[derive(Component, Debug)]
+pub struct Rewinding {
+    elapsed: f32,
+}
+
+/// Dash cooldown.
+#[derive(Component, Debug, Default)]
+pub struct Dash {
+    /// Seconds left until the dash can be used again.
+    pub cooldown: f32,
+}
+
+/// On the player while dashing.
+#[derive(Component, Debug)]
+pub struct Dashing {
+    direction: Vec2,
+    remaining: f32,
+    since_ghost: f32,
+}
+
+/// A fading copy of the player left behind by a dash.
+#[derive(Component)]
+struct DashGhost;
+
+/// Ghosts of the player's body, a little faded from the start.
+const GHOST_ALPHA: f32 = 0.5;
+
+const REWIND_TINT: Color = Color::rgba(0.4, 0.9, 1., 0.6);
+
+pub struct AbilitiesPlugin;
+
+impl Plugin for AbilitiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<RewindConfig>()
+            .register_type::<DashConfig>()
+            .init_resource::<RewindConfig>()
+            .init_resource::<DashConfig>()
+            .add_systems(
+                FixedUpdate,
+                (record_rewind_history, play_dash).run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                ((start_rewind, play_rewind).chain(), start_dash)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(Update, remove_ghosts);
+    }
+}
+
+fn record_rewind_history(
+    config: Res<RewindConfig>,
+    time: Res<Time<Fixed>>,
+    mut players: Query<(&Transform, &Health, &mut RewindHistory), Without<Rewinding>>,
+) {
+    let capacity = (config.seconds / time.timestep().as_secs_f32()).ceil() as usize;
+    for (transform, health, mut history) in &mut players {
+        history.push(
+            RewindSample {
+                position: transform.translation.truncate(),
+                rotation: transform.rotation,
+                health: health.current,
+            },
+            capacity,
+        );
+    }
+}
+
+fn start_rewind(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<RewindConfig>,
+    mut players: Query<
+        (Entity, &ActionState<Action>, &Health, &mut Rewind),
+        (With<Player>, Without<Rewinding>),
+    >,
+) {
+    for (entity, action_state, health, mut rewind) in &mut players {
+        rewind.cooldown = (rewind.cooldown - time.delta_seconds()).max(0.);
+        if action_state.just_pressed(&Action::Rewind) && rewind.cooldown <= 0. && !health.is_dead()
+        {
+            rewind.cooldown = config.cooldown;
+            commands.entity(entity).insert(Rewinding { elapsed: 0. });
+        }
+    }
+}
+
+fn play_rewind(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<RewindConfig>,
+    palette: Res<Palette>,
+    mut players: Query<
+        (
+            Entity,
+            &mut Rewinding,
+            &mut RewindHistory,
+            &mut Transform,
+            &mut Health,
+            &mut Sprite,
+        ),
+        Without<PlayerNose>,
+    >,
+    mut noses: Query<&mut Sprite, With<PlayerNose>>,
+) {
+    for (entity, mut rewinding, mut history, mut transform, mut health, mut sprite) in &mut players
+    {
+        rewinding.elapsed += time.delta_seconds();
+        let t = if config.playback > 0. {
+            rewinding.elapsed / config.playback
+        } else {
+            1.
+        };
+
+        if let Some(sample) = history.sample_back(t) {
+            transform.translation = sample.position.extend(transform.translation.z);
+            transform.rotation = sample.rotation;
+            if t >= 1. {
+                health.current = sample.health.min(health.max);
+            }
+        }
+
+        let color = if t >= 1. { palette.player } else { REWIND_TINT };
+        sprite.color = color;
+        for mut nose in &mut noses {
+            nose.color = color;
+        }
+
+        if t >= 1. {
+            history.clear();
+            commands.entity(entity).remove::<Rewinding>();
+        }
+    }
+}
+
+fn start_dash(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<DashConfig>,
+    controls: Res<ControlSettings>,
+    view: Res<CameraView>,
+    mut players: Query<
+        (
+            Entity,
+            &ActionState<Action>,
+            &Transform,
+            &Health,
+            &mut Dash,
+            Option<&Invulnerable>,
+            Has<Rewinding>,
+        ),
+        (With<Player>, Without<Dashing>),
+    >,
+    mut dashed: EventWriter<PlayerDashed>,
+) {
+    for (entity, action_state, transform, health, mut dash, invulnerable, rewinding) in &mut players
+    {
+        dash.cooldown = (dash.cooldown - time.delta_seconds()).max(0.);
+        if !action_state.just_pressed(&Action::Dash)
+            || dash.cooldown > 0.
+            || rewinding
+            || health.is_dead()
+        {
+            continue;
+        }
+        let stick = |action| {
+            action_state
+                .clamped_axis_pair(&action)
+                .map(|axis| view.to_world(axis.xy()))
+                .unwrap_or_default()
+        };
+        let facing = (transform.rotation * Vec3::X).truncate();
+        let direction =
+            controls
+                .dash_direction
+                .resolve(stick(Action::Move), stick(Action::Look), facing);
+        dash.cooldown = config.cooldown;
+        let mut player = commands.entity(entity);
+        player.insert(Dashing {
+            direction,
+            remaining: config.duration,
+            since_ghost: config.ghost_interval,
+        });
+        // keep a longer invulnerability, like the one after a death
+        if invulnerable.is_none_or(|invulnerable| invulnerable.remaining < config.invulnerable) {
+            player.insert(Invulnerable {
+                remaining: config.invulnerable,
+            });
+        }
+        dashed.send(PlayerDashed { entity, direction });
+    }
+}
+
+pub fn play_dash(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<DashConfig>,
+    quality: Res<EffectsQuality>,
+    palette: Res<Palette>,
+    mut budget: Budget,
+    motion: Res<MotionConfig>,
+    mut players: Query<(Entity, &mut Dashing, &mut Transform, &mut Velocity, &Sprite)>,
+) {
+    let speed = config.distance / config.duration.max(0.01);
+    for (entity, mut dashing, mut transform, mut velocity, sprite) in &mut players {
+        dashing.since_ghost += time.delta_seconds();
+        if dashing.since_ghost >= config.ghost_interval && quality.extras() {
+            dashing.since_ghost = 0.;
+            if budget.admit(&mut commands, BudgetCategory::Vfx) {
+                spawn_ghost(
+                    &mut commands,
+                    &mut budget,
+                    &config,
+                    &palette,
+                    &transform,
+                    sprite,
+                );
+            }
+        }
+
+        // the player's movement carries them along at the dash's speed
+        if dashing.remaining > time.delta_seconds() {
+            dashing.remaining -= time.delta_seconds();
+            velocity.0 = dashing.direction * speed;
+            continue;
+        }
+        // the last, partial tick goes just what's left of the distance,
+        // and a little of the speed is kept into the run that follows
+        transform.translation += (dashing.direction * speed * dashing.remaining).extend(0.);
+        dashing.remaining = 0.;
+        velocity.0 = dashing.direction * speed * motion.dash_carry;
+        commands.entity(entity).remove::<Dashing>();
+    }
+}
+
+fn spawn_ghost(
+    commands: &mut Commands,
+    budget: &mut Budget,
+    config: &DashConfig,
+    palette: &Palette,
+    transform: &Transform,
+    sprite: &Sprite,
+) {
+    // the palette color rather than the sprite's, which may be mid-blink
+    let color = palette.player.with_a(GHOST_ALPHA);
+    let mut ghost = commands.spawn((
+        DashGhost,
+        Tween::new(
+            SpriteColorLens {
+                start: color,
+                end: color.with_a(0.),
+            },
+            Ease::QuadOut,
+            config.ghost_fade,
+        )
+        .with_completion(),
+        SpriteBundle {
+            transform: Transform {
+                // just under the player
+                translation: transform.translation - Vec3::Z * 0.1,
+                ..*transform
+            },
+            sprite: Sprite {
+                color,
+                custom_size: sprite.custom_size,
+                ..default()
+            },
+            ..default()
+        },
+    ));
+    budget.track(&mut ghost, BudgetCategory::Vfx);
+}
+
+fn remove_ghosts(
+    mut completed: EventReader<TweenCompleted>,
+    mut despawns: ResMut<DespawnQueue>,
+    ghosts: Query<(), With<DashGhost>>,
+) {
+    for event in completed.read() {
+        if ghosts.contains(event.entity) {
+            despawns.despawn(event.entity);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Accessibility options: a color-blind-safe palette, UI scale and aim
+//! assist.
+//!
+//! The options are stored in [`AccessibilitySettings`] with the other
+//! settings, and this module puts them into effect:
+//!
+//! - [`ColorBlindMode`] swaps the player, pickup and enemy colors for ones
+//!   that stay apart with red-green or blue-yellow color blindness. The
+//!   [`Palette`] from before is kept, so turning it off puts it back, and
+//!   enemies in play are recolored at once.
+//! - [`AccessibilitySettings::ui_scale`] scales every piece of UI through
+//!   Bevy's [`UiScale`], on top of the window-size text scaling in
+//!   [`crate::fonts`].
+//! - [`AccessibilitySettings::aim_assist`] bends the player's own aim
+//!   towards the enemy nearest it within [`AIM_ASSIST_HALF_ANGLE`]: at
+//!   full strength it points straight at them, at none it is left alone.
+//!   [`TargetLock::aim`] applies it, so shots, facing and the aim line all
+//!   follow. A lock still wins over it.
+//!
+//! [`AccessibilitySettings::reduce_motion`] is read where the motion is:
+//! camera shake, hit stops, hit flashes and muzzle lights.
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::CameraView,
+    config::Palette,
+    defs::EnemyDef,
+    enemy::EnemyKind,
+    layout,
+    lock::{LockConfig, TargetLock},
+    settings::AccessibilitySettings,
+    state::GameState,
+    targeting::EnemyGrid,
+    Action, Player,
+};
+
+/// Half-angle, in radians, of the cone around the player's aim that aim
+/// assist picks an enemy from.
+pub const AIM_ASSIST_HALF_ANGLE: f32 = 0.3;
+
+/// UI scales offered on the settings screen.
+const UI_SCALES: [f32; 4] = [0.75, 1., 1.25, 1.5];
+
+/// Aim assist strengths offered on the settings screen.
+const AIM_ASSIST_STRENGTHS: [f32; 5] = [0., 0.25, 0.5, 0.75, 1.];
+
+/// A palette for a kind of color blindness.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum ColorBlindMode {
+    /// The colors of the [`Palette`] and the enemy defs.
+    #[default]
+    Off,
+    /// Protanopia and deuteranopia, where reds and greens run together.
+    RedGreen,
+    /// Tritanopia, where blues and greens, and yellows and pinks, run
+    /// together.
+    BlueYellow,
+}
+
+impl ColorBlindMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorBlindMode::Off => "Off",
+            ColorBlindMode::RedGreen => "Red-green",
+            ColorBlindMode::BlueYellow => "Blue-yellow",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ColorBlindMode::Off => ColorBlindMode::RedGreen,
+            ColorBlindMode::RedGreen => ColorBlindMode::BlueYellow,
+            ColorBlindMode::BlueYellow => ColorBlindMode::Off,
+        }
+    }
+
+    fn player(self) -> Option<Color> {
+        match self {
+            ColorBlindMode::Off => None,
+            ColorBlindMode::RedGreen => Some(Color::rgb(0.34, 0.71, 0.91)),
+            ColorBlindMode::BlueYellow => Some(Color::rgb(0., 0.75, 0.75)),
+        }
+    }
+
+    fn pickup(self) -> Option<Color> {
+        match self {
+            ColorBlindMode::Off => None,
+            ColorBlindMode::RedGreen | ColorBlindMode::BlueYellow => Some(Color::WHITE),
+        }
+    }
+
+    /// The color of enemies of `kind`, or `None` to keep their def's.
+    pub fn enemy(self, kind: EnemyKind) -> Option<Color> {
+        match (self, kind) {
+            (ColorBlindMode::Off, _) => None,
+            (ColorBlindMode::RedGreen, EnemyKind::Grunt) => Some(Color::rgb(0.84, 0.37, 0.)),
+            (ColorBlindMode::RedGreen, EnemyKind::Runner) => Some(Color::rgb(0.94, 0.89, 0.26)),
+            (ColorBlindMode::RedGreen, EnemyKind::Brute) => Some(Color::rgb(0.8, 0.47, 0.65)),
+            (ColorBlindMode::BlueYellow, EnemyKind::Grunt) => Some(Color::rgb(0.9, 0.1, 0.1)),
+            (ColorBlindMode::BlueYellow, EnemyKind::Runner) => Some(Color::rgb(1., 0.55, 0.75)),
+            (ColorBlindMode::BlueYellow, EnemyKind::Brute) => Some(Color::rgb(0.55, 0., 0.)),
+        }
+    }
+}
+
+/// The color enemies of `def`'s kind are drawn in.
+pub fn enemy_color(settings: &AccessibilitySettings, def: &EnemyDef) -> Color {
+    settings.color_blind.enemy(def.kind).unwrap_or(def.color)
+}
+
+pub fn step_ui_scale(scale: f32) -> f32 {
+    layout::step(&UI_SCALES, scale)
+}
+
+pub fn clean_ui_scale(scale: f32) -> f32 {
+    layout::clean_step(&UI_SCALES, scale, 1.)
+}
+
+pub fn step_aim_assist(strength: f32) -> f32 {
+    layout::step(&AIM_ASSIST_STRENGTHS, strength)
+}
+
+pub fn clean_aim_assist(strength: f32) -> f32 {
+    layout::clean_step(&AIM_ASSIST_STRENGTHS, strength, 0.)
+}
+
+/// The palette as it was before a [`ColorBlindMode`] changed it.
+#[derive(Resource, Default)]
+struct StashedPalette(Option<Palette>);
+
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StashedPalette>().add_systems(
+            Update,
+            (
+                (apply_palette, apply_ui_scale).run_if(resource_changed::<AccessibilitySettings>),
+                track_aim_assist.run_if(in_state(GameState::Playing)),
+            ),
+        );
+    }
+}
+
+fn apply_palette(
+    settings: Res<AccessibilitySettings>,
+    mut palette: ResMut<Palette>,
+    mut stash: ResMut<StashedPalette>,
+) {
+    let mode = settings.color_blind;
+    if mode == ColorBlindMode::Off {
+        if let Some(original) = stash.0.take() {
+            *palette = original;
+        }
+        return;
+    }
+    let original = stash.0.get_or_insert_with(|| palette.clone());
+    *palette = Palette {
+        player: mode.player().unwrap_or(original.player),
+        pickup: mode.pickup().unwrap_or(original.pickup),
+        ..original.clone()
+    };
+}
+
+fn apply_ui_scale(settings: Res<AccessibilitySettings>, mut ui_scale: ResMut<UiScale>) {
+    if ui_scale.0 != settings.ui_scale {
+        ui_scale.0 = settings.ui_scale;
+    }
+}
+
+/// Picks the enemy aim assist pulls towards, from where the look stick
+/// points.
+fn track_aim_assist(
+    settings: Res<AccessibilitySettings>,
+    config: Res<LockConfig>,
+    grid: Res<EnemyGrid>,
+    view: Res<CameraView>,
+    mut lock: ResMut<TargetLock>,
+    players: Query<(&Transform, &ActionState<Action>), With<Player>>,
+) {
+    let assist = players
+        .get_single()
+        .ok()
+        .filter(|_| settings.aim_assist > 0.)
+        .and_then(|(transform, action_state)| {
+            let aim = action_state
+                .clamped_axis_pair(&Action::Look)
+                .and_then(|axis| view.to_world(axis.xy()).try_normalize())?;
+            grid.nearest_in_cone(
+                transform.translation.truncate(),
+                aim,
+                AIM_ASSIST_HALF_ANGLE,
+                config.range,
+            )
+        })
+        .map(|(_, position)| (position, settings.aim_assist));
+    if lock.assist != assist {
+        lock.assist = assist;
+    }
+}
@@ -0,0 +1,165 @@
+//! Achievements: goals on the lifetime [`Stats`], unlocked once and for all.
+//!
+//! Each [`AchievementDef`] names a stat and a threshold to reach, with a
+//! line saying what it asks for. The list comes from
+//! `assets/defs/default.achievements.ron` through [`Defs`], with
+//! [`AchievementDef::builtin`] standing in until it loads. Progress is the
+//! stats themselves, which the kill, movement, wave and death events keep
+//! up to date, so an achievement added to the file counts what was already
+//! done before it.
+//!
+//! An achievement unlocks as soon as its stat reaches the threshold, with a
+//! toast, and the ids of those unlocked are kept in [`storage`]. One taken
+//! out of the file stays unlocked, in case it comes back.
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    defs::Defs,
+    stats::{track_stats, StatKind, Stats},
+    storage,
+    toast::{Toast, ToastKind},
+};
+
+const ACHIEVEMENTS_KEY: &str = "achievements";
+
+/// An achievement that unlocks once `stat` reaches `threshold`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AchievementDef {
+    /// Stable identifier used for persistence.
+    pub id: String,
+    pub name: String,
+    /// What it takes, for the stats screen.
+    pub description: String,
+    pub stat: StatKind,
+    pub threshold: f64,
+}
+
+impl AchievementDef {
+    fn new(id: &str, name: &str, description: &str, stat: StatKind, threshold: f64) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            description: description.into(),
+            stat,
+            threshold,
+        }
+    }
+
+    /// The achievements there are until the def file has loaded.
+    pub fn builtin() -> Vec<Self> {
+        vec![
+            Self::new(
+                "first_blood",
+                "First Blood",
+                "Kill an enemy",
+                StatKind::Kills,
+                1.,
+            ),
+            Self::new(
+                "kills_100",
+                "100 Kills",
+                "Kill 100 enemies",
+                StatKind::Kills,
+                100.,
+            ),
+            Self::new(
+                "wanderer",
+                "Wanderer",
+                "Cover 10000 units on foot",
+                StatKind::Distance,
+                10_000.,
+            ),
+            Self::new(
+                "combo_10",
+                "Combo x10",
+                "Reach a combo of 10",
+                StatKind::BestCombo,
+                10.,
+            ),
+            Self::new(
+                "wave_10",
+                "Reach Wave 10",
+                "Reach wave 10 in a run",
+                StatKind::HighestWave,
+                10.,
+            ),
+            Self::new(
+                "survivor",
+                "Survivor",
+                "Clear 50 waves in all",
+                StatKind::WavesCleared,
+                50.,
+            ),
+            Self::new(
+                "persistent",
+                "Persistent",
+                "Die 10 times",
+                StatKind::Deaths,
+                10.,
+            ),
+        ]
+    }
+
+    /// How far `stats` are towards it, `0..=1`.
+    pub fn progress(&self, stats: &Stats) -> f32 {
+        if self.threshold <= 0. {
+            return 1.;
+        }
+        (stats.get(self.stat) / self.threshold).clamp(0., 1.) as f32
+    }
+}
+
+/// Ids of the achievements unlocked so far.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Achievements {
+    pub unlocked: HashSet<String>,
+}
+
+impl Achievements {
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.contains(id)
+    }
+}
+
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(storage::load::<Achievements>(ACHIEVEMENTS_KEY).unwrap_or_default())
+            .add_systems(
+                Update,
+                unlock_achievements
+                    .after(track_stats)
+                    .run_if(resource_changed::<Stats>.or_else(resource_changed::<Defs>)),
+            );
+    }
+}
+
+fn unlock_achievements(
+    stats: Res<Stats>,
+    defs: Res<Defs>,
+    mut achievements: ResMut<Achievements>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let mut unlocked_any = false;
+    for def in defs.achievements() {
+        if achievements.is_unlocked(&def.id) || stats.get(def.stat) < def.threshold {
+            continue;
+        }
+        achievements.unlocked.insert(def.id.clone());
+        toasts.send(
+            Toast::new(format!("Achievement unlocked: {}", def.name))
+                .with_kind(ToastKind::Achievement),
+        );
+        unlocked_any = true;
+    }
+
+    if unlocked_any {
+        storage::save(ACHIEVEMENTS_KEY, &*achievements);
+    }
+}
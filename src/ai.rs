@@ -0,0 +1,295 @@
+//! Enemy behaviour.
+//!
+//! Enemies start out wandering. One with a [`Vision`] cone switches to
+//! chasing once the player is inside it, and gives up again if the player
+//! gets far enough away. Enemies without one chase from the start, and
+//! chasers follow the [flow field](crate::pathfinding) around walls. In
+//! debug builds the cones can be drawn by turning on [`VisionDebug`].
+
+use bevy::prelude::*;
+
+use crate::{
+    defs::Defs,
+    difficulty::DifficultyScale,
+    enemy::{Enemy, EnemyKind},
+    pathfinding::{FlowField, NavGrid},
+    rng::GameRng,
+    state::GameState,
+    status::Statuses,
+    targeting::EnemyGrid,
+    Player,
+};
+
+/// What an enemy notices: the player is seen when within `range` and
+/// within `half_angle` radians of the way the enemy faces.
+#[derive(Component, Reflect, Debug, Clone, Copy)]
+pub struct Vision {
+    pub range: f32,
+    pub half_angle: f32,
+}
+
+impl Default for Vision {
+    fn default() -> Self {
+        Self {
+            range: 250.,
+            half_angle: 0.6,
+        }
+    }
+}
+
+impl Vision {
+    /// Whether an enemy at `position` facing `facing` sees `target`.
+    pub fn sees(&self, position: Vec2, facing: Vec2, target: Vec2) -> bool {
+        let offset = target - position;
+        if offset.length_squared() > self.range * self.range {
+            return false;
+        }
+        match (offset.try_normalize(), facing.try_normalize()) {
+            (Some(to_target), Some(facing)) => to_target.dot(facing) >= self.half_angle.cos(),
+            // standing on top of the target
+            (None, _) => true,
+            (_, None) => false,
+        }
+    }
+}
+
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq)]
+pub enum Behavior {
+    /// Drifting in `heading` until `remaining` seconds run out, then
+    /// picking a new heading.
+    Wander {
+        heading: Vec2,
+        remaining: f32,
+    },
+    Chase,
+}
+
+impl Default for Behavior {
+    fn default() -> Self {
+        Behavior::Wander {
+            heading: Vec2::X,
+            remaining: 0.,
+        }
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct EnemyAiConfig {
+    pub wander_speed: f32,
+    pub chase_speed: f32,
+    /// Seconds between wander heading changes, at most.
+    pub wander_interval: f32,
+    /// A chase is given up once the player is this many times the vision
+    /// range away.
+    pub give_up_factor: f32,
+    /// Enemies closer than this push each other apart, so a chasing pack
+    /// doesn't pile into one spot.
+    pub separation_radius: f32,
+    /// Speed two enemies on top of each other are pushed apart at, easing
+    /// off to nothing at the separation radius.
+    pub separation_speed: f32,
+}
+
+impl Default for EnemyAiConfig {
+    fn default() -> Self {
+        Self {
+            wander_speed: 30.,
+            chase_speed: 80.,
+            wander_interval: 2.,
+            give_up_factor: 1.5,
+            separation_radius: 22.,
+            separation_speed: 60.,
+        }
+    }
+}
+
+/// Draw every [`Vision`] cone. Only has an effect in debug builds.
+#[derive(Resource, Reflect, Debug, Clone, Default)]
+#[reflect(Resource)]
+pub struct VisionDebug {
+    pub show_cones: bool,
+}
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<EnemyAiConfig>()
+            .register_type::<VisionDebug>()
+            .register_type::<Vision>()
+            .register_type::<Behavior>()
+            .init_resource::<EnemyAiConfig>()
+            .init_resource::<VisionDebug>()
+            .add_systems(
+                FixedUpdate,
+                (update_aggro, move_enemies, separate_enemies)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+
+        #[cfg(debug_assertions)]
+        app.add_systems(Update, draw_vision_cones);
+    }
+}
+
+fn facing(transform: &Transform) -> Vec2 {
+    (transform.rotation * Vec3::X).truncate()
+}
+
+fn update_aggro(
+    config: Res<EnemyAiConfig>,
+    players: Query<&Transform, With<Player>>,
+    mut enemies: Query<
+        (&Transform, &mut Behavior, Option<&Vision>),
+        (With<Enemy>, Without<Player>),
+    >,
+) {
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+    let target = player.translation.truncate();
+
+    for (transform, mut behavior, vision) in &mut enemies {
+        let position = transform.translation.truncate();
+        let Some(vision) = vision else {
+            if *behavior != Behavior::Chase {
+                *behavior = Behavior::Chase;
+            }
+            continue;
+        };
+
+        match *behavior {
+            Behavior::Wander { .. } if vision.sees(position, facing(transform), target) => {
+                *behavior = Behavior::Chase;
+            }
+            Behavior::Chase if position.distance(target) > vision.range * config.give_up_factor => {
+                *behavior = Behavior::default();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn move_enemies(
+    time: Res<Time>,
+    config: Res<EnemyAiConfig>,
+    difficulty: Res<DifficultyScale>,
+    defs: Res<Defs>,
+    grid: Res<NavGrid>,
+    field: Res<FlowField>,
+    mut rng: ResMut<GameRng>,
+    players: Query<&Transform, With<Player>>,
+    mut enemies: Query<
+        (
+            &mut Transform,
+            &mut Behavior,
+            Option<&EnemyKind>,
+            Option<&Statuses>,
+        ),
+        (With<Enemy>, Without<Player>),
+    >,
+) {
+    let target = players
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation.truncate());
+    let dt = time.delta_seconds();
+    let speed = difficulty.enemy_speed;
+
+    for (mut transform, mut behavior, kind, statuses) in &mut enemies {
+        let position = transform.translation.truncate();
+        let speed = speed
+            * kind.map_or(1., |kind| defs.enemy(*kind).speed)
+            * statuses.map_or(1., Statuses::speed_multiplier);
+        let velocity = match &mut *behavior {
+            Behavior::Wander { heading, remaining } => {
+                *remaining -= dt;
+                if *remaining <= 0. {
+                    *heading = Vec2::from_angle(rng.range(0., std::f32::consts::TAU));
+                    *remaining = rng.range(0.5, 1.) * config.wander_interval;
+                }
+                *heading * config.wander_speed * speed
+            }
+            // around walls when there are any in the way
+            Behavior::Chase => target
+                .and_then(|target| {
+                    field
+                        .direction(&grid, position)
+                        .or_else(|| (target - position).try_normalize())
+                })
+                .map_or(Vec2::ZERO, |direction| {
+                    direction * config.chase_speed * speed
+                }),
+        };
+
+        transform.translation += (velocity * dt).extend(0.);
+        if let Some(direction) = velocity.try_normalize() {
+            transform.rotation = Quat::from_rotation_z(Vec2::X.angle_between(direction));
+        }
+    }
+}
+
+/// Pushes enemies out of each other, using the positions in the
+/// [`EnemyGrid`] from the start of the frame.
+fn separate_enemies(
+    time: Res<Time>,
+    config: Res<EnemyAiConfig>,
+    grid: Res<EnemyGrid>,
+    mut enemies: Query<(Entity, &mut Transform), With<Enemy>>,
+) {
+    let radius = config.separation_radius;
+    if radius <= 0. {
+        return;
+    }
+    let step = config.separation_speed * time.delta_seconds();
+    for (entity, mut transform) in &mut enemies {
+        let position = transform.translation.truncate();
+        let push: Vec2 = grid
+            .nearby_in_radius(position, radius)
+            .filter(|(other, _)| *other != entity)
+            .filter_map(|(_, other)| {
+                let offset = position - other;
+                let closeness = 1. - offset.length() / radius;
+                // exactly on top of each other there's no way apart yet;
+                // chasing will split them
+                offset.try_normalize().map(|away| away * closeness)
+            })
+            .sum();
+        if push != Vec2::ZERO {
+            transform.translation += (push.clamp_length_max(1.) * step).extend(0.);
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+const CONE_ARC_SEGMENTS: usize = 16;
+
+#[cfg(debug_assertions)]
+fn draw_vision_cones(
+    debug: Res<VisionDebug>,
+    mut gizmos: Gizmos,
+    enemies: Query<(&Transform, &Vision, &Behavior), With<Enemy>>,
+) {
+    if !debug.show_cones {
+        return;
+    }
+    for (transform, vision, behavior) in &enemies {
+        let position = transform.translation.truncate();
+        let facing = facing(transform);
+        let color = match behavior {
+            Behavior::Chase => Color::rgba(1., 0.2, 0.2, 0.6),
+            Behavior::Wander { .. } => Color::rgba(1., 1., 0.3, 0.4),
+        };
+        for side in [-vision.half_angle, vision.half_angle] {
+            let edge = Vec2::from_angle(side).rotate(facing) * vision.range;
+            gizmos.line_2d(position, position + edge, color);
+        }
+        let arc = (0..=CONE_ARC_SEGMENTS).map(|i| {
+            let t = i as f32 / CONE_ARC_SEGMENTS as f32;
+            let angle = -vision.half_angle + 2. * vision.half_angle * t;
+            position + Vec2::from_angle(angle).rotate(facing) * vision.range
+        });
+        gizmos.linestrip_2d(arc, color);
+    }
+}
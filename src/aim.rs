@@ -0,0 +1,151 @@
+//! Aim-line range indicator, and the landing marker for lobbed weapons.
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    camera::CameraView,
+    config::GameConfig,
+    lock::{LockConfig, TargetLock},
+    settings::DisplaySettings,
+    targeting::EnemyGrid,
+    weapon::{ArcConfig, Weapon},
+    Action, Player,
+};
+
+/// Enemies within this angle of the aim line count as targeted.
+const AIM_LINE_HALF_ANGLE: f32 = 0.1;
+
+const AIM_LINE_WIDTH: f32 = 2.;
+const AIM_LINE_COLOR: Color = Color::rgba(1., 1., 1., 0.15);
+const AIM_LINE_TARGET_COLOR: Color = Color::rgba(1., 0.3, 0.3, 0.35);
+const LANDING_MARKER_SIZE: f32 = 14.;
+const LANDING_MARKER_COLOR: Color = Color::rgba(1., 0.8, 0.3, 0.35);
+
+#[derive(Component)]
+struct AimLine;
+
+/// Where a lobbed shot would land, if nothing is in the way, shown while
+/// aiming a weapon with an [`ArcConfig`].
+#[derive(Component)]
+struct LandingMarker;
+
+pub struct AimPlugin;
+
+impl Plugin for AimPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, (spawn_aim_line, spawn_landing_marker))
+            .add_systems(Update, (update_aim_line, update_landing_marker));
+    }
+}
+
+fn spawn_aim_line(mut commands: Commands) {
+    commands.spawn((
+        AimLine,
+        SpriteBundle {
+            sprite: Sprite {
+                color: AIM_LINE_COLOR,
+                custom_size: Some(Vec2::new(0., AIM_LINE_WIDTH)),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+}
+
+fn spawn_landing_marker(mut commands: Commands) {
+    commands.spawn((
+        LandingMarker,
+        SpriteBundle {
+            sprite: Sprite {
+                color: LANDING_MARKER_COLOR,
+                custom_size: Some(Vec2::splat(LANDING_MARKER_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+}
+
+fn update_aim_line(
+    config: Res<GameConfig>,
+    display: Res<DisplaySettings>,
+    enemies: Res<EnemyGrid>,
+    lock: Res<TargetLock>,
+    lock_config: Res<LockConfig>,
+    view: Res<CameraView>,
+    players: Query<(&Transform, &ActionState<Action>, &Weapon), (With<Player>, Without<AimLine>)>,
+    mut lines: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<AimLine>>,
+) {
+    let Ok((mut line_transform, mut sprite, mut visibility)) = lines.get_single_mut() else {
+        return;
+    };
+    let Ok((player_transform, action_state, weapon)) = players.get_single() else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let aim = action_state
+        .clamped_axis_pair(&Action::Look)
+        .map(|axis| view.to_world(axis.xy()))
+        .unwrap_or_default();
+    let origin = player_transform.translation.truncate();
+    let direction = lock.aim(&lock_config, origin, aim);
+    let Some(direction) = direction.filter(|_| display.aim_line) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let start = origin + direction * config.player.nose_tip();
+    let range = weapon.range();
+
+    let on_target = enemies
+        .nearest_in_cone(start, direction, AIM_LINE_HALF_ANGLE, range)
+        .is_some();
+    sprite.color = if on_target {
+        AIM_LINE_TARGET_COLOR
+    } else {
+        AIM_LINE_COLOR
+    };
+    sprite.custom_size = Some(Vec2::new(range, AIM_LINE_WIDTH));
+
+    // the sprite is centered, so place it halfway along the line, just
+    // beneath the player
+    line_transform.translation = (start + direction * range / 2.).extend(-0.1);
+    line_transform.rotation = Quat::from_rotation_z(Vec2::X.angle_between(direction));
+    *visibility = Visibility::Visible;
+}
+
+fn update_landing_marker(
+    view: Res<CameraView>,
+    players: Query<
+        (&Transform, &ActionState<Action>, &Weapon),
+        (With<Player>, Without<LandingMarker>),
+    >,
+    mut markers: Query<(&mut Transform, &mut Visibility), With<LandingMarker>>,
+) {
+    let Ok((mut marker_transform, mut visibility)) = markers.get_single_mut() else {
+        return;
+    };
+    let aim = players
+        .get_single()
+        .ok()
+        .filter(|(_, _, weapon)| weapon.arc.is_some())
+        .and_then(|(transform, action_state, weapon)| {
+            let aim = action_state.clamped_axis_pair(&Action::Look)?.xy();
+            (aim != Vec2::ZERO).then(|| (transform, view.to_world(aim), weapon))
+        });
+    let Some((player_transform, aim, weapon)) = aim else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    // how far the stick is pushed picks the distance, up to the weapon's range
+    let origin = player_transform.translation.truncate();
+    let target = ArcConfig::landing_point(origin, aim, weapon.range());
+    marker_transform.translation = target.extend(-0.1);
+    *visibility = Visibility::Visible;
+}
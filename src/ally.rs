@@ -0,0 +1,253 @@
+//! A turret the player can deploy to fight beside them.
+//!
+//! [`Action::Deploy`] drops a [`Turret`] where the player stands, then
+//! starts [`AllyConfig::cooldown`]. The turret stays put for
+//! [`AllyConfig::duration`] seconds, blinking for the last
+//! [`EXPIRY_BLINK`] of them, turning to the nearest enemy within
+//! [`AllyConfig::range`] and shooting at it with [`AllyConfig::weapon`].
+//! Its shots are ordinary [projectiles](crate::projectile), out of the same
+//! pool and budget as the player's, so its kills score like the player's
+//! own. Enemies ignore it, and it can't be hurt.
+//!
+//! The HUD shows the cooldown beside the rewind and dash ones. A new run
+//! takes any turret away and makes the next one ready at once.
+
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::{prelude::*, sprite::Anchor};
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    budget::Budget,
+    death::StartNewRun,
+    despawn::DespawnQueue,
+    fx::Fx,
+    health::Health,
+    net::is_client,
+    projectile::{self, ShotPools},
+    state::GameState,
+    targeting::EnemyGrid,
+    weapon::Weapon,
+    Action, Player,
+};
+
+/// Seconds before expiring that a turret starts blinking.
+pub const EXPIRY_BLINK: f32 = 2.;
+const BLINK_RATE: f32 = 8.;
+const BARREL_LENGTH: f32 = 14.;
+const BARREL_WIDTH: f32 = 5.;
+/// Under the player, over the ground.
+const TURRET_Z: f32 = -0.1;
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct AllyConfig {
+    /// Seconds a turret stays deployed.
+    pub duration: f32,
+    /// Seconds from deploying a turret until the next can be.
+    pub cooldown: f32,
+    /// Furthest an enemy can be to be shot at.
+    pub range: f32,
+    /// What the turret fires, at the weapon's own rate.
+    pub weapon: Weapon,
+    /// Side length of the turret's base.
+    pub size: f32,
+    pub color: Color,
+}
+
+impl Default for AllyConfig {
+    fn default() -> Self {
+        Self {
+            duration: 10.,
+            cooldown: 25.,
+            range: 260.,
+            weapon: Weapon {
+                fire_rate: 2.5,
+                damage: Weapon::pistol().damage * 0.6,
+                ..Weapon::pistol()
+            },
+            size: 18.,
+            color: Color::rgb(0.5, 0.8, 0.6),
+        }
+    }
+}
+
+/// The player's turret cooldown.
+#[derive(Component, Debug, Default)]
+pub struct AllyDeploy {
+    /// Seconds left until a turret can be deployed again.
+    pub cooldown: f32,
+}
+
+/// A deployed turret.
+#[derive(Component, Debug)]
+pub struct Turret {
+    /// Seconds left before it goes.
+    pub remaining: f32,
+    /// Seconds left until it can fire again.
+    reload: f32,
+}
+
+/// The part of a turret that turns to its target.
+#[derive(Component)]
+struct TurretBarrel;
+
+pub struct AllyPlugin;
+
+impl Plugin for AllyPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AllyConfig>()
+            .init_resource::<AllyConfig>()
+            .add_systems(
+                Update,
+                (
+                    clear_turrets,
+                    (deploy_turret, fire_turrets, expire_turrets)
+                        .chain()
+                        .run_if(in_state(GameState::Playing))
+                        // a co-op client's turret is run by the host
+                        .run_if(not(is_client)),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn deploy_turret(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<AllyConfig>,
+    mut players: Query<(&ActionState<Action>, &Transform, &Health, &mut AllyDeploy), With<Player>>,
+) {
+    for (action_state, transform, health, mut deploy) in &mut players {
+        deploy.cooldown = (deploy.cooldown - time.delta_seconds()).max(0.);
+        if !action_state.just_pressed(&Action::Deploy) || deploy.cooldown > 0. || health.is_dead() {
+            continue;
+        }
+        deploy.cooldown = config.cooldown;
+        let position = transform.translation.truncate();
+        commands
+            .spawn((
+                Turret {
+                    remaining: config.duration,
+                    reload: 0.,
+                },
+                SpriteBundle {
+                    transform: Transform::from_translation(position.extend(TURRET_Z))
+                        .with_rotation(Quat::from_rotation_z(FRAC_PI_4)),
+                    sprite: Sprite {
+                        color: config.color,
+                        custom_size: Some(Vec2::splat(config.size)),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    TurretBarrel,
+                    SpriteBundle {
+                        // undoes the base's turn, so the barrel starts along +x
+                        transform: Transform::from_rotation(Quat::from_rotation_z(-FRAC_PI_4))
+                            .with_translation(Vec3::Z * 0.05),
+                        sprite: Sprite {
+                            color: config.color,
+                            custom_size: Some(Vec2::new(BARREL_LENGTH, BARREL_WIDTH)),
+                            anchor: Anchor::CenterLeft,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                ));
+            });
+    }
+}
+
+/// Turns each turret to the nearest enemy in range and shoots at it.
+fn fire_turrets(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<AllyConfig>,
+    grid: Res<EnemyGrid>,
+    mut budget: Budget,
+    mut pools: ShotPools,
+    mut turrets: Query<(&mut Turret, &Transform, &Children)>,
+    mut barrels: Query<&mut Transform, (With<TurretBarrel>, Without<Turret>)>,
+    mut fx: EventWriter<Fx>,
+) {
+    let weapon = &config.weapon;
+    for (mut turret, transform, children) in &mut turrets {
+        turret.reload = (turret.reload - time.delta_seconds()).max(0.);
+        let origin = transform.translation.truncate();
+        let Some(direction) = grid
+            .nearest_enemy(origin)
+            .filter(|(_, position)| position.distance(origin) <= config.range)
+            .and_then(|(_, position)| (position - origin).try_normalize())
+        else {
+            continue;
+        };
+
+        // the barrel is a child of the turned base
+        let angle = Vec2::X.angle_between(direction) - FRAC_PI_4;
+        for child in children {
+            if let Ok(mut barrel) = barrels.get_mut(*child) {
+                barrel.rotation = Quat::from_rotation_z(angle);
+            }
+        }
+        if turret.reload > 0. || weapon.fire_rate <= 0. {
+            continue;
+        }
+        turret.reload = 1. / weapon.fire_rate;
+        let muzzle = origin + direction * BARREL_LENGTH;
+        projectile::spawn_projectile(
+            &mut commands,
+            &mut budget,
+            &mut pools,
+            weapon,
+            weapon.modifiers,
+            muzzle,
+            direction,
+        );
+        fx.send(Fx::MuzzleFlash {
+            position: muzzle,
+            direction,
+        });
+    }
+}
+
+fn expire_turrets(
+    time: Res<Time>,
+    mut despawns: ResMut<DespawnQueue>,
+    mut turrets: Query<(Entity, &mut Turret, &mut Visibility)>,
+) {
+    for (entity, mut turret, mut visibility) in &mut turrets {
+        turret.remaining -= time.delta_seconds();
+        if turret.remaining <= 0. {
+            despawns.despawn(entity);
+            continue;
+        }
+        *visibility =
+            if turret.remaining < EXPIRY_BLINK && (turret.remaining * BLINK_RATE).fract() < 0.5 {
+                Visibility::Hidden
+            } else {
+                Visibility::Inherited
+            };
+    }
+}
+
+fn clear_turrets(
+    mut new_runs: EventReader<StartNewRun>,
+    mut despawns: ResMut<DespawnQueue>,
+    turrets: Query<Entity, With<Turret>>,
+    mut players: Query<&mut AllyDeploy>,
+) {
+    if new_runs.read().count() == 0 {
+        return;
+    }
+    for entity in &turrets {
+        despawns.despawn(entity);
+    }
+    for mut deploy in &mut players {
+        deploy.cooldown = 0.;
+    }
+}
@@ -0,0 +1,295 @@
+//! Anonymous session analytics, built only with the `telemetry` feature.
+//!
+//! Where [`crate::telemetry`] logs what happens for whoever is debugging,
+//! this keeps a few [`AnalyticsEvent`]s about each session for later: when
+//! it starts, how far each run gets and how long it lasts, how long the
+//! session ran when the app is suspended or closed, and any panic, from a
+//! panic hook. It is opt-in through the same [`DebugSettings::telemetry`]
+//! setting, and nothing is recorded while that is off. Records carry a
+//! random id drawn fresh each session and the seconds into it, and nothing
+//! about the player: no name, no device id, no scores.
+//!
+//! Records go to an [`AnalyticsBackend`]. The built-in ones keep them in
+//! [`storage`] on the device, or post each as JSON to
+//! [`AnalyticsConfig::endpoint`] when one is set; another kind only has to
+//! implement the trait and be picked from the config alongside them. A
+//! backend is called from the panic hook too, so it should be quick and not
+//! panic itself.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use bevy::{app::AppExit, prelude::*, utils::Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    death::StartNewRun, events::WaveStarted, lifecycle::AppSuspended, settings::DebugSettings,
+    state::GameState, storage,
+};
+
+const ANALYTICS_KEY: &str = "analytics";
+/// Most records kept on the device; the oldest go first.
+const MAX_STORED: usize = 500;
+
+#[derive(Resource, Reflect, Debug, Clone, Default)]
+#[reflect(Resource)]
+pub struct AnalyticsConfig {
+    /// Where records are posted; empty keeps them on the device.
+    pub endpoint: String,
+}
+
+/// Something worth knowing about a session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AnalyticsEvent {
+    SessionStarted {
+        /// `std::env::consts::OS`, like `android` or `windows`.
+        platform: String,
+        version: String,
+    },
+    /// A run ended in a game over.
+    RunEnded {
+        /// Highest wave reached.
+        wave: u32,
+        /// Seconds from the run's start.
+        duration: f32,
+    },
+    /// The app went into the background, which on a phone may be the last
+    /// it ever hears.
+    Suspended,
+    SessionEnded,
+    Crash {
+        message: String,
+    },
+}
+
+/// One [`AnalyticsEvent`], of one session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyticsRecord {
+    pub session: u64,
+    /// Seconds into the session.
+    pub at: f32,
+    pub event: AnalyticsEvent,
+}
+
+/// Where records go.
+pub trait AnalyticsBackend: Send + 'static {
+    fn record(&mut self, record: &AnalyticsRecord);
+}
+
+/// Keeps records in [`storage`], up to [`MAX_STORED`].
+pub struct LocalBackend {
+    records: Vec<AnalyticsRecord>,
+}
+
+impl LocalBackend {
+    /// Carries on after the records already stored.
+    pub fn load() -> Self {
+        Self {
+            records: storage::load(ANALYTICS_KEY).unwrap_or_default(),
+        }
+    }
+}
+
+impl AnalyticsBackend for LocalBackend {
+    fn record(&mut self, record: &AnalyticsRecord) {
+        if self.records.len() >= MAX_STORED {
+            self.records.remove(0);
+        }
+        self.records.push(record.clone());
+        storage::save(ANALYTICS_KEY, &self.records);
+    }
+}
+
+/// Posts each record as JSON to an endpoint.
+pub struct EndpointBackend {
+    pub endpoint: String,
+}
+
+impl AnalyticsBackend for EndpointBackend {
+    fn record(&mut self, record: &AnalyticsRecord) {
+        let Ok(body) = serde_json::to_vec(record) else {
+            return;
+        };
+        let mut request = ehttp::Request::post(&self.endpoint, body);
+        request.headers.insert("Content-Type", "application/json");
+        // nothing waits on the answer
+        ehttp::fetch(request, |response| match response {
+            Ok(response) if !response.ok => {
+                warn!("analytics: {} {}", response.status, response.status_text);
+            }
+            Err(err) => warn!("analytics: {err}"),
+            Ok(_) => {}
+        });
+    }
+}
+
+/// The built-in backend for `config`.
+fn backend(config: &AnalyticsConfig) -> Box<dyn AnalyticsBackend> {
+    if config.endpoint.is_empty() {
+        Box::new(LocalBackend::load())
+    } else {
+        Box::new(EndpointBackend {
+            endpoint: config.endpoint.clone(),
+        })
+    }
+}
+
+/// The session, shared with the panic hook.
+struct Session {
+    id: u64,
+    started: Instant,
+    enabled: bool,
+    backend: Box<dyn AnalyticsBackend>,
+}
+
+impl Session {
+    fn record(&mut self, event: AnalyticsEvent) {
+        if !self.enabled {
+            return;
+        }
+        let record = AnalyticsRecord {
+            session: self.id,
+            at: self.started.elapsed().as_secs_f32(),
+            event,
+        };
+        self.backend.record(&record);
+    }
+}
+
+/// Records [`AnalyticsEvent`]s for this session.
+#[derive(Resource, Clone)]
+pub struct Analytics(Arc<Mutex<Session>>);
+
+impl Analytics {
+    pub fn record(&self, event: AnalyticsEvent) {
+        if let Ok(mut session) = self.0.lock() {
+            session.record(event);
+        }
+    }
+
+    /// Sends records to `backend` from now on.
+    fn replace_backend(&self, backend: Box<dyn AnalyticsBackend>) {
+        if let Ok(mut session) = self.0.lock() {
+            session.backend = backend;
+        }
+    }
+}
+
+/// When the run in play started, and the furthest wave it has reached.
+#[derive(Resource, Debug)]
+struct RunProgress {
+    started: Instant,
+    wave: u32,
+}
+
+impl Default for RunProgress {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            wave: 0,
+        }
+    }
+}
+
+pub struct AnalyticsPlugin;
+
+impl Plugin for AnalyticsPlugin {
+    fn build(&self, app: &mut App) {
+        let config = app
+            .world
+            .get_resource_or_insert_with(AnalyticsConfig::default);
+        let analytics = Analytics(Arc::new(Mutex::new(Session {
+            // anything that tells sessions apart, and nothing else
+            id: RandomState::new().build_hasher().finish(),
+            started: Instant::now(),
+            enabled: false,
+            backend: backend(&config),
+        })));
+        install_panic_hook(analytics.clone());
+        app.register_type::<AnalyticsConfig>()
+            .init_resource::<RunProgress>()
+            .insert_resource(analytics)
+            .add_systems(
+                Update,
+                (
+                    opt_in.run_if(resource_changed::<DebugSettings>),
+                    pick_backend.run_if(resource_changed::<AnalyticsConfig>),
+                    track_runs,
+                )
+                    .chain(),
+            )
+            .add_systems(OnEnter(GameState::GameOver), record_run)
+            .add_systems(Last, record_session_end);
+    }
+}
+
+/// Records a panic, then lets the hook from before report it as usual.
+fn install_panic_hook(analytics: Analytics) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // a panic while the session is locked would wait forever
+        if let Ok(mut session) = analytics.0.try_lock() {
+            session.record(AnalyticsEvent::Crash {
+                message: info.to_string(),
+            });
+        }
+        previous(info);
+    }));
+}
+
+fn opt_in(settings: Res<DebugSettings>, analytics: Res<Analytics>) {
+    let Ok(mut session) = analytics.0.lock() else {
+        return;
+    };
+    let starting = settings.telemetry && !session.enabled;
+    session.enabled = settings.telemetry;
+    if starting {
+        session.record(AnalyticsEvent::SessionStarted {
+            platform: std::env::consts::OS.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        });
+    }
+}
+
+fn pick_backend(config: Res<AnalyticsConfig>, analytics: Res<Analytics>) {
+    // the session started out with it
+    if !config.is_added() {
+        analytics.replace_backend(backend(&config));
+    }
+}
+
+fn track_runs(
+    mut new_runs: EventReader<StartNewRun>,
+    mut waves: EventReader<WaveStarted>,
+    mut progress: ResMut<RunProgress>,
+) {
+    if new_runs.read().count() > 0 {
+        *progress = RunProgress::default();
+    }
+    for event in waves.read() {
+        progress.wave = progress.wave.max(event.wave);
+    }
+}
+
+fn record_run(progress: Res<RunProgress>, analytics: Res<Analytics>) {
+    analytics.record(AnalyticsEvent::RunEnded {
+        wave: progress.wave,
+        duration: progress.started.elapsed().as_secs_f32(),
+    });
+}
+
+fn record_session_end(
+    suspended: Res<AppSuspended>,
+    mut exits: EventReader<AppExit>,
+    analytics: Res<Analytics>,
+) {
+    if suspended.is_changed() && suspended.0 {
+        analytics.record(AnalyticsEvent::Suspended);
+    }
+    if exits.read().count() > 0 {
+        analytics.record(AnalyticsEvent::SessionEnded);
+    }
+}
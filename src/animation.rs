@@ -0,0 +1,286 @@
+//! Sprite sheet animation.
+//!
+//! An [`Animator`] plays one [`AnimationClip`] at a time, a run of frames in
+//! the entity's [`TextureAtlas`], picked by its [`AnimationState`]. The
+//! player is idle, running or shooting from their input, and enemies idle
+//! while wandering and run while chasing; both play their death clip when
+//! they die.
+//!
+//! The sheets are set in [`AnimationSheets`]. Without one, or until its
+//! image has loaded, the player and enemies stay the plain rectangles they
+//! are spawned as, so a missing or slow file never leaves them invisible.
+//! Sheets are tinted by the sprite color like any other texture, so drawing
+//! them in white keeps palettes and hit flashes working.
+
+use bevy::{asset::LoadState, prelude::*};
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    ai::Behavior,
+    enemy::{Dying, Enemy, Forming},
+    events::PlayerFired,
+    health::Health,
+    Action, Player,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum AnimationState {
+    #[default]
+    Idle,
+    Run,
+    Shoot,
+    Death,
+}
+
+/// `frames` frames of a sheet from index `first`, played at `fps`.
+#[derive(Debug, Clone, Copy, Reflect)]
+pub struct AnimationClip {
+    pub first: usize,
+    pub frames: usize,
+    pub fps: f32,
+    /// Whether the clip starts over at the end, rather than holding its
+    /// last frame.
+    pub looping: bool,
+}
+
+impl AnimationClip {
+    /// Atlas index `elapsed` seconds into the clip.
+    pub fn index(&self, elapsed: f32) -> usize {
+        let frames = self.frames.max(1);
+        let frame = (elapsed.max(0.) * self.fps) as usize;
+        let frame = if self.looping {
+            frame % frames
+        } else {
+            frame.min(frames - 1)
+        };
+        self.first + frame
+    }
+
+    /// Whether a clip that doesn't loop has played through by `elapsed`.
+    pub fn finished(&self, elapsed: f32) -> bool {
+        !self.looping && elapsed * self.fps >= self.frames as f32
+    }
+}
+
+/// The clip for each state of one sheet.
+#[derive(Debug, Clone, Copy, Reflect)]
+pub struct AnimationClips {
+    pub idle: AnimationClip,
+    pub run: AnimationClip,
+    pub shoot: AnimationClip,
+    pub death: AnimationClip,
+}
+
+impl AnimationClips {
+    pub fn get(&self, state: AnimationState) -> &AnimationClip {
+        match state {
+            AnimationState::Idle => &self.idle,
+            AnimationState::Run => &self.run,
+            AnimationState::Shoot => &self.shoot,
+            AnimationState::Death => &self.death,
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct Animator {
+    pub clips: AnimationClips,
+    pub state: AnimationState,
+    /// Seconds into the current clip.
+    pub elapsed: f32,
+}
+
+impl Animator {
+    pub fn new(clips: AnimationClips) -> Self {
+        Self {
+            clips,
+            state: default(),
+            elapsed: 0.,
+        }
+    }
+
+    /// Switches to `state`'s clip from its start, unless already playing it.
+    pub fn play(&mut self, state: AnimationState) {
+        if self.state != state {
+            self.restart(state);
+        }
+    }
+
+    /// Plays `state`'s clip from its start.
+    pub fn restart(&mut self, state: AnimationState) {
+        self.state = state;
+        self.elapsed = 0.;
+    }
+
+    pub fn clip(&self) -> &AnimationClip {
+        self.clips.get(self.state)
+    }
+
+    pub fn finished(&self) -> bool {
+        self.clip().finished(self.elapsed)
+    }
+}
+
+/// An image of equal frames in a grid, and the clips in it.
+#[derive(Reflect, Debug, Clone)]
+pub struct SheetConfig {
+    pub path: String,
+    pub frame_size: Vec2,
+    pub columns: usize,
+    pub rows: usize,
+    pub clips: AnimationClips,
+}
+
+#[derive(Resource, Reflect, Debug, Clone, Default)]
+#[reflect(Resource)]
+pub struct AnimationSheets {
+    pub player: Option<SheetConfig>,
+    pub enemy: Option<SheetConfig>,
+}
+
+/// A sheet's image and atlas layout once requested.
+#[derive(Debug, Clone)]
+struct LoadedSheet {
+    image: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+    clips: AnimationClips,
+}
+
+impl LoadedSheet {
+    fn components(&self) -> (Handle<Image>, TextureAtlas, Animator) {
+        (
+            self.image.clone(),
+            TextureAtlas {
+                layout: self.layout.clone(),
+                index: self.clips.idle.first,
+            },
+            Animator::new(self.clips),
+        )
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+struct LoadedSheets {
+    player: Option<LoadedSheet>,
+    enemy: Option<LoadedSheet>,
+}
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AnimationSheets>()
+            .init_resource::<AnimationSheets>()
+            .init_resource::<LoadedSheets>()
+            .add_systems(
+                Update,
+                (
+                    load_sheets.run_if(resource_changed::<AnimationSheets>),
+                    attach_sheets,
+                    (select_player_state, select_enemy_state),
+                    advance_animations,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn load_sheets(
+    sheets: Res<AnimationSheets>,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut loaded: ResMut<LoadedSheets>,
+) {
+    let mut load = |sheet: &Option<SheetConfig>| {
+        sheet.as_ref().map(|sheet| LoadedSheet {
+            image: asset_server.load(sheet.path.clone()),
+            layout: layouts.add(TextureAtlasLayout::from_grid(
+                sheet.frame_size,
+                sheet.columns,
+                sheet.rows,
+                None,
+                None,
+            )),
+            clips: sheet.clips,
+        })
+    };
+    loaded.player = load(&sheets.player);
+    loaded.enemy = load(&sheets.enemy);
+}
+
+/// Gives the player and enemies their sheet once its image has loaded.
+fn attach_sheets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    loaded: Res<LoadedSheets>,
+    players: Query<Entity, (With<Player>, Without<Animator>)>,
+    enemies: Query<Entity, (Or<(With<Enemy>, With<Forming>)>, Without<Animator>)>,
+) {
+    let ready = |sheet: &Option<LoadedSheet>| {
+        sheet
+            .as_ref()
+            .filter(|sheet| asset_server.load_state(&sheet.image) == LoadState::Loaded)
+            .cloned()
+    };
+    if let Some(sheet) = ready(&loaded.player) {
+        for entity in &players {
+            commands.entity(entity).insert(sheet.components());
+        }
+    }
+    if let Some(sheet) = ready(&loaded.enemy) {
+        for entity in &enemies {
+            commands.entity(entity).insert(sheet.components());
+        }
+    }
+}
+
+fn select_player_state(
+    mut fired: EventReader<PlayerFired>,
+    mut players: Query<(Entity, &ActionState<Action>, &Health, &mut Animator), With<Player>>,
+) {
+    let shooters: Vec<_> = fired.read().map(|event| event.entity).collect();
+    for (entity, action_state, health, mut animator) in &mut players {
+        if health.is_dead() {
+            animator.play(AnimationState::Death);
+            continue;
+        }
+        if shooters.contains(&entity) {
+            animator.restart(AnimationState::Shoot);
+            continue;
+        }
+        if animator.state == AnimationState::Shoot && !animator.finished() {
+            continue;
+        }
+        let moving = action_state
+            .clamped_axis_pair(&Action::Move)
+            .is_some_and(|axis| axis.xy() != Vec2::ZERO);
+        animator.play(if moving {
+            AnimationState::Run
+        } else {
+            AnimationState::Idle
+        });
+    }
+}
+
+fn select_enemy_state(
+    mut enemies: Query<(&mut Animator, Option<&Behavior>, Has<Dying>), Without<Player>>,
+) {
+    for (mut animator, behavior, dying) in &mut enemies {
+        let state = match behavior {
+            _ if dying => AnimationState::Death,
+            Some(Behavior::Chase) => AnimationState::Run,
+            _ => AnimationState::Idle,
+        };
+        animator.play(state);
+    }
+}
+
+fn advance_animations(time: Res<Time>, mut animated: Query<(&mut Animator, &mut TextureAtlas)>) {
+    for (mut animator, mut atlas) in &mut animated {
+        animator.elapsed += time.delta_seconds();
+        let index = animator.clip().index(animator.elapsed);
+        if atlas.index != index {
+            atlas.index = index;
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! Music and sound effects.
+//!
+//! Sound effects are one-shots played by sending [`PlaySfx`]; the gameplay
+//! events in [`crate::events`] send the usual ones, so most systems never
+//! touch audio. Each plays on its own entity that despawns when done, at
+//! most [`AudioConfig::max_voices`] at once, and the same effect starts at
+//! most once every [`AudioConfig::repeat_interval`] seconds so a burst of
+//! shots or kills doesn't stack into noise. Music is a single looping
+//! track. The two have separate volumes in [`AudioSettings`], stored with
+//! the other settings.
+//!
+//! Browsers keep audio suspended until the page gets a tap, click or key
+//! press. `index.html` resumes it on the first one, and until then nothing
+//! is played here: effects sent while locked are dropped rather than all
+//! going off at once, and the music starts on unlock.
+//!
+//! While the app is [suspended](crate::lifecycle) everything playing is
+//! paused and nothing new starts; it all picks up again on resume.
+//!
+//! Files live under `assets/audio`. A missing one is logged by the asset
+//! server and otherwise just stays silent.
+
+use bevy::{audio::Volume, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::{
+        EnemyKilled, PlayerDashed, PlayerDied, PlayerFired, PlayerHurt, WaveCleared, WaveStarted,
+    },
+    lifecycle::AppSuspended,
+};
+
+#[derive(Resource, Reflect, Serialize, Deserialize, Debug, Clone)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// `0..=1`.
+    pub music_volume: f32,
+    /// `0..=1`.
+    pub sfx_volume: f32,
+    /// Silences everything without losing the volumes.
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            music_volume: 0.6,
+            sfx_volume: 0.8,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn music(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.music_volume
+        }
+    }
+
+    pub fn sfx(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.sfx_volume
+        }
+    }
+
+    pub fn validate(&mut self) {
+        self.music_volume = clean_volume(self.music_volume);
+        self.sfx_volume = clean_volume(self.sfx_volume);
+    }
+}
+
+fn clean_volume(volume: f32) -> f32 {
+    if volume.is_finite() {
+        volume.clamp(0., 1.)
+    } else {
+        1.
+    }
+}
+
+/// The volume after `volume` on the settings screen: up a fifth, wrapping
+/// from full to silent.
+pub fn step_volume(volume: f32) -> f32 {
+    if volume >= 1. {
+        0.
+    } else {
+        ((volume * 5.).round() + 1.).min(5.) / 5.
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum Sfx {
+    Shoot,
+    Dash,
+    Hurt,
+    Death,
+    EnemyKilled,
+    WaveStarted,
+    WaveCleared,
+}
+
+impl Sfx {
+    pub const ALL: [Sfx; 7] = [
+        Sfx::Shoot,
+        Sfx::Dash,
+        Sfx::Hurt,
+        Sfx::Death,
+        Sfx::EnemyKilled,
+        Sfx::WaveStarted,
+        Sfx::WaveCleared,
+    ];
+
+    fn path(self) -> &'static str {
+        match self {
+            Sfx::Shoot => "audio/sfx/shoot.ogg",
+            Sfx::Dash => "audio/sfx/dash.ogg",
+            Sfx::Hurt => "audio/sfx/hurt.ogg",
+            Sfx::Death => "audio/sfx/death.ogg",
+            Sfx::EnemyKilled => "audio/sfx/enemy_killed.ogg",
+            Sfx::WaveStarted => "audio/sfx/wave_started.ogg",
+            Sfx::WaveCleared => "audio/sfx/wave_cleared.ogg",
+        }
+    }
+}
+
+/// Play `sfx` once, at `volume` times the effects volume.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlaySfx {
+    pub sfx: Sfx,
+    pub volume: f32,
+}
+
+impl PlaySfx {
+    pub fn new(sfx: Sfx) -> Self {
+        Self { sfx, volume: 1. }
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct AudioConfig {
+    pub music: String,
+    /// Most sound effects playing at once; more are dropped.
+    pub max_voices: usize,
+    /// Least time between two starts of the same sound effect, in seconds.
+    pub repeat_interval: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            music: "audio/music/rain.ogg".to_string(),
+            max_voices: 16,
+            repeat_interval: 0.05,
+        }
+    }
+}
+
+/// Whether the browser lets the page play sound yet. Always true off the
+/// web.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct AudioUnlocked(pub bool);
+
+impl Default for AudioUnlocked {
+    fn default() -> Self {
+        Self(!cfg!(target_arch = "wasm32"))
+    }
+}
+
+#[derive(Resource, Default)]
+struct SfxHandles(HashMap<Sfx, Handle<AudioSource>>);
+
+#[derive(Component)]
+struct SfxVoice;
+
+#[derive(Component)]
+struct Music;
+
+pub struct AudioPlugin;
+
+impl Plugin for AudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AudioConfig>()
+            .register_type::<AudioSettings>()
+            .init_resource::<AudioConfig>()
+            .init_resource::<AudioUnlocked>()
+            .init_resource::<SfxHandles>()
+            .add_event::<PlaySfx>()
+            .add_systems(Startup, load_sfx)
+            .add_systems(
+                Update,
+                (
+                    unlock.run_if(|unlocked: Res<AudioUnlocked>| !unlocked.0),
+                    pause_when_suspended.run_if(resource_changed::<AppSuspended>),
+                    sfx_from_events,
+                    play_sfx,
+                    start_music,
+                    apply_music_volume.run_if(resource_changed::<AudioSettings>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn load_sfx(asset_server: Res<AssetServer>, mut handles: ResMut<SfxHandles>) {
+    for sfx in Sfx::ALL {
+        handles.0.insert(sfx, asset_server.load(sfx.path()));
+    }
+}
+
+/// Matches the gestures `index.html` resumes the browser's audio on.
+fn unlock(
+    mut unlocked: ResMut<AudioUnlocked>,
+    touches: Res<Touches>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+) {
+    if touches.any_just_pressed()
+        || mouse.get_just_pressed().next().is_some()
+        || keys.get_just_pressed().next().is_some()
+    {
+        unlocked.0 = true;
+    }
+}
+
+fn pause_when_suspended(suspended: Res<AppSuspended>, sinks: Query<&AudioSink>) {
+    for sink in &sinks {
+        if suspended.0 {
+            sink.pause();
+        } else {
+            sink.play();
+        }
+    }
+}
+
+fn sfx_from_events(
+    mut fired: EventReader<PlayerFired>,
+    mut dashed: EventReader<PlayerDashed>,
+    mut hurt: EventReader<PlayerHurt>,
+    mut died: EventReader<PlayerDied>,
+    mut killed: EventReader<EnemyKilled>,
+    mut waves: EventReader<WaveStarted>,
+    mut cleared: EventReader<WaveCleared>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    for (count, effect) in [
+        (fired.read().count(), Sfx::Shoot),
+        (dashed.read().count(), Sfx::Dash),
+        (hurt.read().count(), Sfx::Hurt),
+        (died.read().count(), Sfx::Death),
+        (killed.read().count(), Sfx::EnemyKilled),
+        (waves.read().count(), Sfx::WaveStarted),
+        (cleared.read().count(), Sfx::WaveCleared),
+    ] {
+        // several in one frame would only be heard as one
+        if count > 0 {
+            sfx.send(PlaySfx::new(effect));
+        }
+    }
+}
+
+fn play_sfx(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    config: Res<AudioConfig>,
+    settings: Res<AudioSettings>,
+    unlocked: Res<AudioUnlocked>,
+    suspended: Res<AppSuspended>,
+    handles: Res<SfxHandles>,
+    mut requests: EventReader<PlaySfx>,
+    mut last_played: Local<HashMap<Sfx, f32>>,
+    voices: Query<(), With<SfxVoice>>,
+) {
+    let mut playing = voices.iter().count();
+    let now = time.elapsed_seconds();
+    for request in requests.read() {
+        let volume = settings.sfx() * request.volume;
+        if !unlocked.0 || suspended.0 || volume <= 0. || playing >= config.max_voices {
+            continue;
+        }
+        if last_played
+            .get(&request.sfx)
+            .is_some_and(|last| now - last < config.repeat_interval)
+        {
+            continue;
+        }
+        let Some(source) = handles.0.get(&request.sfx) else {
+            continue;
+        };
+        commands.spawn((
+            SfxVoice,
+            AudioBundle {
+                source: source.clone(),
+                settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(volume)),
+            },
+        ));
+        last_played.insert(request.sfx, now);
+        playing += 1;
+    }
+}
+
+fn start_music(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<AudioConfig>,
+    settings: Res<AudioSettings>,
+    unlocked: Res<AudioUnlocked>,
+    suspended: Res<AppSuspended>,
+    music: Query<(), With<Music>>,
+) {
+    if !unlocked.0 || suspended.0 || !music.is_empty() {
+        return;
+    }
+    commands.spawn((
+        Music,
+        AudioBundle {
+            source: asset_server.load(config.music.clone()),
+            settings: PlaybackSettings::LOOP.with_volume(Volume::new(settings.music())),
+        },
+    ));
+}
+
+fn apply_music_volume(settings: Res<AudioSettings>, sinks: Query<&AudioSink, With<Music>>) {
+    for sink in &sinks {
+        sink.set_volume(settings.music());
+    }
+}
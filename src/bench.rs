@@ -0,0 +1,332 @@
+//! A benchmark of the simulation, for catching slowdowns.
+//!
+//! Launched with `--bench`, the game skips the menu and plays one run for
+//! [`Bench::frames`] frames, then prints how long the frames took and how
+//! many entities there were, and quits. `--bench=FRAMES` sets the number
+//! of frames, [`DEFAULT_FRAMES`] otherwise, and `--bench-budget=MS` fails
+//! the benchmark, with a nonzero exit code, if the 95th percentile frame
+//! takes longer than that many milliseconds.
+//!
+//! Each frame is one [simulation tick](crate::interpolation::SIMULATION_HZ)
+//! of game time, forced through [`TimeUpdateStrategy`], however long it
+//! really takes. The run is seeded with [`BENCH_SEED`], the player can't
+//! be hurt, and their input is scripted: circling, aiming the other way
+//! round, always shooting and dashing every [`DASH_INTERVAL`] frames, so
+//! the waves build up, fight and spawn shots and particles the same way
+//! every time. [Storage](crate::storage) is off, so the run starts from
+//! the defaults and saves nothing, and [scenes](crate::cutscene) are left
+//! out. The first [`WARMUP_FRAMES`], which load the assets and fill the
+//! pools, aren't counted.
+//!
+//! Frame times are measured between the ends of consecutive frames, so
+//! they include rendering unless the game is built with the `headless`
+//! feature, which runs without a window or a renderer. With a window the
+//! benchmark turns off vsync.
+
+use std::time::Duration;
+
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    time::TimeUpdateStrategy,
+    utils::Instant,
+    window::{PresentMode, PrimaryWindow},
+};
+use leafwing_input_manager::{
+    axislike::DualAxisData, buttonlike::ButtonState, plugin::InputManagerSystem, prelude::*,
+};
+
+use crate::{
+    death::{Invulnerable, StartNewRun},
+    enemy::Enemy,
+    generation, gestures,
+    interpolation::SIMULATION_HZ,
+    particles::Particle,
+    projectile::Projectile,
+    replay,
+    rng::GameRng,
+    state::GameState,
+    Action, Player,
+};
+
+/// Frames counted when `--bench` doesn't say.
+pub const DEFAULT_FRAMES: u32 = 3600;
+/// Frames run before counting starts.
+pub const WARMUP_FRAMES: u32 = 120;
+/// The seed every benchmark run is played with.
+pub const BENCH_SEED: u64 = 0x5eed_be4c;
+/// Frames between the scripted dashes.
+const DASH_INTERVAL: u32 = 120;
+/// Turns of the scripted circling per second.
+const CIRCLE_RATE: f32 = 0.25;
+
+/// A benchmark to run, from the command line.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Bench {
+    /// Frames counted, after the warmup.
+    pub frames: u32,
+    /// Slowest 95th percentile frame that passes, in milliseconds.
+    pub budget_ms: Option<f32>,
+}
+
+impl Bench {
+    /// The benchmark `args` ask for, if `--bench` is among them.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut bench = None;
+        let mut budget_ms = None;
+        for arg in args {
+            if arg == "--bench" {
+                bench = Some(DEFAULT_FRAMES);
+            } else if let Some(frames) = arg.strip_prefix("--bench=") {
+                match frames.parse() {
+                    Ok(frames) => bench = Some(frames),
+                    Err(_) => warn!("ignoring `{arg}`: not a number of frames"),
+                }
+            } else if let Some(ms) = arg.strip_prefix("--bench-budget=") {
+                match ms.parse() {
+                    Ok(ms) => budget_ms = Some(ms),
+                    Err(_) => warn!("ignoring `{arg}`: not a number of milliseconds"),
+                }
+            }
+        }
+        bench.map(|frames| Self { frames, budget_ms })
+    }
+}
+
+/// Whether a benchmark is running, for what it should leave out.
+pub fn is_benchmarking(bench: Option<Res<Bench>>) -> bool {
+    bench.is_some()
+}
+
+/// Entities of each kind in one frame.
+#[derive(Debug, Default, Clone, Copy)]
+struct EntityCounts {
+    entities: usize,
+    enemies: usize,
+    projectiles: usize,
+    particles: usize,
+}
+
+impl EntityCounts {
+    fn max(self, other: Self) -> Self {
+        Self {
+            entities: self.entities.max(other.entities),
+            enemies: self.enemies.max(other.enemies),
+            projectiles: self.projectiles.max(other.projectiles),
+            particles: self.particles.max(other.particles),
+        }
+    }
+}
+
+/// What the benchmark has measured so far.
+#[derive(Resource, Debug, Default)]
+struct BenchProgress {
+    /// Frames run, warmup included.
+    frame: u32,
+    /// When the last frame ended.
+    last_frame: Option<Instant>,
+    /// Each counted frame's time, in milliseconds.
+    frame_times: Vec<f32>,
+    /// Summed over the counted frames.
+    total: EntityCounts,
+    peak: EntityCounts,
+}
+
+/// Runs the benchmark in [`Bench`], if any.
+pub struct BenchPlugin(pub Option<Bench>);
+
+impl Plugin for BenchPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(bench) = self.0.clone() else {
+            return;
+        };
+        app.insert_resource(bench)
+            .init_resource::<BenchProgress>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+                1. / SIMULATION_HZ,
+            )))
+            .add_systems(Startup, (start_bench, turn_off_vsync))
+            .add_systems(
+                Update,
+                seed_run
+                    .after(replay::begin_run)
+                    .before(generation::build_arena),
+            )
+            .add_systems(
+                PreUpdate,
+                (keep_invulnerable, script_input)
+                    .after(InputManagerSystem::Update)
+                    .after(gestures::press_gesture_actions)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(Last, measure_frame);
+    }
+}
+
+fn start_bench(
+    bench: Res<Bench>,
+    mut new_runs: EventWriter<StartNewRun>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    info!(
+        "benchmarking {} frames after {WARMUP_FRAMES} of warmup",
+        bench.frames
+    );
+    new_runs.send(StartNewRun);
+    next_state.set(GameState::Playing);
+}
+
+fn turn_off_vsync(mut windows: Query<&mut Window, With<PrimaryWindow>>) {
+    for mut window in &mut windows {
+        window.present_mode = PresentMode::AutoNoVsync;
+    }
+}
+
+/// Reseeds the run after the replay has picked its own seed.
+fn seed_run(mut new_runs: EventReader<StartNewRun>, mut rng: ResMut<GameRng>) {
+    if new_runs.read().count() > 0 {
+        rng.reseed(BENCH_SEED);
+    }
+}
+
+fn keep_invulnerable(
+    mut commands: Commands,
+    players: Query<Entity, (With<Player>, Without<Invulnerable>)>,
+) {
+    for entity in &players {
+        commands.entity(entity).insert(Invulnerable {
+            remaining: f32::INFINITY,
+        });
+    }
+}
+
+fn script_input(
+    progress: Res<BenchProgress>,
+    mut shooting: Local<bool>,
+    mut players: Query<&mut ActionState<Action>, With<Player>>,
+) {
+    let Ok(mut action_state) = players.get_single_mut() else {
+        return;
+    };
+    let frame = progress.frame;
+    let angle = frame as f32 / SIMULATION_HZ as f32 * CIRCLE_RATE * std::f32::consts::TAU;
+    let movement = Vec2::from_angle(angle);
+    let look = Vec2::from_angle(-angle);
+    for (action, axis) in [(Action::Move, movement), (Action::Look, look)] {
+        if let Some(data) = action_state.action_data_mut(&action) {
+            data.axis_pair = Some(DualAxisData::from_xy(axis));
+            data.value = axis.length();
+        }
+    }
+
+    let shoot = if std::mem::replace(&mut *shooting, true) {
+        ButtonState::Pressed
+    } else {
+        ButtonState::JustPressed
+    };
+    let dash = match frame % DASH_INTERVAL {
+        0 => ButtonState::JustPressed,
+        1 => ButtonState::JustReleased,
+        _ => ButtonState::Released,
+    };
+    for (action, state) in [(Action::Shoot, shoot), (Action::Dash, dash)] {
+        if action_state.action_data_mut(&action).is_none() {
+            action_state.release(&action);
+        }
+        if let Some(data) = action_state.action_data_mut(&action) {
+            // leafwing sets these from the real input first, so they are
+            // written as a whole rather than pressed or released
+            data.state = state;
+        }
+    }
+}
+
+fn measure_frame(
+    bench: Res<Bench>,
+    mut progress: ResMut<BenchProgress>,
+    entities: Query<()>,
+    enemies: Query<(), With<Enemy>>,
+    projectiles: Query<(), With<Projectile>>,
+    particles: Query<(), With<Particle>>,
+    mut exits: EventWriter<AppExit>,
+) {
+    let now = Instant::now();
+    let last_frame = progress.last_frame.replace(now);
+    progress.frame += 1;
+    if progress.frame <= WARMUP_FRAMES {
+        return;
+    }
+    if let Some(last_frame) = last_frame {
+        let ms = (now - last_frame).as_secs_f32() * 1000.;
+        progress.frame_times.push(ms);
+    }
+    let counts = EntityCounts {
+        entities: entities.iter().len(),
+        enemies: enemies.iter().len(),
+        projectiles: projectiles.iter().len(),
+        particles: particles.iter().len(),
+    };
+    progress.total = EntityCounts {
+        entities: progress.total.entities + counts.entities,
+        enemies: progress.total.enemies + counts.enemies,
+        projectiles: progress.total.projectiles + counts.projectiles,
+        particles: progress.total.particles + counts.particles,
+    };
+    progress.peak = progress.peak.max(counts);
+
+    if progress.frame < WARMUP_FRAMES + bench.frames {
+        return;
+    }
+    let passed = report(&bench, &progress);
+    if !passed {
+        std::process::exit(1);
+    }
+    exits.send(AppExit);
+}
+
+/// Prints what the benchmark measured, and whether it kept to its budget.
+fn report(bench: &Bench, progress: &BenchProgress) -> bool {
+    let mut times = progress.frame_times.clone();
+    times.sort_by(f32::total_cmp);
+    let count = times.len().max(1);
+    let percentile = |p: f32| {
+        let index = ((times.len() as f32 * p).ceil() as usize).clamp(1, count) - 1;
+        times.get(index).copied().unwrap_or(0.)
+    };
+    let mean = times.iter().sum::<f32>() / count as f32;
+    let p95 = percentile(0.95);
+
+    println!(
+        "bench: {} frames at {SIMULATION_HZ} Hz, seed {BENCH_SEED:#x}",
+        times.len()
+    );
+    println!(
+        "frame ms: mean {mean:.2}  min {:.2}  p50 {:.2}  p95 {p95:.2}  p99 {:.2}  max {:.2}",
+        times.first().copied().unwrap_or(0.),
+        percentile(0.5),
+        percentile(0.99),
+        times.last().copied().unwrap_or(0.),
+    );
+    let frames = bench.frames.max(1) as f32;
+    let (total, peak) = (progress.total, progress.peak);
+    for (name, total, peak) in [
+        ("entities", total.entities, peak.entities),
+        ("enemies", total.enemies, peak.enemies),
+        ("projectiles", total.projectiles, peak.projectiles),
+        ("particles", total.particles, peak.particles),
+    ] {
+        println!("{name}: mean {:.0}  peak {peak}", total as f32 / frames);
+    }
+
+    match bench.budget_ms {
+        Some(budget) if p95 > budget => {
+            println!("FAILED: p95 {p95:.2} ms over the {budget:.2} ms budget");
+            false
+        }
+        Some(budget) => {
+            println!("passed: p95 {p95:.2} ms within the {budget:.2} ms budget");
+            true
+        }
+        None => true,
+    }
+}
@@ -0,0 +1,684 @@
+//! Boss fights, every few waves.
+//!
+//! When [the director](crate::director) makes a wave a boss wave, the
+//! spawner sends [`SpawnBoss`] instead of opening portals. The boss comes in
+//! with a scripted intro: its [entrance scene](crate::cutscene) plays, and
+//! a banner with its name shows while it fades in across the arena from
+//! the player, untouchable, for [`BossConfig::intro`] seconds. The arena
+//! closes in to [`BossConfig::arena`] as it arrives and opens back up once
+//! it is gone, its walls drawn while it is closed.
+//!
+//! The fight is in [`BossConfig::phases`]: the boss moves on to a phase once
+//! its health drops to that phase's [`BossPhase::below`], and in each phase
+//! loops through a pattern of [`BossAttack`]s, one after the other. Chasing,
+//! charging, rings of shots, summoned packs and pauses mix into any pattern.
+//! Its health shows in a bar along the bottom of the screen, and the wave is
+//! cleared once it and everything it summoned are dead.
+//!
+//! The boss is an [`Enemy`] without an [`EnemyKind`](crate::enemy::EnemyKind),
+//! so it can't be sent to [co-op](crate::net) clients or kept in
+//! [saves](crate::save). A co-op run has no boss waves instead, and no
+//! snapshot is taken during one, so a continued run goes back to before
+//! the fight.
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::{
+    camera::CameraShake,
+    config::{GameConfig, Palette},
+    cutscene::{CutsceneId, PlayCutscene},
+    death::StartNewRun,
+    despawn::DespawnQueue,
+    director::DirectorConfig,
+    enemy::{Dying, Enemy, EnemyKind, Unpooled},
+    events::EnemyKilled,
+    floating_text::FloatingText,
+    fonts::UiFonts,
+    haptics::HapticPulse,
+    health::{DamageEvent, Health},
+    i18n::{Locale, LocalizedText},
+    interpolation::Interpolated,
+    physics::{Collider, CollisionLayer},
+    portal::{PortalConfig, PortalSpawn},
+    state::{GameState, StateScoped},
+    status::Statuses,
+    toast::{Toast, ToastKind},
+    Player,
+};
+
+const SHOT_SIZE: f32 = 10.;
+/// Seconds a shot flies before it fades out.
+const SHOT_LIFETIME: f32 = 4.;
+/// Opacity and scale the boss starts its intro from.
+const INTRO_ALPHA: f32 = 0.;
+const INTRO_SCALE: f32 = 0.3;
+/// How much the boss swells and shrinks while winding up a charge.
+const WINDUP_PULSE: f32 = 0.12;
+const BAR_WIDTH: f32 = 320.;
+const BAR_HEIGHT: f32 = 10.;
+const BAR_COLOR: Color = Color::rgb(0.9, 0.2, 0.2);
+
+/// Bring in the boss of `wave`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SpawnBoss {
+    pub wave: u32,
+}
+
+/// One move in a boss's pattern.
+#[derive(Reflect, Debug, Clone, Copy)]
+pub enum BossAttack {
+    /// Walk towards the player for `duration` seconds.
+    Chase { speed: f32, duration: f32 },
+    /// Stand and swell for `windup` seconds, then rush for `duration`
+    /// seconds along the line to where the player was.
+    Charge {
+        windup: f32,
+        speed: f32,
+        duration: f32,
+    },
+    /// A ring of `shots` shots, each taking `damage` off the player it hits.
+    Volley { shots: u32, speed: f32, damage: f32 },
+    /// Open a portal of `count` enemies of `kind` next to the boss.
+    Summon { kind: EnemyKind, count: u32 },
+    /// Stand still for `duration` seconds.
+    Rest { duration: f32 },
+}
+
+/// A stretch of the fight.
+#[derive(Reflect, Debug, Clone)]
+pub struct BossPhase {
+    /// Fraction of its health the boss is down to when the phase starts.
+    pub below: f32,
+    /// Attacks made in order, starting over after the last.
+    pub pattern: Vec<BossAttack>,
+}
+
+impl BossPhase {
+    pub fn new(below: f32, pattern: impl Into<Vec<BossAttack>>) -> Self {
+        Self {
+            below,
+            pattern: pattern.into(),
+        }
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct BossConfig {
+    /// Shown in the intro banner and over the health bar.
+    pub name: String,
+    /// Health of the first boss of a run.
+    pub health: f32,
+    /// Added to the health multiplier for each boss after the first.
+    pub health_growth: f32,
+    pub size: f32,
+    pub color: Color,
+    /// Seconds of the intro, before the boss can be hit or attacks.
+    pub intro: f32,
+    /// Size the arena closes in to while the boss is alive.
+    pub arena: Vec2,
+    /// How far from the player the boss comes in.
+    pub distance: f32,
+    /// In order, each starting at a lower [`BossPhase::below`] than the last.
+    pub phases: Vec<BossPhase>,
+}
+
+impl Default for BossConfig {
+    fn default() -> Self {
+        let chase = |speed, duration| BossAttack::Chase { speed, duration };
+        let charge = |windup, speed| BossAttack::Charge {
+            windup,
+            speed,
+            duration: 0.6,
+        };
+        let volley = |shots, speed| BossAttack::Volley {
+            shots,
+            speed,
+            damage: 10.,
+        };
+        let rest = |duration| BossAttack::Rest { duration };
+        Self {
+            name: "The Warden".into(),
+            health: 1500.,
+            health_growth: 0.5,
+            size: 64.,
+            color: Color::rgb(0.75, 0.1, 0.35),
+            intro: 2.5,
+            arena: Vec2::new(900., 700.),
+            distance: 320.,
+            phases: vec![
+                BossPhase::new(
+                    1.,
+                    [
+                        chase(90., 3.),
+                        volley(12, 220.),
+                        rest(0.8),
+                        charge(0.8, 520.),
+                        rest(1.),
+                    ],
+                ),
+                BossPhase::new(
+                    0.6,
+                    [
+                        BossAttack::Summon {
+                            kind: EnemyKind::Runner,
+                            count: 4,
+                        },
+                        chase(110., 2.),
+                        volley(16, 240.),
+                        charge(0.7, 560.),
+                        volley(16, 240.),
+                        rest(0.6),
+                    ],
+                ),
+                BossPhase::new(
+                    0.3,
+                    [
+                        charge(0.5, 620.),
+                        volley(24, 260.),
+                        BossAttack::Summon {
+                            kind: EnemyKind::Grunt,
+                            count: 6,
+                        },
+                        charge(0.5, 620.),
+                        volley(24, 260.),
+                        rest(0.4),
+                    ],
+                ),
+            ],
+        }
+    }
+}
+
+/// Where a boss is in its fight.
+#[derive(Component, Debug, Default)]
+pub struct Boss {
+    /// Index into [`BossConfig::phases`].
+    phase: usize,
+    /// Attacks made so far in the phase.
+    step: usize,
+    /// Seconds into the current attack.
+    elapsed: f32,
+    /// Where a charge is headed.
+    heading: Vec2,
+    /// Volleys fired, so each can be turned from the last.
+    volleys: u32,
+}
+
+/// A boss playing its intro, `elapsed` seconds in.
+#[derive(Component, Debug, Default)]
+struct BossIntro {
+    elapsed: f32,
+}
+
+/// A shot fired by a boss.
+#[derive(Component, Debug)]
+struct BossShot {
+    velocity: Vec2,
+    damage: f32,
+    remaining: f32,
+}
+
+#[derive(Component)]
+struct BossBanner;
+
+#[derive(Component)]
+struct BossBar;
+
+#[derive(Component)]
+struct BossBarFill;
+
+/// The arena's size from before it closed in, while a boss is alive.
+#[derive(Resource, Debug, Default)]
+struct ArenaLock {
+    open: Option<Vec2>,
+}
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BossConfig>()
+            .init_resource::<BossConfig>()
+            .init_resource::<ArenaLock>()
+            .add_event::<SpawnBoss>()
+            .add_systems(OnEnter(GameState::Playing), spawn_bar)
+            .add_systems(
+                Update,
+                (
+                    (spawn_boss, play_intro, announce_defeat, clear_shots),
+                    (lock_arena, draw_walls, update_bar),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (run_bosses, move_shots)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn spawn_boss(
+    mut commands: Commands,
+    config: Res<BossConfig>,
+    director: Res<DirectorConfig>,
+    game: Res<GameConfig>,
+    fonts: Res<UiFonts>,
+    palette: Res<Palette>,
+    mut requests: EventReader<SpawnBoss>,
+    mut scenes: EventWriter<PlayCutscene>,
+    players: Query<&Transform, With<Player>>,
+) {
+    for SpawnBoss { wave } in requests.read() {
+        let player = players.get_single().map_or(game.arena.start, |transform| {
+            transform.translation.truncate()
+        });
+        let arena = game.arena.size.min(config.arena);
+        let half = (arena / 2. - Vec2::splat(config.size)).max(Vec2::ZERO);
+        // across the arena's centre from the player
+        let away = (-player).try_normalize().unwrap_or(Vec2::Y);
+        let position = (player + away * config.distance).clamp(-half, half);
+
+        let appearance = (wave / director.boss_every.max(1)).max(1);
+        let health = config.health * (1. + config.health_growth * (appearance - 1) as f32);
+        commands.spawn((
+            Name::new("Boss"),
+            Boss::default(),
+            BossIntro::default(),
+            Enemy,
+            Unpooled,
+            Health::new(health),
+            Interpolated::default(),
+            SpriteBundle {
+                transform: Transform::from_translation(position.extend(0.))
+                    .with_scale(Vec3::splat(INTRO_SCALE)),
+                sprite: Sprite {
+                    color: config.color.with_a(INTRO_ALPHA),
+                    custom_size: Some(Vec2::splat(config.size)),
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+
+        commands
+            .spawn((
+                BossBanner,
+                StateScoped(GameState::Playing),
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Percent(28.),
+                        width: Val::Percent(100.),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(6.),
+                        ..default()
+                    },
+                    z_index: ZIndex::Global(4),
+                    ..default()
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    LocalizedText::new("Boss incoming"),
+                    TextBundle::from_section("Boss incoming", fonts.bold(20., palette.accent)),
+                ));
+                parent.spawn((
+                    LocalizedText::new(config.name.clone()),
+                    TextBundle::from_section(config.name.clone(), fonts.bold(40., config.color)),
+                ));
+            });
+        scenes.send(PlayCutscene(CutsceneId::Boss));
+    }
+}
+
+fn play_intro(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<BossConfig>,
+    mut shake: ResMut<CameraShake>,
+    mut pulses: EventWriter<HapticPulse>,
+    mut bosses: Query<(Entity, &mut BossIntro, &mut Transform, &mut Sprite), Without<Dying>>,
+    banners: Query<Entity, With<BossBanner>>,
+) {
+    for (entity, mut intro, mut transform, mut sprite) in &mut bosses {
+        intro.elapsed += time.delta_seconds();
+        let t = (intro.elapsed / config.intro.max(0.01)).min(1.);
+        transform.scale = Vec3::splat(INTRO_SCALE + (1. - INTRO_SCALE) * t);
+        sprite.color.set_a(INTRO_ALPHA + (1. - INTRO_ALPHA) * t);
+        if t >= 1. {
+            commands
+                .entity(entity)
+                .remove::<BossIntro>()
+                .insert(Collider::new(config.size / 2., CollisionLayer::ENEMY));
+            shake.add_trauma(0.6);
+            pulses.send(HapticPulse::Heavy);
+        }
+    }
+    // the banner goes with the last intro, finished or cut short
+    if bosses
+        .iter()
+        .all(|(_, intro, ..)| intro.elapsed >= config.intro)
+    {
+        for banner in &banners {
+            commands.entity(banner).despawn_recursive();
+        }
+    }
+}
+
+fn run_bosses(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<BossConfig>,
+    game: Res<GameConfig>,
+    portals: Res<PortalConfig>,
+    palette: Res<Palette>,
+    mut shake: ResMut<CameraShake>,
+    mut bosses: Query<
+        (&mut Boss, &mut Transform, &Health, Option<&Statuses>),
+        (Without<BossIntro>, Without<Dying>, Without<Player>),
+    >,
+    players: Query<&Transform, With<Player>>,
+    mut spawns: EventWriter<PortalSpawn>,
+    mut texts: EventWriter<FloatingText>,
+) {
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+    let target = player.translation.truncate();
+    let dt = time.delta_seconds();
+    let area = game.arena.inner_rect(config.size / 2.);
+    for (mut boss, mut transform, health, statuses) in &mut bosses {
+        let mut position = transform.translation.truncate();
+        let slow = statuses.map_or(1., Statuses::speed_multiplier);
+
+        let fraction = health.current / health.max.max(1.);
+        if let Some(phase) = config
+            .phases
+            .iter()
+            .rposition(|phase| fraction <= phase.below)
+        {
+            if phase > boss.phase {
+                *boss = Boss {
+                    phase,
+                    volleys: boss.volleys,
+                    ..default()
+                };
+                transform.scale = Vec3::ONE;
+                texts.send(
+                    FloatingText::new(position, "Enraged!")
+                        .with_color(palette.accent)
+                        .with_size(24.),
+                );
+                shake.add_trauma(0.4);
+            }
+        }
+        let Some(attack) = config
+            .phases
+            .get(boss.phase)
+            .and_then(|phase| phase.pattern.get(boss.step % phase.pattern.len().max(1)))
+            .copied()
+        else {
+            continue;
+        };
+
+        boss.elapsed += dt;
+        let toward = (target - position).normalize_or_zero();
+        let done = match attack {
+            BossAttack::Chase { speed, duration } => {
+                position += toward * speed * slow * dt;
+                boss.elapsed >= duration
+            }
+            BossAttack::Charge {
+                windup,
+                speed,
+                duration,
+            } => {
+                if boss.elapsed < windup {
+                    boss.heading = toward;
+                    let pulse = (boss.elapsed / windup * TAU * 3.).sin() * WINDUP_PULSE;
+                    transform.scale = Vec3::splat(1. + pulse);
+                } else {
+                    transform.scale = Vec3::ONE;
+                    position += boss.heading * speed * slow * dt;
+                }
+                boss.elapsed >= windup + duration
+            }
+            BossAttack::Volley {
+                shots,
+                speed,
+                damage,
+            } => {
+                let shots = shots.max(1);
+                // every other ring sits in the gaps of the last
+                let offset = (boss.volleys % 2) as f32 * 0.5;
+                for i in 0..shots {
+                    let angle = (i as f32 + offset) / shots as f32 * TAU;
+                    commands.spawn((
+                        BossShot {
+                            velocity: Vec2::from_angle(angle) * speed,
+                            damage,
+                            remaining: SHOT_LIFETIME,
+                        },
+                        Interpolated::default(),
+                        SpriteBundle {
+                            transform: Transform::from_translation(position.extend(1.)),
+                            sprite: Sprite {
+                                color: config.color,
+                                custom_size: Some(Vec2::splat(SHOT_SIZE)),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                    ));
+                }
+                boss.volleys += 1;
+                true
+            }
+            BossAttack::Summon { kind, count } => {
+                let position = position + toward * config.size;
+                spawns.send(PortalSpawn {
+                    count,
+                    kind,
+                    ..portals.spawn(position)
+                });
+                true
+            }
+            BossAttack::Rest { duration } => boss.elapsed >= duration,
+        };
+        if done {
+            boss.step += 1;
+            boss.elapsed = 0.;
+        }
+
+        position = position.clamp(area.min, area.max);
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+fn move_shots(
+    time: Res<Time>,
+    game: Res<GameConfig>,
+    mut despawns: ResMut<DespawnQueue>,
+    mut shots: Query<(Entity, &mut BossShot, &mut Transform, &mut Sprite), Without<Player>>,
+    players: Query<(Entity, &Transform, &Collider), With<Player>>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    let dt = time.delta_seconds();
+    let area = game.arena.inner_rect(0.);
+    let player = players.get_single().ok();
+    for (entity, mut shot, mut transform, mut sprite) in &mut shots {
+        shot.remaining -= dt;
+        let position = transform.translation.truncate() + shot.velocity * dt;
+        transform.translation = position.extend(transform.translation.z);
+        sprite.color.set_a((shot.remaining / 0.5).clamp(0., 1.));
+        if shot.remaining <= 0. || !area.contains(position) {
+            despawns.despawn(entity);
+            continue;
+        }
+        if let Some((target, player, collider)) = player {
+            let reach = collider.radius + SHOT_SIZE / 2.;
+            if player.translation.truncate().distance(position) <= reach {
+                damage.send(DamageEvent {
+                    target,
+                    amount: shot.damage,
+                });
+                despawns.despawn(entity);
+            }
+        }
+    }
+}
+
+fn announce_defeat(
+    config: Res<BossConfig>,
+    locale: Res<Locale>,
+    mut killed: EventReader<EnemyKilled>,
+    bosses: Query<(), With<Boss>>,
+    mut toasts: EventWriter<Toast>,
+) {
+    for event in killed.read() {
+        if bosses.contains(event.entity) {
+            toasts.send(
+                Toast::new(format!(
+                    "{} {}",
+                    locale.tr(&config.name),
+                    locale.tr("defeated!")
+                ))
+                .with_kind(ToastKind::Record),
+            );
+        }
+    }
+}
+
+/// Takes the shots still flying out of play when a run starts over.
+fn clear_shots(
+    mut new_runs: EventReader<StartNewRun>,
+    mut despawns: ResMut<DespawnQueue>,
+    shots: Query<Entity, With<BossShot>>,
+) {
+    if new_runs.read().count() == 0 {
+        return;
+    }
+    for shot in &shots {
+        despawns.despawn(shot);
+    }
+}
+
+fn lock_arena(
+    config: Res<BossConfig>,
+    mut game: ResMut<GameConfig>,
+    mut lock: ResMut<ArenaLock>,
+    bosses: Query<(), (With<Boss>, Without<Dying>)>,
+) {
+    match (bosses.is_empty(), lock.open) {
+        (false, None) => {
+            lock.open = Some(game.arena.size);
+            game.arena.size = game.arena.size.min(config.arena);
+        }
+        (true, Some(size)) => {
+            game.arena.size = size;
+            lock.open = None;
+        }
+        _ => {}
+    }
+}
+
+fn draw_walls(
+    time: Res<Time>,
+    game: Res<GameConfig>,
+    config: Res<BossConfig>,
+    lock: Res<ArenaLock>,
+    mut gizmos: Gizmos,
+) {
+    if lock.open.is_none() {
+        return;
+    }
+    let alpha = 0.5 + 0.3 * (time.elapsed_seconds() * 4.).sin();
+    gizmos.rect_2d(Vec2::ZERO, 0., game.arena.size, config.color.with_a(alpha));
+}
+
+fn spawn_bar(mut commands: Commands, config: Res<BossConfig>, fonts: Res<UiFonts>) {
+    commands
+        .spawn((
+            BossBar,
+            StateScoped(GameState::Playing),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(24.),
+                    width: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(4.),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                z_index: ZIndex::Global(4),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                LocalizedText::new(config.name.clone()),
+                TextBundle::from_section(config.name.clone(), fonts.bold(18., Color::WHITE)),
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(BAR_WIDTH),
+                        height: Val::Px(BAR_HEIGHT),
+                        border: UiRect::all(Val::Px(1.)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0., 0., 0., 0.7).into(),
+                    border_color: Color::rgba(1., 1., 1., 0.3).into(),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn((
+                        BossBarFill,
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            background_color: BAR_COLOR.into(),
+                            ..default()
+                        },
+                    ));
+                });
+        });
+}
+
+fn update_bar(
+    bosses: Query<&Health, (With<Boss>, Without<BossIntro>, Without<Dying>)>,
+    mut bars: Query<&mut Visibility, With<BossBar>>,
+    mut fills: Query<&mut Style, With<BossBarFill>>,
+) {
+    let (current, max) = bosses.iter().fold((0., 0.), |(current, max), health| {
+        (current + health.current.max(0.), max + health.max)
+    });
+    let shown = max > 0.;
+    for mut visibility in &mut bars {
+        let wanted = if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+    if shown {
+        for mut style in &mut fills {
+            style.width = Val::Percent(current / max * 100.);
+        }
+    }
+}
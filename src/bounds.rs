@@ -0,0 +1,236 @@
+//! The edges of the play area.
+//!
+//! The player is kept inside [`ArenaConfig`]'s area according to
+//! [`GameplaySettings::edge_mode`]. Every mode works on the area shrunk by
+//! the player's size, so the sprite is always wholly inside: clamping stops
+//! it at the edge, bouncing pushes it back in, and wrapping moves it to the
+//! opposite edge without ever showing it half on each side. A wrap also
+//! moves the camera by the same amount, so the view doesn't sweep across
+//! the whole area to catch up.
+//!
+//! A bounce always pushes straight inwards on each axis that touched an
+//! edge, rather than mirroring the player's movement, so in a corner both
+//! pushes point out of it and the player can't get stuck bouncing between
+//! the two walls.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::{Detached, MainCamera},
+    config::GameConfig,
+    settings::GameplaySettings,
+    tween::approach,
+    Player,
+};
+
+/// Speed the player is pushed off an edge with, in [`EdgeMode::Bounce`].
+const BOUNCE_SPEED: f32 = 320.;
+/// Seconds for a bounce to lose half its speed.
+const BOUNCE_HALF_LIFE: f32 = 0.08;
+const BORDER_WIDTH: f32 = 2.;
+const BORDER_COLOR: Color = Color::rgba(1., 1., 1., 0.25);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum EdgeMode {
+    /// Stop at the edge.
+    #[default]
+    Clamp,
+    /// Get pushed back from the edge.
+    Bounce,
+    /// Come out at the opposite edge.
+    Wrap,
+}
+
+impl EdgeMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            EdgeMode::Clamp => "Stop",
+            EdgeMode::Bounce => "Bounce",
+            EdgeMode::Wrap => "Wrap around",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            EdgeMode::Clamp => EdgeMode::Bounce,
+            EdgeMode::Bounce => EdgeMode::Wrap,
+            EdgeMode::Wrap => EdgeMode::Clamp,
+        }
+    }
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub struct ArenaConfig {
+    /// Size of the play area, centred on the origin.
+    pub size: Vec2,
+    /// Where the player starts a run.
+    pub start: Vec2,
+}
+
+impl Default for ArenaConfig {
+    fn default() -> Self {
+        Self {
+            size: Vec2::new(1600., 1200.),
+            start: Vec2::ZERO,
+        }
+    }
+}
+
+impl ArenaConfig {
+    /// Where the centre of something `radius` in size may go.
+    pub fn inner_rect(&self, radius: f32) -> Rect {
+        let half = (self.size / 2. - Vec2::splat(radius)).max(Vec2::ZERO);
+        Rect::from_center_half_size(Vec2::ZERO, half)
+    }
+}
+
+/// `rect` with every edge moved out by `by`, or in when it is negative,
+/// stopping at zero size around its center.
+pub fn grow(rect: Rect, by: f32) -> Rect {
+    Rect::from_center_half_size(
+        rect.center(),
+        (rect.half_size() + Vec2::splat(by)).max(Vec2::ZERO),
+    )
+}
+
+/// `position` brought back inside `area` under `mode`, and for each axis
+/// the direction back inside if it touched an edge there, or zero.
+pub fn contain(mode: EdgeMode, area: Rect, position: Vec2) -> (Vec2, Vec2) {
+    match mode {
+        EdgeMode::Clamp | EdgeMode::Bounce => {
+            let contained = position.clamp(area.min, area.max);
+            let inward = Vec2::select(
+                position.cmple(area.min),
+                Vec2::ONE,
+                Vec2::select(position.cmpge(area.max), Vec2::NEG_ONE, Vec2::ZERO),
+            );
+            (contained, inward)
+        }
+        EdgeMode::Wrap => {
+            let size = area.size();
+            if size.min_element() <= 0. {
+                return (area.center(), Vec2::ZERO);
+            }
+            (
+                area.min + (position - area.min).rem_euclid(size),
+                Vec2::ZERO,
+            )
+        }
+    }
+}
+
+/// Speed left over from bouncing off an edge.
+#[derive(Component, Debug, Default)]
+pub struct EdgeBounce {
+    velocity: Vec2,
+}
+
+#[derive(Component)]
+struct ArenaBorder;
+
+pub struct BoundsPlugin;
+
+impl Plugin for BoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, draw_border.run_if(resource_changed::<GameConfig>));
+    }
+}
+
+/// Keeps the player inside the arena. Runs right after the player moves.
+pub fn keep_in_bounds(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    settings: Res<GameplaySettings>,
+    time: Res<Time>,
+    mut players: Query<(Entity, &mut Transform, Option<&mut EdgeBounce>), With<Player>>,
+    mut cameras: Query<&mut Transform, (With<MainCamera>, Without<Detached>, Without<Player>)>,
+) {
+    let Ok((entity, mut transform, bounce)) = players.get_single_mut() else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    let mut position = transform.translation.truncate();
+
+    let mut velocity = bounce.as_ref().map_or(Vec2::ZERO, |bounce| bounce.velocity);
+    if settings.edge_mode != EdgeMode::Bounce {
+        velocity = Vec2::ZERO;
+    }
+    position += velocity * dt;
+
+    let area = config
+        .arena
+        .inner_rect(config.player.size.max_element() / 2.);
+    let (contained, inward) = contain(settings.edge_mode, area, position);
+
+    if settings.edge_mode == EdgeMode::Wrap && contained != position {
+        for mut camera in &mut cameras {
+            camera.translation += (contained - position).extend(0.);
+        }
+    }
+
+    // replace any speed still heading out with a push back in
+    let pushed = Vec2::select(
+        inward.cmpne(Vec2::ZERO) & (velocity * inward).cmple(Vec2::ZERO),
+        inward * BOUNCE_SPEED,
+        velocity,
+    );
+    velocity = approach(pushed, Vec2::ZERO, BOUNCE_HALF_LIFE, dt);
+    if velocity.length_squared() < 1. {
+        velocity = Vec2::ZERO;
+    }
+    match bounce {
+        Some(mut bounce) => bounce.velocity = velocity,
+        None if velocity != Vec2::ZERO => {
+            commands.entity(entity).insert(EdgeBounce { velocity });
+        }
+        None => {}
+    }
+
+    if contained != transform.translation.truncate() {
+        transform.translation = contained.extend(transform.translation.z);
+    }
+}
+
+fn draw_border(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    borders: Query<Entity, With<ArenaBorder>>,
+) {
+    for entity in &borders {
+        commands.entity(entity).despawn();
+    }
+    let half = config.arena.size / 2.;
+    let sides = [
+        (
+            Vec2::new(0., half.y),
+            Vec2::new(config.arena.size.x, BORDER_WIDTH),
+        ),
+        (
+            Vec2::new(0., -half.y),
+            Vec2::new(config.arena.size.x, BORDER_WIDTH),
+        ),
+        (
+            Vec2::new(half.x, 0.),
+            Vec2::new(BORDER_WIDTH, config.arena.size.y),
+        ),
+        (
+            Vec2::new(-half.x, 0.),
+            Vec2::new(BORDER_WIDTH, config.arena.size.y),
+        ),
+    ];
+    for (center, size) in sides {
+        commands.spawn((
+            ArenaBorder,
+            SpriteBundle {
+                transform: Transform::from_translation(center.extend(-0.5)),
+                sprite: Sprite {
+                    color: BORDER_COLOR,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+    }
+}
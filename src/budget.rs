@@ -0,0 +1,246 @@
+//! Hard caps on how many enemies, projectiles and effects exist at once.
+//!
+//! Every spawner asks [`Budget::admit`] before creating an entity of a
+//! [`BudgetCategory`] and hands the new entity to [`Budget::track`]. Once a
+//! category is at its [`EntityBudget`] limit, its [`SheddingPolicy`] decides:
+//! the new entity is skipped, or the oldest one of that category is removed
+//! to make room. This keeps a runaway wave or a held trigger from piling up
+//! thousands of entities on a phone.
+
+use std::collections::VecDeque;
+
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashSet};
+
+use crate::{
+    despawn::{DespawnQueue, ReturnToPool},
+    enemy,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum BudgetCategory {
+    Enemy,
+    Projectile,
+    Vfx,
+}
+
+impl BudgetCategory {
+    pub const ALL: [BudgetCategory; 3] = [
+        BudgetCategory::Enemy,
+        BudgetCategory::Projectile,
+        BudgetCategory::Vfx,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BudgetCategory::Enemy => "enemies",
+            BudgetCategory::Projectile => "projectiles",
+            BudgetCategory::Vfx => "effects",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// What happens to a spawn once its category is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum SheddingPolicy {
+    /// The new entity isn't created.
+    Skip,
+    /// The oldest entity of the category is removed to make room.
+    ReplaceOldest,
+}
+
+#[derive(Debug, Clone, Copy, Reflect)]
+pub struct CategoryBudget {
+    pub max: usize,
+    pub policy: SheddingPolicy,
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct EntityBudget {
+    pub enemies: CategoryBudget,
+    pub projectiles: CategoryBudget,
+    pub vfx: CategoryBudget,
+}
+
+impl Default for EntityBudget {
+    fn default() -> Self {
+        Self {
+            // culling a live enemy would look like a free kill
+            enemies: CategoryBudget {
+                max: 150,
+                policy: SheddingPolicy::Skip,
+            },
+            projectiles: CategoryBudget {
+                max: 400,
+                policy: SheddingPolicy::ReplaceOldest,
+            },
+            vfx: CategoryBudget {
+                max: 300,
+                policy: SheddingPolicy::ReplaceOldest,
+            },
+        }
+    }
+}
+
+impl EntityBudget {
+    pub fn get(&self, category: BudgetCategory) -> CategoryBudget {
+        match category {
+            BudgetCategory::Enemy => self.enemies,
+            BudgetCategory::Projectile => self.projectiles,
+            BudgetCategory::Vfx => self.vfx,
+        }
+    }
+}
+
+/// Counts an entity against its category's budget until it is despawned or
+/// the component is removed.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Budgeted;
+
+/// Live budgeted entities per category, oldest first.
+#[derive(Resource, Debug, Default)]
+pub struct BudgetLedger {
+    live: [VecDeque<Entity>; 3],
+}
+
+impl BudgetLedger {
+    pub fn count(&self, category: BudgetCategory) -> usize {
+        self.live[category.index()].len()
+    }
+}
+
+/// What a spawner needs to stay within the [`EntityBudget`].
+#[derive(SystemParam)]
+pub struct Budget<'w> {
+    limits: Res<'w, EntityBudget>,
+    ledger: ResMut<'w, BudgetLedger>,
+    despawns: ResMut<'w, DespawnQueue>,
+}
+
+impl Budget<'_> {
+    /// Whether an entity of `category` may be spawned, making room first if
+    /// the category is full and replaces its oldest.
+    pub fn admit(&mut self, commands: &mut Commands, category: BudgetCategory) -> bool {
+        let limit = self.limits.get(category);
+        let live = &mut self.ledger.live[category.index()];
+        while live.len() >= limit.max {
+            if limit.policy == SheddingPolicy::Skip {
+                return false;
+            }
+            let Some(oldest) = live.pop_front() else {
+                // a limit of zero
+                return false;
+            };
+            match category {
+                BudgetCategory::Enemy => enemy::retire(commands, oldest),
+                BudgetCategory::Projectile | BudgetCategory::Vfx => {
+                    self.despawns.despawn(oldest);
+                }
+            }
+        }
+        true
+    }
+
+    /// Counts the freshly spawned `entity` against `category`.
+    pub fn track(
+        &mut self,
+        entity: &mut bevy::ecs::system::EntityCommands,
+        category: BudgetCategory,
+    ) {
+        entity.insert(Budgeted);
+        self.ledger.live[category.index()].push_back(entity.id());
+    }
+
+    /// Stops counting `entity`, which its owner is despawning this frame, so
+    /// that it isn't also picked to make room.
+    pub fn release(&mut self, entity: Entity) {
+        for live in &mut self.ledger.live {
+            live.retain(|other| *other != entity);
+        }
+    }
+
+    /// Stops counting the pooled `entity` and hands it back to its pool. It
+    /// may be acquired again before [`Budgeted`] is seen to come off, so it
+    /// has to leave the ledger now.
+    pub fn return_to_pool(&mut self, entity: Entity, put_back: ReturnToPool) {
+        self.release(entity);
+        self.despawns.return_to_pool(entity, put_back);
+    }
+}
+
+pub struct BudgetPlugin;
+
+impl Plugin for BudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<EntityBudget>()
+            .init_resource::<EntityBudget>()
+            .init_resource::<BudgetLedger>()
+            .add_systems(First, forget_removed);
+    }
+}
+
+fn forget_removed(
+    mut removed: RemovedComponents<Budgeted>,
+    mut ledger: ResMut<BudgetLedger>,
+    budgeted: Query<(), With<Budgeted>>,
+) {
+    // a pooled entity may already be back in use, and tracked again
+    let gone: HashSet<Entity> = removed
+        .read()
+        .filter(|entity| !budgeted.contains(*entity))
+        .collect();
+    if gone.is_empty() {
+        return;
+    }
+    for live in &mut ledger.live {
+        live.retain(|entity| !gone.contains(entity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::despawn::DespawnPlugin;
+
+    fn put_back(world: &mut World, entity: Entity) {
+        world.entity_mut(entity).remove::<Budgeted>();
+    }
+
+    fn count(app: &App) -> usize {
+        app.world
+            .resource::<BudgetLedger>()
+            .count(BudgetCategory::Projectile)
+    }
+
+    #[test]
+    fn a_pooled_entity_used_again_is_counted_once() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, BudgetPlugin, DespawnPlugin))
+            .init_resource::<EntityBudget>();
+        let shot = app.world.spawn_empty().id();
+        app.world
+            .run_system_once(move |mut commands: Commands, mut budget: Budget| {
+                budget.track(&mut commands.entity(shot), BudgetCategory::Projectile);
+            });
+        assert_eq!(count(&app), 1);
+
+        // returned to its pool and handed to the next spawn before the
+        // removal of `Budgeted` is read
+        app.world.run_system_once(move |mut budget: Budget| {
+            budget.return_to_pool(shot, put_back);
+        });
+        app.update();
+        app.world
+            .run_system_once(move |mut commands: Commands, mut budget: Budget| {
+                budget.track(&mut commands.entity(shot), BudgetCategory::Projectile);
+            });
+        app.update();
+        assert_eq!(count(&app), 1);
+    }
+}
@@ -0,0 +1,243 @@
+//! Gamepad stick dead zone and recentering.
+//!
+//! By default small stick readings are ignored, which hides the drift of
+//! worn sticks at the cost of precision. With [`ControlSettings`]'
+//! `no_dead_zone` on, both bevy's and leafwing's dead zones are turned off
+//! and every reading counts. Recentering then makes up for drift instead:
+//! sending [`CalibrateSticks`] averages the resting gamepad sticks for
+//! [`CALIBRATION_SECONDS`], stores the result as a [`StickCalibration`],
+//! and from then on that offset is taken off the move and look axes while
+//! the [`ActiveInputDevice`] is a gamepad. Movement speed follows how far
+//! the stick is pushed, so what little drift is left creeps rather than
+//! runs.
+
+use bevy::{
+    input::gamepad::{AxisSettings, GamepadSettings},
+    prelude::*,
+};
+use leafwing_input_manager::{
+    axislike::{DeadZoneShape, DualAxisData},
+    plugin::InputManagerSystem,
+    prelude::*,
+    user_input::{InputKind, UserInput},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input_device::{ActiveInputDevice, InputDevice},
+    settings::ControlSettings,
+    smoothing,
+    toast::Toast,
+    Action, Player,
+};
+
+/// Radius of leafwing's stick dead zone while it is on.
+pub const DEAD_ZONE: f32 = 0.1;
+
+/// Seconds the sticks are sampled for when recentering.
+pub const CALIBRATION_SECONDS: f32 = 1.;
+
+/// Largest offset a calibration can store. A stick resting further out than
+/// this is being held, not drifting.
+const MAX_OFFSET: f32 = 0.3;
+
+/// Resting positions of the gamepad sticks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StickCalibration {
+    pub move_offset: Vec2,
+    pub look_offset: Vec2,
+}
+
+impl StickCalibration {
+    pub fn offset(&self, action: Action) -> Vec2 {
+        match action {
+            Action::Move => self.move_offset,
+            Action::Look => self.look_offset,
+            _ => Vec2::ZERO,
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// `value` read relative to where the stick rests.
+    pub fn apply(&self, action: Action, value: Vec2) -> Vec2 {
+        (value - self.offset(action)).clamp_length_max(1.)
+    }
+
+    pub fn validate(&mut self) {
+        self.move_offset = self.move_offset.clamp_length_max(MAX_OFFSET);
+        self.look_offset = self.look_offset.clamp_length_max(MAX_OFFSET);
+    }
+}
+
+/// Starts recentering the gamepad sticks.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CalibrateSticks;
+
+/// A recentering in progress.
+#[derive(Resource, Debug, Default)]
+struct Sampling {
+    remaining: f32,
+    sum: [Vec2; 2],
+    samples: u32,
+}
+
+const CALIBRATED: [Action; 2] = [Action::Move, Action::Look];
+
+pub struct CalibrationPlugin;
+
+impl Plugin for CalibrationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<StickCalibration>()
+            .add_event::<CalibrateSticks>()
+            .add_systems(
+                PreUpdate,
+                (
+                    apply_dead_zone.before(InputManagerSystem::Update),
+                    recenter_sticks
+                        .after(InputManagerSystem::Update)
+                        .before(smoothing::smooth_sticks),
+                ),
+            )
+            .add_systems(Update, (start_calibration, sample_sticks).chain());
+    }
+}
+
+/// The stick input for `action` with the dead zone on or off.
+fn stick(action: Action, dead_zone: bool) -> DualAxis {
+    let stick = match action {
+        Action::Look => DualAxis::right_stick(),
+        _ => DualAxis::left_stick(),
+    };
+    // leafwing divides by the radius
+    let radius = if dead_zone { DEAD_ZONE } else { f32::EPSILON };
+    stick.with_deadzone(DeadZoneShape::Ellipse {
+        radius_x: radius,
+        radius_y: radius,
+    })
+}
+
+fn apply_dead_zone(
+    settings: Res<ControlSettings>,
+    mut gamepad_settings: ResMut<GamepadSettings>,
+    mut players: Query<&mut InputMap<Action>, With<Player>>,
+) {
+    let changed = settings.is_changed();
+    let dead_zone = !settings.no_dead_zone;
+    if changed {
+        let mut axis = AxisSettings::default();
+        if !dead_zone {
+            axis.set_deadzone_lowerbound(0.);
+            axis.set_deadzone_upperbound(0.);
+        }
+        gamepad_settings.default_axis_settings = axis;
+    }
+
+    for mut map in &mut players {
+        // a new player gets the setting too, not only a new setting
+        if !changed && !map.is_added() {
+            continue;
+        }
+        for action in CALIBRATED {
+            let others: Vec<UserInput> = map
+                .get(&action)
+                .into_iter()
+                .flatten()
+                .filter(|input| !matches!(input, UserInput::Single(InputKind::DualAxis(_))))
+                .cloned()
+                .collect();
+            map.clear_action(&action);
+            map.insert(action, stick(action, dead_zone));
+            for input in others {
+                map.insert(action, input);
+            }
+        }
+    }
+}
+
+fn recenter_sticks(
+    settings: Res<ControlSettings>,
+    device: Res<ActiveInputDevice>,
+    mut players: Query<&mut ActionState<Action>, With<Player>>,
+) {
+    // with a dead zone, a resting stick already reads zero
+    if !settings.no_dead_zone
+        || !settings.calibration.is_set()
+        || device.device != InputDevice::Gamepad
+    {
+        return;
+    }
+    let Ok(mut action_state) = players.get_single_mut() else {
+        return;
+    };
+    for action in CALIBRATED {
+        let Some(data) = action_state.action_data_mut(&action) else {
+            continue;
+        };
+        let raw = data.axis_pair.map_or(Vec2::ZERO, |axis| axis.xy());
+        let value = settings.calibration.apply(action, raw);
+        data.axis_pair = Some(DualAxisData::from_xy(value));
+        data.value = value.length();
+    }
+}
+
+fn start_calibration(
+    mut commands: Commands,
+    mut requests: EventReader<CalibrateSticks>,
+    mut toasts: EventWriter<Toast>,
+) {
+    if requests.read().last().is_none() {
+        return;
+    }
+    commands.insert_resource(Sampling {
+        remaining: CALIBRATION_SECONDS,
+        ..default()
+    });
+    toasts.send(Toast::new("Let go of the sticks..."));
+}
+
+fn sample_sticks(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    sampling: Option<ResMut<Sampling>>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut settings: ResMut<ControlSettings>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let Some(mut sampling) = sampling else {
+        return;
+    };
+    let Some(gamepad) = gamepads.iter().next() else {
+        commands.remove_resource::<Sampling>();
+        toasts.send(Toast::new("No gamepad to recenter"));
+        return;
+    };
+
+    let read = |x, y| {
+        Vec2::new(
+            axes.get(GamepadAxis::new(gamepad, x)).unwrap_or(0.),
+            axes.get(GamepadAxis::new(gamepad, y)).unwrap_or(0.),
+        )
+    };
+    sampling.sum[0] += read(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY);
+    sampling.sum[1] += read(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY);
+    sampling.samples += 1;
+    sampling.remaining -= time.delta_seconds();
+    if sampling.remaining > 0. {
+        return;
+    }
+
+    let samples = sampling.samples as f32;
+    let mut calibration = StickCalibration {
+        move_offset: sampling.sum[0] / samples,
+        look_offset: sampling.sum[1] / samples,
+    };
+    calibration.validate();
+    settings.calibration = calibration;
+    commands.remove_resource::<Sampling>();
+    toasts.send(Toast::new("Sticks recentered"));
+}
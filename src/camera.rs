@@ -0,0 +1,532 @@
+//! The gameplay camera.
+//!
+//! The camera follows the player, smoothly and with a bit of slack: it
+//! only moves once the player leaves the [`CameraFollow::deadzone`] around
+//! the middle of the screen, and it leads a little in the direction they
+//! move. It stops at the edges of the arena rather than showing what lies
+//! beyond, unless the arena is smaller than the view, which then stays
+//! centred on it. In [`CameraMode::Rotate`] it also turns
+//! so the player always faces up on screen; movement and aim input are
+//! then read relative to the screen through [`CameraView::to_world`], so
+//! pushing up still means "forward". UI nodes are unaffected and stay
+//! screen-aligned. Anything drawn in screen terms on top of the world (rain,
+//! parallax) has to rotate with [`CameraView::rotation`].
+//!
+//! The zoom is the sum of the offsets in [`CameraZoom`], each written by
+//! its own source: the player through [`Action::Zoom`] (the mouse wheel, or
+//! a pinch), sprinting, and with [`DisplaySettings::auto_zoom`] on, the
+//! [`DangerLevel`] around the player, so crowded fights zoom out and a
+//! cleared screen zooms back in. It is applied before the camera's
+//! projection is updated, so [`visible_rect`] always reads the zoom the
+//! frame was drawn with; spawners keeping out of sight get the zoomed-out
+//! area for free.
+//!
+//! Hits shake the view through [`CameraShake`]: anything can
+//! [`add_trauma`](CameraShake::add_trauma), which wears off over real time
+//! and shakes by its square, so small knocks barely move the view and big
+//! ones stack up to a jolt. The player getting hurt shakes it and briefly
+//! stops time; exploding shots shake it too. The shake sits on top of the
+//! followed position rather than feeding into it, and is off with
+//! [`AccessibilitySettings::reduce_motion`].
+
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use bevy::{prelude::*, render::camera::CameraUpdateSystem, transform::TransformSystem};
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::GameConfig,
+    danger::DangerLevel,
+    events::PlayerHurt,
+    game_time::HitStop,
+    health::Health,
+    interpolation,
+    settings::{AccessibilitySettings, DisplaySettings},
+    state::GameState,
+    tween::approach,
+    Action, Player,
+};
+
+/// The camera gameplay is viewed through.
+#[derive(Component, Debug, Default)]
+pub struct MainCamera;
+
+/// Set on the [`MainCamera`] while something else drives it, such as the
+/// debug free camera or photo mode. It then neither follows the player nor
+/// zooms.
+#[derive(Component, Debug, Default)]
+pub struct Detached;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum CameraMode {
+    /// North is always up.
+    #[default]
+    Fixed,
+    /// The view turns with the player so they face up.
+    Rotate,
+}
+
+impl CameraMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraMode::Fixed => "Fixed",
+            CameraMode::Rotate => "Turn with player",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            CameraMode::Fixed => CameraMode::Rotate,
+            CameraMode::Rotate => CameraMode::Fixed,
+        }
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct CameraFollow {
+    /// Seconds for the camera to close half the distance to the player.
+    pub half_life: f32,
+    /// Seconds for a rotating camera to turn halfway to the player's facing.
+    pub turn_half_life: f32,
+    /// Half size, on screen, of the box the player moves in freely before
+    /// the camera follows.
+    pub deadzone: Vec2,
+    /// Seconds of the player's movement the camera looks ahead by.
+    pub look_ahead: f32,
+    /// Furthest the camera looks ahead.
+    pub max_look_ahead: f32,
+    /// Seconds for the look-ahead to settle halfway to a new direction.
+    pub look_ahead_half_life: f32,
+    /// Whether the camera stops at the edges of the arena.
+    pub clamp_to_arena: bool,
+}
+
+impl Default for CameraFollow {
+    fn default() -> Self {
+        Self {
+            half_life: 0.12,
+            turn_half_life: 0.2,
+            deadzone: Vec2::new(40., 30.),
+            look_ahead: 0.3,
+            max_look_ahead: 80.,
+            look_ahead_half_life: 0.3,
+            clamp_to_arena: true,
+        }
+    }
+}
+
+/// Player speeds above this are jumps, such as wrapping around the arena,
+/// and don't make the camera look ahead.
+const TELEPORT_SPEED: f32 = 2000.;
+
+/// Where a camera at `camera` heads for `focus` to stay within `deadzone`
+/// of the middle of the screen.
+pub fn deadzone_target(view: &CameraView, camera: Vec2, focus: Vec2, deadzone: Vec2) -> Vec2 {
+    let offset = view.to_screen(focus - camera);
+    let outside = offset - offset.clamp(-deadzone, deadzone);
+    camera + view.to_world(outside)
+}
+
+/// `center` moved just enough for a view `half_view` in size around it to
+/// stay inside `area`, or the middle of `area` on axes where it can't.
+pub fn clamp_view(center: Vec2, half_view: Vec2, area: Rect) -> Vec2 {
+    let room = (area.half_size() - half_view).max(Vec2::ZERO);
+    center.clamp(area.center() - room, area.center() + room)
+}
+
+/// Moves a [`Detached`] camera by `pan`, in screen pixels at a zoom of 1
+/// with y up, and zooms it by `zoom`, a fraction of its current scale,
+/// within `zoom_range`.
+pub fn pan_and_zoom(
+    transform: &mut Transform,
+    projection: &mut OrthographicProjection,
+    pan: Vec2,
+    zoom: f32,
+    zoom_range: (f32, f32),
+) {
+    let world = (transform.rotation * (pan * projection.scale).extend(0.)).truncate();
+    transform.translation += world.extend(0.);
+    if zoom != 0. {
+        projection.scale = (projection.scale * (1. + zoom)).clamp(zoom_range.0, zoom_range.1);
+    }
+}
+
+/// Zoom-out offsets on top of a projection scale of 1; bigger shows more.
+#[derive(Resource, Reflect, Debug, Clone, Copy, Default)]
+#[reflect(Resource)]
+pub struct CameraZoom {
+    /// Set by hand, e.g. from the inspector.
+    pub manual: f32,
+    /// Set by the player with [`Action::Zoom`].
+    pub input: f32,
+    pub sprint: f32,
+    /// From the danger level; see [`AutoZoomConfig`].
+    pub danger: f32,
+}
+
+impl CameraZoom {
+    /// Projection scale with every offset applied.
+    pub fn scale(&self) -> f32 {
+        (1. + self.manual + self.input + self.sprint + self.danger).max(MIN_SCALE)
+    }
+}
+
+const MIN_SCALE: f32 = 0.1;
+/// Zoom offset for one step of [`Action::Zoom`], like one wheel notch.
+const INPUT_ZOOM_STEP: f32 = 0.1;
+/// Furthest in and out the player can zoom.
+const INPUT_ZOOM_RANGE: (f32, f32) = (-0.5, 1.);
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct AutoZoomConfig {
+    /// Zoom-out offset at full danger.
+    pub max_offset: f32,
+    /// Danger below which the camera doesn't zoom out at all.
+    pub threshold: f32,
+    /// Seconds to zoom halfway out to a higher target.
+    pub out_half_life: f32,
+    /// Seconds to zoom halfway back in.
+    pub in_half_life: f32,
+}
+
+impl Default for AutoZoomConfig {
+    fn default() -> Self {
+        Self {
+            max_offset: 0.3,
+            threshold: 0.2,
+            out_half_life: 1.,
+            in_half_life: 2.,
+        }
+    }
+}
+
+impl AutoZoomConfig {
+    /// Offset the zoom heads toward at `danger`.
+    pub fn target(&self, danger: f32) -> f32 {
+        let t = ((danger - self.threshold) / (1. - self.threshold).max(f32::EPSILON)).clamp(0., 1.);
+        self.max_offset * t
+    }
+}
+
+/// Shake on the view, from `0` to `1`.
+#[derive(Resource, Reflect, Debug, Clone, Copy, Default)]
+#[reflect(Resource)]
+pub struct CameraShake {
+    pub trauma: f32,
+    /// Offset the last frame's shake moved the camera by, taken back out
+    /// before following.
+    #[reflect(ignore)]
+    applied: Vec2,
+}
+
+impl CameraShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0., 1.);
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct ShakeConfig {
+    /// Offset at full trauma, in world units.
+    pub max_offset: f32,
+    /// Turn either way at full trauma, in radians.
+    pub max_angle: f32,
+    /// Trauma lost per second.
+    pub decay: f32,
+    /// How fast the shake wobbles.
+    pub frequency: f32,
+    /// Trauma from the player being hurt, at a hit of their full health.
+    pub hurt: f32,
+    /// Least trauma a hurt adds, however small.
+    pub min_hurt: f32,
+    /// Trauma from a cluster shot bursting.
+    pub explosion: f32,
+    /// Seconds of hit-stop when the player is hurt.
+    pub hurt_stop: f32,
+}
+
+impl Default for ShakeConfig {
+    fn default() -> Self {
+        Self {
+            max_offset: 14.,
+            max_angle: 0.04,
+            decay: 1.6,
+            frequency: 24.,
+            hurt: 1.,
+            min_hurt: 0.25,
+            explosion: 0.3,
+            hurt_stop: 0.06,
+        }
+    }
+}
+
+impl ShakeConfig {
+    /// Offset and turn of the view with `trauma`, `elapsed` real seconds in.
+    pub fn shake(&self, trauma: f32, elapsed: f32) -> (Vec2, f32) {
+        let amount = trauma.clamp(0., 1.).powi(2);
+        let t = elapsed * self.frequency;
+        // a few unrelated sines read as noise without needing a generator
+        let wobble = |phase: f32| (t + phase).sin() * (t * 0.63 + phase * 1.7).sin();
+        let offset = Vec2::new(wobble(0.), wobble(2.1)) * self.max_offset * amount;
+        (offset, wobble(4.3) * self.max_angle * amount)
+    }
+}
+
+/// How the camera is currently turned.
+#[derive(Resource, Reflect, Debug, Clone, Copy, Default)]
+#[reflect(Resource)]
+pub struct CameraView {
+    /// Camera rotation in radians, counter-clockwise; 0 unless rotating.
+    pub rotation: f32,
+}
+
+impl CameraView {
+    /// `screen`, a direction as seen on screen (up is +y), in world space.
+    pub fn to_world(self, screen: Vec2) -> Vec2 {
+        Vec2::from_angle(self.rotation).rotate(screen)
+    }
+
+    /// `world`, a direction in world space, as seen on screen.
+    pub fn to_screen(self, world: Vec2) -> Vec2 {
+        Vec2::from_angle(-self.rotation).rotate(world)
+    }
+}
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<CameraFollow>()
+            .register_type::<CameraView>()
+            .register_type::<CameraZoom>()
+            .register_type::<AutoZoomConfig>()
+            .register_type::<CameraShake>()
+            .register_type::<ShakeConfig>()
+            .init_resource::<CameraShake>()
+            .init_resource::<ShakeConfig>()
+            .init_resource::<CameraFollow>()
+            .init_resource::<CameraView>()
+            .init_resource::<CameraZoom>()
+            .init_resource::<AutoZoomConfig>()
+            .add_systems(
+                Update,
+                (
+                    zoom_with_input.run_if(in_state(GameState::Playing)),
+                    zoom_with_danger,
+                    shake_on_hurt,
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    (remove_shake, follow_player, apply_shake)
+                        .chain()
+                        .after(interpolation::interpolate)
+                        .before(TransformSystem::TransformPropagate),
+                    apply_zoom.before(CameraUpdateSystem),
+                ),
+            );
+    }
+}
+
+/// `angle` wrapped into `-PI..=PI`.
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+fn follow_player(
+    time: Res<Time>,
+    follow: Res<CameraFollow>,
+    display: Res<DisplaySettings>,
+    config: Res<GameConfig>,
+    mut view: ResMut<CameraView>,
+    mut last_position: Local<Option<Vec2>>,
+    mut lead: Local<Vec2>,
+    players: Query<&Transform, With<Player>>,
+    mut cameras: Query<
+        (&mut Transform, &Camera, &GlobalTransform),
+        (With<MainCamera>, Without<Detached>, Without<Player>),
+    >,
+) {
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+    let dt = time.delta_seconds();
+
+    let target_rotation = match display.camera_mode {
+        CameraMode::Fixed => 0.,
+        // the player faces +x at no rotation, so turn a quarter less
+        CameraMode::Rotate => {
+            let facing = (player.rotation * Vec3::X).truncate();
+            Vec2::X.angle_between(facing) - FRAC_PI_2
+        }
+    };
+    let turn = approach(
+        0.,
+        wrap_angle(target_rotation - view.rotation),
+        follow.turn_half_life,
+        dt,
+    );
+    if turn != 0. {
+        view.rotation = wrap_angle(view.rotation + turn);
+    }
+
+    let player_position = player.translation.truncate();
+    let velocity = match last_position.replace(player_position) {
+        Some(last) if dt > 0. => (player_position - last) / dt,
+        _ => Vec2::ZERO,
+    };
+    let velocity = if velocity.length() > TELEPORT_SPEED {
+        Vec2::ZERO
+    } else {
+        velocity
+    };
+    *lead = approach(
+        *lead,
+        (velocity * follow.look_ahead).clamp_length_max(follow.max_look_ahead),
+        follow.look_ahead_half_life,
+        dt,
+    );
+    let focus = player_position + *lead;
+
+    for (mut transform, camera, camera_transform) in &mut cameras {
+        let current = transform.translation.truncate();
+        let target = deadzone_target(&view, current, focus, follow.deadzone);
+        let mut position = approach(current, target, follow.half_life, dt);
+        if follow.clamp_to_arena {
+            if let Some(visible) = visible_rect(camera, camera_transform) {
+                position = clamp_view(position, visible.half_size(), config.arena.inner_rect(0.));
+            }
+        }
+        transform.translation = position.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(view.rotation);
+    }
+}
+
+fn shake_on_hurt(
+    config: Res<ShakeConfig>,
+    mut shake: ResMut<CameraShake>,
+    mut hurt: EventReader<PlayerHurt>,
+    players: Query<&Health, With<Player>>,
+    mut stops: EventWriter<HitStop>,
+) {
+    for event in hurt.read() {
+        let max = players.get(event.entity).map_or(100., |health| health.max);
+        let trauma = config.hurt * event.amount / max.max(1.);
+        shake.add_trauma(trauma.max(config.min_hurt));
+        stops.send(HitStop {
+            duration: config.hurt_stop,
+        });
+    }
+}
+
+fn remove_shake(
+    mut shake: ResMut<CameraShake>,
+    mut cameras: Query<&mut Transform, (With<MainCamera>, Without<Detached>)>,
+) {
+    let applied = std::mem::take(&mut shake.applied);
+    if applied == Vec2::ZERO {
+        return;
+    }
+    for mut transform in &mut cameras {
+        transform.translation -= applied.extend(0.);
+    }
+}
+
+fn apply_shake(
+    time: Res<Time<Real>>,
+    config: Res<ShakeConfig>,
+    settings: Res<AccessibilitySettings>,
+    mut shake: ResMut<CameraShake>,
+    mut cameras: Query<&mut Transform, (With<MainCamera>, Without<Detached>)>,
+) {
+    if shake.trauma <= 0. {
+        return;
+    }
+    shake.trauma = (shake.trauma - config.decay * time.delta_seconds()).max(0.);
+    if settings.reduce_motion {
+        return;
+    }
+    let (offset, angle) = config.shake(shake.trauma, time.elapsed_seconds());
+    for mut transform in &mut cameras {
+        transform.translation += offset.extend(0.);
+        transform.rotate_z(angle);
+    }
+    shake.applied = offset;
+}
+
+fn zoom_with_danger(
+    time: Res<Time>,
+    config: Res<AutoZoomConfig>,
+    display: Res<DisplaySettings>,
+    danger: Res<DangerLevel>,
+    mut zoom: ResMut<CameraZoom>,
+) {
+    let target = if display.auto_zoom {
+        config.target(danger.value)
+    } else {
+        0.
+    };
+    let half_life = if target > zoom.danger {
+        config.out_half_life
+    } else {
+        config.in_half_life
+    };
+    let offset = approach(zoom.danger, target, half_life, time.delta_seconds());
+    if offset != zoom.danger {
+        zoom.danger = offset;
+    }
+}
+
+fn zoom_with_input(
+    mut zoom: ResMut<CameraZoom>,
+    players: Query<&ActionState<Action>, With<Player>>,
+) {
+    let Ok(action_state) = players.get_single() else {
+        return;
+    };
+    // wheel deltas come in lines or pixels depending on the platform, so
+    // any wheel movement in a frame is at most one step
+    let steps = action_state.value(&Action::Zoom).clamp(-1., 1.);
+    if steps == 0. {
+        return;
+    }
+    let (min, max) = INPUT_ZOOM_RANGE;
+    zoom.input = (zoom.input - steps * INPUT_ZOOM_STEP).clamp(min, max);
+}
+
+fn apply_zoom(
+    zoom: Res<CameraZoom>,
+    mut cameras: Query<&mut OrthographicProjection, (With<MainCamera>, Without<Detached>)>,
+) {
+    let scale = zoom.scale();
+    for mut projection in &mut cameras {
+        if projection.scale != scale {
+            projection.scale = scale;
+        }
+    }
+}
+
+/// The world-space rectangle `camera` currently shows. With a rotated
+/// camera this is the bounding box of the rotated view.
+pub fn visible_rect(camera: &Camera, transform: &GlobalTransform) -> Option<Rect> {
+    let size = camera.logical_viewport_size()?;
+    let mut rect: Option<Rect> = None;
+    for corner in [
+        Vec2::ZERO,
+        Vec2::new(size.x, 0.),
+        Vec2::new(0., size.y),
+        size,
+    ] {
+        let point = camera.viewport_to_world_2d(transform, corner)?;
+        rect = Some(match rect {
+            Some(rect) => rect.union_point(point),
+            None => Rect::from_corners(point, point),
+        });
+    }
+    rect
+}
@@ -0,0 +1,157 @@
+//! Balancing cheats for a normal run. Debug builds only.
+//!
+//! F7 spawns a ring of [`SWARM_SIZE`] enemies around the player, F8 toggles
+//! infinite ammo, F9 toggles god mode and F10 kills every enemy on the
+//! field. Kills go through the usual damage path, so they score and count
+//! like any other. The toggles are also the console's `god` and `ammo`.
+
+use bevy::prelude::*;
+
+use crate::{
+    console::AddConsoleCommand,
+    death::Invulnerable,
+    enemy::{Enemy, SpawnEnemy},
+    health::{DamageEvent, Health},
+    toast::Toast,
+    weapon::WeaponInventory,
+    Player,
+};
+
+const SWARM_KEY: KeyCode = KeyCode::F7;
+const AMMO_KEY: KeyCode = KeyCode::F8;
+const GOD_KEY: KeyCode = KeyCode::F9;
+const KILL_KEY: KeyCode = KeyCode::F10;
+/// Enemies spawned per press of the swarm key.
+const SWARM_SIZE: usize = 50;
+/// Distance from the player the swarm spawns at.
+const SWARM_RADIUS: f32 = 250.;
+
+#[derive(Resource, Debug, Default)]
+struct Cheats {
+    infinite_ammo: bool,
+    god_mode: bool,
+}
+
+pub struct CheatsPlugin;
+
+impl Plugin for CheatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Cheats>()
+            .add_console_command("god", "god", toggle_god_mode)
+            .add_console_command("ammo", "ammo", toggle_infinite_ammo)
+            .add_systems(
+                Update,
+                (spawn_swarm, toggle_cheats, apply_cheats, kill_all).chain(),
+            );
+    }
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn toggle_god_mode(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let mut cheats = world.resource_mut::<Cheats>();
+    cheats.god_mode = !cheats.god_mode;
+    Ok(format!("god mode {}", on_off(cheats.god_mode)))
+}
+
+fn toggle_infinite_ammo(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let mut cheats = world.resource_mut::<Cheats>();
+    cheats.infinite_ammo = !cheats.infinite_ammo;
+    Ok(format!("infinite ammo {}", on_off(cheats.infinite_ammo)))
+}
+
+fn spawn_swarm(
+    keys: Res<ButtonInput<KeyCode>>,
+    players: Query<&Transform, With<Player>>,
+    mut spawns: EventWriter<SpawnEnemy>,
+) {
+    if !keys.just_pressed(SWARM_KEY) {
+        return;
+    }
+    let center = players
+        .get_single()
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+    for i in 0..SWARM_SIZE {
+        let angle = std::f32::consts::TAU * i as f32 / SWARM_SIZE as f32;
+        spawns.send(SpawnEnemy::at(
+            center + Vec2::from_angle(angle) * SWARM_RADIUS,
+        ));
+    }
+}
+
+fn toggle_cheats(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut cheats: ResMut<Cheats>,
+    mut toasts: EventWriter<Toast>,
+) {
+    if keys.just_pressed(AMMO_KEY) {
+        cheats.infinite_ammo = !cheats.infinite_ammo;
+        toasts.send(Toast::new(format!(
+            "Infinite ammo {}",
+            on_off(cheats.infinite_ammo)
+        )));
+    }
+    if keys.just_pressed(GOD_KEY) {
+        cheats.god_mode = !cheats.god_mode;
+        toasts.send(Toast::new(format!("God mode {}", on_off(cheats.god_mode))));
+    }
+}
+
+fn apply_cheats(
+    mut commands: Commands,
+    cheats: Res<Cheats>,
+    mut had_infinite_ammo: Local<bool>,
+    mut players: Query<(Entity, &mut WeaponInventory, Option<&Invulnerable>), With<Player>>,
+) {
+    let ammo_toggled_off = *had_infinite_ammo && !cheats.infinite_ammo;
+    *had_infinite_ammo = cheats.infinite_ammo;
+
+    for (entity, mut inventory, invulnerable) in &mut players {
+        // kept up every frame so later pickups are unlimited too
+        if cheats.infinite_ammo {
+            if inventory.slots().iter().any(|slot| slot.ammo.is_some()) {
+                for slot in inventory.slots_mut() {
+                    slot.ammo = None;
+                }
+            }
+        } else if ammo_toggled_off {
+            // back to a full load of each weapon's own ammo
+            for slot in inventory.slots_mut() {
+                slot.ammo = slot.weapon.ammo;
+            }
+        }
+
+        let god = invulnerable.is_some_and(|invulnerable| invulnerable.remaining.is_infinite());
+        if cheats.god_mode && !god {
+            commands.entity(entity).insert(Invulnerable {
+                remaining: f32::INFINITY,
+            });
+        } else if !cheats.god_mode && god {
+            commands
+                .entity(entity)
+                .insert(Invulnerable { remaining: 0. });
+        }
+    }
+}
+
+fn kill_all(
+    keys: Res<ButtonInput<KeyCode>>,
+    enemies: Query<(Entity, &Health), With<Enemy>>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    if !keys.just_pressed(KILL_KEY) {
+        return;
+    }
+    for (entity, health) in &enemies {
+        damage.send(DamageEvent {
+            target: entity,
+            amount: health.current,
+        });
+    }
+}
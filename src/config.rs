@@ -0,0 +1,108 @@
+//! Tunable game data: dimensions in [`GameConfig`], colors in [`Palette`].
+//!
+//! Both are plain resources that can be edited in the inspector; changes are
+//! applied to already-spawned entities.
+
+use std::f32::consts::SQRT_2;
+
+use bevy::prelude::*;
+
+use crate::{bounds::ArenaConfig, Player};
+
+#[derive(Resource, Reflect, Debug, Clone, Default)]
+#[reflect(Resource)]
+pub struct GameConfig {
+    pub player: PlayerConfig,
+    pub arena: ArenaConfig,
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub struct PlayerConfig {
+    /// Size of the player's body sprite.
+    pub size: Vec2,
+    /// Side length of the square "nose", drawn rotated 45°.
+    pub nose_size: f32,
+    /// Nose position relative to the body centre, before rotation.
+    pub nose_offset: Vec2,
+}
+
+impl PlayerConfig {
+    /// Distance from the player's center to the tip of its nose, where
+    /// shots come out.
+    pub fn nose_tip(&self) -> f32 {
+        // the nose is a square on its corner, pointing along x
+        self.nose_offset.x + self.nose_size * SQRT_2 / 2.
+    }
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self {
+            size: Vec2::new(15., 25.),
+            nose_size: 25. / SQRT_2,
+            nose_offset: Vec2::new(5., 0.),
+        }
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct Palette {
+    pub player: Color,
+    /// Plain UI text.
+    pub text: Color,
+    /// Achievements and records.
+    pub reward: Color,
+    /// Waves and other run milestones.
+    pub accent: Color,
+    pub pickup: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            player: Color::ORANGE,
+            text: Color::WHITE,
+            reward: Color::GOLD,
+            accent: Color::CYAN,
+            pickup: Color::LIME_GREEN,
+        }
+    }
+}
+
+/// The pointy part of the player sprite.
+#[derive(Component)]
+pub struct PlayerNose;
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<GameConfig>()
+            .register_type::<Palette>()
+            .init_resource::<GameConfig>()
+            .init_resource::<Palette>()
+            .add_systems(
+                Update,
+                apply_player_look
+                    .run_if(resource_changed::<GameConfig>.or_else(resource_changed::<Palette>)),
+            );
+    }
+}
+
+fn apply_player_look(
+    config: Res<GameConfig>,
+    palette: Res<Palette>,
+    mut players: Query<&mut Sprite, (With<Player>, Without<PlayerNose>)>,
+    mut noses: Query<(&mut Sprite, &mut Transform), With<PlayerNose>>,
+) {
+    for mut sprite in &mut players {
+        sprite.color = palette.player;
+        sprite.custom_size = Some(config.player.size);
+    }
+    for (mut sprite, mut transform) in &mut noses {
+        sprite.color = palette.player;
+        sprite.custom_size = Some(Vec2::splat(config.player.nose_size));
+        transform.translation = config.player.nose_offset.extend(transform.translation.z);
+    }
+}
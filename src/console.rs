@@ -0,0 +1,463 @@
+//! In-game command console. Debug builds only.
+//!
+//! The backtick key, or a tap with [`TOGGLE_FINGERS`] fingers at once,
+//! opens a console at the top of the screen. While it is open, typing goes
+//! to the console and none of it reaches the game; enter runs the line, up
+//! and down step through earlier lines, and escape or backtick closes it
+//! again. `help` lists every command.
+//!
+//! Commands are plain functions of the [`World`] and the line's arguments.
+//! Features add their own with [`AddConsoleCommand::add_console_command`].
+
+use std::{collections::BTreeMap, collections::VecDeque, str::FromStr};
+
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState, InputSystem,
+    },
+    prelude::*,
+    reflect::GetPath,
+};
+use leafwing_input_manager::plugin::InputManagerSystem;
+
+use crate::{
+    config::GameConfig,
+    difficulty::{DifficultyOverride, DifficultyScale},
+    enemy::{EnemyKind, SpawnEnemy},
+    fonts::UiFonts,
+    game_time::GameTime,
+    health::Health,
+    progression::Progression,
+    score::Score,
+    weapon::WeaponInventory,
+    Player,
+};
+
+const TOGGLE_KEY: KeyCode = KeyCode::Backquote;
+/// Fingers down together that toggle the console on a touch screen.
+const TOGGLE_FINGERS: usize = 3;
+/// Output lines kept on screen.
+const LOG_LINES: usize = 12;
+
+/// Runs a command with the words after its name, returning what to print.
+pub type ConsoleFn = fn(&mut World, &[&str]) -> Result<String, String>;
+
+#[derive(Clone, Copy)]
+struct ConsoleCommand {
+    usage: &'static str,
+    run: ConsoleFn,
+}
+
+/// Every command the console knows, by name.
+#[derive(Resource, Default)]
+pub struct ConsoleCommands(BTreeMap<&'static str, ConsoleCommand>);
+
+pub trait AddConsoleCommand {
+    /// Registers `run` as the console command `name`. `usage` is shown by
+    /// `help`, e.g. `"teleport <x> <y>"`.
+    fn add_console_command(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        run: ConsoleFn,
+    ) -> &mut Self;
+}
+
+impl AddConsoleCommand for App {
+    fn add_console_command(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        run: ConsoleFn,
+    ) -> &mut Self {
+        self.init_resource::<ConsoleCommands>()
+            .world
+            .resource_mut::<ConsoleCommands>()
+            .0
+            .insert(name, ConsoleCommand { usage, run });
+        self
+    }
+}
+
+#[derive(Resource, Default)]
+struct Console {
+    open: bool,
+    input: String,
+    /// Lines entered, oldest first.
+    history: Vec<String>,
+    /// Position in `history` while stepping through it.
+    browsing: Option<usize>,
+    /// Lines waiting to be run.
+    pending: Vec<String>,
+    log: VecDeque<String>,
+}
+
+impl Console {
+    fn print(&mut self, line: impl Into<String>) {
+        if self.log.len() == LOG_LINES {
+            self.log.pop_front();
+        }
+        self.log.push_back(line.into());
+    }
+}
+
+#[derive(Component)]
+struct ConsoleOverlay;
+
+#[derive(Component)]
+struct ConsoleText;
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .add_console_command("help", "help", help)
+            .add_console_command("spawn", "spawn [enemy|kind] [count] [radius]", spawn)
+            .add_console_command("health", "health <value>", set_health)
+            .add_console_command("give", "give <hp|ammo|scrap> <amount>", give)
+            .add_console_command("set", "set <timescale|config field path> <value>", set)
+            .add_console_command("score", "score <points>", set_score)
+            .add_console_command(
+                "difficulty",
+                "difficulty <spawn rate> [enemy speed] | off",
+                set_difficulty,
+            )
+            .add_console_command("teleport", "teleport <x> <y>", teleport)
+            .add_console_command("config", "config <field path> <value>", set_config)
+            .add_systems(Startup, spawn_overlay)
+            .add_systems(
+                PreUpdate,
+                (toggle_on_touch, read_console_input)
+                    .after(InputSystem)
+                    .before(InputManagerSystem::Update),
+            )
+            .add_systems(Update, (run_console_commands, update_overlay).chain());
+    }
+}
+
+fn toggle_on_touch(touches: Res<Touches>, mut console: ResMut<Console>, mut toggled: Local<bool>) {
+    let down = touches.iter().count();
+    if down == 0 {
+        *toggled = false;
+    } else if down >= TOGGLE_FINGERS && !*toggled && touches.any_just_pressed() {
+        // once per tap, however the fingers come down
+        console.open = !console.open;
+        *toggled = true;
+    }
+}
+
+fn read_console_input(
+    mut console: ResMut<Console>,
+    mut events: EventReader<KeyboardInput>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+) {
+    for event in events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        if event.key_code == TOGGLE_KEY {
+            console.open = !console.open;
+            continue;
+        }
+        if !console.open {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Enter => {
+                let line = std::mem::take(&mut console.input);
+                console.browsing = None;
+                if !line.trim().is_empty() {
+                    console.history.push(line.clone());
+                    console.pending.push(line);
+                }
+            }
+            Key::Backspace => {
+                console.input.pop();
+            }
+            Key::Escape => console.open = false,
+            Key::ArrowUp => {
+                let index = console
+                    .browsing
+                    .map_or(console.history.len(), |index| index)
+                    .saturating_sub(1);
+                if let Some(line) = console.history.get(index).cloned() {
+                    console.browsing = Some(index);
+                    console.input = line;
+                }
+            }
+            Key::ArrowDown => {
+                let Some(index) = console.browsing else {
+                    continue;
+                };
+                if let Some(line) = console.history.get(index + 1).cloned() {
+                    console.browsing = Some(index + 1);
+                    console.input = line;
+                } else {
+                    console.browsing = None;
+                    console.input.clear();
+                }
+            }
+            Key::Space => console.input.push(' '),
+            Key::Character(text) => console.input.push_str(text),
+            _ => {}
+        }
+    }
+
+    // the game sees no keys at all while typing
+    if console.open {
+        keys.reset_all();
+    }
+}
+
+fn run_console_commands(world: &mut World) {
+    let lines = std::mem::take(&mut world.resource_mut::<Console>().pending);
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((name, args)) = words.split_first() else {
+            continue;
+        };
+        let command = world.resource::<ConsoleCommands>().0.get(name).copied();
+        let output = match command {
+            Some(command) => (command.run)(world, args),
+            None => Err(format!("unknown command `{name}`, try `help`")),
+        };
+
+        let mut console = world.resource_mut::<Console>();
+        console.print(format!("> {line}"));
+        match output {
+            Ok(text) if text.is_empty() => {}
+            Ok(text) => text.lines().for_each(|line| console.print(line)),
+            Err(error) => console.print(format!("error: {error}")),
+        }
+    }
+}
+
+/// The `index`th argument parsed as a `T`.
+pub fn arg<T: FromStr>(args: &[&str], index: usize, name: &str) -> Result<T, String> {
+    let value = args.get(index).ok_or(format!("missing {name}"))?;
+    value
+        .parse()
+        .map_err(|_| format!("`{value}` isn't a valid {name}"))
+}
+
+/// The `index`th argument parsed as a `T`, or `default` if there is none.
+fn optional_arg<T: FromStr>(
+    args: &[&str],
+    index: usize,
+    name: &str,
+    default: T,
+) -> Result<T, String> {
+    if index < args.len() {
+        arg(args, index, name)
+    } else {
+        Ok(default)
+    }
+}
+
+/// Where the player is, or the origin without one.
+pub fn player_position(world: &mut World) -> Vec2 {
+    world
+        .query_filtered::<&Transform, With<Player>>()
+        .get_single(world)
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate())
+}
+
+fn help(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let commands = world.resource::<ConsoleCommands>();
+    Ok(commands
+        .0
+        .values()
+        .map(|command| command.usage)
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn spawn(world: &mut World, args: &[&str]) -> Result<String, String> {
+    // `spawn grunt 10` names the kind first, `spawn 10 200 grunt` last
+    let (kind, args) = match args.split_first() {
+        Some((name, rest)) if name.parse::<f32>().is_err() => (Some(*name), rest),
+        _ => (args.get(2).copied(), args),
+    };
+    let count: usize = optional_arg(args, 0, "count", 1)?;
+    let radius: f32 = optional_arg(args, 1, "radius", 200.)?;
+    let kind = match kind {
+        None | Some("enemy") => EnemyKind::Grunt,
+        Some(name) => EnemyKind::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown kind {name}"))?,
+    };
+    let center = player_position(world);
+    for i in 0..count {
+        let angle = std::f32::consts::TAU * i as f32 / count as f32;
+        world.send_event(SpawnEnemy::at(center + Vec2::from_angle(angle) * radius).with_kind(kind));
+    }
+    Ok(format!("spawned {count}"))
+}
+
+fn set_health(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let value: f32 = arg(args, 0, "health")?;
+    let mut health = world
+        .query_filtered::<&mut Health, With<Player>>()
+        .get_single_mut(world)
+        .map_err(|_| "no player".to_string())?;
+    health.current = value.min(health.max);
+    Ok(String::new())
+}
+
+fn give(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let what = *args.first().ok_or("missing what to give")?;
+    match what {
+        "hp" | "health" => {
+            let amount: f32 = arg(args, 1, "amount")?;
+            let mut health = world
+                .query_filtered::<&mut Health, With<Player>>()
+                .get_single_mut(world)
+                .map_err(|_| "no player".to_string())?;
+            health.current = (health.current + amount).min(health.max);
+            Ok(format!("health {}", health.current))
+        }
+        "ammo" => {
+            let amount: u32 = arg(args, 1, "amount")?;
+            let mut inventory = world
+                .query_filtered::<&mut WeaponInventory, With<Player>>()
+                .get_single_mut(world)
+                .map_err(|_| "no player".to_string())?;
+            for slot in inventory.slots_mut() {
+                if let Some(ammo) = &mut slot.ammo {
+                    *ammo = ammo.saturating_add(amount);
+                }
+            }
+            Ok(String::new())
+        }
+        "scrap" => {
+            let amount: u64 = arg(args, 1, "amount")?;
+            let mut progression = world.resource_mut::<Progression>();
+            progression.scrap = progression.scrap.saturating_add(amount);
+            Ok(format!("scrap {}", progression.scrap))
+        }
+        _ => Err(format!("can't give `{what}`, only hp, ammo or scrap")),
+    }
+}
+
+/// `set timescale`, or else a [`GameConfig`] field like `config`.
+fn set(world: &mut World, args: &[&str]) -> Result<String, String> {
+    match args.first() {
+        Some(&"timescale") => {
+            let scale: f32 = arg(args, 1, "time scale")?;
+            world.resource_mut::<GameTime>().scale = scale.max(0.);
+            Ok(String::new())
+        }
+        _ => set_config(world, args),
+    }
+}
+
+fn set_score(world: &mut World, args: &[&str]) -> Result<String, String> {
+    world.resource_mut::<Score>().points = arg(args, 0, "score")?;
+    Ok(String::new())
+}
+
+/// Pins the [`DifficultyScale`], or with `off` leaves it to the settings
+/// again.
+fn set_difficulty(world: &mut World, args: &[&str]) -> Result<String, String> {
+    if args.first() == Some(&"off") {
+        world.resource_mut::<DifficultyOverride>().0 = None;
+        return Ok("difficulty follows the settings again".to_string());
+    }
+    let spawn_rate: f32 = arg(args, 0, "spawn rate")?;
+    let enemy_speed: f32 = optional_arg(args, 1, "enemy speed", 1.)?;
+    world.resource_mut::<DifficultyOverride>().0 = Some(DifficultyScale {
+        spawn_rate,
+        enemy_speed,
+    });
+    Ok(format!(
+        "difficulty held at spawn rate x{spawn_rate}, enemy speed x{enemy_speed} \
+         until `difficulty off`"
+    ))
+}
+
+fn teleport(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let position = Vec2::new(arg(args, 0, "x")?, arg(args, 1, "y")?);
+    let mut transform = world
+        .query_filtered::<&mut Transform, With<Player>>()
+        .get_single_mut(world)
+        .map_err(|_| "no player".to_string())?;
+    transform.translation = position.extend(transform.translation.z);
+    Ok(String::new())
+}
+
+fn set_config(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let path = *args.first().ok_or("missing field path")?;
+    let value = *args.get(1).ok_or("missing value")?;
+    let mut config = world.resource_mut::<GameConfig>();
+    let field = config
+        .reflect_path_mut(path)
+        .map_err(|error| error.to_string())?;
+
+    if let Some(field) = field.downcast_mut::<f32>() {
+        *field = arg(args, 1, "number")?;
+    } else if let Some(field) = field.downcast_mut::<bool>() {
+        *field = arg(args, 1, "true or false")?;
+    } else if let Some(field) = field.downcast_mut::<u32>() {
+        *field = arg(args, 1, "whole number")?;
+    } else {
+        return Err(format!("`{path}` can't be set from the console"));
+    }
+    Ok(format!("{path} = {value}"))
+}
+
+fn spawn_overlay(mut commands: Commands, fonts: Res<UiFonts>) {
+    commands
+        .spawn((
+            ConsoleOverlay,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(0.),
+                    left: Val::Px(0.),
+                    width: Val::Percent(100.),
+                    padding: UiRect::all(Val::Px(8.)),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.85).into(),
+                visibility: Visibility::Hidden,
+                z_index: ZIndex::Global(40),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                ConsoleText,
+                TextBundle::from_section("", fonts.style(14., Color::WHITE)),
+            ));
+        });
+}
+
+fn update_overlay(
+    console: Res<Console>,
+    mut overlays: Query<&mut Visibility, With<ConsoleOverlay>>,
+    mut texts: Query<&mut Text, With<ConsoleText>>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+    for mut visibility in &mut overlays {
+        *visibility = if console.open {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if !console.open {
+        return;
+    }
+    let mut lines: Vec<&str> = console.log.iter().map(String::as_str).collect();
+    let prompt = format!("> {}_", console.input);
+    lines.push(&prompt);
+    for mut text in &mut texts {
+        text.sections[0].value = lines.join("\n");
+    }
+}
@@ -0,0 +1,96 @@
+//! Enemies hurting the player by touching them.
+//!
+//! The player's collider overlapping an [`Enemy`]'s deals
+//! [`ContactDamageConfig::damage`], then leaves the player
+//! [`Invulnerable`] for [`ContactDamageConfig::grace`] seconds so a crowd
+//! pressing in doesn't take all their health in a few frames. The enemy's
+//! [`EnemyDef::on_hit`](crate::defs::EnemyDef::on_hit) effects go on the
+//! player with each hit, and the touch knocks the player
+//! [`MotionConfig::contact_knockback`] away from the enemy.
+
+use bevy::prelude::*;
+
+use crate::{
+    death::Invulnerable,
+    defs::Defs,
+    enemy::{Enemy, EnemyKind},
+    health::DamageEvent,
+    motion::{Impulse, MotionConfig},
+    physics::CollisionEvent,
+    state::GameState,
+    status::ApplyStatus,
+    Player,
+};
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct ContactDamageConfig {
+    pub damage: f32,
+    /// Seconds the player can't be hurt after a hit.
+    pub grace: f32,
+}
+
+impl Default for ContactDamageConfig {
+    fn default() -> Self {
+        Self {
+            damage: 10.,
+            grace: 0.8,
+        }
+    }
+}
+
+pub struct ContactPlugin;
+
+impl Plugin for ContactPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ContactDamageConfig>()
+            .init_resource::<ContactDamageConfig>()
+            .add_systems(Update, hurt_on_contact.run_if(in_state(GameState::Playing)));
+    }
+}
+
+fn hurt_on_contact(
+    mut commands: Commands,
+    config: Res<ContactDamageConfig>,
+    motion: Res<MotionConfig>,
+    defs: Res<Defs>,
+    mut collisions: EventReader<CollisionEvent>,
+    players: Query<Has<Invulnerable>, With<Player>>,
+    enemies: Query<Option<&EnemyKind>, With<Enemy>>,
+    transforms: Query<&Transform>,
+    mut damage: EventWriter<DamageEvent>,
+    mut statuses: EventWriter<ApplyStatus>,
+    mut impulses: EventWriter<Impulse>,
+) {
+    // one hit a frame however many enemies touch
+    let Some((player, enemy)) = collisions.read().find_map(|event| {
+        [(event.a, event.b), (event.b, event.a)]
+            .into_iter()
+            .find(|(player, enemy)| players.contains(*player) && enemies.contains(*enemy))
+    }) else {
+        return;
+    };
+    if players.get(player).unwrap_or(true) {
+        return;
+    }
+    damage.send(DamageEvent {
+        target: player,
+        amount: config.damage,
+    });
+    if let Ok(Some(kind)) = enemies.get(enemy) {
+        statuses.send_batch(defs.enemy(*kind).on_hit.iter().map(|&effect| ApplyStatus {
+            target: player,
+            effect,
+        }));
+    }
+    if let Ok([from, to]) = transforms.get_many([enemy, player]) {
+        let away = (to.translation - from.translation).truncate();
+        impulses.send(Impulse {
+            target: player,
+            impulse: away.normalize_or_zero() * motion.contact_knockback,
+        });
+    }
+    commands.entity(player).insert(Invulnerable {
+        remaining: config.grace,
+    });
+}
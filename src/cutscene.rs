@@ -0,0 +1,485 @@
+//! Short scripted scenes: the level intro and the boss's entrance.
+//!
+//! A [`Cutscene`] is a list of [`Step`]s loaded from a RON file in
+//! `assets/cutscenes`, named in [`CutsceneId::path`]. The steps run one
+//! after the other: moving the camera to the player, the boss or a point
+//! near the player, showing a line in the dialogue box, spawning an enemy,
+//! or waiting. [`PlayCutscene`] starts one; the first run after the main
+//! menu plays [the intro](CutsceneId::Intro), and a boss coming in plays
+//! [its entrance](CutsceneId::Boss). A missing or broken file, or one
+//! still loading, means no scene, with the asset error logged.
+//!
+//! While a scene plays, gameplay is frozen through
+//! [the game clock](crate::game_time), the player's actions are held
+//! released, and the camera is [`Detached`] from the player. Scenes run in
+//! real time. A tap, click, Space, Enter or the gamepad's South button
+//! skips the rest of one. Skipping still spawns what the steps left would
+//! have, so a skipped scene leaves the run as a watched one does. Co-op
+//! clients don't play scenes; the host runs the spawns. Neither does a
+//! [benchmark](crate::bench), spawns and all, so it measures the run.
+
+use bevy::prelude::*;
+use leafwing_input_manager::{plugin::InputManagerSystem, prelude::*};
+use serde::Deserialize;
+
+use crate::{
+    bench,
+    boss::Boss,
+    camera::{Detached, MainCamera},
+    config::Palette,
+    death::StartNewRun,
+    defs::RonLoader,
+    enemy::{EnemyKind, SpawnEnemy},
+    fonts::UiFonts,
+    gestures,
+    i18n::LocalizedText,
+    net::is_client,
+    state::{GameState, StateScoped},
+    tween::Ease,
+    Action, Player,
+};
+
+/// Seconds an enemy spawned by a scene takes to form.
+const SPAWN_FORMING: f32 = 0.6;
+
+/// Where a [`Step::MoveCamera`] takes the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum CameraTarget {
+    Player,
+    /// The first boss in play, or the player without one.
+    Boss,
+    /// This far from the player.
+    Offset(Vec2),
+}
+
+/// One step of a [`Cutscene`].
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum Step {
+    /// Do nothing for this many seconds.
+    Wait(f32),
+    /// Pan the camera to `to` over `duration` seconds.
+    MoveCamera { to: CameraTarget, duration: f32 },
+    /// Show `text` in the dialogue box, under `speaker` when there is one,
+    /// for `duration` seconds. Both are English, and translated like the
+    /// rest of the UI.
+    Dialogue {
+        #[serde(default)]
+        speaker: Option<String>,
+        text: String,
+        duration: f32,
+    },
+    /// Spawn an enemy of `kind` at `offset` from the player, right away.
+    Spawn { kind: EnemyKind, offset: Vec2 },
+}
+
+/// A scripted scene.
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+pub struct Cutscene {
+    pub steps: Vec<Step>,
+}
+
+/// The scenes there are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CutsceneId {
+    Intro,
+    Boss,
+}
+
+impl CutsceneId {
+    /// The scene's file, relative to the assets folder.
+    pub fn path(self) -> &'static str {
+        match self {
+            CutsceneId::Intro => "cutscenes/intro.cutscene.ron",
+            CutsceneId::Boss => "cutscenes/boss.cutscene.ron",
+        }
+    }
+}
+
+/// Play a scene, unless one is already playing.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayCutscene(pub CutsceneId);
+
+/// Keeps the scene files loaded.
+#[derive(Resource, Debug)]
+struct CutsceneHandles {
+    intro: Handle<Cutscene>,
+    boss: Handle<Cutscene>,
+}
+
+impl CutsceneHandles {
+    fn get(&self, id: CutsceneId) -> &Handle<Cutscene> {
+        match id {
+            CutsceneId::Intro => &self.intro,
+            CutsceneId::Boss => &self.boss,
+        }
+    }
+}
+
+/// The scene playing, if one is.
+#[derive(Resource, Debug, Default)]
+pub struct CutscenePlayer {
+    playing: Option<Playing>,
+}
+
+impl CutscenePlayer {
+    pub fn is_playing(&self) -> bool {
+        self.playing.is_some()
+    }
+}
+
+#[derive(Debug)]
+struct Playing {
+    steps: Vec<Step>,
+    /// The step running.
+    index: usize,
+    /// Seconds into it.
+    elapsed: f32,
+    /// Whether the step has started, for the ones that act once.
+    started: bool,
+    /// Where the camera was when the step running started.
+    camera_from: Vec2,
+}
+
+/// Whether a scene is playing.
+pub fn is_playing(player: Res<CutscenePlayer>) -> bool {
+    player.is_playing()
+}
+
+/// Where the intro is, between the main menu and the run after it.
+#[derive(Resource, Debug, Default, PartialEq, Eq)]
+enum Intro {
+    #[default]
+    Played,
+    /// Back at the main menu, so the next new run plays it.
+    Pending,
+    /// A new run was started, to be played once it is in play.
+    Queued,
+}
+
+#[derive(Component)]
+struct DialogueBox;
+
+#[derive(Component)]
+struct DialogueSpeaker;
+
+#[derive(Component)]
+struct DialogueLine;
+
+pub struct CutscenePlugin;
+
+impl Plugin for CutscenePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Cutscene>()
+            .register_asset_loader(RonLoader::<Cutscene>::new(&["cutscene.ron"]))
+            .init_resource::<CutscenePlayer>()
+            .init_resource::<Intro>()
+            .add_event::<PlayCutscene>()
+            .add_systems(Startup, load_cutscenes)
+            .add_systems(OnEnter(GameState::MainMenu), (stop_cutscene, reset_intro))
+            .add_systems(OnEnter(GameState::Playing), spawn_dialogue_box)
+            .add_systems(
+                PreUpdate,
+                hold_actions
+                    .after(InputManagerSystem::Update)
+                    .after(gestures::press_gesture_actions)
+                    .run_if(is_playing),
+            )
+            .add_systems(
+                Update,
+                // a new run is started from the menu, before the fade in
+                (queue_intro, play_intro.run_if(in_state(GameState::Playing))).chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    start_cutscene.after(play_intro),
+                    skip_cutscene.run_if(is_playing),
+                    run_cutscene.run_if(is_playing),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing))
+                    // the host plays the scenes, and spawns for both
+                    .run_if(not(is_client))
+                    .run_if(not(bench::is_benchmarking)),
+            );
+    }
+}
+
+fn load_cutscenes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(CutsceneHandles {
+        intro: asset_server.load(CutsceneId::Intro.path()),
+        boss: asset_server.load(CutsceneId::Boss.path()),
+    });
+}
+
+fn reset_intro(mut intro: ResMut<Intro>) {
+    *intro = Intro::Pending;
+}
+
+fn queue_intro(mut new_runs: EventReader<StartNewRun>, mut intro: ResMut<Intro>) {
+    if new_runs.read().count() > 0 && *intro == Intro::Pending {
+        *intro = Intro::Queued;
+    }
+}
+
+fn play_intro(mut intro: ResMut<Intro>, mut scenes: EventWriter<PlayCutscene>) {
+    if *intro == Intro::Queued {
+        *intro = Intro::Played;
+        scenes.send(PlayCutscene(CutsceneId::Intro));
+    }
+}
+
+fn start_cutscene(
+    mut commands: Commands,
+    handles: Res<CutsceneHandles>,
+    cutscenes: Res<Assets<Cutscene>>,
+    mut requests: EventReader<PlayCutscene>,
+    mut player: ResMut<CutscenePlayer>,
+    cameras: Query<(Entity, &Transform), With<MainCamera>>,
+) {
+    for PlayCutscene(id) in requests.read() {
+        if player.is_playing() {
+            continue;
+        }
+        let Some(cutscene) = cutscenes.get(handles.get(*id)) else {
+            continue;
+        };
+        let Ok((camera, transform)) = cameras.get_single() else {
+            continue;
+        };
+        commands.entity(camera).insert(Detached);
+        player.playing = Some(Playing {
+            steps: cutscene.steps.clone(),
+            index: 0,
+            elapsed: 0.,
+            started: false,
+            camera_from: transform.translation.truncate(),
+        });
+    }
+}
+
+/// Keeps the player's actions released under a scene, so the tap that
+/// skips it doesn't also fire.
+fn hold_actions(mut players: Query<&mut ActionState<Action>, With<Player>>) {
+    for mut action_state in &mut players {
+        action_state.release_all();
+    }
+}
+
+fn skip_cutscene(
+    mut commands: Commands,
+    touches: Res<Touches>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    mut player: ResMut<CutscenePlayer>,
+    mut spawns: EventWriter<SpawnEnemy>,
+    players: Query<&Transform, With<Player>>,
+    cameras: Query<Entity, With<MainCamera>>,
+    mut boxes: Query<&mut Visibility, With<DialogueBox>>,
+) {
+    // on release, so gestures and held keys don't carry on into the run
+    let skipped = touches.any_just_released()
+        || mouse.just_released(MouseButton::Left)
+        || keys.any_just_released([KeyCode::Space, KeyCode::Enter])
+        || buttons
+            .get_just_released()
+            .any(|button| button.button_type == GamepadButtonType::South);
+    if !skipped {
+        return;
+    }
+    let Some(playing) = player.playing.take() else {
+        return;
+    };
+    let origin = player_position(&players);
+    // a spawn runs as it starts, and moves the index past itself
+    for step in playing.steps.iter().skip(playing.index) {
+        if let Step::Spawn { kind, offset } = step {
+            spawns.send(spawn(*kind, origin + *offset));
+        }
+    }
+    end(&mut commands, &cameras, &mut boxes);
+}
+
+fn run_cutscene(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    mut player: ResMut<CutscenePlayer>,
+    mut spawns: EventWriter<SpawnEnemy>,
+    players: Query<&Transform, (With<Player>, Without<MainCamera>)>,
+    bosses: Query<&Transform, (With<Boss>, Without<MainCamera>)>,
+    mut cameras: Query<(Entity, &mut Transform), With<MainCamera>>,
+    mut boxes: Query<&mut Visibility, With<DialogueBox>>,
+    mut speakers: Query<
+        (&mut LocalizedText, &mut Visibility),
+        (With<DialogueSpeaker>, Without<DialogueBox>),
+    >,
+    mut lines: Query<&mut LocalizedText, (With<DialogueLine>, Without<DialogueSpeaker>)>,
+) {
+    let Some(playing) = player.playing.as_mut() else {
+        return;
+    };
+    let Ok((camera, mut camera_transform)) = cameras.get_single_mut() else {
+        return;
+    };
+    let origin = players
+        .get_single()
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+    playing.elapsed += time.delta_seconds();
+
+    // steps that finish at once run on into the next in the same frame
+    while let Some(step) = playing.steps.get(playing.index) {
+        let starting = !playing.started;
+        playing.started = true;
+        let done = match step {
+            Step::Wait(duration) => playing.elapsed >= *duration,
+            Step::MoveCamera { to, duration } => {
+                if starting {
+                    playing.camera_from = camera_transform.translation.truncate();
+                }
+                let target = match to {
+                    CameraTarget::Player => origin,
+                    CameraTarget::Boss => bosses
+                        .iter()
+                        .next()
+                        .map_or(origin, |transform| transform.translation.truncate()),
+                    CameraTarget::Offset(offset) => origin + *offset,
+                };
+                let t = Ease::QuadInOut.apply(playing.elapsed / duration.max(0.01));
+                let position = playing.camera_from.lerp(target, t);
+                camera_transform.translation.x = position.x;
+                camera_transform.translation.y = position.y;
+                playing.elapsed >= *duration
+            }
+            Step::Dialogue {
+                speaker,
+                text,
+                duration,
+            } => {
+                if starting {
+                    for (mut name, mut visibility) in &mut speakers {
+                        name.0 = speaker.clone().unwrap_or_default().into();
+                        *visibility = if speaker.is_some() {
+                            Visibility::Inherited
+                        } else {
+                            Visibility::Hidden
+                        };
+                    }
+                    for mut line in &mut lines {
+                        line.0 = text.clone().into();
+                    }
+                    for mut visibility in &mut boxes {
+                        *visibility = Visibility::Inherited;
+                    }
+                }
+                let done = playing.elapsed >= *duration;
+                if done {
+                    for mut visibility in &mut boxes {
+                        *visibility = Visibility::Hidden;
+                    }
+                }
+                done
+            }
+            Step::Spawn { kind, offset } => {
+                spawns.send(spawn(*kind, origin + *offset));
+                true
+            }
+        };
+        if !done {
+            return;
+        }
+        playing.index += 1;
+        playing.elapsed = 0.;
+        playing.started = false;
+    }
+
+    player.playing = None;
+    commands.entity(camera).remove::<Detached>();
+    for mut visibility in &mut boxes {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+/// Cuts a scene short when the run is left for the menu.
+fn stop_cutscene(
+    mut commands: Commands,
+    mut player: ResMut<CutscenePlayer>,
+    cameras: Query<Entity, With<MainCamera>>,
+    mut boxes: Query<&mut Visibility, With<DialogueBox>>,
+) {
+    if player.playing.take().is_some() {
+        end(&mut commands, &cameras, &mut boxes);
+    }
+}
+
+fn end(
+    commands: &mut Commands,
+    cameras: &Query<Entity, With<MainCamera>>,
+    boxes: &mut Query<&mut Visibility, With<DialogueBox>>,
+) {
+    for camera in cameras {
+        commands.entity(camera).remove::<Detached>();
+    }
+    for mut visibility in boxes {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn player_position(players: &Query<&Transform, With<Player>>) -> Vec2 {
+    players
+        .get_single()
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate())
+}
+
+fn spawn(kind: EnemyKind, position: Vec2) -> SpawnEnemy {
+    SpawnEnemy {
+        forming: SPAWN_FORMING,
+        ..SpawnEnemy::at(position).with_kind(kind)
+    }
+}
+
+fn spawn_dialogue_box(mut commands: Commands, fonts: Res<UiFonts>, palette: Res<Palette>) {
+    commands
+        .spawn((
+            DialogueBox,
+            StateScoped(GameState::Playing),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Percent(12.),
+                    left: Val::Percent(10.),
+                    right: Val::Percent(10.),
+                    padding: UiRect::axes(Val::Px(16.), Val::Px(10.)),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.),
+                    border: UiRect::all(Val::Px(1.)),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.7).into(),
+                border_color: Color::rgba(1., 1., 1., 0.3).into(),
+                visibility: Visibility::Hidden,
+                z_index: ZIndex::Global(15),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                DialogueSpeaker,
+                LocalizedText::new(""),
+                TextBundle::from_section("", fonts.bold(16., palette.accent)),
+            ));
+            parent.spawn((
+                DialogueLine,
+                LocalizedText::new(""),
+                TextBundle::from_section("", fonts.style(20., palette.text)),
+            ));
+            parent.spawn((
+                LocalizedText::new("Tap to skip"),
+                TextBundle::from_section("Tap to skip", fonts.style(12., palette.text.with_a(0.6)))
+                    .with_style(Style {
+                        align_self: AlignSelf::FlexEnd,
+                        ..default()
+                    }),
+            ));
+        });
+}
@@ -0,0 +1,335 @@
+//! The daily challenge: one run a day, the same for everyone.
+//!
+//! [`DailyRun::today`] derives a seed and [`MODIFIERS_PER_DAY`] modifiers
+//! from the UTC date, so every player gets the same arena, the same waves
+//! and the same twists on a given day. The main menu shows them under its
+//! Daily Challenge button. Pressing it applies the modifiers to the
+//! configs they change, keeping the originals, and starts a run seeded
+//! with the day's seed; restarting keeps to the same run. Going back to
+//! the main menu puts the configs back.
+//!
+//! A daily run keeps its own best score for the day in [`storage`], beside
+//! the lifetime best. It isn't recorded as a [replay](crate::replay) or
+//! [saved](crate::save) to continue, as neither carries the modifiers.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::EnemyAiConfig,
+    contact::ContactDamageConfig,
+    daynight::DayNightConfig,
+    death::StartNewRun,
+    pickups::PickupConfig,
+    replay,
+    rng::GameRng,
+    score::{Score, ScoreConfig},
+    spawner::WaveConfig,
+    state::GameState,
+    storage,
+    toast::{Toast, ToastKind},
+};
+
+const DAILY_BEST_KEY: &str = "daily_best";
+pub const MODIFIERS_PER_DAY: usize = 2;
+/// Mixed into the day so the daily seeds don't line up with anything else.
+const DAILY_SALT: u64 = 0xDA11_7C4A_11E5_0001;
+
+/// A twist on a daily run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DailyModifier {
+    Swarm,
+    Frenzy,
+    Scarcity,
+    Thorns,
+    Midnight,
+    Bounty,
+}
+
+impl DailyModifier {
+    pub const ALL: [DailyModifier; 6] = [
+        DailyModifier::Swarm,
+        DailyModifier::Frenzy,
+        DailyModifier::Scarcity,
+        DailyModifier::Thorns,
+        DailyModifier::Midnight,
+        DailyModifier::Bounty,
+    ];
+
+    /// What it does, for the menu.
+    pub fn description(self) -> &'static str {
+        match self {
+            DailyModifier::Swarm => "Bigger waves",
+            DailyModifier::Frenzy => "Faster enemies",
+            DailyModifier::Scarcity => "Fewer pickups",
+            DailyModifier::Thorns => "Harder hits",
+            DailyModifier::Midnight => "Starts at midnight",
+            DailyModifier::Bounty => "Double kill points",
+        }
+    }
+
+    fn apply(self, configs: &mut DailyConfigs) {
+        match self {
+            DailyModifier::Swarm => {
+                configs.waves.first_wave += configs.waves.first_wave / 2;
+                configs.waves.per_wave *= 2;
+            }
+            DailyModifier::Frenzy => {
+                configs.ai.wander_speed *= 1.3;
+                configs.ai.chase_speed *= 1.3;
+            }
+            DailyModifier::Scarcity => {
+                configs.pickups.drop_chance *= 0.5;
+                configs.pickups.weapon_drop_chance *= 0.5;
+            }
+            DailyModifier::Thorns => configs.contact.damage *= 1.5,
+            DailyModifier::Midnight => configs.day_night.start_hour = 0.,
+            DailyModifier::Bounty => configs.score.kill_points *= 2,
+        }
+    }
+}
+
+/// The day's run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DailyRun {
+    /// Days since 1970-01-01, in UTC.
+    pub day: u32,
+    pub seed: u64,
+    pub modifiers: Vec<DailyModifier>,
+}
+
+impl DailyRun {
+    pub fn new(day: u32) -> Self {
+        let seed = splitmix(u64::from(day) ^ DAILY_SALT);
+        // a generator of its own, so picking modifiers doesn't move the run's
+        let mut rng = GameRng::new(seed);
+        let mut pool = DailyModifier::ALL.to_vec();
+        let mut modifiers = Vec::with_capacity(MODIFIERS_PER_DAY);
+        while modifiers.len() < MODIFIERS_PER_DAY && !pool.is_empty() {
+            let index = rng.next_u32() as usize % pool.len();
+            modifiers.push(pool.swap_remove(index));
+        }
+        Self {
+            day,
+            seed,
+            modifiers,
+        }
+    }
+
+    pub fn today() -> Self {
+        Self::new(today())
+    }
+
+    /// The day as `YYYY-MM-DD`.
+    pub fn date(&self) -> String {
+        let (year, month, day) = civil_from_days(self.day);
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+}
+
+/// SplitMix64's finalizer, spreading neighbouring days far apart.
+fn splitmix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Days since 1970-01-01 in UTC, from the system clock.
+#[cfg(not(target_arch = "wasm32"))]
+fn today() -> u32 {
+    let since_epoch = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    (since_epoch.as_secs() / 86_400) as u32
+}
+
+/// Days since 1970-01-01 in UTC, from the browser's clock.
+#[cfg(target_arch = "wasm32")]
+fn today() -> u32 {
+    (js_sys::Date::now() / 86_400_000.) as u32
+}
+
+/// The year, month and day `days` after 1970-01-01.
+fn civil_from_days(days: u32) -> (i64, u32, u32) {
+    // counted in 400-year eras from 0000-03-01, so leap days fall last
+    let z = i64::from(days) + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// The daily run being played, if one is.
+#[derive(Resource, Debug, Default)]
+pub struct DailyChallenge {
+    pub active: Option<DailyRun>,
+}
+
+impl DailyChallenge {
+    /// The seed a new run starts from, when it is a daily one.
+    pub fn seed(&self) -> Option<u64> {
+        self.active.as_ref().map(|run| run.seed)
+    }
+}
+
+/// Whether a daily run is being played.
+pub fn is_daily(challenge: Res<DailyChallenge>) -> bool {
+    challenge.active.is_some()
+}
+
+/// The best daily score, of the day it was set on.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct DailyBest {
+    pub day: u32,
+    pub points: u64,
+}
+
+impl DailyBest {
+    /// The best score on `day`, if one was set.
+    pub fn on(&self, day: u32) -> Option<u64> {
+        (self.day == day && self.points > 0).then_some(self.points)
+    }
+}
+
+/// The configs the modifiers change.
+#[derive(SystemParam)]
+struct DailyConfigs<'w> {
+    waves: ResMut<'w, WaveConfig>,
+    ai: ResMut<'w, EnemyAiConfig>,
+    pickups: ResMut<'w, PickupConfig>,
+    contact: ResMut<'w, ContactDamageConfig>,
+    day_night: ResMut<'w, DayNightConfig>,
+    score: ResMut<'w, ScoreConfig>,
+}
+
+/// The configs as they were before a daily run changed them.
+struct StashedConfigs {
+    waves: WaveConfig,
+    ai: EnemyAiConfig,
+    pickups: PickupConfig,
+    contact: ContactDamageConfig,
+    day_night: DayNightConfig,
+    score: ScoreConfig,
+}
+
+impl StashedConfigs {
+    fn take(configs: &DailyConfigs) -> Self {
+        Self {
+            waves: configs.waves.clone(),
+            ai: configs.ai.clone(),
+            pickups: configs.pickups.clone(),
+            contact: configs.contact.clone(),
+            day_night: configs.day_night.clone(),
+            score: configs.score.clone(),
+        }
+    }
+
+    fn restore(self, configs: &mut DailyConfigs) {
+        *configs.waves = self.waves;
+        *configs.ai = self.ai;
+        *configs.pickups = self.pickups;
+        *configs.contact = self.contact;
+        *configs.day_night = self.day_night;
+        *configs.score = self.score;
+    }
+}
+
+/// Set while a daily run has the configs changed.
+#[derive(Resource, Default)]
+struct Stash(Option<StashedConfigs>);
+
+/// The main menu button that starts the [`DailyRun`].
+#[derive(Component)]
+pub struct DailyButton;
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DailyChallenge>()
+            .init_resource::<Stash>()
+            .insert_resource(storage::load::<DailyBest>(DAILY_BEST_KEY).unwrap_or_default())
+            .add_systems(
+                Update,
+                press_daily
+                    .run_if(in_state(GameState::MainMenu))
+                    .before(replay::begin_run),
+            )
+            .add_systems(OnEnter(GameState::MainMenu), leave_daily)
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                record_daily_best.run_if(is_daily),
+            );
+    }
+}
+
+fn press_daily(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<DailyButton>)>,
+    mut challenge: ResMut<DailyChallenge>,
+    mut stash: ResMut<Stash>,
+    mut configs: DailyConfigs,
+    mut new_runs: EventWriter<StartNewRun>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !buttons.iter().any(|i| *i == Interaction::Pressed) {
+        return;
+    }
+    let run = DailyRun::today();
+    if stash.0.is_none() {
+        stash.0 = Some(StashedConfigs::take(&configs));
+    }
+    for modifier in &run.modifiers {
+        modifier.apply(&mut configs);
+    }
+    info!("daily run {} from seed {}", run.date(), run.seed);
+    challenge.active = Some(run);
+    new_runs.send(StartNewRun);
+    next_state.set(GameState::Playing);
+}
+
+fn leave_daily(
+    mut challenge: ResMut<DailyChallenge>,
+    mut stash: ResMut<Stash>,
+    mut configs: DailyConfigs,
+) {
+    challenge.active = None;
+    if let Some(stashed) = stash.0.take() {
+        stashed.restore(&mut configs);
+    }
+}
+
+fn record_daily_best(
+    score: Res<Score>,
+    challenge: Res<DailyChallenge>,
+    mut best: ResMut<DailyBest>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let Some(run) = &challenge.active else {
+        return;
+    };
+    if best
+        .on(run.day)
+        .is_some_and(|points| points >= score.points)
+        || score.points == 0
+    {
+        return;
+    }
+    *best = DailyBest {
+        day: run.day,
+        points: score.points,
+    };
+    storage::save(DAILY_BEST_KEY, &*best);
+    toasts.send(Toast::new("New daily best!").with_kind(ToastKind::Record));
+}
@@ -0,0 +1,168 @@
+//! How dangerous the area around the player is right now.
+//!
+//! [`DangerLevel`] is worked out once per tick from the enemies and enemy
+//! bullets within [`DangerConfig::radius`] of the player, closer ones
+//! counting for more, and smoothed so it doesn't flicker. Music intensity,
+//! vignettes and warning indicators should all read it rather than each
+//! counting enemies themselves.
+
+use bevy::prelude::*;
+
+use crate::{
+    physics::{Collider, ColliderGrid, CollisionLayer},
+    targeting::EnemyGrid,
+    tween::approach,
+    Player,
+};
+
+/// Smoothed danger, `0..=1`.
+#[derive(Resource, Reflect, Debug, Clone, Copy, Default)]
+#[reflect(Resource)]
+pub struct DangerLevel {
+    pub value: f32,
+    /// This tick's value before smoothing.
+    pub raw: f32,
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct DangerConfig {
+    /// Threats further than this from the player are ignored.
+    pub radius: f32,
+    /// Weight of an enemy bullet relative to an enemy.
+    pub bullet_weight: f32,
+    /// Total weight at which danger reads 1. A threat weighs its weight
+    /// times how close it is, 1 on top of the player, 0 at `radius`.
+    pub saturation: f32,
+    /// Seconds to rise halfway to a higher value.
+    pub rise_half_life: f32,
+    /// Seconds to fall halfway to a lower value.
+    pub fall_half_life: f32,
+}
+
+impl Default for DangerConfig {
+    fn default() -> Self {
+        Self {
+            radius: 300.,
+            bullet_weight: 0.5,
+            saturation: 5.,
+            rise_half_life: 0.15,
+            fall_half_life: 1.,
+        }
+    }
+}
+
+impl DangerConfig {
+    /// Unsmoothed danger at `position` from the given enemy and bullet
+    /// positions.
+    pub fn danger_at(
+        &self,
+        position: Vec2,
+        enemies: impl IntoIterator<Item = Vec2>,
+        bullets: impl IntoIterator<Item = Vec2>,
+    ) -> f32 {
+        let closeness = |other: Vec2| (1. - other.distance(position) / self.radius).max(0.);
+        let enemies: f32 = enemies.into_iter().map(closeness).sum();
+        let bullets: f32 = bullets.into_iter().map(closeness).sum();
+        ((enemies + bullets * self.bullet_weight) / self.saturation).clamp(0., 1.)
+    }
+}
+
+pub struct DangerPlugin;
+
+impl Plugin for DangerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DangerLevel>()
+            .register_type::<DangerConfig>()
+            .init_resource::<DangerLevel>()
+            .init_resource::<DangerConfig>()
+            .add_systems(Update, update_danger);
+    }
+}
+
+fn update_danger(
+    time: Res<Time>,
+    config: Res<DangerConfig>,
+    enemies: Res<EnemyGrid>,
+    colliders: Res<ColliderGrid>,
+    layers: Query<&Collider>,
+    players: Query<&Transform, With<Player>>,
+    mut danger: ResMut<DangerLevel>,
+) {
+    let raw = players.get_single().map_or(0., |player| {
+        let position = player.translation.truncate();
+        let bullets = colliders
+            .0
+            .nearby_in_radius(position, config.radius)
+            .filter(|(entity, _)| {
+                layers
+                    .get(*entity)
+                    .is_ok_and(|collider| collider.layer.contains(CollisionLayer::ENEMY_BULLET))
+            })
+            .map(|(_, other)| other);
+        config.danger_at(
+            position,
+            enemies
+                .0
+                .nearby_in_radius(position, config.radius)
+                .map(|(_, other)| other),
+            bullets,
+        )
+    });
+
+    let half_life = if raw > danger.value {
+        config.rise_half_life
+    } else {
+        config.fall_half_life
+    };
+    danger.raw = raw;
+    danger.value = approach(danger.value, raw, half_life, time.delta_seconds());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{actual} isn't {expected}"
+        );
+    }
+
+    #[test]
+    fn danger_weighs_threats_by_closeness() {
+        let config = DangerConfig::default();
+        let at_origin = |enemies: &[Vec2], bullets: &[Vec2]| {
+            config.danger_at(Vec2::ZERO, enemies.iter().copied(), bullets.iter().copied())
+        };
+
+        assert_near(at_origin(&[], &[]), 0.);
+        // one enemy weighs 1 on top of the player, half of it halfway out
+        assert_near(at_origin(&[Vec2::ZERO], &[]), 0.2);
+        assert_near(at_origin(&[Vec2::new(150., 0.)], &[]), 0.1);
+        // nothing at the edge of the radius or past it
+        assert_near(
+            at_origin(&[Vec2::new(300., 0.), Vec2::new(0., -900.)], &[]),
+            0.,
+        );
+        // bullets count for half as much as enemies
+        assert_near(
+            at_origin(
+                &[Vec2::new(0., 150.)],
+                &[Vec2::new(75., 0.), Vec2::new(-75., 0.)],
+            ),
+            (0.5 + 0.75 * 2. * 0.5) / 5.,
+        );
+        // and a crowd saturates
+        assert_near(at_origin(&[Vec2::ZERO; 10], &[Vec2::ZERO; 10]), 1.);
+    }
+
+    #[test]
+    fn danger_is_measured_from_the_position() {
+        let config = DangerConfig::default();
+        let enemies = [Vec2::new(1000., 1000.)];
+        assert_near(config.danger_at(Vec2::ZERO, enemies, []), 0.);
+        assert_near(config.danger_at(Vec2::new(1000., 850.), enemies, []), 0.1);
+    }
+}
@@ -0,0 +1,422 @@
+//! The day and night cycle, and the lights that show at night.
+//!
+//! [`TimeOfDay`] runs through a day every [`DayNightConfig::day_length`]
+//! seconds of play, from [`DayNightConfig::start_hour`] on each new run.
+//! Anything can read it: waves come faster at night and it rains harder.
+//!
+//! There is no lighting pass. Night is a shade over the playfield, a
+//! camera-sized sprite below the health bars and the rain whose color and
+//! opacity follow the hour, and lights are soft glow sprites drawn over
+//! it: one around the player, street lamps through the arena, and a brief
+//! flash at the muzzle of every shot. The player's glow and the lamps
+//! brighten as it gets dark and are gone by day. At low
+//! [effects quality](EffectsQuality::lights) they aren't drawn at all.
+
+use std::f32::consts::TAU;
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+use crate::{
+    budget::{Budget, BudgetCategory},
+    camera::{visible_rect, MainCamera},
+    config::GameConfig,
+    death::StartNewRun,
+    despawn::DespawnQueue,
+    events::PlayerFired,
+    quality::EffectsQuality,
+    settings::AccessibilitySettings,
+    state::GameState,
+    tween::{Ease, SpriteColorLens, Tween, TweenCompleted},
+    Player,
+};
+
+/// Above gameplay and projectiles, below health bars and the rain.
+const SHADE_Z: f32 = 1.8;
+const LIGHT_Z: f32 = 1.9;
+
+/// Side length of the glow image, in pixels.
+const GLOW_SIZE: u32 = 64;
+
+#[derive(Resource, Reflect, Debug, Clone, Copy)]
+#[reflect(Resource)]
+pub struct TimeOfDay {
+    /// `0..24`, 0 and 24 being midnight.
+    pub hour: f32,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            hour: DayNightConfig::default().start_hour,
+        }
+    }
+}
+
+impl TimeOfDay {
+    /// How light it is, from 0 at midnight to 1 at noon.
+    pub fn daylight(&self) -> f32 {
+        0.5 - 0.5 * (self.hour / 24. * TAU).cos()
+    }
+
+    /// How dark it is, the other way round from [`Self::daylight`].
+    pub fn night(&self) -> f32 {
+        1. - self.daylight()
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct DayNightConfig {
+    /// Seconds of play in a whole day.
+    pub day_length: f32,
+    /// The hour each run starts at.
+    pub start_hour: f32,
+    /// Color of the shade at midnight; its alpha is how dark it gets.
+    pub night_shade: Color,
+    /// Color the shade passes through at dusk and dawn.
+    pub dusk_shade: Color,
+    /// What wave spawn rates are multiplied by at midnight.
+    pub night_spawn_rate: f32,
+    /// Added to the rain intensity at midnight.
+    pub night_rain: f32,
+    /// Street lamps along each side of the arena.
+    pub lamps: UVec2,
+    pub lamp_color: Color,
+    pub lamp_radius: f32,
+    pub player_glow: Color,
+    pub player_glow_radius: f32,
+    pub muzzle_flash: Color,
+    pub muzzle_flash_radius: f32,
+    /// Seconds a muzzle flash takes to fade.
+    pub muzzle_flash_time: f32,
+}
+
+impl Default for DayNightConfig {
+    fn default() -> Self {
+        Self {
+            day_length: 240.,
+            start_hour: 17.,
+            night_shade: Color::rgba(0.02, 0.03, 0.12, 0.7),
+            dusk_shade: Color::rgba(0.35, 0.15, 0.25, 0.3),
+            night_spawn_rate: 1.3,
+            night_rain: 0.3,
+            lamps: UVec2::new(4, 3),
+            lamp_color: Color::rgba(1., 0.8, 0.45, 0.45),
+            lamp_radius: 160.,
+            player_glow: Color::rgba(0.7, 0.85, 1., 0.35),
+            player_glow_radius: 120.,
+            muzzle_flash: Color::rgba(1., 0.85, 0.5, 0.6),
+            muzzle_flash_radius: 60.,
+            muzzle_flash_time: 0.08,
+        }
+    }
+}
+
+impl DayNightConfig {
+    /// The shade's color at `time`: clear by day, through the dusk color to
+    /// the night one.
+    pub fn shade(&self, time: &TimeOfDay) -> Color {
+        let night = time.night();
+        // the dusk color peaks halfway into the dark
+        let dusk = 1. - (night * 2. - 1.).abs();
+        let [nr, ng, nb, na] = self.night_shade.as_rgba_f32();
+        let [dr, dg, db, da] = self.dusk_shade.as_rgba_f32();
+        let mix = |night: f32, dusk_value: f32| night + (dusk_value - night) * dusk;
+        Color::rgba(
+            mix(nr, dr),
+            mix(ng, dg),
+            mix(nb, db),
+            (na * night).max(da * dusk),
+        )
+    }
+
+    /// What wave spawn rates are multiplied by at `time`.
+    pub fn spawn_rate(&self, time: &TimeOfDay) -> f32 {
+        1. + (self.night_spawn_rate - 1.) * time.night()
+    }
+}
+
+/// A glow that shows in the dark, at `color`'s alpha at midnight.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Light2d {
+    pub color: Color,
+}
+
+#[derive(Component)]
+struct NightShade;
+
+#[derive(Component)]
+struct StreetLamp;
+
+#[derive(Component)]
+struct MuzzleFlash;
+
+#[derive(Resource)]
+struct GlowImage(Handle<Image>);
+
+pub struct DayNightPlugin;
+
+impl Plugin for DayNightPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TimeOfDay>()
+            .register_type::<DayNightConfig>()
+            .init_resource::<TimeOfDay>()
+            .init_resource::<DayNightConfig>()
+            .add_systems(Startup, (create_glow, spawn_shade).chain())
+            .add_systems(
+                Update,
+                (
+                    reset_time_of_day,
+                    advance_time.run_if(in_state(GameState::Playing)),
+                    spawn_lamps.run_if(resource_changed::<DayNightConfig>),
+                    attach_player_glow,
+                    flash_muzzles,
+                    (follow_camera, shade_night, brighten_lights),
+                    remove_flashes,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// A white disc fading out from its centre, tinted per light.
+fn glow_image() -> Image {
+    let mut data = Vec::with_capacity((GLOW_SIZE * GLOW_SIZE * 4) as usize);
+    let radius = GLOW_SIZE as f32 / 2.;
+    for y in 0..GLOW_SIZE {
+        for x in 0..GLOW_SIZE {
+            let offset = Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - Vec2::splat(radius);
+            let falloff = (1. - offset.length() / radius).clamp(0., 1.);
+            let alpha = (falloff * falloff * 255.).round() as u8;
+            data.extend_from_slice(&[255, 255, 255, alpha]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: GLOW_SIZE,
+            height: GLOW_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+fn create_glow(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    commands.insert_resource(GlowImage(images.add(glow_image())));
+}
+
+fn glow(image: &GlowImage, color: Color, radius: f32, translation: Vec3) -> SpriteBundle {
+    SpriteBundle {
+        texture: image.0.clone(),
+        sprite: Sprite {
+            color,
+            custom_size: Some(Vec2::splat(radius * 2.)),
+            ..default()
+        },
+        transform: Transform::from_translation(translation),
+        ..default()
+    }
+}
+
+fn spawn_shade(mut commands: Commands) {
+    commands.spawn((
+        NightShade,
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::NONE,
+                ..default()
+            },
+            transform: Transform::from_xyz(0., 0., SHADE_Z),
+            ..default()
+        },
+    ));
+}
+
+fn reset_time_of_day(
+    config: Res<DayNightConfig>,
+    mut new_runs: EventReader<StartNewRun>,
+    mut time_of_day: ResMut<TimeOfDay>,
+) {
+    if new_runs.read().last().is_some() {
+        time_of_day.hour = config.start_hour;
+    }
+}
+
+fn advance_time(time: Res<Time>, config: Res<DayNightConfig>, mut time_of_day: ResMut<TimeOfDay>) {
+    let hours = time.delta_seconds() / config.day_length.max(1.) * 24.;
+    time_of_day.hour = (time_of_day.hour + hours).rem_euclid(24.);
+}
+
+/// Lamps spread evenly through the arena.
+fn spawn_lamps(
+    mut commands: Commands,
+    config: Res<DayNightConfig>,
+    game: Res<GameConfig>,
+    image: Res<GlowImage>,
+    lamps: Query<Entity, With<StreetLamp>>,
+) {
+    for entity in &lamps {
+        commands.entity(entity).despawn();
+    }
+    let size = game.arena.size;
+    for x in 0..config.lamps.x {
+        for y in 0..config.lamps.y {
+            // centred in equal cells, so none sits right on an edge
+            let cell = Vec2::new(
+                (x as f32 + 0.5) / config.lamps.x as f32,
+                (y as f32 + 0.5) / config.lamps.y as f32,
+            );
+            let position = (cell - 0.5) * size;
+            commands.spawn((
+                StreetLamp,
+                Light2d {
+                    color: config.lamp_color,
+                },
+                glow(
+                    &image,
+                    Color::NONE,
+                    config.lamp_radius,
+                    position.extend(LIGHT_Z),
+                ),
+            ));
+        }
+    }
+}
+
+fn attach_player_glow(
+    mut commands: Commands,
+    config: Res<DayNightConfig>,
+    image: Res<GlowImage>,
+    players: Query<Entity, Added<Player>>,
+) {
+    for player in &players {
+        commands.entity(player).with_children(|parent| {
+            parent.spawn((
+                Light2d {
+                    color: config.player_glow,
+                },
+                glow(
+                    &image,
+                    Color::NONE,
+                    config.player_glow_radius,
+                    Vec3::Z * LIGHT_Z,
+                ),
+            ));
+        });
+    }
+}
+
+fn flash_muzzles(
+    mut commands: Commands,
+    config: Res<DayNightConfig>,
+    quality: Res<EffectsQuality>,
+    settings: Res<AccessibilitySettings>,
+    image: Res<GlowImage>,
+    mut budget: Budget,
+    mut fired: EventReader<PlayerFired>,
+) {
+    for shot in fired.read() {
+        // a light blinking with every shot is the kind of flash to leave out
+        if !quality.extras()
+            || settings.reduce_motion
+            || !budget.admit(&mut commands, BudgetCategory::Vfx)
+        {
+            continue;
+        }
+        let color = config.muzzle_flash;
+        let mut flash = commands.spawn((
+            MuzzleFlash,
+            Tween::new(
+                SpriteColorLens {
+                    start: color,
+                    end: color.with_a(0.),
+                },
+                Ease::QuadOut,
+                config.muzzle_flash_time,
+            )
+            .with_completion(),
+            glow(
+                &image,
+                color,
+                config.muzzle_flash_radius,
+                shot.origin.extend(LIGHT_Z),
+            ),
+        ));
+        budget.track(&mut flash, BudgetCategory::Vfx);
+    }
+}
+
+fn follow_camera(
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut shades: Query<(&mut Transform, &mut Sprite), With<NightShade>>,
+) {
+    let Some(area) = cameras
+        .get_single()
+        .ok()
+        .and_then(|(camera, transform)| visible_rect(camera, transform))
+    else {
+        return;
+    };
+    for (mut transform, mut sprite) in &mut shades {
+        transform.translation = area.center().extend(SHADE_Z);
+        // a little over, so a shaking or rotated camera never shows an edge
+        let size = Vec2::splat(area.size().length());
+        if sprite.custom_size != Some(size) {
+            sprite.custom_size = Some(size);
+        }
+    }
+}
+
+fn shade_night(
+    config: Res<DayNightConfig>,
+    time_of_day: Res<TimeOfDay>,
+    mut shades: Query<&mut Sprite, With<NightShade>>,
+) {
+    if !time_of_day.is_changed() && !config.is_changed() {
+        return;
+    }
+    let color = config.shade(&time_of_day);
+    for mut sprite in &mut shades {
+        sprite.color = color;
+    }
+}
+
+fn brighten_lights(
+    time_of_day: Res<TimeOfDay>,
+    quality: Res<EffectsQuality>,
+    mut lights: Query<(Ref<Light2d>, &mut Sprite, &mut Visibility)>,
+) {
+    let night = time_of_day.night();
+    for (light, mut sprite, mut visibility) in &mut lights {
+        if !time_of_day.is_changed() && !quality.is_changed() && !light.is_added() {
+            continue;
+        }
+        *visibility = if quality.lights() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        sprite.color = light.color.with_a(light.color.a() * night);
+    }
+}
+
+fn remove_flashes(
+    mut completed: EventReader<TweenCompleted>,
+    mut despawns: ResMut<DespawnQueue>,
+    flashes: Query<(), With<MuzzleFlash>>,
+) {
+    for event in completed.read() {
+        if flashes.contains(event.entity) {
+            despawns.despawn(event.entity);
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//! What happens when the player dies.
+//!
+//! In [`DeathMode::Classic`] the run ends on the game over screen. In
+//! [`DeathMode::Continue`] the player gets back up where they fell, at the
+//! cost of part of their score and some wave progress: enemies nearby are
+//! cleared and the player is briefly [`Invulnerable`]. Best score and
+//! highest wave are recorded as they are reached, so neither mode loses them.
+//! The game over screen also shows the [online top scores](crate::leaderboard).
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    boss::Boss,
+    config::GameConfig,
+    enemy::{self, Enemy, Forming},
+    events::PlayerDied,
+    fonts::UiFonts,
+    health::Health,
+    i18n::LocalizedText,
+    leaderboard::LeaderboardText,
+    progression::UpgradesButton,
+    replay,
+    score::Score,
+    settings::GameplaySettings,
+    state::{GameState, StateScoped},
+    stats,
+    toast::Toast,
+    Player,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum DeathMode {
+    /// Dying ends the run.
+    #[default]
+    Classic,
+    /// Dying costs score and wave progress, and the run goes on.
+    Continue,
+}
+
+impl DeathMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            DeathMode::Classic => "Game over",
+            DeathMode::Continue => "Continue",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            DeathMode::Classic => DeathMode::Continue,
+            DeathMode::Continue => DeathMode::Classic,
+        }
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct DeathPenaltyConfig {
+    /// Fraction of the score lost on each death.
+    pub score_fraction: f32,
+    /// Waves the run is set back on each death.
+    pub waves_lost: u32,
+    /// Seconds of invulnerability after getting back up.
+    pub invulnerable: f32,
+    /// Enemies within this distance of the player are cleared.
+    pub clear_radius: f32,
+}
+
+impl Default for DeathPenaltyConfig {
+    fn default() -> Self {
+        Self {
+            score_fraction: 0.25,
+            waves_lost: 1,
+            invulnerable: 3.,
+            clear_radius: 300.,
+        }
+    }
+}
+
+impl DeathPenaltyConfig {
+    /// Takes the penalty from `score`, returning the points lost.
+    pub fn apply(&self, score: &mut Score) -> u64 {
+        let lost = (score.points as f64 * self.score_fraction.clamp(0., 1.) as f64).round() as u64;
+        score.points -= lost;
+        // never back before the first wave once one has started
+        if score.wave > 0 {
+            score.wave = score.wave.saturating_sub(self.waves_lost).max(1);
+        }
+        lost
+    }
+}
+
+/// Damage is ignored for `remaining` more seconds. An infinite `remaining`
+/// never wears off and doesn't blink.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Invulnerable {
+    pub remaining: f32,
+}
+
+/// Blinks per second while invulnerable.
+const BLINK_RATE: f32 = 8.;
+
+#[derive(Component)]
+struct NewRunButton;
+
+/// Request to drop the run in progress and start over from wave 0. The
+/// sender picks the state to go to.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct StartNewRun;
+
+pub struct DeathPlugin;
+
+impl Plugin for DeathPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DeathPenaltyConfig>()
+            .init_resource::<DeathPenaltyConfig>()
+            .add_event::<StartNewRun>()
+            .add_systems(
+                Update,
+                (
+                    handle_player_death.after(stats::track_stats),
+                    wear_off_invulnerability,
+                    press_new_run
+                        .run_if(in_state(GameState::GameOver))
+                        .before(replay::begin_run),
+                    start_new_run,
+                ),
+            )
+            .add_systems(OnEnter(GameState::GameOver), spawn_game_over_screen);
+    }
+}
+
+fn handle_player_death(
+    mut commands: Commands,
+    mut died: EventReader<PlayerDied>,
+    settings: Res<GameplaySettings>,
+    config: Res<DeathPenaltyConfig>,
+    mut score: ResMut<Score>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut toasts: EventWriter<Toast>,
+    mut players: Query<&mut Health, With<Player>>,
+    // a boss stands its ground
+    enemies: Query<(Entity, &Transform), (With<Enemy>, Without<Boss>)>,
+) {
+    let Some(event) = died.read().last() else {
+        return;
+    };
+
+    match settings.death_mode {
+        DeathMode::Classic => next_state.set(GameState::GameOver),
+        DeathMode::Continue => {
+            let lost = config.apply(&mut score);
+            if let Ok(mut health) = players.get_mut(event.entity) {
+                health.current = health.max;
+            }
+            for (entity, transform) in &enemies {
+                if transform.translation.truncate().distance(event.position) <= config.clear_radius
+                {
+                    enemy::retire(&mut commands, entity);
+                }
+            }
+            commands.entity(event.entity).insert(Invulnerable {
+                remaining: config.invulnerable,
+            });
+            toasts.send(Toast::new(format!("Back up! -{lost} points")));
+        }
+    }
+}
+
+fn wear_off_invulnerability(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut players: Query<(Entity, &mut Invulnerable, &mut Sprite)>,
+) {
+    for (entity, mut invulnerable, mut sprite) in &mut players {
+        if invulnerable.remaining.is_infinite() {
+            continue;
+        }
+        invulnerable.remaining -= time.delta_seconds();
+        if invulnerable.remaining <= 0. {
+            sprite.color.set_a(1.);
+            commands.entity(entity).remove::<Invulnerable>();
+            continue;
+        }
+        let visible = ((invulnerable.remaining * BLINK_RATE) as u32).is_multiple_of(2);
+        sprite.color.set_a(if visible { 1. } else { 0.3 });
+    }
+}
+
+fn spawn_game_over_screen(mut commands: Commands, fonts: Res<UiFonts>, score: Res<Score>) {
+    commands
+        .spawn((
+            StateScoped(GameState::GameOver),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(12.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.85).into(),
+                z_index: ZIndex::Global(20),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                LocalizedText::new("Game over"),
+                TextBundle::from_section("Game over", fonts.bold(32., Color::WHITE)),
+            ));
+            parent.spawn(TextBundle::from_section(
+                format!("Score: {}  -  Wave: {}", score.points, score.wave),
+                fonts.style(20., Color::WHITE),
+            ));
+            parent.spawn((LeaderboardText, TextBundle::default()));
+            parent
+                .spawn((
+                    NewRunButton,
+                    ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(16.), Val::Px(8.)),
+                            ..default()
+                        },
+                        background_color: Color::rgba(1., 1., 1., 0.15).into(),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        LocalizedText::new("New Run"),
+                        TextBundle::from_section("New Run", fonts.bold(24., Color::WHITE)),
+                    ));
+                });
+            parent
+                .spawn((
+                    UpgradesButton,
+                    ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(16.), Val::Px(8.)),
+                            ..default()
+                        },
+                        background_color: Color::rgba(1., 1., 1., 0.15).into(),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        LocalizedText::new("Upgrades"),
+                        TextBundle::from_section("Upgrades", fonts.bold(24., Color::WHITE)),
+                    ));
+                });
+        });
+}
+
+fn press_new_run(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<NewRunButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut new_runs: EventWriter<StartNewRun>,
+) {
+    if buttons.iter().any(|i| *i == Interaction::Pressed) {
+        new_runs.send(StartNewRun);
+        next_state.set(GameState::Playing);
+    }
+}
+
+pub fn start_new_run(
+    mut commands: Commands,
+    mut new_runs: EventReader<StartNewRun>,
+    game: Res<GameConfig>,
+    mut score: ResMut<Score>,
+    mut players: Query<(&mut Transform, &mut Health), With<Player>>,
+    enemies: Query<Entity, Or<(With<Enemy>, With<Forming>)>>,
+) {
+    if new_runs.read().count() == 0 {
+        return;
+    }
+
+    *score = Score::default();
+    if let Ok((mut transform, mut health)) = players.get_single_mut() {
+        transform.translation = game.arena.start.extend(transform.translation.z);
+        health.current = health.max;
+    }
+    for entity in &enemies {
+        enemy::retire(&mut commands, entity);
+    }
+}
@@ -0,0 +1,360 @@
+//! Enemy, weapon and wave tuning read from asset files.
+//!
+//! [`Defs`] starts out with the values built into the code, and takes in
+//! the RON files in `assets/defs` as they load: an [`EnemyDef`] per
+//! `.enemy.ron` file replaces that kind's stats, a [`WeaponDef`] per
+//! `.weapon.ron` file replaces the weapon of that name or adds one, and the
+//! [`WaveDef`] in `default.waves.ron` replaces the [`WaveConfig`], and the
+//! [`AchievementList`] in `default.achievements.ron` the achievements. The
+//! files are named in [`ENEMY_PATHS`], [`WEAPON_PATHS`], [`WAVE_PATH`] and
+//! [`ACHIEVEMENT_PATH`],
+//! as the web and Android builds can't list a folder. Enemies and weapon
+//! drops are spawned from [`Defs`], so a missing or broken file only leaves
+//! the built-in values in place, with the asset error logged.
+//!
+//! Built with the `hot_reload` feature, Bevy watches the assets folder and
+//! a saved file takes effect at once: enemies in play take its size, color
+//! and health, and the player's weapons its stats, keeping their ammo.
+
+use std::{io, marker::PhantomData};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    accessibility,
+    achievements::AchievementDef,
+    enemy::{Dying, EnemyKind},
+    health::Health,
+    physics::Collider,
+    settings::AccessibilitySettings,
+    spawner::WaveConfig,
+    status::StatusEffect,
+    weapon::{Weapon, WeaponInventory},
+    Player,
+};
+
+/// Every def file, relative to the assets folder.
+const ENEMY_PATHS: [&str; 3] = [
+    "defs/grunt.enemy.ron",
+    "defs/runner.enemy.ron",
+    "defs/brute.enemy.ron",
+];
+const WEAPON_PATHS: [&str; 4] = [
+    "defs/pistol.weapon.ron",
+    "defs/shotgun.weapon.ron",
+    "defs/grenade.weapon.ron",
+    "defs/laser.weapon.ron",
+];
+const WAVE_PATH: &str = "defs/default.waves.ron";
+const ACHIEVEMENT_PATH: &str = "defs/default.achievements.ron";
+
+/// The stats of one [`EnemyKind`].
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+pub struct EnemyDef {
+    pub kind: EnemyKind,
+    pub health: f32,
+    pub size: f32,
+    /// What the AI's wander and chase speeds are multiplied by.
+    pub speed: f32,
+    pub color: Color,
+    /// On the enemy from when it spawns.
+    #[serde(default)]
+    pub effects: Vec<StatusEffect>,
+    /// Put on the player each time the enemy hurts them by touch.
+    #[serde(default)]
+    pub on_hit: Vec<StatusEffect>,
+}
+
+impl EnemyDef {
+    fn builtin(kind: EnemyKind) -> Self {
+        Self {
+            kind,
+            health: kind.health(),
+            size: kind.size(),
+            speed: kind.speed(),
+            color: kind.color(),
+            effects: Vec::new(),
+            on_hit: match kind {
+                // knocks the wind out of the player
+                EnemyKind::Brute => vec![StatusEffect::slow(0.4, 1.5)],
+                _ => Vec::new(),
+            },
+        }
+    }
+}
+
+/// A weapon, and whether enemies drop it.
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+pub struct WeaponDef {
+    pub weapon: Weapon,
+    #[serde(default)]
+    pub drops: bool,
+}
+
+/// The pacing of waves.
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct WaveDef(pub WaveConfig);
+
+/// Every achievement there is.
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct AchievementList(pub Vec<AchievementDef>);
+
+/// The defs in use.
+#[derive(Resource, Debug, Clone)]
+pub struct Defs {
+    enemies: HashMap<EnemyKind, EnemyDef>,
+    weapons: Vec<WeaponDef>,
+    achievements: Vec<AchievementDef>,
+}
+
+impl Default for Defs {
+    fn default() -> Self {
+        let drops = Weapon::drops().into_iter().map(|weapon| WeaponDef {
+            weapon,
+            drops: true,
+        });
+        Self {
+            enemies: EnemyKind::ALL
+                .into_iter()
+                .map(|kind| (kind, EnemyDef::builtin(kind)))
+                .collect(),
+            weapons: [WeaponDef {
+                weapon: Weapon::pistol(),
+                drops: false,
+            }]
+            .into_iter()
+            .chain(drops)
+            .collect(),
+            achievements: AchievementDef::builtin(),
+        }
+    }
+}
+
+impl Defs {
+    pub fn enemy(&self, kind: EnemyKind) -> &EnemyDef {
+        &self.enemies[&kind]
+    }
+
+    pub fn weapon(&self, name: &str) -> Option<&Weapon> {
+        self.weapons
+            .iter()
+            .map(|def| &def.weapon)
+            .find(|weapon| weapon.name == name)
+    }
+
+    pub fn achievements(&self) -> &[AchievementDef] {
+        &self.achievements
+    }
+
+    /// Weapons enemies can drop.
+    pub fn drops(&self) -> Vec<&Weapon> {
+        self.weapons
+            .iter()
+            .filter(|def| def.drops)
+            .map(|def| &def.weapon)
+            .collect()
+    }
+}
+
+/// Keeps the def files loaded.
+#[derive(Resource, Debug)]
+// only held, so that the files aren't unloaded
+#[allow(dead_code)]
+struct DefHandles(Vec<UntypedHandle>);
+
+pub struct DefsPlugin;
+
+impl Plugin for DefsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<EnemyDef>()
+            .init_asset::<WeaponDef>()
+            .init_asset::<WaveDef>()
+            .init_asset::<AchievementList>()
+            .register_asset_loader(RonLoader::<EnemyDef>::new(&["enemy.ron"]))
+            .register_asset_loader(RonLoader::<WeaponDef>::new(&["weapon.ron"]))
+            .register_asset_loader(RonLoader::<WaveDef>::new(&["waves.ron"]))
+            .register_asset_loader(RonLoader::<AchievementList>::new(&["achievements.ron"]))
+            .init_resource::<Defs>()
+            .add_systems(Startup, load_defs)
+            .add_systems(
+                Update,
+                (
+                    (
+                        read_enemy_defs,
+                        read_weapon_defs,
+                        read_wave_defs,
+                        read_achievement_defs,
+                    ),
+                    refresh_enemies.run_if(
+                        resource_changed::<Defs>.or_else(resource_changed::<AccessibilitySettings>),
+                    ),
+                    refresh_weapons.run_if(resource_changed::<Defs>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn load_defs(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let enemies = ENEMY_PATHS.map(|path| asset_server.load::<EnemyDef>(path).untyped());
+    let weapons = WEAPON_PATHS.map(|path| asset_server.load::<WeaponDef>(path).untyped());
+    let waves = asset_server.load::<WaveDef>(WAVE_PATH).untyped();
+    let achievements = asset_server
+        .load::<AchievementList>(ACHIEVEMENT_PATH)
+        .untyped();
+    commands.insert_resource(DefHandles(
+        enemies
+            .into_iter()
+            .chain(weapons)
+            .chain([waves, achievements])
+            .collect(),
+    ));
+}
+
+/// The defs in `events` loaded or changed since the last frame.
+fn updated<'a, A: Asset>(
+    events: &'a mut EventReader<AssetEvent<A>>,
+    assets: &'a Assets<A>,
+) -> impl Iterator<Item = &'a A> {
+    events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .filter_map(|id| assets.get(id))
+}
+
+fn read_enemy_defs(
+    mut events: EventReader<AssetEvent<EnemyDef>>,
+    assets: Res<Assets<EnemyDef>>,
+    mut defs: ResMut<Defs>,
+) {
+    for def in updated(&mut events, &assets) {
+        defs.enemies.insert(def.kind, def.clone());
+    }
+}
+
+fn read_weapon_defs(
+    mut events: EventReader<AssetEvent<WeaponDef>>,
+    assets: Res<Assets<WeaponDef>>,
+    mut defs: ResMut<Defs>,
+) {
+    for def in updated(&mut events, &assets) {
+        match defs
+            .weapons
+            .iter_mut()
+            .find(|known| known.weapon.name == def.weapon.name)
+        {
+            Some(known) => *known = def.clone(),
+            None => defs.weapons.push(def.clone()),
+        }
+    }
+}
+
+fn read_wave_defs(
+    mut events: EventReader<AssetEvent<WaveDef>>,
+    assets: Res<Assets<WaveDef>>,
+    mut config: ResMut<WaveConfig>,
+) {
+    for WaveDef(waves) in updated(&mut events, &assets) {
+        *config = waves.clone();
+    }
+}
+
+fn read_achievement_defs(
+    mut events: EventReader<AssetEvent<AchievementList>>,
+    assets: Res<Assets<AchievementList>>,
+    mut defs: ResMut<Defs>,
+) {
+    for AchievementList(achievements) in updated(&mut events, &assets) {
+        defs.achievements = achievements.clone();
+    }
+}
+
+/// Gives enemies already spawned the stats of their kind's def, in the
+/// colors of the current [`ColorBlindMode`](accessibility::ColorBlindMode).
+fn refresh_enemies(
+    defs: Res<Defs>,
+    settings: Res<AccessibilitySettings>,
+    mut enemies: Query<
+        (&EnemyKind, &mut Sprite, &mut Health, Option<&mut Collider>),
+        Without<Dying>,
+    >,
+) {
+    for (kind, mut sprite, mut health, collider) in &mut enemies {
+        let def = defs.enemy(*kind);
+        sprite.custom_size = Some(Vec2::splat(def.size));
+        sprite.color = accessibility::enemy_color(&settings, def).with_a(sprite.color.a());
+        if let Some(mut collider) = collider {
+            collider.radius = def.size / 2.;
+        }
+        if health.max != def.health && health.max > 0. {
+            health.current *= def.health / health.max;
+            health.max = def.health;
+        }
+    }
+}
+
+/// Gives the player's weapons the stats of their defs, keeping their ammo.
+fn refresh_weapons(
+    defs: Res<Defs>,
+    mut players: Query<(&mut Weapon, &mut WeaponInventory), With<Player>>,
+) {
+    for (mut weapon, mut inventory) in &mut players {
+        for slot in inventory.slots_mut() {
+            if let Some(def) = defs.weapon(&slot.weapon.name) {
+                slot.weapon = def.clone();
+            }
+        }
+        if let Some(def) = defs.weapon(&weapon.name) {
+            *weapon = def.clone();
+        }
+    }
+}
+
+/// Loads a RON file as an `A`.
+pub(crate) struct RonLoader<A> {
+    extensions: &'static [&'static str],
+    asset: PhantomData<fn() -> A>,
+}
+
+impl<A> RonLoader<A> {
+    pub(crate) fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            asset: PhantomData,
+        }
+    }
+}
+
+impl<A: Asset + DeserializeOwned> AssetLoader for RonLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<A, io::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            ron::de::from_bytes(&bytes)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
@@ -0,0 +1,149 @@
+//! One place where gameplay entities leave the world.
+//!
+//! Several systems may decide to remove the same entity in one tick: a
+//! projectile can expire, leave the screen and hit a wall at once, and an
+//! enemy can be culled while its death animation ends. Despawning it twice
+//! through [`Commands`] warns, and an insert queued for an entity another
+//! system despawned first panics. Instead, systems push into
+//! [`DespawnQueue`], which is drained at the end of every simulation tick,
+//! so a shot spent in one tick can't hit again in the next, and once more
+//! in [`Last`]: each entity is removed only once, a despawn wins over a
+//! return to its pool, and entities that are already gone are skipped.
+//!
+//! UI trees owned by a single system, and [`StateScoped`] screens, still
+//! despawn themselves directly.
+//!
+//! [`StateScoped`]: crate::state::StateScoped
+
+use bevy::{prelude::*, utils::HashMap};
+
+/// Puts a pooled entity back in its pool, instead of despawning it.
+pub type ReturnToPool = fn(&mut World, Entity);
+
+#[derive(Debug, Clone, Copy)]
+pub enum Removal {
+    /// Despawn the entity and its children.
+    Despawn,
+    /// Hand the entity back to its pool.
+    Return(ReturnToPool),
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct DespawnQueue {
+    requests: Vec<(Entity, Removal)>,
+}
+
+impl DespawnQueue {
+    pub fn despawn(&mut self, entity: Entity) {
+        self.requests.push((entity, Removal::Despawn));
+    }
+
+    pub fn return_to_pool(&mut self, entity: Entity, put_back: ReturnToPool) {
+        self.requests.push((entity, Removal::Return(put_back)));
+    }
+
+    /// Takes the queued requests, one per entity in the order each was
+    /// first asked for, with a despawn replacing any return.
+    pub fn drain(&mut self) -> Vec<(Entity, Removal)> {
+        let mut resolved: Vec<(Entity, Removal)> = Vec::new();
+        let mut index: HashMap<Entity, usize> = HashMap::default();
+        for (entity, removal) in self.requests.drain(..) {
+            match index.get(&entity) {
+                Some(&i) => {
+                    if let Removal::Despawn = removal {
+                        resolved[i].1 = removal;
+                    }
+                }
+                None => {
+                    index.insert(entity, resolved.len());
+                    resolved.push((entity, removal));
+                }
+            }
+        }
+        resolved
+    }
+}
+
+pub struct DespawnPlugin;
+
+impl Plugin for DespawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DespawnQueue>()
+            .add_systems(FixedLast, process_despawns)
+            .add_systems(Last, process_despawns);
+    }
+}
+
+fn process_despawns(world: &mut World) {
+    let requests = world.resource_mut::<DespawnQueue>().drain();
+    for (entity, removal) in requests {
+        if world.get_entity(entity).is_none() {
+            continue;
+        }
+        match removal {
+            Removal::Despawn => world.entity_mut(entity).despawn_recursive(),
+            Removal::Return(put_back) => put_back(world, entity),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component)]
+    struct Returned;
+
+    fn put_back(world: &mut World, entity: Entity) {
+        world.entity_mut(entity).insert(Returned);
+    }
+
+    #[test]
+    fn each_entity_is_removed_once() {
+        let (a, b, c) = (
+            Entity::from_raw(1),
+            Entity::from_raw(2),
+            Entity::from_raw(3),
+        );
+        let mut queue = DespawnQueue::default();
+        queue.despawn(a);
+        queue.return_to_pool(b, put_back);
+        queue.despawn(a);
+        queue.return_to_pool(c, put_back);
+        // a return asked for after the despawn doesn't undo it, and a
+        // despawn after a return replaces it
+        queue.return_to_pool(a, put_back);
+        queue.despawn(b);
+
+        let drained = queue.drain();
+        let order: Vec<Entity> = drained.iter().map(|(entity, _)| *entity).collect();
+        assert_eq!(order, [a, b, c]);
+        assert!(matches!(drained[0].1, Removal::Despawn));
+        assert!(matches!(drained[1].1, Removal::Despawn));
+        assert!(matches!(drained[2].1, Removal::Return(_)));
+        assert!(queue.drain().is_empty());
+    }
+
+    #[test]
+    fn entities_already_gone_are_skipped() {
+        let mut world = World::new();
+        world.init_resource::<DespawnQueue>();
+        let gone = world.spawn_empty().id();
+        let pooled = world.spawn_empty().id();
+        let child = world.spawn_empty().id();
+        let parent = world.spawn_empty().add_child(child).id();
+        world.despawn(gone);
+
+        let mut queue = world.resource_mut::<DespawnQueue>();
+        queue.despawn(gone);
+        queue.return_to_pool(gone, put_back);
+        queue.return_to_pool(pooled, put_back);
+        queue.despawn(parent);
+        queue.despawn(parent);
+        process_despawns(&mut world);
+
+        assert!(world.get::<Returned>(pooled).is_some());
+        assert!(world.get_entity(parent).is_none());
+        assert!(world.get_entity(child).is_none());
+    }
+}
@@ -0,0 +1,201 @@
+//! Dynamic difficulty.
+//!
+//! With [`GameplaySettings::dynamic_difficulty`] on, a performance score is
+//! kept from the player's health, how long since they were last hit, their
+//! recent kills and the damage they recently took. Playing well slowly
+//! raises [`DifficultyScale`] and deaths or close calls lower it, within
+//! [`DynamicDifficultyConfig::min`] and [`DynamicDifficultyConfig::max`].
+//! The spawner multiplies its spawn rate, and the AI its enemy speed, by
+//! the scale, on top of whatever they use as a base. With the setting off
+//! the scale stays at 1.
+//!
+//! The console's `difficulty` command pins the scale through
+//! [`DifficultyOverride`], whatever the setting, until it is cleared.
+
+use bevy::prelude::*;
+
+use crate::{
+    events::{EnemyKilled, PlayerDied, PlayerHurt},
+    health::Health,
+    settings::GameplaySettings,
+    tween::approach,
+    Player,
+};
+
+/// Multipliers every difficulty-dependent system reads.
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq)]
+#[reflect(Resource)]
+pub struct DifficultyScale {
+    pub spawn_rate: f32,
+    pub enemy_speed: f32,
+}
+
+impl Default for DifficultyScale {
+    fn default() -> Self {
+        Self {
+            spawn_rate: 1.,
+            enemy_speed: 1.,
+        }
+    }
+}
+
+/// A scale to hold [`DifficultyScale`] at instead of adjusting it.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct DifficultyOverride(pub Option<DifficultyScale>);
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct DynamicDifficultyConfig {
+    /// Bounds of the spawn rate multiplier.
+    pub min: f32,
+    pub max: f32,
+    /// How much of the spawn rate change also applies to enemy speed.
+    pub speed_share: f32,
+    /// Seconds for the multiplier to move halfway to where performance
+    /// says it should be.
+    pub half_life: f32,
+    /// Seconds for recent kills and damage to be half forgotten.
+    pub memory: f32,
+    /// Seconds without being hit that count as a perfect streak.
+    pub streak: f32,
+    /// Recent kills, after decay, that count as dominating.
+    pub kills: f32,
+}
+
+impl Default for DynamicDifficultyConfig {
+    fn default() -> Self {
+        Self {
+            min: 0.7,
+            max: 1.5,
+            speed_share: 0.5,
+            half_life: 5.,
+            memory: 10.,
+            streak: 30.,
+            kills: 15.,
+        }
+    }
+}
+
+/// Recent play, decaying over [`DynamicDifficultyConfig::memory`].
+#[derive(Resource, Debug, Default)]
+struct RecentPlay {
+    kills: f32,
+    /// Damage taken as a fraction of max health.
+    damage: f32,
+    since_hit: f32,
+    multiplier: f32,
+}
+
+impl DynamicDifficultyConfig {
+    /// How well the player is doing, `0..=1`, with 0.5 as neutral.
+    fn performance(&self, recent: &RecentPlay, health: f32) -> f32 {
+        let streak = (recent.since_hit / self.streak).min(1.);
+        let kills = (recent.kills / self.kills).min(1.);
+        let score = 0.5 * health + 0.25 * streak + 0.25 * kills - recent.damage;
+        score.clamp(0., 1.)
+    }
+
+    /// Spawn rate multiplier for a performance score, 1 at neutral.
+    fn multiplier(&self, performance: f32) -> f32 {
+        if performance >= 0.5 {
+            1. + (self.max - 1.) * (performance - 0.5) * 2.
+        } else {
+            1. - (1. - self.min) * (0.5 - performance) * 2.
+        }
+    }
+}
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DifficultyScale>()
+            .register_type::<DynamicDifficultyConfig>()
+            .init_resource::<DifficultyScale>()
+            .init_resource::<DynamicDifficultyConfig>()
+            .init_resource::<DifficultyOverride>()
+            .insert_resource(RecentPlay {
+                multiplier: 1.,
+                ..default()
+            })
+            .add_systems(Update, update_difficulty);
+
+        #[cfg(debug_assertions)]
+        app.add_systems(Update, log_difficulty.after(update_difficulty));
+    }
+}
+
+fn update_difficulty(
+    settings: Res<GameplaySettings>,
+    config: Res<DynamicDifficultyConfig>,
+    forced: Res<DifficultyOverride>,
+    time: Res<Time>,
+    mut recent: ResMut<RecentPlay>,
+    mut scale: ResMut<DifficultyScale>,
+    mut killed: EventReader<EnemyKilled>,
+    mut hurt: EventReader<PlayerHurt>,
+    mut died: EventReader<PlayerDied>,
+    players: Query<&Health, With<Player>>,
+) {
+    if let Some(forced) = forced.0 {
+        scale.set_if_neq(forced);
+        // what was played meanwhile doesn't count once it is cleared
+        killed.clear();
+        hurt.clear();
+        died.clear();
+        return;
+    }
+    if !settings.dynamic_difficulty {
+        *recent = RecentPlay {
+            multiplier: 1.,
+            ..default()
+        };
+        if scale.spawn_rate != 1. || scale.enemy_speed != 1. {
+            *scale = DifficultyScale::default();
+        }
+        return;
+    }
+
+    let dt = time.delta_seconds();
+    let decay = 0.5f32.powf(dt / config.memory);
+    let health = players.get_single().ok();
+    recent.kills = recent.kills * decay + killed.read().count() as f32;
+    recent.damage *= decay;
+    recent.since_hit += dt;
+    for event in hurt.read() {
+        let max = health.map_or(100., |health| health.max);
+        recent.damage += event.amount / max;
+        recent.since_hit = 0.;
+    }
+    // a death counts as a full bar of damage on top of the hit itself
+    recent.damage += died.read().count() as f32;
+
+    let fraction = health.map_or(1., |health| (health.current / health.max).clamp(0., 1.));
+    let target = config.multiplier(config.performance(&recent, fraction));
+    recent.multiplier = approach(recent.multiplier, target, config.half_life, dt);
+
+    scale.spawn_rate = recent.multiplier;
+    scale.enemy_speed = 1. + (recent.multiplier - 1.) * config.speed_share;
+}
+
+#[cfg(debug_assertions)]
+fn log_difficulty(
+    settings: Res<GameplaySettings>,
+    scale: Res<DifficultyScale>,
+    time: Res<Time<Real>>,
+    mut since_log: Local<f32>,
+) {
+    const LOG_INTERVAL: f32 = 5.;
+
+    if !settings.dynamic_difficulty {
+        return;
+    }
+    *since_log += time.delta_seconds();
+    if *since_log >= LOG_INTERVAL {
+        *since_log = 0.;
+        info!(
+            "difficulty: spawn rate x{:.2}, enemy speed x{:.2}",
+            scale.spawn_rate, scale.enemy_speed
+        );
+    }
+}
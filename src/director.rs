@@ -0,0 +1,127 @@
+//! What each wave is made of.
+//!
+//! [The spawner](crate::spawner) gives every wave a budget that grows with
+//! its number, and the director spends it one pack at a time: each pack is
+//! a single [`EnemyKind`], picked at random among those unlocked by the
+//! wave and still affordable, and costs its [`DirectorEntry::cost`] per
+//! enemy. Kinds grow more likely with every wave past the one unlocking
+//! them, so later waves lean towards the expensive ones as well as being
+//! bigger. The wave has spawned once the budget can't pay for anything
+//! more.
+//!
+//! Every [`DirectorConfig::boss_every`]th wave is a [boss](crate::boss)
+//! wave instead, with no packs at all, except in [co-op](crate::net).
+
+use bevy::prelude::*;
+
+use crate::{enemy::EnemyKind, rng::GameRng};
+
+/// How an enemy kind is bought for a wave.
+#[derive(Reflect, Debug, Clone)]
+pub struct DirectorEntry {
+    pub kind: EnemyKind,
+    /// Budget spent on each enemy of this kind.
+    pub cost: u32,
+    /// First wave it can come out in.
+    pub from_wave: u32,
+    /// Odds of a pack being this kind, against the other entries.
+    pub weight: f32,
+}
+
+impl DirectorEntry {
+    pub fn new(kind: EnemyKind, cost: u32, from_wave: u32) -> Self {
+        Self {
+            kind,
+            cost,
+            from_wave,
+            weight: 1.,
+        }
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct DirectorConfig {
+    pub entries: Vec<DirectorEntry>,
+    /// Added to an entry's weight for each wave after it unlocks.
+    pub weight_growth: f32,
+    /// Every this many waves is a boss wave; 0 for none.
+    pub boss_every: u32,
+}
+
+impl Default for DirectorConfig {
+    fn default() -> Self {
+        Self {
+            entries: vec![
+                DirectorEntry::new(EnemyKind::Grunt, 1, 1),
+                DirectorEntry::new(EnemyKind::Runner, 2, 3),
+                DirectorEntry::new(EnemyKind::Brute, 4, 5),
+            ],
+            weight_growth: 0.25,
+            boss_every: 5,
+        }
+    }
+}
+
+impl DirectorConfig {
+    pub fn is_boss_wave(&self, wave: u32) -> bool {
+        self.boss_every > 0 && wave.is_multiple_of(self.boss_every)
+    }
+
+    /// Weight of `entry` in `wave`, 0 while it is still locked.
+    fn weight(&self, entry: &DirectorEntry, wave: u32) -> f32 {
+        if wave < entry.from_wave {
+            return 0.;
+        }
+        entry.weight.max(0.) + self.weight_growth * (wave - entry.from_wave) as f32
+    }
+
+    /// The kind of the next pack in `wave` and how many of it, at most
+    /// `pack`, with `budget` left to spend. `None` once nothing unlocked is
+    /// affordable.
+    pub fn next_pack(
+        &self,
+        wave: u32,
+        budget: u32,
+        pack: u32,
+        rng: &mut GameRng,
+    ) -> Option<(EnemyKind, u32)> {
+        let affordable: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.cost.max(1) <= budget)
+            .map(|entry| (entry, self.weight(entry, wave)))
+            .filter(|(_, weight)| *weight > 0.)
+            .collect();
+        let total: f32 = affordable.iter().map(|(_, weight)| weight).sum();
+        let mut roll = rng.f32() * total;
+        let (entry, _) = affordable
+            .iter()
+            .find(|(_, weight)| {
+                roll -= weight;
+                roll < 0.
+            })
+            .or(affordable.last())?;
+        let cost = entry.cost.max(1);
+        Some((entry.kind, pack.max(1).min(budget / cost)))
+    }
+
+    /// Budget spent on `count` enemies of `kind`.
+    pub fn cost(&self, kind: EnemyKind, count: u32) -> u32 {
+        let cost = self
+            .entries
+            .iter()
+            .find(|entry| entry.kind == kind)
+            .map_or(1, |entry| entry.cost.max(1));
+        cost * count
+    }
+}
+
+pub struct DirectorPlugin;
+
+impl Plugin for DirectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DirectorConfig>()
+            .init_resource::<DirectorConfig>();
+    }
+}
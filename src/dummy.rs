@@ -0,0 +1,172 @@
+//! Target dummies for comparing weapons. Debug builds only.
+//!
+//! The console's `dummy` command puts a [`Dummy`] next to the player. It sits
+//! on the enemy collision layer, so every projectile, pierce and cluster
+//! treats it like an enemy, but it has no AI and never fights back. Its
+//! health refills whenever it runs out, and a readout above it shows the
+//! damage taken since the last `dummy reset` and the damage per second over
+//! the last [`DPS_WINDOW`] seconds.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    console::{arg, player_position, AddConsoleCommand},
+    enemy::ENEMY_SIZE,
+    fonts::UiFonts,
+    health::{DamageEvent, Health},
+    physics::{Collider, CollisionLayer},
+};
+
+/// Seconds of hits the damage per second is averaged over.
+pub const DPS_WINDOW: f32 = 3.;
+const DUMMY_HEALTH: f32 = 10_000.;
+const DUMMY_COLOR: Color = Color::rgb(0.8, 0.7, 0.4);
+/// Distance from the player a dummy spawns at, without a position.
+const SPAWN_DISTANCE: f32 = 150.;
+
+#[derive(Component, Debug, Default)]
+pub struct Dummy {
+    /// Damage taken since spawning or the last reset.
+    pub total: f32,
+    /// Time and amount of each hit within the window, oldest first.
+    hits: VecDeque<(f32, f32)>,
+}
+
+impl Dummy {
+    pub fn record(&mut self, now: f32, amount: f32) {
+        self.total += amount;
+        self.hits.push_back((now, amount));
+    }
+
+    /// Average damage per second over the [`DPS_WINDOW`] before `now`.
+    pub fn dps(&mut self, now: f32) -> f32 {
+        while self
+            .hits
+            .front()
+            .is_some_and(|(time, _)| now - time > DPS_WINDOW)
+        {
+            self.hits.pop_front();
+        }
+        self.hits.iter().map(|(_, amount)| amount).sum::<f32>() / DPS_WINDOW
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[derive(Component)]
+struct DummyReadout;
+
+pub struct DummyPlugin;
+
+impl Plugin for DummyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command("dummy", "dummy [<x> <y> | reset | clear]", dummy)
+            .add_systems(
+                Update,
+                (record_hits, refill_dummies, update_readouts).chain(),
+            );
+    }
+}
+
+fn dummy(world: &mut World, args: &[&str]) -> Result<String, String> {
+    match args.first().copied() {
+        Some("reset") => {
+            let mut dummies = world.query::<(&mut Dummy, &mut Health)>();
+            for (mut dummy, mut health) in dummies.iter_mut(world) {
+                dummy.reset();
+                health.current = health.max;
+            }
+            Ok("dummies reset".to_string())
+        }
+        Some("clear") => {
+            let dummies: Vec<Entity> = world
+                .query_filtered::<Entity, With<Dummy>>()
+                .iter(world)
+                .collect();
+            for &entity in &dummies {
+                world.entity_mut(entity).despawn_recursive();
+            }
+            Ok(format!("removed {}", dummies.len()))
+        }
+        Some(_) => {
+            let position = Vec2::new(arg(args, 0, "x")?, arg(args, 1, "y")?);
+            spawn_dummy(world, position);
+            Ok(String::new())
+        }
+        None => {
+            let position = player_position(world) + Vec2::X * SPAWN_DISTANCE;
+            spawn_dummy(world, position);
+            Ok(String::new())
+        }
+    }
+}
+
+fn spawn_dummy(world: &mut World, position: Vec2) {
+    let style = world.resource::<UiFonts>().style(14., Color::WHITE);
+    world
+        .spawn((
+            Dummy::default(),
+            Health::new(DUMMY_HEALTH),
+            Collider::new(ENEMY_SIZE / 2., CollisionLayer::ENEMY),
+            SpriteBundle {
+                transform: Transform::from_translation(position.extend(0.)),
+                sprite: Sprite {
+                    color: DUMMY_COLOR,
+                    custom_size: Some(Vec2::splat(ENEMY_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                DummyReadout,
+                Text2dBundle {
+                    text: Text::from_section("", style),
+                    transform: Transform::from_xyz(0., ENEMY_SIZE, 1.),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn record_hits(
+    time: Res<Time>,
+    mut damage: EventReader<DamageEvent>,
+    mut dummies: Query<&mut Dummy>,
+) {
+    for event in damage.read() {
+        if let Ok(mut dummy) = dummies.get_mut(event.target) {
+            dummy.record(time.elapsed_seconds(), event.amount);
+        }
+    }
+}
+
+/// Dead targets ignore further hits, so a dummy never stays dead.
+fn refill_dummies(mut dummies: Query<&mut Health, With<Dummy>>) {
+    for mut health in &mut dummies {
+        if health.is_dead() {
+            health.current = health.max;
+        }
+    }
+}
+
+fn update_readouts(
+    time: Res<Time>,
+    mut dummies: Query<(&mut Dummy, &Children)>,
+    mut readouts: Query<&mut Text, With<DummyReadout>>,
+) {
+    let now = time.elapsed_seconds();
+    for (mut dummy, children) in &mut dummies {
+        let dps = dummy.dps(now);
+        for &child in children {
+            if let Ok(mut text) = readouts.get_mut(child) {
+                text.sections[0].value = format!("{:.0} dmg\n{dps:.0} dps", dummy.total);
+            }
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! Enemy lifecycle: forming, alive, dying, pooled.
+//!
+//! An enemy spawned with a [`SpawnEnemy::forming`] time first spends it
+//! [`Forming`]: faded in, without its [`Enemy`] marker or collider, so it
+//! can't be hit, targeted or hurt anyone yet. It then activates as a normal
+//! enemy. Killed or retired enemies play a death animation and go back to
+//! the pool for the next spawn; a killed one also bursts into an
+//! [`Fx::Explode`].
+//!
+//! Every enemy is of an [`EnemyKind`], whose [def](crate::defs) sets its
+//! health, size, speed and color.
+
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accessibility,
+    ai::{Behavior, Vision},
+    budget::{Budget, BudgetCategory, Budgeted},
+    defs::Defs,
+    despawn::DespawnQueue,
+    events::EnemyKilled,
+    fx::Fx,
+    health::Health,
+    interpolation::Interpolated,
+    motion::{Drag, Velocity},
+    physics::{Collider, CollisionLayer},
+    quality::EffectsQuality,
+    settings::AccessibilitySettings,
+    status::Statuses,
+};
+
+#[derive(Component, Debug, Default)]
+pub struct Enemy;
+
+#[derive(
+    Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize,
+)]
+pub enum EnemyKind {
+    #[default]
+    Grunt,
+    /// Small and quick, but goes down in one or two hits.
+    Runner,
+    /// Big and slow, and takes a lot of killing.
+    Brute,
+}
+
+impl EnemyKind {
+    pub const ALL: [EnemyKind; 3] = [EnemyKind::Grunt, EnemyKind::Runner, EnemyKind::Brute];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EnemyKind::Grunt => "Grunt",
+            EnemyKind::Runner => "Runner",
+            EnemyKind::Brute => "Brute",
+        }
+    }
+
+    /// Built-in health; see [`crate::defs`] for the one in use.
+    pub fn health(self) -> f32 {
+        match self {
+            EnemyKind::Grunt => ENEMY_HEALTH,
+            EnemyKind::Runner => ENEMY_HEALTH * 0.5,
+            EnemyKind::Brute => ENEMY_HEALTH * 3.,
+        }
+    }
+
+    /// Built-in size, like the other stats here.
+    pub fn size(self) -> f32 {
+        match self {
+            EnemyKind::Grunt => ENEMY_SIZE,
+            EnemyKind::Runner => ENEMY_SIZE * 0.75,
+            EnemyKind::Brute => ENEMY_SIZE * 1.5,
+        }
+    }
+
+    /// What the AI's wander and chase speeds are multiplied by.
+    pub fn speed(self) -> f32 {
+        match self {
+            EnemyKind::Grunt => 1.,
+            EnemyKind::Runner => 1.6,
+            EnemyKind::Brute => 0.6,
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            EnemyKind::Grunt => Color::CRIMSON,
+            EnemyKind::Runner => Color::ORANGE,
+            EnemyKind::Brute => Color::PURPLE,
+        }
+    }
+}
+
+/// Spawn an enemy at `position`, taking `forming` seconds to become active.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SpawnEnemy {
+    pub position: Vec2,
+    pub forming: f32,
+    pub kind: EnemyKind,
+}
+
+impl SpawnEnemy {
+    /// A grunt active right away.
+    pub fn at(position: Vec2) -> Self {
+        Self {
+            position,
+            forming: 0.,
+            kind: EnemyKind::Grunt,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: EnemyKind) -> Self {
+        self.kind = kind;
+        self
+    }
+}
+
+/// Size of a grunt, and roughly of any enemy for spacing and keeping them
+/// inside the arena.
+pub const ENEMY_SIZE: f32 = 20.;
+const ENEMY_HEALTH: f32 = 30.;
+
+/// How killed enemies leave the screen.
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct EnemyDeathConfig {
+    /// Seconds spent shrinking and fading after the killing blow.
+    pub duration: f32,
+    /// Whether a faded "corpse" lingers after the death animation.
+    pub corpse: bool,
+    /// Seconds the corpse takes to fade out completely.
+    pub corpse_duration: f32,
+    /// Opacity the corpse starts fading from.
+    pub corpse_alpha: f32,
+}
+
+impl Default for EnemyDeathConfig {
+    fn default() -> Self {
+        Self {
+            duration: 0.25,
+            corpse: true,
+            corpse_duration: 1.,
+            corpse_alpha: 0.3,
+        }
+    }
+}
+
+impl EnemyDeathConfig {
+    /// Scale and opacity of a dead enemy `elapsed` seconds after it was
+    /// killed, or `None` once it should be gone. Corpses are skipped when
+    /// `corpse` is off or `extras` is false.
+    pub fn look(&self, elapsed: f32, extras: bool) -> Option<(f32, f32)> {
+        const CORPSE_SCALE: f32 = 0.6;
+
+        let corpse = self.corpse && extras;
+        let corpse_alpha = if corpse { self.corpse_alpha } else { 0. };
+        if elapsed < self.duration {
+            let t = elapsed / self.duration;
+            return Some((1. - (1. - CORPSE_SCALE) * t, 1. - (1. - corpse_alpha) * t));
+        }
+
+        let corpse_elapsed = elapsed - self.duration;
+        if !corpse || corpse_elapsed >= self.corpse_duration {
+            return None;
+        }
+        let t = corpse_elapsed / self.corpse_duration;
+        Some((CORPSE_SCALE, corpse_alpha * (1. - t)))
+    }
+}
+
+/// An enemy still emerging, active once `elapsed` reaches `duration`.
+#[derive(Component, Debug)]
+pub struct Forming {
+    elapsed: f32,
+    duration: f32,
+}
+
+/// Opacity and scale a forming enemy starts from.
+const FORMING_ALPHA: f32 = 0.25;
+const FORMING_SCALE: f32 = 0.5;
+
+/// A killed enemy playing its death animation. It has lost its [`Enemy`]
+/// marker and collider, so nothing targets, moves or hits it any more.
+#[derive(Component, Debug, Default)]
+pub struct Dying {
+    elapsed: f32,
+}
+
+/// An enemy spawned apart from the pool, like a boss, despawned once its
+/// death animation ends instead of being kept for reuse.
+#[derive(Component, Debug, Default)]
+pub struct Unpooled;
+
+/// A hidden enemy entity waiting to be reused by the next spawn.
+#[derive(Component, Debug)]
+struct Pooled;
+
+#[derive(Resource, Default)]
+struct EnemyPool(Vec<Entity>);
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<EnemyDeathConfig>()
+            .register_type::<EnemyKind>()
+            .init_resource::<EnemyDeathConfig>()
+            .init_resource::<EnemyPool>()
+            .add_event::<SpawnEnemy>()
+            .add_systems(
+                Update,
+                (start_dying, animate_dying, spawn_enemies, form_enemies).chain(),
+            );
+    }
+}
+
+fn spawn_enemies(
+    mut commands: Commands,
+    defs: Res<Defs>,
+    settings: Res<AccessibilitySettings>,
+    mut requests: EventReader<SpawnEnemy>,
+    mut pool: ResMut<EnemyPool>,
+    mut budget: Budget,
+) {
+    for request in requests.read() {
+        if !budget.admit(&mut commands, BudgetCategory::Enemy) {
+            continue;
+        }
+        let forming = request.forming > 0.;
+        let (alpha, scale) = if forming {
+            (FORMING_ALPHA, FORMING_SCALE)
+        } else {
+            (1., 1.)
+        };
+        let kind = request.kind;
+        let def = defs.enemy(kind);
+        let bundle = (
+            kind,
+            Health::new(def.health),
+            SpriteBundle {
+                transform: Transform::from_translation(request.position.extend(0.))
+                    .with_scale(Vec3::splat(scale)),
+                sprite: Sprite {
+                    color: accessibility::enemy_color(&settings, def).with_a(alpha),
+                    custom_size: Some(Vec2::splat(def.size)),
+                    ..default()
+                },
+                ..default()
+            },
+        );
+        let mut enemy = match pool.0.pop() {
+            Some(entity) => {
+                let mut enemy = commands.entity(entity);
+                enemy.remove::<Pooled>().insert(bundle);
+                enemy
+            }
+            None => commands.spawn(bundle),
+        };
+        if forming {
+            enemy.insert(Forming {
+                elapsed: 0.,
+                duration: request.forming,
+            });
+        } else {
+            activate(&mut enemy, def.size);
+        }
+        if !def.effects.is_empty() {
+            enemy.insert(Statuses::new(def.effects.iter().copied()));
+        }
+        budget.track(&mut enemy, BudgetCategory::Enemy);
+    }
+}
+
+/// Makes a spawned enemy `size` across hittable, targetable and moving.
+fn activate(enemy: &mut EntityCommands, size: f32) {
+    enemy.insert((
+        Enemy,
+        Behavior::default(),
+        Vision::default(),
+        Collider::new(size / 2., CollisionLayer::ENEMY),
+        Interpolated::default(),
+        Velocity::default(),
+        Drag::default(),
+    ));
+}
+
+fn form_enemies(
+    mut commands: Commands,
+    time: Res<Time>,
+    defs: Res<Defs>,
+    mut forming: Query<
+        (
+            Entity,
+            &EnemyKind,
+            &mut Forming,
+            &mut Transform,
+            &mut Sprite,
+        ),
+        Without<Dying>,
+    >,
+) {
+    for (entity, kind, mut form, mut transform, mut sprite) in &mut forming {
+        form.elapsed += time.delta_seconds();
+        let t = (form.elapsed / form.duration).min(1.);
+        transform.scale = Vec3::splat(FORMING_SCALE + (1. - FORMING_SCALE) * t);
+        sprite.color.set_a(FORMING_ALPHA + (1. - FORMING_ALPHA) * t);
+        if t >= 1. {
+            let mut enemy = commands.entity(entity);
+            enemy.remove::<Forming>();
+            activate(&mut enemy, defs.enemy(*kind).size);
+        }
+    }
+}
+
+/// Takes `entity` out of play without it counting as a kill: it plays the
+/// death animation and then returns to the pool.
+pub fn retire(commands: &mut Commands, entity: Entity) {
+    if let Some(mut entity) = commands.get_entity(entity) {
+        entity
+            .remove::<(Enemy, Forming, Collider, Budgeted, Interpolated, Velocity)>()
+            .insert(Dying::default());
+    }
+}
+
+fn start_dying(
+    mut commands: Commands,
+    mut killed: EventReader<EnemyKilled>,
+    sprites: Query<&Sprite>,
+    mut fx: EventWriter<Fx>,
+) {
+    for event in killed.read() {
+        if let Ok(sprite) = sprites.get(event.entity) {
+            fx.send(Fx::Explode {
+                position: event.position,
+                color: sprite.color,
+                size: sprite.custom_size.map_or(16., |size| size.max_element()),
+            });
+        }
+        retire(&mut commands, event.entity);
+    }
+}
+
+fn animate_dying(
+    time: Res<Time>,
+    config: Res<EnemyDeathConfig>,
+    quality: Res<EffectsQuality>,
+    mut despawns: ResMut<DespawnQueue>,
+    mut dying: Query<(
+        Entity,
+        &mut Dying,
+        &mut Transform,
+        &mut Sprite,
+        Has<Unpooled>,
+    )>,
+) {
+    for (entity, mut death, mut transform, mut sprite, unpooled) in &mut dying {
+        death.elapsed += time.delta_seconds();
+        match config.look(death.elapsed, quality.extras()) {
+            Some((scale, alpha)) => {
+                transform.scale = Vec3::splat(scale);
+                sprite.color.set_a(alpha);
+            }
+            None if unpooled => despawns.despawn(entity),
+            None => despawns.return_to_pool(entity, return_to_pool),
+        }
+    }
+}
+
+fn return_to_pool(world: &mut World, entity: Entity) {
+    let mut enemy = world.entity_mut(entity);
+    enemy.remove::<Dying>().insert((Pooled, Visibility::Hidden));
+    world.resource_mut::<EnemyPool>().0.push(entity);
+}
@@ -0,0 +1,171 @@
+//! Gameplay events.
+//!
+//! Gameplay systems emit these at the moments other subsystems (audio, VFX,
+//! achievements, UI) care about, so those subsystems can react without
+//! querying gameplay state directly.
+
+use bevy::prelude::*;
+
+/// Fired once when a player entity has been spawned.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerSpawned {
+    /// The newly spawned player entity.
+    pub entity: Entity,
+    /// World position the player was spawned at.
+    pub position: Vec2,
+}
+
+/// Fired while the player is moving, at most once every
+/// [`PLAYER_MOVED_INTERVAL`] seconds.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerMoved {
+    pub entity: Entity,
+    /// World position at the time the event was sent.
+    pub position: Vec2,
+    /// Distance travelled since the previous `PlayerMoved`.
+    pub distance: f32,
+}
+
+/// Fired when the player starts a dash.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerDashed {
+    pub entity: Entity,
+    /// Normalized direction of the dash.
+    pub direction: Vec2,
+}
+
+/// Fired for every shot the player fires.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerFired {
+    pub entity: Entity,
+    /// World position the shot left from.
+    pub origin: Vec2,
+    /// Normalized direction of the shot.
+    pub direction: Vec2,
+}
+
+/// Fired when the player's weapon in hand changes, by switching or by a
+/// pickup. Charge, heat and cooldown belong to the weapon in hand, so
+/// systems keeping them reset on this.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WeaponSwitched {
+    pub entity: Entity,
+    /// Inventory slot now selected.
+    pub slot: usize,
+}
+
+/// Fired when the player takes damage and survives or dies from it.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerHurt {
+    pub entity: Entity,
+    /// Damage applied by this hit.
+    pub amount: f32,
+    /// Health remaining after the hit (may be zero or below).
+    pub remaining: f32,
+}
+
+/// Fired exactly once when the player's health crosses zero.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerDied {
+    pub entity: Entity,
+    /// World position the player died at.
+    pub position: Vec2,
+}
+
+/// Fired when an enemy is killed by the player.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct EnemyKilled {
+    pub entity: Entity,
+    /// World position the enemy died at.
+    pub position: Vec2,
+}
+
+/// Fired when a new wave begins.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WaveStarted {
+    /// 1-based number of the wave that just started.
+    pub wave: u32,
+}
+
+/// Fired when the last enemy of a wave is gone, before the lull leading to
+/// the next one.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WaveCleared {
+    /// 1-based number of the wave that was cleared.
+    pub wave: u32,
+}
+
+/// Fired whenever the kill combo counter changes, including resets to zero.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ComboChanged {
+    pub combo: u32,
+}
+
+/// Minimum time between two [`PlayerMoved`] events, in seconds.
+pub const PLAYER_MOVED_INTERVAL: f32 = 0.1;
+
+pub struct GameEventsPlugin;
+
+impl Plugin for GameEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerSpawned>()
+            .add_event::<PlayerMoved>()
+            .add_event::<PlayerDashed>()
+            .add_event::<PlayerFired>()
+            .add_event::<WeaponSwitched>()
+            .add_event::<PlayerHurt>()
+            .add_event::<PlayerDied>()
+            .add_event::<EnemyKilled>()
+            .add_event::<WaveStarted>()
+            .add_event::<WaveCleared>()
+            .add_event::<ComboChanged>();
+        app.add_systems(Update, log_events);
+    }
+}
+
+/// Traces the player's events and kills at debug level, for checking what
+/// fires when.
+fn log_events(
+    mut spawned: EventReader<PlayerSpawned>,
+    mut moved: EventReader<PlayerMoved>,
+    mut dashed: EventReader<PlayerDashed>,
+    mut fired: EventReader<PlayerFired>,
+    mut switched: EventReader<WeaponSwitched>,
+    mut hurt: EventReader<PlayerHurt>,
+    mut died: EventReader<PlayerDied>,
+    mut kills: EventReader<EnemyKilled>,
+) {
+    for event in spawned.read() {
+        debug!("{:?} spawned at {}", event.entity, event.position);
+    }
+    for event in moved.read() {
+        debug!(
+            "{:?} moved {} to {}",
+            event.entity, event.distance, event.position
+        );
+    }
+    for event in dashed.read() {
+        debug!("{:?} dashed toward {}", event.entity, event.direction);
+    }
+    for event in fired.read() {
+        debug!(
+            "{:?} fired from {} toward {}",
+            event.entity, event.origin, event.direction
+        );
+    }
+    for event in switched.read() {
+        debug!("{:?} switched to slot {}", event.entity, event.slot);
+    }
+    for event in hurt.read() {
+        debug!(
+            "{:?} took {} damage, {} left",
+            event.entity, event.amount, event.remaining
+        );
+    }
+    for event in died.read() {
+        debug!("{:?} died at {}", event.entity, event.position);
+    }
+    for event in kills.read() {
+        debug!("{:?} killed at {}", event.entity, event.position);
+    }
+}
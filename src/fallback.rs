@@ -0,0 +1,144 @@
+//! Generated stand-ins for images that fail to load.
+//!
+//! Any UI image or sprite whose texture failed to load (a missing file, or a
+//! dropped connection on the web build) is switched to a generated white
+//! shape and a warning is logged, so a single missing file never leaves the
+//! game unplayable. Tints keep working because the shapes are white. Tag an
+//! entity with [`AssetFallback`] to pick the shape; untagged ones get a
+//! square.
+
+use bevy::{
+    asset::LoadState,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    utils::HashSet,
+};
+
+/// Side length of the generated images, in pixels.
+const FALLBACK_SIZE: u32 = 64;
+/// Ring thickness as a fraction of the radius.
+const RING_WIDTH: f32 = 0.12;
+
+/// The shape to draw in place of an entity's image if it fails to load.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AssetFallback {
+    #[default]
+    Square,
+    Circle,
+    Ring,
+}
+
+impl AssetFallback {
+    const ALL: [AssetFallback; 3] = [
+        AssetFallback::Square,
+        AssetFallback::Circle,
+        AssetFallback::Ring,
+    ];
+
+    /// Opacity of the pixel at (`x`, `y`) in a `size`×`size` image.
+    fn coverage(self, x: u32, y: u32, size: u32) -> f32 {
+        let radius = size as f32 / 2.;
+        let offset = Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - Vec2::splat(radius);
+        let distance = offset.length() / radius;
+        // one pixel of antialiasing at each edge
+        let edge = 1. / radius;
+        let inside = |limit: f32| ((limit - distance) / edge).clamp(0., 1.);
+        match self {
+            AssetFallback::Square => 1.,
+            AssetFallback::Circle => inside(1.),
+            AssetFallback::Ring => inside(1.) * (1. - inside(1. - RING_WIDTH)),
+        }
+    }
+
+    fn image(self) -> Image {
+        let mut data = Vec::with_capacity((FALLBACK_SIZE * FALLBACK_SIZE * 4) as usize);
+        for y in 0..FALLBACK_SIZE {
+            for x in 0..FALLBACK_SIZE {
+                let alpha = (self.coverage(x, y, FALLBACK_SIZE) * 255.).round() as u8;
+                data.extend_from_slice(&[255, 255, 255, alpha]);
+            }
+        }
+        Image::new(
+            Extent3d {
+                width: FALLBACK_SIZE,
+                height: FALLBACK_SIZE,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+    }
+}
+
+#[derive(Resource)]
+struct FallbackImages(Vec<(AssetFallback, Handle<Image>)>);
+
+impl FallbackImages {
+    fn get(&self, shape: AssetFallback) -> Handle<Image> {
+        self.0
+            .iter()
+            .find(|(kind, _)| *kind == shape)
+            .map(|(_, handle)| handle.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl FromWorld for FallbackImages {
+    fn from_world(world: &mut World) -> Self {
+        let mut images = world.resource_mut::<Assets<Image>>();
+        Self(
+            AssetFallback::ALL
+                .into_iter()
+                .map(|shape| (shape, images.add(shape.image())))
+                .collect(),
+        )
+    }
+}
+
+pub struct FallbackPlugin;
+
+impl Plugin for FallbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FallbackImages>()
+            .add_systems(PostUpdate, substitute_failed_images);
+    }
+}
+
+fn failed(asset_server: &AssetServer, handle: &Handle<Image>) -> bool {
+    matches!(asset_server.get_load_state(handle), Some(LoadState::Failed))
+}
+
+fn substitute_failed_images(
+    asset_server: Res<AssetServer>,
+    fallbacks: Res<FallbackImages>,
+    mut warned: Local<HashSet<AssetId<Image>>>,
+    mut ui_images: Query<(&mut UiImage, Option<&AssetFallback>)>,
+    mut sprites: Query<(&mut Handle<Image>, Option<&AssetFallback>), With<Sprite>>,
+) {
+    let mut warn_once = |handle: &Handle<Image>| {
+        if warned.insert(handle.id()) {
+            let path = handle
+                .path()
+                .map_or_else(|| "an image".to_string(), |path| format!("`{path}`"));
+            warn!("failed to load {path}, drawing a placeholder instead");
+        }
+    };
+
+    for (mut image, shape) in &mut ui_images {
+        if failed(&asset_server, &image.texture) {
+            warn_once(&image.texture);
+            image.texture = fallbacks.get(shape.copied().unwrap_or_default());
+        }
+    }
+    for (mut texture, shape) in &mut sprites {
+        if failed(&asset_server, &texture) {
+            warn_once(&texture);
+            *texture = fallbacks.get(shape.copied().unwrap_or_default());
+        }
+    }
+}
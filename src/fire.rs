@@ -0,0 +1,208 @@
+//! Firing the weapon in hand.
+//!
+//! The player fires while [`Action::Shoot`] is held, or the analog trigger
+//! is squeezed, at the cadence [`fire_interval`] gives for the weapon and
+//! the [`fire_intensity`]; with [`AccessibilitySettings::auto_fire`] on it
+//! fires whenever there is something to aim at. Shots leave from the tip
+//! of the nose along the aim of [`TargetLock::aim`], falling back on where
+//! the player faces. Each trigger pull uses one round of the slot's ammo,
+//! scatters its pellets with the weapon's [`Spread`](crate::weapon::Spread)
+//! and sends [`PlayerFired`]. Weapons with an arc lob their shot at the
+//! point the look stick picks instead, and beam weapons fire a beam along
+//! each pellet's direction.
+//!
+//! The time until the next pull is kept in the player's [`FireCooldown`],
+//! cleared on [`WeaponSwitched`]. Rapid fire from a
+//! [power-up](crate::pickups) and the fire rate
+//! [upgrade](crate::progression) multiply the weapon's fire rate.
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    abilities::Rewinding,
+    budget::Budget,
+    camera::CameraView,
+    config::GameConfig,
+    events::{PlayerFired, WeaponSwitched},
+    health::Health,
+    lock::{LockConfig, TargetLock},
+    net::is_client,
+    pickups::{stat_factor, PowerUp, StatModifiers},
+    progression::{fire_rate_factor, UpgradeModifiers},
+    projectile::{
+        shot_modifiers, spawn_beam, spawn_lobbed, spawn_projectile, ModifierBoost, ShotPools,
+    },
+    rng::GameRng,
+    settings::AccessibilitySettings,
+    state::GameState,
+    trigger::{fire_intensity, fire_interval, TriggerPressure},
+    weapon::{ArcConfig, Weapon, WeaponInventory},
+    Action, Player,
+};
+
+/// Seconds until the player's weapon may fire again.
+#[derive(Component, Reflect, Debug, Clone, Copy, Default)]
+pub struct FireCooldown {
+    pub remaining: f32,
+}
+
+pub struct FirePlugin;
+
+impl Plugin for FirePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<FireCooldown>().add_systems(
+            Update,
+            (reset_cooldown, fire_weapon)
+                .chain()
+                .run_if(in_state(GameState::Playing))
+                // a co-op client's shots are fired by the host
+                .run_if(not(is_client)),
+        );
+    }
+}
+
+fn reset_cooldown(
+    mut switched: EventReader<WeaponSwitched>,
+    mut cooldowns: Query<&mut FireCooldown>,
+) {
+    for event in switched.read() {
+        if let Ok(mut cooldown) = cooldowns.get_mut(event.entity) {
+            cooldown.remaining = 0.;
+        }
+    }
+}
+
+fn fire_weapon(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    pressure: Res<TriggerPressure>,
+    accessibility: Res<AccessibilitySettings>,
+    lock: Res<TargetLock>,
+    lock_config: Res<LockConfig>,
+    view: Res<CameraView>,
+    mut rng: ResMut<GameRng>,
+    mut budget: Budget,
+    mut pools: ShotPools,
+    mut players: Query<
+        (
+            Entity,
+            &Transform,
+            &ActionState<Action>,
+            &Weapon,
+            &Health,
+            &mut WeaponInventory,
+            &mut FireCooldown,
+            Option<&ModifierBoost>,
+            Option<&StatModifiers>,
+            Option<&UpgradeModifiers>,
+        ),
+        (With<Player>, Without<Rewinding>),
+    >,
+    mut fired: EventWriter<PlayerFired>,
+) {
+    let Ok((
+        entity,
+        transform,
+        action_state,
+        weapon,
+        health,
+        mut inventory,
+        mut cooldown,
+        boost,
+        modifiers,
+        upgrades,
+    )) = players.get_single_mut()
+    else {
+        return;
+    };
+    cooldown.remaining = (cooldown.remaining - time.delta_seconds()).max(0.);
+    if health.is_dead() {
+        return;
+    }
+
+    let stick = |action| {
+        action_state
+            .clamped_axis_pair(&action)
+            .map(|axis| view.to_world(axis.xy()))
+            .unwrap_or_default()
+    };
+    let look = stick(Action::Look);
+    let position = transform.translation.truncate();
+    let aim = lock.aim(&lock_config, position, look);
+
+    let mut intensity = fire_intensity(action_state.pressed(&Action::Shoot), pressure.0);
+    if accessibility.auto_fire && aim.is_some() {
+        intensity = intensity.max(1.);
+    }
+    let fire_rate =
+        weapon.fire_rate * stat_factor(modifiers, PowerUp::RapidFire) * fire_rate_factor(upgrades);
+    let Some(interval) = fire_interval(fire_rate, intensity) else {
+        return;
+    };
+    if cooldown.remaining > 0. || !inventory.spend_ammo(1) {
+        return;
+    }
+    cooldown.remaining = interval;
+
+    let facing = (transform.rotation * Vec3::X).truncate();
+    let direction = aim.unwrap_or(facing);
+    let origin = position + direction * config.player.nose_tip();
+    fired.send(PlayerFired {
+        entity,
+        origin,
+        direction,
+    });
+
+    if let Some(arc) = weapon.arc {
+        // the stick picks the distance; without one, lob at full range
+        let reach = if look == Vec2::ZERO { direction } else { look };
+        let target = ArcConfig::landing_point(origin, reach, weapon.range());
+        let down = view.to_world(Vec2::NEG_Y);
+        spawn_lobbed(
+            &mut commands,
+            &mut budget,
+            &mut pools,
+            weapon,
+            arc,
+            origin,
+            target,
+            down,
+        );
+        return;
+    }
+
+    let modifiers = shot_modifiers(weapon, boost);
+    let speed_fraction = stick(Action::Move).length();
+    for shot in weapon
+        .spread
+        .shot_directions(direction, speed_fraction, &mut rng)
+    {
+        match weapon.beam {
+            Some(beam) => {
+                spawn_beam(
+                    &mut commands,
+                    &mut budget,
+                    &mut pools,
+                    weapon,
+                    beam,
+                    modifiers,
+                    origin,
+                    shot,
+                );
+            }
+            None => {
+                spawn_projectile(
+                    &mut commands,
+                    &mut budget,
+                    &mut pools,
+                    weapon,
+                    modifiers,
+                    origin,
+                    shot,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Numbers and words that pop up in the world and drift away.
+//!
+//! Anything can send a [`FloatingText`]; damage to enemies, pickups being
+//! collected and every [`COMBO_MILESTONE`]th kill of a combo send their
+//! own. Hits on the same enemy in one frame, like a shotgun's pellets, show
+//! as one number. A text rises for [`LIFETIME`] seconds, fading out over
+//! the second half. It keeps the same size on screen however far the
+//! camera is zoomed, and stays upright when the view turns.
+//!
+//! The texts are pooled: a finished one is hidden and reused, and at most
+//! [`MAX_TEXTS`] show at once, with new ones skipped beyond that.
+
+use bevy::prelude::*;
+
+use crate::{
+    camera::{CameraView, MainCamera},
+    config::Palette,
+    enemy::Enemy,
+    events::ComboChanged,
+    fonts::UiFonts,
+    health::DamageEvent,
+    Player,
+};
+
+const MAX_TEXTS: usize = 48;
+/// Seconds a text shows for.
+const LIFETIME: f32 = 0.8;
+/// How fast a text rises, in pixels a second on screen.
+const RISE_SPEED: f32 = 40.;
+/// Above enemies and the player.
+const TEXT_Z: f32 = 10.;
+const DAMAGE_SIZE: f32 = 14.;
+/// Combos of this many kills, and multiples of it, get called out.
+const COMBO_MILESTONE: u32 = 10;
+
+/// Show `text` at `position` for a moment.
+#[derive(Event, Debug, Clone)]
+pub struct FloatingText {
+    pub position: Vec2,
+    pub text: String,
+    pub color: Color,
+    pub font_size: f32,
+}
+
+impl FloatingText {
+    pub fn new(position: Vec2, text: impl Into<String>) -> Self {
+        Self {
+            position,
+            text: text.into(),
+            color: Color::WHITE,
+            font_size: 16.,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+}
+
+/// A text showing, `elapsed` seconds after it appeared at `origin`.
+#[derive(Component, Debug)]
+struct Floater {
+    origin: Vec2,
+    elapsed: f32,
+    color: Color,
+}
+
+/// A hidden text waiting for the next [`FloatingText`].
+#[derive(Component, Debug)]
+struct Pooled;
+
+#[derive(Resource, Default)]
+struct FloaterPool(Vec<Entity>);
+
+pub struct FloatingTextPlugin;
+
+impl Plugin for FloatingTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FloaterPool>()
+            .add_event::<FloatingText>()
+            .add_systems(
+                Update,
+                (
+                    (damage_numbers, combo_milestones),
+                    spawn_texts,
+                    animate_texts,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn damage_numbers(
+    mut damage: EventReader<DamageEvent>,
+    enemies: Query<&Transform, With<Enemy>>,
+    mut texts: EventWriter<FloatingText>,
+) {
+    let mut hits: Vec<(Entity, f32)> = Vec::new();
+    for event in damage.read() {
+        match hits.iter_mut().find(|(target, _)| *target == event.target) {
+            Some((_, amount)) => *amount += event.amount,
+            None => hits.push((event.target, event.amount)),
+        }
+    }
+    for (target, amount) in hits {
+        if let Ok(transform) = enemies.get(target) {
+            texts.send(
+                FloatingText::new(transform.translation.truncate(), format!("{amount:.0}"))
+                    .with_size(DAMAGE_SIZE),
+            );
+        }
+    }
+}
+
+fn combo_milestones(
+    palette: Res<Palette>,
+    mut combos: EventReader<ComboChanged>,
+    players: Query<&Transform, With<Player>>,
+    mut texts: EventWriter<FloatingText>,
+) {
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+    for ComboChanged { combo } in combos.read() {
+        if *combo > 0 && combo % COMBO_MILESTONE == 0 {
+            texts.send(
+                FloatingText::new(player.translation.truncate(), format!("{combo} combo!"))
+                    .with_color(palette.accent)
+                    .with_size(22.),
+            );
+        }
+    }
+}
+
+fn spawn_texts(
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    mut pool: ResMut<FloaterPool>,
+    mut requests: EventReader<FloatingText>,
+    floaters: Query<(), With<Floater>>,
+) {
+    let mut showing = floaters.iter().len();
+    for request in requests.read() {
+        if showing >= MAX_TEXTS {
+            continue;
+        }
+        showing += 1;
+        let bundle = (
+            Floater {
+                origin: request.position,
+                elapsed: 0.,
+                color: request.color,
+            },
+            Text2dBundle {
+                text: Text::from_section(
+                    request.text.clone(),
+                    fonts.bold(request.font_size, request.color),
+                ),
+                transform: Transform::from_translation(request.position.extend(TEXT_Z)),
+                ..default()
+            },
+        );
+        match pool.0.pop() {
+            Some(entity) => {
+                commands.entity(entity).remove::<Pooled>().insert(bundle);
+            }
+            None => {
+                commands.spawn(bundle);
+            }
+        }
+    }
+}
+
+fn animate_texts(
+    mut commands: Commands,
+    time: Res<Time>,
+    view: Res<CameraView>,
+    mut pool: ResMut<FloaterPool>,
+    cameras: Query<&OrthographicProjection, With<MainCamera>>,
+    mut floaters: Query<(
+        Entity,
+        &mut Floater,
+        &mut Transform,
+        &mut Text,
+        &mut Visibility,
+    )>,
+) {
+    let zoom = cameras
+        .get_single()
+        .map_or(1., |projection| projection.scale);
+    let up = view.to_world(Vec2::Y);
+    for (entity, mut floater, mut transform, mut text, mut visibility) in &mut floaters {
+        floater.elapsed += time.delta_seconds();
+        if floater.elapsed >= LIFETIME {
+            *visibility = Visibility::Hidden;
+            commands.entity(entity).remove::<Floater>().insert(Pooled);
+            pool.0.push(entity);
+            continue;
+        }
+        let rise = up * RISE_SPEED * floater.elapsed * zoom;
+        transform.translation = (floater.origin + rise).extend(TEXT_Z);
+        transform.rotation = Quat::from_rotation_z(view.rotation);
+        transform.scale = Vec3::splat(zoom);
+        let fade = ((LIFETIME - floater.elapsed) / (LIFETIME / 2.)).min(1.);
+        for section in &mut text.sections {
+            section.style.color = floater.color.with_a(floater.color.a() * fade);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Fonts and text size for every piece of UI text.
+//!
+//! [`UiFonts`] holds a regular and a bold font for the
+//! [`UiFontFace`] picked in [`AccessibilitySettings::font`], loaded before
+//! anything is spawned. Text spawners build their styles with
+//! [`UiFonts::style`] and [`UiFonts::bold`]. Picking another face, or a font
+//! failing to load, updates the handles and every text already on screen;
+//! a missing font falls back to bevy's built-in one, with a warning.
+//!
+//! Font sizes are written for a [`REFERENCE_SIZE`] viewport and scaled with
+//! the window's shorter side, within [`MIN_TEXT_SCALE`] and
+//! [`MAX_TEXT_SCALE`], so text stays legible on a phone without looking
+//! tiny on a desktop monitor.
+
+use bevy::{asset::LoadState, prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::AccessibilitySettings;
+
+/// Shorter window side, in logical pixels, that font sizes are written for.
+pub const REFERENCE_SIZE: f32 = 600.;
+pub const MIN_TEXT_SCALE: f32 = 0.85;
+pub const MAX_TEXT_SCALE: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum UiFontFace {
+    /// DejaVu Sans.
+    #[default]
+    Standard,
+    /// DejaVu Sans Mono: wider letters, and no two characters alike, such
+    /// as `0` and `O` or `1`, `l` and `I`.
+    Legible,
+}
+
+impl UiFontFace {
+    pub fn name(self) -> &'static str {
+        match self {
+            UiFontFace::Standard => "Standard",
+            UiFontFace::Legible => "High legibility",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            UiFontFace::Standard => UiFontFace::Legible,
+            UiFontFace::Legible => UiFontFace::Standard,
+        }
+    }
+
+    /// Asset paths of the regular and bold fonts.
+    fn paths(self) -> (&'static str, &'static str) {
+        match self {
+            UiFontFace::Standard => ("fonts/DejaVuSans.ttf", "fonts/DejaVuSans-Bold.ttf"),
+            UiFontFace::Legible => ("fonts/DejaVuSansMono.ttf", "fonts/DejaVuSansMono-Bold.ttf"),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct UiFonts {
+    pub face: UiFontFace,
+    pub regular: Handle<Font>,
+    pub bold: Handle<Font>,
+}
+
+impl UiFonts {
+    fn load(asset_server: &AssetServer, face: UiFontFace) -> Self {
+        let (regular, bold) = face.paths();
+        Self {
+            face,
+            regular: asset_server.load(regular),
+            bold: asset_server.load(bold),
+        }
+    }
+
+    pub fn style(&self, font_size: f32, color: Color) -> TextStyle {
+        TextStyle {
+            font: self.regular.clone(),
+            font_size,
+            color,
+        }
+    }
+
+    /// For titles and buttons.
+    pub fn bold(&self, font_size: f32, color: Color) -> TextStyle {
+        TextStyle {
+            font: self.bold.clone(),
+            font_size,
+            color,
+        }
+    }
+}
+
+/// Scale for font sizes in a window of `size` logical pixels.
+pub fn text_scale(size: Vec2) -> f32 {
+    (size.min_element() / REFERENCE_SIZE).clamp(MIN_TEXT_SCALE, MAX_TEXT_SCALE)
+}
+
+/// The font sizes a text was spawned with, before scaling.
+#[derive(Component, Debug)]
+struct BaseFontSizes(Vec<f32>);
+
+pub struct FontsPlugin;
+
+impl Plugin for FontsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreStartup, load_fonts).add_systems(
+            Update,
+            (
+                (switch_face, fall_back_on_failed_fonts, retarget_text).chain(),
+                scale_text,
+            ),
+        );
+    }
+}
+
+fn load_fonts(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<AccessibilitySettings>,
+) {
+    commands.insert_resource(UiFonts::load(&asset_server, settings.font));
+}
+
+fn switch_face(
+    asset_server: Res<AssetServer>,
+    settings: Res<AccessibilitySettings>,
+    mut fonts: ResMut<UiFonts>,
+) {
+    if settings.is_changed() && settings.font != fonts.face {
+        *fonts = UiFonts::load(&asset_server, settings.font);
+    }
+}
+
+fn fall_back_on_failed_fonts(asset_server: Res<AssetServer>, mut fonts: ResMut<UiFonts>) {
+    let failed = |handle: &Handle<Font>| {
+        matches!(asset_server.get_load_state(handle), Some(LoadState::Failed))
+    };
+    if !failed(&fonts.regular) && !failed(&fonts.bold) {
+        return;
+    }
+    let fonts = &mut *fonts;
+    for handle in [&mut fonts.regular, &mut fonts.bold] {
+        if failed(handle) {
+            let path = handle
+                .path()
+                .map_or_else(|| "a font".to_string(), |path| format!("`{path}`"));
+            warn!("failed to load {path}, using the built-in font instead");
+            *handle = Handle::default();
+        }
+    }
+}
+
+/// Moves text spawned with the previous fonts onto the current ones.
+fn retarget_text(
+    fonts: Res<UiFonts>,
+    mut previous: Local<Option<UiFonts>>,
+    mut texts: Query<&mut Text>,
+) {
+    if !fonts.is_changed() {
+        return;
+    }
+    let Some(old) = previous.replace(fonts.clone()) else {
+        return;
+    };
+    if old == *fonts {
+        return;
+    }
+    for mut text in &mut texts {
+        for section in &mut text.sections {
+            let font = &mut section.style.font;
+            if *font == old.bold {
+                *font = fonts.bold.clone();
+            } else if *font == old.regular {
+                *font = fonts.regular.clone();
+            }
+        }
+    }
+}
+
+fn scale_text(
+    mut commands: Commands,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut current: Local<Option<f32>>,
+    mut texts: Query<(Entity, &mut Text, Option<&BaseFontSizes>)>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let scale = text_scale(Vec2::new(window.width(), window.height()));
+    let rescale = *current != Some(scale);
+    *current = Some(scale);
+
+    for (entity, mut text, base) in &mut texts {
+        let sizes = match base {
+            Some(base) if rescale => base.0.clone(),
+            Some(_) => continue,
+            None => {
+                let sizes: Vec<f32> = text.sections.iter().map(|s| s.style.font_size).collect();
+                commands.entity(entity).insert(BaseFontSizes(sizes.clone()));
+                sizes
+            }
+        };
+        for (section, size) in text.sections.iter_mut().zip(sizes) {
+            section.style.font_size = size * scale;
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Free-flying camera for debugging. Debug builds only.
+//!
+//! F6 detaches the camera from the player; F6 again hands it back to the
+//! follow system, which eases it home. While detached, drag with the left
+//! mouse button or use T/F/G/H to pan, and the mouse wheel or +/- to zoom.
+//! Home snaps straight back onto the player and re-attaches. Panning
+//! deliberately avoids WASD and the arrows so the player isn't steered at
+//! the same time. Only the camera is touched; gameplay carries on as usual.
+//! It is left alone in photo mode, which detaches the camera itself.
+
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+};
+
+use crate::{
+    camera::{pan_and_zoom, Detached, MainCamera},
+    fonts::UiFonts,
+    state::GameState,
+    Player,
+};
+
+const TOGGLE_KEY: KeyCode = KeyCode::F6;
+const SNAP_KEY: KeyCode = KeyCode::Home;
+/// Pan speed with the keys, in pixels per second at a zoom of 1.
+const PAN_SPEED: f32 = 600.;
+/// Zoom change per wheel notch or per second of a held key.
+const ZOOM_STEP: f32 = 0.1;
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 8.;
+
+#[derive(Component)]
+struct FreeCamIndicator;
+
+pub struct FreeCameraPlugin;
+
+impl Plugin for FreeCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_indicator).add_systems(
+            Update,
+            (toggle_free_camera, fly_camera, show_indicator)
+                .chain()
+                .run_if(not(in_state(GameState::PhotoMode))),
+        );
+    }
+}
+
+fn spawn_indicator(mut commands: Commands, fonts: Res<UiFonts>) {
+    commands.spawn((
+        FreeCamIndicator,
+        TextBundle {
+            text: Text::from_section("FREE CAM", fonts.style(16., Color::YELLOW)),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(12.),
+                left: Val::Px(12.),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(30),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_free_camera(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    players: Query<&Transform, With<Player>>,
+    mut cameras: Query<
+        (Entity, &mut Transform, Has<Detached>),
+        (With<MainCamera>, Without<Player>),
+    >,
+) {
+    let toggle = keys.just_pressed(TOGGLE_KEY);
+    let snap = keys.just_pressed(SNAP_KEY);
+    if !toggle && !snap {
+        return;
+    }
+
+    for (entity, mut transform, detached) in &mut cameras {
+        if snap || detached {
+            if snap {
+                if let Ok(player) = players.get_single() {
+                    transform.translation.x = player.translation.x;
+                    transform.translation.y = player.translation.y;
+                }
+            }
+            // the regular zoom takes over again once attached
+            commands.entity(entity).remove::<Detached>();
+        } else {
+            commands.entity(entity).insert(Detached);
+        }
+    }
+}
+
+fn fly_camera(
+    time: Res<Time<Real>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<Detached>>,
+) {
+    let dt = time.delta_seconds();
+    let dragged: Vec2 = motion.read().map(|event| event.delta).sum();
+    let notches: f32 = wheel.read().map(|event| event.y.signum()).sum();
+
+    let mut pan = Vec2::ZERO;
+    for (key, direction) in [
+        (KeyCode::KeyT, Vec2::Y),
+        (KeyCode::KeyG, Vec2::NEG_Y),
+        (KeyCode::KeyF, Vec2::NEG_X),
+        (KeyCode::KeyH, Vec2::X),
+    ] {
+        if keys.pressed(key) {
+            pan += direction;
+        }
+    }
+    let mut zoom = -notches * ZOOM_STEP;
+    if keys.pressed(KeyCode::Equal) {
+        zoom -= ZOOM_STEP * 10. * dt;
+    }
+    if keys.pressed(KeyCode::Minus) {
+        zoom += ZOOM_STEP * 10. * dt;
+    }
+
+    for (mut transform, mut projection) in &mut cameras {
+        let mut offset = pan * PAN_SPEED * dt;
+        if buttons.pressed(MouseButton::Left) {
+            // drag the world along with the cursor; screen y grows downward
+            offset += Vec2::new(-dragged.x, dragged.y);
+        }
+        pan_and_zoom(
+            &mut transform,
+            &mut projection,
+            offset,
+            zoom,
+            (MIN_ZOOM, MAX_ZOOM),
+        );
+    }
+}
+
+fn show_indicator(
+    cameras: Query<Has<Detached>, With<MainCamera>>,
+    mut indicators: Query<&mut Visibility, With<FreeCamIndicator>>,
+) {
+    let detached = cameras.iter().any(|detached| detached);
+    for mut visibility in &mut indicators {
+        let wanted = if detached {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+}
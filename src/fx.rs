@@ -0,0 +1,224 @@
+//! Reusable visual effects, each started by one [`Fx`] event.
+//!
+//! Gameplay says what happened and where; how it looks lives here:
+//!
+//! - [`Fx::HitFlash`] flashes a sprite white for [`HIT_FLASH_DURATION`].
+//!   Only its red, green and blue are flashed, so fades and blinks on the
+//!   alpha carry on underneath. Damage sends one for every hit. With
+//!   [`AccessibilitySettings::reduce_motion`] on it only goes part of the
+//!   way to white.
+//! - [`Fx::Explode`] bursts a sprite into square fragments of its color,
+//!   flying out and tumbling down. Killed enemies send one.
+//! - [`Fx::MuzzleFlash`] sprays a few sparks out of a muzzle, with every
+//!   shot the player fires.
+//! - [`Fx::Dust`] kicks up a puff behind something setting off, like the
+//!   player dashing.
+//!
+//! The fragments, sparks and dust are [particles](crate::particles), so
+//! they come out of the shared pool and the effects budget, and there are
+//! fewer of them at lower [`EffectsQuality`].
+
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::{
+    events::{PlayerDashed, PlayerFired},
+    particles::{EmitParticles, ParticleEffect},
+    quality::EffectsQuality,
+    settings::AccessibilitySettings,
+    tween::{lerp_color, Ease},
+};
+
+/// Seconds a hit sprite takes to fade back from white.
+pub const HIT_FLASH_DURATION: f32 = 0.12;
+/// How far towards white a hit flash goes with reduced motion.
+const REDUCED_FLASH: f32 = 0.35;
+/// Fragments of an explosion at full quality.
+const FRAGMENTS: usize = 12;
+/// Share of the exploding sprite's size one fragment is.
+const FRAGMENT_SIZE: f32 = 0.25;
+const FRAGMENT_SPEED: f32 = 140.;
+const SPARKS: usize = 4;
+const DUST: usize = 6;
+/// Over the ground, under whoever kicked it up.
+const DUST_Z: f32 = -0.4;
+/// Over the fighters.
+const SPARK_Z: f32 = 1.;
+
+/// Play an effect.
+#[derive(Event, Debug, Clone, Copy)]
+pub enum Fx {
+    HitFlash {
+        target: Entity,
+    },
+    Explode {
+        position: Vec2,
+        color: Color,
+        /// Size of what exploded.
+        size: f32,
+    },
+    MuzzleFlash {
+        position: Vec2,
+        /// Normalized direction of the shot.
+        direction: Vec2,
+    },
+    Dust {
+        position: Vec2,
+        /// Normalized direction of the move kicking it up.
+        direction: Vec2,
+    },
+}
+
+/// A sprite fading back to `color` after a hit.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct HitFlash {
+    pub remaining: f32,
+    pub color: Color,
+}
+
+pub struct FxPlugin;
+
+impl Plugin for FxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Fx>().add_systems(
+            Update,
+            ((muzzle_flashes, dash_dust), play_fx, flash_hits).chain(),
+        );
+    }
+}
+
+fn muzzle_flashes(mut fired: EventReader<PlayerFired>, mut fx: EventWriter<Fx>) {
+    for shot in fired.read() {
+        fx.send(Fx::MuzzleFlash {
+            position: shot.origin,
+            direction: shot.direction,
+        });
+    }
+}
+
+fn dash_dust(
+    mut dashed: EventReader<PlayerDashed>,
+    players: Query<&Transform>,
+    mut fx: EventWriter<Fx>,
+) {
+    for dash in dashed.read() {
+        if let Ok(transform) = players.get(dash.entity) {
+            fx.send(Fx::Dust {
+                position: transform.translation.truncate(),
+                direction: dash.direction,
+            });
+        }
+    }
+}
+
+fn play_fx(
+    mut commands: Commands,
+    quality: Res<EffectsQuality>,
+    mut requests: EventReader<Fx>,
+    sprites: Query<(&Sprite, Option<&HitFlash>)>,
+    mut particles: EventWriter<EmitParticles>,
+) {
+    // at least one of each, whatever the quality
+    let count = |base| quality.scale_count(base).max(1) as u32;
+    for fx in requests.read() {
+        match *fx {
+            Fx::HitFlash { target } => {
+                let Ok((sprite, flash)) = sprites.get(target) else {
+                    continue;
+                };
+                // a second hit mid-flash keeps the color from before the first
+                let color = flash.map_or(sprite.color, |flash| flash.color);
+                commands.entity(target).insert(HitFlash {
+                    remaining: HIT_FLASH_DURATION,
+                    color,
+                });
+            }
+            Fx::Explode {
+                position,
+                color,
+                size,
+            } => {
+                particles.send(EmitParticles {
+                    effect: ParticleEffect {
+                        lifetime: 0.6,
+                        size: size * FRAGMENT_SIZE,
+                        velocity: Vec2::X * FRAGMENT_SPEED,
+                        spread: PI,
+                        gravity: Vec2::NEG_Y * 200.,
+                        start_color: color.with_a(1.),
+                        end_color: color.with_a(0.),
+                        end_scale: 0.3,
+                        ease: Ease::QuadOut,
+                        ..default()
+                    },
+                    position,
+                    count: count(FRAGMENTS),
+                });
+            }
+            Fx::MuzzleFlash {
+                position,
+                direction,
+            } => {
+                particles.send(EmitParticles {
+                    effect: ParticleEffect {
+                        lifetime: 0.08,
+                        size: 3.,
+                        velocity: direction * 260.,
+                        spread: 0.4,
+                        start_color: Color::rgb(1., 0.95, 0.7),
+                        end_color: Color::rgba(1., 0.6, 0.2, 0.),
+                        end_scale: 0.5,
+                        z: SPARK_Z,
+                        ..default()
+                    },
+                    position,
+                    count: count(SPARKS),
+                });
+            }
+            Fx::Dust {
+                position,
+                direction,
+            } => {
+                particles.send(EmitParticles {
+                    effect: ParticleEffect {
+                        lifetime: 0.4,
+                        size: 6.,
+                        velocity: -direction * 60.,
+                        spread: 0.9,
+                        start_color: Color::rgba(0.7, 0.7, 0.65, 0.5),
+                        end_color: Color::rgba(0.7, 0.7, 0.65, 0.),
+                        end_scale: 2.,
+                        ease: Ease::QuadOut,
+                        z: DUST_Z,
+                        ..default()
+                    },
+                    position,
+                    count: count(DUST),
+                });
+            }
+        }
+    }
+}
+
+fn flash_hits(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<AccessibilitySettings>,
+    mut flashes: Query<(Entity, &mut HitFlash, &mut Sprite)>,
+) {
+    for (entity, mut flash, mut sprite) in &mut flashes {
+        flash.remaining -= time.delta_seconds();
+        let t = (1. - flash.remaining / HIT_FLASH_DURATION).clamp(0., 1.);
+        let peak = if settings.reduce_motion {
+            lerp_color(flash.color, Color::WHITE, REDUCED_FLASH)
+        } else {
+            Color::WHITE
+        };
+        let alpha = sprite.color.a();
+        sprite.color = lerp_color(peak, flash.color, t).with_a(alpha);
+        if flash.remaining <= 0. {
+            commands.entity(entity).remove::<HitFlash>();
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Shared gameplay time-scale.
+//!
+//! [`GameTime::scale`] drives Bevy's virtual clock, so every system timed
+//! with `Res<Time>` in `Update` or `FixedUpdate` (movement, spawn timers,
+//! rain and other effects) slows down, speeds up or freezes together.
+//! Systems that must keep running in real time, like UI animations, read
+//! `Res<Time<Real>>` instead.
+//!
+//! Effects should advance by `time.delta_seconds()` and spawn from timers
+//! ticked with `time.delta()` rather than per frame, so their density and
+//! speed are the same at any frame rate.
+//!
+//! A [`HitStop`] event freezes gameplay for a moment on top of the scale, to
+//! make a big impact land. Stops queued back to back add up to at most
+//! [`HitStopConfig::max_total`], and none starts within
+//! [`HitStopConfig::cooldown`] of the last one ending, so a burst of hits
+//! can't stall the game. With [`AccessibilitySettings::reduce_motion`] on
+//! there are no hit-stops at all.
+//!
+//! While the app is [suspended](crate::lifecycle) the clock is frozen too,
+//! so `FixedUpdate` has no backlog to run through on resume, and in
+//! [photo mode](crate::photo), so the rain and particles hold still, and
+//! while a [cutscene](crate::cutscene) plays.
+
+use bevy::prelude::*;
+
+use crate::{
+    cutscene::CutscenePlayer, lifecycle::AppSuspended, settings::AccessibilitySettings,
+    state::GameState,
+};
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct GameTime {
+    /// `1.` is normal speed, `0.` freezes gameplay and effects.
+    pub scale: f32,
+}
+
+impl Default for GameTime {
+    fn default() -> Self {
+        Self { scale: 1. }
+    }
+}
+
+/// Freeze gameplay for `duration` real seconds.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct HitStop {
+    pub duration: f32,
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct HitStopConfig {
+    /// Stop when a cluster shot bursts.
+    pub explosion: f32,
+    /// Most seconds of stop that can be queued at once.
+    pub max_total: f32,
+    /// Seconds after a stop ends during which new ones are ignored.
+    pub cooldown: f32,
+}
+
+impl Default for HitStopConfig {
+    fn default() -> Self {
+        Self {
+            explosion: 0.05,
+            max_total: 0.12,
+            cooldown: 0.2,
+        }
+    }
+}
+
+/// Real seconds of the current stop left, and since the last one ended.
+#[derive(Resource, Debug, Default)]
+struct HitStopTimer {
+    remaining: f32,
+    since_end: f32,
+}
+
+pub struct GameTimePlugin;
+
+impl Plugin for GameTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<GameTime>()
+            .register_type::<HitStopConfig>()
+            .init_resource::<GameTime>()
+            .init_resource::<HitStopConfig>()
+            .init_resource::<HitStopTimer>()
+            .add_event::<HitStop>()
+            .add_systems(PreUpdate, (update_hit_stop, apply_time_scale).chain());
+    }
+}
+
+fn update_hit_stop(
+    time: Res<Time<Real>>,
+    config: Res<HitStopConfig>,
+    settings: Res<AccessibilitySettings>,
+    mut stops: EventReader<HitStop>,
+    mut timer: ResMut<HitStopTimer>,
+) {
+    let dt = time.delta_seconds();
+    if timer.remaining > 0. {
+        timer.remaining = (timer.remaining - dt).max(0.);
+    } else {
+        timer.since_end += dt;
+    }
+
+    let requested: f32 = stops.read().map(|stop| stop.duration.max(0.)).sum();
+    if settings.reduce_motion {
+        timer.remaining = 0.;
+        return;
+    }
+    let stopping = timer.remaining > 0.;
+    if requested > 0. && (stopping || timer.since_end >= config.cooldown) {
+        timer.remaining = (timer.remaining + requested).min(config.max_total);
+        timer.since_end = 0.;
+    }
+}
+
+fn apply_time_scale(
+    game_time: Res<GameTime>,
+    timer: Res<HitStopTimer>,
+    suspended: Res<AppSuspended>,
+    cutscene: Res<CutscenePlayer>,
+    state: Res<State<GameState>>,
+    mut applied: Local<Option<f32>>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    let photo = *state.get() == GameState::PhotoMode;
+    let scale = if timer.remaining > 0. || suspended.0 || photo || cutscene.is_playing() {
+        0.
+    } else {
+        game_time.scale
+    };
+    if *applied == Some(scale) {
+        return;
+    }
+    *applied = Some(scale);
+
+    if scale <= 0. {
+        time.pause();
+    } else {
+        time.unpause();
+        time.set_relative_speed(scale);
+    }
+}
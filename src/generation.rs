@@ -0,0 +1,238 @@
+//! Generated arenas for endless play.
+//!
+//! With [`GameplaySettings::generated_arena`] on, every run gets an arena
+//! laid out from a seed: a few large obstacles, shorter lines of cover, and
+//! spawn zones that waves open their portals at, all kept clear of where
+//! the player starts. [`generate`] is a pure function of the seed and the
+//! [`GenerationConfig`], so the same seed always builds the same arena.
+//! The seed is [`GenerationConfig::seed`] if set, and otherwise drawn from
+//! the run's [`GameRng`].
+//!
+//! Obstacles and cover are rows of square blocks, each a wall collider, so
+//! shots bounce off them and the player and enemies can't pass. A level
+//! loaded from a map brings its own walls, so turn this off to play one.
+
+use bevy::prelude::*;
+
+use crate::{
+    bounds::grow,
+    config::GameConfig,
+    death::StartNewRun,
+    level::SpawnPoint,
+    physics::{Collider, CollisionLayer},
+    rng::GameRng,
+    settings::GameplaySettings,
+};
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct GenerationConfig {
+    /// Seed every run builds from, or `None` for a new one each run.
+    pub seed: Option<u64>,
+    /// Scales how many obstacles and cover lines are placed.
+    pub density: f32,
+    /// Large obstacles at a density of 1.
+    pub obstacles: u32,
+    /// Smallest and largest obstacle side, in blocks.
+    pub obstacle_blocks: (u32, u32),
+    /// Cover lines at a density of 1.
+    pub cover: u32,
+    /// Shortest and longest cover line, in blocks.
+    pub cover_blocks: (u32, u32),
+    pub spawn_zones: u32,
+    /// Side of one block, in world units.
+    pub block_size: f32,
+    /// Least space left between two pieces, in world units.
+    pub gap: f32,
+    /// Radius around the start kept free of obstacles.
+    pub clear_radius: f32,
+    /// Closest a spawn zone may be to the start. Lower is harder.
+    pub spawn_distance: f32,
+    /// Tries to place each piece before giving up on it.
+    pub attempts: u32,
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            density: 1.,
+            obstacles: 6,
+            obstacle_blocks: (2, 4),
+            cover: 10,
+            cover_blocks: (2, 5),
+            spawn_zones: 4,
+            block_size: 32.,
+            gap: 64.,
+            clear_radius: 160.,
+            spawn_distance: 400.,
+            attempts: 20,
+        }
+    }
+}
+
+/// What [`generate`] builds: block centres and spawn zones, in the world.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArenaLayout {
+    pub obstacles: Vec<Vec2>,
+    pub cover: Vec<Vec2>,
+    pub spawn_zones: Vec<Vec2>,
+}
+
+/// Lays out an arena in `area` around a player starting at `start`.
+pub fn generate(config: &GenerationConfig, area: Rect, start: Vec2, seed: u64) -> ArenaLayout {
+    let mut rng = GameRng::new(seed);
+    let mut layout = ArenaLayout::default();
+    let mut placed: Vec<Rect> = Vec::new();
+    let block = config.block_size.max(1.);
+    let count = |base: u32| (base as f32 * config.density.max(0.)).round() as u32;
+
+    let blocks = |(min, max): (u32, u32), rng: &mut GameRng| {
+        let min = min.max(1);
+        min + (rng.f32() * (max.max(min) - min + 1) as f32) as u32
+    };
+    let place = |size: UVec2, rng: &mut GameRng, placed: &mut Vec<Rect>| {
+        let half = size.as_vec2() * block / 2.;
+        let room = grow(area, -config.gap);
+        for _ in 0..config.attempts {
+            let centre = Vec2::new(
+                rng.range(room.min.x + half.x, room.max.x - half.x),
+                rng.range(room.min.y + half.y, room.max.y - half.y),
+            );
+            let rect = Rect::from_center_half_size(centre, half);
+            let near_start = grow(rect, config.clear_radius).contains(start);
+            let crowded = placed
+                .iter()
+                .any(|other| !grow(*other, config.gap).intersect(rect).is_empty());
+            if !near_start && !crowded && room.contains(centre) {
+                placed.push(rect);
+                return Some(block_centres(rect, size, block));
+            }
+        }
+        None
+    };
+
+    for _ in 0..count(config.obstacles) {
+        let size = UVec2::new(
+            blocks(config.obstacle_blocks, &mut rng),
+            blocks(config.obstacle_blocks, &mut rng),
+        );
+        if let Some(centres) = place(size, &mut rng, &mut placed) {
+            layout.obstacles.extend(centres);
+        }
+    }
+    for _ in 0..count(config.cover) {
+        let length = blocks(config.cover_blocks, &mut rng);
+        let size = if rng.f32() < 0.5 {
+            UVec2::new(length, 1)
+        } else {
+            UVec2::new(1, length)
+        };
+        if let Some(centres) = place(size, &mut rng, &mut placed) {
+            layout.cover.extend(centres);
+        }
+    }
+
+    let room = grow(area, -block);
+    for _ in 0..config.spawn_zones {
+        for _ in 0..config.attempts {
+            let point = Vec2::new(
+                rng.range(room.min.x, room.max.x),
+                rng.range(room.min.y, room.max.y),
+            );
+            let blocked = placed.iter().any(|rect| grow(*rect, block).contains(point));
+            if point.distance(start) >= config.spawn_distance && !blocked {
+                layout.spawn_zones.push(point);
+                break;
+            }
+        }
+    }
+    layout
+}
+
+/// Centres of the `size` blocks filling `rect`.
+fn block_centres(rect: Rect, size: UVec2, block: f32) -> impl Iterator<Item = Vec2> {
+    (0..size.y).flat_map(move |y| {
+        (0..size.x).map(move |x| rect.min + (Vec2::new(x as f32, y as f32) + 0.5) * block)
+    })
+}
+
+/// Parent of everything spawned for the generated arena.
+#[derive(Component)]
+pub struct GeneratedRoot;
+
+const OBSTACLE_COLOR: Color = Color::rgb(0.3, 0.32, 0.38);
+const COVER_COLOR: Color = Color::rgb(0.22, 0.24, 0.3);
+
+pub struct GenerationPlugin;
+
+impl Plugin for GenerationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<GenerationConfig>()
+            .init_resource::<GenerationConfig>()
+            .add_systems(Update, build_arena);
+    }
+}
+
+pub fn build_arena(
+    mut commands: Commands,
+    settings: Res<GameplaySettings>,
+    config: Res<GenerationConfig>,
+    game: Res<GameConfig>,
+    mut rng: ResMut<GameRng>,
+    mut new_runs: EventReader<StartNewRun>,
+    mut enabled: Local<bool>,
+    roots: Query<Entity, With<GeneratedRoot>>,
+) {
+    let new_run = new_runs.read().count() > 0;
+    let toggled = *enabled != settings.generated_arena;
+    *enabled = settings.generated_arena;
+    if !(toggled || (new_run && *enabled)) {
+        return;
+    }
+
+    for entity in &roots {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !*enabled {
+        return;
+    }
+
+    let seed = config
+        .seed
+        .unwrap_or_else(|| (u64::from(rng.next_u32()) << 32) | u64::from(rng.next_u32()));
+    let area = game.arena.inner_rect(0.);
+    let layout = generate(&config, area, game.arena.start, seed);
+    info!("arena generated from seed {seed}");
+
+    let block = Vec2::splat(config.block_size);
+    commands
+        .spawn((GeneratedRoot, SpatialBundle::default()))
+        .with_children(|parent| {
+            let pieces = layout
+                .obstacles
+                .iter()
+                .map(|centre| (centre, OBSTACLE_COLOR))
+                .chain(layout.cover.iter().map(|centre| (centre, COVER_COLOR)));
+            for (centre, color) in pieces {
+                parent.spawn((
+                    Collider::new(config.block_size / 2., CollisionLayer::WALL),
+                    SpriteBundle {
+                        transform: Transform::from_translation(centre.extend(-0.5)),
+                        sprite: Sprite {
+                            color,
+                            custom_size: Some(block),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                ));
+            }
+            for zone in &layout.spawn_zones {
+                parent.spawn((
+                    SpawnPoint,
+                    SpatialBundle::from_transform(Transform::from_translation(zone.extend(0.))),
+                ));
+            }
+        });
+}
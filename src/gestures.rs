@@ -0,0 +1,303 @@
+//! Touch gestures away from the sticks.
+//!
+//! Touches that start on a touch stick or a button belong to them; the rest
+//! are read as gestures, as [`TouchOwners`] decides. A quick tap fires a
+//! shot and a second tap close behind it dashes. A quick tap on the look
+//! stick, without pushing it, toggles the [target lock](crate::lock).
+//! Holding a finger still fires for as long as it stays down. A quick swipe
+//! switches weapons. Two fingers pinching in or out zoom the camera.
+//!
+//! Gestures don't act on anything directly: they press the same actions the
+//! sticks, keys and buttons are bound to, right after leafwing has updated
+//! them from those, so everything reading [`Action::Shoot`],
+//! [`Action::Dash`], [`Action::SwitchWeapon`], [`Action::Lock`] or
+//! [`Action::Zoom`] sees a gesture like any other input. Each recognized
+//! gesture is also sent as a [`Gesture`] event.
+
+use bevy::{prelude::*, utils::HashMap};
+use leafwing_input_manager::{plugin::InputManagerSystem, prelude::*};
+
+use crate::{
+    state::GameState,
+    touch_owners::{self, TouchOwner, TouchOwners},
+    Action, Player,
+};
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct GestureConfig {
+    /// Longest a touch can stay down and still count as a tap, in seconds.
+    pub tap_time: f32,
+    /// Furthest a finger can drift, in logical pixels, and still count as
+    /// a tap or a hold.
+    pub slop: f32,
+    /// Longest gap between the taps of a double tap, in seconds.
+    pub double_tap_time: f32,
+    /// Furthest apart the taps of a double tap can be, in logical pixels.
+    pub double_tap_distance: f32,
+    /// Seconds a finger has to stay still to start a hold.
+    pub hold_time: f32,
+    /// Longest a touch can stay down and still count as a swipe, in
+    /// seconds.
+    pub swipe_time: f32,
+    /// Shortest distance a finger has to travel for a swipe, in logical
+    /// pixels.
+    pub swipe_distance: f32,
+    /// [`Action::Zoom`] for a pinch that doubles or halves the distance
+    /// between the fingers.
+    pub pinch_zoom: f32,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            tap_time: 0.25,
+            slop: 24.,
+            double_tap_time: 0.3,
+            double_tap_distance: 80.,
+            hold_time: 0.35,
+            swipe_time: 0.3,
+            swipe_distance: 80.,
+            pinch_zoom: 7.,
+        }
+    }
+}
+
+/// A recognized gesture, at a screen position where it has one.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    Tap(Vec2),
+    DoubleTap(Vec2),
+    HoldStarted(Vec2),
+    HoldEnded,
+    /// A quick flick, along the screen direction it went.
+    Swipe(Vec2),
+    /// A tap on the look stick that didn't push it.
+    LookTap(Vec2),
+    /// Zoom this frame, positive when the fingers spread.
+    Pinch(f32),
+}
+
+/// Two fingers pinching, followed from one frame to the next.
+#[derive(Debug, Default)]
+pub struct Pinch {
+    /// Distance between the fingers last frame.
+    distance: Option<f32>,
+}
+
+impl Pinch {
+    /// How many times the distance between the fingers at `a` and `b`
+    /// doubled since last frame, negative when they closed in. `None` ends
+    /// the pinch.
+    pub fn update(&mut self, fingers: Option<(Vec2, Vec2)>) -> f32 {
+        let Some((a, b)) = fingers else {
+            self.distance = None;
+            return 0.;
+        };
+        let distance = a.distance(b);
+        let spread = self
+            .distance
+            .filter(|previous| *previous > 0.)
+            .map_or(0., |previous| {
+                (distance / previous).max(f32::EPSILON).log2()
+            });
+        self.distance = Some(distance);
+        spread
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TrackedTouch {
+    started: f32,
+    /// Drifted past the slop or part of a pinch, so neither a tap nor a
+    /// hold.
+    moved: bool,
+    /// Part of a pinch, so not a swipe either.
+    pinched: bool,
+    holding: bool,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct GestureTracker {
+    touches: HashMap<u64, TrackedTouch>,
+    /// When each touch on the look stick started.
+    look_touches: HashMap<u64, f32>,
+    /// Time and position of the last tap, waiting for a second one.
+    last_tap: Option<(f32, Vec2)>,
+    pinch: Pinch,
+}
+
+impl GestureTracker {
+    fn holding(&self) -> bool {
+        self.touches.values().any(|touch| touch.holding)
+    }
+}
+
+pub struct GesturesPlugin;
+
+impl Plugin for GesturesPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<GestureConfig>()
+            .init_resource::<GestureConfig>()
+            .init_resource::<GestureTracker>()
+            .add_event::<Gesture>()
+            .add_systems(
+                PreUpdate,
+                (recognize_gestures, press_gesture_actions)
+                    .chain()
+                    .after(InputManagerSystem::Update)
+                    .after(touch_owners::assign_touches)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), reset_gestures);
+    }
+}
+
+fn recognize_gestures(
+    time: Res<Time<Real>>,
+    config: Res<GestureConfig>,
+    touches: Res<Touches>,
+    owners: Res<TouchOwners>,
+    mut tracker: ResMut<GestureTracker>,
+    mut gestures: EventWriter<Gesture>,
+) {
+    let now = time.elapsed_seconds();
+    for touch in touches.iter_just_pressed() {
+        match owners.owner(touch.id()) {
+            Some(TouchOwner::LookStick) => {
+                tracker.look_touches.insert(touch.id(), now);
+            }
+            Some(TouchOwner::Gestures) => {
+                tracker.touches.insert(
+                    touch.id(),
+                    TrackedTouch {
+                        started: now,
+                        moved: false,
+                        pinched: false,
+                        holding: false,
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+
+    // pinch between the first two fingers down
+    let mut pinching: Vec<_> = touches
+        .iter()
+        .filter(|touch| tracker.touches.contains_key(&touch.id()))
+        .collect();
+    pinching.sort_by_key(|touch| touch.id());
+    let fingers = match pinching[..] {
+        [a, b, ..] => Some((a, b)),
+        _ => None,
+    };
+    let zoom = tracker
+        .pinch
+        .update(fingers.map(|(a, b)| (a.position(), b.position())))
+        * config.pinch_zoom;
+    if zoom != 0. {
+        gestures.send(Gesture::Pinch(zoom));
+    }
+    for touch in fingers.into_iter().flat_map(|(a, b)| [a, b]) {
+        if let Some(tracked) = tracker.touches.get_mut(&touch.id()) {
+            tracked.moved = true;
+            tracked.pinched = true;
+        }
+    }
+
+    for touch in touches.iter() {
+        let Some(tracked) = tracker.touches.get_mut(&touch.id()) else {
+            continue;
+        };
+        if touch.distance().length() > config.slop {
+            tracked.moved = true;
+        }
+        let stopped_holding = tracked.holding && tracked.moved;
+        let started_holding =
+            !tracked.holding && !tracked.moved && now - tracked.started >= config.hold_time;
+        if stopped_holding {
+            tracked.holding = false;
+            gestures.send(Gesture::HoldEnded);
+        } else if started_holding {
+            tracked.holding = true;
+            gestures.send(Gesture::HoldStarted(touch.position()));
+        }
+    }
+
+    let ended = touches
+        .iter_just_released()
+        .chain(touches.iter_just_canceled());
+    for touch in ended {
+        if let Some(started) = tracker.look_touches.remove(&touch.id()) {
+            if now - started <= config.tap_time && touch.distance().length() <= config.slop {
+                gestures.send(Gesture::LookTap(touch.position()));
+            }
+            continue;
+        }
+        let Some(tracked) = tracker.touches.remove(&touch.id()) else {
+            continue;
+        };
+        if tracked.holding {
+            gestures.send(Gesture::HoldEnded);
+            continue;
+        }
+        let travelled = touch.distance();
+        if !tracked.pinched
+            && now - tracked.started <= config.swipe_time
+            && travelled.length() >= config.swipe_distance
+        {
+            gestures.send(Gesture::Swipe(travelled.normalize()));
+            continue;
+        }
+        if tracked.moved || now - tracked.started > config.tap_time {
+            continue;
+        }
+        let position = touch.position();
+        let double = tracker.last_tap.is_some_and(|(time, last)| {
+            now - time <= config.double_tap_time
+                && last.distance(position) <= config.double_tap_distance
+        });
+        if double {
+            tracker.last_tap = None;
+            gestures.send(Gesture::DoubleTap(position));
+        } else {
+            tracker.last_tap = Some((now, position));
+            gestures.send(Gesture::Tap(position));
+        }
+    }
+}
+
+pub fn press_gesture_actions(
+    tracker: Res<GestureTracker>,
+    mut gestures: EventReader<Gesture>,
+    mut players: Query<&mut ActionState<Action>, With<Player>>,
+) {
+    let Ok(mut action_state) = players.get_single_mut() else {
+        return;
+    };
+    let mut zoom = 0.;
+    for gesture in gestures.read() {
+        match gesture {
+            Gesture::Tap(_) => action_state.press(&Action::Shoot),
+            Gesture::DoubleTap(_) => action_state.press(&Action::Dash),
+            Gesture::Swipe(_) => action_state.press(&Action::SwitchWeapon),
+            Gesture::LookTap(_) => action_state.press(&Action::Lock),
+            Gesture::Pinch(amount) => zoom += amount,
+            Gesture::HoldStarted(_) | Gesture::HoldEnded => {}
+        }
+    }
+    // leafwing releases it every frame no bound input holds it
+    if tracker.holding() {
+        action_state.press(&Action::Shoot);
+    }
+    if zoom != 0. {
+        if let Some(data) = action_state.action_data_mut(&Action::Zoom) {
+            data.value += zoom;
+        }
+    }
+}
+
+fn reset_gestures(mut tracker: ResMut<GestureTracker>) {
+    *tracker = GestureTracker::default();
+}
@@ -0,0 +1,174 @@
+//! Vibration on phones.
+//!
+//! Anything can send a [`HapticPulse`]; gameplay events send the usual
+//! ones: a heavy pulse when the player is hurt, a light one on a kill, and
+//! medium ones on a dash and at the start of a wave. Pulses closer together
+//! than [`MIN_INTERVAL`] are dropped, the strongest of a frame wins, and
+//! none play with [`ControlSettings::haptics`] off.
+//!
+//! Android drives the system vibrator through JNI, which needs the
+//! `VIBRATE` permission in the manifest. iOS plays the system's peek, pop
+//! and nope haptics. On the web the Vibration API takes a pulse length,
+//! and browsers without it, like Safari, ignore it. Desktops have nothing
+//! to vibrate and do nothing.
+
+use bevy::prelude::*;
+
+use crate::{
+    events::{EnemyKilled, PlayerDashed, PlayerHurt, WaveStarted},
+    settings::ControlSettings,
+};
+
+/// Fewest seconds between two pulses.
+const MIN_INTERVAL: f32 = 0.06;
+
+/// A vibration, by strength.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HapticPulse {
+    Light,
+    Medium,
+    Heavy,
+}
+
+impl HapticPulse {
+    /// Length of the pulse where only the length can be picked.
+    #[cfg_attr(
+        not(any(target_os = "android", target_arch = "wasm32")),
+        allow(dead_code)
+    )]
+    fn millis(self) -> u32 {
+        match self {
+            HapticPulse::Light => 10,
+            HapticPulse::Medium => 25,
+            HapticPulse::Heavy => 50,
+        }
+    }
+}
+
+pub struct HapticsPlugin;
+
+impl Plugin for HapticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<HapticPulse>()
+            .add_systems(Update, (pulse_from_events, play_pulses).chain());
+    }
+}
+
+fn pulse_from_events(
+    mut hurt: EventReader<PlayerHurt>,
+    mut killed: EventReader<EnemyKilled>,
+    mut dashed: EventReader<PlayerDashed>,
+    mut waves: EventReader<WaveStarted>,
+    mut pulses: EventWriter<HapticPulse>,
+) {
+    for (count, pulse) in [
+        (hurt.read().count(), HapticPulse::Heavy),
+        (killed.read().count(), HapticPulse::Light),
+        (dashed.read().count(), HapticPulse::Medium),
+        (waves.read().count(), HapticPulse::Medium),
+    ] {
+        if count > 0 {
+            pulses.send(pulse);
+        }
+    }
+}
+
+fn play_pulses(
+    time: Res<Time<Real>>,
+    settings: Res<ControlSettings>,
+    mut pulses: EventReader<HapticPulse>,
+    mut last: Local<Option<f32>>,
+) {
+    let Some(pulse) = pulses.read().copied().max() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    if !settings.haptics || last.is_some_and(|last| now - last < MIN_INTERVAL) {
+        return;
+    }
+    *last = Some(now);
+    backend::vibrate(pulse);
+}
+
+#[cfg(target_os = "android")]
+mod backend {
+    use bevy::prelude::*;
+    use jni::{
+        objects::{JObject, JValue},
+        JavaVM,
+    };
+
+    use super::HapticPulse;
+
+    pub fn vibrate(pulse: HapticPulse) {
+        let Some(app) = bevy::winit::ANDROID_APP.get() else {
+            return;
+        };
+        // SAFETY: both pointers stay valid for as long as the activity runs
+        let vm = unsafe { JavaVM::from_raw(app.vm_as_ptr().cast()) };
+        let activity = unsafe { JObject::from_raw(app.activity_as_ptr().cast()) };
+        let result = vm.and_then(|vm| {
+            let mut env = vm.attach_current_thread()?;
+            let name = env.new_string("vibrator")?;
+            let vibrator = env
+                .call_method(
+                    &activity,
+                    "getSystemService",
+                    "(Ljava/lang/String;)Ljava/lang/Object;",
+                    &[JValue::Object(&name)],
+                )?
+                .l()?;
+            if !vibrator.is_null() {
+                env.call_method(
+                    &vibrator,
+                    "vibrate",
+                    "(J)V",
+                    &[JValue::Long(pulse.millis().into())],
+                )?;
+            }
+            Ok(())
+        });
+        if let Err(err) = result {
+            warn!("failed to vibrate: {err}");
+        }
+    }
+}
+
+#[cfg(target_os = "ios")]
+mod backend {
+    use super::HapticPulse;
+
+    #[link(name = "AudioToolbox", kind = "framework")]
+    extern "C" {
+        fn AudioServicesPlaySystemSound(sound: u32);
+    }
+
+    pub fn vibrate(pulse: HapticPulse) {
+        // the peek, pop and nope feedback sounds
+        let sound = match pulse {
+            HapticPulse::Light => 1519,
+            HapticPulse::Medium => 1520,
+            HapticPulse::Heavy => 1521,
+        };
+        // SAFETY: plays a system sound, with nothing to keep alive
+        unsafe { AudioServicesPlaySystemSound(sound) };
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use super::HapticPulse;
+
+    pub fn vibrate(pulse: HapticPulse) {
+        if let Some(window) = web_sys::window() {
+            window.navigator().vibrate_with_duration(pulse.millis());
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+mod backend {
+    use super::HapticPulse;
+
+    pub fn vibrate(_pulse: HapticPulse) {}
+}
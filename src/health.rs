@@ -0,0 +1,168 @@
+//! Health and damage.
+//!
+//! Anything with [`Health`] takes damage through [`DamageEvent`]s, which
+//! report the player being hurt or dying and enemies being killed; what
+//! happens next lives with the player's death and the enemy lifecycle. A
+//! hit also sends an [`Fx::HitFlash`] for what it hit. A
+//! [shield](crate::status) takes what it can of a hit first.
+
+use bevy::prelude::*;
+
+use crate::{
+    death::Invulnerable,
+    enemy::Enemy,
+    events::{EnemyKilled, PlayerDied, PlayerHurt},
+    fx::Fx,
+    status::Statuses,
+    Player,
+};
+
+#[derive(Component, Reflect, Debug, Clone, Copy)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.
+    }
+
+    /// Applies `amount` damage, returning `true` if this hit took health
+    /// from above zero to zero or below.
+    pub fn damage(&mut self, amount: f32) -> bool {
+        let was_alive = !self.is_dead();
+        self.current -= amount;
+        was_alive && self.is_dead()
+    }
+}
+
+/// Request to deal `amount` damage to `target`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+}
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Health>()
+            .add_event::<DamageEvent>()
+            .add_systems(Update, apply_damage);
+    }
+}
+
+fn apply_damage(
+    mut damage_events: EventReader<DamageEvent>,
+    mut targets: Query<(
+        &mut Health,
+        &Transform,
+        Has<Player>,
+        Has<Enemy>,
+        Has<Invulnerable>,
+        Option<&mut Statuses>,
+    )>,
+    mut hurt: EventWriter<PlayerHurt>,
+    mut died: EventWriter<PlayerDied>,
+    mut killed: EventWriter<EnemyKilled>,
+    mut fx: EventWriter<Fx>,
+) {
+    for event in damage_events.read() {
+        let Ok((mut health, transform, is_player, is_enemy, invulnerable, statuses)) =
+            targets.get_mut(event.target)
+        else {
+            continue;
+        };
+
+        // already dead, further hits are ignored so death is reported once
+        if health.is_dead() || invulnerable {
+            continue;
+        }
+
+        let amount = match statuses {
+            Some(mut statuses) => statuses.absorb(event.amount),
+            None => event.amount,
+        };
+        // all of it soaked up by a shield
+        if amount <= 0. {
+            continue;
+        }
+        let crossed_zero = health.damage(amount);
+
+        fx.send(Fx::HitFlash {
+            target: event.target,
+        });
+
+        if is_player {
+            hurt.send(PlayerHurt {
+                entity: event.target,
+                amount,
+                remaining: health.current,
+            });
+
+            if crossed_zero {
+                died.send(PlayerDied {
+                    entity: event.target,
+                    position: transform.translation.truncate(),
+                });
+            }
+        }
+
+        if is_enemy && crossed_zero {
+            killed.send(EnemyKilled {
+                entity: event.target,
+                position: transform.translation.truncate(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_death_is_reported_once() {
+        let mut app = App::new();
+        app.add_event::<DamageEvent>()
+            .add_event::<PlayerHurt>()
+            .add_event::<PlayerDied>()
+            .add_event::<EnemyKilled>()
+            .add_event::<Fx>()
+            .add_systems(Update, apply_damage);
+        let player = app
+            .world
+            .spawn((
+                Player { max_speed: 0. },
+                Health::new(10.),
+                Transform::default(),
+            ))
+            .id();
+
+        // the killing blow, and more in the same frame and the next
+        for amount in [6., 6., 6.] {
+            app.world.send_event(DamageEvent {
+                target: player,
+                amount,
+            });
+        }
+        app.update();
+        app.world.send_event(DamageEvent {
+            target: player,
+            amount: 6.,
+        });
+        app.update();
+
+        let died = app.world.resource::<Events<PlayerDied>>();
+        let mut reader = died.get_reader();
+        let deaths: Vec<_> = reader.read(died).collect();
+        assert_eq!(deaths.len(), 1);
+        assert_eq!(deaths[0].entity, player);
+    }
+}
@@ -0,0 +1,163 @@
+//! Health bars above wounded enemies.
+//!
+//! Bars come from a small pool of sprite pairs that follow their enemy.
+//! An enemy only gets one once it is hurt, the bar fades in when it does,
+//! and it goes back to the pool when the enemy dies or heals to full.
+//! At most [`MAX_BARS`] are shown at once, so a big wave stays readable and
+//! cheap.
+
+use bevy::{prelude::*, sprite::Anchor, transform::TransformSystem, utils::HashSet};
+
+use crate::{enemy::Enemy, health::Health};
+
+const MAX_BARS: usize = 32;
+const BAR_SIZE: Vec2 = Vec2::new(24., 4.);
+/// Gap between the top of the enemy sprite and the bar.
+const BAR_GAP: f32 = 6.;
+/// Above enemies and their sprites, below UI.
+const BAR_Z: f32 = 2.;
+const FADE_IN: f32 = 0.2;
+const BACKGROUND_COLOR: Color = Color::rgba(0., 0., 0., 0.7);
+const FULL_COLOR: Color = Color::rgb(0.3, 0.9, 0.3);
+const EMPTY_COLOR: Color = Color::rgb(0.9, 0.2, 0.2);
+
+#[derive(Component, Default)]
+struct HealthBar {
+    target: Option<Entity>,
+    /// Seconds since the bar was given to its target.
+    shown: f32,
+}
+
+#[derive(Component)]
+struct HealthBarFill;
+
+#[derive(Resource, Default)]
+struct HealthBarPool(Vec<Entity>);
+
+pub struct HealthBarPlugin;
+
+impl Plugin for HealthBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HealthBarPool>().add_systems(
+            PostUpdate,
+            update_health_bars.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Fill color for a bar at `fraction` of full health.
+pub fn fill_color(fraction: f32) -> Color {
+    let [r0, g0, b0, _] = EMPTY_COLOR.as_rgba_f32();
+    let [r1, g1, b1, _] = FULL_COLOR.as_rgba_f32();
+    let t = fraction.clamp(0., 1.);
+    Color::rgb(r0 + (r1 - r0) * t, g0 + (g1 - g0) * t, b0 + (b1 - b0) * t)
+}
+
+fn spawn_bar(commands: &mut Commands) -> Entity {
+    commands
+        .spawn((
+            HealthBar::default(),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: BACKGROUND_COLOR,
+                    custom_size: Some(BAR_SIZE),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                HealthBarFill,
+                SpriteBundle {
+                    transform: Transform::from_xyz(-BAR_SIZE.x / 2., 0., 0.1),
+                    sprite: Sprite {
+                        anchor: Anchor::CenterLeft,
+                        custom_size: Some(BAR_SIZE),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ));
+        })
+        .id()
+}
+
+fn update_health_bars(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pool: ResMut<HealthBarPool>,
+    enemies: Query<(Entity, &Health, &Transform, &Sprite), With<Enemy>>,
+    mut bars: Query<
+        (
+            Entity,
+            &mut HealthBar,
+            &mut Transform,
+            &mut Sprite,
+            &mut Visibility,
+            &Children,
+        ),
+        Without<Enemy>,
+    >,
+    mut fills: Query<&mut Sprite, (With<HealthBarFill>, Without<HealthBar>, Without<Enemy>)>,
+) {
+    let is_wounded = |health: &Health| !health.is_dead() && health.current < health.max;
+
+    let mut tracked = HashSet::new();
+    let mut free = Vec::new();
+    for (entity, mut bar, mut transform, mut sprite, mut visibility, children) in &mut bars {
+        let target = bar
+            .target
+            .and_then(|target| enemies.get(target).ok())
+            .filter(|(_, health, ..)| is_wounded(health));
+        let Some((target, health, target_transform, target_sprite)) = target else {
+            bar.target = None;
+            *visibility = Visibility::Hidden;
+            free.push(entity);
+            continue;
+        };
+        tracked.insert(target);
+
+        bar.shown += time.delta_seconds();
+        let alpha = (bar.shown / FADE_IN).min(1.);
+        let height = target_sprite.custom_size.map_or(0., |size| size.y);
+        let above = height / 2. * target_transform.scale.y + BAR_GAP;
+        transform.translation =
+            (target_transform.translation.truncate() + Vec2::Y * above).extend(BAR_Z);
+        sprite.color.set_a(BACKGROUND_COLOR.a() * alpha);
+        *visibility = Visibility::Visible;
+
+        let fraction = health.current / health.max;
+        for &child in children {
+            if let Ok(mut fill) = fills.get_mut(child) {
+                fill.color = fill_color(fraction).with_a(alpha);
+                fill.custom_size = Some(Vec2::new(BAR_SIZE.x * fraction, BAR_SIZE.y));
+            }
+        }
+    }
+
+    let mut capacity = MAX_BARS.saturating_sub(tracked.len());
+    for (target, health, ..) in &enemies {
+        if capacity == 0 {
+            break;
+        }
+        if !is_wounded(health) || tracked.contains(&target) {
+            continue;
+        }
+        let bar = match free.pop() {
+            Some(bar) => bar,
+            None if pool.0.len() < MAX_BARS => {
+                let bar = spawn_bar(&mut commands);
+                pool.0.push(bar);
+                bar
+            }
+            None => break,
+        };
+        commands.entity(bar).insert(HealthBar {
+            target: Some(target),
+            shown: 0.,
+        });
+        capacity -= 1;
+    }
+}
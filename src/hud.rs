@@ -0,0 +1,359 @@
+//! The in-run overlay: the player's health, the wave, the score, the
+//! rewind, dash and turret cooldowns and the power-ups running.
+//!
+//! It is spawned on entering `Playing` and gone in every other state. Each
+//! part is rewritten only when what it shows changes, or when the HUD has
+//! just been spawned, so a quiet frame touches no UI at all.
+
+use bevy::{ecs::query::QueryFilter, prelude::*};
+
+use crate::{
+    abilities::{Dash, DashConfig, Rewind, RewindConfig},
+    ally::{AllyConfig, AllyDeploy},
+    events::WaveCleared,
+    fonts::UiFonts,
+    health::Health,
+    health_bar::fill_color,
+    layout::{Mirrored, StickSide},
+    pickups::StatModifiers,
+    score::{Score, ScoreConfig},
+    state::{GameState, StateScoped},
+    stats::Stats,
+    Player,
+};
+
+const HEALTH_BAR_SIZE: Vec2 = Vec2::new(160., 12.);
+const COOLDOWN_BAR_SIZE: Vec2 = Vec2::new(80., 6.);
+const BAR_BACKGROUND: Color = Color::rgba(0., 0., 0., 0.6);
+const COOLDOWN_COLOR: Color = Color::rgba(0.4, 0.9, 1., 0.5);
+const READY_COLOR: Color = Color::rgb(0.4, 0.9, 1.);
+const DIM_TEXT: Color = Color::rgba(1., 1., 1., 0.6);
+
+#[derive(Component)]
+struct Hud;
+
+#[derive(Component)]
+struct HealthFill;
+
+#[derive(Component)]
+struct WaveText;
+
+#[derive(Component)]
+struct ScoreText;
+
+#[derive(Component)]
+struct BestScoreText;
+
+#[derive(Component)]
+struct RewindFill;
+
+#[derive(Component)]
+struct DashFill;
+
+#[derive(Component)]
+struct TurretFill;
+
+#[derive(Component)]
+struct PowerUpText;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Playing), spawn_hud)
+            .add_systems(
+                Update,
+                (
+                    update_health,
+                    update_wave,
+                    update_score,
+                    update_best_score,
+                    update_rewind,
+                    update_dash,
+                    update_turret,
+                    update_power_ups,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// A bar of `size`, with a fill `marker`ed for updating.
+fn spawn_bar(parent: &mut ChildBuilder, size: Vec2, marker: impl Component) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(size.x),
+                height: Val::Px(size.y),
+                ..default()
+            },
+            background_color: BAR_BACKGROUND.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                marker,
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// A cooldown bar for an ability, with its name beside it.
+fn spawn_cooldown(parent: &mut ChildBuilder, fonts: &UiFonts, label: &str, marker: impl Component) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(6.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            spawn_bar(parent, COOLDOWN_BAR_SIZE, marker);
+            parent.spawn(TextBundle::from_section(label, fonts.style(14., DIM_TEXT)));
+        });
+}
+
+fn spawn_hud(mut commands: Commands, fonts: Res<UiFonts>) {
+    // health, wave and cooldowns under the pause button
+    commands
+        .spawn((
+            Hud,
+            StateScoped(GameState::Playing),
+            Mirrored::new(StickSide::Move, 12.),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(72.),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(6.),
+                    ..default()
+                },
+                z_index: ZIndex::Global(4),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            spawn_bar(parent, HEALTH_BAR_SIZE, HealthFill);
+            parent.spawn((
+                WaveText,
+                TextBundle::from_section("", fonts.style(18., Color::WHITE)),
+            ));
+            spawn_cooldown(parent, &fonts, "Rewind", RewindFill);
+            spawn_cooldown(parent, &fonts, "Dash", DashFill);
+            spawn_cooldown(parent, &fonts, "Turret", TurretFill);
+            parent.spawn((
+                PowerUpText,
+                TextBundle::from_section("", fonts.style(14., READY_COLOR)),
+            ));
+        });
+
+    // score along the top
+    commands
+        .spawn((
+            Hud,
+            StateScoped(GameState::Playing),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(12.),
+                    width: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(4),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                ScoreText,
+                TextBundle::from_section("", fonts.bold(28., Color::WHITE)),
+            ));
+            parent.spawn((
+                BestScoreText,
+                TextBundle::from_section("", fonts.style(16., DIM_TEXT)),
+            ));
+        });
+}
+
+fn update_health(
+    spawned: Query<(), Added<Hud>>,
+    players: Query<Ref<Health>, With<Player>>,
+    mut fills: Query<(&mut Style, &mut BackgroundColor), With<HealthFill>>,
+) {
+    let Ok(health) = players.get_single() else {
+        return;
+    };
+    if spawned.is_empty() && !health.is_changed() {
+        return;
+    }
+    let fraction = if health.max > 0. {
+        (health.current / health.max).clamp(0., 1.)
+    } else {
+        0.
+    };
+    for (mut style, mut color) in &mut fills {
+        style.width = Val::Percent(fraction * 100.);
+        *color = fill_color(fraction).into();
+    }
+}
+
+fn update_wave(
+    spawned: Query<(), Added<Hud>>,
+    score: Res<Score>,
+    mut cleared: EventReader<WaveCleared>,
+    mut texts: Query<&mut Text, With<WaveText>>,
+) {
+    // until the next wave starts and changes the score
+    if let Some(event) = cleared.read().last() {
+        for mut text in &mut texts {
+            text.sections[0].value = format!("Wave {} cleared", event.wave);
+        }
+        return;
+    }
+    if spawned.is_empty() && !score.is_changed() {
+        return;
+    }
+    for mut text in &mut texts {
+        text.sections[0].value = if score.wave > 0 {
+            format!("Wave {}", score.wave)
+        } else {
+            String::new()
+        };
+    }
+}
+
+fn update_score(
+    spawned: Query<(), Added<Hud>>,
+    score: Res<Score>,
+    config: Res<ScoreConfig>,
+    mut texts: Query<&mut Text, With<ScoreText>>,
+) {
+    if spawned.is_empty() && !score.is_changed() {
+        return;
+    }
+    for mut text in &mut texts {
+        text.sections[0].value = if score.combo > 1 {
+            format!("{}  x{:.1}", score.points, config.multiplier(score.combo))
+        } else {
+            score.points.to_string()
+        };
+    }
+}
+
+fn update_best_score(
+    spawned: Query<(), Added<Hud>>,
+    stats: Res<Stats>,
+    mut texts: Query<&mut Text, With<BestScoreText>>,
+) {
+    if spawned.is_empty() && !stats.is_changed() {
+        return;
+    }
+    for mut text in &mut texts {
+        text.sections[0].value = format!("Best {}", stats.best_score);
+    }
+}
+
+/// Fills a cooldown bar to how far `remaining` is through `cooldown`.
+fn fill_cooldown(
+    fills: &mut Query<(&mut Style, &mut BackgroundColor), impl QueryFilter>,
+    remaining: f32,
+    cooldown: f32,
+) {
+    let ready = if cooldown > 0. {
+        1. - (remaining / cooldown).clamp(0., 1.)
+    } else {
+        1.
+    };
+    for (mut style, mut color) in fills {
+        style.width = Val::Percent(ready * 100.);
+        *color = if ready >= 1. {
+            READY_COLOR
+        } else {
+            COOLDOWN_COLOR
+        }
+        .into();
+    }
+}
+
+fn update_rewind(
+    spawned: Query<(), Added<Hud>>,
+    config: Res<RewindConfig>,
+    players: Query<Ref<Rewind>, With<Player>>,
+    mut fills: Query<(&mut Style, &mut BackgroundColor), With<RewindFill>>,
+) {
+    let Ok(rewind) = players.get_single() else {
+        return;
+    };
+    if spawned.is_empty() && !rewind.is_changed() {
+        return;
+    }
+    fill_cooldown(&mut fills, rewind.cooldown, config.cooldown);
+}
+
+fn update_dash(
+    spawned: Query<(), Added<Hud>>,
+    config: Res<DashConfig>,
+    players: Query<Ref<Dash>, With<Player>>,
+    mut fills: Query<(&mut Style, &mut BackgroundColor), With<DashFill>>,
+) {
+    let Ok(dash) = players.get_single() else {
+        return;
+    };
+    if spawned.is_empty() && !dash.is_changed() {
+        return;
+    }
+    fill_cooldown(&mut fills, dash.cooldown, config.cooldown);
+}
+
+fn update_turret(
+    spawned: Query<(), Added<Hud>>,
+    config: Res<AllyConfig>,
+    players: Query<Ref<AllyDeploy>, With<Player>>,
+    mut fills: Query<(&mut Style, &mut BackgroundColor), With<TurretFill>>,
+) {
+    let Ok(deploy) = players.get_single() else {
+        return;
+    };
+    if spawned.is_empty() && !deploy.is_changed() {
+        return;
+    }
+    fill_cooldown(&mut fills, deploy.cooldown, config.cooldown);
+}
+
+/// One line per power-up running, like "Speed 5s".
+fn update_power_ups(
+    players: Query<Option<&StatModifiers>, With<Player>>,
+    mut texts: Query<&mut Text, With<PowerUpText>>,
+) {
+    let Ok(modifiers) = players.get_single() else {
+        return;
+    };
+    let lines: Vec<_> = modifiers
+        .map(|modifiers| {
+            modifiers
+                .active
+                .iter()
+                .map(|active| format!("{} {:.0}s", active.power_up.name(), active.remaining.ceil()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let value = lines.join("\n");
+    for mut text in &mut texts {
+        // counting down changes the modifiers every frame, but the text
+        // only once a second
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
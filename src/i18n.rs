@@ -0,0 +1,185 @@
+//! UI text in the player's language.
+//!
+//! English is written in the code and is also the key: each other
+//! [`Language`] has a table at `lang/<code>.ron` in the assets, a RON map
+//! from the English text to the translation, loaded as a [`Translations`]
+//! asset when the language is picked in [`DisplaySettings::language`].
+//! Text not in the table, or shown before it has loaded, stays in English.
+//!
+//! A UI text spawned with a [`LocalizedText`] has its first section kept in
+//! the current language, including when the language changes with the
+//! text on screen. Text built at runtime, like setting values, goes through
+//! [`Locale::tr`] instead.
+
+use std::{borrow::Cow, io};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::DisplaySettings;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+    French,
+    Spanish,
+}
+
+impl Language {
+    /// The language's name, in that language.
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::French => "Français",
+            Language::Spanish => "Español",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Language::English => Language::French,
+            Language::French => Language::Spanish,
+            Language::Spanish => Language::English,
+        }
+    }
+
+    /// Asset path of the translation table; English needs none.
+    fn path(self) -> Option<&'static str> {
+        match self {
+            Language::English => None,
+            Language::French => Some("lang/fr.ron"),
+            Language::Spanish => Some("lang/es.ron"),
+        }
+    }
+}
+
+/// English text mapped to its translation in one language.
+#[derive(Asset, TypePath, Debug, Deserialize)]
+#[serde(transparent)]
+pub struct Translations(HashMap<String, String>);
+
+/// The language UI text is shown in, with its table once loaded.
+#[derive(Resource, Debug, Default)]
+pub struct Locale {
+    language: Language,
+    handle: Option<Handle<Translations>>,
+    table: HashMap<String, String>,
+}
+
+impl Locale {
+    /// `text` in the current language, or as given without a translation.
+    pub fn tr<'a>(&'a self, text: &'a str) -> &'a str {
+        self.table.get(text).map_or(text, String::as_str)
+    }
+}
+
+/// Keeps the text's first section in the current language; holds the
+/// English text.
+#[derive(Component, Debug, Clone)]
+pub struct LocalizedText(pub Cow<'static, str>);
+
+impl LocalizedText {
+    pub fn new(text: impl Into<Cow<'static, str>>) -> Self {
+        Self(text.into())
+    }
+}
+
+pub struct I18nPlugin;
+
+impl Plugin for I18nPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Translations>()
+            .register_asset_loader(TranslationsLoader)
+            .init_resource::<Locale>()
+            .add_systems(
+                Update,
+                (
+                    switch_language.run_if(resource_changed::<DisplaySettings>),
+                    fill_table,
+                    localize_text,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn switch_language(
+    settings: Res<DisplaySettings>,
+    asset_server: Res<AssetServer>,
+    mut locale: ResMut<Locale>,
+) {
+    if settings.language == locale.language && !settings.is_added() {
+        return;
+    }
+    info!("language: {}", settings.language.name());
+    *locale = Locale {
+        language: settings.language,
+        handle: settings.language.path().map(|path| asset_server.load(path)),
+        table: default(),
+    };
+}
+
+fn fill_table(
+    mut events: EventReader<AssetEvent<Translations>>,
+    translations: Res<Assets<Translations>>,
+    mut locale: ResMut<Locale>,
+) {
+    for event in events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        if locale.handle.as_ref().map(Handle::id) != Some(*id) {
+            continue;
+        }
+        if let Some(Translations(table)) = translations.get(*id) {
+            locale.table = table.clone();
+        }
+    }
+}
+
+fn localize_text(locale: Res<Locale>, mut texts: Query<(Ref<LocalizedText>, &mut Text)>) {
+    for (localized, mut text) in &mut texts {
+        if !locale.is_changed() && !localized.is_changed() {
+            continue;
+        }
+        let value = locale.tr(&localized.0);
+        if let Some(section) = text.sections.first_mut() {
+            if section.value != value {
+                section.value = value.to_string();
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct TranslationsLoader;
+
+impl AssetLoader for TranslationsLoader {
+    type Asset = Translations;
+    type Settings = ();
+    type Error = io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Translations, io::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            ron::de::from_bytes(&bytes)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
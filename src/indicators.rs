@@ -0,0 +1,163 @@
+//! Arrows at the screen's edge pointing at what is off it.
+//!
+//! Each enemy and pickup outside the camera's view, but within
+//! [`IndicatorConfig::range`] of the player, gets an arrow on the edge of
+//! the screen where the line from its centre to them leaves it, pointing
+//! their way, in the enemy's own color or the pickup color. They are
+//! worked out from the camera's projection, so they follow the zoom and
+//! the rotating [camera mode](crate::camera). Arrows fade as what they
+//! point at gets further away, down to [`IndicatorConfig::far_alpha`] at
+//! the range, so the nearest threats stand out. At most [`MAX_ARROWS`]
+//! are drawn, the nearest first; like the [minimap](crate::minimap)'s
+//! dots, the arrows are kept and moved rather than respawned.
+
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::{
+    camera::MainCamera,
+    config::Palette,
+    enemy::Enemy,
+    pickups::Pickup,
+    state::{GameState, StateScoped},
+    weapon::WeaponPickup,
+    Player,
+};
+
+/// Arrows kept for enemies and pickups.
+pub const MAX_ARROWS: usize = 16;
+const ARROW_LENGTH: f32 = 14.;
+const ARROW_WIDTH: f32 = 6.;
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct IndicatorConfig {
+    /// World distance from the player past which nothing gets an arrow.
+    pub range: f32,
+    /// Pixels between an arrow and the edge of the screen.
+    pub margin: f32,
+    /// Opacity of an arrow pointing at something just off the screen.
+    pub near_alpha: f32,
+    /// Opacity of an arrow pointing at something at the range.
+    pub far_alpha: f32,
+}
+
+impl Default for IndicatorConfig {
+    fn default() -> Self {
+        Self {
+            range: 900.,
+            margin: 16.,
+            near_alpha: 0.9,
+            far_alpha: 0.2,
+        }
+    }
+}
+
+/// The `index`th arrow, in order of distance from the player.
+#[derive(Component)]
+struct Arrow(usize);
+
+pub struct IndicatorsPlugin;
+
+impl Plugin for IndicatorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<IndicatorConfig>()
+            .init_resource::<IndicatorConfig>()
+            .add_systems(OnEnter(GameState::Playing), spawn_arrows)
+            .add_systems(
+                PostUpdate,
+                update_arrows
+                    // reads the camera where it ends up this frame
+                    .after(TransformSystem::TransformPropagate)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn spawn_arrows(mut commands: Commands) {
+    for index in 0..MAX_ARROWS {
+        commands.spawn((
+            Arrow(index),
+            StateScoped(GameState::Playing),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(ARROW_LENGTH),
+                    height: Val::Px(ARROW_WIDTH),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                z_index: ZIndex::Global(3),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn update_arrows(
+    config: Res<IndicatorConfig>,
+    palette: Res<Palette>,
+    ui_scale: Res<UiScale>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    players: Query<&GlobalTransform, With<Player>>,
+    enemies: Query<(&GlobalTransform, &Sprite), With<Enemy>>,
+    pickups: Query<&GlobalTransform, Or<(With<Pickup>, With<WeaponPickup>)>>,
+    mut arrows: Query<(
+        &Arrow,
+        &mut Style,
+        &mut Transform,
+        &mut BackgroundColor,
+        &mut Visibility,
+    )>,
+) {
+    let view = cameras
+        .get_single()
+        .ok()
+        .and_then(|(camera, transform)| Some((camera, transform, camera.logical_viewport_size()?)));
+    let (Some((camera, camera_transform, size)), Ok(player)) = (view, players.get_single()) else {
+        for (_, _, _, _, mut visibility) in &mut arrows {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+    let origin = player.translation().truncate();
+    let center = size / 2.;
+    // where arrows sit, in from the edge
+    let edge = (center - config.margin - ARROW_LENGTH / 2.).max(Vec2::ONE);
+
+    let enemy_arrows = enemies
+        .iter()
+        .map(|(transform, sprite)| (transform, sprite.color.with_a(1.)));
+    let pickup_arrows = pickups.iter().map(|transform| (transform, palette.pickup));
+    let mut targets: Vec<(f32, Vec2, Color)> = enemy_arrows
+        .chain(pickup_arrows)
+        .filter_map(|(transform, color)| {
+            let distance = transform.translation().truncate().distance(origin);
+            if distance > config.range {
+                return None;
+            }
+            let point = camera.world_to_viewport(camera_transform, transform.translation())?;
+            let on_screen = point.cmpge(Vec2::ZERO).all() && point.cmple(size).all();
+            (!on_screen).then_some((distance, point - center, color))
+        })
+        .collect();
+    targets.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    for (arrow, mut style, mut transform, mut color, mut visibility) in &mut arrows {
+        let Some(&(distance, offset, target_color)) = targets.get(arrow.0) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        // out along the offset until the first edge it reaches
+        let reach = (edge / offset.abs().max(Vec2::splat(f32::EPSILON))).min_element();
+        let position = (center + offset * reach) / ui_scale.0;
+        style.left = Val::Px(position.x - ARROW_LENGTH / 2.);
+        style.top = Val::Px(position.y - ARROW_WIDTH / 2.);
+        // the viewport's y grows downwards, like the UI's
+        transform.rotation = Quat::from_rotation_z(offset.y.atan2(offset.x));
+
+        let t = (distance / config.range.max(1.)).clamp(0., 1.);
+        let alpha = config.near_alpha + (config.far_alpha - config.near_alpha) * t;
+        *color = target_color.with_a(alpha).into();
+        *visibility = Visibility::Inherited;
+    }
+}
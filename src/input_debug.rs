@@ -0,0 +1,279 @@
+//! Input visualizer for bug reports. Debug builds only.
+//!
+//! F3 toggles an overlay with a pad per stick, the raw axis values, the
+//! pressed actions, the active input device and the live entity counts
+//! against their [`EntityBudget`]. The last few seconds of input
+//! are always recorded, and F4 dumps them to the log. Everything is read
+//! from the player's [`ActionState`] exactly as `move_player` reads it, so
+//! an odd reading here is the stick, and an odd reading elsewhere is the
+//! movement code.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    budget::{BudgetCategory, BudgetLedger, EntityBudget},
+    fonts::UiFonts,
+    input_device::{ActiveInputDevice, InputDevice},
+    Action, Player,
+};
+
+const TOGGLE_KEY: KeyCode = KeyCode::F3;
+const DUMP_KEY: KeyCode = KeyCode::F4;
+/// Seconds of input kept for a dump.
+const RECORD_SECONDS: f32 = 5.;
+const PAD_SIZE: f32 = 60.;
+const DOT_SIZE: f32 = 8.;
+
+const ACTIONS: [Action; 9] = [
+    Action::Move,
+    Action::Look,
+    Action::Shoot,
+    Action::Trigger,
+    Action::Lock,
+    Action::Rewind,
+    Action::Dash,
+    Action::SwitchWeapon,
+    Action::Deploy,
+];
+
+/// One frame of the player's input.
+#[derive(Debug, Clone)]
+struct InputFrame {
+    time: f32,
+    motion: Vec2,
+    look: Vec2,
+    trigger: f32,
+    pressed: Vec<Action>,
+    device: InputDevice,
+}
+
+impl InputFrame {
+    fn read(time: f32, action_state: &ActionState<Action>, device: InputDevice) -> Self {
+        let axis = |action| {
+            action_state
+                .clamped_axis_pair(&action)
+                .map_or(Vec2::ZERO, |axis| axis.xy())
+        };
+        Self {
+            time,
+            motion: axis(Action::Move),
+            look: axis(Action::Look),
+            trigger: action_state.value(&Action::Trigger),
+            pressed: ACTIONS
+                .into_iter()
+                .filter(|action| action_state.pressed(action))
+                .collect(),
+            device,
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{:8.3}s move ({:+.2}, {:+.2}) look ({:+.2}, {:+.2}) trigger {:.2} {:?} {:?}",
+            self.time,
+            self.motion.x,
+            self.motion.y,
+            self.look.x,
+            self.look.y,
+            self.trigger,
+            self.pressed,
+            self.device,
+        )
+    }
+}
+
+#[derive(Resource, Default)]
+struct InputRecording {
+    frames: VecDeque<InputFrame>,
+}
+
+#[derive(Component)]
+struct InputOverlay;
+
+/// The dot showing a stick's position on its pad.
+#[derive(Component)]
+struct StickDot(Action);
+
+#[derive(Component)]
+struct InputReadout;
+
+pub struct InputDebugPlugin;
+
+impl Plugin for InputDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputRecording>()
+            .add_systems(Startup, spawn_overlay)
+            .add_systems(
+                Update,
+                (record_input, dump_recording, toggle_overlay, update_overlay).chain(),
+            );
+    }
+}
+
+fn record_input(
+    time: Res<Time<Real>>,
+    device: Res<ActiveInputDevice>,
+    mut recording: ResMut<InputRecording>,
+    players: Query<&ActionState<Action>, With<Player>>,
+) {
+    let Ok(action_state) = players.get_single() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    let frame = InputFrame::read(now, action_state, device.device);
+    recording.frames.push_back(frame);
+    while recording
+        .frames
+        .front()
+        .is_some_and(|frame| now - frame.time > RECORD_SECONDS)
+    {
+        recording.frames.pop_front();
+    }
+}
+
+fn dump_recording(keys: Res<ButtonInput<KeyCode>>, recording: Res<InputRecording>) {
+    if !keys.just_pressed(DUMP_KEY) {
+        return;
+    }
+    info!("input recording, last {} frames:", recording.frames.len());
+    for frame in &recording.frames {
+        info!("{}", frame.describe());
+    }
+}
+
+fn spawn_overlay(mut commands: Commands, fonts: Res<UiFonts>) {
+    commands
+        .spawn((
+            InputOverlay,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(12.),
+                    right: Val::Px(12.),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(6.),
+                    padding: UiRect::all(Val::Px(8.)),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.7).into(),
+                visibility: Visibility::Hidden,
+                z_index: ZIndex::Global(30),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for action in [Action::Move, Action::Look] {
+                        parent
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    width: Val::Px(PAD_SIZE),
+                                    height: Val::Px(PAD_SIZE),
+                                    ..default()
+                                },
+                                background_color: Color::rgba(1., 1., 1., 0.15).into(),
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    StickDot(action),
+                                    NodeBundle {
+                                        style: Style {
+                                            position_type: PositionType::Absolute,
+                                            width: Val::Px(DOT_SIZE),
+                                            height: Val::Px(DOT_SIZE),
+                                            ..default()
+                                        },
+                                        background_color: Color::CYAN.into(),
+                                        ..default()
+                                    },
+                                ));
+                            });
+                    }
+                });
+            parent.spawn((
+                InputReadout,
+                TextBundle::from_section("", fonts.style(12., Color::WHITE)),
+            ));
+        });
+}
+
+fn toggle_overlay(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut overlays: Query<&mut Visibility, With<InputOverlay>>,
+) {
+    if !keys.just_pressed(TOGGLE_KEY) {
+        return;
+    }
+    for mut visibility in &mut overlays {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Visible,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn update_overlay(
+    recording: Res<InputRecording>,
+    budget: Res<EntityBudget>,
+    ledger: Res<BudgetLedger>,
+    overlays: Query<&Visibility, With<InputOverlay>>,
+    mut dots: Query<(&StickDot, &mut Style)>,
+    mut readouts: Query<&mut Text, With<InputReadout>>,
+) {
+    if overlays
+        .iter()
+        .all(|visibility| *visibility == Visibility::Hidden)
+    {
+        return;
+    }
+    let Some(frame) = recording.frames.back() else {
+        return;
+    };
+
+    for (dot, mut style) in &mut dots {
+        let axis = match dot.0 {
+            Action::Move => frame.motion,
+            _ => frame.look,
+        };
+        // screen y grows downward
+        let offset = (Vec2::new(axis.x, -axis.y) + 1.) / 2. * PAD_SIZE - DOT_SIZE / 2.;
+        style.left = Val::Px(offset.x);
+        style.top = Val::Px(offset.y);
+    }
+    let counts: String = BudgetCategory::ALL
+        .into_iter()
+        .map(|category| {
+            format!(
+                "\n{} {}/{}",
+                category.name(),
+                ledger.count(category),
+                budget.get(category).max
+            )
+        })
+        .collect();
+    for mut text in &mut readouts {
+        text.sections[0].value = format!(
+            "move {:+.2} {:+.2}\nlook {:+.2} {:+.2}\ntrigger {:.2}\npressed {:?}\ndevice {:?}{}",
+            frame.motion.x,
+            frame.motion.y,
+            frame.look.x,
+            frame.look.y,
+            frame.trigger,
+            frame.pressed,
+            frame.device,
+            counts,
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Which device the player is using right now.
+//!
+//! [`ActiveInputDevice`] starts from the platform guess and then follows
+//! the most recent deliberate input. Prompts, layouts and menus that differ
+//! per device should read it rather than watching input themselves.
+//!
+//! Switching has hysteresis: the mouse has to travel
+//! [`InputDeviceConfig::mouse_distance`] in quick succession, gamepad sticks
+//! have to pass [`InputDeviceConfig::axis_threshold`], and no switch happens
+//! within [`InputDeviceConfig::min_dwell`] of the last one. A nudged mouse or
+//! a drifting stick therefore doesn't flip the prompts mid-fight.
+//!
+//! Plugging in a controller is deliberate enough on its own: it switches to
+//! the gamepad straight away and says so in a toast. Unplugging the one in
+//! use goes back to the platform guess. The physical controllers plugged in
+//! are kept in [`ConnectedGamepads`]; the touch sticks' virtual gamepad
+//! never connects, so it isn't among them.
+
+use bevy::{
+    input::{
+        gamepad::{GamepadConnection, GamepadConnectionEvent, GamepadEvent},
+        mouse::{MouseMotion, MouseWheel},
+    },
+    prelude::*,
+};
+
+use crate::{layout::InputMode, toast::Toast};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+pub enum InputDevice {
+    /// Keyboard and mouse.
+    #[default]
+    Keyboard,
+    Gamepad,
+    Touch,
+}
+
+#[derive(Resource, Reflect, Debug, Clone, Copy, Default)]
+#[reflect(Resource)]
+pub struct ActiveInputDevice {
+    pub device: InputDevice,
+    /// Seconds since the last switch.
+    since_switch: f32,
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct InputDeviceConfig {
+    /// Seconds after a switch during which no other switch happens.
+    pub min_dwell: f32,
+    /// Pixels the mouse must move, with older movement fading over
+    /// `mouse_memory`, before it counts as mouse use.
+    pub mouse_distance: f32,
+    /// Seconds for past mouse movement to be half forgotten.
+    pub mouse_memory: f32,
+    /// Gamepad stick deflection that counts as gamepad use.
+    pub axis_threshold: f32,
+}
+
+impl Default for InputDeviceConfig {
+    fn default() -> Self {
+        Self {
+            min_dwell: 0.75,
+            mouse_distance: 48.,
+            mouse_memory: 0.2,
+            axis_threshold: 0.35,
+        }
+    }
+}
+
+/// The physical gamepads plugged in, oldest first.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ConnectedGamepads(pub Vec<Gamepad>);
+
+impl ConnectedGamepads {
+    pub fn any(&self) -> bool {
+        !self.0.is_empty()
+    }
+}
+
+/// The device to start from, and to fall back to when a gamepad goes.
+fn platform_device() -> InputDevice {
+    match InputMode::detect() {
+        InputMode::Touch => InputDevice::Touch,
+        InputMode::Desktop => InputDevice::Keyboard,
+    }
+}
+
+pub struct InputDevicePlugin;
+
+impl Plugin for InputDevicePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ActiveInputDevice>()
+            .register_type::<InputDeviceConfig>()
+            .insert_resource(ActiveInputDevice {
+                device: platform_device(),
+                since_switch: 0.,
+            })
+            .init_resource::<InputDeviceConfig>()
+            .init_resource::<ConnectedGamepads>()
+            .add_systems(PreUpdate, (track_gamepads, detect_input_device).chain());
+    }
+}
+
+fn detect_input_device(
+    time: Res<Time<Real>>,
+    config: Res<InputDeviceConfig>,
+    mut active: ResMut<ActiveInputDevice>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    mut gamepad: EventReader<GamepadEvent>,
+    mut mouse_travel: Local<f32>,
+) {
+    let dt = time.delta_seconds();
+    active.bypass_change_detection().since_switch += dt;
+
+    // touch sticks report through a virtual gamepad, and browsers turn
+    // touches into mouse events too, so while a finger is down neither
+    // counts on its own
+    let touching = touches.iter().next().is_some() || touches.any_just_released();
+
+    *mouse_travel *= 0.5f32.powf(dt / config.mouse_memory);
+    *mouse_travel += motion.read().map(|event| event.delta.length()).sum::<f32>();
+    let mouse = !touching
+        && (*mouse_travel >= config.mouse_distance
+            || mouse_buttons.get_just_pressed().next().is_some()
+            || wheel.read().count() > 0);
+
+    let gamepad = gamepad
+        .read()
+        .filter(|event| match event {
+            GamepadEvent::Button(button) => button.value > 0.5,
+            GamepadEvent::Axis(axis) => axis.value.abs() >= config.axis_threshold,
+            GamepadEvent::Connection(_) => false,
+        })
+        .count()
+        > 0;
+
+    let candidate = if touches.any_just_pressed() {
+        Some(InputDevice::Touch)
+    } else if keys.get_just_pressed().next().is_some() || mouse {
+        Some(InputDevice::Keyboard)
+    } else if gamepad && !touching {
+        Some(InputDevice::Gamepad)
+    } else {
+        None
+    };
+
+    let Some(candidate) = candidate else {
+        return;
+    };
+    if candidate == active.device || active.since_switch < config.min_dwell {
+        return;
+    }
+    *active = ActiveInputDevice {
+        device: candidate,
+        since_switch: 0.,
+    };
+}
+
+fn track_gamepads(
+    mut connections: EventReader<GamepadConnectionEvent>,
+    mut connected: ResMut<ConnectedGamepads>,
+    mut active: ResMut<ActiveInputDevice>,
+    mut toasts: EventWriter<Toast>,
+) {
+    for event in connections.read() {
+        match &event.connection {
+            GamepadConnection::Connected(info) => {
+                if !connected.0.contains(&event.gamepad) {
+                    connected.0.push(event.gamepad);
+                }
+                toasts.send(Toast::new(format!("Controller connected: {}", info.name)));
+                *active = ActiveInputDevice {
+                    device: InputDevice::Gamepad,
+                    since_switch: 0.,
+                };
+            }
+            GamepadConnection::Disconnected => {
+                connected.0.retain(|gamepad| *gamepad != event.gamepad);
+                toasts.send(Toast::new("Controller disconnected"));
+                if active.device == InputDevice::Gamepad && !connected.any() {
+                    *active = ActiveInputDevice {
+                        device: platform_device(),
+                        since_switch: 0.,
+                    };
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! A fixed-rate simulation, drawn smoothly at any frame rate.
+//!
+//! Movement, collisions and hits run in [`FixedUpdate`] at
+//! [`SIMULATION_HZ`] ticks a second, so the player, enemies and shots move
+//! the same on a 60 Hz and a 120 Hz screen. Between ticks an
+//! [`Interpolated`] entity is drawn part of the way from where the tick
+//! before last left it to where the last tick did, by how far the clock is
+//! into the next one, and what the simulation left is put back in its
+//! `Transform` before the next tick runs. Systems in `Update` still see an
+//! entity where it is drawn.
+//!
+//! Anything that moves an interpolated entity outside the simulation, like
+//! a respawn, a teleport or a rewind playing back, is taken as where it now
+//! is, without easing into it. So is a jump of more than [`SNAP_DISTANCE`]
+//! in one tick, like wrapping around the arena's edge.
+
+use bevy::{prelude::*, transform::TransformSystem};
+
+/// Simulation ticks a second.
+pub const SIMULATION_HZ: f64 = 60.;
+
+/// A tick moving an entity further than this is a jump, not motion.
+const SNAP_DISTANCE: f32 = 100.;
+
+/// Draws the entity between its last two simulated positions.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct Interpolated {
+    previous: Vec3,
+    current: Vec3,
+    /// What was drawn, while it is in the `Transform` instead of `current`.
+    rendered: Option<Vec3>,
+}
+
+impl Interpolated {
+    fn snap(&mut self, translation: Vec3) {
+        self.previous = translation;
+        self.current = translation;
+        self.rendered = None;
+    }
+}
+
+pub struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Time::<Fixed>::from_hz(SIMULATION_HZ))
+            .add_systems(FixedFirst, begin_tick)
+            .add_systems(FixedLast, end_tick)
+            .add_systems(
+                PostUpdate,
+                interpolate.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Puts back the simulated position and keeps it as the tick's start.
+fn begin_tick(mut entities: Query<(&mut Transform, &mut Interpolated)>) {
+    for (mut transform, mut interpolated) in &mut entities {
+        if interpolated.is_added() {
+            interpolated.snap(transform.translation);
+            continue;
+        }
+        if let Some(rendered) = interpolated.rendered.take() {
+            if transform.translation == rendered {
+                transform.translation = interpolated.current;
+            } else {
+                // moved since it was drawn
+                interpolated.current = transform.translation;
+            }
+        }
+        interpolated.previous = transform.translation;
+    }
+}
+
+fn end_tick(mut entities: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in &mut entities {
+        interpolated.current = transform.translation;
+        if interpolated.previous.distance(interpolated.current) > SNAP_DISTANCE {
+            interpolated.previous = interpolated.current;
+        }
+    }
+}
+
+pub fn interpolate(
+    time: Res<Time<Fixed>>,
+    mut entities: Query<(&mut Transform, &mut Interpolated)>,
+) {
+    let t = time.overstep_fraction();
+    for (mut transform, mut interpolated) in &mut entities {
+        let expected = interpolated.rendered.unwrap_or(interpolated.current);
+        if interpolated.is_added() || transform.translation != expected {
+            interpolated.snap(transform.translation);
+            continue;
+        }
+        let drawn = interpolated.previous.lerp(interpolated.current, t);
+        transform.translation = drawn;
+        interpolated.rendered = Some(drawn);
+    }
+}
@@ -0,0 +1,467 @@
+//! Which controls are on screen.
+//!
+//! [`ControlLayout::Auto`] picks touch controls on phones and tablets and
+//! keyboard/mouse on desktop, and drops the touch controls for as long as a
+//! controller is plugged in; the settings screen can force either. On
+//! touch the move and look sticks and on-screen buttons are shown; on
+//! desktop the sticks are not spawned and a hint with the bindings is shown
+//! instead, written for the keyboard or the gamepad depending on the
+//! [`ActiveInputDevice`].
+//!
+//! The move stick takes the left half of the screen and the look stick the
+//! right, or the other way round with [`ControlSettings::swap_sticks`]. Only
+//! the screen sides swap: the move stick always drives [`Action::Move`]
+//! through the left gamepad stick mapping. HUD nodes that belong to a
+//! side are spawned with a [`Mirrored`] side rather than a fixed edge, and
+//! follow the sticks when they swap, so a left-handed layout is a mirror
+//! image of the default one.
+//!
+//! With [`StickMode::Fixed`] each stick sits at a set spot in its half; the
+//! floating and dynamic modes instead put the stick under the thumb
+//! wherever it first touches that half, and dynamic also drags it along
+//! when the thumb moves past its rim. Each stick ignores readings inside
+//! [`ControlSettings::touch_dead_zone`], and the smoothing scales what is
+//! left by [`ControlSettings::touch_sensitivity`].
+//!
+//! The sticks only exist while [`GameState::Playing`]: pausing or opening a
+//! menu removes them, so a thumb resting on the screen can't keep steering
+//! and touches go to the menu's buttons instead.
+//!
+//! [`Action::Move`]: crate::Action::Move
+
+use bevy::prelude::*;
+use bevy_touch_stick::{
+    prelude::*, TouchStickInteractionArea, TouchStickUiKnob, TouchStickUiOutline,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fallback::AssetFallback,
+    fonts::UiFonts,
+    input_device::{ActiveInputDevice, ConnectedGamepads, InputDevice},
+    settings::ControlSettings,
+    skin::StickSkinSettings,
+    state::{GameState, StateScoped},
+    virtual_buttons::VirtualButtonConfig,
+    Stick,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum ControlLayout {
+    /// Detected from the platform at startup.
+    #[default]
+    Auto,
+    Touch,
+    Desktop,
+}
+
+impl ControlLayout {
+    pub const ALL: [ControlLayout; 3] = [
+        ControlLayout::Auto,
+        ControlLayout::Touch,
+        ControlLayout::Desktop,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ControlLayout::Auto => "Automatic",
+            ControlLayout::Touch => "Touch",
+            ControlLayout::Desktop => "Keyboard",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|layout| *layout == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// The controls to show, with or without a physical gamepad plugged in.
+    pub fn resolve(self, gamepad: bool) -> InputMode {
+        match self {
+            ControlLayout::Auto if gamepad => InputMode::Desktop,
+            ControlLayout::Auto => InputMode::detect(),
+            ControlLayout::Touch => InputMode::Touch,
+            ControlLayout::Desktop => InputMode::Desktop,
+        }
+    }
+}
+
+/// Where a touch stick sits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum StickMode {
+    /// At the same spot in its half of the screen.
+    #[default]
+    Fixed,
+    /// Under the first touch in its half of the screen, staying there until
+    /// released.
+    Floating,
+    /// Under the first touch, and dragged along by the thumb past its rim.
+    Dynamic,
+}
+
+impl StickMode {
+    pub const ALL: [StickMode; 3] = [StickMode::Fixed, StickMode::Floating, StickMode::Dynamic];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StickMode::Fixed => "Fixed",
+            StickMode::Floating => "Floating",
+            StickMode::Dynamic => "Dynamic",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn stick_type(self) -> TouchStickType {
+        match self {
+            StickMode::Fixed => TouchStickType::Fixed,
+            StickMode::Floating => TouchStickType::Floating,
+            StickMode::Dynamic => TouchStickType::Dynamic,
+        }
+    }
+}
+
+pub const DEFAULT_TOUCH_DEAD_ZONE: f32 = 0.05;
+
+/// Touch dead zones offered on the settings screen, as a fraction of the
+/// stick's reach.
+const TOUCH_DEAD_ZONES: [f32; 5] = [0., 0.05, 0.1, 0.15, 0.2];
+
+/// Touch sensitivities offered on the settings screen.
+const TOUCH_SENSITIVITIES: [f32; 5] = [0.75, 1., 1.25, 1.5, 2.];
+
+/// The value after `value` in `steps`, wrapping from the last to the first.
+pub(crate) fn step(steps: &[f32], value: f32) -> f32 {
+    steps
+        .iter()
+        .copied()
+        .find(|step| *step > value + 0.001)
+        .unwrap_or(steps[0])
+}
+
+pub fn step_touch_dead_zone(dead_zone: f32) -> f32 {
+    step(&TOUCH_DEAD_ZONES, dead_zone)
+}
+
+pub fn step_touch_sensitivity(sensitivity: f32) -> f32 {
+    step(&TOUCH_SENSITIVITIES, sensitivity)
+}
+
+/// `value` if finite, clamped to the range of `steps`, otherwise `default`.
+pub(crate) fn clean_step(steps: &[f32], value: f32, default: f32) -> f32 {
+    if value.is_finite() {
+        value.clamp(steps[0], steps[steps.len() - 1])
+    } else {
+        default
+    }
+}
+
+pub fn clean_touch_dead_zone(dead_zone: f32) -> f32 {
+    clean_step(&TOUCH_DEAD_ZONES, dead_zone, DEFAULT_TOUCH_DEAD_ZONE)
+}
+
+pub fn clean_touch_sensitivity(sensitivity: f32) -> f32 {
+    clean_step(&TOUCH_SENSITIVITIES, sensitivity, 1.)
+}
+
+/// The controls currently in use, resolved from [`ControlLayout`].
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputMode {
+    #[default]
+    Touch,
+    Desktop,
+}
+
+impl InputMode {
+    /// Touch on mobile targets and on touch-capable browsers, desktop
+    /// everywhere else.
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let touch_points = web_sys::window()
+                .map(|window| window.navigator().max_touch_points())
+                .unwrap_or(0);
+            if touch_points > 0 {
+                InputMode::Touch
+            } else {
+                InputMode::Desktop
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if cfg!(any(target_os = "android", target_os = "ios")) {
+                InputMode::Touch
+            } else {
+                InputMode::Desktop
+            }
+        }
+    }
+}
+
+/// A half of the screen by the stick on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum StickSide {
+    Move,
+    Look,
+}
+
+/// Keeps a UI node `inset` logical pixels from the screen edge on `side`,
+/// whichever edge that is with [`ControlSettings::swap_sticks`].
+///
+/// The node's `left` and `right` are set from this, so its style should
+/// leave them alone.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Mirrored {
+    pub side: StickSide,
+    pub inset: f32,
+}
+
+impl Mirrored {
+    pub fn new(side: StickSide, inset: f32) -> Self {
+        Self { side, inset }
+    }
+
+    /// `left` and `right` for the node.
+    pub fn position(self, swap_sticks: bool) -> (Val, Val) {
+        let inset = Val::Px(self.inset);
+        if (self.side == StickSide::Look) != swap_sticks {
+            (Val::Auto, inset)
+        } else {
+            (inset, Val::Auto)
+        }
+    }
+}
+
+/// Root of an on-screen stick.
+#[derive(Component)]
+pub struct TouchStickRoot;
+
+#[derive(Component)]
+struct KeyHint;
+
+/// The bindings hint for `device`.
+fn hint_text(device: InputDevice, swap_sticks: bool) -> &'static str {
+    match device {
+        InputDevice::Keyboard => {
+            "WASD move  -  Mouse or arrows aim  -  Click/Space fire  -  Shift dash  -  Q/1-3 weapon  -  L lock  -  R rewind"
+        }
+        InputDevice::Gamepad => {
+            "Left stick move  -  Right stick aim  -  R1/R2 fire  -  A dash  -  X weapon  -  R3 lock  -  Y rewind  -  Start pause"
+        }
+        InputDevice::Touch if swap_sticks => "Drag right to move  -  Drag left to aim",
+        InputDevice::Touch => "Drag left to move  -  Drag right to aim",
+    }
+}
+
+pub struct LayoutPlugin;
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMode>().add_systems(
+            Update,
+            (
+                apply_control_layout.run_if(
+                    resource_changed::<ControlSettings>
+                        .or_else(resource_changed::<ConnectedGamepads>)
+                        .or_else(resource_changed::<VirtualButtonConfig>)
+                        .or_else(state_changed::<GameState>),
+                ),
+                update_key_hint.run_if(resource_changed::<ActiveInputDevice>),
+                place_mirrored,
+            )
+                .chain(),
+        );
+    }
+}
+
+fn apply_control_layout(
+    mut commands: Commands,
+    settings: Res<ControlSettings>,
+    state: Res<State<GameState>>,
+    mut mode: ResMut<InputMode>,
+    asset_server: Res<AssetServer>,
+    skin: Res<StickSkinSettings>,
+    device: Res<ActiveInputDevice>,
+    fonts: Res<UiFonts>,
+    buttons: Res<VirtualButtonConfig>,
+    gamepads: Res<ConnectedGamepads>,
+    mut applied: Local<Option<(InputMode, bool, bool, StickMode, f32, [f32; 2])>>,
+    sticks: Query<Entity, With<TouchStickRoot>>,
+    hints: Query<Entity, With<KeyHint>>,
+) {
+    let resolved = settings.layout.resolve(gamepads.any());
+    let playing = *state.get() == GameState::Playing;
+    let wanted = (
+        resolved,
+        settings.swap_sticks,
+        playing,
+        settings.stick_mode,
+        settings.touch_dead_zone,
+        [
+            buttons.reserved(StickSide::Move),
+            buttons.reserved(StickSide::Look),
+        ],
+    );
+    if *applied == Some(wanted) {
+        return;
+    }
+    *applied = Some(wanted);
+    if *mode != resolved {
+        *mode = resolved;
+    }
+
+    for entity in sticks.iter().chain(&hints) {
+        commands.entity(entity).despawn_recursive();
+    }
+    match resolved {
+        InputMode::Touch if !playing => {}
+        InputMode::Touch => {
+            for id in [Stick::Left, Stick::Right] {
+                spawn_stick(&mut commands, &asset_server, &skin, &settings, &buttons, id);
+            }
+        }
+        InputMode::Desktop => spawn_key_hint(
+            &mut commands,
+            &fonts,
+            hint_text(device.device, settings.swap_sticks),
+        ),
+    }
+}
+
+/// Spawns the move (left) or look (right) stick, whose touch area is its
+/// half of the screen short of the edge the buttons there keep.
+fn spawn_stick(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    skin: &StickSkinSettings,
+    settings: &ControlSettings,
+    buttons: &VirtualButtonConfig,
+    id: Stick,
+) {
+    // mapped as gamepad sticks (through bevy_input), which leafwing reads
+    // like a normal gamepad
+    let (mapping, side) = match id {
+        Stick::Left => (TouchStickGamepadMapping::LEFT_STICK, StickSide::Move),
+        Stick::Right => (TouchStickGamepadMapping::RIGHT_STICK, StickSide::Look),
+    };
+    let reserved = buttons.reserved(side);
+    let on_left = (side == StickSide::Move) != settings.swap_sticks;
+    let (left, right) = if on_left {
+        (Val::Px(reserved), Val::Percent(50.))
+    } else {
+        (Val::Percent(50.), Val::Px(reserved))
+    };
+    let (height, bottom) = match settings.stick_mode {
+        // a fixed stick rests at the centre of its area
+        StickMode::Fixed => (Val::Percent(100.), Val::Percent(-25.)),
+        // a moving one can start anywhere below the HUD and pause button
+        StickMode::Floating | StickMode::Dynamic => (Val::Percent(75.), Val::Percent(0.)),
+    };
+    commands
+        .spawn((
+            TouchStickRoot,
+            StateScoped(GameState::Playing),
+            mapping,
+            TouchStickUiBundle {
+                stick: TouchStick {
+                    id,
+                    radius: 10.0,
+                    stick_type: settings.stick_mode.stick_type(),
+                    dead_zone: settings.touch_dead_zone,
+                    // crate::touch_owners hands it its touches instead
+                    interactable_zone: Rect {
+                        min: Vec2::INFINITY,
+                        max: Vec2::NEG_INFINITY,
+                    },
+                    ..default()
+                },
+                style: Style {
+                    height,
+                    position_type: PositionType::Absolute,
+                    left,
+                    right,
+                    bottom,
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        // which would set the area back from the node every frame
+        .remove::<TouchStickInteractionArea>()
+        .with_children(|parent| {
+            parent.spawn((
+                TouchStickUiKnob,
+                AssetFallback::Circle,
+                ImageBundle {
+                    image: asset_server.load(skin.skin.knob_path().to_string()).into(),
+                    style: Style {
+                        width: Val::Px(75.),
+                        height: Val::Px(75.),
+                        ..default()
+                    },
+                    background_color: skin.tint.into(),
+                    ..default()
+                },
+            ));
+            parent.spawn((
+                TouchStickUiOutline,
+                AssetFallback::Ring,
+                ImageBundle {
+                    image: asset_server
+                        .load(skin.skin.outline_path().to_string())
+                        .into(),
+                    style: Style {
+                        width: Val::Px(150.),
+                        height: Val::Px(150.),
+                        ..default()
+                    },
+                    background_color: skin.tint.into(),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn place_mirrored(settings: Res<ControlSettings>, mut nodes: Query<(Ref<Mirrored>, &mut Style)>) {
+    for (mirrored, mut style) in &mut nodes {
+        if !settings.is_changed() && !mirrored.is_changed() {
+            continue;
+        }
+        let (left, right) = mirrored.position(settings.swap_sticks);
+        style.left = left;
+        style.right = right;
+    }
+}
+
+fn spawn_key_hint(commands: &mut Commands, fonts: &UiFonts, text: &str) {
+    commands.spawn((
+        KeyHint,
+        TextBundle::from_section(text, fonts.style(16., Color::rgba(1., 1., 1., 0.5))).with_style(
+            Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(12.),
+                left: Val::Px(12.),
+                ..default()
+            },
+        ),
+    ));
+}
+
+fn update_key_hint(
+    device: Res<ActiveInputDevice>,
+    settings: Res<ControlSettings>,
+    mut hints: Query<&mut Text, With<KeyHint>>,
+) {
+    let text = hint_text(device.device, settings.swap_sticks);
+    for mut hint in &mut hints {
+        if hint.sections[0].value != text {
+            hint.sections[0].value = text.to_string();
+        }
+    }
+}
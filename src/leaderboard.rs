@@ -0,0 +1,168 @@
+//! The online top scores, shown on the game over screen.
+//!
+//! When a run ends its score is posted as JSON to
+//! [`LeaderboardConfig::endpoint`], which answers with the best
+//! [`LeaderboardConfig::top`] scores. The request goes through `ehttp`, a
+//! thread natively and `fetch` on the web, so the game keeps running while
+//! it is out; the answer is picked up by a system once it's back. Without
+//! an endpoint, or when the request fails, the board falls back to the
+//! player's own best score from the [`Stats`].
+
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{fonts::UiFonts, replay, score::Score, state::GameState, stats::Stats};
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct LeaderboardConfig {
+    /// Where scores are posted; empty keeps the board offline.
+    pub endpoint: String,
+    /// Name the score is posted under.
+    pub player_name: String,
+    /// Scores shown.
+    pub top: usize,
+}
+
+impl Default for LeaderboardConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            player_name: "Player".to_string(),
+            top: 10,
+        }
+    }
+}
+
+/// What is posted at the end of a run.
+#[derive(Serialize, Debug, Clone)]
+struct Submission<'a> {
+    name: &'a str,
+    score: u64,
+    wave: u32,
+    top: usize,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct LeaderboardEntry {
+    name: String,
+    score: u64,
+}
+
+/// Where an answer lands once the request is back.
+type Reply = Arc<Mutex<Option<Result<Vec<LeaderboardEntry>, String>>>>;
+
+#[derive(Resource, Debug, Default)]
+enum Leaderboard {
+    #[default]
+    Idle,
+    Submitting(Reply),
+    Loaded(Vec<LeaderboardEntry>),
+    Offline,
+}
+
+/// Text on the game over screen the board is written into.
+#[derive(Component)]
+pub struct LeaderboardText;
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<LeaderboardConfig>()
+            .init_resource::<LeaderboardConfig>()
+            .init_resource::<Leaderboard>()
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                submit_score.run_if(not(replay::is_replaying)),
+            )
+            .add_systems(
+                Update,
+                (receive_board, show_board)
+                    .chain()
+                    .run_if(in_state(GameState::GameOver)),
+            );
+    }
+}
+
+fn submit_score(
+    config: Res<LeaderboardConfig>,
+    score: Res<Score>,
+    mut leaderboard: ResMut<Leaderboard>,
+) {
+    if config.endpoint.is_empty() {
+        *leaderboard = Leaderboard::Offline;
+        return;
+    }
+    let submission = Submission {
+        name: &config.player_name,
+        score: score.points,
+        wave: score.wave,
+        top: config.top,
+    };
+    let Ok(body) = serde_json::to_vec(&submission) else {
+        *leaderboard = Leaderboard::Offline;
+        return;
+    };
+    let mut request = ehttp::Request::post(&config.endpoint, body);
+    request.headers.insert("Content-Type", "application/json");
+
+    let reply = Reply::default();
+    let slot = reply.clone();
+    ehttp::fetch(request, move |response| {
+        let board = response.and_then(|response| {
+            if !response.ok {
+                return Err(format!("{} {}", response.status, response.status_text));
+            }
+            serde_json::from_slice(&response.bytes).map_err(|err| err.to_string())
+        });
+        *slot.lock().unwrap() = Some(board);
+    });
+    *leaderboard = Leaderboard::Submitting(reply);
+}
+
+fn receive_board(mut leaderboard: ResMut<Leaderboard>) {
+    let Leaderboard::Submitting(reply) = &*leaderboard else {
+        return;
+    };
+    let Some(board) = reply.lock().unwrap().take() else {
+        return;
+    };
+    *leaderboard = match board {
+        Ok(entries) => Leaderboard::Loaded(entries),
+        Err(err) => {
+            warn!("couldn't reach the leaderboard: {err}");
+            Leaderboard::Offline
+        }
+    };
+}
+
+fn show_board(
+    config: Res<LeaderboardConfig>,
+    leaderboard: Res<Leaderboard>,
+    stats: Res<Stats>,
+    fonts: Res<UiFonts>,
+    mut texts: Query<(&mut Text, Ref<LeaderboardText>)>,
+) {
+    for (mut text, marker) in &mut texts {
+        if !leaderboard.is_changed() && !marker.is_added() {
+            continue;
+        }
+        let board = match &*leaderboard {
+            Leaderboard::Idle | Leaderboard::Submitting(_) => "Submitting score...".to_string(),
+            Leaderboard::Loaded(entries) if entries.is_empty() => "No scores yet".to_string(),
+            Leaderboard::Loaded(entries) => entries
+                .iter()
+                .take(config.top)
+                .enumerate()
+                .map(|(rank, entry)| format!("{}. {}  {}", rank + 1, entry.name, entry.score))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Leaderboard::Offline => format!("Leaderboard offline\nYour best: {}", stats.best_score),
+        };
+        *text = Text::from_section(board, fonts.style(18., Color::WHITE))
+            .with_justify(JustifyText::Center);
+    }
+}
@@ -0,0 +1,439 @@
+//! Levels made in the Tiled map editor.
+//!
+//! Maps saved as Tiled JSON (`.tmj`) load as [`Level`] assets. Sending
+//! [`LoadLevel`] with one replaces whatever level is in play once the map
+//! has loaded:
+//!
+//! - each tile layer is drawn from its tileset, one sprite per tile, and a
+//!   layer with a `collision` property set to true also gets a wall
+//!   collider on every tile, whether drawn or not;
+//! - the arena takes the size of the map, centred on the origin, so the
+//!   edge modes and camera stop at its edges;
+//! - an object of type `player_start` is where the player starts, now and
+//!   on every new run;
+//! - objects of type `enemy_spawner` become [`SpawnPoint`]s, which waves
+//!   open their portals at instead of the screen edges.
+//!
+//! Only tilesets embedded in the map are read, and tile flips are ignored.
+//! LDtk projects aren't supported.
+
+use std::io;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::{
+    config::GameConfig,
+    physics::{Collider, CollisionLayer},
+    Player,
+};
+
+/// Tiled keeps flip flags in the top bits of a tile id.
+const GID_MASK: u32 = 0x1FFF_FFFF;
+
+/// Below the player and enemies.
+const TILE_Z: f32 = -1.;
+
+#[derive(Asset, TypePath, Debug)]
+pub struct Level {
+    /// Size in tiles.
+    pub size: UVec2,
+    pub tile_size: Vec2,
+    pub layers: Vec<TileLayer>,
+    pub tilesets: Vec<Tileset>,
+    pub objects: Vec<LevelObject>,
+}
+
+impl Level {
+    /// Size in world units.
+    pub fn world_size(&self) -> Vec2 {
+        self.size.as_vec2() * self.tile_size
+    }
+
+    /// A point in Tiled's pixel coordinates, whose origin is the top left
+    /// corner and whose y points down, in the world.
+    pub fn to_world(&self, point: Vec2) -> Vec2 {
+        let half = self.world_size() / 2.;
+        Vec2::new(point.x - half.x, half.y - point.y)
+    }
+
+    /// The centre of the tile at `index` in a layer, in the world.
+    pub fn tile_position(&self, index: usize) -> Vec2 {
+        let width = self.size.x.max(1) as usize;
+        let cell = Vec2::new((index % width) as f32, (index / width) as f32);
+        self.to_world((cell + 0.5) * self.tile_size)
+    }
+
+    /// The index in [`Self::tilesets`] of the tileset `gid` belongs to, and
+    /// the tile's index within it.
+    pub fn tile(&self, gid: u32) -> Option<(usize, usize)> {
+        let gid = gid & GID_MASK;
+        let (index, tileset) = self
+            .tilesets
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, tileset)| gid >= tileset.first_gid)?;
+        let tile = gid - tileset.first_gid;
+        (tile < tileset.tile_count).then_some((index, tile as usize))
+    }
+}
+
+#[derive(Debug)]
+pub struct TileLayer {
+    pub name: String,
+    /// Tile ids row by row from the top left, 0 where empty.
+    pub tiles: Vec<u32>,
+    pub collision: bool,
+}
+
+#[derive(Debug)]
+pub struct Tileset {
+    pub first_gid: u32,
+    pub image: Handle<Image>,
+    pub tile_size: Vec2,
+    pub columns: u32,
+    pub tile_count: u32,
+    pub margin: f32,
+    pub spacing: f32,
+}
+
+#[derive(Debug)]
+pub struct LevelObject {
+    /// The object's type, or class, in Tiled.
+    pub kind: String,
+    pub name: String,
+    /// Centre of the object, in the world.
+    pub position: Vec2,
+}
+
+/// Replace the level in play with this one once it has loaded.
+#[derive(Event, Debug, Clone)]
+pub struct LoadLevel(pub Handle<Level>);
+
+/// Where a wave may open a portal.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SpawnPoint;
+
+/// Parent of everything spawned for the current level.
+#[derive(Component)]
+struct LevelRoot;
+
+/// The level asked for last, and whether it still has to be spawned.
+#[derive(Resource, Debug)]
+struct CurrentLevel {
+    handle: Handle<Level>,
+    pending: bool,
+}
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Level>()
+            .register_asset_loader(LevelLoader)
+            .add_event::<LoadLevel>()
+            .add_systems(
+                Update,
+                (
+                    request_level,
+                    spawn_level.run_if(resource_exists::<CurrentLevel>),
+                )
+                    .chain(),
+            );
+        #[cfg(debug_assertions)]
+        {
+            use crate::console::{self, AddConsoleCommand};
+
+            app.add_console_command("level", "level <path>", |world, args| {
+                let path: String = console::arg(args, 0, "path")?;
+                let handle = world.resource::<AssetServer>().load(path.clone());
+                world.send_event(LoadLevel(handle));
+                Ok(format!("loading {path}"))
+            });
+        }
+    }
+}
+
+fn request_level(mut commands: Commands, mut requests: EventReader<LoadLevel>) {
+    if let Some(LoadLevel(handle)) = requests.read().last() {
+        commands.insert_resource(CurrentLevel {
+            handle: handle.clone(),
+            pending: true,
+        });
+    }
+}
+
+fn spawn_level(
+    mut commands: Commands,
+    mut current: ResMut<CurrentLevel>,
+    asset_server: Res<AssetServer>,
+    levels: Res<Assets<Level>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut config: ResMut<GameConfig>,
+    roots: Query<Entity, With<LevelRoot>>,
+    mut players: Query<&mut Transform, With<Player>>,
+) {
+    if !current.pending {
+        return;
+    }
+    let Some(level) = levels.get(&current.handle) else {
+        if asset_server.load_state(&current.handle) == LoadState::Failed {
+            warn!("level {:?} failed to load", current.handle.path());
+            current.pending = false;
+        }
+        return;
+    };
+    current.pending = false;
+
+    for entity in &roots {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let start = level
+        .objects
+        .iter()
+        .find(|object| object.kind == "player_start")
+        .map_or(Vec2::ZERO, |object| object.position);
+    config.arena.size = level.world_size();
+    config.arena.start = start;
+    if let Ok(mut transform) = players.get_single_mut() {
+        transform.translation = start.extend(transform.translation.z);
+    }
+
+    let atlases: Vec<_> = level
+        .tilesets
+        .iter()
+        .map(|tileset| {
+            let rows = tileset.tile_count.div_ceil(tileset.columns.max(1));
+            layouts.add(TextureAtlasLayout::from_grid(
+                tileset.tile_size,
+                tileset.columns as usize,
+                rows as usize,
+                Some(Vec2::splat(tileset.spacing)),
+                Some(Vec2::splat(tileset.margin)),
+            ))
+        })
+        .collect();
+
+    commands
+        .spawn((LevelRoot, SpatialBundle::default()))
+        .with_children(|parent| {
+            for (depth, layer) in level.layers.iter().enumerate() {
+                // later layers draw over earlier ones
+                let z = TILE_Z + depth as f32 * 0.01;
+                parent
+                    .spawn((Name::new(layer.name.clone()), SpatialBundle::default()))
+                    .with_children(|parent| {
+                        for (index, &gid) in layer.tiles.iter().enumerate() {
+                            if gid & GID_MASK == 0 {
+                                continue;
+                            }
+                            let position = level.tile_position(index);
+                            let mut tile = parent.spawn(SpatialBundle::from_transform(
+                                Transform::from_translation(position.extend(z)),
+                            ));
+                            if let Some((tileset, tile_index)) = level.tile(gid) {
+                                tile.insert((
+                                    Sprite::default(),
+                                    level.tilesets[tileset].image.clone(),
+                                    TextureAtlas {
+                                        layout: atlases[tileset].clone(),
+                                        index: tile_index,
+                                    },
+                                ));
+                            }
+                            if layer.collision {
+                                tile.insert(Collider::new(
+                                    level.tile_size.max_element() / 2.,
+                                    CollisionLayer::WALL,
+                                ));
+                            }
+                        }
+                    });
+            }
+            for object in &level.objects {
+                if object.kind == "enemy_spawner" {
+                    parent.spawn((
+                        SpawnPoint,
+                        Name::new(object.name.clone()),
+                        SpatialBundle::from_transform(Transform::from_translation(
+                            object.position.extend(0.),
+                        )),
+                    ));
+                }
+            }
+        });
+}
+
+#[derive(Default)]
+struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Level, io::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let map: TiledMap = serde_json::from_slice(&bytes)?;
+            Ok(map.into_level(load_context))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmj"]
+    }
+}
+
+/// The parts of a Tiled JSON map that levels use.
+#[derive(Deserialize)]
+struct TiledMap {
+    width: u32,
+    height: u32,
+    tilewidth: f32,
+    tileheight: f32,
+    #[serde(default)]
+    layers: Vec<TiledLayer>,
+    #[serde(default)]
+    tilesets: Vec<TiledTileset>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum TiledLayer {
+    Tilelayer {
+        name: String,
+        #[serde(default)]
+        data: Vec<u32>,
+        #[serde(default)]
+        properties: Vec<TiledProperty>,
+    },
+    Objectgroup {
+        #[serde(default)]
+        objects: Vec<TiledObject>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct TiledProperty {
+    name: String,
+    value: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct TiledObject {
+    #[serde(default, rename = "type", alias = "class")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+}
+
+#[derive(Deserialize)]
+struct TiledTileset {
+    firstgid: u32,
+    /// Set instead of everything else for a tileset in its own file.
+    source: Option<String>,
+    image: Option<String>,
+    #[serde(default)]
+    tilewidth: f32,
+    #[serde(default)]
+    tileheight: f32,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    margin: f32,
+    #[serde(default)]
+    spacing: f32,
+}
+
+impl TiledMap {
+    fn into_level(self, load_context: &mut LoadContext) -> Level {
+        let directory = load_context
+            .path()
+            .parent()
+            .map(|path| path.to_path_buf())
+            .unwrap_or_default();
+        let tilesets = self
+            .tilesets
+            .into_iter()
+            .filter_map(|tileset| {
+                if let Some(source) = &tileset.source {
+                    warn!("skipping external tileset {source}, embed it in the map instead");
+                    return None;
+                }
+                let image = tileset.image?;
+                Some(Tileset {
+                    first_gid: tileset.firstgid,
+                    image: load_context.load(directory.join(image)),
+                    tile_size: Vec2::new(tileset.tilewidth, tileset.tileheight),
+                    columns: tileset.columns,
+                    tile_count: tileset.tilecount,
+                    margin: tileset.margin,
+                    spacing: tileset.spacing,
+                })
+            })
+            .collect();
+
+        let mut level = Level {
+            size: UVec2::new(self.width, self.height),
+            tile_size: Vec2::new(self.tilewidth, self.tileheight),
+            layers: Vec::new(),
+            tilesets,
+            objects: Vec::new(),
+        };
+        for layer in self.layers {
+            match layer {
+                TiledLayer::Tilelayer {
+                    name,
+                    data,
+                    properties,
+                } => {
+                    let collision = properties.iter().any(|property| {
+                        property.name == "collision" && property.value.as_bool() == Some(true)
+                    });
+                    level.layers.push(TileLayer {
+                        name,
+                        tiles: data,
+                        collision,
+                    });
+                }
+                TiledLayer::Objectgroup { objects } => {
+                    for object in objects {
+                        let centre =
+                            Vec2::new(object.x + object.width / 2., object.y + object.height / 2.);
+                        let position = level.to_world(centre);
+                        level.objects.push(LevelObject {
+                            kind: object.kind,
+                            name: object.name,
+                            position,
+                        });
+                    }
+                }
+                TiledLayer::Other => {}
+            }
+        }
+        level
+    }
+}
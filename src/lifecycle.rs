@@ -0,0 +1,44 @@
+//! The app going to the background and coming back.
+//!
+//! On Android and iOS a backgrounded app is suspended, which winit reports
+//! as [`ApplicationLifetime`] events; the app gets one more frame before it
+//! stops. [`AppSuspended`] follows them, and what must react does so on that
+//! frame: a run in progress [pauses](crate::pause), audio
+//! [pauses](crate::audio), and the [virtual clock](crate::game_time)
+//! freezes, so `FixedUpdate` doesn't try to catch up on the time spent away
+//! once the app is back. Resuming brings the audio back where it stopped
+//! and shows the pause menu, so the player picks when play continues.
+//!
+//! Focus loss on the desktop and the web is handled by the pause module on
+//! its own, as it depends on [`GameplaySettings::pause_on_blur`]; the run
+//! is saved on both in [`crate::save`].
+//!
+//! [`GameplaySettings::pause_on_blur`]: crate::settings::GameplaySettings::pause_on_blur
+
+use bevy::{prelude::*, window::ApplicationLifetime};
+
+/// Whether the app is, or is about to be, in the background.
+#[derive(Resource, Debug, Default)]
+pub struct AppSuspended(pub bool);
+
+pub struct LifecyclePlugin;
+
+impl Plugin for LifecyclePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AppSuspended>()
+            .add_systems(First, track_lifetime);
+    }
+}
+
+fn track_lifetime(
+    mut lifetime: EventReader<ApplicationLifetime>,
+    mut suspended: ResMut<AppSuspended>,
+) {
+    for event in lifetime.read() {
+        let now = matches!(event, ApplicationLifetime::Suspended);
+        if suspended.0 != now {
+            info!("app {}", if now { "suspended" } else { "resumed" });
+            suspended.0 = now;
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Target lock.
+//!
+//! Pressing [`Action::Lock`] locks onto the nearest enemy in the aim cone,
+//! and pressing it again releases the lock. On touch screens, a quick tap
+//! on the look stick [presses it](crate::gestures). While locked, [`TargetLock::aim`]
+//! points at that enemy instead of re-picking the nearest every frame. When
+//! the target dies or leaves [`LockConfig::range`], the lock moves to the
+//! next nearest enemy, or clears. Pushing the look stick firmly always takes
+//! over from the lock. Aim that isn't locked is bent by
+//! [aim assist](crate::accessibility), when it is on.
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    camera::CameraView, enemy::Enemy, state::GameState, targeting::EnemyGrid, Action, Player,
+};
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct LockConfig {
+    /// Furthest an enemy can be to be locked, or to stay locked.
+    pub range: f32,
+    /// Half-angle, in radians, of the cone a new lock is picked from.
+    pub half_angle: f32,
+    /// Move the lock to the next nearest enemy when the target is lost,
+    /// instead of clearing it.
+    pub retarget: bool,
+    /// Look stick magnitude above which manual aim overrides the lock.
+    pub manual_override: f32,
+}
+
+impl Default for LockConfig {
+    fn default() -> Self {
+        Self {
+            range: 400.,
+            half_angle: 0.35,
+            retarget: true,
+            manual_override: 0.5,
+        }
+    }
+}
+
+/// The locked enemy and where it was this frame.
+#[derive(Resource, Debug, Default)]
+pub struct TargetLock {
+    pub target: Option<(Entity, Vec2)>,
+    /// Where the enemy aim assist pulls towards is, and how hard, `0..=1`.
+    pub assist: Option<(Vec2, f32)>,
+}
+
+impl TargetLock {
+    /// Normalized aim direction from `origin`: the look stick (`manual`) when
+    /// it is pushed past [`LockConfig::manual_override`], otherwise the
+    /// locked target, otherwise whatever direction the look stick points.
+    /// Aim taken from the look stick is turned towards [`Self::assist`].
+    pub fn aim(&self, config: &LockConfig, origin: Vec2, manual: Vec2) -> Option<Vec2> {
+        let manual_aim = || manual.try_normalize().map(|aim| self.assisted(origin, aim));
+        if manual.length() >= config.manual_override {
+            return manual_aim();
+        }
+        self.target
+            .and_then(|(_, position)| (position - origin).try_normalize())
+            .or_else(manual_aim)
+    }
+
+    /// `aim` turned towards the aim assist's enemy, by its strength.
+    fn assisted(&self, origin: Vec2, aim: Vec2) -> Vec2 {
+        let Some((position, strength)) = self.assist else {
+            return aim;
+        };
+        match (position - origin).try_normalize() {
+            Some(to_target) => {
+                Vec2::from_angle(aim.angle_between(to_target) * strength).rotate(aim)
+            }
+            None => aim,
+        }
+    }
+}
+
+const RETICLE_SIZE: f32 = 30.;
+const RETICLE_CORNER: Vec2 = Vec2::new(8., 2.);
+const RETICLE_COLOR: Color = Color::rgb(1., 0.85, 0.2);
+const RETICLE_SPIN: f32 = 1.5;
+
+#[derive(Component)]
+struct LockReticle;
+
+pub struct LockPlugin;
+
+impl Plugin for LockPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<LockConfig>()
+            .init_resource::<LockConfig>()
+            .init_resource::<TargetLock>()
+            .add_systems(Startup, spawn_reticle)
+            .add_systems(
+                Update,
+                (toggle_lock, track_lock, update_reticle)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Look stick direction, falling back to the way the player faces.
+fn aim_direction(
+    transform: &Transform,
+    action_state: &ActionState<Action>,
+    view: &CameraView,
+) -> Vec2 {
+    action_state
+        .clamped_axis_pair(&Action::Look)
+        .and_then(|axis| view.to_world(axis.xy()).try_normalize())
+        .unwrap_or_else(|| (transform.rotation * Vec3::X).truncate())
+}
+
+fn toggle_lock(
+    config: Res<LockConfig>,
+    enemies: Res<EnemyGrid>,
+    view: Res<CameraView>,
+    mut lock: ResMut<TargetLock>,
+    players: Query<(&Transform, &ActionState<Action>), With<Player>>,
+) {
+    let Ok((transform, action_state)) = players.get_single() else {
+        return;
+    };
+    if !action_state.just_pressed(&Action::Lock) {
+        return;
+    }
+
+    lock.target = match lock.target {
+        Some(_) => None,
+        None => enemies.nearest_in_cone(
+            transform.translation.truncate(),
+            aim_direction(transform, action_state, &view),
+            config.half_angle,
+            config.range,
+        ),
+    };
+}
+
+fn track_lock(
+    config: Res<LockConfig>,
+    grid: Res<EnemyGrid>,
+    mut lock: ResMut<TargetLock>,
+    players: Query<&Transform, With<Player>>,
+    enemies: Query<&GlobalTransform, With<Enemy>>,
+) {
+    let Some((target, _)) = lock.target else {
+        return;
+    };
+    let Ok(player) = players.get_single() else {
+        lock.target = None;
+        return;
+    };
+    let origin = player.translation.truncate();
+
+    let current = enemies
+        .get(target)
+        .ok()
+        .map(|transform| transform.translation().truncate())
+        .filter(|position| position.distance(origin) <= config.range);
+    lock.target = match current {
+        Some(position) => Some((target, position)),
+        None if config.retarget => grid.nearest_enemy(origin).filter(|(entity, position)| {
+            *entity != target && position.distance(origin) <= config.range
+        }),
+        None => None,
+    };
+}
+
+fn spawn_reticle(mut commands: Commands) {
+    commands
+        .spawn((
+            LockReticle,
+            SpatialBundle {
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            // four corner brackets around the target
+            for (i, corner) in [
+                Vec2::new(1., 1.),
+                Vec2::new(-1., 1.),
+                Vec2::new(-1., -1.),
+                Vec2::new(1., -1.),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                let angle = std::f32::consts::FRAC_PI_2 * i as f32;
+                parent.spawn(SpriteBundle {
+                    transform: Transform {
+                        translation: (corner * RETICLE_SIZE / 2.).extend(0.),
+                        rotation: Quat::from_rotation_z(angle - std::f32::consts::FRAC_PI_4),
+                        ..default()
+                    },
+                    sprite: Sprite {
+                        color: RETICLE_COLOR,
+                        custom_size: Some(RETICLE_CORNER),
+                        ..default()
+                    },
+                    ..default()
+                });
+            }
+        });
+}
+
+fn update_reticle(
+    time: Res<Time>,
+    lock: Res<TargetLock>,
+    mut reticles: Query<(&mut Transform, &mut Visibility), With<LockReticle>>,
+) {
+    for (mut transform, mut visibility) in &mut reticles {
+        let Some((_, position)) = lock.target else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        transform.translation = position.extend(2.);
+        transform.rotate_z(RETICLE_SPIN * time.delta_seconds());
+        *visibility = Visibility::Visible;
+    }
+}
@@ -1,9 +1,140 @@
+// Bevy systems take their data as parameters, spelled out as query types
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
 use std::f32::consts::PI;
 
-use bevy::{asset::AssetMetaCheck, prelude::*};
+use bevy::{app::PluginGroupBuilder, asset::AssetMetaCheck, prelude::*};
+#[cfg(all(debug_assertions, not(feature = "headless")))]
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use bevy_touch_stick::{prelude::*, TouchStickUiKnob, TouchStickUiOutline};
+use bevy_touch_stick::prelude::*;
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use abilities::{Dash, Dashing, Rewind, RewindHistory, Rewinding};
+use ally::AllyDeploy;
+use camera::{CameraMode, CameraView, MainCamera};
+use config::{GameConfig, Palette, PlayerNose};
+use events::{PlayerMoved, PlayerSpawned, PLAYER_MOVED_INTERVAL};
+use fire::FireCooldown;
+use health::Health;
+use interpolation::Interpolated;
+use lock::{LockConfig, TargetLock};
+use motion::{MotionConfig, Velocity};
+use physics::{Collider, CollisionLayer};
+use pickups::{stat_factor, PowerUp, StatModifiers};
+use progression::{move_speed_factor, Progression, UpgradeModifiers};
+use settings::DisplaySettings;
+use sprint::{Sprint, SprintConfig};
+use state::{GameState, RunScoped};
+use status::Statuses;
+use weapon::{Weapon, WeaponInventory};
+
+mod abilities;
+mod accessibility;
+mod achievements;
+mod ai;
+mod aim;
+mod ally;
+#[cfg(feature = "telemetry")]
+mod analytics;
+mod animation;
+mod audio;
+mod bench;
+mod boss;
+mod bounds;
+mod budget;
+mod calibration;
+mod camera;
+#[cfg(debug_assertions)]
+mod cheats;
+mod config;
+#[cfg(debug_assertions)]
+mod console;
+mod contact;
+mod cutscene;
+mod daily;
+mod danger;
+mod daynight;
+mod death;
+mod defs;
+mod despawn;
+mod difficulty;
+mod director;
+#[cfg(debug_assertions)]
+mod dummy;
+mod enemy;
+mod events;
+mod fallback;
+mod fire;
+mod floating_text;
+mod fonts;
+#[cfg(debug_assertions)]
+mod free_camera;
+mod fx;
+mod game_time;
+mod generation;
+mod gestures;
+mod haptics;
+mod health;
+mod health_bar;
+mod hud;
+mod i18n;
+mod indicators;
+#[cfg(debug_assertions)]
+mod input_debug;
+mod input_device;
+mod interpolation;
+mod layout;
+mod leaderboard;
+mod level;
+mod lifecycle;
+mod lock;
+mod menu;
+mod minimap;
+mod motion;
+mod mouse_aim;
+mod net;
+mod particles;
+mod pathfinding;
+mod pause;
+mod perf_overlay;
+mod photo;
+mod physics;
+mod pickups;
+mod pool;
+mod portal;
+mod postfx;
+mod progression;
+mod projectile;
+mod quality;
+mod remap;
+mod replay;
+mod rng;
+mod save;
+mod score;
+mod settings;
+mod skin;
+mod smoothing;
+mod spatial;
+mod spawner;
+mod sprint;
+mod state;
+mod stats;
+mod status;
+mod storage;
+mod targeting;
+mod telegraph;
+mod telemetry;
+mod toast;
+mod touch_owners;
+mod transition;
+mod trigger;
+mod tutorial;
+mod tween;
+mod virtual_buttons;
+mod weapon;
+mod weather;
+mod web;
 
 /// Marker type for our touch stick
 #[derive(Default, Reflect, Hash, Clone, PartialEq, Eq)]
@@ -13,53 +144,296 @@ enum Stick {
     Right,
 }
 
-#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect)]
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect, Serialize, Deserialize)]
 enum Action {
     Move,
     Look,
+    /// Fire at full rate while held.
+    Shoot,
+    /// Analog fire pressure, `0..=1`.
+    Trigger,
+    /// Lock onto, or release, a target.
+    Lock,
+    Rewind,
+    Dash,
+    /// Cycle to the next carried weapon.
+    SwitchWeapon,
+    /// Put down a turret; see [`ally`].
+    Deploy,
+    /// Camera zoom, positive zooming in.
+    Zoom,
 }
 
 fn main() {
+    let bench = bench::Bench::from_args(std::env::args().skip(1));
+    if bench.is_some() {
+        // before any plugin loads what it has stored
+        storage::disable();
+    }
+
     App::new()
         .insert_resource(ClearColor(Color::BLACK))
         .insert_resource(AssetMetaCheck::Never)
         .add_plugins((
-            DefaultPlugins,
+            default_plugins(),
             // add an inspector for easily changing settings at runtime
-            #[cfg(debug_assertions)]
+            #[cfg(all(debug_assertions, not(feature = "headless")))]
             WorldInspectorPlugin::default(),
             // add the plugin
             TouchStickPlugin::<Stick>::default(),
             // add leafwing plugin
             InputManagerPlugin::<Action>::default(),
         ))
+        .add_plugins((
+            events::GameEventsPlugin,
+            game_time::GameTimePlugin,
+            health::HealthPlugin,
+            toast::ToastPlugin,
+            stats::StatsPlugin,
+            settings::SettingsPlugin,
+            skin::SkinPlugin,
+            targeting::TargetingPlugin,
+            physics::PhysicsPlugin,
+            aim::AimPlugin,
+            web::WebPlugin,
+            rng::RngPlugin,
+            weapon::WeaponPlugin,
+            enemy::EnemyPlugin,
+            telegraph::TelegraphPlugin,
+        ))
+        .add_plugins((
+            save::SavePlugin,
+            config::ConfigPlugin,
+            trigger::TriggerPlugin,
+            pause::PausePlugin,
+            sprint::SprintPlugin,
+            telemetry::TelemetryPlugin,
+            health_bar::HealthBarPlugin,
+            lock::LockPlugin,
+            fallback::FallbackPlugin,
+            ai::AiPlugin,
+            abilities::AbilitiesPlugin,
+            layout::LayoutPlugin,
+            quality::QualityPlugin,
+            tween::TweenPlugin,
+            camera::CameraPlugin,
+        ))
+        .add_plugins((
+            score::ScorePlugin,
+            death::DeathPlugin,
+            difficulty::DifficultyPlugin,
+            danger::DangerPlugin,
+            projectile::ProjectilePlugin,
+            budget::BudgetPlugin,
+            state::StatePlugin,
+            remap::RemapPlugin,
+            smoothing::SmoothingPlugin,
+            input_device::InputDevicePlugin,
+            calibration::CalibrationPlugin,
+            #[cfg(debug_assertions)]
+            input_debug::InputDebugPlugin,
+            #[cfg(debug_assertions)]
+            free_camera::FreeCameraPlugin,
+            #[cfg(debug_assertions)]
+            cheats::CheatsPlugin,
+            #[cfg(debug_assertions)]
+            console::ConsolePlugin,
+        ))
+        .add_plugins((
+            portal::PortalPlugin,
+            despawn::DespawnPlugin,
+            fonts::FontsPlugin,
+            bounds::BoundsPlugin,
+            particles::ParticlesPlugin,
+            mouse_aim::MouseAimPlugin,
+            fire::FirePlugin,
+            spawner::SpawnerPlugin,
+            contact::ContactPlugin,
+            menu::MenuPlugin,
+            weather::WeatherPlugin,
+            level::LevelPlugin,
+            generation::GenerationPlugin,
+            #[cfg(debug_assertions)]
+            dummy::DummyPlugin,
+        ))
+        .add_plugins((
+            animation::AnimationPlugin,
+            audio::AudioPlugin,
+            hud::HudPlugin,
+            gestures::GesturesPlugin,
+            virtual_buttons::VirtualButtonsPlugin,
+            daynight::DayNightPlugin,
+            director::DirectorPlugin,
+            pickups::PickupsPlugin,
+            progression::ProgressionPlugin,
+            pathfinding::PathfindingPlugin,
+            net::NetPlugin,
+            leaderboard::LeaderboardPlugin,
+            replay::ReplayPlugin,
+            perf_overlay::PerfOverlayPlugin,
+            lifecycle::LifecyclePlugin,
+        ))
+        .add_plugins((
+            interpolation::InterpolationPlugin,
+            haptics::HapticsPlugin,
+            tutorial::TutorialPlugin,
+            i18n::I18nPlugin,
+            defs::DefsPlugin,
+            minimap::MinimapPlugin,
+            floating_text::FloatingTextPlugin,
+            boss::BossPlugin,
+            status::StatusPlugin,
+            postfx::PostFxPlugin,
+            photo::PhotoPlugin,
+            achievements::AchievementsPlugin,
+            daily::DailyPlugin,
+            fx::FxPlugin,
+        ))
+        .add_plugins((
+            accessibility::AccessibilityPlugin,
+            transition::TransitionPlugin,
+            ally::AllyPlugin,
+            motion::MotionPlugin,
+            indicators::IndicatorsPlugin,
+            cutscene::CutscenePlugin,
+            #[cfg(feature = "telemetry")]
+            analytics::AnalyticsPlugin,
+            touch_owners::TouchOwnersPlugin,
+            bench::BenchPlugin(bench),
+        ))
+        .init_state::<GameState>()
         .add_systems(Startup, setup)
-        .add_systems(Update, move_player)
+        .add_systems(OnEnter(GameState::Playing), spawn_player)
+        .add_systems(
+            FixedUpdate,
+            (move_player, bounds::keep_in_bounds)
+                .chain()
+                .after(abilities::play_dash)
+                .after(motion::apply_impulses)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(Update, look_player.run_if(in_state(GameState::Playing)))
         .run();
 }
 
+/// Bevy's plugins, with a window and a renderer.
+#[cfg(not(feature = "headless"))]
+fn default_plugins() -> PluginGroupBuilder {
+    DefaultPlugins.build()
+}
+
+/// Bevy's plugins without a window or a renderer, looping as fast as it
+/// can, for [benchmarks](bench) on machines with neither.
+#[cfg(feature = "headless")]
+fn default_plugins() -> PluginGroupBuilder {
+    use bevy::{
+        app::ScheduleRunnerPlugin,
+        render::{settings::WgpuSettings, RenderPlugin},
+        window::ExitCondition,
+        winit::WinitPlugin,
+    };
+
+    DefaultPlugins
+        .build()
+        .set(WindowPlugin {
+            primary_window: None,
+            // with no window to close, it would quit straight away
+            exit_condition: ExitCondition::DontExit,
+            close_when_requested: false,
+        })
+        .set(RenderPlugin {
+            render_creation: WgpuSettings {
+                backends: None,
+                ..default()
+            }
+            .into(),
+            ..default()
+        })
+        .disable::<WinitPlugin>()
+        .add(ScheduleRunnerPlugin::run_loop(std::time::Duration::ZERO))
+}
+
+/// Health the player starts a run with, before upgrades.
+const PLAYER_HEALTH: f32 = 100.;
+
 #[derive(Component)]
 struct Player {
     max_speed: f32,
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.spawn(Camera2dBundle {
-        transform: Transform::from_xyz(0., 0., 5.0),
-        ..default()
-    });
+/// The bindings a new player starts with.
+fn default_input_map() -> InputMap<Action> {
+    InputMap::default()
+        .insert(Action::Move, DualAxis::left_stick())
+        .insert(Action::Move, VirtualDPad::wasd())
+        .insert(Action::Move, VirtualDPad::dpad())
+        .insert(Action::Look, DualAxis::right_stick())
+        .insert(Action::Look, VirtualDPad::arrow_keys())
+        .insert(Action::Shoot, KeyCode::Space)
+        .insert(Action::Shoot, MouseButton::Left)
+        .insert(Action::Shoot, GamepadButtonType::RightTrigger)
+        .insert(Action::Trigger, GamepadButtonType::RightTrigger2)
+        .insert(Action::Lock, GamepadButtonType::RightThumb)
+        .insert(Action::Lock, KeyCode::KeyL)
+        .insert(Action::Rewind, GamepadButtonType::North)
+        .insert(Action::Rewind, KeyCode::KeyR)
+        .insert(Action::Dash, GamepadButtonType::South)
+        .insert(Action::Dash, KeyCode::ShiftLeft)
+        .insert(Action::SwitchWeapon, GamepadButtonType::West)
+        .insert(Action::SwitchWeapon, KeyCode::KeyQ)
+        .insert(Action::Deploy, GamepadButtonType::LeftTrigger)
+        .insert(Action::Deploy, KeyCode::KeyE)
+        .insert(Action::Zoom, SingleAxis::mouse_wheel_y())
+        .build()
+}
 
-    // spawn a player
-    commands
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        MainCamera,
+        Camera2dBundle {
+            transform: Transform::from_xyz(0., 0., 5.0),
+            ..default()
+        },
+    ));
+}
+
+/// Spawns the player on entering [`GameState::Playing`], unless it is there
+/// already from before a pause or a game over.
+fn spawn_player(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    palette: Res<Palette>,
+    progression: Res<Progression>,
+    players: Query<(), With<Player>>,
+    mut spawned: EventWriter<PlayerSpawned>,
+) {
+    if !players.is_empty() {
+        return;
+    }
+    let modifiers = progression.modifiers();
+    let player = commands
         .spawn((
-            Player { max_speed: 150. },
+            (Player { max_speed: 150. }, RunScoped),
+            (Health::new(PLAYER_HEALTH * modifiers.max_health), modifiers),
+            Weapon::default(),
+            WeaponInventory::default(),
+            FireCooldown::default(),
+            Collider::new(
+                config.player.size.min_element() / 2.,
+                CollisionLayer::PLAYER,
+            ),
+            Sprint::default(),
+            RewindHistory::default(),
+            Rewind::default(),
+            Dash::default(),
+            AllyDeploy::default(),
+            Velocity::default(),
+            Interpolated::default(),
             InputManagerBundle::<Action> {
                 // Stores "which actions are currently activated"
                 action_state: ActionState::default(),
                 // Describes how to convert from player inputs into those actions
-                input_map: InputMap::default()
-                    .insert(Action::Move, DualAxis::left_stick())
-                    .build(),
+                input_map: default_input_map(),
             },
             SpriteBundle {
                 transform: Transform {
@@ -67,8 +441,8 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                     ..default()
                 },
                 sprite: Sprite {
-                    color: Color::ORANGE,
-                    custom_size: Some(Vec2::new(15., 25.)),
+                    color: palette.player,
+                    custom_size: Some(config.player.size),
                     ..default()
                 },
                 ..default()
@@ -76,95 +450,234 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         ))
         .with_children(|parent| {
             // pointy "nose" for player
-            parent.spawn(SpriteBundle {
-                transform: Transform {
-                    translation: Vec3::new(5., 0., 0.),
-                    rotation: Quat::from_rotation_z(PI / 4.),
-                    ..default()
-                },
-                sprite: Sprite {
-                    color: Color::ORANGE,
-                    custom_size: Some(Vec2::splat(25. / f32::sqrt(2.))),
-                    ..default()
-                },
-                ..default()
-            });
-        });
-
-    // spawn a move stick
-    commands
-        .spawn((
-            // map this stick as a left gamepad stick (through bevy_input)
-            // leafwing will register this as a normal gamepad
-            TouchStickGamepadMapping::LEFT_STICK,
-            TouchStickUiBundle {
-                stick: TouchStick {
-                    id: Stick::Left,
-                    radius: 10.0,
-                    ..default()
-                },
-                style: Style {
-                    width: Val::Percent(100.0),  // Width of the touchstick area
-                    height: Val::Percent(100.0), // Height of the touchstick area
-                    position_type: PositionType::Absolute,
-                    bottom: Val::Percent(-25.0), // At the bottom of the screen
-                    ..default()
-                },
-                ..default()
-            },
-        ))
-        .with_children(|parent| {
             parent.spawn((
-                TouchStickUiKnob,
-                ImageBundle {
-                    image: asset_server.load("knob.png").into(),
-                    style: Style {
-                        width: Val::Px(75.),
-                        height: Val::Px(75.),
+                PlayerNose,
+                SpriteBundle {
+                    transform: Transform {
+                        translation: config.player.nose_offset.extend(0.),
+                        rotation: Quat::from_rotation_z(PI / 4.),
                         ..default()
                     },
-                    ..default()
-                },
-            ));
-            parent.spawn((
-                TouchStickUiOutline,
-                ImageBundle {
-                    image: asset_server.load("outline.png").into(),
-                    style: Style {
-                        width: Val::Px(150.),
-                        height: Val::Px(150.),
+                    sprite: Sprite {
+                        color: palette.player,
+                        custom_size: Some(Vec2::splat(config.player.nose_size)),
                         ..default()
                     },
                     ..default()
                 },
             ));
-        });
+        })
+        .id();
+
+    spawned.send(PlayerSpawned {
+        entity: player,
+        position: Vec2::ZERO,
+    });
+}
+
+/// Distance and time accumulated since the last [`PlayerMoved`] was sent.
+#[derive(Default)]
+struct MoveThrottle {
+    elapsed: f32,
+    distance: f32,
 }
 
 fn move_player(
-    mut players: Query<(&mut Transform, &ActionState<Action>, &Player)>,
+    mut players: Query<(
+        Entity,
+        &mut Transform,
+        &mut Velocity,
+        &ActionState<Action>,
+        &Player,
+        &Sprint,
+        Has<Rewinding>,
+        Has<Dashing>,
+        Option<&StatModifiers>,
+        Option<&UpgradeModifiers>,
+        Option<&Statuses>,
+    )>,
+    sprint_config: Res<SprintConfig>,
+    motion: Res<MotionConfig>,
+    view: Res<CameraView>,
     time: Res<Time>,
+    mut throttle: Local<MoveThrottle>,
+    mut moved: EventWriter<PlayerMoved>,
 ) {
-    let (mut player_transform, action_state, player) = players.single_mut();
+    let (
+        entity,
+        mut player_transform,
+        mut velocity,
+        action_state,
+        player,
+        sprint,
+        rewinding,
+        dashing,
+        modifiers,
+        upgrades,
+        statuses,
+    ) = players.single_mut();
+    // the rewind puts the player back where they were, moving or not
+    if rewinding {
+        velocity.0 = Vec2::ZERO;
+        return;
+    }
+    let dt = time.delta_seconds();
+    // a dash sets its own velocity
+    if !dashing {
+        let max_speed = player.max_speed
+            * sprint_config.speed_factor(sprint.level)
+            * stat_factor(modifiers, PowerUp::Speed)
+            * move_speed_factor(upgrades)
+            * statuses.map_or(1., Statuses::speed_multiplier);
+        let target = action_state
+            .clamped_axis_pair(&Action::Move)
+            .filter(|_| action_state.pressed(&Action::Move))
+            .map_or(Vec2::ZERO, |axis| move_target(*view, axis.xy(), max_speed));
+        velocity.0 = motion::move_towards(velocity.0, target, motion.acceleration * dt);
+    }
 
-    if action_state.pressed(&Action::Move) {
-        let axis_value = action_state.clamped_axis_pair(&Action::Move).unwrap().xy();
+    let move_delta = velocity.0 * dt;
+    if move_delta == Vec2::ZERO {
+        return;
+    }
+    player_transform.translation += move_delta.extend(0.);
 
-        info!("moving: {axis_value}");
+    throttle.elapsed += dt;
+    throttle.distance += move_delta.length();
+    if throttle.elapsed >= PLAYER_MOVED_INTERVAL && throttle.distance > 0. {
+        moved.send(PlayerMoved {
+            entity,
+            position: player_transform.translation.truncate(),
+            distance: throttle.distance,
+        });
+        *throttle = MoveThrottle::default();
+    }
+}
 
-        let mut move_delta = axis_value * player.max_speed * time.delta_seconds();
-        let length = (move_delta.x.powi(2) + move_delta.y.powi(2)).sqrt();
-        if length > 0.0 {
-            move_delta.x /= length;
-            move_delta.y /= length;
+/// Turns the player to face where they aim, twin-stick style, and where
+/// they move while there is nothing to aim at.
+/// The velocity the move stick at `axis` steers towards. Speed follows how
+/// far the stick is pushed, so a stick drifting without a dead zone creeps
+/// instead of running at full speed.
+fn move_target(view: CameraView, axis: Vec2, max_speed: f32) -> Vec2 {
+    view.to_world(axis).clamp_length_max(1.) * max_speed
+}
+
+fn look_player(
+    mut players: Query<(&mut Transform, &ActionState<Action>, Has<Rewinding>), With<Player>>,
+    lock: Res<TargetLock>,
+    lock_config: Res<LockConfig>,
+    view: Res<CameraView>,
+    display: Res<DisplaySettings>,
+) {
+    let Ok((mut transform, action_state, rewinding)) = players.get_single_mut() else {
+        return;
+    };
+    if rewinding {
+        return;
+    }
+    let stick = |action| {
+        action_state
+            .clamped_axis_pair(&action)
+            .map(|axis| view.to_world(axis.xy()))
+            .unwrap_or_default()
+    };
+    let movement = stick(Action::Move).try_normalize();
+    let facing = match display.camera_mode {
+        // the view turns with the player's facing and aim is read relative
+        // to the view, so facing the aim would keep the camera spinning
+        CameraMode::Rotate => movement,
+        CameraMode::Fixed => {
+            let origin = transform.translation.truncate();
+            lock.aim(&lock_config, origin, stick(Action::Look))
+                .or(movement)
         }
+    };
+    if let Some(facing) = facing {
+        transform.rotation = Quat::from_rotation_z(Vec2::X.angle_between(facing));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        abilities::{DashConfig, RewindConfig},
+        ally::AllyConfig,
+        events::WaveCleared,
+        fonts::UiFonts,
+        score::{Score, ScoreConfig},
+        state::{StatePlugin, StateScoped},
+        stats::Stats,
+    };
+
+    fn go_to(app: &mut App, state: GameState) {
+        app.world.resource_mut::<NextState<GameState>>().set(state);
+        app.update();
+    }
+
+    #[test]
+    fn a_drifting_stick_creeps() {
+        let view = CameraView::default();
+        let drift = move_target(view, Vec2::new(0.02, 0.), 150.);
+        assert!((drift.length() - 3.).abs() < 1e-4);
+        let pushed = move_target(view, Vec2::new(0., 1.), 150.);
+        assert!((pushed.length() - 150.).abs() < 1e-4);
+    }
+
+    /// Stands in for the title screen: a scoped root with a child.
+    #[derive(Component)]
+    struct Menu;
+
+    fn spawn_menu(mut commands: Commands) {
+        commands
+            .spawn((Menu, StateScoped(GameState::MainMenu)))
+            .with_children(|parent| {
+                parent.spawn_empty();
+            });
+    }
+
+    #[test]
+    fn a_run_leaves_nothing_behind_in_the_menu() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatePlugin, hud::HudPlugin))
+            .init_state::<GameState>()
+            .add_event::<PlayerSpawned>()
+            .add_event::<WaveCleared>()
+            .insert_resource(UiFonts {
+                face: default(),
+                regular: default(),
+                bold: default(),
+            })
+            .init_resource::<GameConfig>()
+            .init_resource::<Palette>()
+            .init_resource::<Progression>()
+            .init_resource::<Score>()
+            .init_resource::<ScoreConfig>()
+            .init_resource::<Stats>()
+            .init_resource::<RewindConfig>()
+            .init_resource::<DashConfig>()
+            .init_resource::<AllyConfig>()
+            .add_systems(OnEnter(GameState::MainMenu), spawn_menu)
+            .add_systems(OnEnter(GameState::Playing), spawn_player);
+        app.update();
+        let baseline = app.world.iter_entities().count();
 
-        move_delta *= player.max_speed * time.delta_seconds();
-        player_transform.translation += move_delta.extend(0.);
+        let mut players = app.world.query_filtered::<(), With<Player>>();
+        let mut menus = app.world.query_filtered::<(), With<Menu>>();
+        for _ in 0..2 {
+            go_to(&mut app, GameState::Playing);
+            assert_eq!(players.iter(&app.world).count(), 1);
+            assert_eq!(menus.iter(&app.world).count(), 0);
+            // a pause neither despawns the player nor spawns another
+            go_to(&mut app, GameState::Paused);
+            go_to(&mut app, GameState::Playing);
+            assert_eq!(players.iter(&app.world).count(), 1);
 
-        if axis_value != Vec2::ZERO {
-            let dir = Vec2::angle_between(Vec2::X, axis_value.normalize());
-            player_transform.rotation = Quat::from_rotation_z(dir);
+            go_to(&mut app, GameState::MainMenu);
+            assert_eq!(players.iter(&app.world).count(), 0);
+            assert_eq!(app.world.iter_entities().count(), baseline);
         }
     }
 }
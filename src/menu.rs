@@ -0,0 +1,238 @@
+//! The title screen the game opens on.
+//!
+//! The app starts in [`GameState::MainMenu`], with the arena already set up
+//! but frozen behind the menu, and pressing Play starts the run, spawning
+//! the player. With a stored run to go back to, Continue restores it
+//! instead; see [`crate::save`]. Daily Challenge starts the day's
+//! [daily run](crate::daily), shown below it with the day's best score.
+//! Upgrades opens the [upgrade screen](crate::progression), Host and Join
+//! start a [co-op session](crate::net), and Watch Best Run plays back the
+//! best [recorded run](crate::replay). Options opens the
+//! [settings](crate::settings) over the menu, and on native builds Quit
+//! closes the game.
+//!
+//! The menu is see-through, over the world as the last run left it but
+//! without the player, and it rains harder behind it: [`MENU_RAIN`]
+//! replaces the [weather](crate::weather) while the menu and the screens
+//! opened from it are up, and the weather from before comes back when play
+//! starts. Going into and out of it fades through black; see
+//! [`crate::transition`].
+
+use bevy::prelude::*;
+
+use crate::{
+    daily::{DailyBest, DailyButton, DailyRun},
+    death::StartNewRun,
+    fonts::UiFonts,
+    i18n::LocalizedText,
+    net::{HostButton, JoinButton},
+    progression::UpgradesButton,
+    replay::{self, BestReplay, WatchReplayButton},
+    save::{ContinueButton, StoredRun},
+    settings::SettingsReturn,
+    state::{GameState, StateScoped},
+    transition::Curtain,
+    weather::WeatherState,
+};
+
+/// How hard it rains behind the menu, `0..=1`.
+pub const MENU_RAIN: f32 = 0.8;
+
+#[derive(Component)]
+struct PlayButton;
+
+#[derive(Component)]
+struct OptionsButton;
+
+#[derive(Component)]
+struct QuitButton;
+
+/// The weather as it was before the menu changed it.
+#[derive(Resource, Default)]
+struct StashedWeather(Option<WeatherState>);
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StashedWeather>()
+            .add_systems(
+                OnEnter(GameState::MainMenu),
+                (spawn_main_menu, start_menu_rain),
+            )
+            .add_systems(OnEnter(GameState::Playing), stop_menu_rain)
+            .add_systems(
+                Update,
+                (
+                    press_play.before(replay::begin_run),
+                    press_options,
+                    press_quit,
+                )
+                    .run_if(in_state(GameState::MainMenu)),
+            );
+    }
+}
+
+fn spawn_main_menu(
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    stored_run: Option<Res<StoredRun>>,
+    best_replay: Option<Res<BestReplay>>,
+    daily_best: Res<DailyBest>,
+) {
+    let daily = DailyRun::today();
+    commands
+        .spawn((
+            StateScoped(GameState::MainMenu),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(12.),
+                    ..default()
+                },
+                // light enough for the rain to show through
+                background_color: Color::rgba(0., 0., 0., 0.55).into(),
+                z_index: ZIndex::Global(20),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section("Rain", fonts.bold(64., Color::WHITE)).with_style(Style {
+                    margin: UiRect::bottom(Val::Px(12.)),
+                    ..default()
+                }),
+            );
+            if stored_run.is_some() {
+                spawn_button(parent, &fonts, ContinueButton, "Continue");
+                spawn_button(parent, &fonts, PlayButton, "New Run");
+            } else {
+                spawn_button(parent, &fonts, PlayButton, "Play");
+            }
+            spawn_button(parent, &fonts, DailyButton, "Daily Challenge");
+            spawn_daily_info(parent, &fonts, &daily, daily_best.on(daily.day));
+            spawn_button(parent, &fonts, UpgradesButton, "Upgrades");
+            if best_replay.is_some() {
+                spawn_button(parent, &fonts, WatchReplayButton, "Watch Best Run");
+            }
+            spawn_button(parent, &fonts, HostButton, "Host Co-op");
+            spawn_button(parent, &fonts, JoinButton, "Join Co-op");
+            spawn_button(parent, &fonts, OptionsButton, "Options");
+            // browsers don't let a page close its own tab
+            if cfg!(not(target_arch = "wasm32")) {
+                spawn_button(parent, &fonts, QuitButton, "Quit");
+            }
+        });
+}
+
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    fonts: &UiFonts,
+    button: impl Bundle,
+    label: &'static str,
+) {
+    parent
+        .spawn((
+            button,
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::axes(Val::Px(16.), Val::Px(8.)),
+                    ..default()
+                },
+                background_color: Color::rgba(1., 1., 1., 0.15).into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                LocalizedText::new(label),
+                TextBundle::from_section(label, fonts.bold(24., Color::WHITE)),
+            ));
+        });
+}
+
+/// The day's date, seed, modifiers and best score, under its button.
+fn spawn_daily_info(
+    parent: &mut ChildBuilder,
+    fonts: &UiFonts,
+    daily: &DailyRun,
+    best: Option<u64>,
+) {
+    let color = Color::rgba(1., 1., 1., 0.7);
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                format!("{}  seed {:016X}", daily.date(), daily.seed),
+                fonts.style(14., color),
+            ));
+            for modifier in &daily.modifiers {
+                let text = modifier.description();
+                parent.spawn((
+                    LocalizedText::new(text),
+                    TextBundle::from_section(text, fonts.style(14., color)),
+                ));
+            }
+            if let Some(points) = best {
+                parent.spawn(TextBundle::from_section(
+                    format!("Best today: {points}"),
+                    fonts.style(14., color),
+                ));
+            }
+        });
+}
+
+fn press_play(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<PlayButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut new_runs: EventWriter<StartNewRun>,
+) {
+    if buttons.iter().any(|i| *i == Interaction::Pressed) {
+        // reseeds the run, so it can be replayed
+        new_runs.send(StartNewRun);
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn press_options(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<OptionsButton>)>,
+    mut settings_return: ResMut<SettingsReturn>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if buttons.iter().any(|i| *i == Interaction::Pressed) {
+        settings_return.0 = GameState::MainMenu;
+        next_state.set(GameState::Settings);
+    }
+}
+
+fn press_quit(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<QuitButton>)>,
+    mut curtain: ResMut<Curtain>,
+) {
+    if buttons.iter().any(|i| *i == Interaction::Pressed) {
+        curtain.quit();
+    }
+}
+
+fn start_menu_rain(mut weather: ResMut<WeatherState>, mut stash: ResMut<StashedWeather>) {
+    stash.0.get_or_insert(*weather);
+    weather.intensity = MENU_RAIN;
+}
+
+fn stop_menu_rain(mut weather: ResMut<WeatherState>, mut stash: ResMut<StashedWeather>) {
+    if let Some(stashed) = stash.0.take() {
+        *weather = stashed;
+    }
+}
@@ -0,0 +1,198 @@
+//! A small map of what is around the player.
+//!
+//! With [`DisplaySettings::minimap`] on, a square in the top corner on the
+//! look stick's side shows the player at its centre, with a dot for each
+//! enemy and pickup within [`MinimapConfig::range`] of them along either
+//! axis, in the enemy's own color or the pickup color. It turns with the
+//! view in the rotating [camera mode](crate::camera). At most [`MAX_DOTS`]
+//! are drawn, the nearest first; the dots are kept and moved rather than
+//! respawned.
+
+use bevy::prelude::*;
+
+use crate::{
+    camera::CameraView,
+    config::Palette,
+    enemy::Enemy,
+    layout::{Mirrored, StickSide},
+    pickups::Pickup,
+    settings::DisplaySettings,
+    state::{GameState, StateScoped},
+    weapon::WeaponPickup,
+    Player,
+};
+
+/// Dots kept for enemies and pickups.
+const MAX_DOTS: usize = 64;
+const DOT_SIZE: f32 = 4.;
+const PLAYER_DOT_SIZE: f32 = 6.;
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct MinimapConfig {
+    /// World distance from the player to the map's edge.
+    pub range: f32,
+    /// Side length of the map, in pixels.
+    pub size: f32,
+}
+
+impl Default for MinimapConfig {
+    fn default() -> Self {
+        Self {
+            range: 600.,
+            size: 120.,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Minimap;
+
+/// The `index`th dot, in order of distance from the player.
+#[derive(Component)]
+struct MinimapDot(usize);
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<MinimapConfig>()
+            .init_resource::<MinimapConfig>()
+            .add_systems(OnEnter(GameState::Playing), spawn_minimap)
+            .add_systems(
+                Update,
+                (
+                    show_minimap.run_if(resource_changed::<DisplaySettings>),
+                    update_minimap.run_if(minimap_enabled),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn minimap_enabled(settings: Res<DisplaySettings>) -> bool {
+    settings.minimap
+}
+
+fn spawn_minimap(
+    mut commands: Commands,
+    config: Res<MinimapConfig>,
+    palette: Res<Palette>,
+    settings: Res<DisplaySettings>,
+) {
+    let center = (config.size - PLAYER_DOT_SIZE) / 2.;
+    commands
+        .spawn((
+            Minimap,
+            StateScoped(GameState::Playing),
+            Mirrored::new(StickSide::Look, 12.),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(52.),
+                    width: Val::Px(config.size),
+                    height: Val::Px(config.size),
+                    border: UiRect::all(Val::Px(1.)),
+                    overflow: Overflow::clip(),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.4).into(),
+                border_color: Color::rgba(1., 1., 1., 0.3).into(),
+                visibility: if settings.minimap {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                },
+                z_index: ZIndex::Global(4),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            for index in 0..MAX_DOTS {
+                parent.spawn((
+                    MinimapDot(index),
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            width: Val::Px(DOT_SIZE),
+                            height: Val::Px(DOT_SIZE),
+                            ..default()
+                        },
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                ));
+            }
+            // drawn last, over the dots
+            parent.spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(center),
+                    top: Val::Px(center),
+                    width: Val::Px(PLAYER_DOT_SIZE),
+                    height: Val::Px(PLAYER_DOT_SIZE),
+                    ..default()
+                },
+                background_color: palette.player.into(),
+                ..default()
+            });
+        });
+}
+
+fn show_minimap(settings: Res<DisplaySettings>, mut maps: Query<&mut Visibility, With<Minimap>>) {
+    for mut visibility in &mut maps {
+        *visibility = if settings.minimap {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn update_minimap(
+    config: Res<MinimapConfig>,
+    palette: Res<Palette>,
+    view: Res<CameraView>,
+    players: Query<&Transform, With<Player>>,
+    enemies: Query<(&Transform, &Sprite), With<Enemy>>,
+    pickups: Query<&Transform, Or<(With<Pickup>, With<WeaponPickup>)>>,
+    mut dots: Query<(
+        &MinimapDot,
+        &mut Style,
+        &mut BackgroundColor,
+        &mut Visibility,
+    )>,
+) {
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+    let origin = player.translation.truncate();
+    let enemy_dots = enemies
+        .iter()
+        .map(|(transform, sprite)| (transform, sprite.color.with_a(1.)));
+    let pickup_dots = pickups.iter().map(|transform| (transform, palette.pickup));
+    let mut markers: Vec<(Vec2, Color)> = enemy_dots
+        .chain(pickup_dots)
+        .map(|(transform, color)| {
+            let offset = view.to_screen(transform.translation.truncate() - origin);
+            (offset, color)
+        })
+        .filter(|(offset, _)| offset.abs().max_element() <= config.range)
+        .collect();
+    markers.sort_by(|a, b| a.0.length_squared().total_cmp(&b.0.length_squared()));
+
+    let scale = config.size / 2. / config.range.max(1.);
+    for (dot, mut style, mut color, mut visibility) in &mut dots {
+        let Some((offset, marker)) = markers.get(dot.0) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        // screen up is +y, UI top grows downwards
+        let position = Vec2::new(offset.x, -offset.y) * scale + config.size / 2. - DOT_SIZE / 2.;
+        style.left = Val::Px(position.x);
+        style.top = Val::Px(position.y);
+        *color = (*marker).into();
+        *visibility = Visibility::Inherited;
+    }
+}
@@ -0,0 +1,169 @@
+//! Velocity, and the knocks that push things about.
+//!
+//! The player moves by [`Velocity`]: the move stick sets the speed they
+//! steer towards, and they get there at [`MotionConfig::acceleration`]
+//! rather than at once. An [`Impulse`] adds to the velocity on top, so a
+//! knock sends the player off and the stick wins them back over a few
+//! frames instead of being overridden. Enemies touching the player knock
+//! them away, and a dash leaves [`MotionConfig::dash_carry`] of its speed
+//! behind as it ends.
+//!
+//! Enemies steer themselves, so their [`Velocity`] is only what they've
+//! been knocked with. It moves them on top of their steering and dies away
+//! at their [`Drag`]. Shots knock the enemies they hit along their flight,
+//! and a [`Blast`] pushes every enemy near it outward, less the further
+//! out they are. Cluster shots send one where they split.
+
+use bevy::prelude::*;
+
+use crate::{death::StartNewRun, state::GameState, targeting::EnemyGrid};
+
+/// How quickly an enemy's knockback dies away, per second, unless it has
+/// a [`Drag`] of its own.
+pub const DEFAULT_DRAG: f32 = 8.;
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct MotionConfig {
+    /// How quickly the player's speed changes towards the one the stick
+    /// asks for, in world units per second per second.
+    pub acceleration: f32,
+    /// Fastest anything is knocked, in world units per second.
+    pub max_knockback: f32,
+    /// Speed the player is knocked away from an enemy touching them.
+    pub contact_knockback: f32,
+    /// Speed a shot knocks the enemy it hits along its flight.
+    pub hit_knockback: f32,
+    /// Furthest from a [`Blast`] an enemy is pushed.
+    pub blast_radius: f32,
+    /// Speed an enemy at the middle of a [`Blast`] is pushed.
+    pub blast_knockback: f32,
+    /// Share of a dash's speed the player keeps as it ends.
+    pub dash_carry: f32,
+}
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        Self {
+            acceleration: 1200.,
+            max_knockback: 600.,
+            contact_knockback: 260.,
+            hit_knockback: 60.,
+            blast_radius: 90.,
+            blast_knockback: 240.,
+            dash_carry: 0.15,
+        }
+    }
+}
+
+/// World units per second something is moving.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Velocity(pub Vec2);
+
+/// How quickly a [`Velocity`] nothing else steers dies away, per second.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Drag(pub f32);
+
+impl Default for Drag {
+    fn default() -> Self {
+        Self(DEFAULT_DRAG)
+    }
+}
+
+/// Knock `target` by adding `impulse` to its [`Velocity`].
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Impulse {
+    pub target: Entity,
+    pub impulse: Vec2,
+}
+
+/// An explosion at `position`, pushing the enemies around it outward.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Blast {
+    pub position: Vec2,
+}
+
+/// `current` moved towards `target` by at most `max_delta`.
+pub fn move_towards(current: Vec2, target: Vec2, max_delta: f32) -> Vec2 {
+    let delta = target - current;
+    let distance = delta.length();
+    if distance <= max_delta || distance <= f32::EPSILON {
+        target
+    } else {
+        current + delta / distance * max_delta
+    }
+}
+
+pub struct MotionPlugin;
+
+impl Plugin for MotionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<MotionConfig>()
+            .init_resource::<MotionConfig>()
+            .add_event::<Impulse>()
+            .add_event::<Blast>()
+            .add_systems(
+                FixedUpdate,
+                (apply_blasts, apply_impulses, drift)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(Update, stop_on_new_run);
+    }
+}
+
+fn apply_blasts(
+    config: Res<MotionConfig>,
+    grid: Res<EnemyGrid>,
+    mut blasts: EventReader<Blast>,
+    mut impulses: EventWriter<Impulse>,
+) {
+    for blast in blasts.read() {
+        for (enemy, position) in grid.nearby_in_radius(blast.position, config.blast_radius) {
+            let offset = position - blast.position;
+            let falloff = 1. - (offset.length() / config.blast_radius).min(1.);
+            // one right on top of it still goes somewhere
+            let direction = offset.try_normalize().unwrap_or(Vec2::X);
+            impulses.send(Impulse {
+                target: enemy,
+                impulse: direction * config.blast_knockback * falloff,
+            });
+        }
+    }
+}
+
+pub fn apply_impulses(
+    config: Res<MotionConfig>,
+    mut impulses: EventReader<Impulse>,
+    mut velocities: Query<&mut Velocity>,
+) {
+    for impulse in impulses.read() {
+        if let Ok(mut velocity) = velocities.get_mut(impulse.target) {
+            velocity.0 = (velocity.0 + impulse.impulse).clamp_length_max(config.max_knockback);
+        }
+    }
+}
+
+/// Moves whatever has been knocked, and slows it down.
+fn drift(time: Res<Time>, mut movers: Query<(&mut Transform, &mut Velocity, &Drag)>) {
+    let dt = time.delta_seconds();
+    for (mut transform, mut velocity, drag) in &mut movers {
+        if velocity.0 == Vec2::ZERO {
+            continue;
+        }
+        transform.translation += (velocity.0 * dt).extend(0.);
+        velocity.0 *= (-drag.0 * dt).exp();
+        if velocity.0.length_squared() < 1. {
+            velocity.0 = Vec2::ZERO;
+        }
+    }
+}
+
+fn stop_on_new_run(mut new_runs: EventReader<StartNewRun>, mut velocities: Query<&mut Velocity>) {
+    if new_runs.read().count() == 0 {
+        return;
+    }
+    for mut velocity in &mut velocities {
+        velocity.0 = Vec2::ZERO;
+    }
+}
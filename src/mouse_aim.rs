@@ -0,0 +1,80 @@
+//! Aiming with the mouse.
+//!
+//! leafwing has no input for "where the cursor is", so once the mouse has
+//! moved while the [`ActiveInputDevice`] is the keyboard, the cursor's
+//! offset from the player is written into [`Action::Look`] after input is
+//! read. It is the same axis a stick produces, relative to the screen, with
+//! the length saying how far out the cursor is relative to the weapon's
+//! range, so lobbed weapons land under the cursor. Arrow keys still aim
+//! when pressed, and switching to another device hands aiming back to it.
+
+use bevy::{input::mouse::MouseMotion, prelude::*, window::PrimaryWindow};
+use leafwing_input_manager::{axislike::DualAxisData, prelude::*};
+
+use crate::{
+    camera::{CameraView, MainCamera},
+    input_device::{ActiveInputDevice, InputDevice},
+    smoothing,
+    weapon::Weapon,
+    Action, Player,
+};
+
+pub struct MouseAimPlugin;
+
+impl Plugin for MouseAimPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, aim_at_cursor.after(smoothing::smooth_sticks));
+    }
+}
+
+/// Look axis for a cursor `offset` from the player, in world units.
+pub fn cursor_look(view: &CameraView, offset: Vec2, range: f32) -> Vec2 {
+    (view.to_screen(offset) / range.max(1.)).clamp_length_max(1.)
+}
+
+fn aim_at_cursor(
+    device: Res<ActiveInputDevice>,
+    view: Res<CameraView>,
+    mut motion: EventReader<MouseMotion>,
+    mut following: Local<bool>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut players: Query<(&Transform, &Weapon, &mut ActionState<Action>), With<Player>>,
+) {
+    let moved = motion.read().count() > 0;
+    if device.device != InputDevice::Keyboard {
+        *following = false;
+        return;
+    }
+    *following |= moved;
+    if !*following {
+        return;
+    }
+
+    let Ok((transform, weapon, mut action_state)) = players.get_single_mut() else {
+        return;
+    };
+    let Some(data) = action_state.action_data_mut(&Action::Look) else {
+        return;
+    };
+    // the arrow keys win while held
+    if data.axis_pair.is_some_and(|axis| axis.xy() != Vec2::ZERO) {
+        return;
+    }
+    let cursor = windows
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+        .zip(cameras.get_single().ok())
+        .and_then(|(cursor, (camera, camera_transform))| {
+            camera.viewport_to_world_2d(camera_transform, cursor)
+        });
+    let Some(cursor) = cursor else {
+        return;
+    };
+
+    let offset = cursor - transform.translation.truncate();
+    let value = cursor_look(&view, offset, weapon.range());
+    data.axis_pair = Some(DualAxisData::from_xy(value));
+    data.value = value.length();
+}
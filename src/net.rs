@@ -0,0 +1,711 @@
+//! Two player co-op over WebRTC.
+//!
+//! One player presses Host on the main menu and the other Join, and the
+//! two are paired in [`NetConfig::room`] on the matchbox signaling server
+//! at [`NetConfig::signaling_url`]. The host runs the game as usual and has
+//! the final word on everything in it. The joining player appears there as
+//! a [`Partner`], moved and fired by the inputs the client sends every
+//! frame. [`NetConfig::snapshot_rate`] times a second the host sends back a
+//! [`Snapshot`] of both players, the enemies, the projectiles and the
+//! score. The client shows those as plain sprites, in place of enemies and
+//! shots of its own: its spawner and weapon stay idle.
+//!
+//! The client's own player still moves by the action system as soon as
+//! its input is read, without waiting for the host. Each input carries the
+//! distance moved and a sequence number, and each snapshot says which
+//! input the host applied last and where that left the partner. The client
+//! replays the moves the host hasn't seen yet from there, and if its player
+//! has drifted from that it is corrected: snapped back when far off, eased
+//! back otherwise.
+//!
+//! The partner isn't hurt by enemies, and enemies only chase the host.
+
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, sprite::Anchor, utils::HashMap};
+use bevy_matchbox::prelude::*;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bounds::contain,
+    budget::Budget,
+    config::GameConfig,
+    defs::Defs,
+    enemy::{Enemy, EnemyKind},
+    fire::FireCooldown,
+    projectile::{shot_modifiers, spawn_projectile, Beam, Projectile, ShotPools},
+    score::Score,
+    settings::GameplaySettings,
+    state::GameState,
+    toast::Toast,
+    weapon::Weapon,
+    Action, Player,
+};
+
+/// The other player's color, on either screen.
+const PARTNER_COLOR: Color = Color::AQUAMARINE;
+
+/// Inputs the client keeps for replay before giving up on the host
+/// answering.
+const MAX_PENDING_INPUTS: usize = 240;
+
+/// Longest frame an input is allowed to cover, in seconds.
+const MAX_INPUT_DT: f32 = 0.1;
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct NetConfig {
+    /// Address of the matchbox signaling server.
+    pub signaling_url: String,
+    /// Room the two players meet in.
+    pub room: String,
+    /// Snapshots the host sends a second.
+    pub snapshot_rate: f32,
+    /// Fastest the partner may move, in pixels per second, however far the
+    /// client says it went. Leaves room for sprinting and dashing.
+    pub max_partner_speed: f32,
+    /// A client player this far from where the host has it is snapped
+    /// straight there.
+    pub snap_distance: f32,
+    /// Share of a smaller drift corrected with each snapshot.
+    pub correction: f32,
+    /// How quickly enemies and the other player shown on the client catch
+    /// up with their latest position, per second.
+    pub smoothing: f32,
+}
+
+impl Default for NetConfig {
+    fn default() -> Self {
+        Self {
+            signaling_url: "ws://localhost:3536".to_string(),
+            room: "rain".to_string(),
+            snapshot_rate: 20.,
+            max_partner_speed: 1200.,
+            snap_distance: 48.,
+            correction: 0.2,
+            smoothing: 15.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NetRole {
+    #[default]
+    Offline,
+    Host,
+    Client,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct NetSession {
+    pub role: NetRole,
+    /// The other player, once connected.
+    peer: Option<PeerId>,
+    /// Last snapshot sent, or on the client, received.
+    tick: u32,
+    /// Seconds since the host last sent a snapshot.
+    since_snapshot: f32,
+}
+
+/// The player who joined, as the host sees them.
+#[derive(Component, Debug)]
+pub struct Partner {
+    peer: PeerId,
+    /// Last input applied.
+    acked: u32,
+    shooting: bool,
+}
+
+/// Opens a host session from the main menu.
+#[derive(Component)]
+pub struct HostButton;
+
+/// Joins a host's session from the main menu.
+#[derive(Component)]
+pub struct JoinButton;
+
+/// What the client did this frame.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct PartnerInput {
+    seq: u32,
+    /// How far the client's player moved.
+    motion: Vec2,
+    dt: f32,
+    /// Facing, in radians.
+    rotation: f32,
+    shooting: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct BodyState {
+    position: Vec2,
+    rotation: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct EnemyState {
+    id: u64,
+    kind: EnemyKind,
+    position: Vec2,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct ShotState {
+    /// Centre of the sprite.
+    position: Vec2,
+    velocity: Vec2,
+    rotation: f32,
+    size: Vec2,
+    color: Color,
+}
+
+/// The host's game, as sent to the client.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Snapshot {
+    tick: u32,
+    host: BodyState,
+    partner: Option<BodyState>,
+    /// Last of the client's inputs applied to the partner.
+    acked: u32,
+    enemies: Vec<EnemyState>,
+    shots: Vec<ShotState>,
+    points: u64,
+    wave: u32,
+    combo: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+enum Message {
+    Input(PartnerInput),
+    Snapshot(Snapshot),
+}
+
+impl Message {
+    fn encode(&self) -> Box<[u8]> {
+        bincode::serialize(self)
+            .expect("messages always serialize")
+            .into_boxed_slice()
+    }
+
+    fn decode(packet: &[u8]) -> Option<Self> {
+        bincode::deserialize(packet).ok()
+    }
+}
+
+/// The client's moves the host hasn't applied yet.
+#[derive(Resource, Debug, Default)]
+struct Prediction {
+    seq: u32,
+    pending: VecDeque<(u32, Vec2)>,
+    /// Where the player was when the last input was sent.
+    last_position: Option<Vec2>,
+}
+
+/// Something the host has, shown on the client and eased towards where
+/// the host last had it.
+#[derive(Component, Debug)]
+struct Ghost {
+    target: Vec2,
+}
+
+/// A shot the host has, shown on the client and carried along by its
+/// velocity between snapshots.
+#[derive(Component, Debug)]
+struct ShotGhost {
+    velocity: Vec2,
+}
+
+#[derive(Resource, Debug, Default)]
+struct Ghosts {
+    host: Option<Entity>,
+    enemies: HashMap<u64, Entity>,
+    shots: Vec<Entity>,
+}
+
+pub fn is_host(session: Res<NetSession>) -> bool {
+    session.role == NetRole::Host
+}
+
+pub fn is_client(session: Res<NetSession>) -> bool {
+    session.role == NetRole::Client
+}
+
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<NetConfig>()
+            .init_resource::<NetConfig>()
+            .init_resource::<NetSession>()
+            .init_resource::<Prediction>()
+            .init_resource::<Ghosts>()
+            .add_systems(
+                Update,
+                (
+                    press_session_buttons.run_if(in_state(GameState::MainMenu)),
+                    update_peers.run_if(resource_exists::<MatchboxSocket<SingleChannel>>),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    receive_inputs,
+                    fire_partner.run_if(in_state(GameState::Playing)),
+                    send_snapshots,
+                )
+                    .chain()
+                    .after(update_peers)
+                    .run_if(is_host),
+            )
+            .add_systems(
+                Update,
+                (receive_snapshots, follow_ghosts, send_input)
+                    .chain()
+                    .after(update_peers)
+                    .run_if(is_client),
+            );
+    }
+}
+
+fn press_session_buttons(
+    mut commands: Commands,
+    config: Res<NetConfig>,
+    mut session: ResMut<NetSession>,
+    host: Query<&Interaction, (Changed<Interaction>, With<HostButton>)>,
+    join: Query<&Interaction, (Changed<Interaction>, With<JoinButton>)>,
+    mut toasts: EventWriter<Toast>,
+) {
+    if session.role != NetRole::Offline {
+        return;
+    }
+    let role = if host.iter().any(|i| *i == Interaction::Pressed) {
+        NetRole::Host
+    } else if join.iter().any(|i| *i == Interaction::Pressed) {
+        NetRole::Client
+    } else {
+        return;
+    };
+    // the server pairs whoever comes next into a room of two
+    let url = format!("{}/{}?next=2", config.signaling_url, config.room);
+    commands.insert_resource(MatchboxSocket::new_unreliable(url));
+    *session = NetSession { role, ..default() };
+    toasts.send(Toast::new(format!(
+        "Waiting for a partner in room {}",
+        config.room
+    )));
+}
+
+fn update_peers(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    mut session: ResMut<NetSession>,
+    mut prediction: ResMut<Prediction>,
+    mut ghosts: ResMut<Ghosts>,
+    mut socket: ResMut<MatchboxSocket<SingleChannel>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    players: Query<&Transform, With<Player>>,
+    partners: Query<Entity, With<Partner>>,
+    replicas: Query<Entity, Or<(With<Ghost>, With<ShotGhost>)>>,
+    mut toasts: EventWriter<Toast>,
+) {
+    for (peer, state) in socket.update_peers() {
+        match state {
+            // a room only pairs two, but a third could still knock
+            PeerState::Connected if session.peer.is_none() => {
+                session.peer = Some(peer);
+                match session.role {
+                    NetRole::Host => {
+                        let position = players
+                            .get_single()
+                            .map_or(Vec2::ZERO, |player| player.translation.truncate());
+                        spawn_partner(&mut commands, &config, peer, position);
+                        toasts.send(Toast::new("Partner joined"));
+                    }
+                    NetRole::Client => {
+                        *prediction = Prediction::default();
+                        toasts.send(Toast::new("Joined the host"));
+                        next_state.set(GameState::Playing);
+                    }
+                    NetRole::Offline => {}
+                }
+            }
+            PeerState::Connected => {}
+            PeerState::Disconnected if session.peer == Some(peer) => {
+                for entity in partners.iter().chain(&replicas) {
+                    commands.entity(entity).despawn_recursive();
+                }
+                *ghosts = Ghosts::default();
+                *session = NetSession::default();
+                commands.remove_resource::<MatchboxSocket<SingleChannel>>();
+                toasts.send(Toast::new("Partner left"));
+                return;
+            }
+            PeerState::Disconnected => {}
+        }
+    }
+}
+
+fn spawn_partner(commands: &mut Commands, config: &GameConfig, peer: PeerId, position: Vec2) {
+    commands.spawn((
+        Partner {
+            peer,
+            acked: 0,
+            shooting: false,
+        },
+        Weapon::default(),
+        FireCooldown::default(),
+        SpriteBundle {
+            transform: Transform::from_translation(
+                (position + Vec2::X * config.player.size.x * 2.).extend(0.),
+            ),
+            sprite: Sprite {
+                color: PARTNER_COLOR,
+                custom_size: Some(config.player.size),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+fn receive_inputs(
+    net: Res<NetConfig>,
+    config: Res<GameConfig>,
+    settings: Res<GameplaySettings>,
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    mut partners: Query<(&mut Partner, &mut Transform)>,
+) {
+    let Some(mut socket) = socket else {
+        return;
+    };
+    let area = config
+        .arena
+        .inner_rect(config.player.size.max_element() / 2.);
+    for (peer, packet) in socket.receive() {
+        let Some(Message::Input(input)) = Message::decode(&packet) else {
+            continue;
+        };
+        let Some((mut partner, mut transform)) = partners
+            .iter_mut()
+            .find(|(partner, _)| partner.peer == peer)
+        else {
+            continue;
+        };
+        // inputs arrive out of order, or twice
+        if input.seq <= partner.acked {
+            continue;
+        }
+        let limit = net.max_partner_speed * input.dt.clamp(0., MAX_INPUT_DT);
+        let position = transform.translation.truncate() + input.motion.clamp_length_max(limit);
+        let (contained, _) = contain(settings.edge_mode, area, position);
+        transform.translation = contained.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(input.rotation);
+        partner.acked = input.seq;
+        partner.shooting = input.shooting;
+    }
+}
+
+fn fire_partner(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    mut budget: Budget,
+    mut pools: ShotPools,
+    mut partners: Query<(&Partner, &Transform, &Weapon, &mut FireCooldown)>,
+) {
+    for (partner, transform, weapon, mut cooldown) in &mut partners {
+        cooldown.remaining = (cooldown.remaining - time.delta_seconds()).max(0.);
+        if !partner.shooting || cooldown.remaining > 0. || weapon.fire_rate <= 0. {
+            continue;
+        }
+        cooldown.remaining = 1. / weapon.fire_rate;
+        let direction = (transform.rotation * Vec3::X).truncate();
+        let origin = transform.translation.truncate() + direction * config.player.nose_tip();
+        spawn_projectile(
+            &mut commands,
+            &mut budget,
+            &mut pools,
+            weapon,
+            shot_modifiers(weapon, None),
+            origin,
+            direction,
+        );
+    }
+}
+
+fn body(transform: &Transform) -> BodyState {
+    BodyState {
+        position: transform.translation.truncate(),
+        rotation: transform.rotation.to_euler(EulerRot::ZYX).0,
+    }
+}
+
+fn send_snapshots(
+    time: Res<Time>,
+    config: Res<NetConfig>,
+    mut session: ResMut<NetSession>,
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    score: Res<Score>,
+    players: Query<&Transform, With<Player>>,
+    partners: Query<(&Transform, &Partner)>,
+    enemies: Query<(Entity, &Transform, &EnemyKind, &Visibility), With<Enemy>>,
+    shots: Query<(&Transform, &Projectile, &Sprite)>,
+    beams: Query<(&Transform, &Sprite), With<Beam>>,
+) {
+    let (Some(mut socket), Some(peer)) = (socket, session.peer) else {
+        return;
+    };
+    session.since_snapshot += time.delta_seconds();
+    if session.since_snapshot < 1. / config.snapshot_rate.max(1.) {
+        return;
+    }
+    session.since_snapshot = 0.;
+    let Ok(host) = players.get_single() else {
+        return;
+    };
+    session.tick += 1;
+
+    let partner = partners.iter().next();
+    let shots = shots
+        .iter()
+        .map(|(transform, projectile, sprite)| ShotState {
+            position: transform.translation.truncate(),
+            velocity: projectile.velocity,
+            rotation: body(transform).rotation,
+            size: sprite.custom_size.unwrap_or(Vec2::ONE),
+            color: sprite.color,
+        })
+        .chain(beams.iter().map(|(transform, sprite)| {
+            let size = sprite.custom_size.unwrap_or(Vec2::ONE);
+            // beams are drawn from their end, ghosts from their centre
+            let along = match sprite.anchor {
+                Anchor::CenterLeft => size.x / 2.,
+                _ => 0.,
+            };
+            let direction = (transform.rotation * Vec3::X).truncate();
+            ShotState {
+                position: transform.translation.truncate() + direction * along,
+                velocity: Vec2::ZERO,
+                rotation: body(transform).rotation,
+                size,
+                color: sprite.color,
+            }
+        }))
+        .collect();
+    let snapshot = Snapshot {
+        tick: session.tick,
+        host: body(host),
+        partner: partner.map(|(transform, _)| body(transform)),
+        acked: partner.map_or(0, |(_, partner)| partner.acked),
+        enemies: enemies
+            .iter()
+            .filter(|(_, _, _, visibility)| **visibility != Visibility::Hidden)
+            .map(|(entity, transform, kind, _)| EnemyState {
+                id: entity.to_bits(),
+                kind: *kind,
+                position: transform.translation.truncate(),
+            })
+            .collect(),
+        shots,
+        points: score.points,
+        wave: score.wave,
+        combo: score.combo,
+    };
+    socket.send(Message::Snapshot(snapshot).encode(), peer);
+}
+
+fn receive_snapshots(
+    mut commands: Commands,
+    net: Res<NetConfig>,
+    config: Res<GameConfig>,
+    defs: Res<Defs>,
+    mut session: ResMut<NetSession>,
+    mut prediction: ResMut<Prediction>,
+    mut ghosts: ResMut<Ghosts>,
+    mut score: ResMut<Score>,
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    mut players: Query<&mut Transform, (With<Player>, Without<Ghost>, Without<ShotGhost>)>,
+    mut followers: Query<(&mut Ghost, &mut Transform), (Without<Player>, Without<ShotGhost>)>,
+    mut shot_ghosts: Query<
+        (&mut ShotGhost, &mut Transform, &mut Sprite, &mut Visibility),
+        (Without<Player>, Without<Ghost>),
+    >,
+) {
+    let Some(mut socket) = socket else {
+        return;
+    };
+    let Some(snapshot) = socket
+        .receive()
+        .into_iter()
+        .filter_map(|(_, packet)| match Message::decode(&packet)? {
+            Message::Snapshot(snapshot) => Some(snapshot),
+            Message::Input(_) => None,
+        })
+        .filter(|snapshot| snapshot.tick > session.tick)
+        .max_by_key(|snapshot| snapshot.tick)
+    else {
+        return;
+    };
+    session.tick = snapshot.tick;
+    score.points = snapshot.points;
+    score.wave = snapshot.wave;
+    score.combo = snapshot.combo;
+
+    prediction.pending.retain(|(seq, _)| *seq > snapshot.acked);
+    if let (Some(partner), Ok(mut transform)) = (snapshot.partner, players.get_single_mut()) {
+        let unapplied: Vec2 = prediction.pending.iter().map(|(_, motion)| *motion).sum();
+        let drift = partner.position + unapplied - transform.translation.truncate();
+        let correction = if drift.length() > net.snap_distance {
+            drift
+        } else {
+            drift * net.correction
+        };
+        transform.translation += correction.extend(0.);
+        // a correction isn't a move of the player's own
+        if let Some(last) = &mut prediction.last_position {
+            *last += correction;
+        }
+    }
+
+    let host = *ghosts.host.get_or_insert_with(|| {
+        spawn_ghost(
+            &mut commands,
+            snapshot.host.position,
+            PARTNER_COLOR,
+            config.player.size,
+        )
+    });
+    if let Ok((mut ghost, mut transform)) = followers.get_mut(host) {
+        ghost.target = snapshot.host.position;
+        transform.rotation = Quat::from_rotation_z(snapshot.host.rotation);
+    }
+
+    let mut seen = HashMap::default();
+    for enemy in &snapshot.enemies {
+        let entity = match ghosts.enemies.remove(&enemy.id) {
+            Some(entity) => {
+                if let Ok((mut ghost, _)) = followers.get_mut(entity) {
+                    ghost.target = enemy.position;
+                }
+                entity
+            }
+            None => {
+                let def = defs.enemy(enemy.kind);
+                spawn_ghost(
+                    &mut commands,
+                    enemy.position,
+                    def.color,
+                    Vec2::splat(def.size),
+                )
+            }
+        };
+        seen.insert(enemy.id, entity);
+    }
+    // whatever is left wasn't in this snapshot
+    for (_, entity) in ghosts.enemies.drain() {
+        commands.entity(entity).despawn_recursive();
+    }
+    ghosts.enemies = seen;
+
+    while ghosts.shots.len() < snapshot.shots.len() {
+        let entity = commands
+            .spawn((
+                ShotGhost {
+                    velocity: Vec2::ZERO,
+                },
+                SpriteBundle::default(),
+            ))
+            .id();
+        ghosts.shots.push(entity);
+    }
+    for (index, entity) in ghosts.shots.iter().enumerate() {
+        let Ok((mut ghost, mut transform, mut sprite, mut visibility)) =
+            shot_ghosts.get_mut(*entity)
+        else {
+            // spawned this frame; shown from the next snapshot
+            continue;
+        };
+        let Some(shot) = snapshot.shots.get(index) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        ghost.velocity = shot.velocity;
+        *transform = Transform::from_translation(shot.position.extend(1.))
+            .with_rotation(Quat::from_rotation_z(shot.rotation));
+        sprite.color = shot.color;
+        sprite.custom_size = Some(shot.size);
+        *visibility = Visibility::Visible;
+    }
+}
+
+fn spawn_ghost(commands: &mut Commands, position: Vec2, color: Color, size: Vec2) -> Entity {
+    commands
+        .spawn((
+            Ghost { target: position },
+            SpriteBundle {
+                transform: Transform::from_translation(position.extend(0.)),
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .id()
+}
+
+fn follow_ghosts(
+    time: Res<Time>,
+    config: Res<NetConfig>,
+    mut ghosts: Query<(&Ghost, &mut Transform), Without<ShotGhost>>,
+    mut shots: Query<(&ShotGhost, &mut Transform), Without<Ghost>>,
+) {
+    let dt = time.delta_seconds();
+    let ease = 1. - (-config.smoothing * dt).exp();
+    for (ghost, mut transform) in &mut ghosts {
+        let position = transform.translation.truncate();
+        let next = position.lerp(ghost.target, ease);
+        transform.translation = next.extend(transform.translation.z);
+    }
+    for (ghost, mut transform) in &mut shots {
+        transform.translation += (ghost.velocity * dt).extend(0.);
+    }
+}
+
+fn send_input(
+    time: Res<Time>,
+    session: Res<NetSession>,
+    mut prediction: ResMut<Prediction>,
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    players: Query<(&Transform, &ActionState<Action>), With<Player>>,
+) {
+    let (Some(mut socket), Some(peer)) = (socket, session.peer) else {
+        return;
+    };
+    let Ok((transform, action_state)) = players.get_single() else {
+        return;
+    };
+    let position = transform.translation.truncate();
+    let motion = prediction
+        .last_position
+        .map_or(Vec2::ZERO, |last| position - last);
+    prediction.last_position = Some(position);
+    prediction.seq += 1;
+    let seq = prediction.seq;
+    prediction.pending.push_back((seq, motion));
+    // the host has stopped answering; it will be snapped back to anyway
+    while prediction.pending.len() > MAX_PENDING_INPUTS {
+        prediction.pending.pop_front();
+    }
+    let input = PartnerInput {
+        seq,
+        motion,
+        dt: time.delta_seconds(),
+        rotation: body(transform).rotation,
+        shooting: action_state.pressed(&Action::Shoot),
+    };
+    socket.send(Message::Input(input).encode(), peer);
+}
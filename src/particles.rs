@@ -0,0 +1,219 @@
+//! Short-lived animated sprites: trails, flashes, sparks.
+//!
+//! A [`ParticleEffect`] says how each particle moves and fades over its
+//! lifetime. An [`Emitter`] streams particles from its entity's position at
+//! a steady rate, scaled by [`EffectsQuality::density`], and
+//! [`EmitParticles`] bursts a number of them at once. Either way they are
+//! plain sprites taken from a [`Pool`] and counted against
+//! [`BudgetCategory::Vfx`], so a busy fight can't flood the screen with
+//! them.
+//!
+//! Particles advance with `Res<Time>`, following the game's time scale and
+//! freezing during hit-stops. Their random spread comes from a generator of
+//! their own, so effects never shift the run's [`GameRng`] sequence.
+
+use bevy::prelude::*;
+
+use crate::{
+    budget::{Budget, BudgetCategory, Budgeted},
+    pool::{self, Pool, PoolPlugin, Poolable},
+    quality::EffectsQuality,
+    rng::GameRng,
+    tween::{lerp_color, Ease},
+};
+
+const PARTICLE_SEED: u64 = 0x9A27_1C1E;
+
+/// How the particles of one effect look and move.
+#[derive(Debug, Clone, Copy, Reflect)]
+pub struct ParticleEffect {
+    /// Seconds each particle lives.
+    pub lifetime: f32,
+    /// Side length of the square sprite, at a scale of 1.
+    pub size: f32,
+    pub velocity: Vec2,
+    /// Largest random turn of `velocity` either way, in radians.
+    pub spread: f32,
+    pub gravity: Vec2,
+    pub start_color: Color,
+    pub end_color: Color,
+    pub start_scale: f32,
+    pub end_scale: f32,
+    /// Curve the color and scale follow from start to end.
+    pub ease: Ease,
+    /// Draw depth.
+    pub z: f32,
+}
+
+impl Default for ParticleEffect {
+    fn default() -> Self {
+        Self {
+            lifetime: 0.5,
+            size: 4.,
+            velocity: Vec2::ZERO,
+            spread: 0.,
+            gravity: Vec2::ZERO,
+            start_color: Color::WHITE,
+            end_color: Color::WHITE.with_a(0.),
+            start_scale: 1.,
+            end_scale: 1.,
+            ease: Ease::Linear,
+            z: 0.,
+        }
+    }
+}
+
+impl ParticleEffect {
+    /// Color and scale at `age` seconds into a particle's life.
+    pub fn sample(&self, age: f32) -> (Color, f32) {
+        let t = self.ease.apply(age / self.lifetime);
+        let scale = self.start_scale + (self.end_scale - self.start_scale) * t;
+        (lerp_color(self.start_color, self.end_color, t), scale)
+    }
+}
+
+/// Streams particles of `effect` from this entity, `rate` per second.
+#[derive(Component, Debug, Clone)]
+pub struct Emitter {
+    pub effect: ParticleEffect,
+    pub rate: f32,
+    /// Particles owed since the last one spawned.
+    pending: f32,
+}
+
+impl Emitter {
+    pub fn new(effect: ParticleEffect, rate: f32) -> Self {
+        Self {
+            effect,
+            rate,
+            pending: 0.,
+        }
+    }
+}
+
+/// Spawn `count` particles of `effect` at `position` at once.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct EmitParticles {
+    pub effect: ParticleEffect,
+    pub position: Vec2,
+    pub count: u32,
+}
+
+#[derive(Component, Debug)]
+pub struct Particle {
+    effect: ParticleEffect,
+    age: f32,
+    velocity: Vec2,
+}
+
+impl Poolable for Particle {
+    type Parts = (Particle, Budgeted);
+}
+
+#[derive(Resource)]
+struct ParticleRng(GameRng);
+
+impl Default for ParticleRng {
+    fn default() -> Self {
+        Self(GameRng::new(PARTICLE_SEED))
+    }
+}
+
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        // room for the whole effects budget
+        app.add_plugins(PoolPlugin::<Particle>::new(64, 300))
+            .init_resource::<ParticleRng>()
+            .add_event::<EmitParticles>()
+            .add_systems(
+                Update,
+                (run_emitters, spawn_particles, update_particles).chain(),
+            );
+    }
+}
+
+fn run_emitters(
+    time: Res<Time>,
+    quality: Res<EffectsQuality>,
+    mut emitters: Query<(&mut Emitter, &GlobalTransform)>,
+    mut bursts: EventWriter<EmitParticles>,
+) {
+    for (mut emitter, transform) in &mut emitters {
+        if emitter.rate <= 0. {
+            emitter.pending = 0.;
+            continue;
+        }
+        emitter.pending += emitter.rate * quality.density() * time.delta_seconds();
+        let count = emitter.pending.floor();
+        if count < 1. {
+            continue;
+        }
+        emitter.pending -= count;
+        bursts.send(EmitParticles {
+            effect: emitter.effect,
+            position: transform.translation().truncate(),
+            count: count as u32,
+        });
+    }
+}
+
+fn spawn_particles(
+    mut commands: Commands,
+    mut requests: EventReader<EmitParticles>,
+    mut pool: ResMut<Pool<Particle>>,
+    mut rng: ResMut<ParticleRng>,
+    mut budget: Budget,
+) {
+    for request in requests.read() {
+        let effect = request.effect;
+        let (color, scale) = effect.sample(0.);
+        for _ in 0..request.count {
+            if !budget.admit(&mut commands, BudgetCategory::Vfx) {
+                break;
+            }
+            let turn = rng.0.range(-effect.spread, effect.spread);
+            let bundle = (
+                Particle {
+                    effect,
+                    age: 0.,
+                    velocity: Vec2::from_angle(turn).rotate(effect.velocity),
+                },
+                SpriteBundle {
+                    transform: Transform::from_translation(request.position.extend(effect.z))
+                        .with_scale(Vec3::splat(scale)),
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(effect.size)),
+                        ..default()
+                    },
+                    ..default()
+                },
+            );
+            let mut particle = pool.acquire(&mut commands, bundle);
+            budget.track(&mut particle, BudgetCategory::Vfx);
+        }
+    }
+}
+
+fn update_particles(
+    time: Res<Time>,
+    mut budget: Budget,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut particle, mut transform, mut sprite) in &mut particles {
+        particle.age += dt;
+        if particle.age >= particle.effect.lifetime {
+            budget.return_to_pool(entity, pool::release::<Particle>);
+            continue;
+        }
+        let gravity = particle.effect.gravity;
+        particle.velocity += gravity * dt;
+        transform.translation += (particle.velocity * dt).extend(0.);
+        let (color, scale) = particle.effect.sample(particle.age);
+        sprite.color = color;
+        transform.scale = Vec3::splat(scale);
+    }
+}
@@ -0,0 +1,304 @@
+//! Finding the way to the player around walls.
+//!
+//! The arena is split into a [`NavGrid`] of [`PathfindingConfig::cell_size`]
+//! cells, blocked wherever an enemy would touch a wall collider, rebuilt
+//! whenever walls come or go. Rather than each enemy searching its own
+//! path, one [`FlowField`] counts the steps from the player's cell to every
+//! other cell, and a chasing enemy just heads for the neighbouring cell
+//! closest to the player; see [`FlowField::direction`].
+//!
+//! The field is searched a [`PathfindingConfig::cells_per_frame`] cells at
+//! a time, so a big arena never costs a frame more than that. Enemies keep
+//! following the last finished field while the next one is searched, and a
+//! new search starts once the player is in another cell. With no walls at
+//! all there is nothing to go around, and enemies head straight for the
+//! player as before.
+
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::{
+    config::GameConfig,
+    enemy::ENEMY_SIZE,
+    physics::{Collider, CollisionLayer},
+    state::GameState,
+    Player,
+};
+
+/// Steps to a cell the search hasn't reached, or can't.
+const UNREACHED: u32 = u32::MAX;
+
+/// Orthogonal neighbours, then diagonal ones.
+const NEIGHBOURS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct PathfindingConfig {
+    /// Width of a grid cell, in pixels.
+    pub cell_size: f32,
+    /// Cells the flow field search may visit in one frame.
+    pub cells_per_frame: usize,
+}
+
+impl Default for PathfindingConfig {
+    fn default() -> Self {
+        Self {
+            cell_size: 32.,
+            cells_per_frame: 600,
+        }
+    }
+}
+
+/// Which cells of the arena an enemy can't stand in.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct NavGrid {
+    /// World position of the bottom left corner of the first cell.
+    origin: Vec2,
+    cell_size: f32,
+    size: IVec2,
+    blocked: Vec<bool>,
+    /// Whether any cell is blocked.
+    walls: bool,
+}
+
+impl NavGrid {
+    pub fn new(area: Rect, cell_size: f32) -> Self {
+        let cell_size = cell_size.max(1.);
+        let size = (area.size() / cell_size).ceil().as_ivec2().max(IVec2::ONE);
+        Self {
+            origin: area.min,
+            cell_size,
+            size,
+            blocked: vec![false; (size.x * size.y) as usize],
+            walls: false,
+        }
+    }
+
+    /// Blocks every cell whose centre is within `radius` of `center`.
+    pub fn block_circle(&mut self, center: Vec2, radius: f32) {
+        let min = self.cell(center - Vec2::splat(radius));
+        let max = self.cell(center + Vec2::splat(radius));
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let cell = IVec2::new(x, y);
+                if self.center(cell).distance(center) <= radius {
+                    if let Some(index) = self.index(cell) {
+                        self.blocked[index] = true;
+                        self.walls = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The cell `position` is in, clamped to the grid.
+    pub fn cell(&self, position: Vec2) -> IVec2 {
+        ((position - self.origin) / self.cell_size)
+            .floor()
+            .as_ivec2()
+            .clamp(IVec2::ZERO, self.size - IVec2::ONE)
+    }
+
+    pub fn center(&self, cell: IVec2) -> Vec2 {
+        self.origin + (cell.as_vec2() + 0.5) * self.cell_size
+    }
+
+    /// The part of the world the grid covers.
+    pub fn area(&self) -> Rect {
+        Rect::from_corners(
+            self.origin,
+            self.origin + self.size.as_vec2() * self.cell_size,
+        )
+    }
+
+    fn index(&self, cell: IVec2) -> Option<usize> {
+        let inside = cell.cmpge(IVec2::ZERO).all() && cell.cmplt(self.size).all();
+        inside.then(|| (cell.y * self.size.x + cell.x) as usize)
+    }
+
+    pub fn is_blocked(&self, cell: IVec2) -> bool {
+        self.index(cell).is_none_or(|index| self.blocked[index])
+    }
+
+    /// Neighbours of `cell` that can be walked to from it. Diagonals are
+    /// left out when they would cut a blocked corner.
+    fn open_neighbours(&self, cell: IVec2) -> impl Iterator<Item = IVec2> + '_ {
+        NEIGHBOURS.into_iter().filter_map(move |step| {
+            let next = cell + step;
+            let diagonal = step.x != 0 && step.y != 0;
+            let corner_blocked = diagonal
+                && (self.is_blocked(cell + IVec2::new(step.x, 0))
+                    || self.is_blocked(cell + IVec2::new(0, step.y)));
+            (!self.is_blocked(next) && !corner_blocked).then_some(next)
+        })
+    }
+}
+
+/// A search of the grid from the player's cell, in progress.
+#[derive(Debug, Clone)]
+struct Search {
+    target: IVec2,
+    steps: Vec<u32>,
+    frontier: VecDeque<IVec2>,
+}
+
+/// Steps from every cell to the player's, as of the last finished search.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct FlowField {
+    target: Option<IVec2>,
+    steps: Vec<u32>,
+    search: Option<Search>,
+}
+
+impl FlowField {
+    /// Which way an enemy at `position` should go to reach the player, or
+    /// `None` when it may as well go straight there: there are no walls, the
+    /// player is close, or the field doesn't know the way yet.
+    pub fn direction(&self, grid: &NavGrid, position: Vec2) -> Option<Vec2> {
+        if !grid.walls {
+            return None;
+        }
+        let cell = grid.cell(position);
+        let here = self.steps_at(grid, cell);
+        if here == UNREACHED || here <= 1 {
+            return None;
+        }
+        let next = grid
+            .open_neighbours(cell)
+            .min_by_key(|next| self.steps_at(grid, *next))
+            .filter(|next| self.steps_at(grid, *next) < here)?;
+        (grid.center(next) - position).try_normalize()
+    }
+
+    fn steps_at(&self, grid: &NavGrid, cell: IVec2) -> u32 {
+        grid.index(cell)
+            .and_then(|index| self.steps.get(index).copied())
+            .unwrap_or(UNREACHED)
+    }
+
+    /// Drops the field and any search, for a grid that has changed.
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+pub struct PathfindingPlugin;
+
+impl Plugin for PathfindingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<PathfindingConfig>()
+            .init_resource::<PathfindingConfig>()
+            .init_resource::<NavGrid>()
+            .init_resource::<FlowField>()
+            .add_systems(
+                Update,
+                (build_nav_grid, search_flow_field)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn build_nav_grid(
+    config: Res<PathfindingConfig>,
+    game: Res<GameConfig>,
+    mut grid: ResMut<NavGrid>,
+    mut field: ResMut<FlowField>,
+    changed: Query<&Collider, Changed<Collider>>,
+    mut removed: RemovedComponents<Collider>,
+    colliders: Query<(Entity, &Transform, &Collider)>,
+    mut walls: Local<HashSet<Entity>>,
+) {
+    let area = game.arena.inner_rect(0.);
+    let resized = grid.origin != area.min || grid.cell_size != config.cell_size.max(1.);
+    let walls_added = changed
+        .iter()
+        .any(|collider| collider.layer.contains(CollisionLayer::WALL));
+    // bullets and enemies come and go all the time, walls hardly ever
+    let walls_removed = removed.read().any(|entity| walls.contains(&entity));
+    if !resized && !walls_added && !walls_removed && !grid.blocked.is_empty() {
+        return;
+    }
+    walls.clear();
+    let mut next = NavGrid::new(area, config.cell_size);
+    for (entity, transform, collider) in &colliders {
+        if collider.layer.contains(CollisionLayer::WALL) {
+            walls.insert(entity);
+            next.block_circle(
+                transform.translation.truncate(),
+                collider.radius + ENEMY_SIZE / 2.,
+            );
+        }
+    }
+    if next.blocked != grid.blocked || next.size != grid.size || resized {
+        *grid = next;
+        field.clear();
+    }
+}
+
+fn search_flow_field(
+    config: Res<PathfindingConfig>,
+    grid: Res<NavGrid>,
+    mut field: ResMut<FlowField>,
+    players: Query<&Transform, With<Player>>,
+) {
+    if !grid.walls {
+        return;
+    }
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+    let target = grid.cell(player.translation.truncate());
+
+    let searching = field.search.as_ref().map(|search| search.target);
+    if searching.is_none() && field.target != Some(target) {
+        let mut steps = vec![UNREACHED; grid.blocked.len()];
+        let mut frontier = VecDeque::new();
+        if let Some(index) = grid.index(target) {
+            steps[index] = 0;
+            frontier.push_back(target);
+        }
+        field.search = Some(Search {
+            target,
+            steps,
+            frontier,
+        });
+    }
+
+    let Some(search) = field.search.as_mut() else {
+        return;
+    };
+    for _ in 0..config.cells_per_frame.max(1) {
+        let Some(cell) = search.frontier.pop_front() else {
+            break;
+        };
+        let Some(here) = grid.index(cell).map(|index| search.steps[index]) else {
+            continue;
+        };
+        // the player's own cell may be blocked when hugging a wall, but
+        // the way out of it isn't
+        for next in grid.open_neighbours(cell) {
+            if let Some(index) = grid.index(next) {
+                if search.steps[index] == UNREACHED {
+                    search.steps[index] = here + 1;
+                    search.frontier.push_back(next);
+                }
+            }
+        }
+    }
+    if search.frontier.is_empty() {
+        let search = field.search.take().expect("searching");
+        field.target = Some(search.target);
+        field.steps = search.steps;
+    }
+}
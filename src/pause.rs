@@ -0,0 +1,263 @@
+//! Pausing, by hand or when the window loses focus.
+//!
+//! While playing, a pause button sits in the top left corner, since touch
+//! screens have no key to pause with; Escape does the same on a keyboard
+//! and Start on a gamepad. Any of them pauses straight into the pause menu,
+//! which resumes, restarts the run, goes into [photo mode](crate::photo),
+//! or quits it to the main menu. Escape or Start on the menu resumes too.
+//!
+//! Losing focus (tabbing away, or the tab being hidden on the web) moves
+//! `Playing` to `Paused`. Getting focus back doesn't resume: it shows a pause
+//! menu, so the player picks when play continues. While unfocused the app
+//! also only wakes about once a second instead of rendering every frame.
+//! All of this is skipped when [`GameplaySettings::pause_on_blur`] is off.
+//! Being [suspended](crate::lifecycle) in the background pauses the same
+//! way, whatever the setting.
+
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    window::WindowFocused,
+    winit::{UpdateMode, WinitSettings},
+};
+
+use crate::{
+    death::StartNewRun,
+    fonts::UiFonts,
+    i18n::LocalizedText,
+    layout::{Mirrored, StickSide},
+    lifecycle::AppSuspended,
+    replay,
+    settings::GameplaySettings,
+    state::{GameState, StateScoped},
+};
+
+/// How often the app wakes while unfocused.
+const UNFOCUSED_WAIT: Duration = Duration::from_secs(1);
+
+/// Set while the game is paused because focus was lost or the app was
+/// suspended, until the pause menu is shown.
+#[derive(Resource, Debug, Default)]
+struct BlurPaused(bool);
+
+#[derive(Component)]
+struct PauseMenu;
+
+/// Pauses into the pause menu when pressed.
+#[derive(Component)]
+pub struct PauseButton;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum PauseMenuButton {
+    Resume,
+    Restart,
+    Photo,
+    Quit,
+}
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlurPaused>()
+            .add_systems(OnEnter(GameState::Playing), spawn_pause_button)
+            .add_systems(
+                Update,
+                (
+                    apply_unfocused_mode
+                        // there is no winit in headless builds
+                        .run_if(resource_exists::<WinitSettings>)
+                        .run_if(resource_changed::<GameplaySettings>),
+                    pause_on_blur,
+                    press_pause.run_if(in_state(GameState::Playing)),
+                    press_pause_menu_buttons
+                        .run_if(in_state(GameState::Paused))
+                        .before(replay::begin_run),
+                ),
+            );
+    }
+}
+
+fn apply_unfocused_mode(settings: Res<GameplaySettings>, mut winit: ResMut<WinitSettings>) {
+    winit.unfocused_mode = if settings.pause_on_blur {
+        UpdateMode::ReactiveLowPower {
+            wait: UNFOCUSED_WAIT,
+        }
+    } else {
+        UpdateMode::Continuous
+    };
+}
+
+fn pause_on_blur(
+    mut commands: Commands,
+    mut focus: EventReader<WindowFocused>,
+    suspended: Res<AppSuspended>,
+    settings: Res<GameplaySettings>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut blur_paused: ResMut<BlurPaused>,
+    fonts: Res<UiFonts>,
+    menus: Query<(), With<PauseMenu>>,
+) {
+    // suspension pauses whatever the setting
+    let suspension = suspended.is_changed().then_some((!suspended.0, true));
+    let changes = focus
+        .read()
+        .map(|event| (event.focused, settings.pause_on_blur))
+        .chain(suspension);
+    for (focused, pause) in changes {
+        if !focused {
+            if pause && *state.get() == GameState::Playing {
+                next_state.set(GameState::Paused);
+                blur_paused.0 = true;
+            }
+        } else if blur_paused.0 {
+            blur_paused.0 = false;
+            if *state.get() == GameState::Paused && menus.is_empty() {
+                spawn_pause_menu(&mut commands, &fonts);
+            }
+        }
+    }
+}
+
+fn spawn_pause_button(mut commands: Commands, fonts: Res<UiFonts>) {
+    commands
+        .spawn((
+            PauseButton,
+            StateScoped(GameState::Playing),
+            Mirrored::new(StickSide::Move, 12.),
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(12.),
+                    // big enough for a thumb
+                    min_width: Val::Px(48.),
+                    min_height: Val::Px(48.),
+                    padding: UiRect::axes(Val::Px(10.), Val::Px(6.)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(1., 1., 1., 0.15).into(),
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "II",
+                fonts.bold(20., Color::WHITE),
+            ));
+        });
+}
+
+/// Whether Start was just pressed on any gamepad.
+fn start_pressed(gamepad_buttons: &ButtonInput<GamepadButton>) -> bool {
+    gamepad_buttons
+        .get_just_pressed()
+        .any(|button| button.button_type == GamepadButtonType::Start)
+}
+
+fn press_pause(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    fonts: Res<UiFonts>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<PauseButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keys.just_pressed(KeyCode::Escape)
+        || start_pressed(&gamepad_buttons)
+        || buttons.iter().any(|i| *i == Interaction::Pressed)
+    {
+        next_state.set(GameState::Paused);
+        spawn_pause_menu(&mut commands, &fonts);
+    }
+}
+
+pub fn spawn_pause_menu(commands: &mut Commands, fonts: &UiFonts) {
+    commands
+        .spawn((
+            PauseMenu,
+            StateScoped(GameState::Paused),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(12.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.85).into(),
+                z_index: ZIndex::Global(20),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                LocalizedText::new("Paused"),
+                TextBundle::from_section("Paused", fonts.bold(32., Color::WHITE)),
+            ));
+            for (button, label) in [
+                (PauseMenuButton::Resume, "Resume"),
+                (PauseMenuButton::Restart, "Restart"),
+                (PauseMenuButton::Photo, "Photo mode"),
+                (PauseMenuButton::Quit, "Quit"),
+            ] {
+                parent
+                    .spawn((
+                        button,
+                        ButtonBundle {
+                            style: Style {
+                                min_width: Val::Px(160.),
+                                padding: UiRect::axes(Val::Px(16.), Val::Px(12.)),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            background_color: Color::rgba(1., 1., 1., 0.15).into(),
+                            ..default()
+                        },
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            LocalizedText::new(label),
+                            TextBundle::from_section(label, fonts.bold(24., Color::WHITE)),
+                        ));
+                    });
+            }
+        });
+}
+
+fn press_pause_menu_buttons(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    buttons: Query<(&Interaction, &PauseMenuButton), Changed<Interaction>>,
+    menus: Query<(), With<PauseMenu>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut new_runs: EventWriter<StartNewRun>,
+) {
+    let pressed = buttons
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| *button);
+    // other screens pause too; Escape and Start only answer this menu
+    let escape = (keys.just_pressed(KeyCode::Escape) || start_pressed(&gamepad_buttons))
+        && !menus.is_empty();
+    match pressed.or(escape.then_some(PauseMenuButton::Resume)) {
+        Some(PauseMenuButton::Resume) => next_state.set(GameState::Playing),
+        Some(PauseMenuButton::Restart) => {
+            new_runs.send(StartNewRun);
+            next_state.set(GameState::Playing);
+        }
+        Some(PauseMenuButton::Photo) => next_state.set(GameState::PhotoMode),
+        Some(PauseMenuButton::Quit) => {
+            new_runs.send(StartNewRun);
+            next_state.set(GameState::MainMenu);
+        }
+        None => {}
+    }
+}
@@ -0,0 +1,201 @@
+//! Frame rate and entity counts on screen.
+//!
+//! With [`DebugSettings::performance_overlay`] on, a panel in the top right
+//! corner shows the frame rate, a graph of the last [`GRAPH_FRAMES`] frame
+//! times, the number of entities, and how many of those are enemies,
+//! projectiles and particles. F2 flips the setting at any time, in release
+//! builds too. The numbers come from Bevy's frame time and entity count
+//! diagnostics; the graph's bars are green within a 60 fps frame, yellow
+//! within a 30 fps one and red beyond.
+
+use std::collections::VecDeque;
+
+use bevy::{
+    diagnostic::{
+        DiagnosticPath, DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin,
+    },
+    prelude::*,
+};
+
+use crate::{
+    enemy::Enemy, fonts::UiFonts, particles::Particle, projectile::Projectile,
+    settings::DebugSettings,
+};
+
+const TOGGLE_KEY: KeyCode = KeyCode::F2;
+/// Frames shown in the graph, one bar each.
+const GRAPH_FRAMES: usize = 90;
+const BAR_WIDTH: f32 = 2.;
+const GRAPH_HEIGHT: f32 = 48.;
+/// Frame time at the top of the graph, in milliseconds.
+const GRAPH_MAX_MS: f32 = 50.;
+const GOOD_MS: f32 = 1000. / 60.;
+const OK_MS: f32 = 1000. / 30.;
+
+#[derive(Component)]
+struct PerfOverlay;
+
+#[derive(Component)]
+struct PerfText;
+
+/// The `index`th bar of the graph, counted from the oldest frame.
+#[derive(Component)]
+struct FrameBar(usize);
+
+pub struct PerfOverlayPlugin;
+
+impl Plugin for PerfOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        if !app.is_plugin_added::<EntityCountDiagnosticsPlugin>() {
+            app.add_plugins(EntityCountDiagnosticsPlugin);
+        }
+        app.add_systems(Startup, spawn_overlay).add_systems(
+            Update,
+            (
+                toggle_overlay,
+                show_overlay.run_if(resource_changed::<DebugSettings>),
+                update_overlay.run_if(overlay_enabled),
+            )
+                .chain(),
+        );
+    }
+}
+
+fn overlay_enabled(settings: Res<DebugSettings>) -> bool {
+    settings.performance_overlay
+}
+
+fn spawn_overlay(mut commands: Commands, fonts: Res<UiFonts>) {
+    commands
+        .spawn((
+            PerfOverlay,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(8.),
+                    right: Val::Px(8.),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.),
+                    padding: UiRect::all(Val::Px(6.)),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                visibility: Visibility::Hidden,
+                z_index: ZIndex::Global(35),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                PerfText,
+                TextBundle::from_section("", fonts.style(12., Color::WHITE)),
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(GRAPH_FRAMES as f32 * BAR_WIDTH),
+                        height: Val::Px(GRAPH_HEIGHT),
+                        align_items: AlignItems::FlexEnd,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|graph| {
+                    for index in 0..GRAPH_FRAMES {
+                        graph.spawn((
+                            FrameBar(index),
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Px(BAR_WIDTH),
+                                    height: Val::Px(0.),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                        ));
+                    }
+                });
+        });
+}
+
+fn toggle_overlay(keys: Res<ButtonInput<KeyCode>>, mut settings: ResMut<DebugSettings>) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        settings.performance_overlay = !settings.performance_overlay;
+    }
+}
+
+fn show_overlay(
+    settings: Res<DebugSettings>,
+    mut overlays: Query<&mut Visibility, With<PerfOverlay>>,
+) {
+    for mut visibility in &mut overlays {
+        *visibility = if settings.performance_overlay {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn update_overlay(
+    diagnostics: Res<DiagnosticsStore>,
+    mut frame_times: Local<VecDeque<f32>>,
+    enemies: Query<(), With<Enemy>>,
+    projectiles: Query<(), With<Projectile>>,
+    particles: Query<(), With<Particle>>,
+    mut texts: Query<&mut Text, With<PerfText>>,
+    mut bars: Query<(&FrameBar, &mut Style, &mut BackgroundColor)>,
+) {
+    let value = |path: &DiagnosticPath| {
+        diagnostics
+            .get(path)
+            .and_then(|diagnostic| diagnostic.smoothed())
+            .unwrap_or(0.)
+    };
+    let fps = value(&FrameTimeDiagnosticsPlugin::FPS);
+    let entities = value(&EntityCountDiagnosticsPlugin::ENTITY_COUNT);
+    if let Some(frame_time) = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FRAME_TIME)
+        .and_then(|diagnostic| diagnostic.value())
+    {
+        if frame_times.len() == GRAPH_FRAMES {
+            frame_times.pop_front();
+        }
+        frame_times.push_back(frame_time as f32);
+    }
+    let frame_time = frame_times.back().copied().unwrap_or(0.);
+
+    for mut text in &mut texts {
+        text.sections[0].value = format!(
+            "{fps:.0} fps  {frame_time:.1} ms\n\
+             entities {entities:.0}\n\
+             enemies {}  projectiles {}  particles {}",
+            enemies.iter().len(),
+            projectiles.iter().len(),
+            particles.iter().len(),
+        );
+    }
+
+    // the newest frame is on the right
+    let offset = GRAPH_FRAMES - frame_times.len();
+    for (bar, mut style, mut color) in &mut bars {
+        let ms = bar
+            .0
+            .checked_sub(offset)
+            .and_then(|index| frame_times.get(index))
+            .copied()
+            .unwrap_or(0.);
+        style.height = Val::Px((ms / GRAPH_MAX_MS).min(1.) * GRAPH_HEIGHT);
+        *color = if ms <= GOOD_MS {
+            Color::rgb(0.3, 0.9, 0.4)
+        } else if ms <= OK_MS {
+            Color::rgb(0.95, 0.8, 0.2)
+        } else {
+            Color::rgb(0.95, 0.3, 0.25)
+        }
+        .into();
+    }
+}
@@ -0,0 +1,363 @@
+//! Photo mode: a frozen moment of the run to frame and take a picture of.
+//!
+//! The pause menu's "Photo mode" freezes the game clock (see
+//! [`game_time`](crate::game_time)), so the rain hangs in the air, hides
+//! every bit of UI and lets go of the camera. Drag with a finger or the
+//! left mouse button, the arrows or WASD, or a gamepad's left stick to pan,
+//! within the arena; pinch, scroll, +/- or the right stick to zoom. The
+//! camera keeps the view's turn in the rotating camera mode.
+//!
+//! "Capture", Enter or the gamepad's south button saves a screenshot,
+//! without the photo mode's own buttons on it, to
+//! [`storage::photo_path`]; on the web the browser downloads it instead.
+//! "Back", Escape, or the east button or Start returns to the pause menu,
+//! and the camera eases back onto the player once play resumes.
+
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+    render::view::screenshot::ScreenshotManager,
+    window::PrimaryWindow,
+};
+
+use crate::{
+    camera::{pan_and_zoom, Detached, MainCamera},
+    config::GameConfig,
+    fonts::UiFonts,
+    gestures::Pinch,
+    i18n::{Locale, LocalizedText},
+    pause::spawn_pause_menu,
+    state::{GameState, StateScoped},
+    storage,
+};
+
+/// Pan speed with keys or a stick, in pixels per second at a zoom of 1.
+const PAN_SPEED: f32 = 600.;
+/// Zoom change per wheel notch, or per second of a held key or stick.
+const ZOOM_STEP: f32 = 0.1;
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 4.;
+/// Frames the buttons stay hidden for after a capture, so the picture is
+/// taken without them.
+const CAPTURE_FRAMES: u8 = 2;
+
+#[derive(Component)]
+struct PhotoOverlay;
+
+/// Shows whether the last picture was saved.
+#[derive(Component)]
+struct PhotoStatus;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum PhotoButton {
+    Capture,
+    Back,
+}
+
+/// UI hidden for photo mode, with the visibility it had before.
+#[derive(Component)]
+struct PhotoHidden(Visibility);
+
+/// Frames left until the buttons show again after a capture.
+#[derive(Resource, Debug, Default)]
+struct Capturing(u8);
+
+#[derive(Resource, Debug, Default)]
+struct PhotoPinch(Pinch);
+
+pub struct PhotoPlugin;
+
+impl Plugin for PhotoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Capturing>()
+            .init_resource::<PhotoPinch>()
+            .add_systems(
+                OnEnter(GameState::PhotoMode),
+                (detach_camera, spawn_overlay),
+            )
+            .add_systems(
+                Update,
+                (hide_ui, move_camera, press_photo_buttons, show_overlay)
+                    .chain()
+                    .run_if(in_state(GameState::PhotoMode)),
+            )
+            .add_systems(OnExit(GameState::PhotoMode), (restore_ui, attach_camera));
+    }
+}
+
+fn detach_camera(mut commands: Commands, cameras: Query<Entity, With<MainCamera>>) {
+    for entity in &cameras {
+        commands.entity(entity).insert(Detached);
+    }
+}
+
+fn attach_camera(
+    mut commands: Commands,
+    mut pinch: ResMut<PhotoPinch>,
+    cameras: Query<Entity, With<MainCamera>>,
+) {
+    *pinch = PhotoPinch::default();
+    for entity in &cameras {
+        // the follow and the regular zoom ease it home
+        commands.entity(entity).remove::<Detached>();
+    }
+}
+
+fn spawn_overlay(mut commands: Commands, fonts: Res<UiFonts>) {
+    commands
+        .spawn((
+            PhotoOverlay,
+            StateScoped(GameState::PhotoMode),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(24.),
+                    width: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                z_index: ZIndex::Global(20),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            let hint = "Drag to pan, pinch or scroll to zoom";
+            parent.spawn((
+                LocalizedText::new(hint),
+                TextBundle::from_section(hint, fonts.style(16., Color::WHITE)),
+            ));
+            parent.spawn((
+                PhotoStatus,
+                TextBundle::from_section("", fonts.style(16., Color::WHITE)),
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(12.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (button, label) in [
+                        (PhotoButton::Capture, "Capture"),
+                        (PhotoButton::Back, "Back"),
+                    ] {
+                        parent
+                            .spawn((
+                                button,
+                                ButtonBundle {
+                                    style: Style {
+                                        min_width: Val::Px(120.),
+                                        padding: UiRect::axes(Val::Px(16.), Val::Px(12.)),
+                                        justify_content: JustifyContent::Center,
+                                        ..default()
+                                    },
+                                    background_color: Color::rgba(0., 0., 0., 0.5).into(),
+                                    ..default()
+                                },
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    LocalizedText::new(label),
+                                    TextBundle::from_section(label, fonts.bold(24., Color::WHITE)),
+                                ));
+                            });
+                    }
+                });
+        });
+}
+
+/// Keeps every other piece of UI hidden, including any that shows up
+/// while in photo mode.
+fn hide_ui(
+    mut commands: Commands,
+    mut roots: Query<
+        (Entity, &mut Visibility, Has<PhotoHidden>),
+        (With<Node>, Without<Parent>, Without<PhotoOverlay>),
+    >,
+) {
+    for (entity, mut visibility, hidden) in &mut roots {
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+        if !hidden {
+            commands.entity(entity).insert(PhotoHidden(*visibility));
+        }
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn restore_ui(mut commands: Commands, mut hidden: Query<(Entity, &PhotoHidden, &mut Visibility)>) {
+    for (entity, PhotoHidden(before), mut visibility) in &mut hidden {
+        *visibility = *before;
+        commands.entity(entity).remove::<PhotoHidden>();
+    }
+}
+
+fn move_camera(
+    time: Res<Time<Real>>,
+    game: Res<GameConfig>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut pinch: ResMut<PhotoPinch>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let dt = time.delta_seconds();
+    let notches: f32 = wheel.read().map(|event| event.y.signum()).sum();
+    let mut zoom = -notches * ZOOM_STEP;
+    // screen pixels to drag the world by, y up
+    let mut dragged = Vec2::ZERO;
+    if buttons.pressed(MouseButton::Left) {
+        let moved: Vec2 = motion.read().map(|event| event.delta).sum();
+        dragged += Vec2::new(moved.x, -moved.y);
+    } else {
+        motion.clear();
+    }
+
+    let fingers: Vec<_> = touches.iter().collect();
+    if let [finger] = fingers[..] {
+        dragged += Vec2::new(finger.delta().x, -finger.delta().y);
+    }
+    let pinched = match fingers[..] {
+        [a, b, ..] => Some((a.position(), b.position())),
+        _ => None,
+    };
+    // spreading the fingers zooms in, keeping up with them
+    zoom -= pinch.0.update(pinched) * std::f32::consts::LN_2;
+
+    let mut pan = Vec2::ZERO;
+    for (keys_for, direction) in [
+        ([KeyCode::ArrowUp, KeyCode::KeyW], Vec2::Y),
+        ([KeyCode::ArrowDown, KeyCode::KeyS], Vec2::NEG_Y),
+        ([KeyCode::ArrowLeft, KeyCode::KeyA], Vec2::NEG_X),
+        ([KeyCode::ArrowRight, KeyCode::KeyD], Vec2::X),
+    ] {
+        if keys.any_pressed(keys_for) {
+            pan += direction;
+        }
+    }
+    if keys.pressed(KeyCode::Equal) {
+        zoom -= ZOOM_STEP * 10. * dt;
+    }
+    if keys.pressed(KeyCode::Minus) {
+        zoom += ZOOM_STEP * 10. * dt;
+    }
+    for gamepad in gamepads.iter() {
+        let axis = |axis_type| axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.);
+        pan += Vec2::new(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+        zoom -= axis(GamepadAxisType::RightStickY) * ZOOM_STEP * 10. * dt;
+    }
+
+    let bounds = game.arena.inner_rect(0.);
+    for (mut transform, mut projection) in &mut cameras {
+        let offset = pan.clamp_length_max(1.) * PAN_SPEED * dt - dragged;
+        pan_and_zoom(
+            &mut transform,
+            &mut projection,
+            offset,
+            zoom,
+            (MIN_ZOOM, MAX_ZOOM),
+        );
+        let position = transform
+            .translation
+            .truncate()
+            .clamp(bounds.min, bounds.max);
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+/// Whether `button` was just pressed on any gamepad.
+fn gamepad_pressed(
+    gamepad_buttons: &ButtonInput<GamepadButton>,
+    button: GamepadButtonType,
+) -> bool {
+    gamepad_buttons
+        .get_just_pressed()
+        .any(|pressed| pressed.button_type == button)
+}
+
+fn press_photo_buttons(
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    buttons: Query<(&Interaction, &PhotoButton), Changed<Interaction>>,
+    windows: Query<Entity, With<PrimaryWindow>>,
+    mut screenshots: ResMut<ScreenshotManager>,
+    mut capturing: ResMut<Capturing>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut status: Query<&mut Text, With<PhotoStatus>>,
+) {
+    let pressed = buttons
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| *button);
+    let back = keys.just_pressed(KeyCode::Escape)
+        || gamepad_pressed(&gamepad_buttons, GamepadButtonType::East)
+        || gamepad_pressed(&gamepad_buttons, GamepadButtonType::Start);
+    let capture = keys.just_pressed(KeyCode::Enter)
+        || gamepad_pressed(&gamepad_buttons, GamepadButtonType::South);
+
+    match pressed
+        .or(back.then_some(PhotoButton::Back))
+        .or(capture.then_some(PhotoButton::Capture))
+    {
+        Some(PhotoButton::Back) => {
+            next_state.set(GameState::Paused);
+            spawn_pause_menu(&mut commands, &fonts);
+        }
+        Some(PhotoButton::Capture) if capturing.0 == 0 => {
+            let Ok(window) = windows.get_single() else {
+                return;
+            };
+            let path = storage::photo_path();
+            let saved = screenshots.save_screenshot_to_disk(window, &path);
+            let message = match saved {
+                Ok(()) => {
+                    info!("saving a photo to {}", path.display());
+                    capturing.0 = CAPTURE_FRAMES;
+                    "Photo saved"
+                }
+                Err(err) => {
+                    error!("failed to take a photo: {err}");
+                    "Photo failed"
+                }
+            };
+            for mut text in &mut status {
+                text.sections[0].value = locale.tr(message).into();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Hides the buttons for the frames a capture is being taken, and shows
+/// them again after.
+fn show_overlay(
+    mut capturing: ResMut<Capturing>,
+    mut overlays: Query<&mut Visibility, With<PhotoOverlay>>,
+) {
+    let wanted = if capturing.0 > 0 {
+        capturing.0 -= 1;
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    for mut visibility in &mut overlays {
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+}
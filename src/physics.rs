@@ -0,0 +1,262 @@
+//! Circle collision detection with layer filtering.
+//!
+//! Every [`Collider`] belongs to one [`CollisionLayer`] and carries a
+//! [`CollisionMask`] of the layers it wants to touch. A pair is only tested
+//! when either side's mask includes the other's layer, and that check runs
+//! on the spatial grid candidates before any distance test.
+//!
+//! Walls are solid: the player or an enemy overlapping a [`CollisionLayer::WALL`]
+//! collider is pushed back out along the line between their centres.
+
+use bevy::{prelude::*, utils::HashMap};
+use bitflags::bitflags;
+
+use crate::spatial::SpatialGrid;
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct CollisionLayer: u32 {
+        const PLAYER = 1 << 0;
+        const PLAYER_BULLET = 1 << 1;
+        const ENEMY = 1 << 2;
+        const ENEMY_BULLET = 1 << 3;
+        const PICKUP = 1 << 4;
+        const WALL = 1 << 5;
+    }
+}
+
+/// The layers a collider interacts with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionMask(pub CollisionLayer);
+
+/// Default interaction rules: each layer and the layers it wants to touch.
+/// Enemies are deliberately absent from their own mask, and bullets never
+/// include their owner's faction.
+const DEFAULT_RULES: [(CollisionLayer, CollisionLayer); 6] = [
+    (
+        CollisionLayer::PLAYER,
+        CollisionLayer::ENEMY
+            .union(CollisionLayer::ENEMY_BULLET)
+            .union(CollisionLayer::PICKUP)
+            .union(CollisionLayer::WALL),
+    ),
+    (
+        CollisionLayer::PLAYER_BULLET,
+        CollisionLayer::ENEMY.union(CollisionLayer::WALL),
+    ),
+    (
+        CollisionLayer::ENEMY,
+        CollisionLayer::PLAYER
+            .union(CollisionLayer::PLAYER_BULLET)
+            .union(CollisionLayer::WALL),
+    ),
+    (
+        CollisionLayer::ENEMY_BULLET,
+        CollisionLayer::PLAYER.union(CollisionLayer::WALL),
+    ),
+    (CollisionLayer::PICKUP, CollisionLayer::PLAYER),
+    (
+        CollisionLayer::WALL,
+        CollisionLayer::PLAYER
+            .union(CollisionLayer::PLAYER_BULLET)
+            .union(CollisionLayer::ENEMY)
+            .union(CollisionLayer::ENEMY_BULLET),
+    ),
+];
+
+impl CollisionMask {
+    /// The default interaction rules for a collider on `layer`.
+    pub fn for_layer(layer: CollisionLayer) -> Self {
+        let mask = DEFAULT_RULES
+            .iter()
+            .filter(|(rule_layer, _)| layer.contains(*rule_layer))
+            .fold(CollisionLayer::empty(), |mask, (_, rule_mask)| {
+                mask | *rule_mask
+            });
+        Self(mask)
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Collider {
+    pub radius: f32,
+    pub layer: CollisionLayer,
+    pub mask: CollisionMask,
+}
+
+impl Collider {
+    /// A collider on `layer` using the default rules of [`CollisionMask::for_layer`].
+    pub fn new(radius: f32, layer: CollisionLayer) -> Self {
+        Self {
+            radius,
+            layer,
+            mask: CollisionMask::for_layer(layer),
+        }
+    }
+
+    /// Whether a pair of colliders should be tested against each other.
+    pub fn interacts_with(&self, other: &Collider) -> bool {
+        self.mask.0.intersects(other.layer) || other.mask.0.intersects(self.layer)
+    }
+}
+
+/// Two colliders overlapped this tick.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CollisionEvent {
+    pub a: Entity,
+    pub b: Entity,
+}
+
+/// Size of a [`ColliderGrid`] cell, in world units.
+const COLLIDER_GRID_CELL_SIZE: f32 = 64.;
+
+/// Every collider bucketed by position, rebuilt each tick before detection.
+#[derive(Resource, Debug, Clone)]
+pub struct ColliderGrid(pub SpatialGrid);
+
+impl Default for ColliderGrid {
+    fn default() -> Self {
+        Self(SpatialGrid::new(COLLIDER_GRID_CELL_SIZE))
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CollisionSet;
+
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ColliderGrid>()
+            .add_event::<CollisionEvent>()
+            .add_systems(
+                FixedUpdate,
+                (detect_collisions, push_out_of_walls)
+                    .chain()
+                    .in_set(CollisionSet),
+            );
+    }
+}
+
+fn detect_collisions(
+    mut grid: ResMut<ColliderGrid>,
+    colliders: Query<(Entity, &Transform, &Collider)>,
+    mut collisions: EventWriter<CollisionEvent>,
+) {
+    grid.0.clear();
+    let mut by_entity = HashMap::default();
+    let mut max_radius: f32 = 0.;
+    for (entity, transform, collider) in &colliders {
+        let position = transform.translation.truncate();
+        grid.0.insert(entity, position);
+        by_entity.insert(entity, *collider);
+        max_radius = max_radius.max(collider.radius);
+    }
+
+    for (entity, transform, collider) in &colliders {
+        let position = transform.translation.truncate();
+        for (other, other_position) in grid.0.query_cells(position, collider.radius + max_radius) {
+            // each pair is visited from both sides, keep one
+            if other <= entity {
+                continue;
+            }
+            let Some(other_collider) = by_entity.get(&other) else {
+                continue;
+            };
+            if !collider.interacts_with(other_collider) {
+                continue;
+            }
+            let reach = collider.radius + other_collider.radius;
+            if position.distance_squared(other_position) <= reach * reach {
+                collisions.send(CollisionEvent {
+                    a: entity,
+                    b: other,
+                });
+            }
+        }
+    }
+}
+
+fn push_out_of_walls(
+    mut collisions: EventReader<CollisionEvent>,
+    mut colliders: Query<(&mut Transform, &Collider)>,
+) {
+    for event in collisions.read() {
+        for (wall, mover) in [(event.a, event.b), (event.b, event.a)] {
+            let Ok([(wall_transform, wall_collider), (mut transform, collider)]) =
+                colliders.get_many_mut([wall, mover])
+            else {
+                continue;
+            };
+            if !wall_collider.layer.contains(CollisionLayer::WALL)
+                || !collider
+                    .layer
+                    .intersects(CollisionLayer::PLAYER | CollisionLayer::ENEMY)
+            {
+                continue;
+            }
+            let offset = transform.translation.truncate() - wall_transform.translation.truncate();
+            let overlap = wall_collider.radius + collider.radius - offset.length();
+            if overlap > 0. {
+                // dead centre on a wall: any way out will do
+                let direction = offset.try_normalize().unwrap_or(Vec2::Y);
+                transform.translation += (direction * overlap).extend(0.);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_rules_pick_the_pairs_that_interact() {
+        let collider = |layer| Collider::new(1., layer);
+        let player = collider(CollisionLayer::PLAYER);
+        let player_bullet = collider(CollisionLayer::PLAYER_BULLET);
+        let enemy = collider(CollisionLayer::ENEMY);
+        let enemy_bullet = collider(CollisionLayer::ENEMY_BULLET);
+        let pickup = collider(CollisionLayer::PICKUP);
+        let wall = collider(CollisionLayer::WALL);
+
+        for (a, b) in [
+            (&player, &enemy),
+            (&player, &enemy_bullet),
+            (&player, &pickup),
+            (&player, &wall),
+            (&player_bullet, &enemy),
+            (&player_bullet, &wall),
+            (&enemy, &wall),
+            (&enemy_bullet, &wall),
+        ] {
+            assert!(a.interacts_with(b), "{:?} and {:?}", a.layer, b.layer);
+            assert!(b.interacts_with(a), "{:?} and {:?}", b.layer, a.layer);
+        }
+        for (a, b) in [
+            (&player, &player_bullet),
+            (&enemy, &enemy),
+            (&enemy, &enemy_bullet),
+            (&enemy, &pickup),
+            (&player_bullet, &player_bullet),
+            (&player_bullet, &enemy_bullet),
+            (&pickup, &wall),
+        ] {
+            assert!(!a.interacts_with(b), "{:?} and {:?}", a.layer, b.layer);
+            assert!(!b.interacts_with(a), "{:?} and {:?}", b.layer, a.layer);
+        }
+    }
+
+    #[test]
+    fn one_sided_masks_still_interact() {
+        let mut ghost = Collider::new(1., CollisionLayer::ENEMY);
+        ghost.mask = CollisionMask(CollisionLayer::empty());
+        let bullet = Collider::new(1., CollisionLayer::PLAYER_BULLET);
+        assert!(ghost.interacts_with(&bullet));
+        assert!(bullet.interacts_with(&ghost));
+
+        let mut blind = Collider::new(1., CollisionLayer::PICKUP);
+        blind.mask = CollisionMask(CollisionLayer::empty());
+        assert!(!blind.interacts_with(&Collider::new(1., CollisionLayer::WALL)));
+    }
+}
@@ -0,0 +1,446 @@
+//! Health, ammo and power-ups dropped by enemies.
+//!
+//! A killed enemy drops a [`Pickup`] [`PickupConfig::drop_chance`] of the
+//! time, and one of the [weapons that drop](crate::defs::Defs::drops) as a
+//! [`WeaponPickup`] [`PickupConfig::weapon_drop_chance`] of the time. It
+//! lies where the enemy fell for [`PickupConfig::lifetime`] seconds, fading
+//! out towards the end, and slides towards the player once they are within
+//! [`PickupConfig::magnet_radius`]. Health and ammo are used on the spot. A
+//! [`PowerUp`] goes into the player's [`StatModifiers`] for
+//! [`PickupConfig::power_up_duration`] seconds, where movement and firing
+//! read it; a shield also keeps the player [`Invulnerable`] while it lasts.
+//! Picking up a power-up already running starts its time over. The ones
+//! running are shown on the [HUD](crate::hud).
+//!
+//! Pickups are bucketed into a [`PickupGrid`] at the start of each frame,
+//! so the magnet and collection only look at the ones around the player.
+
+use bevy::prelude::*;
+
+use crate::{
+    death::{Invulnerable, StartNewRun},
+    defs::Defs,
+    despawn::DespawnQueue,
+    events::EnemyKilled,
+    floating_text::FloatingText,
+    health::Health,
+    rng::GameRng,
+    spatial::SpatialGrid,
+    state::GameState,
+    weapon::{spawn_weapon_pickup, WeaponInventory, WeaponPickup},
+    Player,
+};
+
+const PICKUP_SIZE: f32 = 12.;
+/// Seconds before a pickup expires that it starts fading out.
+const FADE_TIME: f32 = 2.;
+
+/// A timed boost from a pickup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum PowerUp {
+    Speed,
+    RapidFire,
+    Shield,
+}
+
+impl PowerUp {
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUp::Speed => "Speed",
+            PowerUp::RapidFire => "Rapid fire",
+            PowerUp::Shield => "Shield",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum PickupKind {
+    Health,
+    Ammo,
+    PowerUp(PowerUp),
+}
+
+impl PickupKind {
+    pub const ALL: [PickupKind; 5] = [
+        PickupKind::Health,
+        PickupKind::Ammo,
+        PickupKind::PowerUp(PowerUp::Speed),
+        PickupKind::PowerUp(PowerUp::RapidFire),
+        PickupKind::PowerUp(PowerUp::Shield),
+    ];
+
+    pub fn color(self) -> Color {
+        match self {
+            PickupKind::Health => Color::rgb(0.3, 1., 0.4),
+            PickupKind::Ammo => Color::rgb(1., 0.85, 0.3),
+            PickupKind::PowerUp(PowerUp::Speed) => Color::rgb(0.3, 0.8, 1.),
+            PickupKind::PowerUp(PowerUp::RapidFire) => Color::rgb(1., 0.4, 0.2),
+            PickupKind::PowerUp(PowerUp::Shield) => Color::rgb(0.8, 0.5, 1.),
+        }
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct PickupConfig {
+    /// Odds of a killed enemy dropping a pickup, from 0 to 1.
+    pub drop_chance: f32,
+    /// Odds of a killed enemy dropping a weapon, from 0 to 1.
+    pub weapon_drop_chance: f32,
+    /// Seconds a pickup lies around before it's gone.
+    pub lifetime: f32,
+    /// Distance from the player pickups start sliding towards them.
+    pub magnet_radius: f32,
+    /// Speed pickups slide towards the player at, in pixels per second.
+    pub magnet_speed: f32,
+    /// Distance from the player pickups are collected at.
+    pub collect_radius: f32,
+    /// Health a health pack gives back.
+    pub health: f32,
+    /// Share of each weapon's full ammo an ammo pickup gives back.
+    pub ammo: f32,
+    /// Seconds a power-up lasts.
+    pub power_up_duration: f32,
+    /// What the player's speed is multiplied by during a speed boost.
+    pub speed_factor: f32,
+    /// What the fire rate is multiplied by during rapid fire.
+    pub fire_rate_factor: f32,
+}
+
+impl Default for PickupConfig {
+    fn default() -> Self {
+        Self {
+            drop_chance: 0.12,
+            weapon_drop_chance: 0.02,
+            lifetime: 12.,
+            magnet_radius: 90.,
+            magnet_speed: 260.,
+            collect_radius: 20.,
+            health: 25.,
+            ammo: 0.5,
+            power_up_duration: 8.,
+            speed_factor: 1.4,
+            fire_rate_factor: 2.,
+        }
+    }
+}
+
+/// A pickup lying in the world, gone after `remaining` seconds.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    pub remaining: f32,
+}
+
+#[derive(Reflect, Debug, Clone, Copy)]
+pub struct ActiveModifier {
+    pub power_up: PowerUp,
+    /// What the stat the power-up boosts is multiplied by.
+    pub factor: f32,
+    pub remaining: f32,
+}
+
+/// The player's running power-ups. Inserted by the first one picked up.
+#[derive(Component, Reflect, Debug, Clone, Default)]
+pub struct StatModifiers {
+    pub active: Vec<ActiveModifier>,
+}
+
+impl StatModifiers {
+    /// Starts `power_up` for `duration` seconds, or starts it over if it's
+    /// already running.
+    pub fn grant(&mut self, power_up: PowerUp, factor: f32, duration: f32) {
+        self.active.retain(|active| active.power_up != power_up);
+        self.active.push(ActiveModifier {
+            power_up,
+            factor,
+            remaining: duration,
+        });
+    }
+
+    /// What the stat `power_up` boosts is multiplied by, 1 when it isn't
+    /// running.
+    pub fn factor(&self, power_up: PowerUp) -> f32 {
+        self.active
+            .iter()
+            .filter(|active| active.power_up == power_up)
+            .map(|active| active.factor)
+            .product()
+    }
+}
+
+/// What the player's stats are multiplied by for `power_up`, 1 without any
+/// modifiers.
+pub fn stat_factor(modifiers: Option<&StatModifiers>, power_up: PowerUp) -> f32 {
+    modifiers.map_or(1., |modifiers| modifiers.factor(power_up))
+}
+
+/// Size of a [`PickupGrid`] cell, in world units.
+const PICKUP_GRID_CELL_SIZE: f32 = 64.;
+
+/// Where the pickups were at the start of the frame.
+#[derive(Resource, Debug, Clone)]
+pub struct PickupGrid(pub SpatialGrid);
+
+impl Default for PickupGrid {
+    fn default() -> Self {
+        Self(SpatialGrid::new(PICKUP_GRID_CELL_SIZE))
+    }
+}
+
+pub struct PickupsPlugin;
+
+impl Plugin for PickupsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<PickupConfig>()
+            .register_type::<StatModifiers>()
+            .init_resource::<PickupConfig>()
+            .init_resource::<PickupGrid>()
+            .add_systems(PreUpdate, rebuild_pickup_grid)
+            .add_systems(
+                Update,
+                (
+                    drop_pickups,
+                    (
+                        age_pickups,
+                        attract_pickups,
+                        collect_pickups,
+                        wear_off_modifiers,
+                    )
+                        .chain()
+                        .run_if(in_state(GameState::Playing)),
+                    clear_pickups,
+                ),
+            );
+    }
+}
+
+pub fn spawn_pickup(
+    commands: &mut Commands,
+    config: &PickupConfig,
+    kind: PickupKind,
+    position: Vec2,
+) -> Entity {
+    commands
+        .spawn((
+            Pickup {
+                kind,
+                remaining: config.lifetime,
+            },
+            SpriteBundle {
+                transform: Transform::from_translation(position.extend(0.5))
+                    .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+                sprite: Sprite {
+                    color: kind.color(),
+                    custom_size: Some(Vec2::splat(PICKUP_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .id()
+}
+
+fn drop_pickups(
+    mut commands: Commands,
+    config: Res<PickupConfig>,
+    defs: Res<Defs>,
+    mut rng: ResMut<GameRng>,
+    mut killed: EventReader<EnemyKilled>,
+) {
+    for event in killed.read() {
+        if rng.f32() < config.weapon_drop_chance {
+            let weapons = defs.drops();
+            let index = (rng.f32() * weapons.len() as f32) as usize;
+            if let Some(weapon) = weapons.get(index.min(weapons.len().saturating_sub(1))) {
+                spawn_weapon_pickup(&mut commands, (*weapon).clone(), event.position);
+                continue;
+            }
+        }
+        if rng.f32() >= config.drop_chance {
+            continue;
+        }
+        let index = (rng.f32() * PickupKind::ALL.len() as f32) as usize;
+        let kind = PickupKind::ALL[index.min(PickupKind::ALL.len() - 1)];
+        spawn_pickup(&mut commands, &config, kind, event.position);
+    }
+}
+
+fn age_pickups(
+    time: Res<Time>,
+    mut despawns: ResMut<DespawnQueue>,
+    mut pickups: Query<(Entity, &mut Pickup, &mut Sprite)>,
+) {
+    for (entity, mut pickup, mut sprite) in &mut pickups {
+        pickup.remaining -= time.delta_seconds();
+        if pickup.remaining <= 0. {
+            despawns.despawn(entity);
+            continue;
+        }
+        sprite.color.set_a((pickup.remaining / FADE_TIME).min(1.));
+    }
+}
+
+fn rebuild_pickup_grid(
+    mut grid: ResMut<PickupGrid>,
+    pickups: Query<(Entity, &GlobalTransform), With<Pickup>>,
+) {
+    grid.0.clear();
+    for (entity, transform) in &pickups {
+        grid.0.insert(entity, transform.translation().truncate());
+    }
+}
+
+fn attract_pickups(
+    time: Res<Time>,
+    config: Res<PickupConfig>,
+    grid: Res<PickupGrid>,
+    players: Query<&Transform, With<Player>>,
+    mut pickups: Query<&mut Transform, (With<Pickup>, Without<Player>)>,
+) {
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+    let target = player.translation.truncate();
+    let step = config.magnet_speed * time.delta_seconds();
+    for (entity, _) in grid.0.nearby_in_radius(target, config.magnet_radius) {
+        let Ok(mut transform) = pickups.get_mut(entity) else {
+            continue;
+        };
+        let offset = target - transform.translation.truncate();
+        let distance = offset.length();
+        if distance > config.magnet_radius || distance == 0. {
+            continue;
+        }
+        transform.translation += (offset / distance * step.min(distance)).extend(0.);
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    config: Res<PickupConfig>,
+    grid: Res<PickupGrid>,
+    mut despawns: ResMut<DespawnQueue>,
+    mut texts: EventWriter<FloatingText>,
+    pickups: Query<(Entity, &Pickup, &Transform)>,
+    mut players: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Health,
+            &mut WeaponInventory,
+            Option<&mut StatModifiers>,
+            Option<&Invulnerable>,
+        ),
+        (With<Player>, Without<Pickup>),
+    >,
+) {
+    let Ok((player, transform, mut health, mut inventory, modifiers, invulnerable)) =
+        players.get_single_mut()
+    else {
+        return;
+    };
+    if health.is_dead() {
+        return;
+    }
+    let position = transform.translation.truncate();
+    let mut granted = modifiers.as_deref().cloned().unwrap_or_default();
+    let mut powered_up = false;
+    // the magnet may have pulled pickups in since the grid was built
+    let reach = config.collect_radius.max(config.magnet_radius);
+    let nearby = grid.0.nearby_in_radius(position, reach);
+    for (entity, pickup, pickup_transform) in
+        nearby.filter_map(|(entity, _)| pickups.get(entity).ok())
+    {
+        if pickup_transform.translation.truncate().distance(position) > config.collect_radius {
+            continue;
+        }
+        despawns.despawn(entity);
+        let label = match pickup.kind {
+            PickupKind::Health => format!("+{:.0}", config.health),
+            PickupKind::Ammo => "Ammo".to_string(),
+            PickupKind::PowerUp(power_up) => power_up.name().to_string(),
+        };
+        texts.send(
+            FloatingText::new(pickup_transform.translation.truncate(), label)
+                .with_color(pickup.kind.color()),
+        );
+        match pickup.kind {
+            PickupKind::Health => {
+                health.current = (health.current + config.health).min(health.max);
+            }
+            PickupKind::Ammo => {
+                for slot in inventory.slots_mut() {
+                    if let (Some(ammo), Some(full)) = (&mut slot.ammo, slot.weapon.ammo) {
+                        let refill = (full as f32 * config.ammo).ceil() as u32;
+                        *ammo = (*ammo + refill).min(full);
+                    }
+                }
+            }
+            PickupKind::PowerUp(power_up) => {
+                let factor = match power_up {
+                    PowerUp::Speed => config.speed_factor,
+                    PowerUp::RapidFire => config.fire_rate_factor,
+                    PowerUp::Shield => 1.,
+                };
+                granted.grant(power_up, factor, config.power_up_duration);
+                powered_up = true;
+                // keep a longer invulnerability, like the one after a death
+                let shield = power_up == PowerUp::Shield
+                    && invulnerable.is_none_or(|invulnerable| {
+                        invulnerable.remaining < config.power_up_duration
+                    });
+                if shield {
+                    commands.entity(player).insert(Invulnerable {
+                        remaining: config.power_up_duration,
+                    });
+                }
+            }
+        }
+    }
+    if !powered_up {
+        return;
+    }
+    match modifiers {
+        Some(mut modifiers) => *modifiers = granted,
+        None => {
+            commands.entity(player).insert(granted);
+        }
+    }
+}
+
+fn wear_off_modifiers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut players: Query<(Entity, &mut StatModifiers)>,
+) {
+    for (entity, mut modifiers) in &mut players {
+        for active in &mut modifiers.active {
+            active.remaining -= time.delta_seconds();
+        }
+        modifiers.active.retain(|active| active.remaining > 0.);
+        if modifiers.active.is_empty() {
+            commands.entity(entity).remove::<StatModifiers>();
+        }
+    }
+}
+
+/// A new run starts without pickups or weapons lying around, or power-ups
+/// running.
+fn clear_pickups(
+    mut commands: Commands,
+    mut new_runs: EventReader<StartNewRun>,
+    mut despawns: ResMut<DespawnQueue>,
+    pickups: Query<Entity, Or<(With<Pickup>, With<WeaponPickup>)>>,
+    players: Query<Entity, With<StatModifiers>>,
+) {
+    if new_runs.read().count() == 0 {
+        return;
+    }
+    for entity in &pickups {
+        despawns.despawn(entity);
+    }
+    for entity in &players {
+        commands.entity(entity).remove::<StatModifiers>();
+    }
+}
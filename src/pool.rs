@@ -0,0 +1,112 @@
+//! Reusing short-lived sprites instead of spawning and despawning them.
+//!
+//! Shots and particles come and go by the hundred in a fight. Rather than
+//! spawn each one and despawn it again, a [`Pool<T>`] keeps the entities of
+//! kind `T` that have gone out of use, hidden, and [`Pool::acquire`] hands
+//! one of those to the next spawn. One goes back through the
+//! [`DespawnQueue`](crate::despawn::DespawnQueue) with [`release::<T>`](release),
+//! which strips the [`Poolable::Parts`] the kind adds on top of its sprite.
+//! A pool keeps at most its capacity, and anything released beyond that is
+//! despawned, so one big burst doesn't leave a thousand idle sprites behind.
+//!
+//! [`PoolPlugin`] sets a kind up and spawns some idle entities at startup,
+//! so the first fight doesn't pay for them. Enemies, portals and health
+//! bars keep pools of their own, as they wait in a particular state
+//! between uses.
+
+use std::marker::PhantomData;
+
+use bevy::{ecs::system::EntityCommands, prelude::*};
+
+/// A kind of entity kept in a [`Pool`].
+pub trait Poolable: Send + Sync + 'static {
+    /// What comes off an entity when it is released. The sprite left over
+    /// is replaced on the next [`Pool::acquire`].
+    type Parts: Bundle;
+}
+
+/// Idle entities of kind `T`, ready to be used again.
+#[derive(Resource, Debug)]
+pub struct Pool<T> {
+    idle: Vec<Entity>,
+    capacity: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Poolable> Pool<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            idle: Vec::with_capacity(capacity),
+            capacity,
+            marker: PhantomData,
+        }
+    }
+
+    /// An idle entity given `bundle`, or a new one if none is left.
+    pub fn acquire<'a>(
+        &mut self,
+        commands: &'a mut Commands,
+        bundle: impl Bundle,
+    ) -> EntityCommands<'a> {
+        match self.idle.pop() {
+            Some(entity) => {
+                let mut reused = commands.entity(entity);
+                reused.insert(bundle);
+                reused
+            }
+            None => commands.spawn(bundle),
+        }
+    }
+}
+
+/// Puts `entity` back in the pool of `T`, or despawns it if that is full.
+/// Handed to [`DespawnQueue::return_to_pool`](crate::despawn::DespawnQueue::return_to_pool).
+pub fn release<T: Poolable>(world: &mut World, entity: Entity) {
+    let pool = world.resource::<Pool<T>>();
+    if pool.idle.len() >= pool.capacity {
+        world.entity_mut(entity).despawn_recursive();
+        return;
+    }
+    world
+        .entity_mut(entity)
+        .remove::<T::Parts>()
+        .insert(Visibility::Hidden);
+    world.resource_mut::<Pool<T>>().idle.push(entity);
+}
+
+/// Adds the [`Pool`] of `T`, with `prewarm` idle sprites spawned at startup
+/// and room for `capacity`.
+pub struct PoolPlugin<T> {
+    prewarm: usize,
+    capacity: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> PoolPlugin<T> {
+    pub fn new(prewarm: usize, capacity: usize) -> Self {
+        Self {
+            prewarm,
+            capacity,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Poolable> Plugin for PoolPlugin<T> {
+    fn build(&self, app: &mut App) {
+        let prewarm = self.prewarm.min(self.capacity);
+        app.insert_resource(Pool::<T>::new(self.capacity))
+            .add_systems(
+                Startup,
+                move |mut commands: Commands, mut pool: ResMut<Pool<T>>| {
+                    for _ in 0..prewarm {
+                        let idle = commands.spawn(SpriteBundle {
+                            visibility: Visibility::Hidden,
+                            ..default()
+                        });
+                        pool.idle.push(idle.id());
+                    }
+                },
+            );
+    }
+}
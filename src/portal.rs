@@ -0,0 +1,180 @@
+//! Portals enemies emerge from.
+//!
+//! A [`PortalSpawn`] opens a portal at its position and spawns its whole
+//! pack there at once, each enemy [`Forming`](crate::enemy::Forming) for
+//! the portal's duration. Pack members are spread out along
+//! [`pack_offsets`] so they don't stack on one point. Spawners fill the
+//! pack size and duration from their wave's entry, falling back to
+//! [`PortalConfig`] through [`PortalConfig::spawn`]. Portal sprites are
+//! pooled, as a big wave opens a lot of them.
+
+use std::f32::consts::{FRAC_PI_4, PI};
+
+use bevy::prelude::*;
+
+use crate::{
+    defs::Defs,
+    despawn::DespawnQueue,
+    enemy::{EnemyKind, SpawnEnemy, ENEMY_SIZE},
+    tween::{Ease, ScaleLens, SpriteColorLens, Tween, TweenCompleted},
+};
+
+const PORTAL_COLOR: Color = Color::rgb(0.7, 0.3, 1.);
+
+/// Spawn `count` enemies of `kind` out of one portal at `position`, active
+/// after `duration` seconds.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PortalSpawn {
+    pub position: Vec2,
+    pub count: u32,
+    pub duration: f32,
+    pub kind: EnemyKind,
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct PortalConfig {
+    /// Seconds from the portal opening to its enemies becoming active.
+    pub duration: f32,
+    /// Enemies per portal.
+    pub group_size: u32,
+    /// Distance between neighbouring pack members.
+    pub spacing: f32,
+}
+
+impl Default for PortalConfig {
+    fn default() -> Self {
+        Self {
+            duration: 0.6,
+            group_size: 1,
+            spacing: 26.,
+        }
+    }
+}
+
+impl PortalConfig {
+    /// A portal of grunts at `position` with the default pack size and
+    /// duration.
+    pub fn spawn(&self, position: Vec2) -> PortalSpawn {
+        PortalSpawn {
+            position,
+            count: self.group_size,
+            duration: self.duration,
+            kind: EnemyKind::Grunt,
+        }
+    }
+}
+
+/// Offsets from the portal's center for a pack of `count`, on a sunflower
+/// spiral so each is at least `spacing` from the others and the pack stays
+/// round however big it gets.
+pub fn pack_offsets(count: u32, spacing: f32) -> impl Iterator<Item = Vec2> {
+    let golden_angle = PI * (3. - 5f32.sqrt());
+    (0..count).map(move |i| {
+        let i = i as f32;
+        Vec2::from_angle(i * golden_angle) * spacing * i.sqrt()
+    })
+}
+
+#[derive(Component)]
+struct Portal;
+
+#[derive(Resource, Default)]
+struct PortalPool(Vec<Entity>);
+
+pub struct PortalPlugin;
+
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<PortalConfig>()
+            .init_resource::<PortalConfig>()
+            .init_resource::<PortalPool>()
+            .add_event::<PortalSpawn>()
+            .add_systems(Update, (open_portals, close_portals));
+    }
+}
+
+fn open_portals(
+    mut commands: Commands,
+    config: Res<PortalConfig>,
+    defs: Res<Defs>,
+    mut pool: ResMut<PortalPool>,
+    mut requests: EventReader<PortalSpawn>,
+    mut spawns: EventWriter<SpawnEnemy>,
+) {
+    for request in requests.read() {
+        let count = request.count.max(1);
+        let duration = request.duration.max(0.);
+        let size = defs.enemy(request.kind).size;
+        let spacing = config.spacing * size / ENEMY_SIZE;
+        for offset in pack_offsets(count, spacing) {
+            spawns.send(SpawnEnemy {
+                position: request.position + offset,
+                forming: duration,
+                kind: request.kind,
+            });
+        }
+        if duration == 0. {
+            continue;
+        }
+
+        let radius = spacing * ((count - 1) as f32).sqrt();
+        let size = 2. * radius + size * 1.5;
+        let bundle = (
+            Portal,
+            Tween::new(
+                ScaleLens {
+                    start: Vec3::splat(0.2),
+                    end: Vec3::ONE,
+                },
+                Ease::BackOut,
+                duration,
+            )
+            .with_completion(),
+            Tween::new(
+                SpriteColorLens {
+                    start: PORTAL_COLOR.with_a(0.8),
+                    end: PORTAL_COLOR.with_a(0.),
+                },
+                Ease::QuadIn,
+                duration,
+            ),
+            SpriteBundle {
+                transform: Transform::from_translation(request.position.extend(-0.2))
+                    .with_rotation(Quat::from_rotation_z(FRAC_PI_4))
+                    .with_scale(Vec3::splat(0.2)),
+                sprite: Sprite {
+                    color: PORTAL_COLOR.with_a(0.8),
+                    custom_size: Some(Vec2::splat(size)),
+                    ..default()
+                },
+                ..default()
+            },
+        );
+        match pool.0.pop() {
+            Some(entity) => {
+                commands.entity(entity).insert(bundle);
+            }
+            None => {
+                commands.spawn(bundle);
+            }
+        }
+    }
+}
+
+fn close_portals(
+    mut completed: EventReader<TweenCompleted>,
+    mut despawns: ResMut<DespawnQueue>,
+    portals: Query<(), With<Portal>>,
+) {
+    for event in completed.read() {
+        if portals.contains(event.entity) {
+            despawns.return_to_pool(event.entity, return_to_pool);
+        }
+    }
+}
+
+fn return_to_pool(world: &mut World, entity: Entity) {
+    world.entity_mut(entity).insert(Visibility::Hidden);
+    world.resource_mut::<PortalPool>().0.push(entity);
+}
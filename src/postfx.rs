@@ -0,0 +1,276 @@
+//! Bloom, a vignette and a CRT look over the game world.
+//!
+//! Each is a [`DisplaySettings`] toggle. Bloom makes the main camera render
+//! in HDR with Bevy's [`BloomSettings`] and [`Tonemapping::AcesFitted`],
+//! which needs no lookup tables. The vignette and the CRT scanlines are one
+//! full-screen pass of `shaders/postfx.wgsl`, run after tonemapping and
+//! before the UI is drawn, so the HUD stays sharp; the camera only carries
+//! [`PostFx`] while one of them is on, and the pass is skipped otherwise.
+//!
+//! HDR and bloom cost fill rate that phones and WebGL2 can't always spare,
+//! so they turn off at [`QualityLevel::Low`], and in the browser at anything
+//! below [`QualityLevel::High`], whatever the setting says. The pass's
+//! uniform is a plain 16 bytes, which WebGL2 takes without padding, and
+//! until its shader has compiled the frame goes out untouched.
+
+use bevy::{
+    core_pipeline::{
+        bloom::BloomSettings,
+        core_2d::graph::{Core2d, Node2d},
+        fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+        tonemapping::Tonemapping,
+    },
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        extract_component::{ComponentUniforms, ExtractComponentPlugin, UniformComponentPlugin},
+        render_graph::{
+            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+        },
+        render_resource::{
+            binding_types::{sampler, texture_2d, uniform_buffer},
+            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, CachedRenderPipelineId,
+            ColorTargetState, ColorWrites, FragmentState, MultisampleState, Operations,
+            PipelineCache, PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor,
+            RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor, ShaderStages,
+            TextureFormat, TextureSampleType,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::BevyDefault,
+        view::ViewTarget,
+        RenderApp,
+    },
+};
+
+use crate::{
+    camera::MainCamera,
+    quality::{EffectsQuality, QualityLevel},
+    settings::DisplaySettings,
+};
+
+const SHADER_PATH: &str = "shaders/postfx.wgsl";
+/// How dark the corners get with the vignette on, `0..=1`.
+const VIGNETTE_STRENGTH: f32 = 0.45;
+/// How much the CRT effect shows, `0..=1`.
+const CRT_STRENGTH: f32 = 1.;
+
+pub use uniform::PostFx;
+
+// ShaderType checks each field's type with a function next to the struct
+// that is never called, which trips the dead_code lint
+#[allow(dead_code)]
+mod uniform {
+    use bevy::{
+        prelude::*,
+        render::{extract_component::ExtractComponent, render_resource::ShaderType},
+    };
+
+    /// Settings of the full-screen pass, on a camera that runs it.
+    #[derive(Component, Debug, Clone, Copy, Default, ExtractComponent, ShaderType)]
+    pub struct PostFx {
+        pub vignette: f32,
+        /// Scanlines and a slight color fringe.
+        pub crt: f32,
+        /// Keeps the uniform at 16 bytes.
+        pub(super) _padding: Vec2,
+    }
+}
+
+pub struct PostFxPlugin;
+
+impl Plugin for PostFxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ExtractComponentPlugin::<PostFx>::default(),
+            UniformComponentPlugin::<PostFx>::default(),
+        ))
+        .add_systems(
+            Update,
+            apply_postfx.run_if(
+                resource_changed::<DisplaySettings>.or_else(resource_changed::<EffectsQuality>),
+            ),
+        );
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<PostFxNode>>(Core2d, PostFxLabel)
+            .add_render_graph_edges(
+                Core2d,
+                (
+                    Node2d::Tonemapping,
+                    PostFxLabel,
+                    Node2d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<PostFxPipeline>();
+    }
+}
+
+/// Whether bloom may run at `level` on this platform.
+fn bloom_allowed(level: QualityLevel) -> bool {
+    if cfg!(target_arch = "wasm32") {
+        level >= QualityLevel::High
+    } else {
+        level > QualityLevel::Low
+    }
+}
+
+fn apply_postfx(
+    mut commands: Commands,
+    settings: Res<DisplaySettings>,
+    quality: Res<EffectsQuality>,
+    mut cameras: Query<(Entity, &mut Camera, &mut Tonemapping), With<MainCamera>>,
+) {
+    let bloom = settings.bloom && bloom_allowed(quality.level);
+    for (entity, mut camera, mut tonemapping) in &mut cameras {
+        if camera.hdr != bloom {
+            camera.hdr = bloom;
+        }
+        let mut entity = commands.entity(entity);
+        if bloom {
+            *tonemapping = Tonemapping::AcesFitted;
+            entity.insert(BloomSettings::NATURAL);
+        } else {
+            *tonemapping = Tonemapping::default();
+            entity.remove::<BloomSettings>();
+        }
+        if settings.vignette || settings.crt {
+            entity.insert(PostFx {
+                vignette: if settings.vignette {
+                    VIGNETTE_STRENGTH
+                } else {
+                    0.
+                },
+                crt: if settings.crt { CRT_STRENGTH } else { 0. },
+                ..default()
+            });
+        } else {
+            entity.remove::<PostFx>();
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct PostFxLabel;
+
+#[derive(Default)]
+struct PostFxNode;
+
+impl ViewNode for PostFxNode {
+    type ViewQuery = (&'static ViewTarget, &'static PostFx);
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, _): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let postfx = world.resource::<PostFxPipeline>();
+        let pipeline_id = if view_target.is_hdr() {
+            postfx.hdr
+        } else {
+            postfx.sdr
+        };
+        // still compiling, or failed to
+        let Some(pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(pipeline_id)
+        else {
+            return Ok(());
+        };
+        let uniforms = world.resource::<ComponentUniforms<PostFx>>();
+        let Some(uniform) = uniforms.uniforms().binding() else {
+            return Ok(());
+        };
+
+        let target = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "postfx_bind_group",
+            &postfx.layout,
+            &BindGroupEntries::sequential((target.source, &postfx.sampler, uniform)),
+        );
+        let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("postfx_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_render_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+/// The pass's pipelines, for a camera rendering in HDR and one not.
+#[derive(Resource)]
+struct PostFxPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    sdr: CachedRenderPipelineId,
+    hdr: CachedRenderPipelineId,
+}
+
+impl FromWorld for PostFxPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "postfx_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    uniform_buffer::<PostFx>(false),
+                ),
+            ),
+        );
+        let sampler = render_device.create_sampler(&SamplerDescriptor::default());
+        let shader = world.resource::<AssetServer>().load(SHADER_PATH);
+
+        let descriptor = |format| RenderPipelineDescriptor {
+            label: Some("postfx_pipeline".into()),
+            layout: vec![layout.clone()],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: shader.clone(),
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            push_constant_ranges: vec![],
+        };
+        let sdr = descriptor(TextureFormat::bevy_default());
+        let hdr = descriptor(ViewTarget::TEXTURE_FORMAT_HDR);
+        let cache = world.resource::<PipelineCache>();
+        let sdr = cache.queue_render_pipeline(sdr);
+        let hdr = cache.queue_render_pipeline(hdr);
+        Self {
+            layout,
+            sampler,
+            sdr,
+            hdr,
+        }
+    }
+}
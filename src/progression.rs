@@ -0,0 +1,396 @@
+//! Permanent upgrades bought between runs.
+//!
+//! Every run that ends in a game over earns scrap, one piece per
+//! [`ProgressionConfig::points_per_scrap`] points of its score. Scrap buys
+//! levels of the upgrades in [`UPGRADES`], each level costing more than the
+//! last; an upgrade with a `requires` is locked until the other one has at
+//! least one level, which makes the list a small tree. Scrap and levels
+//! are kept in [`Progression`] and persisted through [`storage`].
+//!
+//! The upgrade screen opens over the main menu and the game over screen
+//! with their Upgrades button. What has been bought is put on the player
+//! as [`UpgradeModifiers`] when it spawns, a new run starts or an upgrade
+//! is bought: max health is raised right away, and movement and firing
+//! read the rest.
+
+use std::collections::HashMap;
+
+use bevy::{prelude::*, ui::FocusPolicy};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    death::StartNewRun,
+    fonts::UiFonts,
+    health::Health,
+    replay,
+    score::Score,
+    state::{GameState, StateScoped},
+    storage,
+    toast::Toast,
+    Player, PLAYER_HEALTH,
+};
+
+const PROGRESSION_KEY: &str = "progression";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Upgrade {
+    MaxHealth,
+    MoveSpeed,
+    FireRate,
+}
+
+pub struct UpgradeDef {
+    pub upgrade: Upgrade,
+    pub name: &'static str,
+    pub max_level: u32,
+    /// Scrap for the first level; each after it costs this much more.
+    pub cost: u64,
+    /// Added to the stat's multiplier by each level.
+    pub step: f32,
+    /// Upgrade needing a level before this one can be bought.
+    pub requires: Option<Upgrade>,
+}
+
+pub const UPGRADES: &[UpgradeDef] = &[
+    UpgradeDef {
+        upgrade: Upgrade::MaxHealth,
+        name: "Max health",
+        max_level: 5,
+        cost: 20,
+        step: 0.1,
+        requires: None,
+    },
+    UpgradeDef {
+        upgrade: Upgrade::MoveSpeed,
+        name: "Move speed",
+        max_level: 5,
+        cost: 30,
+        step: 0.05,
+        requires: Some(Upgrade::MaxHealth),
+    },
+    UpgradeDef {
+        upgrade: Upgrade::FireRate,
+        name: "Fire rate",
+        max_level: 5,
+        cost: 30,
+        step: 0.08,
+        requires: Some(Upgrade::MaxHealth),
+    },
+];
+
+fn def(upgrade: Upgrade) -> &'static UpgradeDef {
+    UPGRADES
+        .iter()
+        .find(|def| def.upgrade == upgrade)
+        .expect("every upgrade is listed")
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct ProgressionConfig {
+    /// Score points per piece of scrap earned.
+    pub points_per_scrap: u64,
+}
+
+impl Default for ProgressionConfig {
+    fn default() -> Self {
+        Self {
+            points_per_scrap: 100,
+        }
+    }
+}
+
+/// Scrap to spend and the upgrade levels bought.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Progression {
+    pub scrap: u64,
+    pub levels: HashMap<Upgrade, u32>,
+}
+
+/// Why an upgrade can't be bought.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unavailable {
+    Maxed,
+    Locked(Upgrade),
+    TooExpensive(u64),
+}
+
+impl Progression {
+    pub fn level(&self, upgrade: Upgrade) -> u32 {
+        self.levels.get(&upgrade).copied().unwrap_or(0)
+    }
+
+    /// Scrap the next level of `upgrade` costs.
+    pub fn cost(&self, upgrade: Upgrade) -> u64 {
+        def(upgrade).cost * (self.level(upgrade) as u64 + 1)
+    }
+
+    pub fn check(&self, upgrade: Upgrade) -> Result<u64, Unavailable> {
+        let def = def(upgrade);
+        if self.level(upgrade) >= def.max_level {
+            return Err(Unavailable::Maxed);
+        }
+        if let Some(required) = def.requires.filter(|required| self.level(*required) == 0) {
+            return Err(Unavailable::Locked(required));
+        }
+        let cost = self.cost(upgrade);
+        if cost > self.scrap {
+            return Err(Unavailable::TooExpensive(cost));
+        }
+        Ok(cost)
+    }
+
+    /// Buys the next level of `upgrade`, if it's available.
+    pub fn buy(&mut self, upgrade: Upgrade) -> Result<(), Unavailable> {
+        let cost = self.check(upgrade)?;
+        self.scrap -= cost;
+        *self.levels.entry(upgrade).or_default() += 1;
+        Ok(())
+    }
+
+    /// What the player's stat for `upgrade` is multiplied by.
+    pub fn factor(&self, upgrade: Upgrade) -> f32 {
+        1. + def(upgrade).step * self.level(upgrade) as f32
+    }
+
+    pub fn modifiers(&self) -> UpgradeModifiers {
+        UpgradeModifiers {
+            max_health: self.factor(Upgrade::MaxHealth),
+            move_speed: self.factor(Upgrade::MoveSpeed),
+            fire_rate: self.factor(Upgrade::FireRate),
+        }
+    }
+}
+
+/// Multipliers on the player's stats from the upgrades bought.
+#[derive(Component, Reflect, Debug, Clone, Copy)]
+pub struct UpgradeModifiers {
+    pub max_health: f32,
+    pub move_speed: f32,
+    pub fire_rate: f32,
+}
+
+/// What the player's speed is multiplied by, 1 without upgrades.
+pub fn move_speed_factor(upgrades: Option<&UpgradeModifiers>) -> f32 {
+    upgrades.map_or(1., |upgrades| upgrades.move_speed)
+}
+
+/// What the player's fire rate is multiplied by, 1 without upgrades.
+pub fn fire_rate_factor(upgrades: Option<&UpgradeModifiers>) -> f32 {
+    upgrades.map_or(1., |upgrades| upgrades.fire_rate)
+}
+
+/// Opens the upgrade screen over the current one.
+#[derive(Component)]
+pub struct UpgradesButton;
+
+#[derive(Component)]
+struct UpgradesScreen;
+
+#[derive(Component)]
+struct ScrapText;
+
+/// A row's button, buying the next level of `0`.
+#[derive(Component, Debug, Clone, Copy)]
+struct BuyButton(Upgrade);
+
+#[derive(Component)]
+struct CloseUpgradesButton;
+
+pub struct ProgressionPlugin;
+
+impl Plugin for ProgressionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ProgressionConfig>()
+            .register_type::<UpgradeModifiers>()
+            .init_resource::<ProgressionConfig>()
+            .insert_resource(storage::load::<Progression>(PROGRESSION_KEY).unwrap_or_default())
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                earn_scrap.run_if(not(replay::is_replaying)),
+            )
+            .add_systems(
+                Update,
+                (
+                    apply_upgrades,
+                    (open_upgrades, press_upgrade_buttons, update_upgrade_screen)
+                        .chain()
+                        .run_if(
+                            in_state(GameState::MainMenu).or_else(in_state(GameState::GameOver)),
+                        ),
+                ),
+            );
+    }
+}
+
+fn earn_scrap(
+    config: Res<ProgressionConfig>,
+    score: Res<Score>,
+    mut progression: ResMut<Progression>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let earned = score.points / config.points_per_scrap.max(1);
+    if earned == 0 {
+        return;
+    }
+    progression.scrap += earned;
+    storage::save(PROGRESSION_KEY, &*progression);
+    toasts.send(Toast::new(format!("+{earned} scrap")));
+}
+
+fn apply_upgrades(
+    mut commands: Commands,
+    progression: Res<Progression>,
+    mut new_runs: EventReader<StartNewRun>,
+    mut players: Query<(Entity, &mut Health), With<Player>>,
+) {
+    // a new player is spawned with them, see `crate::spawn_player`
+    let new_run = new_runs.read().count() > 0;
+    if !new_run && !progression.is_changed() {
+        return;
+    }
+    let modifiers = progression.modifiers();
+    for (entity, mut health) in &mut players {
+        let max = PLAYER_HEALTH * modifiers.max_health;
+        if new_run {
+            health.current = max;
+        } else if health.max > 0. {
+            // full stays full, and a dead player stays dead
+            health.current *= max / health.max;
+        }
+        health.max = max;
+        commands.entity(entity).insert(modifiers);
+    }
+}
+
+fn open_upgrades(
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    state: Res<State<GameState>>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<UpgradesButton>)>,
+    screens: Query<(), With<UpgradesScreen>>,
+) {
+    if !buttons.iter().any(|i| *i == Interaction::Pressed) || !screens.is_empty() {
+        return;
+    }
+    let button = |width: f32| ButtonBundle {
+        style: Style {
+            min_width: Val::Px(width),
+            padding: UiRect::axes(Val::Px(8.), Val::Px(6.)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: Color::rgba(1., 1., 1., 0.15).into(),
+        ..default()
+    };
+    let text = |value: &str| TextBundle::from_section(value, fonts.style(20., Color::WHITE));
+
+    commands
+        .spawn((
+            UpgradesScreen,
+            StateScoped(*state.get()),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.85).into(),
+                // keep clicks off the screen underneath
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(21),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Upgrades",
+                fonts.bold(32., Color::WHITE),
+            ));
+            parent.spawn((ScrapText, text("")));
+            for def in UPGRADES {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(420.),
+                            column_gap: Val::Px(8.),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(text(def.name).with_style(Style {
+                            flex_grow: 1.,
+                            ..default()
+                        }));
+                        parent
+                            .spawn((BuyButton(def.upgrade), button(200.)))
+                            .with_children(|parent| {
+                                parent.spawn(text(""));
+                            });
+                    });
+            }
+            let mut done = button(96.);
+            done.style.margin = UiRect::top(Val::Px(12.));
+            parent
+                .spawn((CloseUpgradesButton, done))
+                .with_children(|parent| {
+                    parent.spawn(text("Done"));
+                });
+        });
+}
+
+fn press_upgrade_buttons(
+    mut commands: Commands,
+    buy: Query<(&Interaction, &BuyButton), Changed<Interaction>>,
+    close: Query<&Interaction, (Changed<Interaction>, With<CloseUpgradesButton>)>,
+    screens: Query<Entity, With<UpgradesScreen>>,
+    mut progression: ResMut<Progression>,
+) {
+    for (interaction, button) in &buy {
+        if *interaction == Interaction::Pressed && progression.buy(button.0).is_ok() {
+            storage::save(PROGRESSION_KEY, &*progression);
+        }
+    }
+    if close.iter().any(|i| *i == Interaction::Pressed) {
+        for entity in &screens {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn update_upgrade_screen(
+    progression: Res<Progression>,
+    added: Query<(), Added<UpgradesScreen>>,
+    buttons: Query<(&BuyButton, &Children)>,
+    mut scrap: Query<&mut Text, With<ScrapText>>,
+    mut texts: Query<&mut Text, Without<ScrapText>>,
+) {
+    if added.is_empty() && !progression.is_changed() {
+        return;
+    }
+    for mut text in &mut scrap {
+        text.sections[0].value = format!("Scrap: {}", progression.scrap);
+    }
+    for (button, children) in &buttons {
+        let upgrade = button.0;
+        let level = format!("{}/{}", progression.level(upgrade), def(upgrade).max_level);
+        let value = match progression.check(upgrade) {
+            Ok(cost) | Err(Unavailable::TooExpensive(cost)) => format!("{level}  Buy ({cost})"),
+            Err(Unavailable::Maxed) => format!("{level}  Max"),
+            Err(Unavailable::Locked(required)) => format!("Needs {}", def(required).name),
+        };
+        for child in children {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value.clone_from(&value);
+            }
+        }
+    }
+}
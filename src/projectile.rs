@@ -0,0 +1,726 @@
+//! Player projectiles: flight, expiry, hits, and the pierce and bounce
+//! modifiers.
+//!
+//! A projectile with [`Pierce`] passes through that many enemies, damaging
+//! each once, before it is used up. One with [`Bounce`] ricochets off walls
+//! and the screen edges that many times before expiring. The modifiers
+//! come from the weapon's [`ProjectileModifiers`], plus any temporary
+//! [`ModifierBoost`] on the shooter.
+//!
+//! A weapon with a [`ClusterConfig`] fires [`Cluster`] shots, which split
+//! into a ring of smaller projectiles when their fuse or lifetime runs out,
+//! when they hit something, or at the screen edge when they would leave it,
+//! with a [`Blast`] that pushes nearby enemies away.
+//!
+//! A weapon with an [`ArcConfig`] lobs [`Ballistic`] shots at a point on
+//! the ground. They curve under gravity toward the bottom of the screen
+//! and land when their lifetime runs out, hitting what they meet on the
+//! way like any other shot. A lobbed cluster instead flies over everything
+//! and splits where it lands, whatever its fuse says.
+//!
+//! A weapon with a [`BeamConfig`] fires a [`Beam`] instead: it hits
+//! whatever is along its line, up to the weapon's range, the moment it is
+//! fired, and stops at a wall or at the enemy its pierce runs out on. It
+//! then stays on screen, fading, for the beam's duration.
+//!
+//! A shot knocks the enemy it hits along its flight, by
+//! [`MotionConfig::hit_knockback`].
+//!
+//! Shots and beams from a weapon with [status effects](crate::status) carry
+//! them in [`Inflicts`], for every enemy they damage.
+//!
+//! Shots and beams are taken from [`ShotPools`] and go back to them once
+//! spent, rather than being spawned and despawned every time.
+
+use bevy::{ecs::system::SystemParam, prelude::*, sprite::Anchor, utils::HashSet};
+
+use crate::{
+    budget::{Budget, BudgetCategory, Budgeted},
+    camera::{visible_rect, CameraShake, MainCamera, ShakeConfig},
+    game_time::{HitStop, HitStopConfig},
+    health::DamageEvent,
+    interpolation::Interpolated,
+    motion::{Blast, Impulse, MotionConfig},
+    particles::{EmitParticles, ParticleEffect},
+    physics::{Collider, CollisionEvent, CollisionLayer, CollisionSet},
+    pool::{self, Pool, PoolPlugin, Poolable},
+    state::GameState,
+    status::{ApplyStatus, Inflicts},
+    tween::Ease,
+    weapon::{ArcConfig, BeamConfig, ClusterConfig, ProjectileModifiers, Weapon},
+};
+
+const PROJECTILE_SIZE: f32 = 6.;
+const CLUSTER_CHILD_SIZE: f32 = 4.;
+const PROJECTILE_COLOR: Color = Color::rgb(1., 0.9, 0.4);
+const SPLIT_FLASH: ParticleEffect = ParticleEffect {
+    lifetime: 0.2,
+    size: 40.,
+    velocity: Vec2::ZERO,
+    spread: 0.,
+    gravity: Vec2::ZERO,
+    start_color: Color::rgba(1., 0.9, 0.4, 0.8),
+    end_color: Color::rgba(1., 0.9, 0.4, 0.),
+    start_scale: 0.2,
+    end_scale: 1.,
+    ease: Ease::QuadOut,
+    z: 1.,
+};
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Projectile {
+    pub velocity: Vec2,
+    /// Seconds left before the projectile expires.
+    pub remaining: f32,
+    pub damage: f32,
+}
+
+/// Enemies this projectile can still pass through.
+#[derive(Component, Debug, Clone, Default)]
+pub struct Pierce {
+    pub count: u32,
+    /// Enemies already hit, so none is hit twice.
+    hit: HashSet<Entity>,
+}
+
+impl Pierce {
+    pub fn new(count: u32) -> Self {
+        Self {
+            count,
+            hit: HashSet::default(),
+        }
+    }
+}
+
+/// Ricochets left before the projectile expires at a wall or screen edge.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Bounce {
+    pub count: u32,
+}
+
+/// Splits into [`ClusterConfig::children`] projectiles once `fuse` or the
+/// projectile's lifetime runs out, on first impact, or on leaving the
+/// screen.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Cluster {
+    pub config: ClusterConfig,
+    /// Seconds left before the split.
+    pub fuse: f32,
+}
+
+/// A lobbed shot, accelerated by `gravity`, a world-space vector pointing
+/// down the screen. A lobbed cluster has no collider while in the air.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Ballistic {
+    pub gravity: Vec2,
+}
+
+/// A beam from its transform along its rotation, hitting everything in the
+/// way on the first frame it's there.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Beam {
+    pub range: f32,
+    pub width: f32,
+    pub damage: f32,
+    /// Enemies it passes through before stopping at one.
+    pub pierce: u32,
+    pub duration: f32,
+    pub remaining: f32,
+    fired: bool,
+}
+
+impl Poolable for Projectile {
+    type Parts = (
+        Projectile,
+        Collider,
+        Pierce,
+        Bounce,
+        Cluster,
+        Ballistic,
+        Inflicts,
+        Budgeted,
+        Interpolated,
+    );
+}
+
+impl Poolable for Beam {
+    type Parts = (Beam, Inflicts, Budgeted);
+}
+
+/// The pools shots and beams are taken from.
+#[derive(SystemParam)]
+pub struct ShotPools<'w> {
+    shots: ResMut<'w, Pool<Projectile>>,
+    beams: ResMut<'w, Pool<Beam>>,
+}
+
+/// Extra modifiers on top of the weapon's own, for `remaining` seconds.
+/// Pickups grant these.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ModifierBoost {
+    pub modifiers: ProjectileModifiers,
+    pub remaining: f32,
+}
+
+/// The modifiers `weapon` fires with, including an active `boost`.
+pub fn shot_modifiers(weapon: &Weapon, boost: Option<&ModifierBoost>) -> ProjectileModifiers {
+    boost.map_or(weapon.modifiers, |boost| {
+        weapon.modifiers.with(boost.modifiers)
+    })
+}
+
+/// Spawns one projectile fired from `origin` along the normalized
+/// `direction`, with `modifiers` attached. Returns `None` if the projectile
+/// budget skipped it.
+pub fn spawn_projectile(
+    commands: &mut Commands,
+    budget: &mut Budget,
+    pools: &mut ShotPools,
+    weapon: &Weapon,
+    modifiers: ProjectileModifiers,
+    origin: Vec2,
+    direction: Vec2,
+) -> Option<Entity> {
+    let mut projectile = spawn_shot(
+        commands,
+        budget,
+        pools,
+        Projectile {
+            velocity: direction * weapon.projectile_speed,
+            remaining: weapon.projectile_lifetime,
+            damage: weapon.damage,
+        },
+        origin,
+        PROJECTILE_SIZE,
+    )?;
+    if let Some(config) = weapon.cluster {
+        projectile.insert(Cluster {
+            config,
+            fuse: config.fuse,
+        });
+    }
+    if modifiers.pierce > 0 {
+        projectile.insert(Pierce::new(modifiers.pierce));
+    }
+    if modifiers.bounce > 0 {
+        projectile.insert(Bounce {
+            count: modifiers.bounce,
+        });
+    }
+    if !weapon.effects.is_empty() {
+        projectile.insert(Inflicts(weapon.effects.clone()));
+    }
+    Some(projectile.id())
+}
+
+/// Spawns one shot lobbed by `weapon`, with its `arc`, from `origin` to land
+/// on `target`. `down` is the normalized world direction of the bottom of
+/// the screen. Returns `None` if the projectile budget skipped it.
+pub fn spawn_lobbed(
+    commands: &mut Commands,
+    budget: &mut Budget,
+    pools: &mut ShotPools,
+    weapon: &Weapon,
+    arc: ArcConfig,
+    origin: Vec2,
+    target: Vec2,
+    down: Vec2,
+) -> Option<Entity> {
+    let (velocity, flight) = arc.launch(origin, target, weapon.projectile_speed, down);
+    let mut projectile = spawn_shot(
+        commands,
+        budget,
+        pools,
+        Projectile {
+            velocity,
+            remaining: flight,
+            damage: weapon.damage,
+        },
+        origin,
+        PROJECTILE_SIZE,
+    )?;
+    projectile.insert(Ballistic {
+        gravity: down * arc.gravity,
+    });
+    if let Some(config) = weapon.cluster {
+        // flies over everything, to split where it lands
+        projectile.remove::<Collider>().insert(Cluster {
+            config,
+            fuse: config.fuse,
+        });
+    }
+    if !weapon.effects.is_empty() {
+        projectile.insert(Inflicts(weapon.effects.clone()));
+    }
+    Some(projectile.id())
+}
+
+/// Spawns a beam fired by `weapon` from `origin` along the normalized
+/// `direction`. Returns `None` if the projectile budget skipped it.
+pub fn spawn_beam(
+    commands: &mut Commands,
+    budget: &mut Budget,
+    pools: &mut ShotPools,
+    weapon: &Weapon,
+    beam: BeamConfig,
+    modifiers: ProjectileModifiers,
+    origin: Vec2,
+    direction: Vec2,
+) -> Option<Entity> {
+    if !budget.admit(commands, BudgetCategory::Projectile) {
+        return None;
+    }
+    let range = weapon.range();
+    let mut shot = pools.beams.acquire(
+        commands,
+        (
+            Beam {
+                range,
+                width: beam.width,
+                damage: weapon.damage,
+                pierce: modifiers.pierce,
+                duration: beam.duration,
+                remaining: beam.duration,
+                fired: false,
+            },
+            SpriteBundle {
+                transform: Transform::from_translation(origin.extend(1.))
+                    .with_rotation(Quat::from_rotation_z(Vec2::X.angle_between(direction))),
+                sprite: Sprite {
+                    color: PROJECTILE_COLOR,
+                    custom_size: Some(Vec2::new(range, beam.width)),
+                    anchor: Anchor::CenterLeft,
+                    ..default()
+                },
+                ..default()
+            },
+        ),
+    );
+    if !weapon.effects.is_empty() {
+        shot.insert(Inflicts(weapon.effects.clone()));
+    }
+    budget.track(&mut shot, BudgetCategory::Projectile);
+    Some(shot.id())
+}
+
+fn spawn_shot<'a>(
+    commands: &'a mut Commands,
+    budget: &mut Budget,
+    pools: &mut ShotPools,
+    projectile: Projectile,
+    origin: Vec2,
+    size: f32,
+) -> Option<bevy::ecs::system::EntityCommands<'a>> {
+    if !budget.admit(commands, BudgetCategory::Projectile) {
+        return None;
+    }
+    let mut shot = pools.shots.acquire(
+        commands,
+        (
+            projectile,
+            Collider::new(size / 2., CollisionLayer::PLAYER_BULLET),
+            Interpolated::default(),
+            SpriteBundle {
+                transform: Transform::from_translation(origin.extend(1.)),
+                sprite: Sprite {
+                    color: PROJECTILE_COLOR,
+                    custom_size: Some(Vec2::splat(size)),
+                    ..default()
+                },
+                ..default()
+            },
+        ),
+    );
+    budget.track(&mut shot, BudgetCategory::Projectile);
+    Some(shot)
+}
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        // room for the whole projectile budget
+        app.add_plugins((
+            PoolPlugin::<Projectile>::new(64, 400),
+            PoolPlugin::<Beam>::new(4, 16),
+        ))
+        .add_systems(
+            FixedUpdate,
+            (
+                move_projectiles.before(CollisionSet),
+                (split_clusters, resolve_hits).after(CollisionSet),
+            )
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (fire_beams, wear_off_boosts).run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// `velocity` reflected off a surface with the given normal.
+fn reflect(velocity: Vec2, normal: Vec2) -> Vec2 {
+    velocity - 2. * velocity.dot(normal) * normal
+}
+
+fn move_projectiles(
+    mut budget: Budget,
+    time: Res<Time>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut projectiles: Query<(
+        Entity,
+        &mut Projectile,
+        &mut Transform,
+        Option<&mut Bounce>,
+        Option<&Ballistic>,
+        Has<Cluster>,
+    )>,
+) {
+    let dt = time.delta_seconds();
+    let view = cameras
+        .get_single()
+        .ok()
+        .and_then(|(camera, transform)| visible_rect(camera, transform));
+
+    for (entity, mut projectile, mut transform, bounce, ballistic, cluster) in &mut projectiles {
+        projectile.remaining -= dt;
+        // clusters split instead of just expiring
+        if projectile.remaining <= 0. && !cluster {
+            budget.return_to_pool(entity, pool::release::<Projectile>);
+            continue;
+        }
+        if let Some(ballistic) = ballistic {
+            projectile.velocity += ballistic.gravity * dt;
+        }
+        let mut position = transform.translation.truncate() + projectile.velocity * dt;
+
+        // a lob may arc off the top of the screen and come back down
+        if let Some(view) = view.filter(|_| ballistic.is_none()) {
+            if !view.contains(position) {
+                match bounce {
+                    Some(mut bounce) if bounce.count > 0 => {
+                        bounce.count -= 1;
+                        if position.x < view.min.x || position.x > view.max.x {
+                            projectile.velocity.x = -projectile.velocity.x;
+                        }
+                        if position.y < view.min.y || position.y > view.max.y {
+                            projectile.velocity.y = -projectile.velocity.y;
+                        }
+                        position = position.clamp(view.min, view.max);
+                    }
+                    // split at the edge, rather than vanishing with the
+                    // children never seen
+                    _ if cluster => {
+                        projectile.remaining = 0.;
+                        position = position.clamp(view.min, view.max);
+                    }
+                    _ => {
+                        budget.return_to_pool(entity, pool::release::<Projectile>);
+                        continue;
+                    }
+                }
+            }
+        }
+
+        transform.translation = position.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(Vec2::X.angle_between(projectile.velocity));
+    }
+}
+
+fn resolve_hits(
+    mut budget: Budget,
+    mut collisions: EventReader<CollisionEvent>,
+    mut damage: EventWriter<DamageEvent>,
+    mut statuses: EventWriter<ApplyStatus>,
+    motion: Res<MotionConfig>,
+    mut impulses: EventWriter<Impulse>,
+    mut projectiles: Query<
+        (
+            &mut Projectile,
+            &Transform,
+            Option<&mut Pierce>,
+            Option<&mut Bounce>,
+            Option<&Inflicts>,
+        ),
+        Without<Cluster>,
+    >,
+    targets: Query<(&Transform, &Collider), Without<Projectile>>,
+) {
+    // a projectile can touch several things in one tick
+    let mut spent = HashSet::default();
+    for event in collisions.read() {
+        let (shot, other) = if projectiles.contains(event.a) {
+            (event.a, event.b)
+        } else {
+            (event.b, event.a)
+        };
+        if spent.contains(&shot) {
+            continue;
+        }
+        let Ok((mut projectile, transform, pierce, bounce, inflicts)) = projectiles.get_mut(shot)
+        else {
+            continue;
+        };
+
+        let Ok((target, collider)) = targets.get(other) else {
+            continue;
+        };
+
+        // enemies, and anything else that wants to be shot like one
+        if collider.layer.contains(CollisionLayer::ENEMY) {
+            if let Some(mut pierce) = pierce {
+                if !pierce.hit.insert(other) {
+                    continue;
+                }
+                if pierce.count > 0 {
+                    pierce.count -= 1;
+                } else {
+                    spent.insert(shot);
+                }
+            } else {
+                spent.insert(shot);
+            }
+            damage.send(DamageEvent {
+                target: other,
+                amount: projectile.damage,
+            });
+            impulses.send(Impulse {
+                target: other,
+                impulse: projectile.velocity.normalize_or_zero() * motion.hit_knockback,
+            });
+            if let Some(inflicts) = inflicts {
+                statuses.send_batch(inflicts.on(other));
+            }
+            continue;
+        }
+
+        if !collider.layer.contains(CollisionLayer::WALL) {
+            continue;
+        }
+        let normal = (transform.translation - target.translation)
+            .truncate()
+            .normalize_or_zero();
+        // already bounced and on its way out
+        if projectile.velocity.dot(normal) >= 0. {
+            continue;
+        }
+        match bounce {
+            Some(mut bounce) if bounce.count > 0 => {
+                bounce.count -= 1;
+                projectile.velocity = reflect(projectile.velocity, normal);
+            }
+            _ => {
+                spent.insert(shot);
+            }
+        }
+    }
+
+    for shot in spent {
+        budget.return_to_pool(shot, pool::release::<Projectile>);
+    }
+}
+
+/// How far along a ray from `origin` in the normalized `direction` it
+/// first touches the circle of `radius` at `center`, if it does within
+/// `range`.
+fn ray_hit(origin: Vec2, direction: Vec2, range: f32, center: Vec2, radius: f32) -> Option<f32> {
+    let offset = center - origin;
+    let along = offset.dot(direction);
+    if along < -radius || along > range + radius {
+        return None;
+    }
+    let across = offset.length_squared() - along * along;
+    if across > radius * radius {
+        return None;
+    }
+    Some((along - (radius * radius - across).sqrt()).clamp(0., range))
+}
+
+fn fire_beams(
+    time: Res<Time>,
+    mut budget: Budget,
+    mut damage: EventWriter<DamageEvent>,
+    mut statuses: EventWriter<ApplyStatus>,
+    mut beams: Query<(
+        Entity,
+        &mut Beam,
+        &Transform,
+        &mut Sprite,
+        Option<&Inflicts>,
+    )>,
+    targets: Query<(Entity, &Transform, &Collider), Without<Beam>>,
+) {
+    for (entity, mut beam, transform, mut sprite, inflicts) in &mut beams {
+        if !beam.fired {
+            beam.fired = true;
+            let origin = transform.translation.truncate();
+            let direction = (transform.rotation * Vec3::X).truncate();
+            let mut hits: Vec<_> = targets
+                .iter()
+                .filter(|(_, _, collider)| {
+                    collider
+                        .layer
+                        .intersects(CollisionLayer::ENEMY | CollisionLayer::WALL)
+                })
+                .filter_map(|(target, target_transform, collider)| {
+                    let center = target_transform.translation.truncate();
+                    let radius = collider.radius + beam.width / 2.;
+                    ray_hit(origin, direction, beam.range, center, radius)
+                        .map(|distance| (distance, target, collider.layer))
+                })
+                .collect();
+            hits.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut length = beam.range;
+            let mut pierce = beam.pierce;
+            for (distance, target, layer) in hits {
+                if layer.contains(CollisionLayer::WALL) {
+                    length = distance;
+                    break;
+                }
+                damage.send(DamageEvent {
+                    target,
+                    amount: beam.damage,
+                });
+                if let Some(inflicts) = inflicts {
+                    statuses.send_batch(inflicts.on(target));
+                }
+                if pierce == 0 {
+                    length = distance;
+                    break;
+                }
+                pierce -= 1;
+            }
+            sprite.custom_size = Some(Vec2::new(length, beam.width));
+        }
+
+        beam.remaining -= time.delta_seconds();
+        if beam.remaining <= 0. {
+            budget.return_to_pool(entity, pool::release::<Beam>);
+            continue;
+        }
+        sprite
+            .color
+            .set_a(beam.remaining / beam.duration.max(f32::EPSILON));
+    }
+}
+
+fn split_clusters(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut collisions: EventReader<CollisionEvent>,
+    mut damage: EventWriter<DamageEvent>,
+    mut statuses: EventWriter<ApplyStatus>,
+    mut clusters: Query<(
+        Entity,
+        &mut Cluster,
+        &Projectile,
+        &Transform,
+        Has<Ballistic>,
+        Option<&Inflicts>,
+    )>,
+    targets: Query<&Collider, Without<Cluster>>,
+    mut budget: Budget,
+    mut pools: ShotPools,
+    hit_stop: Res<HitStopConfig>,
+    mut stops: EventWriter<HitStop>,
+    shake_config: Res<ShakeConfig>,
+    mut shake: ResMut<CameraShake>,
+    mut particles: EventWriter<EmitParticles>,
+    mut blasts: EventWriter<Blast>,
+) {
+    let mut impacted = HashSet::default();
+    for event in collisions.read() {
+        for (shot, other) in [(event.a, event.b), (event.b, event.a)] {
+            let Ok((_, _, projectile, _, _, inflicts)) = clusters.get(shot) else {
+                continue;
+            };
+            let Ok(collider) = targets.get(other) else {
+                continue;
+            };
+            let is_enemy = collider.layer.contains(CollisionLayer::ENEMY);
+            if !collider
+                .layer
+                .intersects(CollisionLayer::ENEMY | CollisionLayer::WALL)
+            {
+                continue;
+            }
+            // the first thing touched takes the impact
+            if impacted.insert(shot) && is_enemy {
+                damage.send(DamageEvent {
+                    target: other,
+                    amount: projectile.damage,
+                });
+                if let Some(inflicts) = inflicts {
+                    statuses.send_batch(inflicts.on(other));
+                }
+            }
+        }
+    }
+
+    for (entity, mut cluster, projectile, transform, lobbed, inflicts) in &mut clusters {
+        cluster.fuse -= time.delta_seconds();
+        // a lob only splits where it lands
+        let expired = projectile.remaining <= 0. || (cluster.fuse <= 0. && !lobbed);
+        if !expired && !impacted.contains(&entity) {
+            continue;
+        }
+
+        let origin = transform.translation.truncate();
+        let heading = Vec2::X.angle_between(projectile.velocity);
+        let config = cluster.config;
+        let count = config.children.max(1);
+        // a full circle leaves no gap between the last and first child
+        let step = if config.spread >= std::f32::consts::TAU - f32::EPSILON {
+            config.spread / count as f32
+        } else {
+            config.spread / (count.max(2) - 1) as f32
+        };
+        // the children take the cluster's place, not that of an older shot
+        budget.return_to_pool(entity, pool::release::<Projectile>);
+        for i in 0..count {
+            let angle = heading - config.spread / 2. + step * i as f32;
+            let child = spawn_shot(
+                &mut commands,
+                &mut budget,
+                &mut pools,
+                Projectile {
+                    velocity: Vec2::from_angle(angle) * config.child_speed,
+                    remaining: config.child_lifetime,
+                    damage: config.child_damage,
+                },
+                origin,
+                CLUSTER_CHILD_SIZE,
+            );
+            // the children carry the cluster's effects on
+            if let (Some(mut child), Some(inflicts)) = (child, inflicts) {
+                child.insert(inflicts.clone());
+            }
+        }
+        // the brief ring shown where a cluster split
+        particles.send(EmitParticles {
+            effect: SPLIT_FLASH,
+            position: origin,
+            count: 1,
+        });
+        stops.send(HitStop {
+            duration: hit_stop.explosion,
+        });
+        shake.add_trauma(shake_config.explosion);
+        blasts.send(Blast { position: origin });
+    }
+}
+
+fn wear_off_boosts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut boosts: Query<(Entity, &mut ModifierBoost)>,
+) {
+    for (entity, mut boost) in &mut boosts {
+        boost.remaining -= time.delta_seconds();
+        if boost.remaining <= 0. {
+            commands.entity(entity).remove::<ModifierBoost>();
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Cosmetic effect quality.
+//!
+//! [`EffectsQuality`] tells purely visual systems (trails, corpses, rain,
+//! particles, night lights) how much to draw. It is either picked on the
+//! settings screen or, on [`QualityPreset::Auto`], lowered when the frame
+//! rate stays under [`PerformanceConfig::lower_below_fps`], just short of
+//! 60 fps, and raised again once it recovers. Nothing that affects gameplay
+//! may read it.
+//!
+//! Low also turns multisampling off, the one per-pixel cost of the 2D
+//! renderer, which on a high density screen saves about what rendering at
+//! a lower resolution would.
+
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::DisplaySettings;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Reflect)]
+pub enum QualityLevel {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl QualityLevel {
+    fn lower(self) -> Option<Self> {
+        match self {
+            QualityLevel::Low => None,
+            QualityLevel::Medium => Some(QualityLevel::Low),
+            QualityLevel::High => Some(QualityLevel::Medium),
+        }
+    }
+
+    fn higher(self) -> Option<Self> {
+        match self {
+            QualityLevel::Low => Some(QualityLevel::Medium),
+            QualityLevel::Medium => Some(QualityLevel::High),
+            QualityLevel::High => None,
+        }
+    }
+}
+
+/// The quality setting the player picked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum QualityPreset {
+    /// Follow the frame rate.
+    #[default]
+    Auto,
+    Low,
+    Medium,
+    High,
+}
+
+impl QualityPreset {
+    pub const ALL: [QualityPreset; 4] = [
+        QualityPreset::Auto,
+        QualityPreset::Low,
+        QualityPreset::Medium,
+        QualityPreset::High,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QualityPreset::Auto => "Auto",
+            QualityPreset::Low => "Low",
+            QualityPreset::Medium => "Medium",
+            QualityPreset::High => "High",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|preset| *preset == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn fixed(self) -> Option<QualityLevel> {
+        match self {
+            QualityPreset::Auto => None,
+            QualityPreset::Low => Some(QualityLevel::Low),
+            QualityPreset::Medium => Some(QualityLevel::Medium),
+            QualityPreset::High => Some(QualityLevel::High),
+        }
+    }
+}
+
+/// How much cosmetic work to do this frame.
+#[derive(Resource, Reflect, Debug, Clone, Copy, Default)]
+#[reflect(Resource)]
+pub struct EffectsQuality {
+    pub level: QualityLevel,
+}
+
+impl EffectsQuality {
+    /// Multiplier for effect density: rain drops, particle counts,
+    /// trail length.
+    pub fn density(&self) -> f32 {
+        match self.level {
+            QualityLevel::Low => 0.25,
+            QualityLevel::Medium => 0.6,
+            QualityLevel::High => 1.,
+        }
+    }
+
+    /// `base` scaled by [`Self::density`], for counts and budgets.
+    pub fn scale_count(&self, base: usize) -> usize {
+        (base as f32 * self.density()).round() as usize
+    }
+
+    /// Whether lingering extras such as corpses are drawn at all.
+    pub fn extras(&self) -> bool {
+        self.level > QualityLevel::Low
+    }
+
+    /// Whether the glows of the night lights are drawn.
+    pub fn lights(&self) -> bool {
+        self.level > QualityLevel::Low
+    }
+
+    pub fn msaa(&self) -> Msaa {
+        match self.level {
+            QualityLevel::Low => Msaa::Off,
+            QualityLevel::Medium | QualityLevel::High => Msaa::Sample4,
+        }
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct PerformanceConfig {
+    /// Lower quality after the frame rate stays below this...
+    pub lower_below_fps: f32,
+    /// ...and raise it again after it stays above this.
+    pub raise_above_fps: f32,
+    /// Seconds a frame rate has to last before quality changes.
+    pub sustain: f32,
+}
+
+impl Default for PerformanceConfig {
+    fn default() -> Self {
+        Self {
+            lower_below_fps: 55.,
+            raise_above_fps: 59.,
+            sustain: 3.,
+        }
+    }
+}
+
+/// Seconds the frame rate has been past a threshold, negative below and
+/// positive above.
+#[derive(Resource, Default)]
+struct FrameRateTrend(f32);
+
+pub struct QualityPlugin;
+
+impl Plugin for QualityPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        app.register_type::<EffectsQuality>()
+            .register_type::<PerformanceConfig>()
+            .init_resource::<EffectsQuality>()
+            .init_resource::<PerformanceConfig>()
+            .init_resource::<FrameRateTrend>()
+            .add_systems(
+                Update,
+                (
+                    update_quality,
+                    apply_msaa.run_if(resource_changed::<EffectsQuality>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn update_quality(
+    display: Res<DisplaySettings>,
+    config: Res<PerformanceConfig>,
+    diagnostics: Res<DiagnosticsStore>,
+    time: Res<Time<Real>>,
+    mut trend: ResMut<FrameRateTrend>,
+    mut quality: ResMut<EffectsQuality>,
+) {
+    if let Some(level) = display.quality.fixed() {
+        trend.0 = 0.;
+        if quality.level != level {
+            quality.level = level;
+        }
+        return;
+    }
+
+    let Some(fps) = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+    else {
+        return;
+    };
+
+    let dt = time.delta_seconds();
+    let fps = fps as f32;
+    trend.0 = if fps < config.lower_below_fps {
+        trend.0.min(0.) - dt
+    } else if fps > config.raise_above_fps {
+        trend.0.max(0.) + dt
+    } else {
+        0.
+    };
+
+    let change = if trend.0 <= -config.sustain {
+        quality.level.lower()
+    } else if trend.0 >= config.sustain {
+        quality.level.higher()
+    } else {
+        None
+    };
+    if let Some(level) = change {
+        info!(
+            "effects quality {:?} -> {level:?} at {fps:.0} fps",
+            quality.level
+        );
+        quality.level = level;
+        trend.0 = 0.;
+    }
+}
+
+fn apply_msaa(quality: Res<EffectsQuality>, mut msaa: ResMut<Msaa>) {
+    let wanted = quality.msaa();
+    if *msaa != wanted {
+        *msaa = wanted;
+    }
+}
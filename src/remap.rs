@@ -0,0 +1,850 @@
+//! Checks on the player's bindings, for rebinding at runtime.
+//!
+//! [`rebind`] is the one way bindings should be changed. It refuses, swaps
+//! or clears when the new input already belongs to another action,
+//! depending on the [`ConflictResolution`]. [`binding_problems`] lists what
+//! is wrong with a map as it stands: inputs bound to two actions, and
+//! [`CriticalActions`] left with no binding at all. The settings screen
+//! shows those problems inline.
+//!
+//! The bindings screen, opened from the settings with [`OpenBindings`],
+//! lists the [`REBINDABLE`] actions with their keyboard or mouse binding
+//! and their gamepad one. Pressing either waits for the next key, mouse or
+//! gamepad button of that kind and binds it in place of the shown one.
+//! When another action already has it, a prompt asks whether to swap, so
+//! that action gets the shown binding, or to clear it from that action;
+//! cancelling leaves both as they were. Escape stops waiting, or cancels.
+//! The rebound actions are stored in [`ControlSettings::bindings`] and put
+//! back on the player's map whenever it or the settings change.
+
+use bevy::{prelude::*, ui::FocusPolicy};
+use leafwing_input_manager::{
+    prelude::*,
+    user_input::{InputKind, UserInput},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    default_input_map,
+    fonts::UiFonts,
+    settings::ControlSettings,
+    state::{GameState, StateScoped},
+    Action, Player,
+};
+
+/// Actions with a button each that the bindings screen can change.
+pub const REBINDABLE: [Action; 6] = [
+    Action::Shoot,
+    Action::Dash,
+    Action::Rewind,
+    Action::Lock,
+    Action::SwitchWeapon,
+    Action::Deploy,
+];
+
+fn action_name(action: Action) -> &'static str {
+    match action {
+        Action::Shoot => "Fire",
+        Action::Dash => "Dash",
+        Action::Rewind => "Rewind",
+        Action::Lock => "Lock on",
+        Action::SwitchWeapon => "Switch weapon",
+        Action::Deploy => "Deploy turret",
+        Action::Move => "Move",
+        Action::Look => "Aim",
+        Action::Trigger => "Trigger",
+        Action::Zoom => "Zoom",
+    }
+}
+
+/// Which half of an action's bindings a slot on the bindings screen shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingDevice {
+    /// A key or mouse button.
+    Keyboard,
+    Gamepad,
+}
+
+impl BindingDevice {
+    pub fn owns(self, input: &UserInput) -> bool {
+        matches!(
+            (self, input),
+            (
+                BindingDevice::Keyboard,
+                UserInput::Single(InputKind::PhysicalKey(_) | InputKind::Mouse(_))
+            ) | (
+                BindingDevice::Gamepad,
+                UserInput::Single(InputKind::GamepadButton(_))
+            )
+        )
+    }
+
+    /// The first of `action`'s bindings on this device.
+    pub fn binding(self, map: &InputMap<Action>, action: Action) -> Option<UserInput> {
+        map.get(&action)?
+            .iter()
+            .find(|input| self.owns(input))
+            .cloned()
+    }
+}
+
+/// The bindings of an action as rebound on the bindings screen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredBinding {
+    pub action: Action,
+    pub inputs: Vec<UserInput>,
+}
+
+/// The [`REBINDABLE`] actions' bindings in `map`, for storing.
+pub fn stored_bindings(map: &InputMap<Action>) -> Vec<StoredBinding> {
+    REBINDABLE
+        .iter()
+        .map(|action| StoredBinding {
+            action: *action,
+            inputs: map.get(action).cloned().unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Actions the game can't be played without.
+#[derive(Resource, Debug, Clone)]
+pub struct CriticalActions(pub Vec<Action>);
+
+impl Default for CriticalActions {
+    fn default() -> Self {
+        Self(vec![Action::Move])
+    }
+}
+
+/// What [`rebind`] does when the input is already bound to another action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// Leave the bindings as they are and report the conflict.
+    Prevent,
+    /// Give the other action the binding being replaced.
+    Swap,
+    /// Take the input away from the other action.
+    Clear,
+}
+
+// only ever a handful of these, listed for the player
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindingProblem {
+    /// The input triggers more than one action.
+    Conflict {
+        input: UserInput,
+        actions: Vec<Action>,
+    },
+    /// A critical action has no binding.
+    Unbound(Action),
+}
+
+impl BindingProblem {
+    pub fn message(&self) -> String {
+        match self {
+            BindingProblem::Conflict { input, actions } => {
+                format!("{} is bound to {actions:?}", describe(input))
+            }
+            BindingProblem::Unbound(action) => format!("{action:?} has no binding"),
+        }
+    }
+}
+
+fn describe(input: &UserInput) -> String {
+    match input {
+        UserInput::Single(InputKind::PhysicalKey(key)) => {
+            let name = format!("{key:?}");
+            // KeyQ -> Q, Digit1 -> 1
+            name.strip_prefix("Key")
+                .or_else(|| name.strip_prefix("Digit"))
+                .unwrap_or(&name)
+                .to_string()
+        }
+        UserInput::Single(InputKind::Mouse(button)) => format!("Mouse {button:?}"),
+        UserInput::Single(InputKind::GamepadButton(button)) => format!("{button:?}"),
+        UserInput::Single(kind) => format!("{kind:?}"),
+        other => format!("{other:?}"),
+    }
+}
+
+/// Binds `new` to `action`, in place of `old` if given. When `new` already
+/// belongs to another action, `resolution` decides what happens; with
+/// [`ConflictResolution::Prevent`] nothing changes and that action is
+/// returned.
+pub fn rebind(
+    map: &mut InputMap<Action>,
+    action: Action,
+    old: Option<&UserInput>,
+    new: UserInput,
+    resolution: ConflictResolution,
+) -> Result<(), Action> {
+    let owner = map
+        .iter()
+        .find(|(other, inputs)| **other != action && inputs.contains(&new))
+        .map(|(other, _)| *other);
+
+    if let Some(other) = owner {
+        match resolution {
+            ConflictResolution::Prevent => return Err(other),
+            ConflictResolution::Clear => {
+                map.remove(&other, new.clone());
+            }
+            ConflictResolution::Swap => {
+                map.remove(&other, new.clone());
+                if let Some(old) = old {
+                    map.insert(other, old.clone());
+                }
+            }
+        }
+    }
+    if let Some(old) = old {
+        map.remove(&action, old.clone());
+    }
+    map.insert(action, new);
+    Ok(())
+}
+
+/// Everything wrong with `map`: conflicts first, then unbound critical
+/// actions.
+pub fn binding_problems(map: &InputMap<Action>, critical: &CriticalActions) -> Vec<BindingProblem> {
+    let mut problems: Vec<BindingProblem> = Vec::new();
+    for (action, inputs) in map.iter() {
+        for input in inputs {
+            let existing = problems.iter_mut().find_map(|problem| match problem {
+                BindingProblem::Conflict {
+                    input: other,
+                    actions,
+                } if other == input => Some(actions),
+                _ => None,
+            });
+            match existing {
+                Some(actions) => {
+                    if !actions.contains(action) {
+                        actions.push(*action);
+                    }
+                }
+                None => problems.push(BindingProblem::Conflict {
+                    input: input.clone(),
+                    actions: vec![*action],
+                }),
+            }
+        }
+    }
+    problems.retain(
+        |problem| matches!(problem, BindingProblem::Conflict { actions, .. } if actions.len() > 1),
+    );
+
+    for action in &critical.0 {
+        if map.get(action).is_none_or(|inputs| inputs.is_empty()) {
+            problems.push(BindingProblem::Unbound(*action));
+        }
+    }
+    problems
+}
+
+/// Text listing the current [`BindingProblem`]s, empty when there are none.
+#[derive(Component)]
+pub struct BindingWarnings;
+
+/// Opens the bindings screen over the settings.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct OpenBindings;
+
+#[derive(Component)]
+struct BindingsScreen;
+
+/// A button showing, and rebinding, `action`'s binding on `device`.
+#[derive(Component, Debug, Clone, Copy)]
+struct BindingSlot {
+    action: Action,
+    device: BindingDevice,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum BindingsButton {
+    Reset,
+    Done,
+}
+
+/// The slot waiting for an input to bind, if any.
+#[derive(Resource, Debug, Default)]
+struct Listening(Option<BindingSlot>);
+
+/// An input for `slot` that `other` already has, waiting on the player to
+/// pick a [`ConflictResolution`].
+#[derive(Debug, Clone)]
+struct PendingConflict {
+    slot: BindingSlot,
+    input: UserInput,
+    other: Action,
+}
+
+#[derive(Resource, Debug, Default)]
+struct Conflict(Option<PendingConflict>);
+
+/// The prompt asking how to settle the [`Conflict`].
+#[derive(Component)]
+struct ConflictPrompt;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum ConflictButton {
+    Resolve(ConflictResolution),
+    Cancel,
+}
+
+pub struct RemapPlugin;
+
+impl Plugin for RemapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CriticalActions>()
+            .init_resource::<Listening>()
+            .init_resource::<Conflict>()
+            .add_event::<OpenBindings>()
+            .add_systems(Update, (apply_stored_bindings, update_binding_warnings))
+            .add_systems(
+                Update,
+                (
+                    open_bindings,
+                    capture_binding,
+                    press_bindings_buttons,
+                    press_conflict_buttons,
+                    show_conflict_prompt,
+                    update_binding_slots,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Settings)),
+            )
+            .add_systems(OnExit(GameState::Settings), stop_listening);
+    }
+}
+
+fn apply_stored_bindings(
+    settings: Res<ControlSettings>,
+    mut maps: Query<&mut InputMap<Action>, With<Player>>,
+) {
+    for mut map in &mut maps {
+        // a new player gets them too, not only new bindings
+        if !settings.is_changed() && !map.is_added() {
+            continue;
+        }
+        for stored in &settings.bindings {
+            if map.get(&stored.action) == Some(&stored.inputs) {
+                continue;
+            }
+            map.clear_action(&stored.action);
+            for input in &stored.inputs {
+                map.insert(stored.action, input.clone());
+            }
+        }
+    }
+}
+
+fn update_binding_warnings(
+    critical: Res<CriticalActions>,
+    maps: Query<Ref<InputMap<Action>>, With<Player>>,
+    mut warnings: Query<(&mut Text, Ref<BindingWarnings>)>,
+) {
+    let Ok(map) = maps.get_single() else {
+        return;
+    };
+    for (mut text, marker) in &mut warnings {
+        if !marker.is_added() && !map.is_changed() && !critical.is_changed() {
+            continue;
+        }
+        text.sections[0].value = binding_problems(&map, &critical)
+            .iter()
+            .map(BindingProblem::message)
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+}
+
+fn open_bindings(
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    mut requests: EventReader<OpenBindings>,
+    screens: Query<(), With<BindingsScreen>>,
+) {
+    if requests.read().last().is_none() || !screens.is_empty() {
+        return;
+    }
+    let button = |width: f32| ButtonBundle {
+        style: Style {
+            min_width: Val::Px(width),
+            padding: UiRect::axes(Val::Px(8.), Val::Px(6.)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: Color::rgba(1., 1., 1., 0.15).into(),
+        ..default()
+    };
+    let text = |value: &str| TextBundle::from_section(value, fonts.style(20., Color::WHITE));
+
+    commands
+        .spawn((
+            BindingsScreen,
+            StateScoped(GameState::Settings),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.85).into(),
+                // keep clicks off the settings underneath
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(21),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Bindings",
+                fonts.bold(32., Color::WHITE),
+            ));
+            for action in REBINDABLE {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(420.),
+                            column_gap: Val::Px(8.),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(text(action_name(action)).with_style(Style {
+                            flex_grow: 1.,
+                            ..default()
+                        }));
+                        for device in [BindingDevice::Keyboard, BindingDevice::Gamepad] {
+                            parent
+                                .spawn((BindingSlot { action, device }, button(120.)))
+                                .with_children(|parent| {
+                                    parent.spawn(text(""));
+                                });
+                        }
+                    });
+            }
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::top(Val::Px(12.)),
+                        column_gap: Val::Px(12.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (kind, label) in [
+                        (BindingsButton::Reset, "Reset"),
+                        (BindingsButton::Done, "Done"),
+                    ] {
+                        parent.spawn((kind, button(96.))).with_children(|parent| {
+                            parent.spawn(text(label));
+                        });
+                    }
+                });
+        });
+}
+
+/// The input just pressed on `device`, if any. Clicks count only when
+/// `clicks` is set.
+fn just_pressed(
+    device: BindingDevice,
+    clicks: bool,
+    keys: &ButtonInput<KeyCode>,
+    mouse: &ButtonInput<MouseButton>,
+    gamepad_buttons: &ButtonInput<GamepadButton>,
+) -> Option<UserInput> {
+    match device {
+        BindingDevice::Keyboard => keys
+            .get_just_pressed()
+            .next()
+            .map(|key| UserInput::from(*key))
+            .or_else(|| {
+                mouse
+                    .get_just_pressed()
+                    .next()
+                    .filter(|_| clicks)
+                    .map(|button| UserInput::from(*button))
+            }),
+        BindingDevice::Gamepad => gamepad_buttons
+            .get_just_pressed()
+            .next()
+            .map(|button| UserInput::from(button.button_type)),
+    }
+}
+
+fn capture_binding(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut listening: ResMut<Listening>,
+    mut settings: ResMut<ControlSettings>,
+    mut maps: Query<&mut InputMap<Action>, With<Player>>,
+    interactions: Query<&Interaction>,
+    mut conflict: ResMut<Conflict>,
+) {
+    let Some(slot) = listening.0 else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::Escape) {
+        listening.0 = None;
+        return;
+    }
+    // a click on a button of the screen is for the button
+    let clicks = interactions
+        .iter()
+        .all(|interaction| *interaction == Interaction::None);
+    let Some(new) = just_pressed(slot.device, clicks, &keys, &mouse, &gamepad_buttons) else {
+        return;
+    };
+    listening.0 = None;
+    let Ok(mut map) = maps.get_single_mut() else {
+        return;
+    };
+    let old = slot.device.binding(&map, slot.action);
+    match rebind(
+        &mut map,
+        slot.action,
+        old.as_ref(),
+        new.clone(),
+        ConflictResolution::Prevent,
+    ) {
+        Ok(()) => settings.bindings = stored_bindings(&map),
+        Err(other) => {
+            conflict.0 = Some(PendingConflict {
+                slot,
+                input: new,
+                other,
+            });
+        }
+    }
+}
+
+fn press_bindings_buttons(
+    mut commands: Commands,
+    slots: Query<(&Interaction, &BindingSlot), Changed<Interaction>>,
+    buttons: Query<(&Interaction, &BindingsButton), Changed<Interaction>>,
+    screens: Query<Entity, With<BindingsScreen>>,
+    mut listening: ResMut<Listening>,
+    mut settings: ResMut<ControlSettings>,
+    mut maps: Query<&mut InputMap<Action>, With<Player>>,
+) {
+    for (interaction, slot) in &slots {
+        if *interaction == Interaction::Pressed {
+            listening.0 = Some(*slot);
+        }
+    }
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            BindingsButton::Reset => {
+                let defaults = default_input_map();
+                if let Ok(mut map) = maps.get_single_mut() {
+                    for action in REBINDABLE {
+                        map.clear_action(&action);
+                        for input in defaults.get(&action).into_iter().flatten() {
+                            map.insert(action, input.clone());
+                        }
+                    }
+                }
+                settings.bindings.clear();
+                listening.0 = None;
+            }
+            BindingsButton::Done => {
+                for entity in &screens {
+                    commands.entity(entity).despawn_recursive();
+                }
+                listening.0 = None;
+            }
+        }
+    }
+}
+
+fn press_conflict_buttons(
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Query<(&Interaction, &ConflictButton), Changed<Interaction>>,
+    mut conflict: ResMut<Conflict>,
+    mut settings: ResMut<ControlSettings>,
+    mut maps: Query<&mut InputMap<Action>, With<Player>>,
+) {
+    let Some(pending) = conflict.0.clone() else {
+        return;
+    };
+    let pressed = buttons
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| *button)
+        .or(keys
+            .just_pressed(KeyCode::Escape)
+            .then_some(ConflictButton::Cancel));
+    let Some(pressed) = pressed else {
+        return;
+    };
+    conflict.0 = None;
+    let (ConflictButton::Resolve(resolution), Ok(mut map)) = (pressed, maps.get_single_mut())
+    else {
+        return;
+    };
+    let PendingConflict { slot, input, .. } = pending;
+    let old = slot.device.binding(&map, slot.action);
+    if rebind(&mut map, slot.action, old.as_ref(), input, resolution).is_ok() {
+        settings.bindings = stored_bindings(&map);
+    }
+}
+
+/// Opens the prompt for a new [`Conflict`], and closes it once settled.
+fn show_conflict_prompt(
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    conflict: Res<Conflict>,
+    prompts: Query<Entity, With<ConflictPrompt>>,
+) {
+    if !conflict.is_changed() {
+        return;
+    }
+    for entity in &prompts {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(pending) = &conflict.0 else {
+        return;
+    };
+    let text = |value: &str| TextBundle::from_section(value, fonts.style(20., Color::WHITE));
+    let question = format!(
+        "{} is bound to {}",
+        describe(&pending.input),
+        action_name(pending.other)
+    );
+
+    commands
+        .spawn((
+            ConflictPrompt,
+            StateScoped(GameState::Settings),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(12.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                // keep clicks off the bindings underneath
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(22),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(text(&question));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(12.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (button, label) in [
+                        (ConflictButton::Resolve(ConflictResolution::Swap), "Swap"),
+                        (ConflictButton::Resolve(ConflictResolution::Clear), "Clear"),
+                        (ConflictButton::Cancel, "Cancel"),
+                    ] {
+                        parent
+                            .spawn((
+                                button,
+                                ButtonBundle {
+                                    style: Style {
+                                        min_width: Val::Px(96.),
+                                        padding: UiRect::axes(Val::Px(8.), Val::Px(6.)),
+                                        justify_content: JustifyContent::Center,
+                                        ..default()
+                                    },
+                                    background_color: Color::rgba(1., 1., 1., 0.15).into(),
+                                    ..default()
+                                },
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(text(label));
+                            });
+                    }
+                });
+        });
+}
+
+fn update_binding_slots(
+    listening: Res<Listening>,
+    maps: Query<Ref<InputMap<Action>>, With<Player>>,
+    slots: Query<(Ref<BindingSlot>, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    let Ok(map) = maps.get_single() else {
+        return;
+    };
+    for (slot, children) in &slots {
+        if !slot.is_added() && !map.is_changed() && !listening.is_changed() {
+            continue;
+        }
+        let waiting = listening.0.is_some_and(|listened| {
+            listened.action == slot.action && listened.device == slot.device
+        });
+        let value = if waiting {
+            "Press...".to_string()
+        } else {
+            slot.device
+                .binding(&map, slot.action)
+                .map_or_else(|| "-".to_string(), |input| describe(&input))
+        };
+        for child in children {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value.clone_from(&value);
+            }
+        }
+    }
+}
+
+fn stop_listening(mut listening: ResMut<Listening>, mut conflict: ResMut<Conflict>) {
+    listening.0 = None;
+    conflict.0 = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: KeyCode) -> UserInput {
+        UserInput::Single(InputKind::PhysicalKey(key))
+    }
+
+    fn map() -> InputMap<Action> {
+        InputMap::new([
+            (Action::Move, KeyCode::KeyW),
+            (Action::Lock, KeyCode::KeyL),
+            (Action::Rewind, KeyCode::KeyR),
+        ])
+    }
+
+    #[test]
+    fn a_double_bind_is_prevented_or_reported() {
+        let mut map = map();
+        let key_l = key(KeyCode::KeyL);
+        let key_r = key(KeyCode::KeyR);
+        let critical = CriticalActions::default();
+
+        let refused = rebind(
+            &mut map,
+            Action::Rewind,
+            Some(&key_r),
+            key_l.clone(),
+            ConflictResolution::Prevent,
+        );
+        assert_eq!(refused, Err(Action::Lock));
+        assert_eq!(map.get(&Action::Rewind), Some(&vec![key_r.clone()]));
+        assert!(binding_problems(&map, &critical).is_empty());
+
+        // bound past rebind, the way an old stored binding could be
+        map.insert(Action::Rewind, key_l.clone());
+        match binding_problems(&map, &critical).as_slice() {
+            [BindingProblem::Conflict { input, actions }] => {
+                assert_eq!(input, &key_l);
+                assert_eq!(actions.len(), 2);
+                assert!(actions.contains(&Action::Lock) && actions.contains(&Action::Rewind));
+            }
+            problems => panic!("expected one conflict, got {problems:?}"),
+        }
+    }
+
+    #[test]
+    fn a_swap_leaves_no_problems() {
+        let mut map = map();
+        let key_l = key(KeyCode::KeyL);
+        let key_r = key(KeyCode::KeyR);
+
+        let swapped = rebind(
+            &mut map,
+            Action::Rewind,
+            Some(&key_r),
+            key_l.clone(),
+            ConflictResolution::Swap,
+        );
+        assert_eq!(swapped, Ok(()));
+        assert_eq!(map.get(&Action::Rewind), Some(&vec![key_l]));
+        assert_eq!(map.get(&Action::Lock), Some(&vec![key_r]));
+        assert!(binding_problems(&map, &CriticalActions::default()).is_empty());
+    }
+
+    #[test]
+    fn clearing_moves_only_binding_reports_it_unbound() {
+        let mut map = map();
+        let w = key(KeyCode::KeyW);
+
+        let cleared = rebind(&mut map, Action::Lock, None, w, ConflictResolution::Clear);
+        assert_eq!(cleared, Ok(()));
+        assert_eq!(
+            binding_problems(&map, &CriticalActions::default()),
+            vec![BindingProblem::Unbound(Action::Move)]
+        );
+    }
+
+    /// Rebinds Rewind's key to Lock's through the bindings screen, and
+    /// answers the prompt with `answer`.
+    fn answer_conflict(answer: ConflictButton) -> InputMap<Action> {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .init_resource::<ButtonInput<GamepadButton>>()
+            .init_resource::<ControlSettings>()
+            .init_resource::<Conflict>()
+            .insert_resource(Listening(Some(BindingSlot {
+                action: Action::Rewind,
+                device: BindingDevice::Keyboard,
+            })))
+            .add_systems(Update, (capture_binding, press_conflict_buttons).chain());
+        let player = app.world.spawn((Player { max_speed: 150. }, map())).id();
+
+        app.world
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyL);
+        app.update();
+        assert!(app.world.resource::<Conflict>().0.is_some());
+        assert_eq!(app.world.get::<InputMap<Action>>(player), Some(&map()));
+
+        app.world.spawn((answer, Interaction::Pressed));
+        app.update();
+        assert!(app.world.resource::<Conflict>().0.is_none());
+        app.world.get::<InputMap<Action>>(player).unwrap().clone()
+    }
+
+    #[test]
+    fn the_conflict_prompt_swaps_clears_or_cancels() {
+        let (key_l, key_r) = (key(KeyCode::KeyL), key(KeyCode::KeyR));
+
+        let swapped = answer_conflict(ConflictButton::Resolve(ConflictResolution::Swap));
+        assert_eq!(swapped.get(&Action::Rewind), Some(&vec![key_l.clone()]));
+        assert_eq!(swapped.get(&Action::Lock), Some(&vec![key_r]));
+
+        let cleared = answer_conflict(ConflictButton::Resolve(ConflictResolution::Clear));
+        assert_eq!(cleared.get(&Action::Rewind), Some(&vec![key_l]));
+        assert!(cleared
+            .get(&Action::Lock)
+            .is_none_or(|inputs| inputs.is_empty()));
+
+        assert_eq!(answer_conflict(ConflictButton::Cancel), map());
+    }
+}
@@ -0,0 +1,542 @@
+//! Recording runs and playing them back.
+//!
+//! Every new run but a [daily one](crate::daily) is recorded: the seed
+//! [`GameRng`] is reseeded with when it starts, the upgrades it is played
+//! with, and for every frame spent playing the time it took and the
+//! player's [`ActionState`]. Frames are taken once the state for the frame
+//! is settled, after all of [`PreUpdate`]'s input handling, so a frame is
+//! recorded exactly when the gameplay systems run. A frame packs into
+//! [`ReplayFrame`]'s twelve bytes; stick axes, the trigger, pressure slider
+//! included, and the zoom are rounded to what fits there, and written back
+//! rounded, so the run plays on exactly the input that was kept. When the
+//! run ends with a better score than the stored replay, it replaces it, and
+//! the main menu offers to watch it.
+//!
+//! Playing a replay starts a new run from the same seed and upgrades, with
+//! each recorded frame's time forced through [`TimeUpdateStrategy`] and its
+//! input replacing the player's; the pressure slider is left alone
+//! meanwhile. While it plays nothing counts: no stats, scrap, saves or
+//! leaderboard. It ends with the recording, or when the run does, back on
+//! the main menu.
+//!
+//! Every [`CHECK_INTERVAL`] frames the recording keeps a checksum of the
+//! player's position, the score and the generator, and playback compares
+//! its own against it. The first frame they differ at is where the
+//! replay desynced, and is logged.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+
+use bevy::{ecs::schedule::apply_state_transition, prelude::*, time::TimeUpdateStrategy};
+use leafwing_input_manager::{axislike::DualAxisData, buttonlike::ButtonState, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    daily::DailyChallenge,
+    death::{self, StartNewRun},
+    fonts::UiFonts,
+    generation,
+    progression::Progression,
+    rng::GameRng,
+    score::Score,
+    state::{GameState, StateScoped},
+    storage,
+    toast::Toast,
+    Action, Player,
+};
+
+const REPLAY_KEY: &str = "best_replay";
+
+/// Bumped whenever [`Replay`] or [`ReplayFrame`] change shape.
+const REPLAY_VERSION: u32 = 3;
+
+/// Frames between two checksums.
+const CHECK_INTERVAL: usize = 60;
+
+/// Actions whose pressed state is recorded, one bit each.
+const RECORDED: [Action; 9] = [
+    Action::Move,
+    Action::Look,
+    Action::Shoot,
+    Action::Trigger,
+    Action::Lock,
+    Action::Rewind,
+    Action::Dash,
+    Action::SwitchWeapon,
+    Action::Deploy,
+];
+
+/// One frame of input.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+struct ReplayFrame {
+    /// Length of the frame in real time.
+    nanos: u32,
+    /// Bit per [`RECORDED`] action pressed.
+    pressed: u16,
+    movement: [i8; 2],
+    look: [i8; 2],
+    trigger: u8,
+    /// [`Action::Zoom`], which moves the view and so what spawns in sight.
+    zoom: i8,
+}
+
+fn pack_axis(axis: Vec2) -> [i8; 2] {
+    let axis = axis.clamp(Vec2::NEG_ONE, Vec2::ONE) * i8::MAX as f32;
+    [axis.x.round() as i8, axis.y.round() as i8]
+}
+
+fn unpack_axis(axis: [i8; 2]) -> Vec2 {
+    Vec2::new(axis[0] as f32, axis[1] as f32) / i8::MAX as f32
+}
+
+impl ReplayFrame {
+    /// The frame for `action_state`, which is rounded to match it.
+    fn record(dt: Duration, action_state: &mut ActionState<Action>) -> Self {
+        let pressed = RECORDED
+            .iter()
+            .enumerate()
+            .filter(|(_, action)| action_state.pressed(action))
+            .fold(0, |bits, (bit, _)| bits | 1 << bit);
+        let axis = |action| {
+            action_state
+                .axis_pair(&action)
+                .map_or(Vec2::ZERO, |axis| axis.xy())
+        };
+        let frame = Self {
+            nanos: dt.as_nanos().min(u32::MAX as u128) as u32,
+            pressed,
+            movement: pack_axis(axis(Action::Move)),
+            look: pack_axis(axis(Action::Look)),
+            trigger: (action_state.value(&Action::Trigger).clamp(0., 1.) * u8::MAX as f32).round()
+                as u8,
+            // the camera takes at most a step a frame
+            zoom: (action_state.value(&Action::Zoom).clamp(-1., 1.) * i8::MAX as f32).round() as i8,
+        };
+        frame.write_values(action_state);
+        frame
+    }
+
+    /// Puts this frame's input into `action_state`, after `previous`.
+    fn play(&self, previous: Option<&ReplayFrame>, action_state: &mut ActionState<Action>) {
+        for (bit, action) in RECORDED.iter().enumerate() {
+            let was = previous.is_some_and(|previous| previous.pressed & 1 << bit != 0);
+            let is = self.pressed & 1 << bit != 0;
+            if action_state.action_data_mut(action).is_none() {
+                action_state.release(action);
+            }
+            if let Some(data) = action_state.action_data_mut(action) {
+                // leafwing sets these from the real input first, so they are
+                // written as a whole rather than pressed or released
+                data.state = match (was, is) {
+                    (false, false) => ButtonState::Released,
+                    (false, true) => ButtonState::JustPressed,
+                    (true, true) => ButtonState::Pressed,
+                    (true, false) => ButtonState::JustReleased,
+                };
+            }
+        }
+        self.write_values(action_state);
+    }
+
+    fn write_values(&self, action_state: &mut ActionState<Action>) {
+        for (action, axis) in [(Action::Move, self.movement), (Action::Look, self.look)] {
+            if let Some(data) = action_state.action_data_mut(&action) {
+                let axis = unpack_axis(axis);
+                data.axis_pair = Some(DualAxisData::from_xy(axis));
+                data.value = axis.length();
+            }
+        }
+        if let Some(data) = action_state.action_data_mut(&Action::Trigger) {
+            data.value = self.trigger as f32 / u8::MAX as f32;
+        }
+        if let Some(data) = action_state.action_data_mut(&Action::Zoom) {
+            data.value = self.zoom as f32 / i8::MAX as f32;
+        }
+    }
+
+    fn dt(&self) -> Duration {
+        Duration::from_nanos(self.nanos.into())
+    }
+}
+
+/// A recorded run.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Replay {
+    version: u32,
+    seed: u64,
+    progression: Progression,
+    /// Score the run ended on.
+    pub points: u64,
+    frames: Vec<ReplayFrame>,
+    /// A checksum every [`CHECK_INTERVAL`] frames.
+    checks: Vec<u64>,
+}
+
+impl Replay {
+    fn new(seed: u64, progression: Progression) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            seed,
+            progression,
+            points: 0,
+            frames: Vec::new(),
+            checks: Vec::new(),
+        }
+    }
+
+    fn load() -> Option<Self> {
+        let bytes = storage::load_bytes(REPLAY_KEY)?;
+        let replay: Self = bincode::deserialize(&bytes)
+            .map_err(|err| warn!("ignoring unreadable replay: {err}"))
+            .ok()?;
+        (replay.version == REPLAY_VERSION).then_some(replay)
+    }
+
+    fn save(&self) {
+        match bincode::serialize(self) {
+            Ok(bytes) => storage::save_bytes(REPLAY_KEY, &bytes),
+            Err(err) => error!("failed to serialize the replay: {err}"),
+        }
+    }
+}
+
+/// The best run recorded, offered on the main menu.
+#[derive(Resource, Debug)]
+pub struct BestReplay(pub Replay);
+
+/// The main menu button that plays the [`BestReplay`].
+#[derive(Component)]
+pub struct WatchReplayButton;
+
+#[derive(Component)]
+struct ReplayBanner;
+
+#[derive(Resource, Debug, Default)]
+pub enum ReplayMode {
+    #[default]
+    Off,
+    Recording(Replay),
+    Playback {
+        replay: Replay,
+        /// Frames played so far.
+        frame: usize,
+        /// Whether the run has been reseeded and frames are playing.
+        started: bool,
+        /// Out of frames, or the run is over.
+        ended: bool,
+        desynced: bool,
+        /// The player's own upgrades, put back afterwards.
+        stashed: Progression,
+    },
+    /// Just finished playing back. Events sent on the way out are read up
+    /// to a frame later, and still mustn't count.
+    Closing {
+        frames: u8,
+    },
+}
+
+/// Whether a replay is playing, for what a replayed run mustn't touch.
+pub fn is_replaying(mode: Res<ReplayMode>) -> bool {
+    matches!(
+        *mode,
+        ReplayMode::Playback { .. } | ReplayMode::Closing { .. }
+    )
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(replay) = Replay::load() {
+            app.insert_resource(BestReplay(replay));
+        }
+
+        app.init_resource::<ReplayMode>()
+            .add_systems(First, close_playback)
+            .add_systems(
+                StateTransition,
+                replay_input
+                    .after(apply_state_transition::<GameState>)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (
+                    press_watch_replay
+                        .run_if(in_state(GameState::MainMenu))
+                        .run_if(resource_exists::<BestReplay>),
+                    begin_run,
+                    end_playback,
+                )
+                    .chain()
+                    .before(generation::build_arena)
+                    .before(death::start_new_run),
+            )
+            .add_systems(OnEnter(GameState::GameOver), end_run)
+            .add_systems(OnEnter(GameState::MainMenu), stop_recording)
+            .add_systems(
+                OnEnter(GameState::Playing),
+                spawn_banner.run_if(is_replaying),
+            )
+            .add_systems(
+                Last,
+                (
+                    check_sync.run_if(in_state(GameState::Playing)),
+                    schedule_frame_time,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn press_watch_replay(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<WatchReplayButton>)>,
+    best: Res<BestReplay>,
+    mut mode: ResMut<ReplayMode>,
+    mut progression: ResMut<Progression>,
+    mut new_runs: EventWriter<StartNewRun>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !buttons.iter().any(|i| *i == Interaction::Pressed) {
+        return;
+    }
+    let replay = best.0.clone();
+    let stashed = std::mem::replace(&mut *progression, replay.progression.clone());
+    *mode = ReplayMode::Playback {
+        replay,
+        frame: 0,
+        started: false,
+        ended: false,
+        desynced: false,
+        stashed,
+    };
+    new_runs.send(StartNewRun);
+    next_state.set(GameState::Playing);
+}
+
+/// Seeds a new run, and starts recording it unless it is a replay or a
+/// daily run.
+pub fn begin_run(
+    time: Res<Time<Real>>,
+    progression: Res<Progression>,
+    daily: Res<DailyChallenge>,
+    mut mode: ResMut<ReplayMode>,
+    mut rng: ResMut<GameRng>,
+    mut new_runs: EventReader<StartNewRun>,
+) {
+    if new_runs.read().count() == 0 {
+        return;
+    }
+    match &mut *mode {
+        ReplayMode::Playback {
+            replay,
+            started: started @ false,
+            ..
+        } => {
+            rng.reseed(replay.seed);
+            *started = true;
+        }
+        // restarted or quit while watching
+        ReplayMode::Playback { ended, .. } => *ended = true,
+        ReplayMode::Closing { .. } => {}
+        ReplayMode::Off | ReplayMode::Recording(_) => match daily.seed() {
+            // the same for everyone, and not replayable without its modifiers
+            Some(seed) => {
+                rng.reseed(seed);
+                *mode = ReplayMode::Off;
+            }
+            None => {
+                let entropy = time.elapsed().as_nanos() as u64;
+                let seed = (u64::from(rng.next_u32()) << 32 | u64::from(rng.next_u32())) ^ entropy;
+                rng.reseed(seed);
+                *mode = ReplayMode::Recording(Replay::new(seed, progression.clone()));
+            }
+        },
+    }
+}
+
+fn replay_input(
+    time: Res<Time<Real>>,
+    mut mode: ResMut<ReplayMode>,
+    mut players: Query<&mut ActionState<Action>, With<Player>>,
+) {
+    let Ok(mut action_state) = players.get_single_mut() else {
+        return;
+    };
+    match &mut *mode {
+        ReplayMode::Recording(replay) => {
+            let frame = ReplayFrame::record(time.delta(), &mut action_state);
+            replay.frames.push(frame);
+        }
+        ReplayMode::Playback {
+            replay,
+            frame,
+            started: true,
+            ended: ended @ false,
+            ..
+        } => {
+            let Some(next) = replay.frames.get(*frame) else {
+                *ended = true;
+                return;
+            };
+            let previous = frame.checked_sub(1).and_then(|i| replay.frames.get(i));
+            next.play(previous, &mut action_state);
+            *frame += 1;
+        }
+        _ => {}
+    }
+}
+
+fn end_playback(
+    mut commands: Commands,
+    mut mode: ResMut<ReplayMode>,
+    mut progression: ResMut<Progression>,
+    mut new_runs: EventWriter<StartNewRun>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let ReplayMode::Playback {
+        ended: true,
+        stashed,
+        desynced,
+        ..
+    } = &mut *mode
+    else {
+        return;
+    };
+    *progression = std::mem::take(stashed);
+    if !*desynced {
+        toasts.send(Toast::new("Replay finished"));
+    }
+    commands.insert_resource(TimeUpdateStrategy::Automatic);
+    *mode = ReplayMode::Closing { frames: 2 };
+    // leave nothing of the replayed run behind
+    new_runs.send(StartNewRun);
+    next_state.set(GameState::MainMenu);
+}
+
+fn close_playback(mut mode: ResMut<ReplayMode>) {
+    if let ReplayMode::Closing { frames } = &mut *mode {
+        *frames = frames.saturating_sub(1);
+        if *frames == 0 {
+            *mode = ReplayMode::Off;
+        }
+    }
+}
+
+/// Keeps a finished recording if it beat the best one, or stops playback.
+fn end_run(
+    mut commands: Commands,
+    score: Res<Score>,
+    best: Option<Res<BestReplay>>,
+    mut mode: ResMut<ReplayMode>,
+) {
+    match std::mem::take(&mut *mode) {
+        ReplayMode::Recording(mut replay) => {
+            replay.points = score.points;
+            if best.is_none_or(|best| replay.points > best.0.points) {
+                replay.save();
+                commands.insert_resource(BestReplay(replay));
+            }
+        }
+        ReplayMode::Playback {
+            replay,
+            frame,
+            started,
+            desynced,
+            stashed,
+            ..
+        } => {
+            *mode = ReplayMode::Playback {
+                replay,
+                frame,
+                started,
+                ended: true,
+                desynced,
+                stashed,
+            };
+        }
+        other => *mode = other,
+    }
+}
+
+/// Quitting to the menu throws the run's recording away.
+fn stop_recording(mut mode: ResMut<ReplayMode>) {
+    if matches!(*mode, ReplayMode::Recording(_)) {
+        *mode = ReplayMode::Off;
+    }
+}
+
+fn checksum(rng: &GameRng, score: &Score, position: Vec2) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    rng.clone().next_u32().hash(&mut hasher);
+    score.points.hash(&mut hasher);
+    position.x.to_bits().hash(&mut hasher);
+    position.y.to_bits().hash(&mut hasher);
+    hasher.finish()
+}
+
+fn check_sync(
+    rng: Res<GameRng>,
+    score: Res<Score>,
+    mut mode: ResMut<ReplayMode>,
+    players: Query<&Transform, With<Player>>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let position = players
+        .get_single()
+        .map_or(Vec2::ZERO, |player| player.translation.truncate());
+    match &mut *mode {
+        ReplayMode::Recording(replay)
+            if !replay.frames.is_empty() && replay.frames.len() % CHECK_INTERVAL == 0 =>
+        {
+            replay.checks.push(checksum(&rng, &score, position));
+        }
+        ReplayMode::Playback {
+            replay,
+            frame,
+            desynced: desynced @ false,
+            ..
+        } if *frame > 0 && *frame % CHECK_INTERVAL == 0 => {
+            let recorded = replay.checks.get(*frame / CHECK_INTERVAL - 1);
+            if recorded.is_some_and(|check| *check != checksum(&rng, &score, position)) {
+                warn!("replay desynced by frame {frame}");
+                toasts.send(Toast::new("Replay desynced"));
+                *desynced = true;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Makes the next frame as long as its recorded one.
+fn schedule_frame_time(mut commands: Commands, mode: Res<ReplayMode>) {
+    if let ReplayMode::Playback {
+        replay,
+        frame,
+        started: true,
+        ended: false,
+        ..
+    } = &*mode
+    {
+        if let Some(next) = replay.frames.get(*frame) {
+            commands.insert_resource(TimeUpdateStrategy::ManualDuration(next.dt()));
+        }
+    }
+}
+
+fn spawn_banner(mut commands: Commands, fonts: Res<UiFonts>) {
+    commands.spawn((
+        ReplayBanner,
+        StateScoped(GameState::Playing),
+        TextBundle::from_section("Replay", fonts.bold(24., Color::WHITE)).with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(16.),
+            left: Val::Percent(50.),
+            ..default()
+        }),
+    ));
+}
@@ -0,0 +1,69 @@
+//! The seeded random number generator every gameplay system draws from.
+//!
+//! Keeping a single generator means a run is fully determined by its seed,
+//! as long as systems draw from it in a consistent order.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_SEED: u64 = 0x005E_ED0F_4A1B;
+
+/// A small PCG32 generator. Serializes with its position in the sequence,
+/// so a restored generator carries on where it was saved.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+pub struct GameRng {
+    seed: u64,
+    state: u64,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl GameRng {
+    const MULTIPLIER: u64 = 6364136223846793005;
+    const INCREMENT: u64 = 1442695040888963407;
+
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self { seed, state: 0 };
+        rng.reseed(seed);
+        rng
+    }
+
+    /// Restarts the sequence from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.state = seed.wrapping_add(Self::INCREMENT);
+        self.next_u32();
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(Self::INCREMENT);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Uniform in `[min, max)`.
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.f32()
+    }
+}
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRng>();
+    }
+}
@@ -0,0 +1,324 @@
+//! Snapshot and restore of an in-progress run.
+//!
+//! The run is written to storage whenever the app is backgrounded and at
+//! the start of every wave, and on the web also staged every
+//! [`STAGE_INTERVAL`] for [`ExitSave`] in case the tab is closed. A
+//! snapshot holds the player, the enemies, the score and wave progress,
+//! and the [`GameRng`], so the waves carry on as they would have. None is
+//! taken during a boss wave, which the boss isn't kept in, so the stored
+//! run is from before the fight and has it again.
+//!
+//! On the next launch a stored snapshot is offered as "Continue" on the
+//! main menu, and restored when `Playing` is entered; starting a new run
+//! instead discards it. A snapshot from an incompatible version, or one
+//! that no longer parses, is discarded rather than restored. A
+//! [daily run](crate::daily) is never stored.
+
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    window::{ApplicationLifetime, WindowFocused},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    daily,
+    death::StartNewRun,
+    enemy::{Enemy, EnemyKind, SpawnEnemy},
+    events::WaveStarted,
+    health::Health,
+    replay,
+    rng::GameRng,
+    score::Score,
+    spawner::{WaveConfig, WaveState},
+    state::GameState,
+    storage,
+    web::ExitSave,
+    Player,
+};
+
+const RUN_KEY: &str = "run";
+/// Seconds between two snapshots staged for [`ExitSave`].
+const STAGE_INTERVAL: f32 = 1.;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RunSnapshot {
+    pub version: u32,
+    pub player: PlayerSnapshot,
+    pub enemies: Vec<EnemySnapshot>,
+    pub points: u64,
+    pub wave: WaveSnapshot,
+    pub rng: GameRng,
+}
+
+impl RunSnapshot {
+    /// Bump whenever the layout changes; older snapshots are then dropped.
+    pub const VERSION: u32 = 2;
+
+    /// The stored snapshot, if there is a compatible one. Incompatible
+    /// snapshots are deleted.
+    pub fn load() -> Option<Self> {
+        let snapshot = storage::load::<RunSnapshot>(RUN_KEY);
+        match snapshot {
+            Some(snapshot) if snapshot.version == Self::VERSION => Some(snapshot),
+            _ => {
+                Self::discard();
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        storage::save(RUN_KEY, self);
+    }
+
+    pub fn discard() {
+        storage::remove(RUN_KEY);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct PlayerSnapshot {
+    pub position: Vec2,
+    pub health: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct EnemySnapshot {
+    pub position: Vec2,
+    pub kind: EnemyKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct WaveSnapshot {
+    pub wave: u32,
+    /// Budget of the wave still to spend on portals.
+    pub to_spawn: u32,
+    /// Whether the wave had started, rather than the lull before it.
+    pub started: bool,
+}
+
+/// A snapshot chosen to be restored the next time `Playing` is entered.
+#[derive(Resource, Debug)]
+struct PendingRestore(RunSnapshot);
+
+/// A snapshot found at launch, offered on the main menu until the player
+/// picks Continue or starts a new run.
+#[derive(Resource, Debug)]
+pub struct StoredRun(RunSnapshot);
+
+/// The main menu button that restores the [`StoredRun`].
+#[derive(Component)]
+pub struct ContinueButton;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(snapshot) = RunSnapshot::load() {
+            app.insert_resource(StoredRun(snapshot));
+        }
+
+        app.add_systems(
+            Update,
+            (
+                (snapshot_on_background, snapshot_on_wave, stage_snapshot)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(not(daily::is_daily)),
+                press_continue.run_if(resource_exists::<StoredRun>),
+                discard_on_new_run,
+            )
+                .run_if(not(replay::is_replaying)),
+        )
+        .add_systems(
+            OnEnter(GameState::GameOver),
+            discard_on_game_over.run_if(not(replay::is_replaying)),
+        )
+        .add_systems(
+            OnTransition {
+                from: GameState::MainMenu,
+                to: GameState::Playing,
+            },
+            discard_stored_run
+                .run_if(resource_exists::<StoredRun>)
+                .run_if(not(replay::is_replaying)),
+        )
+        .add_systems(
+            OnEnter(GameState::Playing),
+            restore_run
+                .run_if(resource_exists::<PendingRestore>)
+                .after(crate::spawn_player),
+        );
+    }
+}
+
+fn press_continue(
+    mut commands: Commands,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<ContinueButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !buttons.iter().any(|i| *i == Interaction::Pressed) {
+        return;
+    }
+    commands.add(|world: &mut World| {
+        if let Some(StoredRun(snapshot)) = world.remove_resource::<StoredRun>() {
+            world.insert_resource(PendingRestore(snapshot));
+        }
+    });
+    next_state.set(GameState::Playing);
+}
+
+/// Leaving the main menu without pressing Continue starts a new run.
+fn discard_stored_run(mut commands: Commands, exit_save: Res<ExitSave>) {
+    exit_save.unstage(RUN_KEY);
+    RunSnapshot::discard();
+    commands.remove_resource::<StoredRun>();
+}
+
+fn restore_run(
+    mut commands: Commands,
+    pending: Res<PendingRestore>,
+    config: Res<WaveConfig>,
+    mut score: ResMut<Score>,
+    mut waves: ResMut<WaveState>,
+    mut rng: ResMut<GameRng>,
+    mut players: Query<(&mut Transform, &mut Health), With<Player>>,
+    mut spawns: EventWriter<SpawnEnemy>,
+) {
+    let snapshot = &pending.0;
+    let wave = snapshot.wave;
+    *score = Score {
+        points: snapshot.points,
+        wave: wave.wave,
+        // a continued run starts without a combo going
+        ..default()
+    };
+    *waves = if wave.started {
+        WaveState {
+            wave: wave.wave,
+            to_spawn: wave.to_spawn,
+            timer: config.spawn_interval,
+            started: true,
+            // boss waves aren't stored
+            boss: false,
+        }
+    } else {
+        WaveState::before(wave.wave, &config)
+    };
+    *rng = snapshot.rng.clone();
+    if let Ok((mut transform, mut health)) = players.get_single_mut() {
+        transform.translation = snapshot.player.position.extend(transform.translation.z);
+        health.current = snapshot.player.health.min(health.max);
+    }
+    for enemy in &snapshot.enemies {
+        spawns.send(SpawnEnemy::at(enemy.position).with_kind(enemy.kind));
+    }
+    commands.remove_resource::<PendingRestore>();
+}
+
+/// Everything a snapshot is taken from.
+#[derive(SystemParam)]
+struct RunParams<'w, 's> {
+    score: Res<'w, Score>,
+    waves: Res<'w, WaveState>,
+    rng: Res<'w, GameRng>,
+    players: Query<'w, 's, (&'static Transform, &'static Health), With<Player>>,
+    enemies: Query<'w, 's, (&'static Transform, &'static EnemyKind), With<Enemy>>,
+}
+
+/// The run as it is now, unless the player is missing or dead or a boss
+/// is being fought.
+fn take_snapshot(run: &RunParams) -> Option<RunSnapshot> {
+    let (transform, health) = run.players.get_single().ok()?;
+    if health.is_dead() || (run.waves.started && run.waves.boss) {
+        return None;
+    }
+
+    Some(RunSnapshot {
+        version: RunSnapshot::VERSION,
+        player: PlayerSnapshot {
+            position: transform.translation.truncate(),
+            health: health.current,
+        },
+        enemies: run
+            .enemies
+            .iter()
+            .map(|(transform, kind)| EnemySnapshot {
+                position: transform.translation.truncate(),
+                kind: *kind,
+            })
+            .collect(),
+        points: run.score.points,
+        wave: WaveSnapshot {
+            wave: run.waves.wave,
+            to_spawn: run.waves.to_spawn,
+            started: run.waves.started,
+        },
+        rng: run.rng.clone(),
+    })
+}
+
+fn snapshot_on_background(
+    mut lifetime: EventReader<ApplicationLifetime>,
+    mut focus: EventReader<WindowFocused>,
+    run: RunParams,
+) {
+    let suspended = lifetime
+        .read()
+        .any(|event| matches!(event, ApplicationLifetime::Suspended));
+    let blurred = focus.read().any(|event| !event.focused);
+    if !suspended && !blurred {
+        return;
+    }
+
+    if let Some(snapshot) = take_snapshot(&run) {
+        snapshot.save();
+    }
+}
+
+/// Each new wave is a checkpoint, so a crash or a killed app loses at
+/// most one wave.
+fn snapshot_on_wave(mut waves: EventReader<WaveStarted>, run: RunParams) {
+    if waves.read().count() == 0 {
+        return;
+    }
+    if let Some(snapshot) = take_snapshot(&run) {
+        snapshot.save();
+    }
+}
+
+fn stage_snapshot(
+    time: Res<Time<Real>>,
+    exit_save: Res<ExitSave>,
+    mut since_stage: Local<f32>,
+    run: RunParams,
+) {
+    // staging is a no-op off the web, so skip building the snapshot too
+    if !cfg!(target_arch = "wasm32") {
+        return;
+    }
+    *since_stage += time.delta_seconds();
+    if *since_stage < STAGE_INTERVAL {
+        return;
+    }
+    *since_stage = 0.;
+    match take_snapshot(&run) {
+        Some(snapshot) => exit_save.stage(RUN_KEY, &snapshot),
+        None => exit_save.unstage(RUN_KEY),
+    }
+}
+
+/// A run that has ended can't be continued; dying in continue mode doesn't
+/// end the run, so only the game over screen discards the snapshot.
+fn discard_on_game_over(exit_save: Res<ExitSave>) {
+    exit_save.unstage(RUN_KEY);
+    RunSnapshot::discard();
+}
+
+/// Restarting or quitting from the pause menu ends the run too.
+fn discard_on_new_run(mut new_runs: EventReader<StartNewRun>, exit_save: Res<ExitSave>) {
+    if new_runs.read().count() > 0 {
+        exit_save.unstage(RUN_KEY);
+        RunSnapshot::discard();
+    }
+}
@@ -0,0 +1,145 @@
+//! The current run's score, combo and wave.
+//!
+//! Kills are worth [`ScoreConfig::kill_points`] times the combo multiplier,
+//! and staying alive earns [`ScoreConfig::survival_points`] every
+//! [`ScoreConfig::survival_interval`] seconds. Anything else can award
+//! points by sending [`AwardPoints`]. Each kill within
+//! [`ScoreConfig::combo_window`] of the last one adds to the combo, and the
+//! combo drops back to zero once the window passes without one.
+//!
+//! The best score is kept with the lifetime [`Stats`](crate::stats::Stats).
+//! All of it is shown on the [HUD](crate::hud).
+
+use bevy::prelude::*;
+
+use crate::{
+    events::{ComboChanged, EnemyKilled, WaveStarted},
+    state::GameState,
+};
+
+#[derive(Resource, Reflect, Debug, Clone, Default)]
+#[reflect(Resource)]
+pub struct Score {
+    pub points: u64,
+    /// The wave the run has reached, 0 before the first one starts.
+    pub wave: u32,
+    /// Kills in a row, each within the combo window of the last.
+    pub combo: u32,
+    /// Seconds until the combo drops.
+    pub combo_remaining: f32,
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct ScoreConfig {
+    /// Points for a kill before the combo multiplier.
+    pub kill_points: u64,
+    pub survival_points: u64,
+    /// Seconds of play between two survival awards.
+    pub survival_interval: f32,
+    /// Seconds after a kill for the next one to keep the combo going.
+    pub combo_window: f32,
+    /// Added to the multiplier by each kill of the combo after the first.
+    pub combo_step: f32,
+    pub max_multiplier: f32,
+}
+
+impl Default for ScoreConfig {
+    fn default() -> Self {
+        Self {
+            kill_points: 100,
+            survival_points: 10,
+            survival_interval: 1.,
+            combo_window: 2.,
+            combo_step: 0.1,
+            max_multiplier: 4.,
+        }
+    }
+}
+
+impl ScoreConfig {
+    /// What points are multiplied by at `combo` kills in a row.
+    pub fn multiplier(&self, combo: u32) -> f32 {
+        (1. + self.combo_step * combo.saturating_sub(1) as f32).clamp(1., self.max_multiplier)
+    }
+}
+
+/// Adds `points` to the score as they are.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct AwardPoints {
+    pub points: u64,
+}
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Score>()
+            .register_type::<ScoreConfig>()
+            .init_resource::<Score>()
+            .init_resource::<ScoreConfig>()
+            .add_event::<AwardPoints>()
+            .add_systems(
+                Update,
+                (
+                    (award_survival, decay_combo).run_if(in_state(GameState::Playing)),
+                    award_points,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn award_survival(
+    time: Res<Time>,
+    config: Res<ScoreConfig>,
+    mut survived: Local<f32>,
+    mut awards: EventWriter<AwardPoints>,
+) {
+    *survived += time.delta_seconds();
+    let interval = config.survival_interval.max(0.01);
+    while *survived >= interval {
+        *survived -= interval;
+        awards.send(AwardPoints {
+            points: config.survival_points,
+        });
+    }
+}
+
+fn decay_combo(time: Res<Time>, mut score: ResMut<Score>, mut combos: EventWriter<ComboChanged>) {
+    if score.combo == 0 {
+        return;
+    }
+    score.combo_remaining -= time.delta_seconds();
+    if score.combo_remaining <= 0. {
+        score.combo = 0;
+        combos.send(ComboChanged { combo: 0 });
+    }
+}
+
+pub fn award_points(
+    config: Res<ScoreConfig>,
+    mut score: ResMut<Score>,
+    mut killed: EventReader<EnemyKilled>,
+    mut awards: EventReader<AwardPoints>,
+    mut waves: EventReader<WaveStarted>,
+    mut combos: EventWriter<ComboChanged>,
+) {
+    let kills = killed.read().count() as u32;
+    if kills > 0 {
+        for _ in 0..kills {
+            score.combo += 1;
+            let points = config.kill_points as f32 * config.multiplier(score.combo);
+            score.points += points.round() as u64;
+        }
+        score.combo_remaining = config.combo_window;
+        combos.send(ComboChanged { combo: score.combo });
+    }
+    let points: u64 = awards.read().map(|award| award.points).sum();
+    if points > 0 {
+        score.points += points;
+    }
+    if let Some(event) = waves.read().last() {
+        score.wave = event.wave;
+    }
+}
@@ -0,0 +1,957 @@
+//! Player-facing settings and the screen used to change them.
+//!
+//! Every settings resource is stored together as one versioned
+//! [`SettingsData`]. It is loaded and validated before anything else reads
+//! the resources, and written back whenever one of them changes.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abilities::DashDirection,
+    accessibility::{self, ColorBlindMode},
+    audio::{self, AudioSettings},
+    bounds::EdgeMode,
+    calibration::{CalibrateSticks, StickCalibration},
+    camera::CameraMode,
+    death::DeathMode,
+    fonts::{UiFontFace, UiFonts},
+    i18n::{Language, Locale, LocalizedText},
+    layout::{self, ControlLayout, Mirrored, StickMode, StickSide},
+    quality::QualityPreset,
+    remap::{BindingWarnings, OpenBindings, StoredBinding},
+    skin::StickSkinSettings,
+    smoothing::StickSmoothing,
+    state::{GameState, StateScoped},
+    storage,
+};
+
+const SETTINGS_KEY: &str = "settings";
+
+#[derive(Resource, Reflect, Serialize, Deserialize, Debug, Clone)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    /// Keep firing normal shots at the weapon's cadence whenever there is a
+    /// valid aim direction, without holding the fire button. Still bound by
+    /// ammo, heat and cooldown, and never fires charged shots.
+    pub auto_fire: bool,
+    /// Leave out screen shake, freeze frames and other jarring motion, and
+    /// tone down flashes.
+    pub reduce_motion: bool,
+    /// Typeface of all UI text; see [`crate::fonts`].
+    pub font: UiFontFace,
+    /// Player, pickup and enemy colors; see [`crate::accessibility`].
+    pub color_blind: ColorBlindMode,
+    /// Size of all UI, as a multiple of the usual.
+    pub ui_scale: f32,
+    /// How far aim is turned towards the enemy it points nearest, `0..=1`;
+    /// see [`crate::accessibility`].
+    pub aim_assist: f32,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            auto_fire: false,
+            reduce_motion: false,
+            font: default(),
+            color_blind: default(),
+            ui_scale: 1.,
+            aim_assist: 0.,
+        }
+    }
+}
+
+impl AccessibilitySettings {
+    pub fn validate(&mut self) {
+        self.ui_scale = accessibility::clean_ui_scale(self.ui_scale);
+        self.aim_assist = accessibility::clean_aim_assist(self.aim_assist);
+    }
+}
+
+#[derive(Resource, Reflect, Serialize, Deserialize, Debug, Clone)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct ControlSettings {
+    /// Touch sticks or keyboard hints; see [`crate::layout`].
+    pub layout: ControlLayout,
+    pub dash_direction: DashDirection,
+    /// Move stick on the right and look stick on the left, with the HUD
+    /// mirrored to match.
+    pub swap_sticks: bool,
+    /// Vibrate on hits, dashes and new waves; see [`crate::haptics`].
+    pub haptics: bool,
+    /// Where the touch sticks sit; see [`crate::layout`].
+    pub stick_mode: StickMode,
+    /// Touch stick readings under this fraction of the stick's reach are
+    /// ignored.
+    pub touch_dead_zone: f32,
+    /// What touch stick readings are scaled by, so less of a push reaches
+    /// full speed above 1.
+    pub touch_sensitivity: f32,
+    /// Filtering of jittery touch stick input; see [`crate::smoothing`].
+    pub stick_smoothing: StickSmoothing,
+    /// Read every gamepad stick movement, drift included; see
+    /// [`crate::calibration`].
+    pub no_dead_zone: bool,
+    /// Where the gamepad sticks rest, taken off their readings without a
+    /// dead zone.
+    pub calibration: StickCalibration,
+    /// Actions rebound on the bindings screen; see [`crate::remap`].
+    #[reflect(ignore)]
+    pub bindings: Vec<StoredBinding>,
+}
+
+impl Default for ControlSettings {
+    fn default() -> Self {
+        Self {
+            layout: default(),
+            dash_direction: default(),
+            swap_sticks: false,
+            haptics: true,
+            stick_mode: default(),
+            touch_dead_zone: layout::DEFAULT_TOUCH_DEAD_ZONE,
+            touch_sensitivity: 1.,
+            stick_smoothing: default(),
+            no_dead_zone: false,
+            calibration: default(),
+            bindings: Vec::new(),
+        }
+    }
+}
+
+impl ControlSettings {
+    pub fn validate(&mut self) {
+        self.touch_dead_zone = layout::clean_touch_dead_zone(self.touch_dead_zone);
+        self.touch_sensitivity = layout::clean_touch_sensitivity(self.touch_sensitivity);
+        self.calibration.validate();
+    }
+}
+
+#[derive(Resource, Reflect, Serialize, Deserialize, Debug, Clone, Default)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct DebugSettings {
+    /// Log gameplay events and frame rate samples; see [`crate::telemetry`].
+    pub telemetry: bool,
+    /// Show the frame rate and entity counts; see [`crate::perf_overlay`].
+    pub performance_overlay: bool,
+}
+
+#[derive(Resource, Reflect, Serialize, Deserialize, Debug, Clone, Default)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct DisplaySettings {
+    /// Draw a faint line along the aim direction up to the weapon's range.
+    pub aim_line: bool,
+    /// Amount of cosmetic effects; see [`crate::quality`].
+    pub quality: QualityPreset,
+    pub camera_mode: CameraMode,
+    /// Zoom out when the fight around the player gets crowded; see
+    /// [`crate::camera`].
+    pub auto_zoom: bool,
+    /// Show a small map of what is around the player; see
+    /// [`crate::minimap`].
+    pub minimap: bool,
+    /// Language of UI text; see [`crate::i18n`].
+    pub language: Language,
+    /// Glow around bright colors; see [`crate::postfx`].
+    pub bloom: bool,
+    /// Darken the corners of the screen.
+    pub vignette: bool,
+    /// Scanlines and color fringes, like an old monitor.
+    pub crt: bool,
+}
+
+#[derive(Resource, Reflect, Serialize, Deserialize, Debug, Clone)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct GameplaySettings {
+    /// Pause when the window loses focus or the tab is hidden.
+    pub pause_on_blur: bool,
+    /// Whether dying ends the run or costs a penalty; see [`crate::death`].
+    pub death_mode: DeathMode,
+    /// Scale spawn rate and enemy speed with how well the player is doing;
+    /// see [`crate::difficulty`].
+    pub dynamic_difficulty: bool,
+    /// What happens when the player reaches the edge of the play area; see
+    /// [`crate::bounds`].
+    pub edge_mode: EdgeMode,
+    /// Build a new arena with obstacles for every run; see
+    /// [`crate::generation`].
+    pub generated_arena: bool,
+    /// Whether the controls tutorial has been finished; see
+    /// [`crate::tutorial`].
+    pub tutorial_complete: bool,
+}
+
+impl Default for GameplaySettings {
+    fn default() -> Self {
+        Self {
+            pause_on_blur: true,
+            death_mode: default(),
+            dynamic_difficulty: false,
+            edge_mode: default(),
+            generated_arena: false,
+            tutorial_complete: false,
+        }
+    }
+}
+
+/// The stored form of every settings resource.
+///
+/// Fields missing from an older file are filled with defaults by serde, so
+/// adding a setting doesn't need a new version. Bump [`Self::VERSION`] and
+/// add a step to [`Self::migrate`] only when an existing field changes
+/// meaning or shape.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SettingsData {
+    pub version: u32,
+    pub accessibility: AccessibilitySettings,
+    pub audio: AudioSettings,
+    pub controls: ControlSettings,
+    pub debug: DebugSettings,
+    pub display: DisplaySettings,
+    pub gameplay: GameplaySettings,
+    pub stick_skin: StickSkinSettings,
+}
+
+impl Default for SettingsData {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            accessibility: default(),
+            audio: default(),
+            controls: default(),
+            debug: default(),
+            display: default(),
+            gameplay: default(),
+            stick_skin: default(),
+        }
+    }
+}
+
+impl SettingsData {
+    pub const VERSION: u32 = 1;
+
+    /// Loads the stored settings, or defaults if there are none.
+    pub fn load() -> Self {
+        let mut data = storage::load::<SettingsData>(SETTINGS_KEY).unwrap_or_default();
+        data.migrate();
+        data.validate();
+        data
+    }
+
+    pub fn save(&self) {
+        storage::save(SETTINGS_KEY, self);
+    }
+
+    /// Brings data written by an older version up to [`Self::VERSION`].
+    pub fn migrate(&mut self) {
+        if self.version > Self::VERSION {
+            warn!(
+                "settings were written by a newer version ({}), reading what is understood",
+                self.version
+            );
+        }
+        self.version = Self::VERSION;
+    }
+
+    /// Clamps or resets values a corrupt or hand-edited file could set out
+    /// of range.
+    pub fn validate(&mut self) {
+        let [r, g, b, a] = self.stick_skin.tint.as_rgba_f32();
+        self.stick_skin.tint = Color::rgba(
+            r.clamp(0., 1.),
+            g.clamp(0., 1.),
+            b.clamp(0., 1.),
+            a.clamp(0., 1.),
+        );
+        self.stick_skin.skin.validate();
+        self.accessibility.validate();
+        self.controls.validate();
+        self.audio.validate();
+    }
+}
+
+/// Every settings resource.
+#[derive(SystemParam)]
+struct SettingsMut<'w> {
+    accessibility: ResMut<'w, AccessibilitySettings>,
+    audio: ResMut<'w, AudioSettings>,
+    controls: ResMut<'w, ControlSettings>,
+    debug: ResMut<'w, DebugSettings>,
+    display: ResMut<'w, DisplaySettings>,
+    gameplay: ResMut<'w, GameplaySettings>,
+    skin: ResMut<'w, StickSkinSettings>,
+}
+
+impl SettingsMut<'_> {
+    fn changed(&self) -> [(bool, bool); 7] {
+        [
+            (
+                self.accessibility.is_changed(),
+                self.accessibility.is_added(),
+            ),
+            (self.audio.is_changed(), self.audio.is_added()),
+            (self.controls.is_changed(), self.controls.is_added()),
+            (self.debug.is_changed(), self.debug.is_added()),
+            (self.display.is_changed(), self.display.is_added()),
+            (self.gameplay.is_changed(), self.gameplay.is_added()),
+            (self.skin.is_changed(), self.skin.is_added()),
+        ]
+    }
+
+    fn is_changed(&self) -> bool {
+        self.changed().iter().any(|(changed, _)| *changed)
+    }
+
+    /// Changed since the last check, other than by being inserted at startup.
+    fn is_modified(&self) -> bool {
+        self.changed()
+            .iter()
+            .any(|(changed, added)| *changed && !*added)
+    }
+
+    fn to_data(&self) -> SettingsData {
+        SettingsData {
+            version: SettingsData::VERSION,
+            accessibility: self.accessibility.clone(),
+            audio: self.audio.clone(),
+            controls: self.controls.clone(),
+            debug: self.debug.clone(),
+            display: self.display.clone(),
+            gameplay: self.gameplay.clone(),
+            stick_skin: self.skin.clone(),
+        }
+    }
+}
+
+/// A row on the settings screen; pressing its button steps its value.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingRow {
+    AutoFire,
+    ReduceMotion,
+    Font,
+    ColorBlind,
+    UiScale,
+    AimAssist,
+    Layout,
+    StickSkin,
+    DashDirection,
+    SwapSticks,
+    Haptics,
+    StickMode,
+    TouchDeadZone,
+    TouchSensitivity,
+    StickSmoothing,
+    DeadZone,
+    RecenterSticks,
+    Bindings,
+    AimLine,
+    Quality,
+    CameraMode,
+    AutoZoom,
+    Minimap,
+    Language,
+    Bloom,
+    Vignette,
+    Crt,
+    MusicVolume,
+    SfxVolume,
+    Mute,
+    PauseOnBlur,
+    DeathMode,
+    DynamicDifficulty,
+    EdgeMode,
+    GeneratedArena,
+    Tutorial,
+    Telemetry,
+    PerformanceOverlay,
+}
+
+impl SettingRow {
+    const ALL: [SettingRow; 38] = [
+        SettingRow::AutoFire,
+        SettingRow::ReduceMotion,
+        SettingRow::Font,
+        SettingRow::ColorBlind,
+        SettingRow::UiScale,
+        SettingRow::AimAssist,
+        SettingRow::Layout,
+        SettingRow::StickSkin,
+        SettingRow::DashDirection,
+        SettingRow::SwapSticks,
+        SettingRow::Haptics,
+        SettingRow::StickMode,
+        SettingRow::TouchDeadZone,
+        SettingRow::TouchSensitivity,
+        SettingRow::StickSmoothing,
+        SettingRow::DeadZone,
+        SettingRow::RecenterSticks,
+        SettingRow::Bindings,
+        SettingRow::AimLine,
+        SettingRow::Quality,
+        SettingRow::CameraMode,
+        SettingRow::AutoZoom,
+        SettingRow::Minimap,
+        SettingRow::Language,
+        SettingRow::Bloom,
+        SettingRow::Vignette,
+        SettingRow::Crt,
+        SettingRow::MusicVolume,
+        SettingRow::SfxVolume,
+        SettingRow::Mute,
+        SettingRow::PauseOnBlur,
+        SettingRow::DeathMode,
+        SettingRow::DynamicDifficulty,
+        SettingRow::EdgeMode,
+        SettingRow::GeneratedArena,
+        SettingRow::Tutorial,
+        SettingRow::Telemetry,
+        SettingRow::PerformanceOverlay,
+    ];
+
+    fn section(self) -> &'static str {
+        match self {
+            SettingRow::AutoFire
+            | SettingRow::ReduceMotion
+            | SettingRow::Font
+            | SettingRow::ColorBlind
+            | SettingRow::UiScale
+            | SettingRow::AimAssist => "Accessibility",
+            SettingRow::Layout
+            | SettingRow::StickSkin
+            | SettingRow::DashDirection
+            | SettingRow::SwapSticks
+            | SettingRow::Haptics
+            | SettingRow::StickMode
+            | SettingRow::TouchDeadZone
+            | SettingRow::TouchSensitivity
+            | SettingRow::StickSmoothing
+            | SettingRow::DeadZone
+            | SettingRow::RecenterSticks
+            | SettingRow::Bindings => "Controls",
+            SettingRow::AimLine
+            | SettingRow::Quality
+            | SettingRow::CameraMode
+            | SettingRow::AutoZoom
+            | SettingRow::Minimap
+            | SettingRow::Language
+            | SettingRow::Bloom
+            | SettingRow::Vignette
+            | SettingRow::Crt => "Display",
+            SettingRow::MusicVolume | SettingRow::SfxVolume | SettingRow::Mute => "Audio",
+            SettingRow::PauseOnBlur
+            | SettingRow::DeathMode
+            | SettingRow::DynamicDifficulty
+            | SettingRow::EdgeMode
+            | SettingRow::GeneratedArena
+            | SettingRow::Tutorial => "Gameplay",
+            SettingRow::Telemetry | SettingRow::PerformanceOverlay => "Debug",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SettingRow::AutoFire => "Auto-fire",
+            SettingRow::ReduceMotion => "Reduce motion",
+            SettingRow::Font => "Font",
+            SettingRow::ColorBlind => "Color-blind palette",
+            SettingRow::UiScale => "UI scale",
+            SettingRow::AimAssist => "Aim assist",
+            SettingRow::Layout => "Layout",
+            SettingRow::StickSkin => "Joystick skin",
+            SettingRow::DashDirection => "Dash direction",
+            SettingRow::SwapSticks => "Left-handed",
+            SettingRow::Haptics => "Vibration",
+            SettingRow::StickMode => "Touch stick",
+            SettingRow::TouchDeadZone => "Touch dead zone",
+            SettingRow::TouchSensitivity => "Touch sensitivity",
+            SettingRow::StickSmoothing => "Stick smoothing",
+            SettingRow::DeadZone => "Stick dead zone",
+            SettingRow::RecenterSticks => "Recenter sticks",
+            SettingRow::Bindings => "Bindings",
+            SettingRow::AimLine => "Aim line",
+            SettingRow::Quality => "Effects",
+            SettingRow::CameraMode => "Camera",
+            SettingRow::AutoZoom => "Auto zoom",
+            SettingRow::Minimap => "Minimap",
+            SettingRow::Language => "Language",
+            SettingRow::Bloom => "Bloom",
+            SettingRow::Vignette => "Vignette",
+            SettingRow::Crt => "CRT filter",
+            SettingRow::MusicVolume => "Music volume",
+            SettingRow::SfxVolume => "Sound volume",
+            SettingRow::Mute => "Mute",
+            SettingRow::PauseOnBlur => "Pause when unfocused",
+            SettingRow::DeathMode => "On death",
+            SettingRow::DynamicDifficulty => "Dynamic difficulty",
+            SettingRow::EdgeMode => "At the edge",
+            SettingRow::GeneratedArena => "Generated arena",
+            SettingRow::Tutorial => "Controls tutorial",
+            SettingRow::Telemetry => "Telemetry log",
+            SettingRow::PerformanceOverlay => "Performance overlay",
+        }
+    }
+
+    fn value(self, settings: &SettingsMut) -> String {
+        match self {
+            SettingRow::AutoFire => on_off(settings.accessibility.auto_fire).to_string(),
+            SettingRow::ReduceMotion => on_off(settings.accessibility.reduce_motion).to_string(),
+            SettingRow::Font => settings.accessibility.font.name().to_string(),
+            SettingRow::ColorBlind => settings.accessibility.color_blind.name().to_string(),
+            SettingRow::UiScale => percent(settings.accessibility.ui_scale),
+            SettingRow::AimAssist if settings.accessibility.aim_assist <= 0. => "Off".to_string(),
+            SettingRow::AimAssist => percent(settings.accessibility.aim_assist),
+            SettingRow::Layout => settings.controls.layout.name().to_string(),
+            SettingRow::StickSkin => settings.skin.skin.name().to_string(),
+            SettingRow::DashDirection => settings.controls.dash_direction.name().to_string(),
+            SettingRow::SwapSticks => on_off(settings.controls.swap_sticks).to_string(),
+            SettingRow::Haptics => on_off(settings.controls.haptics).to_string(),
+            SettingRow::StickMode => settings.controls.stick_mode.name().to_string(),
+            SettingRow::TouchDeadZone => percent(settings.controls.touch_dead_zone),
+            SettingRow::TouchSensitivity => format!("{:.2}x", settings.controls.touch_sensitivity),
+            SettingRow::StickSmoothing => settings.controls.stick_smoothing.name().to_string(),
+            SettingRow::DeadZone => on_off(!settings.controls.no_dead_zone).to_string(),
+            SettingRow::RecenterSticks => if settings.controls.calibration.is_set() {
+                "Recentered"
+            } else {
+                "Not set"
+            }
+            .to_string(),
+            SettingRow::Bindings => "Edit".to_string(),
+            SettingRow::AimLine => on_off(settings.display.aim_line).to_string(),
+            SettingRow::Quality => settings.display.quality.name().to_string(),
+            SettingRow::CameraMode => settings.display.camera_mode.name().to_string(),
+            SettingRow::AutoZoom => on_off(settings.display.auto_zoom).to_string(),
+            SettingRow::Minimap => on_off(settings.display.minimap).to_string(),
+            SettingRow::Language => settings.display.language.name().to_string(),
+            SettingRow::Bloom => on_off(settings.display.bloom).to_string(),
+            SettingRow::Vignette => on_off(settings.display.vignette).to_string(),
+            SettingRow::Crt => on_off(settings.display.crt).to_string(),
+            SettingRow::MusicVolume => percent(settings.audio.music_volume),
+            SettingRow::SfxVolume => percent(settings.audio.sfx_volume),
+            SettingRow::Mute => on_off(settings.audio.muted).to_string(),
+            SettingRow::PauseOnBlur => on_off(settings.gameplay.pause_on_blur).to_string(),
+            SettingRow::DeathMode => settings.gameplay.death_mode.name().to_string(),
+            SettingRow::DynamicDifficulty => {
+                on_off(settings.gameplay.dynamic_difficulty).to_string()
+            }
+            SettingRow::EdgeMode => settings.gameplay.edge_mode.name().to_string(),
+            SettingRow::GeneratedArena => on_off(settings.gameplay.generated_arena).to_string(),
+            SettingRow::Tutorial if settings.gameplay.tutorial_complete => "Done".to_string(),
+            SettingRow::Tutorial => "Next run".to_string(),
+            SettingRow::Telemetry => on_off(settings.debug.telemetry).to_string(),
+            SettingRow::PerformanceOverlay => {
+                on_off(settings.debug.performance_overlay).to_string()
+            }
+        }
+    }
+
+    fn activate(self, settings: &mut SettingsMut) {
+        match self {
+            SettingRow::AutoFire => {
+                settings.accessibility.auto_fire = !settings.accessibility.auto_fire;
+            }
+            SettingRow::ReduceMotion => {
+                settings.accessibility.reduce_motion = !settings.accessibility.reduce_motion;
+            }
+            SettingRow::Font => settings.accessibility.font = settings.accessibility.font.next(),
+            SettingRow::ColorBlind => {
+                settings.accessibility.color_blind = settings.accessibility.color_blind.next();
+            }
+            SettingRow::UiScale => {
+                settings.accessibility.ui_scale =
+                    accessibility::step_ui_scale(settings.accessibility.ui_scale);
+            }
+            SettingRow::AimAssist => {
+                settings.accessibility.aim_assist =
+                    accessibility::step_aim_assist(settings.accessibility.aim_assist);
+            }
+            SettingRow::Layout => settings.controls.layout = settings.controls.layout.next(),
+            SettingRow::StickSkin => settings.skin.skin = settings.skin.skin.next(),
+            SettingRow::DashDirection => {
+                settings.controls.dash_direction = settings.controls.dash_direction.next();
+            }
+            SettingRow::SwapSticks => {
+                settings.controls.swap_sticks = !settings.controls.swap_sticks;
+            }
+            SettingRow::Haptics => settings.controls.haptics = !settings.controls.haptics,
+            SettingRow::StickMode => {
+                settings.controls.stick_mode = settings.controls.stick_mode.next();
+            }
+            SettingRow::TouchDeadZone => {
+                settings.controls.touch_dead_zone =
+                    layout::step_touch_dead_zone(settings.controls.touch_dead_zone);
+            }
+            SettingRow::TouchSensitivity => {
+                settings.controls.touch_sensitivity =
+                    layout::step_touch_sensitivity(settings.controls.touch_sensitivity);
+            }
+            SettingRow::StickSmoothing => {
+                settings.controls.stick_smoothing = settings.controls.stick_smoothing.next();
+            }
+            SettingRow::DeadZone => {
+                settings.controls.no_dead_zone = !settings.controls.no_dead_zone;
+            }
+            // sampled over time by crate::calibration, started in press_rows
+            SettingRow::RecenterSticks => {}
+            // a screen of its own in crate::remap, opened in press_rows
+            SettingRow::Bindings => {}
+            SettingRow::AimLine => settings.display.aim_line = !settings.display.aim_line,
+            SettingRow::Quality => settings.display.quality = settings.display.quality.next(),
+            SettingRow::CameraMode => {
+                settings.display.camera_mode = settings.display.camera_mode.next();
+            }
+            SettingRow::AutoZoom => settings.display.auto_zoom = !settings.display.auto_zoom,
+            SettingRow::Minimap => settings.display.minimap = !settings.display.minimap,
+            SettingRow::Language => settings.display.language = settings.display.language.next(),
+            SettingRow::Bloom => settings.display.bloom = !settings.display.bloom,
+            SettingRow::Vignette => settings.display.vignette = !settings.display.vignette,
+            SettingRow::Crt => settings.display.crt = !settings.display.crt,
+            SettingRow::MusicVolume => {
+                settings.audio.music_volume = audio::step_volume(settings.audio.music_volume);
+            }
+            SettingRow::SfxVolume => {
+                settings.audio.sfx_volume = audio::step_volume(settings.audio.sfx_volume);
+            }
+            SettingRow::Mute => settings.audio.muted = !settings.audio.muted,
+            SettingRow::PauseOnBlur => {
+                settings.gameplay.pause_on_blur = !settings.gameplay.pause_on_blur;
+            }
+            SettingRow::DeathMode => {
+                settings.gameplay.death_mode = settings.gameplay.death_mode.next();
+            }
+            SettingRow::DynamicDifficulty => {
+                settings.gameplay.dynamic_difficulty = !settings.gameplay.dynamic_difficulty;
+            }
+            SettingRow::EdgeMode => {
+                settings.gameplay.edge_mode = settings.gameplay.edge_mode.next();
+            }
+            SettingRow::GeneratedArena => {
+                settings.gameplay.generated_arena = !settings.gameplay.generated_arena;
+            }
+            SettingRow::Tutorial => {
+                settings.gameplay.tutorial_complete = !settings.gameplay.tutorial_complete;
+            }
+            SettingRow::Telemetry => settings.debug.telemetry = !settings.debug.telemetry,
+            SettingRow::PerformanceOverlay => {
+                settings.debug.performance_overlay = !settings.debug.performance_overlay;
+            }
+        }
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let data = SettingsData::load();
+
+        app.register_type::<AccessibilitySettings>()
+            .register_type::<ControlSettings>()
+            .register_type::<DebugSettings>()
+            .register_type::<DisplaySettings>()
+            .register_type::<GameplaySettings>()
+            .insert_resource(data.accessibility)
+            .insert_resource(data.audio)
+            .insert_resource(data.controls)
+            .insert_resource(data.debug)
+            .insert_resource(data.display)
+            .insert_resource(data.gameplay)
+            .insert_resource(data.stick_skin)
+            .init_resource::<SettingsReturn>()
+            .add_systems(Startup, spawn_settings_button)
+            .add_systems(PostUpdate, save_settings)
+            .add_systems(
+                Update,
+                (
+                    open_settings.run_if(in_state(GameState::Playing)),
+                    (press_rows, update_row_values, close_settings)
+                        .chain()
+                        .run_if(in_state(GameState::Settings)),
+                ),
+            )
+            .add_systems(OnEnter(GameState::Settings), spawn_settings_screen);
+    }
+}
+
+/// Settings are written as soon as they change, so there is nothing left to
+/// stage for [`ExitSave`](crate::web::ExitSave) when the page closes.
+fn save_settings(settings: SettingsMut) {
+    if settings.is_modified() {
+        settings.to_data().save();
+    }
+}
+
+/// The state closing the settings screen goes back to.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct SettingsReturn(pub GameState);
+
+impl Default for SettingsReturn {
+    fn default() -> Self {
+        Self(GameState::Playing)
+    }
+}
+
+#[derive(Component)]
+struct OpenSettingsButton;
+
+#[derive(Component)]
+struct CloseSettingsButton;
+
+/// Text showing the current value of a [`SettingRow`].
+#[derive(Component)]
+struct RowValue(SettingRow);
+
+fn text(fonts: &UiFonts, value: impl Into<String>, font_size: f32) -> TextBundle {
+    TextBundle::from_section(value, fonts.style(font_size, Color::WHITE))
+}
+
+fn heading(fonts: &UiFonts, value: impl Into<String>, font_size: f32) -> TextBundle {
+    TextBundle::from_section(value, fonts.bold(font_size, Color::WHITE))
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+fn percent(value: f32) -> String {
+    format!("{:.0}%", value * 100.)
+}
+
+fn spawn_settings_button(mut commands: Commands, fonts: Res<UiFonts>) {
+    commands
+        .spawn((
+            OpenSettingsButton,
+            Mirrored::new(StickSide::Look, 90.),
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(12.),
+                    padding: UiRect::axes(Val::Px(10.), Val::Px(6.)),
+                    ..default()
+                },
+                background_color: Color::rgba(1., 1., 1., 0.15).into(),
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                LocalizedText::new("Settings"),
+                text(&fonts, "Settings", 20.),
+            ));
+        });
+}
+
+fn open_settings(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<OpenSettingsButton>)>,
+    mut settings_return: ResMut<SettingsReturn>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if buttons.iter().any(|i| *i == Interaction::Pressed) {
+        settings_return.0 = GameState::Playing;
+        next_state.set(GameState::Settings);
+    }
+}
+
+fn close_settings(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<CloseSettingsButton>)>,
+    settings_return: Res<SettingsReturn>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if buttons.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.set(settings_return.0);
+    }
+}
+
+fn spawn_settings_screen(
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+    settings: SettingsMut,
+) {
+    commands
+        .spawn((
+            StateScoped(GameState::Settings),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.85).into(),
+                z_index: ZIndex::Global(20),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                LocalizedText::new("Settings"),
+                heading(&fonts, "Settings", 32.),
+            ));
+
+            let mut section = "";
+            for row in SettingRow::ALL {
+                if row.section() != section {
+                    section = row.section();
+                    parent.spawn((LocalizedText::new(section), heading(&fonts, section, 24.)));
+                }
+
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(320.),
+                            justify_content: JustifyContent::SpaceBetween,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn((
+                            LocalizedText::new(row.label()),
+                            text(&fonts, row.label(), 20.),
+                        ));
+                        parent
+                            .spawn((
+                                row,
+                                ButtonBundle {
+                                    style: Style {
+                                        min_width: Val::Px(96.),
+                                        padding: UiRect::axes(Val::Px(8.), Val::Px(6.)),
+                                        justify_content: JustifyContent::Center,
+                                        ..default()
+                                    },
+                                    background_color: Color::rgba(1., 1., 1., 0.15).into(),
+                                    ..default()
+                                },
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    RowValue(row),
+                                    text(&fonts, locale.tr(&row.value(&settings)), 20.),
+                                ));
+                            });
+                    });
+            }
+
+            parent.spawn((
+                BindingWarnings,
+                TextBundle::from_section("", fonts.style(16., Color::YELLOW)),
+            ));
+
+            parent
+                .spawn((
+                    CloseSettingsButton,
+                    ButtonBundle {
+                        style: Style {
+                            margin: UiRect::top(Val::Px(12.)),
+                            padding: UiRect::axes(Val::Px(16.), Val::Px(8.)),
+                            ..default()
+                        },
+                        background_color: Color::rgba(1., 1., 1., 0.15).into(),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn((LocalizedText::new("Back"), text(&fonts, "Back", 20.)));
+                });
+        });
+}
+
+fn press_rows(
+    rows: Query<(&Interaction, &SettingRow), Changed<Interaction>>,
+    mut settings: SettingsMut,
+    mut calibrate: EventWriter<CalibrateSticks>,
+    mut bindings: EventWriter<OpenBindings>,
+) {
+    for (interaction, row) in &rows {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match row {
+            SettingRow::RecenterSticks => {
+                calibrate.send(CalibrateSticks);
+            }
+            SettingRow::Bindings => {
+                bindings.send(OpenBindings);
+            }
+            _ => row.activate(&mut settings),
+        }
+    }
+}
+
+fn update_row_values(
+    settings: SettingsMut,
+    locale: Res<Locale>,
+    mut values: Query<(&mut Text, &RowValue)>,
+) {
+    if !settings.is_changed() && !locale.is_changed() {
+        return;
+    }
+    for (mut text, RowValue(row)) in &mut values {
+        text.sections[0].value = locale.tr(&row.value(&settings)).to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skin::StickSkin;
+
+    /// What a settings file looked like when settings were first versioned.
+    const VERSION_1: &str = "(version:1,accessibility:(auto_fire:true),\
+        display:(aim_line:true),stick_skin:(skin:Custom(knob:\"\",outline:\"\"),\
+        tint:Rgba(red:1.5,green:0.5,blue:0.25,alpha:1.0)))";
+
+    fn read(raw: &str) -> SettingsData {
+        let mut data: SettingsData = ron::from_str(raw).unwrap();
+        data.migrate();
+        data.validate();
+        data
+    }
+
+    #[test]
+    fn version_1_settings_load() {
+        let data = read(VERSION_1);
+        assert_eq!(data.version, SettingsData::VERSION);
+        assert!(data.accessibility.auto_fire);
+        assert!(data.display.aim_line);
+        // out of range or broken in the file
+        assert_eq!(data.stick_skin.tint, Color::rgba(1., 0.5, 0.25, 1.));
+        assert_eq!(data.stick_skin.skin, StickSkin::Classic);
+    }
+
+    #[test]
+    fn missing_settings_take_their_defaults() {
+        let data = read("(version:1,display:(aim_line:true))");
+        let defaults = SettingsData::default();
+        assert!(data.display.aim_line);
+        assert_eq!(
+            data.accessibility.auto_fire,
+            defaults.accessibility.auto_fire
+        );
+        assert_eq!(data.stick_skin.tint, defaults.stick_skin.tint);
+    }
+
+    #[test]
+    fn newer_settings_are_read_as_the_current_version() {
+        let data = read("(version:99,display:(aim_line:true),unheard_of:(setting:1))");
+        assert_eq!(data.version, SettingsData::VERSION);
+        assert!(data.display.aim_line);
+    }
+}
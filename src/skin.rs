@@ -0,0 +1,120 @@
+//! Swappable touch stick images.
+
+use bevy::prelude::*;
+use bevy_touch_stick::{TouchStickUiKnob, TouchStickUiOutline};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum StickSkin {
+    Classic,
+    Solid,
+    Square,
+    /// User-provided asset paths.
+    Custom {
+        knob: String,
+        outline: String,
+    },
+}
+
+impl StickSkin {
+    /// Bundled skins, in the order the settings screen cycles through them.
+    pub const BUNDLED: [StickSkin; 3] = [StickSkin::Classic, StickSkin::Solid, StickSkin::Square];
+
+    pub fn name(&self) -> &str {
+        match self {
+            StickSkin::Classic => "Classic",
+            StickSkin::Solid => "Solid",
+            StickSkin::Square => "Square",
+            StickSkin::Custom { .. } => "Custom",
+        }
+    }
+
+    pub fn knob_path(&self) -> &str {
+        match self {
+            StickSkin::Classic => "knob.png",
+            StickSkin::Solid => "skins/solid_knob.png",
+            StickSkin::Square => "skins/square_knob.png",
+            StickSkin::Custom { knob, .. } => knob,
+        }
+    }
+
+    pub fn outline_path(&self) -> &str {
+        match self {
+            StickSkin::Classic => "outline.png",
+            StickSkin::Solid => "skins/solid_outline.png",
+            StickSkin::Square => "skins/square_outline.png",
+            StickSkin::Custom { outline, .. } => outline,
+        }
+    }
+
+    /// Falls back to the classic skin if a custom skin has no paths.
+    pub fn validate(&mut self) {
+        if let StickSkin::Custom { knob, outline } = self {
+            if knob.is_empty() || outline.is_empty() {
+                *self = StickSkin::Classic;
+            }
+        }
+    }
+
+    /// The bundled skin after this one; custom skins cycle back to the first.
+    pub fn next(&self) -> StickSkin {
+        let index = Self::BUNDLED.iter().position(|skin| skin == self);
+        match index {
+            Some(index) => Self::BUNDLED[(index + 1) % Self::BUNDLED.len()].clone(),
+            None => Self::BUNDLED[0].clone(),
+        }
+    }
+}
+
+/// The skin the touch sticks are drawn with. Read when the sticks are
+/// spawned and re-applied to the existing sticks whenever it changes.
+#[derive(Resource, Reflect, Serialize, Deserialize, Debug, Clone)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct StickSkinSettings {
+    pub skin: StickSkin,
+    /// Multiplied with the skin images; white keeps their own colors.
+    pub tint: Color,
+}
+
+impl Default for StickSkinSettings {
+    fn default() -> Self {
+        Self {
+            skin: StickSkin::Classic,
+            tint: Color::WHITE,
+        }
+    }
+}
+
+pub struct SkinPlugin;
+
+impl Plugin for SkinPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<StickSkinSettings>().add_systems(
+            Update,
+            apply_stick_skin.run_if(resource_changed::<StickSkinSettings>),
+        );
+    }
+}
+
+type KnobImage = (With<TouchStickUiKnob>, Without<TouchStickUiOutline>);
+type OutlineImage = (With<TouchStickUiOutline>, Without<TouchStickUiKnob>);
+
+fn apply_stick_skin(
+    settings: Res<StickSkinSettings>,
+    asset_server: Res<AssetServer>,
+    mut knobs: Query<(&mut UiImage, &mut BackgroundColor), KnobImage>,
+    mut outlines: Query<(&mut UiImage, &mut BackgroundColor), OutlineImage>,
+) {
+    let knob = asset_server.load(settings.skin.knob_path().to_string());
+    let outline = asset_server.load(settings.skin.outline_path().to_string());
+
+    for (mut image, mut color) in &mut knobs {
+        image.texture = knob.clone();
+        *color = settings.tint.into();
+    }
+    for (mut image, mut color) in &mut outlines {
+        image.texture = outline.clone();
+        *color = settings.tint.into();
+    }
+}
@@ -0,0 +1,186 @@
+//! Low-pass filtering of the touch sticks.
+//!
+//! Fingers jitter, and the raw touch stick axes twitch the player's facing
+//! and movement with them. While the [`ActiveInputDevice`] is touch, the
+//! move and look axes are eased toward their raw value with the half-life
+//! of [`ControlSettings::stick_smoothing`], after leafwing has applied the
+//! dead zone and before gameplay reads them. Releasing a stick still stops
+//! at once: a zero axis is passed through unfiltered.
+//!
+//! Touch axes are scaled by [`ControlSettings::touch_sensitivity`] first,
+//! up to full length, so the filter eases toward what gameplay should
+//! see.
+
+use bevy::prelude::*;
+use leafwing_input_manager::{axislike::DualAxisData, plugin::InputManagerSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input_device::{ActiveInputDevice, InputDevice},
+    settings::ControlSettings,
+    tween::approach,
+    Action, Player,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum StickSmoothing {
+    Off,
+    #[default]
+    Low,
+    Medium,
+    High,
+}
+
+impl StickSmoothing {
+    pub fn name(self) -> &'static str {
+        match self {
+            StickSmoothing::Off => "Off",
+            StickSmoothing::Low => "Low",
+            StickSmoothing::Medium => "Medium",
+            StickSmoothing::High => "High",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            StickSmoothing::Off => StickSmoothing::Low,
+            StickSmoothing::Low => StickSmoothing::Medium,
+            StickSmoothing::Medium => StickSmoothing::High,
+            StickSmoothing::High => StickSmoothing::Off,
+        }
+    }
+
+    /// Seconds for the filtered axis to close half the gap to the raw one.
+    pub fn half_life(self) -> f32 {
+        match self {
+            StickSmoothing::Off => 0.,
+            StickSmoothing::Low => 0.015,
+            StickSmoothing::Medium => 0.03,
+            StickSmoothing::High => 0.06,
+        }
+    }
+}
+
+/// `previous` filtered axis value moved toward `raw` over `dt` seconds.
+pub fn smooth_axis(previous: Vec2, raw: Vec2, half_life: f32, dt: f32) -> Vec2 {
+    if raw == Vec2::ZERO {
+        return raw;
+    }
+    approach(previous, raw, half_life, dt)
+}
+
+const SMOOTHED: [Action; 2] = [Action::Move, Action::Look];
+
+pub struct SmoothingPlugin;
+
+impl Plugin for SmoothingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, smooth_sticks.after(InputManagerSystem::Update));
+    }
+}
+
+pub fn smooth_sticks(
+    time: Res<Time<Real>>,
+    settings: Res<ControlSettings>,
+    device: Res<ActiveInputDevice>,
+    mut filtered: Local<[Vec2; 2]>,
+    mut players: Query<&mut ActionState<Action>, With<Player>>,
+) {
+    let Ok(mut action_state) = players.get_single_mut() else {
+        return;
+    };
+    let (half_life, sensitivity) = match device.device {
+        InputDevice::Touch => (
+            settings.stick_smoothing.half_life(),
+            settings.touch_sensitivity,
+        ),
+        _ => (0., 1.),
+    };
+
+    for (action, filtered) in SMOOTHED.iter().zip(filtered.iter_mut()) {
+        let Some(data) = action_state.action_data_mut(action) else {
+            continue;
+        };
+        let read = data.axis_pair.map_or(Vec2::ZERO, |axis| axis.xy());
+        let raw = (read * sensitivity).clamp_length_max(1.);
+        *filtered = smooth_axis(*filtered, raw, half_life, time.delta_seconds());
+        if *filtered != read {
+            data.axis_pair = Some(DualAxisData::from_xy(*filtered));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1. / 60.;
+
+    fn variance(values: &[Vec2]) -> f32 {
+        let mean = values.iter().sum::<Vec2>() / values.len() as f32;
+        values
+            .iter()
+            .map(|value| value.distance_squared(mean))
+            .sum::<f32>()
+            / values.len() as f32
+    }
+
+    #[test]
+    fn smoothing_reduces_jitter() {
+        let held = Vec2::new(0.6, -0.3);
+        // a thumb held still, wobbling a little each frame
+        let raw: Vec<Vec2> = (0..240)
+            .map(|frame| {
+                let frame = frame as f32;
+                held + Vec2::new((frame * 2.1).sin(), (frame * 3.7).cos()) * 0.1
+            })
+            .collect();
+        for smoothing in [
+            StickSmoothing::Low,
+            StickSmoothing::Medium,
+            StickSmoothing::High,
+        ] {
+            let mut filtered = Vec::new();
+            let mut value = Vec2::ZERO;
+            for raw in &raw {
+                value = smooth_axis(value, *raw, smoothing.half_life(), DT);
+                filtered.push(value);
+            }
+            // past the first half second of catching up
+            let settled = &filtered[30..];
+            assert!(
+                variance(settled) < variance(&raw[30..]) * 0.8,
+                "{smoothing:?} didn't smooth"
+            );
+            let mean = settled.iter().sum::<Vec2>() / settled.len() as f32;
+            assert!(
+                mean.distance(held) < 0.02,
+                "{smoothing:?} drifted to {mean}"
+            );
+        }
+    }
+
+    #[test]
+    fn a_steady_stick_is_kept() {
+        let held = Vec2::new(-0.2, 0.9);
+        let mut value = Vec2::ZERO;
+        for _ in 0..120 {
+            value = smooth_axis(value, held, StickSmoothing::High.half_life(), DT);
+        }
+        assert!(value.distance(held) < 1e-4, "settled at {value}");
+        // once there it stays there
+        assert_eq!(
+            smooth_axis(held, held, StickSmoothing::High.half_life(), DT),
+            held
+        );
+        // releasing stops at once, and Off follows the raw axis
+        assert_eq!(
+            smooth_axis(held, Vec2::ZERO, StickSmoothing::High.half_life(), DT),
+            Vec2::ZERO
+        );
+        assert_eq!(
+            smooth_axis(Vec2::ZERO, held, StickSmoothing::Off.half_life(), DT),
+            held
+        );
+    }
+}
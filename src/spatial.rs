@@ -0,0 +1,121 @@
+//! Uniform grid for proximity queries.
+//!
+//! Systems that need to know what is near something keep the positions in a
+//! [`SpatialGrid`], rebuilt once a tick, and ask it with
+//! [`SpatialGrid::nearby_in_radius`] instead of testing every pair. The
+//! collision pass has its own in [`ColliderGrid`](crate::physics::ColliderGrid),
+//! enemies are in [`EnemyGrid`](crate::targeting::EnemyGrid) for targeting,
+//! danger and separation, and pickups in
+//! [`PickupGrid`](crate::pickups::PickupGrid) for the magnet.
+
+use bevy::{prelude::*, utils::HashMap};
+
+#[derive(Debug, Clone)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<(Entity, Vec2)>>,
+    /// Bounds of all occupied cells, used to stop ring searches.
+    min_cell: IVec2,
+    max_cell: IVec2,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+            min_cell: IVec2::MAX,
+            max_cell: IVec2::MIN,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        // keep the cell allocations around, the grid is rebuilt every tick
+        for entries in self.cells.values_mut() {
+            entries.clear();
+        }
+        self.min_cell = IVec2::MAX;
+        self.max_cell = IVec2::MIN;
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2) {
+        let cell = self.cell(position);
+        self.min_cell = self.min_cell.min(cell);
+        self.max_cell = self.max_cell.max(cell);
+        self.cells.entry(cell).or_default().push((entity, position));
+    }
+
+    fn cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    fn cell_entries(&self, cell: IVec2) -> &[(Entity, Vec2)] {
+        self.cells.get(&cell).map_or(&[], Vec::as_slice)
+    }
+
+    /// Entries in every cell overlapping the square of `half_extent` around
+    /// `position`. Candidates are not distance-tested.
+    pub fn query_cells(
+        &self,
+        position: Vec2,
+        half_extent: f32,
+    ) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        let min = self.cell(position - Vec2::splat(half_extent));
+        let max = self.cell(position + Vec2::splat(half_extent));
+        (min.y..=max.y)
+            .flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+            .flat_map(|cell| self.cell_entries(cell).iter().copied())
+    }
+
+    /// All entries within `radius` of `position`.
+    pub fn nearby_in_radius(
+        &self,
+        position: Vec2,
+        radius: f32,
+    ) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        let radius_squared = radius * radius;
+        self.query_cells(position, radius)
+            .filter(move |(_, other)| other.distance_squared(position) <= radius_squared)
+    }
+
+    /// The entry closest to `position`, searching outward ring by ring.
+    pub fn nearest(&self, position: Vec2) -> Option<(Entity, Vec2)> {
+        if self.min_cell.x > self.max_cell.x {
+            return None;
+        }
+
+        let center = self.cell(position);
+        let max_ring = (center - self.min_cell)
+            .abs()
+            .max((self.max_cell - center).abs())
+            .max_element();
+
+        let mut best: Option<(Entity, Vec2, f32)> = None;
+        for ring in 0..=max_ring {
+            for cell in ring_cells(center, ring) {
+                for &(entity, other) in self.cell_entries(cell) {
+                    let distance = other.distance(position);
+                    if best.is_none_or(|(_, _, d)| distance < d) {
+                        best = Some((entity, other, distance));
+                    }
+                }
+            }
+            // anything in the next ring is at least `ring` cells away
+            if let Some((_, _, distance)) = best {
+                if distance <= ring as f32 * self.cell_size {
+                    break;
+                }
+            }
+        }
+        best.map(|(entity, other, _)| (entity, other))
+    }
+}
+
+/// Cells at exactly Chebyshev distance `ring` from `center`.
+fn ring_cells(center: IVec2, ring: i32) -> impl Iterator<Item = IVec2> {
+    (-ring..=ring).flat_map(move |y| {
+        (-ring..=ring)
+            .filter(move |x| x.abs() == ring || y.abs() == ring)
+            .map(move |x| center + IVec2::new(x, y))
+    })
+}
@@ -0,0 +1,259 @@
+//! Waves of enemies.
+//!
+//! A run is a series of waves, each with a bigger budget than the last.
+//! After a [`WaveConfig::break_time`] lull a wave starts with
+//! [`WaveStarted`], and its enemies come out of portals just inside the
+//! edges of the screen, or at the level's [`SpawnPoint`]s if it has any,
+//! one pack every [`WaveConfig::spawn_interval`]
+//! seconds, sped up by [`DifficultyScale::spawn_rate`] and at night by
+//! [`DayNightConfig::spawn_rate`]. What each pack is made of is up to
+//! [the director](crate::director), and packs grow every
+//! [`WaveConfig::pack_growth`] waves. Once the whole budget has been spent
+//! and the field is clear, the wave ends with [`WaveCleared`] and the next
+//! lull begins. A boss wave spends nothing and sends [`SpawnBoss`]
+//! instead, and is cleared the same way once the boss is dead.
+//!
+//! The wave number lives in [`Score`]. When it goes back, on a new run or a
+//! death penalty, the wave in progress is dropped and the next one starts
+//! from there after a lull.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    boss::SpawnBoss,
+    bounds::grow,
+    camera::{visible_rect, MainCamera},
+    config::GameConfig,
+    daynight::{DayNightConfig, TimeOfDay},
+    difficulty::DifficultyScale,
+    director::DirectorConfig,
+    enemy::{Enemy, Forming, ENEMY_SIZE},
+    events::{WaveCleared, WaveStarted},
+    level::SpawnPoint,
+    net::{is_client, NetRole, NetSession},
+    portal::{PortalConfig, PortalSpawn},
+    rng::GameRng,
+    score::Score,
+    state::GameState,
+};
+
+#[derive(Resource, Reflect, Serialize, Deserialize, Debug, Clone)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct WaveConfig {
+    /// Budget of the first wave, in grunts.
+    pub first_wave: u32,
+    /// Budget added by each wave after it.
+    pub per_wave: u32,
+    /// Seconds between two portals opening within a wave.
+    pub spawn_interval: f32,
+    /// Seconds of calm before each wave.
+    pub break_time: f32,
+    /// Waves between packs growing by one enemy.
+    pub pack_growth: u32,
+    /// How far inside the screen edge portals open.
+    pub edge_inset: f32,
+}
+
+impl Default for WaveConfig {
+    fn default() -> Self {
+        Self {
+            first_wave: 5,
+            per_wave: 3,
+            spawn_interval: 1.5,
+            break_time: 3.,
+            pack_growth: 3,
+            edge_inset: 40.,
+        }
+    }
+}
+
+impl WaveConfig {
+    /// Budget of `wave`, counting from 1.
+    pub fn wave_size(&self, wave: u32) -> u32 {
+        self.first_wave + self.per_wave * wave.saturating_sub(1)
+    }
+
+    /// Enemies per portal in `wave`, on top of a base pack of `base`.
+    pub fn pack_size(&self, wave: u32, base: u32) -> u32 {
+        base.max(1) + wave.saturating_sub(1) / self.pack_growth.max(1)
+    }
+}
+
+/// A point `inset` inside the edge of `view`, `t` of the way around it
+/// from the bottom left corner, counter-clockwise.
+pub fn edge_point(view: Rect, inset: f32, t: f32) -> Vec2 {
+    let inner = grow(view, -inset);
+    let size = inner.size();
+    let mut along = t.rem_euclid(1.) * 2. * (size.x + size.y);
+    let corners = [
+        (inner.min, Vec2::X, size.x),
+        (Vec2::new(inner.max.x, inner.min.y), Vec2::Y, size.y),
+        (inner.max, Vec2::NEG_X, size.x),
+        (Vec2::new(inner.min.x, inner.max.y), Vec2::NEG_Y, size.y),
+    ];
+    for (start, direction, length) in corners {
+        if along <= length {
+            return start + direction * along;
+        }
+        along -= length;
+    }
+    inner.min
+}
+
+/// Progress of the current wave.
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct WaveState {
+    /// The wave being spawned or fought, 0 before the first.
+    pub wave: u32,
+    /// Budget of the wave still to spend on portals.
+    pub to_spawn: u32,
+    /// Seconds until the next portal, or until the wave starts.
+    pub timer: f32,
+    /// Whether the wave has started, rather than the lull before it.
+    pub started: bool,
+    /// Whether the wave is a boss fight.
+    pub boss: bool,
+}
+
+impl Default for WaveState {
+    fn default() -> Self {
+        Self::before(0, &WaveConfig::default())
+    }
+}
+
+impl WaveState {
+    /// The lull before the wave after `wave`.
+    pub fn before(wave: u32, config: &WaveConfig) -> Self {
+        Self {
+            wave,
+            to_spawn: 0,
+            timer: config.break_time,
+            started: false,
+            boss: false,
+        }
+    }
+}
+
+pub struct SpawnerPlugin;
+
+impl Plugin for SpawnerPlugin {
+    fn build(&self, app: &mut App) {
+        let config = WaveConfig::default();
+        app.register_type::<WaveConfig>()
+            .register_type::<WaveState>()
+            .insert_resource(WaveState::before(0, &config))
+            .insert_resource(config)
+            .add_systems(
+                Update,
+                (follow_score, run_waves)
+                    .chain()
+                    .run_if(in_state(GameState::Playing))
+                    // a co-op client gets its enemies from the host
+                    .run_if(not(is_client)),
+            );
+    }
+}
+
+/// Drops the wave in progress when the score's wave goes back.
+fn follow_score(score: Res<Score>, config: Res<WaveConfig>, mut state: ResMut<WaveState>) {
+    if score.is_changed() && score.wave < state.wave {
+        *state = WaveState::before(score.wave, &config);
+    }
+}
+
+/// What running the waves sends.
+#[derive(SystemParam)]
+struct WaveEvents<'w> {
+    spawns: EventWriter<'w, PortalSpawn>,
+    started: EventWriter<'w, WaveStarted>,
+    bosses: EventWriter<'w, SpawnBoss>,
+    cleared: EventWriter<'w, WaveCleared>,
+}
+
+fn run_waves(
+    time: Res<Time>,
+    config: Res<WaveConfig>,
+    portals: Res<PortalConfig>,
+    director: Res<DirectorConfig>,
+    session: Res<NetSession>,
+    game: Res<GameConfig>,
+    difficulty: Res<DifficultyScale>,
+    day_night: Res<DayNightConfig>,
+    time_of_day: Res<TimeOfDay>,
+    mut state: ResMut<WaveState>,
+    mut rng: ResMut<GameRng>,
+    enemies: Query<(), Or<(With<Enemy>, With<Forming>)>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    spawn_points: Query<&GlobalTransform, With<SpawnPoint>>,
+    mut events: WaveEvents,
+) {
+    state.timer -= time.delta_seconds();
+
+    if !state.started {
+        if state.timer > 0. {
+            return;
+        }
+        let wave = state.wave + 1;
+        // a boss isn't sent to co-op clients, so a co-op run has none
+        let boss = director.is_boss_wave(wave) && session.role == NetRole::Offline;
+        *state = WaveState {
+            wave,
+            to_spawn: if boss { 0 } else { config.wave_size(wave) },
+            // gives the boss time to come out, like the last pack
+            timer: if boss { config.spawn_interval } else { 0. },
+            started: true,
+            boss,
+        };
+        events.started.send(WaveStarted { wave });
+        if boss {
+            events.bosses.send(SpawnBoss { wave });
+        }
+    }
+
+    // also gives the last pack time to come out before the field counts
+    // as clear
+    if state.timer > 0. {
+        return;
+    }
+    if state.to_spawn == 0 {
+        if enemies.is_empty() {
+            events.cleared.send(WaveCleared { wave: state.wave });
+            *state = WaveState::before(state.wave, &config);
+        }
+        return;
+    }
+    let Some(view) = cameras
+        .get_single()
+        .ok()
+        .and_then(|(camera, transform)| visible_rect(camera, transform))
+    else {
+        return;
+    };
+    let rate = difficulty.spawn_rate * day_night.spawn_rate(&time_of_day);
+    state.timer = config.spawn_interval / rate.max(0.01);
+
+    let pack = config.pack_size(state.wave, portals.group_size);
+    let Some((kind, count)) = director.next_pack(state.wave, state.to_spawn, pack, &mut rng) else {
+        // what's left can't pay for anything
+        state.to_spawn = 0;
+        return;
+    };
+    state.to_spawn = state.to_spawn.saturating_sub(director.cost(kind, count));
+    let arena = game.arena.inner_rect(ENEMY_SIZE);
+    let points: Vec<_> = spawn_points.iter().collect();
+    let position = if points.is_empty() {
+        edge_point(view, config.edge_inset, rng.f32())
+    } else {
+        let index = ((rng.f32() * points.len() as f32) as usize).min(points.len() - 1);
+        points[index].translation().truncate()
+    }
+    .clamp(arena.min, arena.max);
+    events.spawns.send(PortalSpawn {
+        count,
+        kind,
+        ..portals.spawn(position)
+    });
+}
@@ -0,0 +1,180 @@
+//! Sprinting by pushing the move stick to its outer ring.
+//!
+//! Past [`SprintConfig::threshold`] the player ramps up to
+//! [`SprintConfig::multiplier`] times its normal speed, draining stamina
+//! while it lasts. Sprinting leaves a short trail and zooms the camera out a
+//! little.
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    camera::CameraZoom,
+    particles::{Emitter, ParticleEffect},
+    quality::EffectsQuality,
+    state::GameState,
+    Action, Player,
+};
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct SprintConfig {
+    /// Stick magnitude, up to 1, above which the player sprints.
+    pub threshold: f32,
+    /// Speed at full sprint, relative to walking.
+    pub multiplier: f32,
+    /// Seconds it takes to reach full sprint, and to slow back down.
+    pub ramp: f32,
+    /// Stamina used per second of sprinting, out of 1.
+    pub stamina_drain: f32,
+    /// Stamina recovered per second while not sprinting.
+    pub stamina_regen: f32,
+    /// How much further the camera zooms out at full sprint.
+    pub zoom: f32,
+}
+
+impl Default for SprintConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 0.95,
+            multiplier: 1.6,
+            ramp: 0.25,
+            stamina_drain: 0.25,
+            stamina_regen: 0.2,
+            zoom: 0.08,
+        }
+    }
+}
+
+impl SprintConfig {
+    /// Speed multiplier at sprint `level` (0 walking, 1 full sprint).
+    pub fn speed_factor(&self, level: f32) -> f32 {
+        1. + (self.multiplier - 1.) * level
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Sprint {
+    /// How far into the sprint ramp the player is, 0 to 1.
+    pub level: f32,
+    pub stamina: f32,
+    /// Set when stamina ran out; sprinting stays off until it has
+    /// recovered past [`Sprint::RECOVERED`].
+    exhausted: bool,
+}
+
+impl Default for Sprint {
+    fn default() -> Self {
+        Self {
+            level: 0.,
+            stamina: 1.,
+            exhausted: false,
+        }
+    }
+}
+
+impl Sprint {
+    const RECOVERED: f32 = 0.25;
+
+    /// Advances the ramp and stamina by `dt` for a stick pushed to
+    /// `magnitude`.
+    pub fn update(&mut self, config: &SprintConfig, magnitude: f32, dt: f32) {
+        if self.stamina <= 0. {
+            self.exhausted = true;
+        } else if self.stamina >= Self::RECOVERED {
+            self.exhausted = false;
+        }
+        let wants = magnitude >= config.threshold && !self.exhausted;
+        let step = if config.ramp > 0. {
+            dt / config.ramp
+        } else {
+            1.
+        };
+        if wants {
+            self.level = (self.level + step).min(1.);
+            self.stamina = (self.stamina - config.stamina_drain * dt).max(0.);
+        } else {
+            self.level = (self.level - step).max(0.);
+            self.stamina = (self.stamina + config.stamina_regen * dt).min(1.);
+        }
+    }
+}
+
+const TRAIL_INTERVAL: f32 = 0.04;
+const TRAIL_LIFETIME: f32 = 0.3;
+const TRAIL_SIZE: f32 = 8.;
+
+pub struct SprintPlugin;
+
+impl Plugin for SprintPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SprintConfig>()
+            .init_resource::<SprintConfig>()
+            .add_systems(
+                Update,
+                (update_sprint, (leave_trail, zoom_out))
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+pub fn update_sprint(
+    config: Res<SprintConfig>,
+    time: Res<Time>,
+    mut players: Query<(&ActionState<Action>, &mut Sprint), With<Player>>,
+) {
+    for (action_state, mut sprint) in &mut players {
+        let magnitude = action_state
+            .clamped_axis_pair(&Action::Move)
+            .map_or(0., |axis| axis.xy().length());
+        sprint.update(&config, magnitude, time.delta_seconds());
+    }
+}
+
+/// Keeps the player's trail [`Emitter`] in step with how hard they sprint.
+fn leave_trail(
+    mut commands: Commands,
+    quality: Res<EffectsQuality>,
+    mut players: Query<(Entity, &Sprint, &Sprite, Option<&mut Emitter>), With<Player>>,
+) {
+    for (entity, sprint, sprite, emitter) in &mut players {
+        let rate = if sprint.level > 0. {
+            1. / TRAIL_INTERVAL
+        } else {
+            0.
+        };
+        let effect = ParticleEffect {
+            // the emitter thins the dots out at lower quality, and they
+            // fade sooner too
+            lifetime: TRAIL_LIFETIME * quality.density(),
+            size: TRAIL_SIZE,
+            start_color: sprite.color.with_a(0.5 * sprint.level),
+            end_color: sprite.color.with_a(0.),
+            end_scale: 0.,
+            z: -0.5,
+            ..default()
+        };
+        match emitter {
+            Some(mut emitter) => {
+                emitter.effect = effect;
+                emitter.rate = rate;
+            }
+            None => {
+                commands.entity(entity).insert(Emitter::new(effect, rate));
+            }
+        }
+    }
+}
+
+fn zoom_out(
+    config: Res<SprintConfig>,
+    players: Query<&Sprint, With<Player>>,
+    mut zoom: ResMut<CameraZoom>,
+) {
+    let level = players.get_single().map_or(0., |sprint| sprint.level);
+    let offset = config.zoom * level;
+    if zoom.sprint != offset {
+        zoom.sprint = offset;
+    }
+}
@@ -0,0 +1,75 @@
+//! The game's top-level states, and cleanup of what each one owns.
+//!
+//! Anything spawned for a single state, like a menu screen, gets a
+//! [`StateScoped`] naming that state and is despawned, children and all, as
+//! soon as the state is left. The sticks are scoped to
+//! [`GameState::Playing`] and spawned again each time play resumes. The
+//! player lives across playing, pausing and the screens opened over a run,
+//! so it gets a [`RunScoped`] instead and is despawned when the run is left
+//! for the main menu, with a new one spawned when the next run starts.
+
+use bevy::prelude::*;
+
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    /// The title screen, before a run starts.
+    #[default]
+    MainMenu,
+    Playing,
+    /// Gameplay is suspended until the player resumes.
+    Paused,
+    /// Paused with the HUD hidden, to frame and take a picture.
+    PhotoMode,
+    /// Lifetime stats and achievements screen.
+    Stats,
+    Settings,
+    /// The run has ended; waiting for the player to start a new one.
+    GameOver,
+}
+
+impl GameState {
+    pub const ALL: [GameState; 7] = [
+        GameState::MainMenu,
+        GameState::Playing,
+        GameState::Paused,
+        GameState::PhotoMode,
+        GameState::Stats,
+        GameState::Settings,
+        GameState::GameOver,
+    ];
+}
+
+/// Despawns the entity, with its children, when leaving the state.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct StateScoped(pub GameState);
+
+/// Despawns the entity, with its children, when going back to the main
+/// menu.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct RunScoped;
+
+pub struct StatePlugin;
+
+impl Plugin for StatePlugin {
+    fn build(&self, app: &mut App) {
+        for state in GameState::ALL {
+            app.add_systems(
+                OnExit(state),
+                move |mut commands: Commands, scoped: Query<(Entity, &StateScoped)>| {
+                    for (entity, scope) in &scoped {
+                        if scope.0 == state {
+                            commands.entity(entity).despawn_recursive();
+                        }
+                    }
+                },
+            );
+        }
+        app.add_systems(OnEnter(GameState::MainMenu), despawn_run);
+    }
+}
+
+fn despawn_run(mut commands: Commands, scoped: Query<Entity, With<RunScoped>>) {
+    for entity in &scoped {
+        commands.entity(entity).despawn_recursive();
+    }
+}
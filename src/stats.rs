@@ -0,0 +1,287 @@
+//! Lifetime stats, and the screen showing them with the achievements.
+//!
+//! [`Stats`] accumulates from gameplay events and is persisted through
+//! [`storage`]. The [achievements](crate::achievements) are goals on them.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    achievements::Achievements,
+    defs::Defs,
+    events::{ComboChanged, EnemyKilled, PlayerDied, PlayerMoved, WaveCleared, WaveStarted},
+    fonts::UiFonts,
+    layout::{Mirrored, StickSide},
+    replay,
+    score::{award_points, Score},
+    state::{GameState, StateScoped},
+    storage,
+    web::ExitSave,
+};
+
+const STATS_KEY: &str = "stats";
+
+/// Minimum time between two saves of [`Stats`], in seconds.
+const STATS_SAVE_INTERVAL: f32 = 5.;
+
+#[derive(Resource, Reflect, Serialize, Deserialize, Default, Debug, Clone)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct Stats {
+    pub kills: u64,
+    pub distance: f32,
+    pub best_combo: u32,
+    pub highest_wave: u32,
+    /// Waves cleared over every run.
+    pub waves_cleared: u64,
+    pub deaths: u64,
+    pub best_score: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum StatKind {
+    Kills,
+    Distance,
+    BestCombo,
+    HighestWave,
+    WavesCleared,
+    Deaths,
+    BestScore,
+}
+
+impl Stats {
+    pub fn get(&self, kind: StatKind) -> f64 {
+        match kind {
+            StatKind::Kills => self.kills as f64,
+            StatKind::Distance => self.distance as f64,
+            StatKind::BestCombo => self.best_combo as f64,
+            StatKind::HighestWave => self.highest_wave as f64,
+            StatKind::WavesCleared => self.waves_cleared as f64,
+            StatKind::Deaths => self.deaths as f64,
+            StatKind::BestScore => self.best_score as f64,
+        }
+    }
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Stats>()
+            .insert_resource(storage::load::<Stats>(STATS_KEY).unwrap_or_default())
+            .add_systems(Startup, spawn_stats_button)
+            .add_systems(
+                Update,
+                (
+                    track_stats
+                        .after(award_points)
+                        .run_if(not(replay::is_replaying)),
+                    save_stats,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    open_stats.run_if(in_state(GameState::Playing)),
+                    close_stats.run_if(in_state(GameState::Stats)),
+                ),
+            )
+            .add_systems(OnEnter(GameState::Stats), spawn_stats_screen);
+    }
+}
+
+pub fn track_stats(
+    mut stats: ResMut<Stats>,
+    score: Res<Score>,
+    mut moved: EventReader<PlayerMoved>,
+    mut killed: EventReader<EnemyKilled>,
+    mut combos: EventReader<ComboChanged>,
+    mut waves: EventReader<WaveStarted>,
+    mut cleared: EventReader<WaveCleared>,
+    mut died: EventReader<PlayerDied>,
+) {
+    // only touch the resource when something happened, so change detection
+    // stays meaningful for the unlock and save systems
+    for event in moved.read() {
+        stats.distance += event.distance;
+    }
+    let kills = killed.read().count() as u64;
+    if kills > 0 {
+        stats.kills += kills;
+    }
+    for event in combos.read() {
+        if event.combo > stats.best_combo {
+            stats.best_combo = event.combo;
+        }
+    }
+    for event in waves.read() {
+        if event.wave > stats.highest_wave {
+            stats.highest_wave = event.wave;
+        }
+    }
+    let waves_cleared = cleared.read().count() as u64;
+    if waves_cleared > 0 {
+        stats.waves_cleared += waves_cleared;
+    }
+    let deaths = died.read().count() as u64;
+    if deaths > 0 {
+        stats.deaths += deaths;
+    }
+    // recorded as the score rises, so a later death penalty can't lower it
+    if score.points > stats.best_score {
+        stats.best_score = score.points;
+    }
+}
+
+fn save_stats(
+    stats: Res<Stats>,
+    time: Res<Time<Real>>,
+    exit_save: Res<ExitSave>,
+    mut since_save: Local<f32>,
+    mut dirty: Local<bool>,
+) {
+    if stats.is_changed() && !stats.is_added() {
+        *dirty = true;
+        // a best score mustn't wait for the next save if the tab is closed
+        exit_save.stage(STATS_KEY, &*stats);
+    }
+
+    *since_save += time.delta_seconds();
+    if *dirty && *since_save >= STATS_SAVE_INTERVAL {
+        storage::save(STATS_KEY, &*stats);
+        *dirty = false;
+        *since_save = 0.;
+    }
+}
+
+#[derive(Component)]
+struct OpenStatsButton;
+
+#[derive(Component)]
+struct CloseStatsButton;
+
+fn button_text(fonts: &UiFonts, text: &str) -> TextBundle {
+    TextBundle::from_section(text, fonts.style(20., Color::WHITE))
+}
+
+fn spawn_stats_button(mut commands: Commands, fonts: Res<UiFonts>) {
+    commands
+        .spawn((
+            OpenStatsButton,
+            Mirrored::new(StickSide::Look, 12.),
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(12.),
+                    padding: UiRect::axes(Val::Px(10.), Val::Px(6.)),
+                    ..default()
+                },
+                background_color: Color::rgba(1., 1., 1., 0.15).into(),
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(button_text(&fonts, "Stats"));
+        });
+}
+
+fn open_stats(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<OpenStatsButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if buttons.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.set(GameState::Stats);
+    }
+}
+
+fn close_stats(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<CloseStatsButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if buttons.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn spawn_stats_screen(
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    stats: Res<Stats>,
+    defs: Res<Defs>,
+    achievements: Res<Achievements>,
+) {
+    let lines = [
+        format!("Kills: {}", stats.kills),
+        format!("Distance: {:.0}", stats.distance),
+        format!("Best combo: {}", stats.best_combo),
+        format!("Highest wave: {}", stats.highest_wave),
+        format!("Waves cleared: {}", stats.waves_cleared),
+        format!("Deaths: {}", stats.deaths),
+        format!("Best score: {}", stats.best_score),
+    ];
+
+    commands
+        .spawn((
+            StateScoped(GameState::Stats),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(6.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.85).into(),
+                z_index: ZIndex::Global(20),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Stats",
+                fonts.bold(32., Color::WHITE),
+            ));
+            for line in lines {
+                parent.spawn(button_text(&fonts, &line));
+            }
+
+            parent.spawn(TextBundle::from_section(
+                "Achievements",
+                fonts.bold(26., Color::WHITE),
+            ));
+            for def in defs.achievements() {
+                let unlocked = achievements.is_unlocked(&def.id);
+                let color = if unlocked { Color::GOLD } else { Color::GRAY };
+                let line = if unlocked {
+                    format!("{}: {}", def.name, def.description)
+                } else {
+                    // whole percent, so 99.6% doesn't read as done
+                    let percent = (def.progress(&stats) * 100.).floor();
+                    format!("{}: {} ({percent:.0}%)", def.name, def.description)
+                };
+                parent.spawn(TextBundle::from_section(line, fonts.style(18., color)));
+            }
+
+            parent
+                .spawn((
+                    CloseStatsButton,
+                    ButtonBundle {
+                        style: Style {
+                            margin: UiRect::top(Val::Px(12.)),
+                            padding: UiRect::axes(Val::Px(16.), Val::Px(8.)),
+                            ..default()
+                        },
+                        background_color: Color::rgba(1., 1., 1., 0.15).into(),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn(button_text(&fonts, "Back"));
+                });
+        });
+}
@@ -0,0 +1,337 @@
+//! Timed effects on the player and enemies: slow, burn and shield.
+//!
+//! Anything can send [`ApplyStatus`]; hits from a weapon with
+//! [`Weapon::effects`](crate::weapon::Weapon::effects) send them for what
+//! they hit, an enemy's [`EnemyDef::on_hit`](crate::defs::EnemyDef::on_hit)
+//! for the player it touches. An enemy also spawns with its
+//! [`EnemyDef::effects`](crate::defs::EnemyDef::effects) on. Each effect
+//! lasts its own [`StatusEffect::duration`], and up to [`MAX_STACKS`] of
+//! one kind stack on an entity, with another past that taking the place of
+//! the one nearest to running out.
+//!
+//! Slows multiply together into [`Statuses::speed_multiplier`], which the
+//! player's and enemies' movement is scaled by. Burns deal their damage a
+//! second every [`BURN_INTERVAL`]. Shields soak up incoming damage until
+//! their strength runs out. An entity under an effect has a colored aura
+//! behind it, and loses everything when it dies or the run starts over.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{death::StartNewRun, enemy::Dying, health::DamageEvent, state::GameState, Player};
+
+/// Stacks of one kind an entity can have at once.
+pub const MAX_STACKS: usize = 5;
+/// Seconds between two burn ticks.
+pub const BURN_INTERVAL: f32 = 0.5;
+/// Slowed as much as it gets, something still moves this fast.
+const MIN_SPEED: f32 = 0.2;
+/// How much bigger than its entity an aura is.
+const AURA_SCALE: f32 = 1.4;
+/// Behind the entity.
+const AURA_Z: f32 = -0.5;
+const AURA_PULSE_SPEED: f32 = 6.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum StatusKind {
+    /// Takes [`StatusEffect::strength`] off the speed, as a fraction.
+    Slow,
+    /// Deals [`StatusEffect::strength`] damage a second.
+    Burn,
+    /// Soaks up [`StatusEffect::strength`] damage.
+    Shield,
+}
+
+impl StatusKind {
+    /// In the order auras pick their color by.
+    const ALL: [StatusKind; 3] = [StatusKind::Shield, StatusKind::Burn, StatusKind::Slow];
+
+    fn color(self) -> Color {
+        match self {
+            StatusKind::Slow => Color::rgb(0.4, 0.7, 1.),
+            StatusKind::Burn => Color::rgb(1., 0.45, 0.1),
+            StatusKind::Shield => Color::rgb(0.85, 0.9, 1.),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub strength: f32,
+    /// Seconds it lasts.
+    pub duration: f32,
+}
+
+impl StatusEffect {
+    pub fn slow(fraction: f32, duration: f32) -> Self {
+        Self {
+            kind: StatusKind::Slow,
+            strength: fraction,
+            duration,
+        }
+    }
+
+    pub fn burn(damage_per_second: f32, duration: f32) -> Self {
+        Self {
+            kind: StatusKind::Burn,
+            strength: damage_per_second,
+            duration,
+        }
+    }
+}
+
+/// Put `effect` on `target`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ApplyStatus {
+    pub target: Entity,
+    pub effect: StatusEffect,
+}
+
+/// Effects a shot puts on whatever it hits.
+#[derive(Component, Debug, Clone, Default)]
+pub struct Inflicts(pub Vec<StatusEffect>);
+
+impl Inflicts {
+    pub fn on(&self, target: Entity) -> impl Iterator<Item = ApplyStatus> + '_ {
+        self.0
+            .iter()
+            .map(move |&effect| ApplyStatus { target, effect })
+    }
+}
+
+/// An effect with `remaining` seconds to go.
+#[derive(Debug, Clone, Copy)]
+struct Stack {
+    effect: StatusEffect,
+    remaining: f32,
+}
+
+/// The effects on an entity; removed once the last one wears off.
+#[derive(Component, Debug, Default)]
+pub struct Statuses {
+    stacks: Vec<Stack>,
+    /// Seconds until burns next deal damage.
+    burn_timer: f32,
+}
+
+impl Statuses {
+    /// `effects` as they start, for an entity spawning with them.
+    pub fn new(effects: impl IntoIterator<Item = StatusEffect>) -> Self {
+        let mut statuses = Self::default();
+        for effect in effects {
+            statuses.add(effect);
+        }
+        statuses
+    }
+
+    fn add(&mut self, effect: StatusEffect) {
+        let stack = Stack {
+            effect,
+            remaining: effect.duration,
+        };
+        let count = self
+            .stacks
+            .iter()
+            .filter(|s| s.effect.kind == effect.kind)
+            .count();
+        if count < MAX_STACKS {
+            // a fresh burn waits a full interval before its first tick
+            if count == 0 && effect.kind == StatusKind::Burn {
+                self.burn_timer = BURN_INTERVAL;
+            }
+            self.stacks.push(stack);
+            return;
+        }
+        if let Some(oldest) = self
+            .stacks
+            .iter_mut()
+            .filter(|s| s.effect.kind == effect.kind)
+            .min_by(|a, b| a.remaining.total_cmp(&b.remaining))
+        {
+            *oldest = stack;
+        }
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.stacks.iter().any(|stack| stack.effect.kind == kind)
+    }
+
+    /// What movement speed is multiplied by.
+    pub fn speed_multiplier(&self) -> f32 {
+        self.stacks
+            .iter()
+            .filter(|stack| stack.effect.kind == StatusKind::Slow)
+            .map(|stack| 1. - stack.effect.strength.clamp(0., 1.))
+            .product::<f32>()
+            .max(MIN_SPEED)
+    }
+
+    /// What is left of `amount` damage once shields, oldest first, have
+    /// taken what they can of it.
+    pub fn absorb(&mut self, amount: f32) -> f32 {
+        let mut left = amount;
+        for stack in &mut self.stacks {
+            if stack.effect.kind != StatusKind::Shield || left <= 0. {
+                continue;
+            }
+            let taken = stack.effect.strength.min(left);
+            stack.effect.strength -= taken;
+            left -= taken;
+        }
+        self.stacks
+            .retain(|stack| stack.effect.kind != StatusKind::Shield || stack.effect.strength > 0.);
+        left
+    }
+
+    fn burn_per_second(&self) -> f32 {
+        self.stacks
+            .iter()
+            .filter(|stack| stack.effect.kind == StatusKind::Burn)
+            .map(|stack| stack.effect.strength)
+            .sum()
+    }
+}
+
+/// The aura drawn behind `target`.
+#[derive(Component, Debug)]
+struct Aura {
+    target: Entity,
+}
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<StatusEffect>()
+            .add_event::<ApplyStatus>()
+            .add_systems(
+                Update,
+                (apply_statuses, clear_statuses, draw_auras)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                FixedUpdate,
+                tick_statuses.run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn apply_statuses(
+    mut commands: Commands,
+    mut requests: EventReader<ApplyStatus>,
+    mut statuses: Query<&mut Statuses>,
+    alive: Query<(), Without<Dying>>,
+) {
+    // the first effect on an entity this frame makes its component, which
+    // only exists once the commands run
+    let mut added: Vec<(Entity, Statuses)> = Vec::new();
+    for request in requests.read() {
+        if !alive.contains(request.target) {
+            continue;
+        }
+        if let Ok(mut existing) = statuses.get_mut(request.target) {
+            existing.add(request.effect);
+            continue;
+        }
+        match added
+            .iter_mut()
+            .find(|(target, _)| *target == request.target)
+        {
+            Some((_, pending)) => pending.add(request.effect),
+            None => added.push((request.target, Statuses::new([request.effect]))),
+        }
+    }
+    for (target, pending) in added {
+        if let Some(mut entity) = commands.get_entity(target) {
+            entity.insert(pending);
+        }
+    }
+}
+
+/// Takes every effect off the dying, and off the player on a new run.
+fn clear_statuses(
+    mut commands: Commands,
+    mut new_runs: EventReader<StartNewRun>,
+    dying: Query<Entity, (With<Statuses>, With<Dying>)>,
+    players: Query<Entity, (With<Statuses>, With<Player>)>,
+) {
+    let restarted = new_runs.read().count() > 0;
+    let players = players.iter().filter(|_| restarted);
+    for entity in dying.iter().chain(players) {
+        commands.entity(entity).remove::<Statuses>();
+    }
+}
+
+fn tick_statuses(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut statuses: Query<(Entity, &mut Statuses), Without<Dying>>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut status) in &mut statuses {
+        let burn = status.burn_per_second();
+        if burn > 0. {
+            status.burn_timer -= dt;
+            if status.burn_timer <= 0. {
+                status.burn_timer += BURN_INTERVAL;
+                damage.send(DamageEvent {
+                    target: entity,
+                    amount: burn * BURN_INTERVAL,
+                });
+            }
+        }
+        for stack in &mut status.stacks {
+            stack.remaining -= dt;
+        }
+        status.stacks.retain(|stack| stack.remaining > 0.);
+        if status.stacks.is_empty() {
+            commands.entity(entity).remove::<Statuses>();
+        }
+    }
+}
+
+fn draw_auras(
+    mut commands: Commands,
+    time: Res<Time>,
+    targets: Query<(Entity, &Statuses, &Transform, Option<&Sprite>), Without<Aura>>,
+    mut auras: Query<(Entity, &Aura, &mut Transform, &mut Sprite)>,
+) {
+    let pulse = 0.45 + 0.15 * (time.elapsed_seconds() * AURA_PULSE_SPEED).sin();
+    let mut drawn = Vec::new();
+    for (entity, aura, mut transform, mut sprite) in &mut auras {
+        let Ok((_, statuses, target, target_sprite)) = targets.get(aura.target) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let Some(kind) = StatusKind::ALL.into_iter().find(|kind| statuses.has(*kind)) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let size = target_sprite
+            .and_then(|sprite| sprite.custom_size)
+            .unwrap_or(Vec2::ONE);
+        transform.translation = target.translation + Vec3::Z * AURA_Z;
+        transform.rotation = target.rotation;
+        transform.scale = target.scale;
+        sprite.custom_size = Some(size * AURA_SCALE);
+        sprite.color = kind.color().with_a(pulse);
+        drawn.push(aura.target);
+    }
+    for (entity, ..) in &targets {
+        if !drawn.contains(&entity) {
+            commands.spawn((
+                Aura { target: entity },
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::NONE,
+                        ..default()
+                    },
+                    ..default()
+                },
+            ));
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Small key/value persistence layer.
+//!
+//! Values are serialized to RON and stored under a key: in `localStorage`
+//! on the web, and elsewhere as a file in the platform's data directory.
+//! That is the app's internal storage on Android, `Application Support` in
+//! the app's sandbox on iOS or the user's on macOS, `%APPDATA%` on Windows,
+//! and `$XDG_DATA_HOME` or `~/.local/share` on other desktops.
+//!
+//! Bulky binary data, like a [replay](crate::replay), can be stored as raw
+//! bytes with [`save_bytes`] instead: a file of its own natively, and hex
+//! in `localStorage`, which only holds strings.
+//!
+//! [Photos](crate::photo) get a fresh file name from [`photo_path`]: in a
+//! `photos` folder of the data directory natively, while the web, which
+//! has no disk to write to, downloads them under that name.
+//!
+//! A [benchmark](crate::bench) turns storage off with [`disable`], so it
+//! runs on the defaults and leaves the player's saves alone.
+
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Reads nothing and writes nothing from now on, for the rest of the
+/// session.
+pub fn disable() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+fn enabled() -> bool {
+    !DISABLED.load(Ordering::Relaxed)
+}
+
+/// Loads the value stored under `key`, if present and readable.
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    if !enabled() {
+        return None;
+    }
+    let raw = backend::read(key)?;
+    match ron::from_str(&raw) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            warn!("ignoring unreadable stored value `{key}`: {err}");
+            None
+        }
+    }
+}
+
+/// Stores `value` under `key`, replacing any previous value.
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    if let Some(raw) = serialize(key, value) {
+        save_serialized(key, &raw);
+    }
+}
+
+/// `value` in the form [`save`] stores it, for writing later with
+/// [`save_serialized`].
+pub fn serialize<T: Serialize>(key: &str, value: &T) -> Option<String> {
+    match ron::to_string(value) {
+        Ok(raw) => Some(raw),
+        Err(err) => {
+            error!("failed to serialize `{key}`: {err}");
+            None
+        }
+    }
+}
+
+/// Stores a value produced by [`serialize`] under `key`.
+pub fn save_serialized(key: &str, raw: &str) {
+    if enabled() {
+        backend::write(key, raw);
+    }
+}
+
+/// Loads the bytes stored under `key` by [`save_bytes`].
+pub fn load_bytes(key: &str) -> Option<Vec<u8>> {
+    enabled().then(|| backend::read_bytes(key)).flatten()
+}
+
+/// Stores raw `bytes` under `key`, replacing any previous value.
+pub fn save_bytes(key: &str, bytes: &[u8]) {
+    if enabled() {
+        backend::write_bytes(key, bytes);
+    }
+}
+
+/// Deletes the value stored under `key`, if any.
+pub fn remove(key: &str) {
+    if enabled() {
+        backend::remove(key);
+    }
+}
+
+/// Where to save a new photo, not taken by an earlier one.
+pub fn photo_path() -> PathBuf {
+    backend::photo_path()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use std::{fs, path::PathBuf};
+
+    use bevy::prelude::*;
+
+    #[cfg(target_os = "android")]
+    fn data_dir() -> PathBuf {
+        bevy::winit::ANDROID_APP
+            .get()
+            .and_then(|app| app.internal_data_path())
+            .unwrap_or_default()
+    }
+
+    #[cfg(any(target_os = "ios", target_os = "macos"))]
+    fn data_dir() -> PathBuf {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        home.map(|home| home.join("Library/Application Support"))
+            .unwrap_or_default()
+            .join("rain")
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_os = "macos")))]
+    fn data_dir() -> PathBuf {
+        let base = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+            .unwrap_or_default();
+        base.join("rain")
+    }
+
+    fn path(key: &str) -> PathBuf {
+        data_dir().join(format!("{key}.ron"))
+    }
+
+    fn bytes_path(key: &str) -> PathBuf {
+        data_dir().join(format!("{key}.bin"))
+    }
+
+    pub fn photo_path() -> PathBuf {
+        let dir = data_dir().join("photos");
+        if let Err(err) = fs::create_dir_all(&dir) {
+            error!("failed to create the photo folder: {err}");
+        }
+        (1..)
+            .map(|n| dir.join(format!("rain-{n:03}.png")))
+            .find(|path| !path.exists())
+            .unwrap_or_default()
+    }
+
+    pub fn read(key: &str) -> Option<String> {
+        fs::read_to_string(path(key)).ok()
+    }
+
+    pub fn write(key: &str, raw: &str) {
+        let result = fs::create_dir_all(data_dir()).and_then(|_| fs::write(path(key), raw));
+        if let Err(err) = result {
+            error!("failed to save `{key}`: {err}");
+        }
+    }
+
+    pub fn read_bytes(key: &str) -> Option<Vec<u8>> {
+        fs::read(bytes_path(key)).ok()
+    }
+
+    pub fn write_bytes(key: &str, bytes: &[u8]) {
+        let result = fs::create_dir_all(data_dir()).and_then(|_| fs::write(bytes_path(key), bytes));
+        if let Err(err) = result {
+            error!("failed to save `{key}`: {err}");
+        }
+    }
+
+    pub fn remove(key: &str) {
+        for path in [path(key), bytes_path(key)] {
+            if path.exists() {
+                if let Err(err) = fs::remove_file(path) {
+                    error!("failed to remove `{key}`: {err}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use std::path::PathBuf;
+
+    use bevy::prelude::*;
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    fn item_key(key: &str) -> String {
+        format!("rain.{key}")
+    }
+
+    pub fn read(key: &str) -> Option<String> {
+        local_storage()?.get_item(&item_key(key)).ok()?
+    }
+
+    pub fn write(key: &str, raw: &str) {
+        let Some(storage) = local_storage() else {
+            warn!("localStorage unavailable, `{key}` not saved");
+            return;
+        };
+        if storage.set_item(&item_key(key), raw).is_err() {
+            error!("failed to save `{key}` to localStorage");
+        }
+    }
+
+    pub fn read_bytes(key: &str) -> Option<Vec<u8>> {
+        let hex = read(key)?;
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+
+    pub fn write_bytes(key: &str, bytes: &[u8]) {
+        let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+        write(key, &hex);
+    }
+
+    pub fn remove(key: &str) {
+        if let Some(storage) = local_storage() {
+            let _ = storage.remove_item(&item_key(key));
+        }
+    }
+
+    /// The browser picks a free name in its downloads itself.
+    pub fn photo_path() -> PathBuf {
+        PathBuf::from("rain.png")
+    }
+}
@@ -0,0 +1,127 @@
+//! Enemy targeting queries shared by aim-assist, homing, the reticle,
+//! off-screen indicators and enemies keeping apart.
+//!
+//! Enemy positions are bucketed into [`EnemyGrid`] once per tick in
+//! `PreUpdate`, and every query during the frame reads that grid.
+
+use bevy::prelude::*;
+
+use crate::{enemy::Enemy, spatial::SpatialGrid};
+
+/// Size of an [`EnemyGrid`] cell, in world units.
+const ENEMY_GRID_CELL_SIZE: f32 = 64.;
+
+#[derive(Resource, Debug, Clone)]
+pub struct EnemyGrid(pub SpatialGrid);
+
+impl Default for EnemyGrid {
+    fn default() -> Self {
+        Self(SpatialGrid::new(ENEMY_GRID_CELL_SIZE))
+    }
+}
+
+impl EnemyGrid {
+    /// Enemies within `radius` of `position`, with their positions.
+    pub fn nearby_in_radius(
+        &self,
+        position: Vec2,
+        radius: f32,
+    ) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        self.0.nearby_in_radius(position, radius)
+    }
+
+    /// The enemy closest to `position`, with its position.
+    pub fn nearest_enemy(&self, position: Vec2) -> Option<(Entity, Vec2)> {
+        self.0.nearest(position)
+    }
+
+    /// The closest enemy within `range` of `position` whose direction lies
+    /// within `half_angle` radians of `direction`.
+    pub fn nearest_in_cone(
+        &self,
+        position: Vec2,
+        direction: Vec2,
+        half_angle: f32,
+        range: f32,
+    ) -> Option<(Entity, Vec2)> {
+        let direction = direction.try_normalize()?;
+        let min_cos = half_angle.cos();
+        self.0
+            .nearby_in_radius(position, range)
+            .filter(|(_, other)| {
+                (*other - position)
+                    .try_normalize()
+                    .is_none_or(|to_other| to_other.dot(direction) >= min_cos)
+            })
+            .min_by(|(_, a), (_, b)| {
+                a.distance_squared(position)
+                    .total_cmp(&b.distance_squared(position))
+            })
+    }
+}
+
+pub struct TargetingPlugin;
+
+impl Plugin for TargetingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EnemyGrid>()
+            .add_systems(PreUpdate, rebuild_enemy_grid);
+    }
+}
+
+fn rebuild_enemy_grid(
+    mut grid: ResMut<EnemyGrid>,
+    enemies: Query<(Entity, &GlobalTransform), With<Enemy>>,
+) {
+    grid.0.clear();
+    for (entity, transform) in &enemies {
+        grid.0.insert(entity, transform.translation().truncate());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One enemy right of the origin, one further up and one close behind.
+    fn layout() -> (EnemyGrid, [Entity; 3]) {
+        let entities = [0, 1, 2].map(Entity::from_raw);
+        let mut grid = EnemyGrid::default();
+        grid.0.insert(entities[0], Vec2::new(100., 0.));
+        grid.0.insert(entities[1], Vec2::new(0., 150.));
+        grid.0.insert(entities[2], Vec2::new(-50., 10.));
+        (grid, entities)
+    }
+
+    #[test]
+    fn nearest_enemy_is_the_closest_in_any_direction() {
+        let (grid, [right, up, behind]) = layout();
+        assert_eq!(grid.nearest_enemy(Vec2::ZERO).map(|(e, _)| e), Some(behind));
+        assert_eq!(
+            grid.nearest_enemy(Vec2::new(90., 0.)).map(|(e, _)| e),
+            Some(right)
+        );
+        assert_eq!(
+            grid.nearest_enemy(Vec2::new(0., 200.)).map(|(e, _)| e),
+            Some(up)
+        );
+        assert_eq!(EnemyGrid::default().nearest_enemy(Vec2::ZERO), None);
+    }
+
+    #[test]
+    fn nearest_in_cone_skips_enemies_outside_it() {
+        let (grid, [right, up, behind]) = layout();
+        let cone = |direction: Vec2, range: f32| {
+            grid.nearest_in_cone(Vec2::ZERO, direction, 0.5, range)
+                .map(|(e, _)| e)
+        };
+        // the closest one is behind, so facing right picks the far one
+        assert_eq!(cone(Vec2::X, 500.), Some(right));
+        assert_eq!(cone(Vec2::Y, 500.), Some(up));
+        assert_eq!(cone(Vec2::NEG_X, 500.), Some(behind));
+        // out of range, and nobody down there
+        assert_eq!(cone(Vec2::X, 80.), None);
+        assert_eq!(cone(Vec2::NEG_Y, 500.), None);
+        assert_eq!(cone(Vec2::ZERO, 500.), None);
+    }
+}
@@ -0,0 +1,136 @@
+//! Warnings shown at the screen edge before an enemy spawns.
+//!
+//! Spawners send [`WarnedSpawn`] instead of [`SpawnEnemy`]; a pulsing marker
+//! appears on the visible edge nearest the spawn point, and the enemy is
+//! spawned at that exact point once the warning runs out.
+
+use bevy::prelude::*;
+
+use crate::{
+    bounds::grow,
+    camera::{visible_rect, MainCamera},
+    despawn::DespawnQueue,
+    enemy::SpawnEnemy,
+};
+
+/// Spawn an enemy at `position` after warning the player.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WarnedSpawn {
+    pub position: Vec2,
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct SpawnWarningConfig {
+    /// When false, warned spawns happen immediately with no marker.
+    pub enabled: bool,
+    /// Seconds the marker is shown before the enemy appears.
+    pub duration: f32,
+}
+
+impl Default for SpawnWarningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            duration: 0.5,
+        }
+    }
+}
+
+/// How far inside the screen edge markers are drawn.
+const MARKER_INSET: f32 = 12.;
+const MARKER_SIZE: f32 = 14.;
+const MARKER_PULSE_SPEED: f32 = 18.;
+const MARKER_COLOR: Color = Color::rgb(1., 0.2, 0.2);
+
+#[derive(Component)]
+struct SpawnWarning {
+    position: Vec2,
+    timer: Timer,
+}
+
+pub struct TelegraphPlugin;
+
+impl Plugin for TelegraphPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SpawnWarningConfig>()
+            .init_resource::<SpawnWarningConfig>()
+            .add_event::<WarnedSpawn>()
+            .add_systems(Update, (start_warnings, update_warnings).chain());
+    }
+}
+
+fn start_warnings(
+    mut commands: Commands,
+    config: Res<SpawnWarningConfig>,
+    mut requests: EventReader<WarnedSpawn>,
+    mut spawns: EventWriter<SpawnEnemy>,
+) {
+    for request in requests.read() {
+        if !config.enabled || config.duration <= 0. {
+            spawns.send(SpawnEnemy::at(request.position));
+            continue;
+        }
+
+        commands.spawn((
+            SpawnWarning {
+                position: request.position,
+                timer: Timer::from_seconds(config.duration, TimerMode::Once),
+            },
+            SpriteBundle {
+                transform: Transform::from_rotation(Quat::from_rotation_z(
+                    std::f32::consts::FRAC_PI_4,
+                )),
+                sprite: Sprite {
+                    color: MARKER_COLOR,
+                    custom_size: Some(Vec2::splat(MARKER_SIZE)),
+                    ..default()
+                },
+                // positioned on the first update
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ));
+    }
+}
+
+fn update_warnings(
+    mut despawns: ResMut<DespawnQueue>,
+    time: Res<Time>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut warnings: Query<(
+        Entity,
+        &mut SpawnWarning,
+        &mut Transform,
+        &mut Sprite,
+        &mut Visibility,
+    )>,
+    mut spawns: EventWriter<SpawnEnemy>,
+) {
+    let view = cameras
+        .get_single()
+        .ok()
+        .and_then(|(camera, transform)| visible_rect(camera, transform));
+
+    for (entity, mut warning, mut transform, mut sprite, mut visibility) in &mut warnings {
+        if warning.timer.tick(time.delta()).finished() {
+            spawns.send(SpawnEnemy::at(warning.position));
+            despawns.despawn(entity);
+            continue;
+        }
+
+        let marker = match view {
+            Some(view) => {
+                let inner = grow(view, -MARKER_INSET);
+                warning.position.clamp(inner.min, inner.max)
+            }
+            None => warning.position,
+        };
+        transform.translation = marker.extend(1.);
+
+        let pulse = (warning.timer.elapsed_secs() * MARKER_PULSE_SPEED).sin() * 0.5 + 0.5;
+        transform.scale = Vec3::splat(0.8 + 0.4 * pulse);
+        sprite.color.set_a(0.5 + 0.5 * pulse);
+        *visibility = Visibility::Visible;
+    }
+}
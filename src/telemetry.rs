@@ -0,0 +1,140 @@
+//! Opt-in gameplay logging.
+//!
+//! With [`DebugSettings::telemetry`] on, meaningful events are logged:
+//! player spawns and deaths and wave changes as they happen, enemy spawns
+//! and kills as one count per second, and a frame rate sample every few
+//! seconds. With it off nothing is logged. Every line is also kept in
+//! [`TelemetryLog`] so it can be read in the inspector; on the web the
+//! lines additionally go to the browser console through Bevy's log output.
+//!
+//! Built with the `telemetry` feature, the same setting also opts into the
+//! session records of `crate::analytics`.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    enemy::SpawnEnemy,
+    events::{EnemyKilled, PlayerDied, PlayerSpawned, WaveStarted},
+    settings::DebugSettings,
+};
+
+/// Seconds between enemy spawn/kill summaries.
+const SUMMARY_INTERVAL: f32 = 1.;
+/// Seconds between frame rate samples.
+const FPS_INTERVAL: f32 = 5.;
+const LOG_LINES: usize = 100;
+
+/// The most recent telemetry lines, oldest first.
+#[derive(Resource, Reflect, Debug, Default)]
+#[reflect(Resource)]
+pub struct TelemetryLog {
+    pub lines: VecDeque<String>,
+}
+
+impl TelemetryLog {
+    fn push(&mut self, line: String) {
+        if self.lines.len() == LOG_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+}
+
+/// Counts collected between summaries.
+#[derive(Default)]
+struct Tally {
+    elapsed: f32,
+    spawned: usize,
+    killed: usize,
+}
+
+#[derive(Default)]
+struct FpsSample {
+    elapsed: f32,
+    frames: u32,
+}
+
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TelemetryLog>()
+            .init_resource::<TelemetryLog>()
+            .add_systems(
+                Last,
+                (log_events, log_tallies, log_fps).run_if(telemetry_enabled),
+            );
+    }
+}
+
+fn telemetry_enabled(settings: Res<DebugSettings>) -> bool {
+    settings.telemetry
+}
+
+fn log_events(
+    mut log: ResMut<TelemetryLog>,
+    mut spawned: EventReader<PlayerSpawned>,
+    mut died: EventReader<PlayerDied>,
+    mut waves: EventReader<WaveStarted>,
+) {
+    for event in spawned.read() {
+        let line = format!("player spawned at {}", event.position);
+        info!("{line}");
+        log.push(line);
+    }
+    for event in died.read() {
+        let line = format!("player died at {}", event.position);
+        info!("{line}");
+        log.push(line);
+    }
+    for event in waves.read() {
+        let line = format!("wave {} started", event.wave);
+        info!("{line}");
+        log.push(line);
+    }
+}
+
+fn log_tallies(
+    time: Res<Time<Real>>,
+    mut tally: Local<Tally>,
+    mut log: ResMut<TelemetryLog>,
+    mut spawns: EventReader<SpawnEnemy>,
+    mut killed: EventReader<EnemyKilled>,
+) {
+    tally.spawned += spawns.read().count();
+    tally.killed += killed.read().count();
+    tally.elapsed += time.delta_seconds();
+    if tally.elapsed < SUMMARY_INTERVAL {
+        return;
+    }
+
+    if tally.spawned > 0 || tally.killed > 0 {
+        let line = format!(
+            "enemies: {} spawned, {} killed",
+            tally.spawned, tally.killed
+        );
+        info!("{line}");
+        log.push(line);
+    }
+    *tally = Tally::default();
+}
+
+fn log_fps(time: Res<Time<Real>>, mut sample: Local<FpsSample>, mut log: ResMut<TelemetryLog>) {
+    sample.frames += 1;
+    sample.elapsed += time.delta_seconds();
+    if sample.elapsed < FPS_INTERVAL {
+        return;
+    }
+
+    let fps = sample.frames as f32 / sample.elapsed;
+    let line = format!("{fps:.1} fps");
+    if fps < 30. {
+        warn!("{line}");
+    } else {
+        info!("{line}");
+    }
+    log.push(line);
+    *sample = FpsSample::default();
+}
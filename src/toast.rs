@@ -0,0 +1,286 @@
+//! Transient on-screen notifications.
+//!
+//! [`Toast`] events are queued and shown a few at a time near the top of the
+//! screen, clear of the touch sticks. Each toast slides in, lingers and fades
+//! out. A toast identical to one already queued or on screen is folded into
+//! it as an "xN" count instead of being shown again, so bursts don't flood
+//! the screen.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{config::Palette, events::WaveStarted, fonts::UiFonts, tween::Ease};
+
+/// What a toast is about; picks its accent color.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ToastKind {
+    #[default]
+    Info,
+    Achievement,
+    Wave,
+    Pickup,
+    Record,
+}
+
+impl ToastKind {
+    pub fn color(self, palette: &Palette) -> Color {
+        match self {
+            ToastKind::Info => palette.text,
+            ToastKind::Achievement | ToastKind::Record => palette.reward,
+            ToastKind::Wave => palette.accent,
+            ToastKind::Pickup => palette.pickup,
+        }
+    }
+}
+
+/// Shows `message` briefly near the top of the screen.
+#[derive(Event, Debug, Clone)]
+pub struct Toast {
+    pub message: String,
+    pub kind: ToastKind,
+}
+
+impl Toast {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            kind: ToastKind::Info,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: ToastKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    fn same_as(&self, other: &Toast) -> bool {
+        self.kind == other.kind && self.message == other.message
+    }
+}
+
+/// How long a toast stays on screen, in seconds, including its slide and fade.
+const TOAST_DURATION: f32 = 3.;
+const SLIDE_IN: f32 = 0.2;
+const FADE_OUT: f32 = 0.4;
+/// How far above its resting place a toast starts sliding in from.
+const SLIDE_DISTANCE: f32 = 30.;
+const MAX_VISIBLE: usize = 3;
+/// Older queued toasts are dropped beyond this.
+const MAX_QUEUED: usize = 8;
+
+/// Vertical offset and opacity of a toast `elapsed` seconds into its life.
+pub fn toast_envelope(elapsed: f32, duration: f32) -> (f32, f32) {
+    let entering = (elapsed / SLIDE_IN).clamp(0., 1.);
+    let leaving = ((duration - elapsed) / FADE_OUT).clamp(0., 1.);
+    let offset = -SLIDE_DISTANCE * (1. - Ease::QuadOut.apply(entering));
+    (offset, entering.min(leaving))
+}
+
+#[derive(Debug, Clone)]
+struct QueuedToast {
+    toast: Toast,
+    count: u32,
+}
+
+impl QueuedToast {
+    fn text(&self) -> String {
+        if self.count > 1 {
+            format!("{} x{}", self.toast.message, self.count)
+        } else {
+            self.toast.message.clone()
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct ToastQueue(VecDeque<QueuedToast>);
+
+/// Column the visible toasts are stacked in.
+#[derive(Component)]
+struct ToastStack;
+
+#[derive(Component)]
+struct ToastNode {
+    toast: QueuedToast,
+    elapsed: f32,
+    /// Set when the count changed and the text needs updating.
+    relabel: bool,
+}
+
+#[derive(Component)]
+struct ToastText;
+
+pub struct ToastPlugin;
+
+impl Plugin for ToastPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Toast>()
+            .init_resource::<ToastQueue>()
+            .add_systems(Startup, spawn_toast_stack)
+            .add_systems(
+                Update,
+                (announce_waves, queue_toasts, show_toasts, animate_toasts).chain(),
+            );
+    }
+}
+
+fn announce_waves(mut waves: EventReader<WaveStarted>, mut toasts: EventWriter<Toast>) {
+    for event in waves.read() {
+        toasts.send(Toast::new(format!("Wave {}", event.wave)).with_kind(ToastKind::Wave));
+    }
+}
+
+fn spawn_toast_stack(mut commands: Commands) {
+    commands.spawn((
+        ToastStack,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(16.),
+                width: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(6.),
+                ..default()
+            },
+            z_index: ZIndex::Global(10),
+            ..default()
+        },
+    ));
+}
+
+fn queue_toasts(
+    mut toasts: EventReader<Toast>,
+    mut queue: ResMut<ToastQueue>,
+    mut visible: Query<&mut ToastNode>,
+) {
+    for toast in toasts.read() {
+        // fold into a toast already on screen, restarting its linger
+        if let Some(mut node) = visible
+            .iter_mut()
+            .find(|node| node.toast.toast.same_as(toast))
+        {
+            node.toast.count += 1;
+            node.elapsed = node.elapsed.min(SLIDE_IN);
+            node.relabel = true;
+            continue;
+        }
+
+        if let Some(queued) = queue
+            .0
+            .iter_mut()
+            .find(|queued| queued.toast.same_as(toast))
+        {
+            queued.count += 1;
+            continue;
+        }
+
+        queue.0.push_back(QueuedToast {
+            toast: toast.clone(),
+            count: 1,
+        });
+        if queue.0.len() > MAX_QUEUED {
+            queue.0.pop_front();
+        }
+    }
+}
+
+fn show_toasts(
+    mut commands: Commands,
+    mut queue: ResMut<ToastQueue>,
+    palette: Res<Palette>,
+    fonts: Res<UiFonts>,
+    stacks: Query<Entity, With<ToastStack>>,
+    visible: Query<(), With<ToastNode>>,
+) {
+    let Ok(stack) = stacks.get_single() else {
+        return;
+    };
+    let free = MAX_VISIBLE.saturating_sub(visible.iter().count());
+    let count = free.min(queue.0.len());
+    for queued in queue.0.drain(..count) {
+        let color = queued.toast.kind.color(&palette);
+        let label = queued.text();
+        let node = commands
+            .spawn((
+                ToastNode {
+                    toast: queued,
+                    elapsed: 0.,
+                    relabel: false,
+                },
+                NodeBundle {
+                    style: Style {
+                        top: Val::Px(-SLIDE_DISTANCE),
+                        padding: UiRect::axes(Val::Px(12.), Val::Px(6.)),
+                        column_gap: Val::Px(8.),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: Color::rgba(0., 0., 0., 0.).into(),
+                    ..default()
+                },
+            ))
+            .with_children(|parent| {
+                // an accent bar in the kind's color
+                parent.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(4.),
+                        height: Val::Px(20.),
+                        ..default()
+                    },
+                    background_color: color.into(),
+                    ..default()
+                });
+                parent.spawn((
+                    ToastText,
+                    TextBundle::from_section(label, fonts.style(20., color)),
+                ));
+            })
+            .id();
+        commands.entity(stack).add_child(node);
+    }
+}
+
+fn animate_toasts(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    mut toasts: Query<(
+        Entity,
+        &mut ToastNode,
+        &mut Style,
+        &mut BackgroundColor,
+        &Children,
+    )>,
+    mut texts: Query<&mut Text, With<ToastText>>,
+    mut colors: Query<&mut BackgroundColor, Without<ToastNode>>,
+) {
+    for (entity, mut toast, mut style, mut background, children) in &mut toasts {
+        toast.elapsed += time.delta_seconds();
+        if toast.elapsed >= TOAST_DURATION {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let label = toast.relabel.then(|| toast.toast.text());
+        toast.relabel = false;
+
+        let (offset, alpha) = toast_envelope(toast.elapsed, TOAST_DURATION);
+        style.top = Val::Px(offset);
+        background.0 = Color::rgba(0., 0., 0., 0.7 * alpha);
+        for &child in children {
+            if let Ok(mut text) = texts.get_mut(child) {
+                if let Some(label) = &label {
+                    text.sections[0].value.clone_from(label);
+                }
+                for section in &mut text.sections {
+                    section.style.color.set_a(alpha);
+                }
+            }
+            // the accent bar
+            if let Ok(mut color) = colors.get_mut(child) {
+                color.0.set_a(alpha);
+            }
+        }
+    }
+}
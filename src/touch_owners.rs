@@ -0,0 +1,288 @@
+//! Which part of the touch controls each finger belongs to.
+//!
+//! With both sticks and a button held, or a gesture made beside them,
+//! three or more fingers are down at once, and telling them apart by where
+//! each one is now gets them crossed as they slide. [`TouchOwners`]
+//! decides once instead, as a touch goes down, from where it started: an
+//! [action button](crate::virtual_buttons) there takes it first, then any
+//! other UI button, then the [pressure slider](crate::trigger), then the
+//! stick whose area it is in, and anything left is a
+//! [gesture](crate::gestures). The touch keeps that [`TouchOwner`]
+//! for every move until it lifts or is canceled, wherever it slides, and
+//! nothing else picks it up.
+//!
+//! A stick or the slider takes one touch at a time: another finger coming
+//! down on it while it is held is a gesture. bevy_touch_stick only moves the sticks
+//! and lets go of them; the sticks are spawned with no area of their own,
+//! and each is handed its touch here, so a finger that slides onto one, or
+//! starts on a button over one, never takes it.
+//!
+//! The owner of a touch that lifted this frame can still be looked up,
+//! for whatever acts on the release; it is forgotten the frame after.
+
+use bevy::{input::InputSystem, prelude::*, utils::HashMap};
+use bevy_touch_stick::TouchStick;
+
+use crate::{
+    layout::TouchStickRoot, trigger::PressureSlider, virtual_buttons::VirtualButton, Stick,
+};
+
+/// What a touch belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TouchOwner {
+    MoveStick,
+    LookStick,
+    /// An action button.
+    Button(Entity),
+    /// Any other UI button, like pause.
+    Ui,
+    /// The trigger's pressure slider.
+    Slider,
+    Gestures,
+}
+
+impl TouchOwner {
+    /// Whether this only takes one touch at a time.
+    fn is_single(self) -> bool {
+        matches!(
+            self,
+            TouchOwner::MoveStick | TouchOwner::LookStick | TouchOwner::Slider
+        )
+    }
+}
+
+/// The owner of every touch down, by touch id.
+#[derive(Resource, Debug, Default)]
+pub struct TouchOwners {
+    owners: HashMap<u64, TouchOwner>,
+}
+
+impl TouchOwners {
+    pub fn owner(&self, id: u64) -> Option<TouchOwner> {
+        self.owners.get(&id).copied()
+    }
+
+    /// The touches `owner` has, in no particular order.
+    pub fn owned_by(&self, owner: TouchOwner) -> impl Iterator<Item = u64> + '_ {
+        self.owners
+            .iter()
+            .filter(move |(_, other)| **other == owner)
+            .map(|(id, _)| *id)
+    }
+
+    /// Gives each touch in `started` the owner of where it started, with
+    /// [`pick_owner`]. A stick or slider held by a touch that is still
+    /// `down` is left out, and the touch goes to whatever else is there.
+    fn assign(
+        &mut self,
+        started: &[(u64, Vec2)],
+        regions: &[(Rect, TouchOwner)],
+        down: impl Fn(u64) -> bool,
+    ) {
+        for &(id, start) in started {
+            let free: Vec<_> = regions
+                .iter()
+                .filter(|(_, owner)| !owner.is_single() || !self.owned_by(*owner).any(&down))
+                .copied()
+                .collect();
+            self.owners.insert(id, pick_owner(start, &free));
+        }
+    }
+}
+
+/// The first owner in `regions` whose rect holds `point`, in the order
+/// they take touches, or [`TouchOwner::Gestures`].
+fn pick_owner(point: Vec2, regions: &[(Rect, TouchOwner)]) -> TouchOwner {
+    regions
+        .iter()
+        .find(|(rect, _)| rect.contains(point))
+        .map_or(TouchOwner::Gestures, |(_, owner)| *owner)
+}
+
+pub struct TouchOwnersPlugin;
+
+impl Plugin for TouchOwnersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TouchOwners>()
+            .add_systems(PreUpdate, assign_touches.after(InputSystem));
+    }
+}
+
+pub fn assign_touches(
+    touches: Res<Touches>,
+    mut owners: ResMut<TouchOwners>,
+    buttons: Query<(Entity, &Node, &GlobalTransform), With<VirtualButton>>,
+    ui: Query<(&Node, &GlobalTransform), (With<Interaction>, Without<VirtualButton>)>,
+    sliders: Query<(&Node, &GlobalTransform, &ViewVisibility), With<PressureSlider>>,
+    mut sticks: Query<(&Node, &GlobalTransform, &mut TouchStick<Stick>), With<TouchStickRoot>>,
+) {
+    // kept through the frame a touch ends in, for whatever acts on it
+    owners.owners.retain(|id, _| {
+        touches.get_pressed(*id).is_some()
+            || touches.just_released(*id)
+            || touches.just_canceled(*id)
+    });
+    let started: Vec<_> = touches
+        .iter_just_pressed()
+        .map(|touch| (touch.id(), touch.start_position()))
+        .collect();
+    if started.is_empty() {
+        return;
+    }
+
+    let buttons = buttons.iter().map(|(entity, node, transform)| {
+        (node.logical_rect(transform), TouchOwner::Button(entity))
+    });
+    let ui = ui
+        .iter()
+        .map(|(node, transform)| (node.logical_rect(transform), TouchOwner::Ui));
+    let sliders = sliders
+        .iter()
+        .filter(|(_, _, visible)| visible.get())
+        .map(|(node, transform, _)| (node.logical_rect(transform), TouchOwner::Slider));
+    let stick_regions = sticks
+        .iter()
+        .map(|(node, transform, stick)| (node.logical_rect(transform), stick_owner(stick)));
+    let regions: Vec<_> = buttons
+        .chain(ui)
+        .chain(sliders)
+        .chain(stick_regions)
+        .collect();
+    owners.assign(&started, &regions, |id| touches.get_pressed(id).is_some());
+
+    for (_, _, mut stick) in &mut sticks {
+        let owner = stick_owner(&stick);
+        let Some(&(id, start)) = started
+            .iter()
+            .find(|(id, _)| owners.owner(*id) == Some(owner))
+        else {
+            continue;
+        };
+        // what bevy_touch_stick does itself for a touch inside its area
+        stick.drag_id = Some(id);
+        stick.drag_start = start;
+        stick.drag_position = start;
+        stick.value = Vec2::ZERO;
+    }
+}
+
+fn stick_owner(stick: &TouchStick<Stick>) -> TouchOwner {
+    match stick.id {
+        Stick::Left => TouchOwner::MoveStick,
+        Stick::Right => TouchOwner::LookStick,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::HashSet;
+
+    use super::*;
+
+    const BUTTON: TouchOwner = TouchOwner::Button(Entity::PLACEHOLDER);
+
+    /// A 800x600 screen: the move stick on the left half, the look stick
+    /// on the right and an action button over the look stick's corner.
+    fn regions() -> Vec<(Rect, TouchOwner)> {
+        vec![
+            (Rect::new(700., 500., 780., 580.), BUTTON),
+            (Rect::new(0., 0., 400., 600.), TouchOwner::MoveStick),
+            (Rect::new(400., 0., 800., 600.), TouchOwner::LookStick),
+        ]
+    }
+
+    #[test]
+    fn the_first_region_holding_the_point_owns_it() {
+        let regions = regions();
+        assert_eq!(
+            pick_owner(Vec2::new(100., 300.), &regions),
+            TouchOwner::MoveStick
+        );
+        assert_eq!(
+            pick_owner(Vec2::new(500., 300.), &regions),
+            TouchOwner::LookStick
+        );
+        // the button is over the stick
+        assert_eq!(pick_owner(Vec2::new(740., 540.), &regions), BUTTON);
+        assert_eq!(
+            pick_owner(Vec2::new(900., 300.), &regions),
+            TouchOwner::Gestures
+        );
+        assert_eq!(pick_owner(Vec2::new(100., 300.), &[]), TouchOwner::Gestures);
+    }
+
+    #[test]
+    fn touches_keep_their_owners_as_they_slide() {
+        let regions = regions();
+        let mut owners = TouchOwners::default();
+        let mut down = HashSet::new();
+        let mut frame = |owners: &mut TouchOwners, started: &[(u64, Vec2)], ended: &[u64]| {
+            for id in ended {
+                down.remove(id);
+            }
+            owners
+                .owners
+                .retain(|id, _| down.contains(id) || ended.contains(id));
+            down.extend(started.iter().map(|(id, _)| *id));
+            owners.assign(started, &regions, |id| down.contains(&id));
+        };
+
+        // both thumbs and a finger on the button
+        frame(
+            &mut owners,
+            &[
+                (1, Vec2::new(100., 300.)),
+                (2, Vec2::new(500., 300.)),
+                (3, Vec2::new(740., 540.)),
+            ],
+            &[],
+        );
+        assert_eq!(owners.owner(1), Some(TouchOwner::MoveStick));
+        assert_eq!(owners.owner(2), Some(TouchOwner::LookStick));
+        assert_eq!(owners.owner(3), Some(BUTTON));
+
+        // they slide across each other's regions, where a fourth finger
+        // lands on the held move stick
+        frame(&mut owners, &[(4, Vec2::new(300., 200.))], &[]);
+        assert_eq!(owners.owner(1), Some(TouchOwner::MoveStick));
+        assert_eq!(owners.owner(2), Some(TouchOwner::LookStick));
+        assert_eq!(owners.owner(3), Some(BUTTON));
+        assert_eq!(owners.owner(4), Some(TouchOwner::Gestures));
+
+        // the move thumb lifts, and is still known for the frame it ends in
+        frame(&mut owners, &[], &[1]);
+        assert_eq!(owners.owner(1), Some(TouchOwner::MoveStick));
+        // then the stick is free for the next touch on it, even with the
+        // gesture finger still in its area
+        frame(&mut owners, &[(5, Vec2::new(50., 50.))], &[]);
+        assert_eq!(owners.owner(1), None);
+        assert_eq!(owners.owner(5), Some(TouchOwner::MoveStick));
+        assert_eq!(owners.owner(4), Some(TouchOwner::Gestures));
+
+        let moving: Vec<_> = owners.owned_by(TouchOwner::MoveStick).collect();
+        assert_eq!(moving, [5]);
+    }
+
+    #[test]
+    fn one_touch_per_stick_when_they_land_together() {
+        let mut owners = TouchOwners::default();
+        let started = [(1, Vec2::new(100., 300.)), (2, Vec2::new(200., 400.))];
+        owners.assign(&started, &regions(), |_| true);
+        assert_eq!(owners.owner(1), Some(TouchOwner::MoveStick));
+        assert_eq!(owners.owner(2), Some(TouchOwner::Gestures));
+    }
+
+    #[test]
+    fn the_slider_keeps_to_its_first_finger() {
+        let mut regions = regions();
+        regions.insert(1, (Rect::new(740., 100., 780., 260.), TouchOwner::Slider));
+        let mut owners = TouchOwners::default();
+        owners.assign(&[(1, Vec2::new(760., 200.))], &regions, |_| true);
+        // a finger landing on it while it is held goes to what is under it
+        owners.assign(&[(2, Vec2::new(760., 150.))], &regions, |_| true);
+        assert_eq!(owners.owner(1), Some(TouchOwner::Slider));
+        assert_eq!(owners.owner(2), Some(TouchOwner::LookStick));
+        let sliding: Vec<_> = owners.owned_by(TouchOwner::Slider).collect();
+        assert_eq!(sliding, [1]);
+    }
+}
@@ -0,0 +1,159 @@
+//! Fades between scenes.
+//!
+//! Going into or out of the [main menu](GameState::MainMenu) fades the
+//! screen to black over [`FADE_OUT`] seconds and back over [`FADE_IN`],
+//! in real time. Whatever asks for the new state sets [`NextState`] as
+//! usual: the request is taken back out before it applies, held while the
+//! screen darkens, and applied once it is black, so the old scene leaves
+//! and the new one arrives under the curtain. The settings screen opens
+//! over the menu without one.
+//!
+//! While the curtain is up it blocks clicks and touches, so a button on
+//! the way out can't be pressed twice. The app opens with a fade in, and
+//! [`Curtain::quit`] fades out before closing the app.
+
+use bevy::{app::AppExit, prelude::*, ui::FocusPolicy};
+
+use crate::state::GameState;
+
+/// Seconds the screen takes to go black.
+pub const FADE_OUT: f32 = 0.3;
+/// Seconds the screen takes to come back.
+pub const FADE_IN: f32 = 0.4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exit {
+    State(GameState),
+    Quit,
+}
+
+/// How dark the screen is, and what happens once it is black.
+#[derive(Resource, Debug)]
+pub struct Curtain {
+    /// `0..=1`, 1 being black.
+    pub opacity: f32,
+    /// Set while fading out.
+    exit: Option<Exit>,
+}
+
+impl Default for Curtain {
+    /// Black, to fade the first scene in.
+    fn default() -> Self {
+        Self {
+            opacity: 1.,
+            exit: None,
+        }
+    }
+}
+
+impl Curtain {
+    /// Fade out, then close the app.
+    pub fn quit(&mut self) {
+        self.exit = Some(Exit::Quit);
+    }
+
+    pub fn is_fading_out(&self) -> bool {
+        self.exit.is_some()
+    }
+}
+
+/// Whether going from `from` to `to` fades through black.
+fn fades(from: GameState, to: GameState) -> bool {
+    from != to
+        && (from == GameState::MainMenu || to == GameState::MainMenu)
+        && from != GameState::Settings
+        && to != GameState::Settings
+}
+
+/// The full-screen node drawn at [`Curtain::opacity`].
+#[derive(Component)]
+struct CurtainNode;
+
+pub struct TransitionPlugin;
+
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Curtain>()
+            .add_systems(Startup, spawn_curtain)
+            .add_systems(Last, (hold_transition, run_curtain, show_curtain).chain());
+    }
+}
+
+fn spawn_curtain(mut commands: Commands) {
+    commands.spawn((
+        CurtainNode,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            background_color: Color::BLACK.into(),
+            focus_policy: FocusPolicy::Block,
+            z_index: ZIndex::Global(100),
+            ..default()
+        },
+    ));
+}
+
+/// Takes a state change that fades out of [`NextState`] until the screen
+/// is black.
+fn hold_transition(
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut curtain: ResMut<Curtain>,
+) {
+    let Some(to) = next_state.0 else {
+        return;
+    };
+    if curtain.is_fading_out() || !fades(*state.get(), to) {
+        return;
+    }
+    next_state.0 = None;
+    curtain.exit = Some(Exit::State(to));
+}
+
+fn run_curtain(
+    time: Res<Time<Real>>,
+    mut curtain: ResMut<Curtain>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let dt = time.delta_seconds();
+    let Some(exit) = curtain.exit else {
+        if curtain.opacity > 0. {
+            curtain.opacity = (curtain.opacity - dt / FADE_IN).max(0.);
+        }
+        return;
+    };
+    curtain.opacity = (curtain.opacity + dt / FADE_OUT).min(1.);
+    if curtain.opacity < 1. {
+        return;
+    }
+    curtain.exit = None;
+    match exit {
+        Exit::State(state) => next_state.set(state),
+        Exit::Quit => {
+            app_exit.send(AppExit);
+        }
+    }
+}
+
+fn show_curtain(
+    curtain: Res<Curtain>,
+    mut nodes: Query<(&mut BackgroundColor, &mut Visibility), With<CurtainNode>>,
+) {
+    if !curtain.is_changed() {
+        return;
+    }
+    for (mut color, mut visibility) in &mut nodes {
+        color.0.set_a(curtain.opacity);
+        // hidden, it stops blocking the UI under it
+        *visibility = if curtain.opacity > 0. {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
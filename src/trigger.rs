@@ -0,0 +1,214 @@
+//! Analog fire intensity.
+//!
+//! The right trigger's pressure is read through [`Action::Trigger`] as a
+//! value in `0..=1`. Touch screens get a vertical pressure slider on the
+//! look stick's edge instead: the higher the touch on it, the harder the
+//! "trigger". The finger that lands on it holds it until it lifts, and
+//! other fingers passing over it are left to their own [`TouchOwner`].
+//! Like the [gestures](crate::gestures), the slider doesn't fire anything
+//! itself: it raises the value of [`Action::Trigger`] right after leafwing
+//! has updated it, and whatever then holds the action ends up in
+//! [`TriggerPressure`], which firing scales its cadence by through
+//! [`fire_intensity`].
+
+use bevy::prelude::*;
+use leafwing_input_manager::{plugin::InputManagerSystem, prelude::*};
+
+use crate::{
+    gestures,
+    layout::{InputMode, Mirrored, StickSide},
+    replay::is_replaying,
+    state::GameState,
+    touch_owners::{self, TouchOwner, TouchOwners},
+    Action, Player,
+};
+
+/// Trigger values below this count as fully released.
+const RELEASED_THRESHOLD: f32 = 0.05;
+
+const SLIDER_WIDTH: f32 = 48.;
+const SLIDER_HEIGHT: f32 = 160.;
+/// Distance from the slider to its screen edge.
+const SLIDER_INSET: f32 = 16.;
+const SLIDER_TRACK_COLOR: Color = Color::rgba(1., 1., 1., 0.1);
+const SLIDER_FILL_COLOR: Color = Color::rgba(1., 0.4, 0.2, 0.5);
+
+/// How hard the fire trigger is held this frame, from `0` (released) to `1`.
+#[derive(Resource, Reflect, Debug, Clone, Copy, Default)]
+#[reflect(Resource)]
+pub struct TriggerPressure(pub f32);
+
+/// How strongly to fire, from the discrete shoot input and the analog
+/// pressure. A held shoot button always fires at full intensity, so the two
+/// bindings never fight; with neither held this is `0` and nothing fires.
+pub fn fire_intensity(shoot_pressed: bool, pressure: f32) -> f32 {
+    if shoot_pressed {
+        1.
+    } else if pressure < RELEASED_THRESHOLD {
+        0.
+    } else {
+        pressure.min(1.)
+    }
+}
+
+/// Seconds between shots for a weapon firing `fire_rate` shots a second at
+/// `intensity`, or `None` when it shouldn't fire at all.
+pub fn fire_interval(fire_rate: f32, intensity: f32) -> Option<f32> {
+    let rate = fire_rate * intensity;
+    (rate > 0.).then(|| 1. / rate)
+}
+
+/// Touch stand-in for an analog trigger.
+#[derive(Component, Default)]
+pub struct PressureSlider {
+    value: f32,
+}
+
+#[derive(Component)]
+struct PressureSliderFill;
+
+pub struct TriggerPlugin;
+
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TriggerPressure>()
+            .init_resource::<TriggerPressure>()
+            .add_systems(Startup, spawn_pressure_slider)
+            .add_systems(
+                Update,
+                show_slider_on_touch.run_if(resource_changed::<InputMode>),
+            )
+            .add_systems(
+                PreUpdate,
+                press_pressure_slider
+                    .after(InputManagerSystem::Update)
+                    .after(touch_owners::assign_touches)
+                    .before(gestures::press_gesture_actions)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(not(is_replaying)),
+            )
+            .add_systems(Update, read_trigger.run_if(in_state(GameState::Playing)))
+            .add_systems(OnExit(GameState::Playing), release_trigger);
+    }
+}
+
+fn spawn_pressure_slider(mut commands: Commands) {
+    commands
+        .spawn((
+            PressureSlider::default(),
+            Mirrored::new(StickSide::Look, SLIDER_INSET),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(40.),
+                    width: Val::Px(SLIDER_WIDTH),
+                    height: Val::Px(SLIDER_HEIGHT),
+                    flex_direction: FlexDirection::ColumnReverse,
+                    ..default()
+                },
+                background_color: SLIDER_TRACK_COLOR.into(),
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                PressureSliderFill,
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(0.),
+                        ..default()
+                    },
+                    background_color: SLIDER_FILL_COLOR.into(),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn show_slider_on_touch(
+    mode: Res<InputMode>,
+    mut sliders: Query<&mut Visibility, With<PressureSlider>>,
+) {
+    for mut visibility in &mut sliders {
+        *visibility = match *mode {
+            InputMode::Touch => Visibility::Inherited,
+            InputMode::Desktop => Visibility::Hidden,
+        };
+    }
+}
+
+/// Pressure for a point at `y` on a slider spanning `top..bottom`, in the
+/// same vertical coordinates.
+fn slider_value(y: f32, top: f32, bottom: f32) -> f32 {
+    ((bottom - y) / (bottom - top)).clamp(0., 1.)
+}
+
+fn press_pressure_slider(
+    touches: Res<Touches>,
+    owners: Res<TouchOwners>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    mut sliders: Query<(
+        &mut PressureSlider,
+        &Node,
+        &GlobalTransform,
+        &ViewVisibility,
+    )>,
+    mut fills: Query<&mut Style, With<PressureSliderFill>>,
+    mut players: Query<&mut ActionState<Action>, With<Player>>,
+) {
+    let cursor = windows
+        .get_single()
+        .ok()
+        .filter(|_| mouse.pressed(MouseButton::Left))
+        .and_then(Window::cursor_position);
+    // the finger holding it, wherever it slides, or a click on it
+    let finger = owners
+        .owned_by(TouchOwner::Slider)
+        .find_map(|id| touches.get_pressed(id))
+        .map(|touch| touch.position());
+
+    for (mut slider, node, transform, visible) in &mut sliders {
+        if !visible.get() {
+            slider.value = 0.;
+            continue;
+        }
+        let rect = node.logical_rect(transform);
+        slider.value = finger
+            .or(cursor.filter(|cursor| rect.contains(*cursor)))
+            .map_or(0., |point| slider_value(point.y, rect.min.y, rect.max.y));
+    }
+
+    let value = sliders
+        .iter()
+        .map(|(slider, ..)| slider.value)
+        .fold(0., f32::max);
+    for mut style in &mut fills {
+        style.height = Val::Percent(value * 100.);
+    }
+    if value == 0. {
+        return;
+    }
+    // the stronger of the slider and the real trigger
+    if let Ok(mut action_state) = players.get_single_mut() {
+        if let Some(data) = action_state.action_data_mut(&Action::Trigger) {
+            data.value = data.value.max(value);
+        }
+    }
+}
+
+fn read_trigger(
+    players: Query<&ActionState<Action>, With<Player>>,
+    mut pressure: ResMut<TriggerPressure>,
+) {
+    pressure.0 = players
+        .get_single()
+        .map_or(0., |action_state| action_state.value(&Action::Trigger))
+        .clamp(0., 1.);
+}
+
+fn release_trigger(mut pressure: ResMut<TriggerPressure>) {
+    pressure.0 = 0.;
+}
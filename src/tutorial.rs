@@ -0,0 +1,211 @@
+//! Prompts teaching the controls on the first run.
+//!
+//! Until [`GameplaySettings::tutorial_complete`] is set, a prompt at the top
+//! of the screen walks through moving, aiming, shooting and dashing, one
+//! [`TutorialStep`] at a time. A step only moves on once the player has
+//! done it: moved for [`MOVE_SECONDS`], aimed for [`AIM_SECONDS`], fired
+//! [`SHOTS`] shots, and dashed. Each prompt names the controls of the
+//! [active input device](crate::input_device). Finishing sets the setting,
+//! stored with the others, so the tutorial shows once; the settings screen
+//! can start it over.
+//!
+//! The prompts leave the run itself alone, waves included, so a run with
+//! them plays and [replays](crate::replay) like any other.
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    events::{PlayerDashed, PlayerFired},
+    fonts::UiFonts,
+    input_device::{ActiveInputDevice, InputDevice},
+    replay,
+    settings::{ControlSettings, GameplaySettings},
+    state::{GameState, StateScoped},
+    toast::Toast,
+    Action, Player,
+};
+
+/// Seconds of moving that finish the move step.
+const MOVE_SECONDS: f32 = 1.;
+/// Seconds of aiming that finish the aim step.
+const AIM_SECONDS: f32 = 0.75;
+/// Shots that finish the shoot step.
+const SHOTS: u32 = 3;
+/// Least stick push that counts as moving or aiming.
+const MIN_PUSH: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TutorialStep {
+    #[default]
+    Move,
+    Aim,
+    Shoot,
+    Dash,
+}
+
+impl TutorialStep {
+    fn next(self) -> Option<Self> {
+        match self {
+            TutorialStep::Move => Some(TutorialStep::Aim),
+            TutorialStep::Aim => Some(TutorialStep::Shoot),
+            TutorialStep::Shoot => Some(TutorialStep::Dash),
+            TutorialStep::Dash => None,
+        }
+    }
+
+    fn prompt(self, device: InputDevice, swap_sticks: bool) -> &'static str {
+        match (self, device) {
+            (TutorialStep::Move, InputDevice::Keyboard) => "Move with WASD",
+            (TutorialStep::Move, InputDevice::Gamepad) => "Move with the left stick",
+            (TutorialStep::Move, InputDevice::Touch) if swap_sticks => "Drag right to move",
+            (TutorialStep::Move, InputDevice::Touch) => "Drag left to move",
+            (TutorialStep::Aim, InputDevice::Keyboard) => "Aim with the mouse or the arrows",
+            (TutorialStep::Aim, InputDevice::Gamepad) => "Aim with the right stick",
+            (TutorialStep::Aim, InputDevice::Touch) if swap_sticks => "Drag left to aim",
+            (TutorialStep::Aim, InputDevice::Touch) => "Drag right to aim",
+            (TutorialStep::Shoot, InputDevice::Keyboard) => "Click or press Space to fire",
+            (TutorialStep::Shoot, InputDevice::Gamepad) => "Fire with R1 or R2",
+            (TutorialStep::Shoot, InputDevice::Touch) => "Tap the screen to fire",
+            (TutorialStep::Dash, InputDevice::Keyboard) => "Press Shift to dash",
+            (TutorialStep::Dash, InputDevice::Gamepad) => "Press A to dash",
+            (TutorialStep::Dash, InputDevice::Touch) => "Double tap to dash",
+        }
+    }
+}
+
+/// Where the tutorial is, while it runs.
+#[derive(Resource, Debug, Default)]
+struct Tutorial {
+    step: TutorialStep,
+    /// Seconds held, or shots and dashes made, towards the step.
+    progress: f32,
+}
+
+#[derive(Component)]
+struct TutorialPrompt;
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Tutorial>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                spawn_prompt
+                    .run_if(tutorial_pending)
+                    .run_if(not(replay::is_replaying)),
+            )
+            .add_systems(
+                Update,
+                (track_progress, update_prompt)
+                    .chain()
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(tutorial_pending)
+                    .run_if(not(replay::is_replaying)),
+            )
+            .add_systems(
+                Update,
+                restart_tutorial.run_if(resource_changed::<GameplaySettings>),
+            );
+    }
+}
+
+fn tutorial_pending(settings: Res<GameplaySettings>) -> bool {
+    !settings.tutorial_complete
+}
+
+fn spawn_prompt(mut commands: Commands, fonts: Res<UiFonts>) {
+    commands.spawn((
+        TutorialPrompt,
+        StateScoped(GameState::Playing),
+        TextBundle::from_section("", fonts.bold(24., Color::WHITE))
+            .with_text_justify(JustifyText::Center)
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(18.),
+                width: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                ..default()
+            }),
+    ));
+}
+
+fn track_progress(
+    time: Res<Time>,
+    mut tutorial: ResMut<Tutorial>,
+    mut settings: ResMut<GameplaySettings>,
+    mut fired: EventReader<PlayerFired>,
+    mut dashed: EventReader<PlayerDashed>,
+    players: Query<&ActionState<Action>, With<Player>>,
+    mut commands: Commands,
+    prompts: Query<Entity, With<TutorialPrompt>>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let Ok(action_state) = players.get_single() else {
+        return;
+    };
+    let pushed = |action| {
+        action_state
+            .clamped_axis_pair(&action)
+            .is_some_and(|axis| axis.xy().length() >= MIN_PUSH)
+    };
+    let held = |action| {
+        if pushed(action) {
+            time.delta_seconds()
+        } else {
+            0.
+        }
+    };
+    let shots = fired.read().count() as f32;
+    let dashes = dashed.read().count() as f32;
+    let (done, goal) = match tutorial.step {
+        TutorialStep::Move => (held(Action::Move), MOVE_SECONDS),
+        TutorialStep::Aim => (held(Action::Look), AIM_SECONDS),
+        TutorialStep::Shoot => (shots, SHOTS as f32),
+        TutorialStep::Dash => (dashes, 1.),
+    };
+    tutorial.progress += done;
+    if tutorial.progress < goal {
+        return;
+    }
+
+    tutorial.progress = 0.;
+    match tutorial.step.next() {
+        Some(step) => tutorial.step = step,
+        None => {
+            *tutorial = Tutorial::default();
+            settings.tutorial_complete = true;
+            for prompt in &prompts {
+                commands.entity(prompt).despawn_recursive();
+            }
+            toasts.send(Toast::new("You're ready. Good luck!"));
+        }
+    }
+}
+
+fn update_prompt(
+    tutorial: Res<Tutorial>,
+    device: Res<ActiveInputDevice>,
+    controls: Res<ControlSettings>,
+    mut prompts: Query<&mut Text, With<TutorialPrompt>>,
+) {
+    let text = tutorial.step.prompt(device.device, controls.swap_sticks);
+    for mut prompt in &mut prompts {
+        if prompt.sections[0].value != text {
+            prompt.sections[0].value = text.to_string();
+        }
+    }
+}
+
+/// Starts over from the first step when the setting is cleared again.
+fn restart_tutorial(
+    settings: Res<GameplaySettings>,
+    mut tutorial: ResMut<Tutorial>,
+    mut was_complete: Local<bool>,
+) {
+    if *was_complete && !settings.tutorial_complete {
+        *tutorial = Tutorial::default();
+    }
+    *was_complete = settings.tutorial_complete;
+}
@@ -0,0 +1,272 @@
+//! Easing curves and a small tweening component.
+//!
+//! [`Ease`] maps linear progress `0..=1` onto a curve. A [`Tween`] drives a
+//! [`Lens`] from its start to its end value over a duration, and sends a
+//! [`TweenCompleted`] when done if asked to. Values that chase a moving
+//! target, like the camera following the player, use [`approach`] instead.
+
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+pub enum Ease {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    /// Overshoots and rings before settling.
+    ElasticOut,
+    /// Pulls back slightly before moving.
+    BackIn,
+    /// Overshoots slightly before settling.
+    BackOut,
+}
+
+impl Ease {
+    /// Eased progress for linear progress `t`, which is clamped to `0..=1`.
+    /// Every curve starts at 0 and ends at 1; elastic and back curves leave
+    /// that range in between.
+    pub fn apply(self, t: f32) -> f32 {
+        const BACK: f32 = 1.70158;
+
+        let t = t.clamp(0., 1.);
+        match self {
+            Ease::Linear => t,
+            Ease::QuadIn => t * t,
+            Ease::QuadOut => 1. - (1. - t).powi(2),
+            Ease::QuadInOut if t < 0.5 => 2. * t * t,
+            Ease::QuadInOut => 1. - (-2. * t + 2.).powi(2) / 2.,
+            Ease::CubicIn => t.powi(3),
+            Ease::CubicOut => 1. - (1. - t).powi(3),
+            Ease::CubicInOut if t < 0.5 => 4. * t.powi(3),
+            Ease::CubicInOut => 1. - (-2. * t + 2.).powi(3) / 2.,
+            Ease::ElasticOut if t == 0. || t == 1. => t,
+            Ease::ElasticOut => {
+                let period = std::f32::consts::TAU / 3.;
+                2f32.powf(-10. * t) * ((t * 10. - 0.75) * period).sin() + 1.
+            }
+            Ease::BackIn => (BACK + 1.) * t.powi(3) - BACK * t * t,
+            Ease::BackOut => 1. + (BACK + 1.) * (t - 1.).powi(3) + BACK * (t - 1.).powi(2),
+        }
+    }
+}
+
+/// Moves `current` toward `target`, halving the distance every `half_life`
+/// seconds regardless of frame rate.
+pub fn approach<T>(current: T, target: T, half_life: f32, dt: f32) -> T
+where
+    T: std::ops::Add<Output = T>
+        + std::ops::Sub<Output = T>
+        + std::ops::Mul<f32, Output = T>
+        + Copy,
+{
+    if half_life <= 0. {
+        return target;
+    }
+    let remaining = 0.5f32.powf(dt / half_life);
+    target + (current - target) * remaining
+}
+
+/// Linear blend between two colors in linear RGBA.
+pub fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let from = from.as_linear_rgba_f32();
+    let to = to.as_linear_rgba_f32();
+    let channel = |i: usize| from[i] + (to[i] - from[i]) * t;
+    Color::rgba_linear(channel(0), channel(1), channel(2), channel(3))
+}
+
+/// What a [`Tween`] animates on its entity.
+pub trait Lens: Send + Sync + 'static {
+    type Target: Component;
+
+    /// Sets `target` to this lens's value at eased progress `t`.
+    fn apply(&self, target: &mut Self::Target, t: f32);
+}
+
+pub struct TranslationLens {
+    pub start: Vec3,
+    pub end: Vec3,
+}
+
+impl Lens for TranslationLens {
+    type Target = Transform;
+
+    fn apply(&self, target: &mut Transform, t: f32) {
+        target.translation = self.start.lerp(self.end, t);
+    }
+}
+
+pub struct ScaleLens {
+    pub start: Vec3,
+    pub end: Vec3,
+}
+
+impl Lens for ScaleLens {
+    type Target = Transform;
+
+    fn apply(&self, target: &mut Transform, t: f32) {
+        target.scale = self.start.lerp(self.end, t);
+    }
+}
+
+pub struct SpriteColorLens {
+    pub start: Color,
+    pub end: Color,
+}
+
+impl Lens for SpriteColorLens {
+    type Target = Sprite;
+
+    fn apply(&self, target: &mut Sprite, t: f32) {
+        target.color = lerp_color(self.start, self.end, t);
+    }
+}
+
+pub struct BackgroundColorLens {
+    pub start: Color,
+    pub end: Color,
+}
+
+impl Lens for BackgroundColorLens {
+    type Target = BackgroundColor;
+
+    fn apply(&self, target: &mut BackgroundColor, t: f32) {
+        target.0 = lerp_color(self.start, self.end, t);
+    }
+}
+
+/// Moves an absolutely positioned UI node, in logical pixels from its
+/// top-left.
+pub struct UiPositionLens {
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+impl Lens for UiPositionLens {
+    type Target = Style;
+
+    fn apply(&self, target: &mut Style, t: f32) {
+        let position = self.start.lerp(self.end, t);
+        target.left = Val::Px(position.x);
+        target.top = Val::Px(position.y);
+    }
+}
+
+/// Animates `L` from its start to its end over `duration` seconds. The
+/// component is removed once finished.
+#[derive(Component)]
+pub struct Tween<L: Lens> {
+    pub lens: L,
+    pub ease: Ease,
+    pub duration: f32,
+    pub elapsed: f32,
+    /// Send a [`TweenCompleted`] once finished.
+    pub notify: bool,
+}
+
+impl<L: Lens> Tween<L> {
+    pub fn new(lens: L, ease: Ease, duration: f32) -> Self {
+        Self {
+            lens,
+            ease,
+            duration,
+            elapsed: 0.,
+            notify: false,
+        }
+    }
+
+    pub fn with_completion(mut self) -> Self {
+        self.notify = true;
+        self
+    }
+
+    fn progress(&self) -> f32 {
+        if self.duration <= 0. {
+            1.
+        } else {
+            (self.elapsed / self.duration).min(1.)
+        }
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TweenCompleted {
+    pub entity: Entity,
+}
+
+/// Runs every [`Tween<L>`] for one lens type.
+struct TweenDriverPlugin<L>(PhantomData<L>);
+
+impl<L: Lens> Plugin for TweenDriverPlugin<L> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, drive_tweens::<L>);
+    }
+}
+
+pub struct TweenPlugin;
+
+impl Plugin for TweenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TweenCompleted>().add_plugins((
+            TweenDriverPlugin::<TranslationLens>(PhantomData),
+            TweenDriverPlugin::<ScaleLens>(PhantomData),
+            TweenDriverPlugin::<SpriteColorLens>(PhantomData),
+            TweenDriverPlugin::<BackgroundColorLens>(PhantomData),
+            TweenDriverPlugin::<UiPositionLens>(PhantomData),
+        ));
+    }
+}
+
+fn drive_tweens<L: Lens>(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut completed: EventWriter<TweenCompleted>,
+    mut tweens: Query<(Entity, &mut Tween<L>, &mut L::Target)>,
+) {
+    for (entity, mut tween, mut target) in &mut tweens {
+        tween.elapsed += time.delta_seconds();
+        let progress = tween.progress();
+        tween.lens.apply(&mut target, tween.ease.apply(progress));
+
+        if progress >= 1. {
+            if tween.notify {
+                completed.send(TweenCompleted { entity });
+            }
+            commands.entity(entity).remove::<Tween<L>>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASES: [Ease; 10] = [
+        Ease::Linear,
+        Ease::QuadIn,
+        Ease::QuadOut,
+        Ease::QuadInOut,
+        Ease::CubicIn,
+        Ease::CubicOut,
+        Ease::CubicInOut,
+        Ease::ElasticOut,
+        Ease::BackIn,
+        Ease::BackOut,
+    ];
+
+    #[test]
+    fn every_ease_starts_at_0_and_ends_at_1() {
+        for ease in EASES {
+            assert!(ease.apply(0.).abs() < 1e-6, "{ease:?} at 0");
+            assert!((ease.apply(1.) - 1.).abs() < 1e-6, "{ease:?} at 1");
+            // clamped outside of 0..=1
+            assert_eq!(ease.apply(-1.), ease.apply(0.), "{ease:?} below 0");
+            assert_eq!(ease.apply(2.), ease.apply(1.), "{ease:?} above 1");
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! On-screen action buttons for touch screens.
+//!
+//! The buttons in [`VirtualButtonConfig`] are shown next to the sticks while
+//! playing with touch controls. An action button holds its [`Action`] down
+//! for as long as the finger that pressed it stays on the screen, wherever
+//! it slides to, right after leafwing has updated the action from the bound
+//! inputs, so gameplay sees it like a key or gamepad button. Each button
+//! follows its own touch, so any number can be held at once and alongside
+//! both sticks, taking only the touches [`TouchOwners`] gives it. The
+//! sticks leave the screen edges the buttons sit on to
+//! them; see [`VirtualButtonConfig::reserved`].
+//!
+//! A pause button is a plain bevy_ui button handled by [`crate::pause`],
+//! since pausing only needs one touch. The default buttons leave it out as
+//! the pause button in the corner is always there.
+
+use bevy::prelude::*;
+use leafwing_input_manager::{buttonlike::ButtonState, plugin::InputManagerSystem, prelude::*};
+
+use crate::{
+    fonts::UiFonts,
+    layout::{InputMode, Mirrored, StickSide},
+    pause::PauseButton,
+    settings::ControlSettings,
+    state::GameState,
+    touch_owners::{self, TouchOwner, TouchOwners},
+    Action, Player,
+};
+
+const BUTTON_COLOR: Color = Color::rgba(1., 1., 1., 0.15);
+const PRESSED_COLOR: Color = Color::rgba(1., 1., 1., 0.35);
+
+/// Space kept clear between the buttons and a stick's touch area.
+const STICK_MARGIN: f32 = 16.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum VirtualButtonKind {
+    Action(Action),
+    Pause,
+}
+
+/// A square button `size` logical pixels across, `inset` from the screen
+/// edge on `side` and `bottom` from the bottom.
+#[derive(Debug, Clone, Reflect)]
+pub struct VirtualButtonSpec {
+    pub kind: VirtualButtonKind,
+    pub label: String,
+    pub side: StickSide,
+    pub inset: f32,
+    pub bottom: f32,
+    pub size: f32,
+}
+
+impl VirtualButtonSpec {
+    pub fn new(kind: VirtualButtonKind, label: &str, side: StickSide) -> Self {
+        Self {
+            kind,
+            label: label.to_string(),
+            side,
+            inset: 80.,
+            bottom: 40.,
+            size: 72.,
+        }
+    }
+
+    pub fn at(mut self, inset: f32, bottom: f32) -> Self {
+        self.inset = inset;
+        self.bottom = bottom;
+        self
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct VirtualButtonConfig {
+    pub buttons: Vec<VirtualButtonSpec>,
+}
+
+impl Default for VirtualButtonConfig {
+    fn default() -> Self {
+        // inside the trigger slider on the look stick's edge
+        Self {
+            buttons: vec![
+                VirtualButtonSpec::new(
+                    VirtualButtonKind::Action(Action::Shoot),
+                    "Fire",
+                    StickSide::Look,
+                ),
+                VirtualButtonSpec::new(
+                    VirtualButtonKind::Action(Action::Dash),
+                    "Dash",
+                    StickSide::Look,
+                )
+                .at(80., 128.),
+                VirtualButtonSpec::new(
+                    VirtualButtonKind::Action(Action::Deploy),
+                    "Turret",
+                    StickSide::Look,
+                )
+                .at(80., 216.),
+            ],
+        }
+    }
+}
+
+impl VirtualButtonConfig {
+    /// Width of the strip along the screen edge on `side` that the buttons
+    /// there need kept free of the stick.
+    pub fn reserved(&self, side: StickSide) -> f32 {
+        self.buttons
+            .iter()
+            .filter(|button| button.side == side)
+            .map(|button| button.inset + button.size + STICK_MARGIN)
+            .fold(0., f32::max)
+    }
+}
+
+/// An on-screen button for an action, and the touch holding it down.
+#[derive(Component, Debug)]
+pub struct VirtualButton {
+    action: Action,
+    touch: Option<u64>,
+    /// Held last frame, so holding on doesn't count as a new press.
+    was_held: bool,
+}
+
+/// A pause button spawned from the config, as opposed to the one
+/// [`crate::pause`] always shows.
+#[derive(Component)]
+struct VirtualPauseButton;
+
+pub struct VirtualButtonsPlugin;
+
+impl Plugin for VirtualButtonsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<VirtualButtonConfig>()
+            .init_resource::<VirtualButtonConfig>()
+            .add_systems(
+                Update,
+                apply_buttons.run_if(
+                    resource_changed::<InputMode>
+                        .or_else(resource_changed::<VirtualButtonConfig>)
+                        .or_else(state_changed::<GameState>),
+                ),
+            )
+            .add_systems(
+                PreUpdate,
+                press_buttons
+                    .after(InputManagerSystem::Update)
+                    .after(touch_owners::assign_touches)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// The buttons for [`VirtualButtonConfig`], when playing on touch.
+fn apply_buttons(
+    mut commands: Commands,
+    mode: Res<InputMode>,
+    state: Res<State<GameState>>,
+    config: Res<VirtualButtonConfig>,
+    settings: Res<ControlSettings>,
+    fonts: Res<UiFonts>,
+    buttons: Query<Entity, Or<(With<VirtualButton>, With<VirtualPauseButton>)>>,
+) {
+    for entity in &buttons {
+        commands.entity(entity).despawn_recursive();
+    }
+    if *mode != InputMode::Touch || *state.get() != GameState::Playing {
+        return;
+    }
+    for spec in &config.buttons {
+        spawn_button(&mut commands, &fonts, &settings, spec);
+    }
+}
+
+fn spawn_button(
+    commands: &mut Commands,
+    fonts: &UiFonts,
+    settings: &ControlSettings,
+    spec: &VirtualButtonSpec,
+) {
+    let mirrored = Mirrored::new(spec.side, spec.inset);
+    let (left, right) = mirrored.position(settings.swap_sticks);
+    let node = NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            left,
+            right,
+            bottom: Val::Px(spec.bottom),
+            width: Val::Px(spec.size),
+            height: Val::Px(spec.size),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: BUTTON_COLOR.into(),
+        z_index: ZIndex::Global(5),
+        ..default()
+    };
+    let mut button = match spec.kind {
+        VirtualButtonKind::Action(action) => commands.spawn((
+            VirtualButton {
+                action,
+                touch: None,
+                was_held: false,
+            },
+            mirrored,
+            node,
+        )),
+        VirtualButtonKind::Pause => commands.spawn((
+            VirtualPauseButton,
+            PauseButton,
+            mirrored,
+            ButtonBundle {
+                style: node.style,
+                background_color: node.background_color,
+                z_index: node.z_index,
+                ..default()
+            },
+        )),
+    };
+    button.with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            spec.label.clone(),
+            fonts.bold(16., Color::WHITE),
+        ));
+    });
+}
+
+fn press_buttons(
+    touches: Res<Touches>,
+    owners: Res<TouchOwners>,
+    mut buttons: Query<(Entity, &mut VirtualButton, &mut BackgroundColor)>,
+    mut players: Query<&mut ActionState<Action>, With<Player>>,
+) {
+    for (entity, mut button, mut color) in &mut buttons {
+        if let Some(id) = button.touch {
+            if touches.get_pressed(id).is_none() {
+                button.touch = None;
+            }
+        }
+        // a second finger on the button carries on when the first lifts
+        if button.touch.is_none() {
+            button.touch = owners
+                .owned_by(TouchOwner::Button(entity))
+                .find(|id| touches.get_pressed(*id).is_some());
+        }
+        let held = button.touch.is_some();
+        let wanted = if held { PRESSED_COLOR } else { BUTTON_COLOR };
+        if color.0 != wanted {
+            color.0 = wanted;
+        }
+
+        if let Ok(mut action_state) = players.get_single_mut() {
+            if held && button.was_held {
+                // leafwing released it for having no bound input held, and a
+                // fresh press would count as just pressed every frame
+                if let Some(data) = action_state.action_data_mut(&button.action) {
+                    data.state = ButtonState::Pressed;
+                }
+            } else if held {
+                action_state.press(&button.action);
+            }
+        }
+        button.was_held = held;
+    }
+}
@@ -0,0 +1,559 @@
+//! Weapon definitions, and the player's small inventory of them.
+//!
+//! The player carries up to [`MAX_WEAPONS`] in a [`WeaponInventory`] and
+//! cycles through them with [`Action::SwitchWeapon`], a swipe on touch
+//! screens, or picks one directly with the number keys. The selected weapon
+//! is mirrored into the player's [`Weapon`] component, so everything that
+//! fires or aims just reads that; ammo is kept per slot in the inventory.
+//! Walking over a [`WeaponPickup`] adds its weapon, or swaps out the one in
+//! hand when the inventory is full, and a toast names the new weapon.
+
+use std::borrow::Cow;
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    despawn::DespawnQueue,
+    events::WeaponSwitched,
+    fonts::UiFonts,
+    layout::{Mirrored, StickSide},
+    rng::GameRng,
+    state::GameState,
+    status::StatusEffect,
+    toast::{Toast, ToastKind},
+    Action, Player,
+};
+
+/// Weapons the player can carry at once.
+pub const MAX_WEAPONS: usize = 3;
+/// How close the player must get to a pickup to collect it.
+const PICKUP_RADIUS: f32 = 24.;
+const PICKUP_SIZE: f32 = 14.;
+const PICKUP_COLOR: Color = Color::rgb(0.4, 0.8, 1.);
+/// Number keys selecting the weapon in the matching slot.
+const SLOT_KEYS: [KeyCode; MAX_WEAPONS] = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3];
+
+/// How shots scatter around the aim direction.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct Spread {
+    /// Half-angle, in radians, of the random cone each projectile is
+    /// scattered within.
+    pub cone: f32,
+    /// Projectiles fired per trigger pull.
+    pub pellets: u32,
+    /// Total angle, in radians, the pellets are fanned across.
+    pub arc: f32,
+    /// Extra cone half-angle added at full movement speed, scaled down
+    /// linearly when slower. Zero disables the moving-while-firing penalty.
+    pub moving_penalty: f32,
+}
+
+impl Spread {
+    /// Near-perfect accuracy.
+    pub const TIGHT: Spread = Spread {
+        cone: 0.01,
+        pellets: 1,
+        arc: 0.,
+        moving_penalty: 0.05,
+    };
+
+    /// Several pellets across a wide arc.
+    pub const SHOTGUN: Spread = Spread {
+        cone: 0.05,
+        pellets: 6,
+        arc: 0.6,
+        moving_penalty: 0.1,
+    };
+
+    /// Directions for one trigger pull aimed along `aim`, with the player
+    /// moving at `speed_fraction` of its maximum speed.
+    pub fn shot_directions(&self, aim: Vec2, speed_fraction: f32, rng: &mut GameRng) -> Vec<Vec2> {
+        let cone = self.cone + self.moving_penalty * speed_fraction.clamp(0., 1.);
+        let pellets = self.pellets.max(1);
+        let step = if pellets > 1 {
+            self.arc / (pellets - 1) as f32
+        } else {
+            0.
+        };
+
+        (0..pellets)
+            .map(|i| {
+                let base = if pellets > 1 {
+                    -self.arc / 2. + step * i as f32
+                } else {
+                    0.
+                };
+                Vec2::from_angle(base + rng.range(-cone, cone)).rotate(aim)
+            })
+            .collect()
+    }
+}
+
+/// Behaviour attached to every projectile a weapon fires; see
+/// [`crate::projectile`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectileModifiers {
+    /// Extra enemies each projectile passes through.
+    pub pierce: u32,
+    /// Times each projectile ricochets before expiring at a wall.
+    pub bounce: u32,
+}
+
+impl ProjectileModifiers {
+    /// Both sets of modifiers stacked.
+    pub fn with(self, other: ProjectileModifiers) -> Self {
+        Self {
+            pierce: self.pierce + other.pierce,
+            bounce: self.bounce + other.bounce,
+        }
+    }
+}
+
+/// Shots that split into a ring of smaller projectiles; see
+/// [`crate::projectile::Cluster`].
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct ClusterConfig {
+    /// Projectiles released by the split.
+    pub children: u32,
+    /// Seconds after firing the shot splits, if it hasn't hit anything.
+    pub fuse: f32,
+    /// Angle, in radians, the children are spread across, centered on the
+    /// parent's heading; a full turn gives an even ring.
+    pub spread: f32,
+    pub child_speed: f32,
+    pub child_lifetime: f32,
+    pub child_damage: f32,
+}
+
+/// Shots that hit everything along a line at once instead of flying; see
+/// [`crate::projectile::Beam`].
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct BeamConfig {
+    pub width: f32,
+    /// Seconds the beam stays on screen, fading out.
+    pub duration: f32,
+}
+
+/// Lobbed shots that arc to a point on the ground instead of flying
+/// straight; see [`crate::projectile::Ballistic`].
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct ArcConfig {
+    /// Pull toward the bottom of the screen, in pixels per second squared.
+    pub gravity: f32,
+    /// Shortest flight, in seconds, so even a close target gets an arc.
+    pub min_flight: f32,
+}
+
+impl ArcConfig {
+    /// Where a lob lands when aimed with the stick value `aim`: its
+    /// direction, at its length times `range` from `origin`.
+    pub fn landing_point(origin: Vec2, aim: Vec2, range: f32) -> Vec2 {
+        origin + aim.clamp_length_max(1.) * range
+    }
+
+    /// Launch velocity and flight time, in seconds, to land on `target`
+    /// from `origin`, crossing the ground at `speed` with gravity pulling
+    /// along the normalized `down`.
+    pub fn launch(&self, origin: Vec2, target: Vec2, speed: f32, down: Vec2) -> (Vec2, f32) {
+        let offset = target - origin;
+        let flight = (offset.length() / speed.max(f32::EPSILON)).max(self.min_flight);
+        // offset = velocity * flight + gravity * flight² / 2
+        let velocity = offset / flight - down * self.gravity * flight / 2.;
+        (velocity, flight)
+    }
+}
+
+#[derive(Component, Debug, Clone, Reflect, Serialize, Deserialize)]
+pub struct Weapon {
+    /// Shown in the HUD, and what tells two weapons apart for pickups.
+    pub name: Cow<'static, str>,
+    /// Trigger pulls per second.
+    pub fire_rate: f32,
+    pub projectile_speed: f32,
+    /// Seconds a projectile lives before expiring.
+    pub projectile_lifetime: f32,
+    /// Damage dealt by each projectile.
+    pub damage: f32,
+    pub spread: Spread,
+    #[serde(default)]
+    pub modifiers: ProjectileModifiers,
+    /// Split each shot into smaller ones after a fuse or on impact.
+    pub cluster: Option<ClusterConfig>,
+    /// Lob shots at a ground position rather than firing them along the
+    /// aim.
+    pub arc: Option<ArcConfig>,
+    /// Fire a beam reaching [`Weapon::range`] instead of projectiles.
+    pub beam: Option<BeamConfig>,
+    /// Trigger pulls of ammo the weapon comes with, or `None` for
+    /// unlimited.
+    pub ammo: Option<u32>,
+    /// Put on every enemy its shots damage.
+    #[serde(default)]
+    pub effects: Vec<StatusEffect>,
+}
+
+impl Weapon {
+    pub fn pistol() -> Self {
+        Self {
+            name: Cow::Borrowed("Pistol"),
+            fire_rate: 4.,
+            projectile_speed: 400.,
+            projectile_lifetime: 0.75,
+            damage: 10.,
+            spread: Spread::TIGHT,
+            modifiers: ProjectileModifiers::default(),
+            cluster: None,
+            arc: None,
+            beam: None,
+            ammo: None,
+            effects: Vec::new(),
+        }
+    }
+
+    pub fn shotgun() -> Self {
+        Self {
+            name: Cow::Borrowed("Shotgun"),
+            fire_rate: 1.2,
+            projectile_speed: 350.,
+            projectile_lifetime: 0.4,
+            damage: 6.,
+            spread: Spread::SHOTGUN,
+            modifiers: ProjectileModifiers::default(),
+            cluster: None,
+            arc: None,
+            beam: None,
+            ammo: Some(24),
+            effects: Vec::new(),
+        }
+    }
+
+    pub fn grenade() -> Self {
+        Self {
+            name: Cow::Borrowed("Grenade launcher"),
+            fire_rate: 0.8,
+            projectile_speed: 250.,
+            projectile_lifetime: 1.,
+            damage: 15.,
+            spread: Spread::TIGHT,
+            modifiers: ProjectileModifiers::default(),
+            cluster: Some(ClusterConfig {
+                children: 8,
+                fuse: 0.6,
+                spread: std::f32::consts::TAU,
+                child_speed: 300.,
+                child_lifetime: 0.3,
+                child_damage: 8.,
+            }),
+            arc: Some(ArcConfig {
+                gravity: 900.,
+                min_flight: 0.35,
+            }),
+            beam: None,
+            ammo: Some(8),
+            // the blast sets what it hits alight
+            effects: vec![StatusEffect::burn(4., 3.)],
+        }
+    }
+
+    pub fn laser() -> Self {
+        Self {
+            name: Cow::Borrowed("Laser"),
+            fire_rate: 6.,
+            projectile_speed: 1000.,
+            projectile_lifetime: 0.32,
+            damage: 5.,
+            spread: Spread {
+                cone: 0.,
+                pellets: 1,
+                arc: 0.,
+                moving_penalty: 0.02,
+            },
+            // cuts through the front of a pack
+            modifiers: ProjectileModifiers {
+                pierce: 2,
+                bounce: 0,
+            },
+            cluster: None,
+            arc: None,
+            beam: Some(BeamConfig {
+                width: 4.,
+                duration: 0.1,
+            }),
+            ammo: Some(60),
+            effects: vec![StatusEffect::slow(0.3, 1.)],
+        }
+    }
+
+    /// Weapons enemies can drop, as opposed to the pistol everyone starts
+    /// with.
+    pub fn drops() -> [Weapon; 3] {
+        [Weapon::shotgun(), Weapon::grenade(), Weapon::laser()]
+    }
+
+    /// How far a projectile travels before expiring.
+    pub fn range(&self) -> f32 {
+        self.projectile_speed * self.projectile_lifetime
+    }
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Self::pistol()
+    }
+}
+
+#[derive(Debug, Clone, Reflect)]
+pub struct WeaponSlot {
+    pub weapon: Weapon,
+    /// Trigger pulls left, `None` for unlimited.
+    pub ammo: Option<u32>,
+}
+
+impl WeaponSlot {
+    pub fn new(weapon: Weapon) -> Self {
+        Self {
+            ammo: weapon.ammo,
+            weapon,
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct WeaponInventory {
+    slots: Vec<WeaponSlot>,
+    selected: usize,
+}
+
+impl Default for WeaponInventory {
+    fn default() -> Self {
+        Self::new([Weapon::default()])
+    }
+}
+
+impl WeaponInventory {
+    /// An inventory holding the first [`MAX_WEAPONS`] of `weapons`, with the
+    /// first selected.
+    pub fn new(weapons: impl IntoIterator<Item = Weapon>) -> Self {
+        Self {
+            slots: weapons
+                .into_iter()
+                .take(MAX_WEAPONS)
+                .map(WeaponSlot::new)
+                .collect(),
+            selected: 0,
+        }
+    }
+
+    pub fn slots(&self) -> &[WeaponSlot] {
+        &self.slots
+    }
+
+    pub fn slots_mut(&mut self) -> &mut [WeaponSlot] {
+        &mut self.slots
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn current(&self) -> Option<&WeaponSlot> {
+        self.slots.get(self.selected)
+    }
+
+    /// Selects the weapon in `slot`, returning whether the selection
+    /// changed.
+    pub fn select(&mut self, slot: usize) -> bool {
+        if slot >= self.slots.len() || slot == self.selected {
+            return false;
+        }
+        self.selected = slot;
+        true
+    }
+
+    /// Selects the next weapon, wrapping around.
+    pub fn cycle(&mut self) -> bool {
+        self.select((self.selected + 1) % self.slots.len().max(1))
+    }
+
+    /// Uses up `pulls` trigger pulls of the current weapon's ammo, returning
+    /// false, and using none, if there isn't enough.
+    pub fn spend_ammo(&mut self, pulls: u32) -> bool {
+        let Some(slot) = self.slots.get_mut(self.selected) else {
+            return false;
+        };
+        match &mut slot.ammo {
+            None => true,
+            Some(ammo) if *ammo >= pulls => {
+                *ammo -= pulls;
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    /// Adds `weapon`, returning the slot it went into. A weapon already
+    /// carried is refilled instead and `None` returned; with no room left,
+    /// the weapon in hand is swapped out.
+    pub fn pick_up(&mut self, weapon: Weapon) -> Option<usize> {
+        if let Some(slot) = self
+            .slots
+            .iter_mut()
+            .find(|slot| slot.weapon.name == weapon.name)
+        {
+            slot.ammo = weapon.ammo;
+            return None;
+        }
+        if self.slots.len() < MAX_WEAPONS {
+            self.slots.push(WeaponSlot::new(weapon));
+            return Some(self.slots.len() - 1);
+        }
+        self.slots[self.selected] = WeaponSlot::new(weapon);
+        Some(self.selected)
+    }
+}
+
+/// A weapon lying in the world, collected by walking over it.
+#[derive(Component, Debug, Clone)]
+pub struct WeaponPickup(pub Weapon);
+
+pub fn spawn_weapon_pickup(commands: &mut Commands, weapon: Weapon, position: Vec2) -> Entity {
+    commands
+        .spawn((
+            WeaponPickup(weapon),
+            SpriteBundle {
+                transform: Transform::from_translation(position.extend(0.5))
+                    .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+                sprite: Sprite {
+                    color: PICKUP_COLOR,
+                    custom_size: Some(Vec2::splat(PICKUP_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .id()
+}
+
+#[derive(Component)]
+struct WeaponHud;
+
+pub struct WeaponPlugin;
+
+impl Plugin for WeaponPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Weapon>()
+            .register_type::<WeaponInventory>()
+            .add_systems(Startup, spawn_weapon_hud)
+            .add_systems(
+                Update,
+                (
+                    (switch_weapons, collect_weapon_pickups).run_if(in_state(GameState::Playing)),
+                    update_weapon_hud,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn switch_weapons(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut players: Query<
+        (
+            Entity,
+            &ActionState<Action>,
+            &mut WeaponInventory,
+            &mut Weapon,
+        ),
+        With<Player>,
+    >,
+    mut switched: EventWriter<WeaponSwitched>,
+) {
+    for (entity, action_state, mut inventory, mut weapon) in &mut players {
+        let direct = SLOT_KEYS.iter().position(|key| keys.just_pressed(*key));
+        let changed = match direct {
+            Some(slot) => inventory.select(slot),
+            None if action_state.just_pressed(&Action::SwitchWeapon) => inventory.cycle(),
+            None => false,
+        };
+        if !changed {
+            continue;
+        }
+        if let Some(slot) = inventory.current() {
+            *weapon = slot.weapon.clone();
+        }
+        switched.send(WeaponSwitched {
+            entity,
+            slot: inventory.selected(),
+        });
+    }
+}
+
+fn collect_weapon_pickups(
+    mut despawns: ResMut<DespawnQueue>,
+    pickups: Query<(Entity, &WeaponPickup, &Transform)>,
+    mut players: Query<
+        (Entity, &Transform, &mut WeaponInventory, &mut Weapon),
+        (With<Player>, Without<WeaponPickup>),
+    >,
+    mut switched: EventWriter<WeaponSwitched>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let Ok((player, transform, mut inventory, mut weapon)) = players.get_single_mut() else {
+        return;
+    };
+    let position = transform.translation.truncate();
+    for (entity, pickup, pickup_transform) in &pickups {
+        if pickup_transform.translation.truncate().distance(position) > PICKUP_RADIUS {
+            continue;
+        }
+        despawns.despawn(entity);
+        let Some(slot) = inventory.pick_up(pickup.0.clone()) else {
+            continue;
+        };
+        // a new weapon goes straight into the player's hands
+        inventory.select(slot);
+        *weapon = pickup.0.clone();
+        toasts
+            .send(Toast::new(format!("Picked up {}", pickup.0.name)).with_kind(ToastKind::Pickup));
+        switched.send(WeaponSwitched {
+            entity: player,
+            slot,
+        });
+    }
+}
+
+fn spawn_weapon_hud(mut commands: Commands, fonts: Res<UiFonts>) {
+    commands.spawn((
+        WeaponHud,
+        Mirrored::new(StickSide::Look, 12.),
+        TextBundle::from_section("", fonts.style(18., Color::WHITE)).with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(12.),
+            ..default()
+        }),
+    ));
+}
+
+/// HUD line for the weapon in `slot`, like "Shotgun  18".
+fn hud_text(slot: &WeaponSlot) -> String {
+    match slot.ammo {
+        Some(ammo) => format!("{}  {ammo}", slot.weapon.name),
+        None => slot.weapon.name.to_string(),
+    }
+}
+
+fn update_weapon_hud(
+    players: Query<&WeaponInventory, (With<Player>, Changed<WeaponInventory>)>,
+    mut huds: Query<&mut Text, With<WeaponHud>>,
+) {
+    let Ok(inventory) = players.get_single() else {
+        return;
+    };
+    let text = inventory.current().map(hud_text).unwrap_or_default();
+    for mut hud in &mut huds {
+        hud.sections[0].value = text.clone();
+    }
+}
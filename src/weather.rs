@@ -0,0 +1,510 @@
+//! Rain.
+//!
+//! [`WeatherState`] says how hard it rains and which way the wind blows;
+//! gameplay can read it, and change it, like any other resource. The drops
+//! are a fixed set of thin sprites spawned once, so the renderer draws
+//! them as one batch and nothing is spawned or despawned while it rains.
+//! [`WeatherState::intensity`] times [`WeatherConfig::max_drops`], scaled
+//! by [`EffectsQuality::density`], picks how many of them are in use, and
+//! the rest stay hidden. It rains harder at night, by up to
+//! [`DayNightConfig::night_rain`], but a dry night stays dry.
+//!
+//! Each drop falls down the screen, leaning with the wind, for a random
+//! time and lands where it is, sometimes splashing with a small
+//! [`EmitParticles`] burst. It then starts over at a random point in or
+//! just upwind of the view, so the rain always covers whatever the camera
+//! sees.
+//!
+//! The ground gets wet with it. [`Wetness::level`] rises while it rains,
+//! reaching 1 after [`WeatherConfig::wet_time`] seconds of full rain, and
+//! falls back to 0 over [`WeatherConfig::dry_time`] once it stops.
+//! [`WeatherConfig::puddles`] puddles lie about the open parts of the
+//! arena, each filling once the ground is wet past its own threshold, so
+//! the first few show after a short shower and more join in as it keeps on.
+//! They reflect the night lights with a screen-space trick: a small glint
+//! in the light's color on the side of the puddle away from the light,
+//! brighter the closer and the brighter the light. Nothing in the rules
+//! reads the wetness yet; movement that should slip on wet ground can go by
+//! [`Wetness::level`].
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+use crate::{
+    camera::{visible_rect, CameraView, MainCamera},
+    daynight::{DayNightConfig, Light2d, TimeOfDay},
+    particles::{EmitParticles, ParticleEffect},
+    pathfinding::NavGrid,
+    quality::EffectsQuality,
+    rng::GameRng,
+    tween::Ease,
+};
+
+const WEATHER_SEED: u64 = 0x5EA7_4E12;
+
+/// Above gameplay and effects, below the camera.
+const RAIN_Z: f32 = 3.;
+/// On the ground: above the level's tiles, below everything on them.
+const PUDDLE_Z: f32 = -0.8;
+/// Side length of the puddle image, in pixels.
+const PUDDLE_IMAGE_SIZE: u32 = 64;
+/// How far from a light, in its radii, a puddle still shows it.
+const REFLECT_REACH: f32 = 1.5;
+/// How bright a glint is next to a light at full strength.
+const REFLECTIVITY: f32 = 0.8;
+/// How big a glint is against its puddle.
+const GLINT_SCALE: f32 = 0.35;
+
+const SPLASH: ParticleEffect = ParticleEffect {
+    lifetime: 0.25,
+    size: 2.,
+    velocity: Vec2::new(0., 40.),
+    spread: std::f32::consts::PI,
+    gravity: Vec2::ZERO,
+    start_color: Color::rgba(0.7, 0.8, 1., 0.6),
+    end_color: Color::rgba(0.7, 0.8, 1., 0.),
+    start_scale: 1.,
+    end_scale: 0.5,
+    ease: Ease::QuadOut,
+    z: RAIN_Z,
+};
+
+#[derive(Resource, Reflect, Debug, Clone, Copy)]
+#[reflect(Resource)]
+pub struct WeatherState {
+    /// How hard it rains, `0..=1`. 0 is dry.
+    pub intensity: f32,
+    /// Sideways drift of the drops, in screen space units per second.
+    pub wind: Vec2,
+}
+
+impl Default for WeatherState {
+    fn default() -> Self {
+        Self {
+            intensity: 0.4,
+            wind: Vec2::new(-60., 0.),
+        }
+    }
+}
+
+impl WeatherState {
+    pub fn is_raining(&self) -> bool {
+        self.intensity > 0.
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct WeatherConfig {
+    /// Drops in use at full intensity and quality.
+    pub max_drops: usize,
+    /// Speed drops fall down the screen at.
+    pub fall_speed: f32,
+    /// Shortest and longest time a drop falls before it lands.
+    pub fall_time: (f32, f32),
+    pub drop_size: Vec2,
+    pub color: Color,
+    /// Chance a landing drop splashes.
+    pub splash_chance: f32,
+    pub splash_count: u32,
+    /// Puddles in the arena.
+    pub puddles: usize,
+    /// Size of the biggest puddle when full.
+    pub puddle_size: Vec2,
+    pub puddle_color: Color,
+    /// Seconds of full rain that soak the ground.
+    pub wet_time: f32,
+    /// Seconds a soaked ground takes to dry.
+    pub dry_time: f32,
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        Self {
+            max_drops: 400,
+            fall_speed: 700.,
+            fall_time: (0.3, 0.8),
+            drop_size: Vec2::new(1.5, 14.),
+            color: Color::rgba(0.7, 0.8, 1., 0.35),
+            splash_chance: 0.3,
+            splash_count: 3,
+            puddles: 24,
+            puddle_size: Vec2::new(90., 40.),
+            puddle_color: Color::rgba(0.35, 0.45, 0.6, 0.4),
+            wet_time: 20.,
+            dry_time: 40.,
+        }
+    }
+}
+
+/// How wet the ground is.
+#[derive(Resource, Reflect, Debug, Clone, Default)]
+#[reflect(Resource)]
+pub struct Wetness {
+    /// `0..=1`, 0 being dry.
+    pub level: f32,
+    #[reflect(ignore)]
+    puddles: Vec<Puddle>,
+}
+
+/// Where a puddle lies, and how wet the ground must be for it to show.
+#[derive(Debug, Clone, Copy)]
+struct Puddle {
+    center: Vec2,
+    /// Half its size when full.
+    half_size: Vec2,
+    threshold: f32,
+}
+
+impl Puddle {
+    /// How full it is at a wetness of `level`, `0..=1`.
+    fn fill(&self, level: f32) -> f32 {
+        ((level - self.threshold) / (1. - self.threshold).max(f32::EPSILON)).clamp(0., 1.)
+    }
+}
+
+/// The sprite of the `index`th puddle in [`Wetness`].
+#[derive(Component, Debug)]
+struct PuddleSprite(usize);
+
+/// The light reflected in a puddle.
+#[derive(Component, Debug)]
+struct Glint;
+
+#[derive(Resource)]
+struct PuddleImage(Handle<Image>);
+
+/// A rain drop, in use while visible. Lands once `remaining` runs out.
+#[derive(Component, Debug, Default)]
+struct RainDrop {
+    remaining: f32,
+}
+
+#[derive(Resource)]
+struct WeatherRng(GameRng);
+
+impl Default for WeatherRng {
+    fn default() -> Self {
+        Self(GameRng::new(WEATHER_SEED))
+    }
+}
+
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<WeatherState>()
+            .register_type::<WeatherConfig>()
+            .init_resource::<WeatherState>()
+            .init_resource::<WeatherConfig>()
+            .register_type::<Wetness>()
+            .init_resource::<WeatherRng>()
+            .init_resource::<Wetness>()
+            .add_systems(Startup, create_puddle_image)
+            .add_systems(
+                Update,
+                (
+                    spawn_drops.run_if(resource_changed::<WeatherConfig>),
+                    fall,
+                    spawn_puddles.run_if(
+                        resource_changed::<WeatherConfig>.or_else(resource_changed::<NavGrid>),
+                    ),
+                    soak,
+                    show_puddles,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// How hard it rains, with the night's extra rain.
+fn rain_intensity(weather: &WeatherState, day_night: &DayNightConfig, time: &TimeOfDay) -> f32 {
+    if weather.is_raining() {
+        (weather.intensity + day_night.night_rain * time.night()).clamp(0., 1.)
+    } else {
+        0.
+    }
+}
+
+/// (Re)creates the drop sprites, all hidden until [`fall`] puts them to use.
+fn spawn_drops(
+    mut commands: Commands,
+    config: Res<WeatherConfig>,
+    drops: Query<Entity, With<RainDrop>>,
+) {
+    for entity in &drops {
+        commands.entity(entity).despawn();
+    }
+    for _ in 0..config.max_drops {
+        commands.spawn((
+            RainDrop::default(),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: config.color,
+                    custom_size: Some(config.drop_size),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ));
+    }
+}
+
+fn fall(
+    time: Res<Time>,
+    weather: Res<WeatherState>,
+    config: Res<WeatherConfig>,
+    quality: Res<EffectsQuality>,
+    view: Res<CameraView>,
+    day_night: Res<DayNightConfig>,
+    time_of_day: Res<TimeOfDay>,
+    mut rng: ResMut<WeatherRng>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut drops: Query<(&mut RainDrop, &mut Transform, &mut Visibility)>,
+    mut splashes: EventWriter<EmitParticles>,
+) {
+    let Some(area) = cameras
+        .get_single()
+        .ok()
+        .and_then(|(camera, transform)| visible_rect(camera, transform))
+    else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    let velocity = view.to_world(Vec2::NEG_Y * config.fall_speed + weather.wind);
+    let rotation = Quat::from_rotation_arc_2d(Vec2::Y, velocity.normalize_or_zero());
+    // start upwind of the view, so the edge it drifts away from isn't bare
+    let travel = velocity * config.fall_time.1;
+    let start = Rect::from_center_size(area.center() - travel / 2., area.size() + travel.abs());
+    let intensity = rain_intensity(&weather, &day_night, &time_of_day);
+    let active = (intensity * quality.scale_count(config.max_drops) as f32).round() as usize;
+
+    for (index, (mut drop, mut transform, mut visibility)) in drops.iter_mut().enumerate() {
+        if index >= active {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        if *visibility == Visibility::Hidden {
+            // just put to use: scatter through the fall so drops don't
+            // land in waves
+            *visibility = Visibility::Visible;
+            let point = Vec2::new(
+                rng.0.range(area.min.x, area.max.x),
+                rng.0.range(area.min.y, area.max.y),
+            );
+            transform.translation = point.extend(RAIN_Z);
+            drop.remaining = rng.0.range(0., config.fall_time.1);
+        }
+
+        drop.remaining -= dt;
+        if drop.remaining <= 0. {
+            let landed = transform.translation.truncate();
+            if quality.extras() && area.contains(landed) && rng.0.f32() < config.splash_chance {
+                splashes.send(EmitParticles {
+                    effect: SPLASH,
+                    position: landed,
+                    count: config.splash_count,
+                });
+            }
+            let point = Vec2::new(
+                rng.0.range(start.min.x, start.max.x),
+                rng.0.range(start.min.y, start.max.y),
+            );
+            transform.translation = point.extend(RAIN_Z);
+            drop.remaining = rng.0.range(config.fall_time.0, config.fall_time.1);
+        }
+        transform.translation += (velocity * dt).extend(0.);
+        transform.rotation = rotation;
+    }
+}
+
+/// A soft-edged disc, stretched into each puddle's shape.
+fn puddle_image() -> Image {
+    let size = PUDDLE_IMAGE_SIZE;
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    let radius = size as f32 / 2.;
+    for y in 0..size {
+        for x in 0..size {
+            let offset = Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - Vec2::splat(radius);
+            // flat in the middle, fading over the outer third
+            let edge = ((1. - offset.length() / radius) * 3.).clamp(0., 1.);
+            let alpha = (edge * 255.).round() as u8;
+            data.extend_from_slice(&[255, 255, 255, alpha]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+fn create_puddle_image(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    commands.insert_resource(PuddleImage(images.add(puddle_image())));
+}
+
+/// Scatters the puddles through the open cells of the arena.
+fn spawn_puddles(
+    mut commands: Commands,
+    config: Res<WeatherConfig>,
+    grid: Res<NavGrid>,
+    image: Res<PuddleImage>,
+    mut rng: ResMut<WeatherRng>,
+    mut wetness: ResMut<Wetness>,
+    sprites: Query<Entity, With<PuddleSprite>>,
+) {
+    for entity in &sprites {
+        commands.entity(entity).despawn_recursive();
+    }
+    wetness.puddles.clear();
+    let area = grid.area();
+    // a few tries each, so a walled arena doesn't loop forever
+    for _ in 0..config.puddles * 4 {
+        if wetness.puddles.len() >= config.puddles {
+            break;
+        }
+        let center = Vec2::new(
+            rng.0.range(area.min.x, area.max.x),
+            rng.0.range(area.min.y, area.max.y),
+        );
+        if grid.is_blocked(grid.cell(center)) {
+            continue;
+        }
+        let index = wetness.puddles.len();
+        wetness.puddles.push(Puddle {
+            center,
+            half_size: config.puddle_size / 2. * rng.0.range(0.5, 1.),
+            threshold: rng.0.range(0., 0.7),
+        });
+        commands
+            .spawn((
+                PuddleSprite(index),
+                SpriteBundle {
+                    texture: image.0.clone(),
+                    sprite: Sprite {
+                        color: Color::NONE,
+                        ..default()
+                    },
+                    transform: Transform::from_translation(center.extend(PUDDLE_Z)),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    Glint,
+                    SpriteBundle {
+                        texture: image.0.clone(),
+                        sprite: Sprite {
+                            color: Color::NONE,
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(0., 0., 0.05),
+                        ..default()
+                    },
+                ));
+            });
+    }
+}
+
+fn soak(
+    time: Res<Time>,
+    weather: Res<WeatherState>,
+    config: Res<WeatherConfig>,
+    day_night: Res<DayNightConfig>,
+    time_of_day: Res<TimeOfDay>,
+    mut wetness: ResMut<Wetness>,
+) {
+    let dt = time.delta_seconds();
+    let intensity = rain_intensity(&weather, &day_night, &time_of_day);
+    let level = if intensity > 0. {
+        wetness.level + dt * intensity / config.wet_time.max(0.01)
+    } else {
+        wetness.level - dt / config.dry_time.max(0.01)
+    }
+    .clamp(0., 1.);
+    if level != wetness.level {
+        wetness.level = level;
+    }
+}
+
+fn show_puddles(
+    config: Res<WeatherConfig>,
+    quality: Res<EffectsQuality>,
+    wetness: Res<Wetness>,
+    lights: Query<(&Light2d, &Sprite, &GlobalTransform, &ViewVisibility), Without<PuddleSprite>>,
+    mut puddles: Query<(&PuddleSprite, &mut Sprite, &mut Visibility, &Children), Without<Light2d>>,
+    mut glints: Query<
+        (&mut Transform, &mut Sprite),
+        (With<Glint>, Without<PuddleSprite>, Without<Light2d>),
+    >,
+) {
+    // without the night lights there is nothing to reflect
+    let lights: Vec<_> = lights
+        .iter()
+        .filter(|(.., visible)| quality.lights() && visible.get())
+        .map(|(light, sprite, transform, _)| {
+            let radius = sprite.custom_size.map_or(0., |size| size.x / 2.);
+            (
+                transform.translation().truncate(),
+                radius * REFLECT_REACH,
+                light.color.with_a(sprite.color.a()),
+            )
+        })
+        .collect();
+
+    for (index, mut sprite, mut visibility, children) in &mut puddles {
+        let Some(puddle) = wetness.puddles.get(index.0) else {
+            continue;
+        };
+        let fill = puddle.fill(wetness.level);
+        if fill <= 0. {
+            if *visibility != Visibility::Hidden {
+                *visibility = Visibility::Hidden;
+            }
+            continue;
+        }
+        *visibility = Visibility::Inherited;
+        // spreads out as it fills
+        let size = puddle.half_size * 2. * (0.4 + 0.6 * fill);
+        sprite.custom_size = Some(size);
+        sprite.color = config.puddle_color.with_a(config.puddle_color.a() * fill);
+
+        // the light standing out most over this puddle
+        let reflected = lights
+            .iter()
+            .filter_map(|(position, reach, color)| {
+                let offset = puddle.center - *position;
+                let strength = color.a() * (1. - offset.length() / reach.max(1.));
+                (strength > 0.).then_some((offset, *color, strength))
+            })
+            .max_by(|a, b| a.2.total_cmp(&b.2));
+        for &child in children {
+            let Ok((mut transform, mut glint)) = glints.get_mut(child) else {
+                continue;
+            };
+            let Some((offset, color, strength)) = reflected else {
+                glint.color = Color::NONE;
+                continue;
+            };
+            // on the far side from the light, as a reflection off a flat
+            // surface seen from above would be
+            let side = offset.normalize_or_zero() * size / 4.;
+            transform.translation = side.extend(transform.translation.z);
+            glint.custom_size = Some(size * GLINT_SCALE);
+            glint.color = color.with_a((strength * REFLECTIVITY * fill).min(1.));
+        }
+    }
+}
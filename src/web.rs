@@ -0,0 +1,259 @@
+//! Browser interop.
+//!
+//! The listeners that feed [`WebGlContextEvent`] are hooked up on `wasm32`
+//! only; elsewhere the event is never sent and the handling here is inert.
+//!
+//! Closing or refreshing a tab gives the game no frame to save in, as the
+//! browser stops rendering hidden pages. Whatever must survive that is kept
+//! up to date in [`ExitSave`], already serialized, and written straight to
+//! storage from the browser's `visibilitychange` (when the page is hidden),
+//! `pagehide` and `beforeunload` callbacks. Hiding comes first and is the
+//! one mobile browsers reliably deliver, so the data is written then rather
+//! than at unload. This is `wasm32` only: elsewhere staging is a no-op and
+//! the usual saves on focus loss and suspension cover quitting.
+
+use std::sync::{Arc, Mutex};
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::Serialize;
+
+use crate::{fonts::UiFonts, state::GameState, storage};
+
+/// Latest serialized copies of data to write to storage if the page goes
+/// away.
+#[derive(Resource, Clone, Default)]
+pub struct ExitSave {
+    staged: Arc<Mutex<HashMap<&'static str, String>>>,
+}
+
+impl ExitSave {
+    /// Replaces what is written under `key` if the page goes away.
+    pub fn stage<T: Serialize>(&self, key: &'static str, value: &T) {
+        if !cfg!(target_arch = "wasm32") {
+            return;
+        }
+        if let Some(raw) = storage::serialize(key, value) {
+            self.staged.lock().unwrap().insert(key, raw);
+        }
+    }
+
+    /// Stops writing `key` if the page goes away, for data that has since
+    /// been deleted.
+    pub fn unstage(&self, key: &'static str) {
+        self.staged.lock().unwrap().remove(key);
+    }
+
+    /// Writes everything staged.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn flush(&self) {
+        for (key, raw) in self.staged.lock().unwrap().iter() {
+            storage::save_serialized(key, raw);
+        }
+    }
+}
+
+/// The browser dropped or gave back the canvas' WebGL context.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub enum WebGlContextEvent {
+    Lost,
+    Restored,
+}
+
+#[derive(Resource, Debug, Default)]
+struct WebGlContextState {
+    lost: bool,
+}
+
+#[derive(Component)]
+struct ContextLostOverlay;
+
+pub struct WebPlugin;
+
+impl Plugin for WebPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<WebGlContextEvent>()
+            .init_resource::<WebGlContextState>()
+            .init_resource::<ExitSave>()
+            .add_systems(Update, (handle_context_events, resume_on_tap).chain());
+
+        #[cfg(target_arch = "wasm32")]
+        app.init_resource::<interop::WebEvents>().add_systems(
+            PreUpdate,
+            (interop::install_listeners, interop::forward_events).chain(),
+        );
+    }
+}
+
+fn handle_context_events(
+    mut commands: Commands,
+    mut events: EventReader<WebGlContextEvent>,
+    mut context: ResMut<WebGlContextState>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut images: ResMut<Assets<Image>>,
+    fonts: Res<UiFonts>,
+    overlays: Query<(), With<ContextLostOverlay>>,
+) {
+    for event in events.read() {
+        match event {
+            WebGlContextEvent::Lost => {
+                warn!("WebGL context lost, pausing");
+                context.lost = true;
+                if *state.get() == GameState::Playing {
+                    next_state.set(GameState::Paused);
+                }
+                if overlays.is_empty() {
+                    spawn_overlay(&mut commands, &fonts);
+                }
+            }
+            WebGlContextEvent::Restored => {
+                info!("WebGL context restored, reuploading textures");
+                context.lost = false;
+                // touching every image marks it modified, which makes the
+                // renderer upload it again to the new context
+                let ids: Vec<_> = images.ids().collect();
+                for id in ids {
+                    images.get_mut(id);
+                }
+            }
+        }
+    }
+}
+
+fn spawn_overlay(commands: &mut Commands, fonts: &UiFonts) {
+    commands
+        .spawn((
+            ContextLostOverlay,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.85).into(),
+                z_index: ZIndex::Global(30),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Tap to resume",
+                fonts.bold(32., Color::WHITE),
+            ));
+        });
+}
+
+fn resume_on_tap(
+    mut commands: Commands,
+    context: Res<WebGlContextState>,
+    touches: Res<Touches>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    overlays: Query<Entity, With<ContextLostOverlay>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if context.lost || overlays.is_empty() {
+        return;
+    }
+    if !touches.any_just_pressed() && !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    for entity in &overlays {
+        commands.entity(entity).despawn_recursive();
+    }
+    next_state.set(GameState::Playing);
+}
+
+#[cfg(target_arch = "wasm32")]
+mod interop {
+    use std::sync::{Arc, Mutex};
+
+    use bevy::prelude::*;
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    use super::{ExitSave, WebGlContextEvent};
+
+    /// Events recorded by the browser callbacks, drained once per frame.
+    #[derive(Resource, Default)]
+    pub struct WebEvents {
+        queue: Arc<Mutex<Vec<WebGlContextEvent>>>,
+        installed: bool,
+    }
+
+    /// Hooks the context listeners onto the canvas once winit has created
+    /// it, and the exit save onto the page.
+    pub fn install_listeners(mut events: ResMut<WebEvents>, exit_save: Res<ExitSave>) {
+        if events.installed {
+            return;
+        }
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let Some(document) = window.document() else {
+            return;
+        };
+        let Some(canvas) = document.query_selector("canvas").ok().flatten() else {
+            return;
+        };
+
+        for (name, event) in [
+            ("webglcontextlost", WebGlContextEvent::Lost),
+            ("webglcontextrestored", WebGlContextEvent::Restored),
+        ] {
+            let queue = events.queue.clone();
+            let callback = Closure::<dyn FnMut(web_sys::Event)>::new(move |e: web_sys::Event| {
+                // without this the browser never attempts to restore the context
+                e.prevent_default();
+                queue.lock().unwrap().push(event);
+            });
+            if canvas
+                .add_event_listener_with_callback(name, callback.as_ref().unchecked_ref())
+                .is_err()
+            {
+                warn!("failed to listen for `{name}`");
+            }
+            // the listener has to live as long as the page
+            callback.forget();
+        }
+
+        let hidden = {
+            let exit_save = exit_save.clone();
+            let document = document.clone();
+            Closure::<dyn FnMut(web_sys::Event)>::new(move |_: web_sys::Event| {
+                if document.hidden() {
+                    exit_save.flush();
+                }
+            })
+        };
+        if document
+            .add_event_listener_with_callback("visibilitychange", hidden.as_ref().unchecked_ref())
+            .is_err()
+        {
+            warn!("failed to listen for `visibilitychange`");
+        }
+        hidden.forget();
+        for name in ["pagehide", "beforeunload"] {
+            let exit_save = exit_save.clone();
+            let callback = Closure::<dyn FnMut(web_sys::Event)>::new(move |_: web_sys::Event| {
+                exit_save.flush();
+            });
+            if window
+                .add_event_listener_with_callback(name, callback.as_ref().unchecked_ref())
+                .is_err()
+            {
+                warn!("failed to listen for `{name}`");
+            }
+            callback.forget();
+        }
+        events.installed = true;
+    }
+
+    pub fn forward_events(events: Res<WebEvents>, mut writer: EventWriter<WebGlContextEvent>) {
+        let mut queue = events.queue.lock().unwrap();
+        writer.send_batch(queue.drain(..));
+    }
+}